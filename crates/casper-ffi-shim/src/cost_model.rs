@@ -0,0 +1,460 @@
+//! Approximate gas accounting for host function calls recorded by the shim.
+//!
+//! The execution engine charges every host function call a base cost plus a weight for each of
+//! its arguments, as configured in the `host_function_costs` table of the chainspec. The shim
+//! records the same arguments (with pointers recorded as `0`), so folding the trace with a
+//! [`CostModel`] gives an estimate of the host function gas a call would consume on chain.
+//!
+//! Wasm opcode costs are not modeled, so the estimate is only useful to catch regressions and
+//! compare implementations, not to predict the exact gas usage of a transaction.
+use std::{collections::BTreeMap, fmt};
+
+use casper_types::HostFunctionCostsV1;
+
+use crate::{HostFunction, TraceRecord};
+
+/// Cost of a single host function: a base cost plus a weight per argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostFunctionCost {
+    pub cost: u64,
+    pub arguments: Vec<u64>,
+}
+
+impl HostFunctionCost {
+    pub fn new(cost: u64, arguments: Vec<u64>) -> Self {
+        Self { cost, arguments }
+    }
+
+    /// Calculates the gas of a call with the given argument values.
+    pub fn calculate(&self, arguments: &[u64]) -> u64 {
+        self.arguments
+            .iter()
+            .zip(arguments)
+            .fold(self.cost, |gas, (weight, argument)| {
+                gas.saturating_add(weight.saturating_mul(*argument))
+            })
+    }
+}
+
+/// Error returned when loading a [`CostModel`] from a chainspec fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CostModelError {
+    /// The document is not valid TOML.
+    Toml(String),
+    /// An entry of the `host_function_costs` table is malformed.
+    InvalidEntry(String),
+}
+
+impl fmt::Display for CostModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CostModelError::Toml(error) => write!(f, "Invalid chainspec TOML: {error}"),
+            CostModelError::InvalidEntry(name) => {
+                write!(f, "Invalid host function cost entry: {name}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for CostModelError {}
+
+/// Per host function costs used to estimate the gas of a trace.
+///
+/// [`CostModel::default`] mirrors the `host_function_costs` of the chainspec the linked
+/// `casper-types` version ships with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostModel {
+    host_functions: BTreeMap<String, HostFunctionCost>,
+}
+
+macro_rules! built_in_costs {
+    ($costs:expr, $($name:ident),* $(,)?) => {
+        [$(
+            (
+                stringify!($name),
+                HostFunctionCost::new(
+                    u64::from($costs.$name.cost()),
+                    $costs.$name.arguments().iter().copied().map(u64::from).collect(),
+                ),
+            ),
+        )*]
+    };
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        let costs = HostFunctionCostsV1::default();
+        let table = built_in_costs!(
+            costs,
+            read_value,
+            dictionary_get,
+            write,
+            dictionary_put,
+            add,
+            new_uref,
+            load_named_keys,
+            ret,
+            get_key,
+            has_key,
+            put_key,
+            remove_key,
+            revert,
+            is_valid_uref,
+            add_associated_key,
+            remove_associated_key,
+            update_associated_key,
+            set_action_threshold,
+            get_caller,
+            get_blocktime,
+            create_purse,
+            transfer_to_account,
+            transfer_from_purse_to_account,
+            transfer_from_purse_to_purse,
+            get_balance,
+            get_phase,
+            get_system_contract,
+            get_main_purse,
+            read_host_buffer,
+            create_contract_package_at_hash,
+            create_contract_user_group,
+            add_contract_version,
+            add_contract_version_with_message_topics,
+            add_package_version_with_message_topics,
+            disable_contract_version,
+            call_contract,
+            call_versioned_contract,
+            get_named_arg_size,
+            get_named_arg,
+            remove_contract_user_group,
+            provision_contract_user_group_uref,
+            remove_contract_user_group_urefs,
+            print,
+            blake2b,
+            random_bytes,
+            enable_contract_version,
+            manage_message_topic,
+            emit_message,
+            get_block_info,
+            generic_hash,
+            recover_secp256k1,
+            verify_signature,
+            call_package_version,
+        );
+        let mut host_functions: BTreeMap<String, HostFunctionCost> = table
+            .into_iter()
+            .map(|(name, cost)| (name.to_string(), cost))
+            .collect();
+        // Charged with a fixed cost by the engine rather than through the chainspec.
+        for name in [LOAD_CALLER_INFORMATION, LOAD_CALL_STACK] {
+            host_functions.insert(name.to_string(), HostFunctionCost::new(10_000, vec![0; 3]));
+        }
+        Self { host_functions }
+    }
+}
+
+const LOAD_CALLER_INFORMATION: &str = "load_caller_information";
+const LOAD_CALL_STACK: &str = "load_call_stack";
+
+impl CostModel {
+    /// Loads costs from a chainspec, overriding the built-in defaults.
+    ///
+    /// Accepts either a complete chainspec, where the costs are found under
+    /// `[wasm.v1.host_function_costs]` (or `[wasm.host_function_costs]` for 1.x chainspecs), or a
+    /// document containing only the entries of that table. Host functions missing from the
+    /// document keep their default cost.
+    pub fn from_chainspec_toml(chainspec: &str) -> Result<Self, CostModelError> {
+        let document: toml::Table =
+            toml::from_str(chainspec).map_err(|error| CostModelError::Toml(error.to_string()))?;
+
+        let wasm = document.get("wasm").and_then(toml::Value::as_table);
+        let table = wasm
+            .and_then(|wasm| wasm.get("v1"))
+            .and_then(toml::Value::as_table)
+            .or(wasm)
+            .and_then(|wasm| wasm.get("host_function_costs"))
+            .and_then(toml::Value::as_table)
+            .unwrap_or(&document);
+
+        let mut model = Self::default();
+        for (name, entry) in table {
+            // Skip scalar settings such as `cost_increase_per_message`.
+            let Some(entry) = entry.as_table() else {
+                continue;
+            };
+            let invalid_entry = || CostModelError::InvalidEntry(name.clone());
+            let cost = entry
+                .get("cost")
+                .and_then(toml::Value::as_integer)
+                .and_then(|cost| u64::try_from(cost).ok())
+                .ok_or_else(invalid_entry)?;
+            let arguments = match entry.get("arguments") {
+                Some(arguments) => arguments
+                    .as_array()
+                    .ok_or_else(invalid_entry)?
+                    .iter()
+                    .map(|weight| {
+                        weight
+                            .as_integer()
+                            .and_then(|weight| u64::try_from(weight).ok())
+                            .ok_or_else(invalid_entry)
+                    })
+                    .collect::<Result<_, _>>()?,
+                None => Vec::new(),
+            };
+            model.set(name.clone(), HostFunctionCost::new(cost, arguments));
+        }
+        Ok(model)
+    }
+
+    /// Returns the cost of the host function with the given chainspec name.
+    pub fn get(&self, name: &str) -> Option<&HostFunctionCost> {
+        self.host_functions.get(name)
+    }
+
+    /// Overrides the cost of the host function with the given chainspec name.
+    pub fn set(&mut self, name: impl Into<String>, cost: HostFunctionCost) {
+        self.host_functions.insert(name.into(), cost);
+    }
+
+    /// Estimates the gas of a single recorded host function call.
+    ///
+    /// Calls of host functions without a cost return `0`.
+    pub fn cost_of(&self, record: &TraceRecord) -> u64 {
+        self.get(record.host_function.cost_name())
+            .map(|cost| cost.calculate(&record.arguments))
+            .unwrap_or_default()
+    }
+
+    /// Estimates the total gas of the recorded host function calls.
+    pub fn estimate<'a>(&self, records: impl IntoIterator<Item = &'a TraceRecord>) -> u64 {
+        records
+            .into_iter()
+            .fold(0u64, |gas, record| gas.saturating_add(self.cost_of(record)))
+    }
+
+    /// Builds a per host function breakdown of the recorded calls.
+    pub fn report<'a>(&self, records: impl IntoIterator<Item = &'a TraceRecord>) -> GasReport {
+        let mut report = GasReport::default();
+        for record in records {
+            let gas = self.cost_of(record);
+            let entry = report
+                .host_functions
+                .entry(record.host_function.cost_name())
+                .or_default();
+            entry.calls += 1;
+            entry.gas = entry.gas.saturating_add(gas);
+            report.total = report.total.saturating_add(gas);
+        }
+        report
+    }
+}
+
+/// Number of calls and estimated gas of a single host function in a [`GasReport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HostFunctionUsage {
+    pub calls: u64,
+    pub gas: u64,
+}
+
+/// Per host function call counts and gas estimates of a trace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GasReport {
+    pub host_functions: BTreeMap<&'static str, HostFunctionUsage>,
+    pub total: u64,
+}
+
+impl fmt::Display for GasReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, usage) in &self.host_functions {
+            writeln!(
+                f,
+                "{name:<40} {:>6} calls {:>14} gas",
+                usage.calls, usage.gas
+            )?;
+        }
+        write!(f, "{:<40} {:>6}       {:>14} gas", "total", "", self.total)
+    }
+}
+
+impl HostFunction {
+    /// Name of the host function in the chainspec `host_function_costs` table.
+    pub fn cost_name(&self) -> &'static str {
+        match self {
+            HostFunction::CasperReadValue => "read_value",
+            HostFunction::CasperWrite => "write",
+            HostFunction::CasperAdd => "add",
+            // The engine charges creating a dictionary as creating a `URef`.
+            HostFunction::CasperNewUref | HostFunction::CasperNewDictionary => "new_uref",
+            HostFunction::CasperLoadAuthorizationKeys => "load_authorization_keys",
+            HostFunction::CasperLoadNamedKeys => "load_named_keys",
+            HostFunction::CasperRet => "ret",
+            HostFunction::CasperGetKey(_) => "get_key",
+            HostFunction::CasperHasKey(_) => "has_key",
+            HostFunction::CasperPutKey(_, _) => "put_key",
+            HostFunction::CasperRemoveKey(_) => "remove_key",
+            HostFunction::CasperRevert => "revert",
+            HostFunction::CasperIsValidUref => "is_valid_uref",
+            HostFunction::CasperAddAssociatedKey => "add_associated_key",
+            HostFunction::CasperRemoveAssociatedKey => "remove_associated_key",
+            HostFunction::CasperUpdateAssociatedKey => "update_associated_key",
+            HostFunction::CasperSetActionThreshold => "set_action_threshold",
+            HostFunction::CasperGetCaller => "get_caller",
+            HostFunction::CasperGetBlocktime => "get_blocktime",
+            HostFunction::CasperCreatePurse => "create_purse",
+            HostFunction::CasperTransferToAccount => "transfer_to_account",
+            HostFunction::CasperTransferFromPurseToAccount => "transfer_from_purse_to_account",
+            HostFunction::CasperTransferFromPurseToPurse => "transfer_from_purse_to_purse",
+            HostFunction::CasperGetBalance => "get_balance",
+            HostFunction::CasperGetPhase => "get_phase",
+            HostFunction::CasperGetSystemContract(_) => "get_system_contract",
+            HostFunction::CasperGetMainPurse => "get_main_purse",
+            HostFunction::CasperReadHostBuffer => "read_host_buffer",
+            HostFunction::CasperCreateContractPackageAtHash => "create_contract_package_at_hash",
+            HostFunction::CasperCreateContractUserGroup => "create_contract_user_group",
+            HostFunction::CasperAddContractVersion => "add_contract_version",
+            HostFunction::CasperAddContractVersionWithMessageTopics => {
+                "add_contract_version_with_message_topics"
+            }
+            HostFunction::CasperAddPackageVersionWithMessageTopics => {
+                "add_package_version_with_message_topics"
+            }
+            HostFunction::CasperDisableContractVersion => "disable_contract_version",
+            HostFunction::CasperCallContract => "call_contract",
+            HostFunction::CasperCallVersionedContract => "call_versioned_contract",
+            HostFunction::CasperGetNamedArgSize => "get_named_arg_size",
+            HostFunction::CasperGetNamedArg => "get_named_arg",
+            HostFunction::CasperRemoveContractUserGroup => "remove_contract_user_group",
+            HostFunction::CasperProvisionContractUserGroupUref => {
+                "provision_contract_user_group_uref"
+            }
+            HostFunction::CasperRemoveContractUserGroupUrefs => "remove_contract_user_group_urefs",
+            HostFunction::CasperBlake2b => "blake2b",
+            HostFunction::CasperLoadCallStack => LOAD_CALL_STACK,
+            HostFunction::CasperPrint => "print",
+            HostFunction::CasperDictionaryGet => "dictionary_get",
+            HostFunction::CasperDictionaryRead => "dictionary_read",
+            HostFunction::CasperDictionaryPut => "dictionary_put",
+            HostFunction::CasperRandomBytes => "random_bytes",
+            HostFunction::CasperEnableContractVersion => "enable_contract_version",
+            HostFunction::CasperManageMessageTopic => "manage_message_topic",
+            HostFunction::CasperEmitMessage => "emit_message",
+            HostFunction::CasperLoadCallerInformation => LOAD_CALLER_INFORMATION,
+            HostFunction::CasperGetBlockInfo => "get_block_info",
+            HostFunction::CasperGenericHash => "generic_hash",
+            HostFunction::CasperRecoverSecp256k1 => "recover_secp256k1",
+            HostFunction::CasperVerifySignature => "verify_signature",
+            HostFunction::CasperCallPackageVersion => "call_package_version",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Excerpt of the `casper-node` 2.0 chainspec, with the cost of `write` raised.
+    const CHAINSPEC: &str = r#"
+[protocol]
+version = '2.0.0'
+
+[wasm.v1]
+max_memory = 64
+
+[wasm.v1.host_function_costs]
+add = { cost = 5_800, arguments = [0, 0, 0, 0] }
+cost_increase_per_message = 50
+dictionary_get = { cost = 5_500, arguments = [0, 590, 0] }
+dictionary_put = { cost = 9_500, arguments = [0, 1_800, 0, 520] }
+read_value = { cost = 60_000, arguments = [0, 120_000, 0] }
+write = { cost = 25_000, arguments = [0, 0, 0, 1_000] }
+"#;
+
+    fn record(host_function: HostFunction, arguments: &[u64]) -> TraceRecord {
+        TraceRecord {
+            depth: 0,
+            host_function,
+            arguments: arguments.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_default_mirrors_chainspec() {
+        let model = CostModel::default();
+        assert_eq!(
+            model.get("write"),
+            Some(&HostFunctionCost::new(14_000, vec![0, 0, 0, 980]))
+        );
+        assert_eq!(
+            model.get("dictionary_put"),
+            Some(&HostFunctionCost::new(9_500, vec![0, 1_800, 0, 520]))
+        );
+        assert_eq!(
+            model.cost_of(&record(HostFunction::CasperWrite, &[0, 33, 0, 10])),
+            14_000 + 980 * 10
+        );
+        assert_eq!(
+            model.cost_of(&record(
+                HostFunction::CasperLoadCallerInformation,
+                &[0, 0, 0]
+            )),
+            10_000
+        );
+    }
+
+    #[test]
+    fn test_from_chainspec_toml() {
+        let model = CostModel::from_chainspec_toml(CHAINSPEC).unwrap();
+        assert_eq!(
+            model.get("write"),
+            Some(&HostFunctionCost::new(25_000, vec![0, 0, 0, 1_000]))
+        );
+        // Entries missing from the chainspec keep their default.
+        assert_eq!(model.get("new_uref"), CostModel::default().get("new_uref"));
+        assert!(model.get("cost_increase_per_message").is_none());
+
+        let trace = [
+            record(HostFunction::CasperWrite, &[0, 33, 0, 10]),
+            record(HostFunction::CasperReadValue, &[0, 33, 0]),
+            record(HostFunction::CasperRevert, &[]),
+        ];
+        assert_eq!(
+            model.estimate(&trace),
+            25_000 + 1_000 * 10 + 60_000 + 120_000 * 33 + 500
+        );
+        let report = model.report(&trace);
+        assert_eq!(report.total, model.estimate(&trace));
+        assert_eq!(
+            report.host_functions["write"],
+            HostFunctionUsage {
+                calls: 1,
+                gas: 35_000
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_chainspec_toml_table_only() {
+        let model =
+            CostModel::from_chainspec_toml("get_caller = { cost = 400, arguments = [0] }").unwrap();
+        assert_eq!(
+            model.get("get_caller"),
+            Some(&HostFunctionCost::new(400, vec![0]))
+        );
+    }
+
+    #[test]
+    fn test_from_chainspec_toml_errors() {
+        assert!(matches!(
+            CostModel::from_chainspec_toml("write = {"),
+            Err(CostModelError::Toml(_))
+        ));
+        assert_eq!(
+            CostModel::from_chainspec_toml("write = { cost = -1 }"),
+            Err(CostModelError::InvalidEntry("write".to_string()))
+        );
+        assert_eq!(
+            CostModel::from_chainspec_toml("write = { cost = 1, arguments = 2 }"),
+            Err(CostModelError::InvalidEntry("write".to_string()))
+        );
+    }
+}
@@ -6,10 +6,31 @@
 //! trying to run unit tests within your smart contract code.
 //!
 //! There is _some_ support for stubbing the host i.e. put_key/get_key/remove_key/has_key
-//! does implement a simple in-memory key-value store. However, most functions
-//! are just stubs that log their invocation and return default values.
+//! does implement a simple in-memory key-value store, and contract packages are
+//! modelled well enough for installers calling `storage::new_contract` to run.
+//! However, most functions are just stubs that log their invocation and return
+//! default values.
 //!
 //! Importing this makes rust-analyzer happy.
+//!
+//! # Unit testing entry points
+//!
+//! An [`Env`], built with [`EnvBuilder`], holds the state host functions work on: named keys,
+//! storage and dictionaries, args, balances, packages and the entry points of other contracts.
+//! [`run_entry_point`] runs an exported entry point in it and returns its value or the
+//! [`RevertError`] it reverted with. Afterwards the test inspects what it did:
+//!
+//! - [`Env::messages`] and [`Env::prints`] (with [`assert_printed!`]) list the messages it emitted
+//!   and the text it printed.
+//! - [`Env::trace`] lists the host functions it called, checked with [`Env::assert_trace`], and
+//!   [`Env::gas_report`] estimates their gas.
+//! - [`Env::packages`] lists the packages installers created, and [`Env::purse_balance`] the
+//!   balances transfers moved.
+//!
+//! Host functions check access rights, assign addresses and fail like the engine, except where
+//! their docs say otherwise: contract calls run stubs registered with
+//! [`EnvBuilder::with_contract_entry_point`] and keep the callee's writes on reverts. The
+//! environment is per thread, see [`Env::activate_on_thread`] and [`dispatch_with_global`].
 #![allow(unused_variables)]
 #![allow(clippy::missing_safety_doc)]
 
@@ -27,20 +48,92 @@ macro_rules! unimplemented_ffi {
 
 use std::{
     cell::RefCell,
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    marker::PhantomData,
     mem,
+    panic::{self, AssertUnwindSafe},
     ptr::NonNull,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, Once, PoisonError, RwLock, RwLockWriteGuard},
 };
 
 use casper_types::{
-    AccessRights, ApiError, CLTyped, CLValue, Key, StoredValue, U256, U512, URef, URefAddr,
+    AccessRights, ApiError, CLType, CLTyped, CLValue, ContractWasm, Digest, Group, Groups,
+    HashAddr, Key, Phase, ProtocolVersion, PublicKey, RuntimeArgs, StoredValue, U256, U512,
+    UREF_SERIALIZED_LENGTH, URef, URefAddr,
+    account::{AccountHash, AssociatedKeys, Weight},
+    addressable_entity::{EntryPoints, MAX_GROUPS, MAX_TOTAL_UREFS},
     api_error,
-    bytesrepr::{self, ToBytes},
+    bytesrepr::{self, Bytes, FromBytes, ToBytes},
+    contract_messages::{MessagePayload, MessageTopicOperation},
+    contracts::{
+        self, Contract, ContractHash, ContractPackage, ContractPackageHash, ContractPackageStatus,
+        ContractVersion, ContractVersionKey, NamedKeys,
+    },
+    crypto::Signature,
+    system::{Caller, CallerInfo, SystemEntityType, mint},
 };
 
-// Custom error type for revert that can be handled without unwinding
-#[derive(Debug, Clone)]
+pub mod cost_model;
+pub mod trace_assert;
+
+pub use cost_model::{CostModel, CostModelError, GasReport, HostFunctionCost};
+pub use trace_assert::{HostFunctionPattern, TraceAssert};
+
+/// Asserts that the estimated host function gas of the trace of `env` is at most `limit`.
+///
+/// Prints a per host function breakdown of the estimate when the assertion fails.
+#[macro_export]
+macro_rules! assert_gas_under {
+    ($env:expr, $model:expr, $limit:expr $(,)?) => {{
+        let report = $env.gas_report(&$model);
+        let limit: u64 = $limit;
+        assert!(
+            report.total <= limit,
+            "estimated gas {} exceeds the limit of {}\n{}",
+            report.total,
+            limit,
+            report
+        );
+    }};
+}
+
+/// Asserts that a print of the call stack of `env` contains `pattern`, or matches the regular
+/// expression of `regex = pattern`.
+///
+/// Lists the captured prints when the assertion fails, see [`Env::prints`].
+#[macro_export]
+macro_rules! assert_printed {
+    ($env:expr, regex = $pattern:expr $(,)?) => {{
+        let prints = $env.prints();
+        let pattern: &str = $pattern;
+        let regex = $crate::__regex::Regex::new(pattern).expect("invalid print pattern");
+        assert!(
+            prints.iter().any(|print| regex.is_match(print)),
+            "no print matches /{}/, printed: {:#?}",
+            pattern,
+            prints
+        );
+    }};
+    ($env:expr, $pattern:expr $(,)?) => {{
+        let prints = $env.prints();
+        let pattern: &str = $pattern;
+        assert!(
+            prints.iter().any(|print| print.contains(pattern)),
+            "no print contains {:?}, printed: {:#?}",
+            pattern,
+            prints
+        );
+    }};
+}
+
+#[doc(hidden)]
+pub use regex as __regex;
+
+/// Environment variable forwarding the prints of every environment to stderr when set.
+pub const VERBOSE_PRINTS_VAR: &str = "CASPER_SHIM_VERBOSE";
+
+/// The error `casper_revert` unwinds with, see [`run_entry_point`].
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RevertError {
     pub status: u32,
     pub api_error: ApiError,
@@ -58,6 +151,12 @@ impl core::error::Error for RevertError {}
 unsafe impl Send for RevertError {}
 unsafe impl Sync for RevertError {}
 
+/// The payload `casper_ret` unwinds with, carrying the value returned by the entry point.
+#[derive(Debug, Clone)]
+pub struct RetSignal {
+    pub value: CLValue,
+}
+
 // Thread-local storage for revert errors
 thread_local! {
     static REVERT_ERROR: RefCell<Option<RevertError>> = const { RefCell::new(None) };
@@ -100,7 +199,8 @@ pub enum HostFunction {
     CasperTransferFromPurseToPurse,
     CasperGetBalance,
     CasperGetPhase,
-    CasperGetSystemContract,
+    /// The index of the system contract, see [`EnvBuilder::with_system_contract`].
+    CasperGetSystemContract(u32),
     CasperGetMainPurse,
     CasperReadHostBuffer,
     CasperCreateContractPackageAtHash,
@@ -135,17 +235,248 @@ pub enum HostFunction {
     CasperCallPackageVersion,
 }
 
-#[derive(Debug, Default)]
+/// A host function call recorded in the trace of an [`Env`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// Call depth the host function was called at.
+    pub depth: usize,
+    pub host_function: HostFunction,
+    /// Arguments of the call as charged by the execution engine, with pointers recorded as `0`
+    /// so that only payload sizes contribute to the cost.
+    pub arguments: Vec<u64>,
+}
+
+/// A message emitted through `casper_emit_message` in an [`Env`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmittedMessage {
+    /// Call depth the message was emitted at.
+    pub depth: usize,
+    pub topic_name: String,
+    pub payload: MessagePayload,
+}
+
+impl EmittedMessage {
+    /// Returns the hash of the topic name, under which the engine stores the message's topic.
+    pub fn topic_name_hash(&self) -> [u8; 32] {
+        Digest::hash(self.topic_name.as_bytes()).value()
+    }
+}
+
+/// A version of a [`PackageRecord`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRecord {
+    pub contract_hash: ContractHash,
+    pub enabled: bool,
+}
+
+/// A contract package created through `casper_create_contract_package_at_hash` in an [`Env`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageRecord {
+    pub access_uref: URef,
+    pub is_locked: bool,
+    pub versions: BTreeMap<ContractVersionKey, VersionRecord>,
+    pub groups: Groups,
+    /// Topics registered by each version, including those carried forward from the previous one.
+    pub message_topics: BTreeMap<ContractHash, BTreeSet<String>>,
+}
+
+impl PackageRecord {
+    fn new(access_uref: URef, is_locked: bool) -> Self {
+        Self {
+            access_uref,
+            is_locked,
+            versions: BTreeMap::new(),
+            groups: Groups::default(),
+            message_topics: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the newest enabled version.
+    pub fn current_version(&self) -> Option<(ContractVersionKey, ContractHash)> {
+        self.versions
+            .iter()
+            .rev()
+            .find(|(_, version)| version.enabled)
+            .map(|(key, version)| (*key, version.contract_hash))
+    }
+
+    /// Returns the package as the engine stores it under `Key::Hash(package_hash)`.
+    pub fn to_contract_package(&self) -> ContractPackage {
+        let versions = self
+            .versions
+            .iter()
+            .map(|(key, version)| (*key, version.contract_hash))
+            .collect();
+        let disabled_versions = self
+            .versions
+            .iter()
+            .filter(|(_, version)| !version.enabled)
+            .map(|(key, _)| *key)
+            .collect();
+        let lock_status = if self.is_locked {
+            ContractPackageStatus::Locked
+        } else {
+            ContractPackageStatus::Unlocked
+        };
+        ContractPackage::new(
+            self.access_uref,
+            versions,
+            disabled_versions,
+            self.groups.clone(),
+            lock_status,
+        )
+    }
+}
+
+type Database = BTreeMap<Key, StoredValue>;
+type Dictionaries = BTreeMap<URefAddr, BTreeMap<String, CLValue>>;
+type KnownURefs = BTreeMap<URefAddr, AccessRights>;
+type Address = [u8; 32];
+type Packages = BTreeMap<HashAddr, PackageRecord>;
+/// Returns the named arguments of `runtime_args` as the args of an environment.
+fn named_args(runtime_args: &RuntimeArgs) -> BTreeMap<String, CLValue> {
+    runtime_args
+        .named_args()
+        .map(|arg| (arg.name().to_string(), arg.cl_value().clone()))
+        .collect()
+}
+
+type ContractEntryPoints = BTreeMap<(ContractHash, String), ContractEntryPoint>;
+
+/// Code run for an entry point of a stub contract, see [`EnvBuilder::with_contract_entry_point`].
+#[derive(Clone)]
+struct ContractEntryPoint(Arc<dyn Fn() + Send + Sync>);
+
+impl core::fmt::Debug for ContractEntryPoint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("ContractEntryPoint(..)")
+    }
+}
+
+/// Main purse `casper_get_main_purse` returns when neither [`EnvBuilder::with_main_purse`] nor
+/// [`EnvBuilder::with_account`] configures one.
+pub const DEFAULT_MAIN_PURSE: URef = URef::new([0xa5; 32], AccessRights::READ_ADD_WRITE);
+
+/// Block time reported by `casper_get_blocktime` unless set with [`EnvBuilder::with_block_time`],
+/// in milliseconds since the Unix epoch.
+pub const DEFAULT_BLOCK_TIME: u64 = 1_700_000_000_000;
+
+/// Hash `casper_get_system_contract` returns for the mint unless set with
+/// [`EnvBuilder::with_system_contract`].
+pub const DEFAULT_MINT_HASH: [u8; 32] = [0x5a; 32];
+
+/// Hash `casper_get_system_contract` returns for handle payment unless set with
+/// [`EnvBuilder::with_system_contract`].
+pub const DEFAULT_HANDLE_PAYMENT_HASH: [u8; 32] = [0x5b; 32];
+
+/// Hash `casper_get_system_contract` returns for standard payment unless set with
+/// [`EnvBuilder::with_system_contract`].
+pub const DEFAULT_STANDARD_PAYMENT_HASH: [u8; 32] = [0x5c; 32];
+
+/// Hash `casper_get_system_contract` returns for the auction unless set with
+/// [`EnvBuilder::with_system_contract`].
+pub const DEFAULT_AUCTION_HASH: [u8; 32] = [0x5d; 32];
+
+/// Returns the system contracts of a new environment by their `SystemEntityType` index.
+fn default_system_contracts() -> BTreeMap<u32, [u8; 32]> {
+    BTreeMap::from([
+        (SystemEntityType::Mint.into(), DEFAULT_MINT_HASH),
+        (
+            SystemEntityType::HandlePayment.into(),
+            DEFAULT_HANDLE_PAYMENT_HASH,
+        ),
+        (
+            SystemEntityType::StandardPayment.into(),
+            DEFAULT_STANDARD_PAYMENT_HASH,
+        ),
+        (SystemEntityType::Auction.into(), DEFAULT_AUCTION_HASH),
+    ])
+}
+
+/// The account executing session code in an [`Env`], see [`EnvBuilder::with_account`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountConfig {
+    pub account_hash: AccountHash,
+    /// Returned by `casper_get_main_purse`, unless [`EnvBuilder::with_main_purse`] overrides it.
+    pub main_purse: URef,
+    pub associated_keys: AssociatedKeys,
+}
+
+impl AccountConfig {
+    /// Creates an account whose only associated key is its own, with a weight of 1.
+    pub fn new(account_hash: AccountHash, main_purse: URef) -> Self {
+        Self {
+            account_hash,
+            main_purse,
+            associated_keys: AssociatedKeys::new(account_hash, Weight::new(1)),
+        }
+    }
+
+    pub fn with_associated_keys(mut self, associated_keys: AssociatedKeys) -> Self {
+        self.associated_keys = associated_keys;
+        self
+    }
+}
+
+#[derive(Debug)]
 pub struct EnvImpl {
     /// Simplified, always creates deterministic addresses by counting up.
-    address_generator: U256,
-    database: BTreeMap<Key, StoredValue>,
+    ///
+    /// Shared across the call stack so nested calls never hand out the same address twice.
+    address_generator: Arc<RwLock<U256>>,
+    /// Addresses the generator skips: those of the state seeded by the [`EnvBuilder`] and those
+    /// of [`EnvBuilder::with_reserved_addresses`].
+    reserved_addresses: Arc<BTreeSet<Address>>,
+    /// Global state shared by all environments of a call stack.
+    ///
+    /// URef keys are stored without access rights, like the engine normalizes them.
+    database: Arc<RwLock<Database>>,
     args: BTreeMap<String, CLValue>,
     named_keys: BTreeMap<String, Key>,
     host_buffer: Option<CLValue>,
-    dictionaries: BTreeMap<URefAddr, BTreeMap<String, CLValue>>,
+    /// Dictionaries shared by all environments of a call stack.
+    dictionaries: Arc<RwLock<Dictionaries>>,
     /// Very simple host function call trace for testing purposes.
-    trace: Vec<HostFunction>,
+    ///
+    /// Shared across the call stack, each entry is annotated with the call depth it was made at.
+    trace: Arc<RwLock<Vec<TraceRecord>>>,
+    /// Call depth of this environment, `0` for the environment passed to [`dispatch_with`].
+    depth: usize,
+    /// Protocol version reported through `casper_get_block_info`.
+    protocol_version: ProtocolVersion,
+    /// Execution phase reported through `casper_get_phase`.
+    phase: Phase,
+    /// Block time reported through `casper_get_blocktime`, in milliseconds.
+    block_time: u64,
+    /// Account executing the session code, if configured.
+    account: Option<AccountConfig>,
+    /// Callers reported by `casper_load_caller_information`, see [`EnvBuilder::with_call_stack`].
+    call_stack: Vec<Caller>,
+    /// Purse returned by `casper_get_main_purse`.
+    main_purse: URef,
+    /// Hashes returned by `casper_get_system_contract`, by system contract index.
+    system_contracts: Arc<BTreeMap<u32, [u8; 32]>>,
+    /// URefs `casper_is_valid_uref` accepts along with their access rights.
+    ///
+    /// Shared across the call stack, it contains the URefs created by the host and those seeded
+    /// by the [`EnvBuilder`].
+    known_urefs: Arc<RwLock<KnownURefs>>,
+    /// Messages emitted by the whole call stack, in emission order.
+    messages: Arc<RwLock<Vec<EmittedMessage>>>,
+    /// Topics of the contract running in this environment, registered through
+    /// `casper_manage_message_topic` or seeded with [`EnvBuilder::with_message_topic`].
+    message_topics: BTreeSet<String>,
+    /// Text printed by the whole call stack through `casper_print`, in print order.
+    prints: Arc<RwLock<Vec<String>>>,
+    /// Contract packages created by the whole call stack, by package hash.
+    ///
+    /// Each package is mirrored into the database as the `ContractPackage` the engine stores.
+    packages: Arc<RwLock<Packages>>,
+    /// Entry points `casper_call_contract` and `casper_call_versioned_contract` run, see
+    /// [`EnvBuilder::with_contract_entry_point`].
+    contract_entry_points: Arc<RwLock<ContractEntryPoints>>,
+    /// Whether prints are forwarded to stderr as well, see [`EnvBuilder::with_verbose_prints`].
+    verbose_prints: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -153,16 +484,414 @@ pub struct Env {
     env_impl: Arc<RwLock<EnvImpl>>,
 }
 
+/// The state of an [`Env`] before an execution, see [`Env::snapshot`].
+struct Snapshot {
+    env: Env,
+    database: Database,
+    dictionaries: Dictionaries,
+    packages: Packages,
+    messages: Vec<EmittedMessage>,
+    named_keys: BTreeMap<String, Key>,
+    message_topics: BTreeSet<String>,
+}
+
+impl Snapshot {
+    /// Restores the state of the environment to the captured one.
+    fn restore(self) {
+        let mut env_impl = self.env.env_impl.write().unwrap();
+        *env_impl.database.write().unwrap() = self.database;
+        *env_impl.dictionaries.write().unwrap() = self.dictionaries;
+        *env_impl.packages.write().unwrap() = self.packages;
+        *env_impl.messages.write().unwrap() = self.messages;
+        env_impl.named_keys = self.named_keys;
+        env_impl.message_topics = self.message_topics;
+        env_impl.host_buffer = None;
+    }
+}
+
+impl Default for EnvImpl {
+    fn default() -> Self {
+        Self {
+            address_generator: Arc::default(),
+            reserved_addresses: Arc::default(),
+            database: Arc::default(),
+            args: BTreeMap::new(),
+            named_keys: BTreeMap::new(),
+            host_buffer: None,
+            dictionaries: Arc::default(),
+            trace: Arc::default(),
+            depth: 0,
+            protocol_version: ProtocolVersion::default(),
+            phase: Phase::Session,
+            block_time: DEFAULT_BLOCK_TIME,
+            account: None,
+            call_stack: Vec::new(),
+            main_purse: DEFAULT_MAIN_PURSE,
+            system_contracts: Arc::new(default_system_contracts()),
+            known_urefs: Arc::default(),
+            messages: Arc::default(),
+            message_topics: BTreeSet::new(),
+            prints: Arc::default(),
+            packages: Arc::default(),
+            contract_entry_points: Arc::default(),
+            verbose_prints: false,
+        }
+    }
+}
+
 impl EnvImpl {
     pub fn new() -> Self {
         Self::default()
     }
+    /// Returns the next address of the generator, skipping reserved addresses.
+    ///
+    /// Panics rather than aliasing a value if the address is already in use, e.g. by a key the
+    /// contract wrote to.
     pub fn next_address(&mut self) -> [u8; 32] {
-        self.address_generator += U256::one();
-        let mut output = [0; 32];
-        self.address_generator.to_little_endian(&mut output);
+        let output = {
+            let mut address_generator = self.address_generator.write().unwrap();
+            loop {
+                *address_generator += U256::one();
+                let mut output = [0; 32];
+                address_generator.to_little_endian(&mut output);
+                if !self.reserved_addresses.contains(&output) {
+                    break output;
+                }
+            }
+        };
+        let database = self.database.read().unwrap();
+        let in_use = [
+            Key::URef(URef::new(output, AccessRights::NONE)),
+            Key::Hash(output),
+            Key::Balance(output),
+        ]
+        .iter()
+        .any(|key| database.contains_key(key))
+            || self.dictionaries.read().unwrap().contains_key(&output);
+        assert!(
+            !in_use,
+            "the address generator reached address {} which is already in use; reserve it with \
+             EnvBuilder::with_reserved_addresses or start the generator past it with \
+             EnvBuilder::with_address_generator",
+            output
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        );
         output
     }
+
+    fn database(&self) -> RwLockWriteGuard<'_, Database> {
+        self.database.write().unwrap()
+    }
+
+    fn dictionaries(&self) -> RwLockWriteGuard<'_, Dictionaries> {
+        self.dictionaries.write().unwrap()
+    }
+
+    /// Creates a URef with full access rights at the next address and marks it as known.
+    fn new_uref(&mut self) -> URef {
+        let uref = URef::new(self.next_address(), AccessRights::READ_ADD_WRITE);
+        grant_uref(&mut self.known_urefs.write().unwrap(), uref);
+        uref
+    }
+
+    fn is_known_uref(&self, uref: &URef) -> bool {
+        self.known_urefs
+            .read()
+            .unwrap()
+            .get(&uref.addr())
+            .is_some_and(|access_rights| access_rights.contains(uref.access_rights()))
+    }
+
+    /// Checks that `uref` carries the `required` access rights and that they were granted, like
+    /// the engine validates the URefs passed to storage host functions.
+    ///
+    /// casper-types has no forged reference API error, so rights that were never granted fail with
+    /// [`ApiError::PermissionDenied`] and a granted URef lacking `required` with
+    /// [`ApiError::NoAccessRights`].
+    fn check_uref(&self, uref: &URef, required: AccessRights) -> Result<(), ApiError> {
+        if !uref.access_rights().contains(required) {
+            return Err(ApiError::NoAccessRights);
+        }
+        if !self.is_known_uref(uref) {
+            return Err(ApiError::PermissionDenied);
+        }
+        Ok(())
+    }
+
+    /// Checks `key` with [`Self::check_uref`] if it is a URef.
+    fn check_key(&self, key: &Key, required: AccessRights) -> Result<(), ApiError> {
+        match key.as_uref() {
+            Some(uref) => self.check_uref(uref, required),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the balance of `purse`, zero for the main purse when no balance was seeded for it.
+    fn purse_balance(&self, purse: URef) -> Option<U512> {
+        self.balance_in(&self.database.read().unwrap(), purse)
+    }
+
+    /// Returns the balance of `purse` in `database`, like [`Self::purse_balance`].
+    fn balance_in(&self, database: &Database, purse: URef) -> Option<U512> {
+        match database.get(&Key::Balance(purse.addr())) {
+            Some(StoredValue::CLValue(balance)) => {
+                Some(balance.clone().into_t().expect("Failed to read balance"))
+            }
+            Some(value) => panic!("Balance of {purse} should be a CLValue: {value:?}"),
+            None => (purse.addr() == self.main_purse.addr()).then(U512::zero),
+        }
+    }
+
+    /// Moves `amount` motes from `source` to `target` like the mint, failing with the mint error
+    /// it returns.
+    ///
+    /// The engine traps when the call stack doesn't hold `source`, which reverts with
+    /// [`ApiError::PermissionDenied`] like [`Self::check_uref`].
+    fn transfer_purse_to_purse(
+        &self,
+        source: URef,
+        target: URef,
+        amount: U512,
+    ) -> Result<Result<(), ApiError>, ApiError> {
+        if !self.is_known_uref(&source) {
+            return Err(ApiError::PermissionDenied);
+        }
+        if !source.is_writeable() || !target.is_addable() {
+            return Ok(Err(mint::Error::InvalidAccessRights.into()));
+        }
+        // Both balances are checked and updated under one lock, so no other thread of the call
+        // stack can move funds in between.
+        let mut database = self.database();
+        let Some(source_balance) = self.balance_in(&database, source) else {
+            return Ok(Err(mint::Error::SourceNotFound.into()));
+        };
+        if amount > source_balance {
+            return Ok(Err(mint::Error::InsufficientFunds.into()));
+        }
+        let Some(target_balance) = self.balance_in(&database, target) else {
+            return Ok(Err(mint::Error::DestNotFound.into()));
+        };
+        if source.addr() == target.addr() {
+            return Ok(Ok(()));
+        }
+        let Some(target_balance) = target_balance.checked_add(amount) else {
+            return Ok(Err(mint::Error::ArithmeticOverflow.into()));
+        };
+
+        for (purse, balance) in [(source, source_balance - amount), (target, target_balance)] {
+            let balance = CLValue::from_t(balance).expect("Failed to create CLValue for balance");
+            database.insert(Key::Balance(purse.addr()), StoredValue::CLValue(balance));
+        }
+        Ok(Ok(()))
+    }
+
+    fn packages(&self) -> RwLockWriteGuard<'_, Packages> {
+        self.packages.write().unwrap()
+    }
+
+    /// Creates a package without versions, returning its hash and access URef.
+    fn create_package(&mut self, is_locked: bool) -> (HashAddr, URef) {
+        let package_hash = self.next_address();
+        let access_uref = self.new_uref();
+        let package = PackageRecord::new(access_uref, is_locked);
+        self.write_package(package_hash, &package);
+        self.packages().insert(package_hash, package);
+        (package_hash, access_uref)
+    }
+
+    /// Returns the package at `package_hash` if the call stack holds its access URef.
+    ///
+    /// The engine traps when either is missing, which the callers turn into a revert with
+    /// [`ApiError::ValueNotFound`] and [`ApiError::PermissionDenied`] respectively.
+    fn validated_package(&self, package_hash: HashAddr) -> Result<PackageRecord, ApiError> {
+        let package = self
+            .packages()
+            .get(&package_hash)
+            .cloned()
+            .ok_or(ApiError::ValueNotFound)?;
+        self.check_uref(&package.access_uref, AccessRights::NONE)
+            .map_err(|_| ApiError::PermissionDenied)?;
+        Ok(package)
+    }
+
+    fn write_package(&self, package_hash: HashAddr, package: &PackageRecord) {
+        self.database().insert(
+            Key::Hash(package_hash),
+            StoredValue::ContractPackage(package.to_contract_package()),
+        );
+    }
+
+    /// Adds a contract version to a package like the engine does for 1.x contract packages.
+    ///
+    /// The named keys and message topics of the current version are carried forward. The new
+    /// contract is stored under `Key::Hash(contract_hash)`, with an empty module as its wasm.
+    /// The outer error is a trap of the engine, e.g. adding a version to a locked package that
+    /// has one reverts with [`ApiError::NotAllowedToAddContractVersion`], and the inner one the
+    /// error code the host function returns.
+    fn add_contract_version(
+        &mut self,
+        package_hash: HashAddr,
+        entry_points: contracts::EntryPoints,
+        mut named_keys: NamedKeys,
+        message_topics: BTreeMap<String, MessageTopicOperation>,
+    ) -> Result<Result<(ContractHash, ContractVersion), ApiError>, ApiError> {
+        let mut package = self.validated_package(package_hash)?;
+        let current_version = package.current_version();
+        if package.is_locked && current_version.is_some() {
+            return Err(ApiError::NotAllowedToAddContractVersion);
+        }
+        for key in named_keys.keys() {
+            self.check_key(key, AccessRights::NONE)
+                .map_err(|_| ApiError::PermissionDenied)?;
+        }
+
+        let mut topics = BTreeSet::new();
+        if let Some((_, previous_hash)) = current_version {
+            if let Some(StoredValue::Contract(previous)) =
+                self.database().get(&Key::Hash(previous_hash.value()))
+            {
+                named_keys.append(previous.named_keys().clone());
+            }
+            topics.extend(
+                package
+                    .message_topics
+                    .get(&previous_hash)
+                    .into_iter()
+                    .flatten()
+                    .cloned(),
+            );
+        }
+        for (topic_name, operation) in message_topics {
+            match operation {
+                MessageTopicOperation::Add => {
+                    if !topics.insert(topic_name) {
+                        return Ok(Err(ApiError::MessageTopicAlreadyRegistered));
+                    }
+                }
+            }
+        }
+
+        let contract_wasm_hash = self.next_address();
+        let contract_hash = ContractHash::new(self.next_address());
+        let major = self.protocol_version.value().major;
+        let contract_version = package
+            .versions
+            .keys()
+            .rev()
+            .find(|key| key.protocol_version_major() == major)
+            .map_or(1, |key| key.contract_version() + 1);
+        package.versions.insert(
+            ContractVersionKey::new(major, contract_version),
+            VersionRecord {
+                contract_hash,
+                enabled: true,
+            },
+        );
+        package.message_topics.insert(contract_hash, topics);
+
+        let contract = Contract::new(
+            ContractPackageHash::new(package_hash),
+            contract_wasm_hash.into(),
+            named_keys,
+            entry_points,
+            self.protocol_version,
+        );
+        {
+            let mut database = self.database();
+            database.insert(
+                Key::Hash(contract_wasm_hash),
+                StoredValue::ContractWasm(ContractWasm::new(Vec::new())),
+            );
+            database.insert(
+                Key::Hash(contract_hash.value()),
+                StoredValue::Contract(contract),
+            );
+        }
+        self.write_package(package_hash, &package);
+        self.packages().insert(package_hash, package);
+        Ok(Ok((contract_hash, contract_version)))
+    }
+
+    /// Enables or disables the version of `contract_hash`, failing with the contract header error
+    /// the engine returns when the package has no such version.
+    ///
+    /// Changing a locked package reverts with [`ApiError::PermissionDenied`].
+    fn set_version_enabled(
+        &mut self,
+        package_hash: HashAddr,
+        contract_hash: ContractHash,
+        enabled: bool,
+    ) -> Result<Result<(), ApiError>, ApiError> {
+        let mut package = self.validated_package(package_hash)?;
+        if package.is_locked {
+            return Err(ApiError::PermissionDenied);
+        }
+        let Some(version) = package
+            .versions
+            .values_mut()
+            .find(|version| version.contract_hash == contract_hash)
+        else {
+            return Ok(Err(contracts::Error::ContractNotFound.into()));
+        };
+        version.enabled = enabled;
+        self.write_package(package_hash, &package);
+        self.packages().insert(package_hash, package);
+        Ok(Ok(()))
+    }
+
+    /// Adds the group `label` to a package, holding `existing_urefs` and `num_new_urefs` new URefs
+    /// granted to the call stack, and returns the new ones.
+    ///
+    /// Like the engine, the new URefs hold a unit value, and an existing label or going over
+    /// [`MAX_GROUPS`] groups or [`MAX_TOTAL_UREFS`] URefs across the groups of the package fail
+    /// with the matching contract header error.
+    fn create_user_group(
+        &mut self,
+        package_hash: HashAddr,
+        label: String,
+        num_new_urefs: u8,
+        mut existing_urefs: BTreeSet<URef>,
+    ) -> Result<Result<Vec<URef>, ApiError>, ApiError> {
+        let mut package = self.validated_package(package_hash)?;
+        let group = Group::new(label);
+        if package.groups.contains(&group) {
+            return Ok(Err(contracts::Error::GroupAlreadyExists.into()));
+        }
+        if package.groups.len() >= usize::from(MAX_GROUPS) {
+            return Ok(Err(contracts::Error::MaxGroupsExceeded.into()));
+        }
+        let total_urefs =
+            package.groups.total_urefs() + usize::from(num_new_urefs) + existing_urefs.len();
+        if total_urefs > MAX_TOTAL_UREFS {
+            return Ok(Err(contracts::Error::MaxTotalURefsExceeded.into()));
+        }
+
+        let new_urefs: Vec<URef> = (0..num_new_urefs)
+            .map(|_| {
+                let uref = self.new_uref();
+                self.database().insert(
+                    Key::URef(uref).normalize(),
+                    StoredValue::CLValue(CLValue::unit()),
+                );
+                uref
+            })
+            .collect();
+        existing_urefs.extend(new_urefs.iter().copied());
+        package.groups.insert(group, existing_urefs);
+        self.write_package(package_hash, &package);
+        self.packages().insert(package_hash, package);
+        Ok(Ok(new_urefs))
+    }
+
+    fn record(&self, host_function: HostFunction, arguments: &[usize]) {
+        self.trace.write().unwrap().push(TraceRecord {
+            depth: self.depth,
+            host_function,
+            arguments: arguments.iter().map(|&argument| argument as u64).collect(),
+        });
+    }
 }
 
 impl Env {
@@ -170,31 +899,382 @@ impl Env {
         self.env_impl.read().unwrap().named_keys.clone()
     }
 
+    /// Returns the named arguments passed to this environment.
+    pub fn args(&self) -> BTreeMap<String, CLValue> {
+        self.env_impl.read().unwrap().args.clone()
+    }
+
+    /// Returns a snapshot of the global state shared by the call stack.
+    ///
+    /// URef keys carry no access rights, see [`Key::normalize`].
+    pub fn storage(&self) -> BTreeMap<Key, StoredValue> {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl.database().clone()
+    }
+
+    /// Returns a snapshot of all dictionaries, by the address of their seed URef.
+    pub fn dictionaries(&self) -> BTreeMap<URefAddr, BTreeMap<String, CLValue>> {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl.dictionaries().clone()
+    }
+
+    /// Returns the items of the dictionary under `uref`, or `None` if there is no such dictionary.
+    pub fn dictionary(&self, uref: URef) -> Option<BTreeMap<String, CLValue>> {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl.dictionaries().get(&uref.addr()).cloned()
+    }
+
+    /// Returns the balance `casper_get_balance` reports for `purse`, or `None` if it isn't a
+    /// purse.
+    pub fn purse_balance(&self, purse: URef) -> Option<U512> {
+        self.env_impl.read().unwrap().purse_balance(purse)
+    }
+
+    /// Runs `f` and asserts that it changed the balance of `purse` by `expected_delta` motes,
+    /// e.g. `-100` for a withdrawal of 100.
+    ///
+    /// Panics if `purse` isn't a purse before or after `f`.
+    pub fn assert_balance_delta(&self, purse: URef, expected_delta: i128, f: impl FnOnce()) {
+        let balance = |when| {
+            self.purse_balance(purse)
+                .unwrap_or_else(|| panic!("{purse} should be a purse {when} the operation"))
+        };
+        let before = balance("before");
+        f();
+        let after = balance("after");
+        let expected_change = U512::from(expected_delta.unsigned_abs());
+        let expected = if expected_delta < 0 {
+            before.checked_sub(expected_change)
+        } else {
+            before.checked_add(expected_change)
+        };
+        assert!(
+            expected == Some(after),
+            "balance of {purse} went from {before} to {after}, expected a delta of {expected_delta}"
+        );
+    }
+
     /// Returns and clears the current trace of host function calls.
     ///
-    /// This is primarily intended for testing purposes.
+    /// The trace is shared by the whole call stack. This is primarily intended for testing purposes.
     pub fn trace(&self) -> Vec<HostFunction> {
-        mem::take(&mut self.env_impl.write().unwrap().trace)
+        self.trace_with_depth()
+            .into_iter()
+            .map(|(_depth, host_function)| host_function)
+            .collect()
+    }
+
+    /// Returns and clears the current trace of host function calls along with the call depth each
+    /// call was made at.
+    pub fn trace_with_depth(&self) -> Vec<(usize, HostFunction)> {
+        self.trace_records()
+            .into_iter()
+            .map(|record| (record.depth, record.host_function))
+            .collect()
+    }
+
+    /// Returns and clears the current trace of host function calls along with their arguments.
+    pub fn trace_records(&self) -> Vec<TraceRecord> {
+        let env_impl = self.env_impl.read().unwrap();
+        mem::take(&mut *env_impl.trace.write().unwrap())
+    }
+
+    /// Takes and clears the current trace like [`Env::trace`], running the assertions of `assert`
+    /// on it, see [`trace_assert`].
+    pub fn assert_trace(&self, assert: impl FnOnce(TraceAssert) -> TraceAssert) {
+        assert(TraceAssert::new(self.trace_records()));
+    }
+
+    /// Estimates the host function gas of the current trace without clearing it.
+    ///
+    /// See [`cost_model`] for what the estimate does and does not account for.
+    pub fn estimated_gas(&self, model: &CostModel) -> u64 {
+        let env_impl = self.env_impl.read().unwrap();
+        model.estimate(env_impl.trace.read().unwrap().iter())
+    }
+
+    /// Returns per host function call counts and gas estimates of the current trace without
+    /// clearing it.
+    pub fn gas_report(&self, model: &CostModel) -> GasReport {
+        let env_impl = self.env_impl.read().unwrap();
+        model.report(env_impl.trace.read().unwrap().iter())
+    }
+
+    /// Returns the messages emitted so far by the whole call stack.
+    ///
+    /// Topics are not checked, so messages of topics the contract never registered are kept too.
+    pub fn messages(&self) -> Vec<EmittedMessage> {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl.messages.read().unwrap().clone()
+    }
+
+    /// Returns the message topics of the contract running in the environment, see
+    /// [`EnvBuilder::with_message_topic`].
+    pub fn message_topics(&self) -> BTreeSet<String> {
+        self.env_impl.read().unwrap().message_topics.clone()
+    }
+
+    /// Returns the text printed so far by the whole call stack, see [`assert_printed!`].
+    ///
+    /// Prints that are not a serialized `String` are captured as their lossy UTF-8 rendering.
+    pub fn prints(&self) -> Vec<String> {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl.prints.read().unwrap().clone()
+    }
+
+    /// Returns the contract packages created so far by the whole call stack, by package hash.
+    pub fn packages(&self) -> BTreeMap<ContractPackageHash, PackageRecord> {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl
+            .packages()
+            .iter()
+            .map(|(package_hash, package)| {
+                (ContractPackageHash::new(*package_hash), package.clone())
+            })
+            .collect()
+    }
+
+    /// Returns the package at `package_hash`, or `None` if the call stack didn't create it.
+    pub fn package(&self, package_hash: ContractPackageHash) -> Option<PackageRecord> {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl.packages().get(&package_hash.value()).cloned()
+    }
+
+    /// Returns the account executing the session code, if configured.
+    pub fn account(&self) -> Option<AccountConfig> {
+        self.env_impl.read().unwrap().account.clone()
+    }
+
+    /// Sets the argument `name` of this environment, e.g. between two entry point calls of a
+    /// test.
+    pub fn set_arg<T: ToBytes + CLTyped>(&self, name: impl Into<String>, value: T) {
+        let value = CLValue::from_t(value).expect("Failed to convert value to CLValue");
+        self.env_impl
+            .write()
+            .unwrap()
+            .args
+            .insert(name.into(), value);
+    }
+
+    /// Registers an entry point like [`EnvBuilder::with_contract_entry_point`], e.g. for a contract
+    /// version added by the code under test, whose hash is only known once it ran.
+    pub fn set_contract_entry_point(
+        &self,
+        contract_hash: ContractHash,
+        name: impl Into<String>,
+        entry_point: impl Fn() + Send + Sync + 'static,
+    ) {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl.contract_entry_points.write().unwrap().insert(
+            (contract_hash, name.into()),
+            ContractEntryPoint(Arc::new(entry_point)),
+        );
+    }
+
+    /// Captures the state a reverted execution restores, see [`run_entry_point`].
+    fn snapshot(&self) -> Snapshot {
+        let env_impl = self.env_impl.read().unwrap();
+        Snapshot {
+            env: self.clone(),
+            database: env_impl.database.read().unwrap().clone(),
+            dictionaries: env_impl.dictionaries.read().unwrap().clone(),
+            packages: env_impl.packages.read().unwrap().clone(),
+            messages: env_impl.messages.read().unwrap().clone(),
+            named_keys: env_impl.named_keys.clone(),
+            message_topics: env_impl.message_topics.clone(),
+        }
+    }
+
+    /// Replaces the access rights granted to the call stack for the URef at `addr`.
+    ///
+    /// Storage host functions fail when a URef carries rights beyond the granted ones, see
+    /// [`EnvBuilder::with_known_uref`]. Meant for tests revoking or narrowing rights mid-execution.
+    pub fn set_access_rights(&self, addr: URefAddr, access_rights: AccessRights) {
+        let env_impl = self.env_impl.read().unwrap();
+        env_impl
+            .known_urefs
+            .write()
+            .unwrap()
+            .insert(addr, access_rights);
+    }
+
+    /// Returns the purse `casper_get_main_purse` reports, see [`EnvBuilder::with_main_purse`].
+    pub fn main_purse(&self) -> URef {
+        self.env_impl.read().unwrap().main_purse
+    }
+
+    /// Call depth of this environment.
+    pub fn depth(&self) -> usize {
+        self.env_impl.read().unwrap().depth
+    }
+
+    /// Makes this environment the current one of the calling thread until the guard is dropped.
+    ///
+    /// Host functions resolve their environment per thread, so a thread spawned by a test adopts
+    /// the environment of the test with this:
+    ///
+    /// ```ignore
+    /// dispatch_with(env, |env| {
+    ///     let env = env.clone();
+    ///     std::thread::spawn(move || {
+    ///         let _guard = env.activate_on_thread();
+    ///         // contract code
+    ///     })
+    ///     .join()
+    ///     .unwrap();
+    /// });
+    /// ```
+    pub fn activate_on_thread(&self) -> EnvGuard {
+        EnvStack::Thread.push(self.clone());
+        EnvGuard {
+            env: self.clone(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Enters a nested call context, simulating a contract call made from this environment.
+    ///
+    /// The callee environment gets its own `args`, `named_keys` and host buffer, while the global
+    /// state (database and dictionaries), the address generator and the trace are shared with this
+    /// environment. The callee stays the current environment until the returned [`CallGuard`] is
+    /// dropped.
+    pub fn enter_call(
+        &self,
+        args: BTreeMap<String, CLValue>,
+        named_keys: BTreeMap<String, Key>,
+    ) -> CallGuard {
+        let callee = {
+            let env_impl = self.env_impl.read().unwrap();
+            Env {
+                env_impl: Arc::new(RwLock::new(EnvImpl {
+                    address_generator: Arc::clone(&env_impl.address_generator),
+                    reserved_addresses: Arc::clone(&env_impl.reserved_addresses),
+                    database: Arc::clone(&env_impl.database),
+                    args,
+                    named_keys,
+                    host_buffer: None,
+                    dictionaries: Arc::clone(&env_impl.dictionaries),
+                    trace: Arc::clone(&env_impl.trace),
+                    depth: env_impl.depth + 1,
+                    protocol_version: env_impl.protocol_version,
+                    phase: env_impl.phase,
+                    block_time: env_impl.block_time,
+                    account: env_impl.account.clone(),
+                    call_stack: env_impl.call_stack.clone(),
+                    main_purse: env_impl.main_purse,
+                    system_contracts: Arc::clone(&env_impl.system_contracts),
+                    known_urefs: Arc::clone(&env_impl.known_urefs),
+                    messages: Arc::clone(&env_impl.messages),
+                    // The callee is another contract, with topics of its own
+                    message_topics: BTreeSet::new(),
+                    prints: Arc::clone(&env_impl.prints),
+                    packages: Arc::clone(&env_impl.packages),
+                    contract_entry_points: Arc::clone(&env_impl.contract_entry_points),
+                    verbose_prints: env_impl.verbose_prints,
+                })),
+            }
+        };
+        let stack = EnvStack::current();
+        stack.push(callee.clone());
+        CallGuard { callee, stack }
+    }
+}
+
+/// Keeps an environment active on a thread, see [`Env::activate_on_thread`].
+///
+/// The environment is popped off the thread's environments when the guard is dropped, on the
+/// thread that activated it.
+#[derive(Debug)]
+#[must_use = "the environment is deactivated when the guard is dropped"]
+pub struct EnvGuard {
+    env: Env,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl EnvGuard {
+    /// The activated environment.
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        let popped = EnvStack::Thread.pop();
+        let is_env = popped
+            .as_ref()
+            .is_some_and(|env| Arc::ptr_eq(&env.env_impl, &self.env.env_impl));
+        debug_assert!(is_env, "Env guards must be dropped in reverse order");
+    }
+}
+
+/// Keeps a nested call context entered with [`Env::enter_call`] active.
+///
+/// The callee environment is popped off the call stack when the guard is dropped.
+#[derive(Debug)]
+pub struct CallGuard {
+    callee: Env,
+    stack: EnvStack,
+}
+
+impl CallGuard {
+    /// The environment of the callee.
+    pub fn env(&self) -> &Env {
+        &self.callee
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        let popped = self.stack.pop();
+        let is_callee = popped
+            .as_ref()
+            .is_some_and(|env| Arc::ptr_eq(&env.env_impl, &self.callee.env_impl));
+        debug_assert!(is_callee, "Call guards must be dropped in reverse order");
     }
 }
 
 #[derive(Debug)]
 pub struct EnvBuilder {
     address_generator: U256,
-    database: BTreeMap<Key, StoredValue>,
+    reserved_addresses: BTreeSet<Address>,
+    database: Database,
     args: BTreeMap<String, CLValue>,
     named_keys: BTreeMap<String, Key>,
-    dictionaries: BTreeMap<URefAddr, BTreeMap<String, CLValue>>,
+    dictionaries: Dictionaries,
+    protocol_version: ProtocolVersion,
+    phase: Phase,
+    block_time: u64,
+    account: Option<AccountConfig>,
+    call_stack: Vec<Caller>,
+    main_purse: Option<URef>,
+    system_contracts: BTreeMap<u32, [u8; 32]>,
+    known_urefs: KnownURefs,
+    contract_entry_points: ContractEntryPoints,
+    message_topics: BTreeSet<String>,
+    verbose_prints: bool,
 }
 
 impl EnvBuilder {
     pub fn new() -> Self {
         Self {
             address_generator: U256::zero(),
+            reserved_addresses: BTreeSet::new(),
             database: BTreeMap::new(),
             args: BTreeMap::new(),
             named_keys: BTreeMap::new(),
             dictionaries: BTreeMap::new(),
+            protocol_version: ProtocolVersion::V2_0_0,
+            phase: Phase::Session,
+            block_time: DEFAULT_BLOCK_TIME,
+            account: None,
+            call_stack: Vec::new(),
+            main_purse: None,
+            system_contracts: default_system_contracts(),
+            known_urefs: BTreeMap::new(),
+            contract_entry_points: BTreeMap::new(),
+            message_topics: BTreeSet::new(),
+            verbose_prints: false,
         }
     }
 
@@ -203,8 +1283,24 @@ impl EnvBuilder {
         self
     }
 
+    /// Keeps the address generator from handing out `addresses`, e.g. those of state a test
+    /// creates after building the environment.
+    ///
+    /// The addresses of URefs, hashes and balances in the seeded database, of seeded
+    /// dictionaries, named keys and known URefs are reserved as well.
+    pub fn with_reserved_addresses(
+        mut self,
+        addresses: impl IntoIterator<Item = [u8; 32]>,
+    ) -> Self {
+        self.reserved_addresses.extend(addresses);
+        self
+    }
+
     pub fn with_database(mut self, database: BTreeMap<Key, StoredValue>) -> Self {
-        self.database = database;
+        self.database = database
+            .into_iter()
+            .map(|(key, value)| (key.normalize(), value))
+            .collect();
         self
     }
 
@@ -219,11 +1315,73 @@ impl EnvBuilder {
         self
     }
 
+    /// Adds the named arguments of `args`, e.g. the `Args` of a generated entry point module
+    /// converted with `into_runtime_args`.
+    ///
+    /// The `CLValue`s are kept as they are rather than re-encoded.
+    pub fn with_runtime_args(mut self, args: RuntimeArgs) -> Self {
+        self.args.extend(named_args(&args));
+        self
+    }
+
+    /// Adds an argument that is already a `CLValue`, e.g. one taken from a captured transaction.
+    pub fn with_arg_clvalue(mut self, name: impl Into<String>, value: CLValue) -> Self {
+        self.args.insert(name.into(), value);
+        self
+    }
+
+    /// Adds an argument from its `cl_type` and serialized `bytes`.
+    ///
+    /// The bytes are not checked against the type, so malformed arguments of a replayed
+    /// transaction fail when the contract deserializes them, as they would on-chain.
+    pub fn with_arg_bytes(self, name: impl Into<String>, cl_type: CLType, bytes: Vec<u8>) -> Self {
+        self.with_arg_clvalue(name, CLValue::from_components(cl_type, bytes))
+    }
+
     pub fn with_storage(mut self, key: Key, value: StoredValue) -> Self {
-        self.database.insert(key, value);
+        self.database.insert(key.normalize(), value);
         self
     }
 
+    /// Creates a builder seeded with global state entries, e.g. the state of a contract exported
+    /// from a network with the rust-sdk's `state_dump::export_contract_state`.
+    ///
+    /// - `Key::Dictionary` entries holding a `DictionaryValue`, the way dictionary items are
+    ///   stored on-chain, become items of the dictionary of their seed URef.
+    /// - `StoredValue::NamedKey` entries become the named keys of the environment, so the code
+    ///   runs as the contract they belong to.
+    /// - URefs holding `CLValue::unit()`, like dictionary seeds do, get a dictionary, so items can
+    ///   be added to dictionaries none of whose items were exported.
+    /// - Every other entry is stored like [`Self::with_storage`].
+    pub fn from_state_entries(entries: impl IntoIterator<Item = (Key, StoredValue)>) -> Self {
+        let mut builder = Self::new();
+        for (key, value) in entries {
+            if let (Key::Dictionary(_), StoredValue::CLValue(cl_value)) = (&key, &value)
+                && let Some((seed_uref_addr, item_key, item)) = unwrap_dictionary_value(cl_value)
+            {
+                builder
+                    .dictionaries
+                    .entry(seed_uref_addr)
+                    .or_default()
+                    .insert(item_key, item);
+                continue;
+            }
+            if let StoredValue::NamedKey(named_key) = &value
+                && let (Ok(name), Ok(named_key)) = (named_key.get_name(), named_key.get_key())
+            {
+                builder.named_keys.insert(name, named_key);
+                continue;
+            }
+            if let (Key::URef(uref), StoredValue::CLValue(cl_value)) = (&key, &value)
+                && *cl_value.cl_type() == CLType::Unit
+            {
+                builder.dictionaries.entry(uref.addr()).or_default();
+            }
+            builder = builder.with_storage(key, value);
+        }
+        builder
+    }
+
     pub fn with_named_keys(mut self, named_keys: BTreeMap<String, Key>) -> Self {
         self.named_keys = named_keys;
         self
@@ -234,21 +1392,204 @@ impl EnvBuilder {
         self
     }
 
-    pub fn build(self) -> Env {
+    pub fn with_protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.protocol_version = protocol_version;
+        self
+    }
+
+    /// Sets the execution phase, [`Phase::Session`] by default.
+    pub fn with_phase(mut self, phase: Phase) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Sets the block time in milliseconds, [`DEFAULT_BLOCK_TIME`] by default.
+    pub fn with_block_time(mut self, block_time: u64) -> Self {
+        self.block_time = block_time;
+        self
+    }
+
+    /// Sets the account executing the session code. Its main purse is a known URef.
+    pub fn with_account(mut self, account: AccountConfig) -> Self {
+        self.account = Some(account);
+        self
+    }
+
+    /// Sets the callers `casper_load_caller_information` reports, from the initiator to the
+    /// immediate caller.
+    ///
+    /// Defaults to the account of [`Self::with_account`] alone. Nested calls report the same stack.
+    pub fn with_call_stack(mut self, call_stack: Vec<Caller>) -> Self {
+        self.call_stack = call_stack;
+        self
+    }
+
+    /// Sets the purse returned by `casper_get_main_purse`, overriding the one of the account.
+    ///
+    /// Defaults to the purse of [`Self::with_account`], or [`DEFAULT_MAIN_PURSE`] without an
+    /// account. The main purse is a known URef with a zero balance unless
+    /// [`Self::with_balance`] seeds one.
+    pub fn with_main_purse(mut self, main_purse: URef) -> Self {
+        self.main_purse = Some(main_purse);
+        self
+    }
+
+    /// Sets the hash `casper_get_system_contract` returns for the system contract at `index`,
+    /// e.g. `SystemEntityType::Mint.into()`.
+    ///
+    /// The mint, handle payment, standard payment and auction default to [`DEFAULT_MINT_HASH`],
+    /// [`DEFAULT_HANDLE_PAYMENT_HASH`], [`DEFAULT_STANDARD_PAYMENT_HASH`] and
+    /// [`DEFAULT_AUCTION_HASH`]; other indexes fail with `ApiError::InvalidSystemContract` like in
+    /// the engine unless set here.
+    pub fn with_system_contract(mut self, index: u32, hash: [u8; 32]) -> Self {
+        self.system_contracts.insert(index, hash);
+        self
+    }
+
+    /// Seeds the balance `casper_get_balance` reports for `purse`.
+    pub fn with_balance(mut self, purse: URef, balance: U512) -> Self {
+        let value = CLValue::from_t(balance).expect("Failed to create CLValue for balance");
+        self.database
+            .insert(Key::Balance(purse.addr()), StoredValue::CLValue(value));
+        self
+    }
+
+    /// Marks `uref` as known, so `casper_is_valid_uref` accepts it with at most its access rights.
+    ///
+    /// URefs under the named keys of the builder are known as well.
+    pub fn with_known_uref(mut self, uref: URef) -> Self {
+        grant_uref(&mut self.known_urefs, uref);
+        self
+    }
+
+    /// Forwards prints to stderr in addition to capturing them, like setting
+    /// [`VERBOSE_PRINTS_VAR`] does for every environment.
+    pub fn with_verbose_prints(mut self, verbose_prints: bool) -> Self {
+        self.verbose_prints = verbose_prints;
+        self
+    }
+
+    /// Registers the message topic `topic_name` for the contract running in the environment, as
+    /// if it was installed with it, so `casper_manage_message_topic` refuses to add it again.
+    pub fn with_message_topic(mut self, topic_name: impl Into<String>) -> Self {
+        self.message_topics.insert(topic_name.into());
+        self
+    }
+
+    /// Makes `casper_call_contract` run `entry_point` for calls of the entry point `name` of the
+    /// contract at `contract_hash`, typically by calling a symbol exported by a
+    /// `#[casper(contract)]` module of the test. `casper_call_versioned_contract` runs it for the
+    /// calls of a package version with that contract hash.
+    ///
+    /// The entry point runs in a nested call context (see [`Env::enter_call`]) with the args of
+    /// the call and the named keys of the `Contract` stored at `contract_hash`, if any. Calls of
+    /// entry points that aren't registered panic.
+    pub fn with_contract_entry_point(
+        mut self,
+        contract_hash: ContractHash,
+        name: impl Into<String>,
+        entry_point: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        self.contract_entry_points.insert(
+            (contract_hash, name.into()),
+            ContractEntryPoint(Arc::new(entry_point)),
+        );
+        self
+    }
+
+    pub fn build(mut self) -> Env {
+        let main_purse = match (self.main_purse, self.account.as_mut()) {
+            (Some(main_purse), Some(account)) => {
+                account.main_purse = main_purse;
+                main_purse
+            }
+            (Some(main_purse), None) => main_purse,
+            (None, Some(account)) => account.main_purse,
+            (None, None) => DEFAULT_MAIN_PURSE,
+        };
+
+        let seeded_urefs = self
+            .named_keys
+            .values()
+            .filter_map(Key::as_uref)
+            .copied()
+            .chain([main_purse])
+            .collect::<Vec<_>>();
+        for uref in seeded_urefs {
+            grant_uref(&mut self.known_urefs, uref);
+        }
+
+        let seeded_addresses = self
+            .database
+            .keys()
+            .chain(self.named_keys.values())
+            .filter_map(key_address)
+            .chain(self.dictionaries.keys().copied())
+            .chain(self.known_urefs.keys().copied())
+            .collect::<Vec<_>>();
+        self.reserved_addresses.extend(seeded_addresses);
+
         Env {
             env_impl: Arc::new(RwLock::new(EnvImpl {
-                address_generator: self.address_generator,
-                database: self.database,
+                address_generator: Arc::new(RwLock::new(self.address_generator)),
+                reserved_addresses: Arc::new(self.reserved_addresses),
+                database: Arc::new(RwLock::new(self.database)),
                 args: self.args,
                 named_keys: self.named_keys,
                 host_buffer: None,
-                dictionaries: self.dictionaries,
-                trace: Vec::new(),
+                dictionaries: Arc::new(RwLock::new(self.dictionaries)),
+                trace: Arc::new(RwLock::new(Vec::new())),
+                depth: 0,
+                protocol_version: self.protocol_version,
+                phase: self.phase,
+                block_time: self.block_time,
+                account: self.account,
+                call_stack: self.call_stack,
+                main_purse,
+                system_contracts: Arc::new(self.system_contracts),
+                known_urefs: Arc::new(RwLock::new(self.known_urefs)),
+                messages: Arc::new(RwLock::new(Vec::new())),
+                message_topics: self.message_topics,
+                prints: Arc::new(RwLock::new(Vec::new())),
+                packages: Arc::new(RwLock::new(BTreeMap::new())),
+                contract_entry_points: Arc::new(RwLock::new(self.contract_entry_points)),
+                verbose_prints: self.verbose_prints,
             })),
         }
     }
 }
 
+/// Returns the address of the keys the address generator creates, i.e. URefs, hashes and balances.
+fn key_address(key: &Key) -> Option<Address> {
+    match key {
+        Key::URef(uref) => Some(uref.addr()),
+        Key::Hash(addr) | Key::Balance(addr) => Some(*addr),
+        _ => None,
+    }
+}
+
+/// Adds the access rights of `uref` to those already known for its address.
+fn grant_uref(known_urefs: &mut KnownURefs, uref: URef) {
+    *known_urefs.entry(uref.addr()).or_insert(AccessRights::NONE) |= uref.access_rights();
+}
+
+/// Splits the `DictionaryValue` stored under a `Key::Dictionary` into the address of the seed
+/// URef, the item key and the item.
+fn unwrap_dictionary_value(cl_value: &CLValue) -> Option<(URefAddr, String, CLValue)> {
+    if *cl_value.cl_type() != CLType::Any {
+        return None;
+    }
+    let (item, remainder) = CLValue::from_bytes(cl_value.inner_bytes()).ok()?;
+    let (seed_uref_addr, remainder) = Bytes::from_bytes(remainder).ok()?;
+    let (item_key, remainder) = Bytes::from_bytes(remainder).ok()?;
+    if !remainder.is_empty() {
+        return None;
+    }
+    let seed_uref_addr = URefAddr::try_from(seed_uref_addr.as_slice()).ok()?;
+    let item_key = String::from_utf8(item_key.into()).ok()?;
+    Some((seed_uref_addr, item_key, item))
+}
+
 impl Default for EnvBuilder {
     fn default() -> Self {
         Self::new()
@@ -260,34 +1601,207 @@ thread_local! {
 
 }
 
+/// Environments of [`dispatch_with_global`], used by threads without environments of their own.
+static GLOBAL_ENV: RwLock<VecDeque<Env>> = RwLock::new(VecDeque::new());
+
+/// Serializes [`dispatch_with_global`] calls.
+static GLOBAL_DISPATCH: Mutex<()> = Mutex::new(());
+
+const NO_ENV_MESSAGE: &str = "no shim Env is active on this thread: run contract code inside \
+    dispatch_with or run_entry_point, adopt the caller's environment on spawned threads with \
+    Env::activate_on_thread, or use dispatch_with_global when the code runs on worker threads of \
+    an async runtime";
+
+/// The environment stacks host functions resolve the current environment from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnvStack {
+    /// The environments of the current thread, the fast path.
+    Thread,
+    /// The environments of [`dispatch_with_global`].
+    Global,
+}
+
+impl EnvStack {
+    /// Returns the thread's stack, or the global one if the thread has no environment while a
+    /// [`dispatch_with_global`] call is running.
+    fn current() -> Self {
+        let thread_is_empty = ENV.with(|stack| stack.borrow().read().unwrap().is_empty());
+        if thread_is_empty && !GLOBAL_ENV.read().unwrap().is_empty() {
+            EnvStack::Global
+        } else {
+            EnvStack::Thread
+        }
+    }
+
+    fn push(self, env: Env) {
+        match self {
+            EnvStack::Thread => ENV.with(|stack| stack.borrow().write().unwrap().push_back(env)),
+            EnvStack::Global => GLOBAL_ENV.write().unwrap().push_back(env),
+        }
+    }
+
+    fn pop(self) -> Option<Env> {
+        match self {
+            EnvStack::Thread => ENV.with(|stack| stack.borrow().write().unwrap().pop_back()),
+            EnvStack::Global => GLOBAL_ENV.write().unwrap().pop_back(),
+        }
+    }
+
+    fn top(self) -> Option<Env> {
+        match self {
+            EnvStack::Thread => ENV.with(|stack| stack.borrow().read().unwrap().back().cloned()),
+            EnvStack::Global => GLOBAL_ENV.read().unwrap().back().cloned(),
+        }
+    }
+}
+
+/// Pops the environment of a dispatch even when its function unwinds, e.g. through a revert.
+struct PopGuard(EnvStack);
+
+impl Drop for PopGuard {
+    fn drop(&mut self) {
+        self.0.pop();
+    }
+}
+
 pub fn dispatch_with<F>(new_env: Env, func: F)
 where
     F: FnOnce(&Env),
 {
-    ENV.with(|stack| {
-        let env = stack.borrow();
-        env.write().unwrap().push_back(new_env.clone());
-
-        // Clear any previous revert error
-        clear_revert();
-
-        // Execute the function
-
-        func(&new_env);
-
-        env.write().unwrap().pop_back();
-    })
+    EnvStack::Thread.push(new_env.clone());
+    let _guard = PopGuard(EnvStack::Thread);
+
+    // Clear any previous revert error
+    clear_revert();
+
+    func(&new_env);
+}
+
+/// Runs `func` with `new_env` as the environment of every thread that has none of its own.
+///
+/// Unlike [`dispatch_with`], contract code running on other threads than the caller sees the
+/// environment, e.g. tasks of a multi-threaded tokio runtime. Calls are serialized by a process
+/// wide lock, so only one test runs in a global environment at a time, and threads with an
+/// environment of their own keep using it.
+pub fn dispatch_with_global<F, R>(new_env: Env, func: F) -> R
+where
+    F: FnOnce(&Env) -> R,
+{
+    let _lock = GLOBAL_DISPATCH
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    EnvStack::Global.push(new_env.clone());
+    let _guard = PopGuard(EnvStack::Global);
+
+    clear_revert();
+
+    func(&new_env)
+}
+
+/// Runs the entry point called by `f` in `env` like [`dispatch_with`], returning the value it
+/// returned or the revert it ended with.
+///
+/// `casper_revert` and `casper_ret` unwind out of the entry point with a [`RevertError`] and a
+/// [`RetSignal`] payload respectively. The value passed to `casper_ret` is converted into `T`, and
+/// an entry point returning without calling it returns a unit, as in the execution engine. Any
+/// other panic is propagated. The panics of reverts and returns are not reported by the panic
+/// hook.
+///
+/// Like the engine, a reverted entry point leaves no effects behind: the global state,
+/// dictionaries, packages, messages and named keys are restored to those `env` had before `f`.
+///
+/// Unwinding through an `extern "C"` declaration is undefined behavior, so the entry point must
+/// reach `casper_revert` and `casper_ret` through `extern "C-unwind"` declarations only. Those of
+/// `veles_casper_contract_api::utils::revert` and `utils::ret` are, and `ResultExt`,
+/// `UnwrapOrRevertCtx` and the wrappers generated for exports use them for the errors and values
+/// entry points return. casper-contract's `runtime::revert`, `runtime::ret` and `UnwrapOrRevert`
+/// call the host through the `extern "C"` declarations of `casper_contract::ext_ffi` instead, as
+/// do helpers built on them such as `runtime::get_named_arg`: contract code tested with the shim
+/// reverts with the former, and tests shouldn't drive it into reverts of the latter. Likewise,
+/// exported symbols are called through `extern "C-unwind"` declarations:
+///
+/// ```ignore
+/// unsafe extern "C-unwind" {
+///     #[link_name = "approve"]
+///     fn approve();
+/// }
+///
+/// let result: Result<(), _> = run_entry_point(env, |_env| unsafe { approve() });
+/// assert_eq!(result.unwrap_err().api_error, ApiError::User(60017));
+/// ```
+pub fn run_entry_point<F, T>(env: Env, f: F) -> Result<T, RevertError>
+where
+    F: FnOnce(&Env),
+    T: CLTyped + FromBytes,
+{
+    install_quiet_panic_hook();
+
+    let snapshot = env.snapshot();
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| dispatch_with(env, f)));
+    let value = match outcome.map_err(|payload| payload.downcast::<RevertError>()) {
+        Ok(()) => CLValue::unit(),
+        Err(Ok(revert)) => {
+            snapshot.restore();
+            return Err(*revert);
+        }
+        Err(Err(payload)) => match payload.downcast::<RetSignal>() {
+            Ok(ret) => ret.value,
+            Err(payload) => panic::resume_unwind(payload),
+        },
+    };
+    Ok(value
+        .into_t()
+        .expect("returned value should convert into the result type"))
+}
+
+/// Installs a panic hook that skips the panics of reverts and returns, delegating the others to
+/// the previous hook.
+fn install_quiet_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            if !payload.is::<RevertError>() && !payload.is::<RetSignal>() {
+                previous_hook(info);
+            }
+        }));
+    });
 }
 
 fn with_current_env<F, R>(func: F) -> R
 where
     F: FnOnce(&mut EnvImpl) -> R,
 {
-    ENV.with(|stack| {
-        let env = stack.borrow();
-        let binding = env.read().unwrap();
-        let back_mut = binding.back().expect("Env should not be empty");
-        func(&mut back_mut.env_impl.write().unwrap())
+    let env = EnvStack::current().top().expect(NO_ENV_MESSAGE);
+    func(&mut env.env_impl.write().unwrap())
+}
+
+/// Reads the value stored under `key` like `casper_read_value`, but returns the whole
+/// [`StoredValue`], so accounts, entities and packages can be read too.
+///
+/// The engine has no such host function; this backs contract-api's `utils::read_stored_value`
+/// off-chain. Records a `CasperReadValue` call.
+pub fn read_stored_value(key: &Key) -> Result<Option<StoredValue>, ApiError> {
+    with_current_env(|env| {
+        env.record(
+            HostFunction::CasperReadValue,
+            &[0, key.serialized_length(), 0],
+        );
+        env.check_key(key, AccessRights::READ)?;
+        Ok(env.database().get(&key.normalize()).cloned())
+    })
+}
+
+/// Returns the `CLType` of the `CLValue` stored under `key`, without recording a host function
+/// call.
+///
+/// This lets contract-api's `utils::read_key` tell the `CLValue::unit()` tombstone apart from
+/// other values that serialize to no bytes, after reading them through `casper_read_value`.
+pub fn stored_cl_type(key: &Key) -> Option<CLType> {
+    with_current_env(|env| match env.database().get(&key.normalize()) {
+        Some(StoredValue::CLValue(cl_value)) => Some(cl_value.cl_type().clone()),
+        _ => None,
     })
 }
 
@@ -302,13 +1816,18 @@ pub unsafe extern "C" fn casper_read_value(
     let mut output_size = NonNull::new(output_size).expect("output_size pointer must not be null");
 
     with_current_env(|env| {
-        env.trace.push(HostFunction::CasperReadValue);
-        match env.database.get(&key) {
+        env.record(HostFunction::CasperReadValue, &[0, key_size, 0]);
+        if let Err(error) = env.check_key(&key, AccessRights::READ) {
+            return api_error::i32_from(Err(error));
+        }
+        let value = env.database().get(&key.normalize()).cloned();
+        match value {
             Some(value) => {
-                let cl_value: CLValue = value
-                    .clone()
-                    .try_into()
-                    .expect("Failed to convert to CLValue");
+                // The engine traps on accounts, entities and packages, which aren't `CLValue`s;
+                // they are read through `read_stored_value` instead.
+                let Ok(cl_value) = CLValue::try_from(value) else {
+                    return api_error::i32_from(Err(ApiError::CLTypeMismatch));
+                };
 
                 unsafe {
                     *output_size.as_mut() = cl_value.inner_bytes().len();
@@ -326,7 +1845,7 @@ pub unsafe extern "C" fn casper_read_value(
     })
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_write(
+pub unsafe extern "C-unwind" fn casper_write(
     key_ptr: *const u8,
     key_size: usize,
     value_ptr: *const u8,
@@ -338,10 +1857,14 @@ pub unsafe extern "C" fn casper_write(
     let value: CLValue =
         bytesrepr::deserialize_from_slice(value).expect("Failed to deserialize value");
 
-    with_current_env(|env| {
-        env.trace.push(HostFunction::CasperWrite);
-        env.database.insert(key, StoredValue::CLValue(value));
+    with_current_env(|env| -> Result<(), ApiError> {
+        env.record(HostFunction::CasperWrite, &[0, key_size, 0, value_size]);
+        env.check_key(&key, AccessRights::WRITE)?;
+        env.database()
+            .insert(key.normalize(), StoredValue::CLValue(value));
+        Ok(())
     })
+    .unwrap_or_else(|api_error| host_revert(api_error))
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_add(
@@ -353,7 +1876,7 @@ pub unsafe extern "C" fn casper_add(
     todo!()
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_new_uref(
+pub unsafe extern "C-unwind" fn casper_new_uref(
     uref_ptr: *mut u8,
     value_ptr: *const u8,
     value_size: usize,
@@ -363,10 +1886,11 @@ pub unsafe extern "C" fn casper_new_uref(
         bytesrepr::deserialize_from_slice(value).expect("Failed to deserialize value");
 
     with_current_env(|env| {
-        env.trace.push(HostFunction::CasperNewUref);
-        let uref = URef::new(env.next_address(), AccessRights::READ_ADD_WRITE);
+        env.record(HostFunction::CasperNewUref, &[0, 0, value_size]);
+        let uref = env.new_uref();
         let key = Key::URef(uref);
-        env.database.insert(key, StoredValue::CLValue(value));
+        env.database()
+            .insert(key.normalize(), StoredValue::CLValue(value));
 
         let key_bytes = uref.to_bytes().expect("Failed to serialize URef");
         unsafe {
@@ -386,11 +1910,42 @@ pub unsafe extern "C" fn casper_load_named_keys(
     total_keys: *mut usize,
     result_size: *mut usize,
 ) -> i32 {
-    todo!()
+    with_current_env(|env| {
+        env.record(HostFunction::CasperLoadNamedKeys, &[0, 0]);
+        let named_keys = NamedKeys::from(env.named_keys.clone());
+        unsafe {
+            *total_keys = named_keys.len();
+            *result_size = 0;
+        }
+        // Like the engine, the host buffer is only written when there are named keys.
+        if named_keys.is_empty() {
+            return;
+        }
+        let value = CLValue::from_t(named_keys).expect("Failed to serialize named keys");
+        unsafe {
+            *result_size = value.inner_bytes().len();
+        }
+        let old_host_buffer = env.host_buffer.replace(value);
+        if let Some(old_host_buffer) = &old_host_buffer {
+            panic!("Host buffer should be empty before writing to it: {old_host_buffer:?}");
+        }
+    });
+    0
 }
+/// Ends the entry point by unwinding with a [`RetSignal`] carrying `value`, see
+/// [`run_entry_point`].
+///
+/// Callers must declare it `extern "C-unwind"`, as `veles_casper_contract_api::utils::ret` does:
+/// `casper_contract::contract_api::runtime::ret` imports it `extern "C"`, which must not be
+/// unwound through.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_ret(value_ptr: *const u8, value_size: usize) -> ! {
-    todo!()
+pub unsafe extern "C-unwind" fn casper_ret(value_ptr: *const u8, value_size: usize) -> ! {
+    let value = unsafe { core::slice::from_raw_parts(value_ptr, value_size) };
+    let value: CLValue =
+        bytesrepr::deserialize_from_slice(value).expect("Failed to deserialize value");
+
+    with_current_env(|env| env.record(HostFunction::CasperRet, &[0, value_size]));
+    panic::panic_any(RetSignal { value })
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_get_key(
@@ -400,11 +1955,14 @@ pub unsafe extern "C" fn casper_get_key(
     output_size: usize,
     bytes_written_ptr: *mut usize,
 ) -> i32 {
+    let Some(name) = (unsafe { deserialize_name(name_ptr, name_size) }) else {
+        return api_error::i32_from(Err(ApiError::Deserialize));
+    };
     let result = with_current_env(|env| {
-        let name_bytes = unsafe { core::slice::from_raw_parts(name_ptr, name_size) };
-        let name: String =
-            bytesrepr::deserialize_from_slice(name_bytes).expect("Failed to deserialize name");
-        env.trace.push(HostFunction::CasperGetKey(name.clone()));
+        env.record(
+            HostFunction::CasperGetKey(name.clone()),
+            &[0, name_size, 0, output_size, 0],
+        );
 
         match env.named_keys.get(&name) {
             Some(key) => {
@@ -425,11 +1983,11 @@ pub unsafe extern "C" fn casper_get_key(
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_has_key(name_ptr: *const u8, name_size: usize) -> i32 {
+    let Some(name) = (unsafe { deserialize_name(name_ptr, name_size) }) else {
+        return api_error::i32_from(Err(ApiError::Deserialize));
+    };
     with_current_env(|env| {
-        let name_bytes = unsafe { core::slice::from_raw_parts(name_ptr, name_size) };
-        let name: String =
-            bytesrepr::deserialize_from_slice(name_bytes).expect("Failed to deserialize name");
-        env.trace.push(HostFunction::CasperHasKey(name.clone()));
+        env.record(HostFunction::CasperHasKey(name.clone()), &[0, name_size]);
         if env.named_keys.contains_key(&name) {
             0 // Key exists
         } else {
@@ -438,54 +1996,75 @@ pub unsafe extern "C" fn casper_has_key(name_ptr: *const u8, name_size: usize) -
     })
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_put_key(
+pub unsafe extern "C-unwind" fn casper_put_key(
     name_ptr: *const u8,
     name_size: usize,
     key_ptr: *const u8,
     key_size: usize,
 ) {
+    let name = unsafe { deserialize_name(name_ptr, name_size) };
+    let key_bytes = unsafe { core::slice::from_raw_parts(key_ptr, key_size) };
+    let key = bytesrepr::deserialize_from_slice::<_, Key>(key_bytes).ok();
+    let (Some(name), Some(key)) = (name, key) else {
+        host_revert(ApiError::Deserialize);
+    };
     with_current_env(|env| {
-        let name_bytes = unsafe { core::slice::from_raw_parts(name_ptr, name_size) };
-        let name: String =
-            bytesrepr::deserialize_from_slice(name_bytes).expect("Failed to deserialize name");
-        let key_bytes = unsafe { core::slice::from_raw_parts(key_ptr, key_size) };
-        let key: Key =
-            bytesrepr::deserialize_from_slice(key_bytes).expect("Failed to deserialize key");
-        env.trace
-            .push(HostFunction::CasperPutKey(name.clone(), key));
+        env.record(
+            HostFunction::CasperPutKey(name.clone(), key),
+            &[0, name_size, 0, key_size],
+        );
         env.named_keys.insert(name, key);
     });
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_remove_key(name_ptr: *const u8, name_size: usize) {
+pub unsafe extern "C-unwind" fn casper_remove_key(name_ptr: *const u8, name_size: usize) {
+    let Some(name) = (unsafe { deserialize_name(name_ptr, name_size) }) else {
+        host_revert(ApiError::Deserialize);
+    };
     with_current_env(|env| {
-        let name_bytes = unsafe { core::slice::from_raw_parts(name_ptr, name_size) };
-        let name: String =
-            bytesrepr::deserialize_from_slice(name_bytes).expect("Failed to deserialize name");
-        env.trace.push(HostFunction::CasperRemoveKey(name.clone()));
+        env.record(HostFunction::CasperRemoveKey(name.clone()), &[0, name_size]);
         env.named_keys.remove(&name);
     });
 }
+/// Deserializes the named key name passed to the named key host functions.
+///
+/// The engine fails on names that don't deserialize: the functions returning an error code
+/// return [`ApiError::Deserialize`], and the others trap, which the shim turns into a revert with
+/// it.
+unsafe fn deserialize_name(name_ptr: *const u8, name_size: usize) -> Option<String> {
+    let name_bytes = unsafe { core::slice::from_raw_parts(name_ptr, name_size) };
+    bytesrepr::deserialize_from_slice(name_bytes).ok()
+}
+/// Aborts the execution with `api_error` like a revert, for host functions that fail without
+/// returning an error code.
+fn host_revert(api_error: ApiError) -> ! {
+    unsafe { casper_revert(u32::from(api_error)) }
+}
+
+/// Ends the entry point by unwinding with a [`RevertError`], see [`run_entry_point`].
+///
+/// Callers must declare it `extern "C-unwind"`, as `veles_casper_contract_api::utils::revert`
+/// does: `casper_contract::contract_api::runtime::revert` imports it `extern "C"`, which must not
+/// be unwound through.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_revert(status: u32) -> ! {
+pub unsafe extern "C-unwind" fn casper_revert(status: u32) -> ! {
     let api_error = ApiError::from(status);
+    let revert = RevertError { status, api_error };
 
     // Store the revert error in thread-local storage for potential inspection
-    REVERT_ERROR.with(|r| *r.borrow_mut() = Some(RevertError { status, api_error }));
-
-    // Print comprehensive error information for debugging
-    eprintln!("=== CASPER REVERT ===");
-    eprintln!("Status: {}", status);
-    eprintln!("API Error: {:?}", api_error);
-    eprintln!("This indicates the smart contract execution was reverted.");
-    eprintln!("====================");
+    REVERT_ERROR.with(|r| *r.borrow_mut() = Some(revert.clone()));
 
-    // Use abort for a clean termination without unwinding issues
-    std::process::abort();
+    panic::panic_any(revert)
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_is_valid_uref(uref_ptr: *const u8, uref_size: usize) -> i32 {
-    todo!()
+    let uref = unsafe { core::slice::from_raw_parts(uref_ptr, uref_size) };
+    let uref: URef = bytesrepr::deserialize_from_slice(uref).expect("Failed to deserialize URef");
+
+    with_current_env(|env| {
+        env.record(HostFunction::CasperIsValidUref, &[0, uref_size]);
+        i32::from(env.is_known_uref(&uref))
+    })
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_add_associated_key(
@@ -520,22 +2099,27 @@ pub unsafe extern "C" fn casper_get_caller(output_size_ptr: *mut usize) -> i32 {
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_get_blocktime(dest_ptr: *const u8) {
-    todo!()
+    let block_time = with_current_env(|env| {
+        env.record(HostFunction::CasperGetBlocktime, &[0]);
+        env.block_time
+    });
+    let bytes = block_time.to_le_bytes();
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), dest_ptr.cast_mut(), bytes.len()) };
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_create_purse(purse_ptr: *mut u8, purse_size: usize) -> i32 {
+pub unsafe extern "C-unwind" fn casper_create_purse(purse_ptr: *mut u8, purse_size: usize) -> i32 {
     with_current_env(|env| {
-        env.trace.push(HostFunction::CasperCreatePurse);
-        let uref = URef::new(env.next_address(), AccessRights::READ_ADD_WRITE);
-        let key_1 = Key::URef(uref);
+        env.record(HostFunction::CasperCreatePurse, &[0, purse_size]);
+        let uref = env.new_uref();
+        let key_1 = Key::URef(uref).normalize();
         let value_1 = StoredValue::CLValue(CLValue::unit());
-        env.database.insert(key_1, value_1);
+        env.database().insert(key_1, value_1);
 
         let key_2 = Key::Balance(uref.addr());
         let value_2 = StoredValue::CLValue(
             CLValue::from_t(U512::zero()).expect("Failed to create CLValue for balance"),
         );
-        env.database.insert(key_2, value_2);
+        env.database().insert(key_2, value_2);
 
         let key_bytes = uref.to_bytes().expect("Failed to serialize URef");
         unsafe {
@@ -571,8 +2155,11 @@ pub unsafe extern "C" fn casper_transfer_from_purse_to_account(
 ) -> i32 {
     todo!()
 }
+/// Moves motes between two purses of the environment, see [`Env::purse_balance`].
+///
+/// The transfer id is ignored, the shim keeps no transfer records.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_transfer_from_purse_to_purse(
+pub unsafe extern "C-unwind" fn casper_transfer_from_purse_to_purse(
     source_ptr: *const u8,
     source_size: usize,
     target_ptr: *const u8,
@@ -582,7 +2169,20 @@ pub unsafe extern "C" fn casper_transfer_from_purse_to_purse(
     id_ptr: *const u8,
     id_size: usize,
 ) -> i32 {
-    todo!()
+    let source: URef = unsafe { deserialize_arg(source_ptr, source_size, "source purse") };
+    let target: URef = unsafe { deserialize_arg(target_ptr, target_size, "target purse") };
+    let amount: U512 = unsafe { deserialize_arg(amount_ptr, amount_size, "amount") };
+    let _id: Option<u64> = unsafe { deserialize_arg(id_ptr, id_size, "transfer id") };
+
+    let result = with_current_env(|env| {
+        env.record(
+            HostFunction::CasperTransferFromPurseToPurse,
+            &[0, source_size, 0, target_size, 0, amount_size, 0, id_size],
+        );
+        env.transfer_purse_to_purse(source, target, amount)
+    })
+    .unwrap_or_else(|api_error| host_revert(api_error));
+    api_error::i32_from(result)
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_get_balance(
@@ -590,11 +2190,34 @@ pub unsafe extern "C" fn casper_get_balance(
     purse_size: usize,
     result_size: *mut usize,
 ) -> i32 {
-    todo!()
+    let purse_bytes = unsafe { core::slice::from_raw_parts(purse_ptr, purse_size) };
+    let purse: URef =
+        bytesrepr::deserialize_from_slice(purse_bytes).expect("Failed to deserialize URef");
+    let mut result_size = NonNull::new(result_size).expect("result_size pointer must not be null");
+
+    let result = with_current_env(|env| -> Result<(), ApiError> {
+        env.record(HostFunction::CasperGetBalance, &[0, purse_size, 0]);
+        let balance = env.purse_balance(purse).ok_or(ApiError::InvalidPurse)?;
+        let balance = CLValue::from_t(balance).expect("Failed to create CLValue for balance");
+
+        unsafe {
+            *result_size.as_mut() = balance.inner_bytes().len();
+        }
+        let old_host_buffer = env.host_buffer.replace(balance);
+        if let Some(old_host_buffer) = &old_host_buffer {
+            panic!("Host buffer should be empty before writing to it: {old_host_buffer:?}");
+        }
+        Ok(())
+    });
+    api_error::i32_from(result)
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_get_phase(dest_ptr: *mut u8) {
-    todo!()
+    let phase = with_current_env(|env| {
+        env.record(HostFunction::CasperGetPhase, &[0]);
+        env.phase
+    });
+    unsafe { *dest_ptr = phase as u8 };
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_get_system_contract(
@@ -602,43 +2225,86 @@ pub unsafe extern "C" fn casper_get_system_contract(
     dest_ptr: *mut u8,
     dest_size: usize,
 ) -> i32 {
-    todo!()
+    with_current_env(|env| {
+        env.record(
+            HostFunction::CasperGetSystemContract(system_contract_index),
+            &[system_contract_index as usize, 0, dest_size],
+        );
+        let Some(hash) = env.system_contracts.get(&system_contract_index) else {
+            return api_error::i32_from(Err(ApiError::InvalidSystemContract));
+        };
+        if dest_size < hash.len() {
+            return api_error::i32_from(Err(ApiError::BufferTooSmall));
+        }
+        unsafe { core::ptr::copy_nonoverlapping(hash.as_ptr(), dest_ptr, hash.len()) };
+        0
+    })
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_get_main_purse(dest_ptr: *mut u8) {
-    todo!()
+    with_current_env(|env| {
+        env.record(HostFunction::CasperGetMainPurse, &[0]);
+        let purse_bytes = env.main_purse.to_bytes().expect("Failed to serialize URef");
+        unsafe {
+            core::ptr::copy_nonoverlapping(purse_bytes.as_ptr(), dest_ptr, purse_bytes.len());
+        }
+    })
 }
+/// Moves the host buffer into `dest_ptr`.
+///
+/// Like the engine, the host buffer is taken even when it doesn't fit in `dest_size` bytes, which
+/// returns [`ApiError::BufferTooSmall`]: reading into an empty destination discards it.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_read_host_buffer(
     dest_ptr: *mut u8,
     dest_size: usize,
     bytes_written: *mut usize,
 ) -> i32 {
-    let result = with_current_env(|env| match env.host_buffer.take() {
-        Some(host_buffer) => {
-            let bytes = host_buffer.inner_bytes();
-
-            unsafe {
-                *bytes_written = bytes.len();
-                assert_eq!(bytes.len(), dest_size, "Host buffer size mismatch");
-                core::ptr::copy_nonoverlapping(bytes.as_ptr(), dest_ptr, dest_size);
+    let result = with_current_env(|env| {
+        env.record(HostFunction::CasperReadHostBuffer, &[0, dest_size, 0]);
+        match env.host_buffer.take() {
+            Some(host_buffer) => {
+                let bytes = host_buffer.inner_bytes();
+                if bytes.len() > dest_size {
+                    return Err(ApiError::BufferTooSmall);
+                }
+
+                unsafe {
+                    *bytes_written = bytes.len();
+                    core::ptr::copy_nonoverlapping(bytes.as_ptr(), dest_ptr, bytes.len());
+                }
+                Ok(())
             }
-            Ok(())
+            None => Err(ApiError::HostBufferEmpty),
         }
-        None => Err(ApiError::HostBufferEmpty),
     });
     api_error::i32_from(result)
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_create_contract_package_at_hash(
+pub unsafe extern "C-unwind" fn casper_create_contract_package_at_hash(
     hash_addr_ptr: *mut u8,
     access_addr_ptr: *mut u8,
     is_locked: bool,
 ) {
-    todo!();
+    let (package_hash, access_uref) = with_current_env(|env| {
+        env.record(
+            HostFunction::CasperCreateContractPackageAtHash,
+            &[0, 0, usize::from(is_locked)],
+        );
+        env.create_package(is_locked)
+    });
+    let access_addr = access_uref.addr();
+    unsafe {
+        core::ptr::copy_nonoverlapping(package_hash.as_ptr(), hash_addr_ptr, package_hash.len());
+        core::ptr::copy_nonoverlapping(access_addr.as_ptr(), access_addr_ptr, access_addr.len());
+    }
 }
+/// Adds a user group to a package created in the environment, see [`Env::package`].
+///
+/// Like the engine, a package missing from the environment or whose access URef the call stack
+/// doesn't hold reverts with [`ApiError::ValueNotFound`] and [`ApiError::PermissionDenied`].
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_create_contract_user_group(
+pub unsafe extern "C-unwind" fn casper_create_contract_user_group(
     contract_package_hash_ptr: *const u8,
     contract_package_hash_size: usize,
     label_ptr: *const u8,
@@ -648,10 +2314,72 @@ pub unsafe extern "C" fn casper_create_contract_user_group(
     existing_urefs_size: usize,
     output_size_ptr: *mut usize,
 ) -> i32 {
-    todo!()
+    let package_hash: ContractPackageHash = unsafe {
+        deserialize_arg(
+            contract_package_hash_ptr,
+            contract_package_hash_size,
+            "package hash",
+        )
+    };
+    let label: String = unsafe { deserialize_arg(label_ptr, label_size, "group label") };
+    let existing_urefs: BTreeSet<URef> =
+        unsafe { deserialize_arg(existing_urefs_ptr, existing_urefs_size, "existing URefs") };
+
+    let result = with_current_env(|env| {
+        env.record(
+            HostFunction::CasperCreateContractUserGroup,
+            &[
+                0,
+                contract_package_hash_size,
+                0,
+                label_size,
+                usize::from(num_new_urefs),
+                0,
+                existing_urefs_size,
+                0,
+            ],
+        );
+        env.create_user_group(package_hash.value(), label, num_new_urefs, existing_urefs)
+    })
+    .unwrap_or_else(|api_error| host_revert(api_error))
+    .map(|new_urefs| {
+        let value = CLValue::from_t(new_urefs).expect("Failed to create CLValue from URefs");
+        unsafe {
+            *output_size_ptr = value.inner_bytes().len();
+        }
+        with_current_env(|env| {
+            let old_host_buffer = env.host_buffer.replace(value);
+            if let Some(old_host_buffer) = &old_host_buffer {
+                panic!("Host buffer should be empty before writing to it: {old_host_buffer:?}");
+            }
+        });
+    });
+    api_error::i32_from(result)
+}
+/// Deserializes the `T` at `ptr` of host function arguments.
+unsafe fn deserialize_arg<T: FromBytes>(ptr: *const u8, size: usize, name: &str) -> T {
+    let bytes = unsafe { core::slice::from_raw_parts(ptr, size) };
+    bytesrepr::deserialize_from_slice(bytes)
+        .unwrap_or_else(|error| panic!("Failed to deserialize {name}: {error}"))
+}
+
+/// Writes the hash and version of a contract version added by `casper_add_*_version*`, returning
+/// the number of bytes written to `output_ptr`.
+unsafe fn write_added_version(
+    (contract_hash, contract_version): (ContractHash, ContractVersion),
+    version_ptr: *const u32,
+    output_ptr: *mut u8,
+) -> usize {
+    let hash_bytes = contract_hash.value();
+    unsafe {
+        *version_ptr.cast_mut() = contract_version;
+        core::ptr::copy_nonoverlapping(hash_bytes.as_ptr(), output_ptr, hash_bytes.len());
+    }
+    hash_bytes.len()
 }
+
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_add_contract_version(
+pub unsafe extern "C-unwind" fn casper_add_contract_version(
     contract_package_hash_ptr: *const u8,
     contract_package_hash_size: usize,
     version_ptr: *const u32,
@@ -663,10 +2391,113 @@ pub unsafe extern "C" fn casper_add_contract_version(
     output_size: usize,
     bytes_written_ptr: *mut usize,
 ) -> i32 {
-    todo!()
+    let package_hash: ContractPackageHash = unsafe {
+        deserialize_arg(
+            contract_package_hash_ptr,
+            contract_package_hash_size,
+            "package hash",
+        )
+    };
+    let entry_points: contracts::EntryPoints =
+        unsafe { deserialize_arg(entry_points_ptr, entry_points_size, "entry points") };
+    let named_keys: NamedKeys =
+        unsafe { deserialize_arg(named_keys_ptr, named_keys_size, "named keys") };
+
+    let result = with_current_env(|env| {
+        env.record(
+            HostFunction::CasperAddContractVersion,
+            &[
+                0,
+                contract_package_hash_size,
+                0,
+                0,
+                entry_points_size,
+                0,
+                named_keys_size,
+                0,
+                output_size,
+                0,
+            ],
+        );
+        if output_size < HashAddr::default().len() {
+            return Ok(Err(ApiError::BufferTooSmall));
+        }
+        env.add_contract_version(
+            package_hash.value(),
+            entry_points,
+            named_keys,
+            BTreeMap::new(),
+        )
+    })
+    .unwrap_or_else(|api_error| host_revert(api_error));
+    let result = result.map(|added| unsafe {
+        *bytes_written_ptr = write_added_version(added, version_ptr, output_ptr);
+    });
+    api_error::i32_from(result)
+}
+
+/// Adds a version with message topics for the `casper_add_*_version_with_message_topics` host
+/// functions, which only differ in how the engine with addressable entities stores packages.
+#[allow(clippy::too_many_arguments)]
+unsafe fn add_version_with_message_topics(
+    host_function: HostFunction,
+    package_hash_ptr: *const u8,
+    package_hash_size: usize,
+    version_ptr: *const u32,
+    entry_points_ptr: *const u8,
+    entry_points_size: usize,
+    named_keys_ptr: *const u8,
+    named_keys_size: usize,
+    message_topics_ptr: *const u8,
+    message_topics_size: usize,
+    output_ptr: *mut u8,
+    output_size: usize,
+) -> i32 {
+    let package_hash: ContractPackageHash =
+        unsafe { deserialize_arg(package_hash_ptr, package_hash_size, "package hash") };
+    let entry_points: EntryPoints =
+        unsafe { deserialize_arg(entry_points_ptr, entry_points_size, "entry points") };
+    let named_keys: NamedKeys =
+        unsafe { deserialize_arg(named_keys_ptr, named_keys_size, "named keys") };
+    let message_topics: BTreeMap<String, MessageTopicOperation> =
+        unsafe { deserialize_arg(message_topics_ptr, message_topics_size, "message topics") };
+
+    let result = with_current_env(|env| {
+        env.record(
+            host_function,
+            &[
+                0,
+                package_hash_size,
+                0,
+                0,
+                entry_points_size,
+                0,
+                named_keys_size,
+                0,
+                message_topics_size,
+                0,
+                output_size,
+            ],
+        );
+        if output_size < HashAddr::default().len() {
+            return Ok(Err(ApiError::BufferTooSmall));
+        }
+        env.add_contract_version(
+            package_hash.value(),
+            entry_points.into(),
+            named_keys,
+            message_topics,
+        )
+    })
+    .unwrap_or_else(|api_error| host_revert(api_error));
+    let result = result.map(|added| unsafe {
+        write_added_version(added, version_ptr, output_ptr);
+    });
+    api_error::i32_from(result)
 }
+
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_add_contract_version_with_message_topics(
+pub unsafe extern "C-unwind" fn casper_add_contract_version_with_message_topics(
     contract_package_hash_ptr: *const u8,
     contract_package_hash_size: usize,
     version_ptr: *const u32,
@@ -679,10 +2510,25 @@ pub unsafe extern "C" fn casper_add_contract_version_with_message_topics(
     output_ptr: *mut u8,
     output_size: usize,
 ) -> i32 {
-    0
+    unsafe {
+        add_version_with_message_topics(
+            HostFunction::CasperAddContractVersionWithMessageTopics,
+            contract_package_hash_ptr,
+            contract_package_hash_size,
+            version_ptr,
+            entry_points_ptr,
+            entry_points_size,
+            named_keys_ptr,
+            named_keys_size,
+            message_topics_ptr,
+            message_topics_size,
+            output_ptr,
+            output_size,
+        )
+    }
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_add_package_version_with_message_topics(
+pub unsafe extern "C-unwind" fn casper_add_package_version_with_message_topics(
     package_hash_ptr: *const u8,
     package_hash_size: usize,
     version_ptr: *const u32,
@@ -695,19 +2541,81 @@ pub unsafe extern "C" fn casper_add_package_version_with_message_topics(
     output_ptr: *mut u8,
     output_size: usize,
 ) -> i32 {
-    todo!()
+    unsafe {
+        add_version_with_message_topics(
+            HostFunction::CasperAddPackageVersionWithMessageTopics,
+            package_hash_ptr,
+            package_hash_size,
+            version_ptr,
+            entry_points_ptr,
+            entry_points_size,
+            named_keys_ptr,
+            named_keys_size,
+            message_topics_ptr,
+            message_topics_size,
+            output_ptr,
+            output_size,
+        )
+    }
+}
+
+/// Enables or disables a contract version for `casper_enable_contract_version` and
+/// `casper_disable_contract_version`.
+unsafe fn set_contract_version_enabled(
+    host_function: HostFunction,
+    contract_package_hash_ptr: *const u8,
+    contract_package_hash_size: usize,
+    contract_hash_ptr: *const u8,
+    contract_hash_size: usize,
+    enabled: bool,
+) -> i32 {
+    let package_hash: ContractPackageHash = unsafe {
+        deserialize_arg(
+            contract_package_hash_ptr,
+            contract_package_hash_size,
+            "package hash",
+        )
+    };
+    let contract_hash: ContractHash =
+        unsafe { deserialize_arg(contract_hash_ptr, contract_hash_size, "contract hash") };
+
+    let result = with_current_env(|env| {
+        env.record(
+            host_function,
+            &[0, contract_package_hash_size, 0, contract_hash_size],
+        );
+        env.set_version_enabled(package_hash.value(), contract_hash, enabled)
+    })
+    .unwrap_or_else(|api_error| host_revert(api_error));
+    api_error::i32_from(result)
 }
+
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_disable_contract_version(
+pub unsafe extern "C-unwind" fn casper_disable_contract_version(
     contract_package_hash_ptr: *const u8,
     contract_package_hash_size: usize,
     contract_hash_ptr: *const u8,
     contract_hash_size: usize,
 ) -> i32 {
-    todo!()
+    unsafe {
+        set_contract_version_enabled(
+            HostFunction::CasperDisableContractVersion,
+            contract_package_hash_ptr,
+            contract_package_hash_size,
+            contract_hash_ptr,
+            contract_hash_size,
+            false,
+        )
+    }
 }
+/// Runs an entry point registered with [`EnvBuilder::with_contract_entry_point`].
+///
+/// Like in the engine, a revert of the callee reverts the whole execution: it unwinds on through
+/// the caller, so callers must declare this function `extern "C-unwind"`, as
+/// `veles_casper_contract_api::utils::call_contract_typed` does. [`run_entry_point`] discards the
+/// writes made before the revert.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_call_contract(
+pub unsafe extern "C-unwind" fn casper_call_contract(
     contract_hash_ptr: *const u8,
     contract_hash_size: usize,
     entry_point_name_ptr: *const u8,
@@ -716,10 +2624,109 @@ pub unsafe extern "C" fn casper_call_contract(
     runtime_args_size: usize,
     result_size: *mut usize,
 ) -> i32 {
-    todo!()
+    let contract_hash =
+        unsafe { core::slice::from_raw_parts(contract_hash_ptr, contract_hash_size) };
+    let contract_hash: ContractHash = bytesrepr::deserialize_from_slice(contract_hash)
+        .expect("Failed to deserialize ContractHash");
+    let entry_point_name =
+        unsafe { core::slice::from_raw_parts(entry_point_name_ptr, entry_point_name_size) };
+    let entry_point_name: String = bytesrepr::deserialize_from_slice(entry_point_name)
+        .expect("Failed to deserialize entry point name");
+    let runtime_args = unsafe { core::slice::from_raw_parts(runtime_args_ptr, runtime_args_size) };
+    let runtime_args: RuntimeArgs =
+        bytesrepr::deserialize_from_slice(runtime_args).expect("Failed to deserialize RuntimeArgs");
+
+    with_current_env(|env| {
+        env.record(
+            HostFunction::CasperCallContract,
+            &[
+                0,
+                contract_hash_size,
+                0,
+                entry_point_name_size,
+                0,
+                runtime_args_size,
+                0,
+            ],
+        );
+    });
+    unsafe { call_entry_point(contract_hash, entry_point_name, &runtime_args, result_size) }
+}
+
+/// Runs the entry point `entry_point_name` registered for `contract_hash` with `runtime_args`,
+/// the shared part of `casper_call_contract` and `casper_call_versioned_contract`.
+unsafe fn call_entry_point(
+    contract_hash: ContractHash,
+    entry_point_name: String,
+    runtime_args: &RuntimeArgs,
+    result_size: *mut usize,
+) -> i32 {
+    let caller = EnvStack::current().top().expect(NO_ENV_MESSAGE);
+    let (entry_point, named_keys) = with_current_env(|env| {
+        let entry_point = env
+            .contract_entry_points
+            .read()
+            .unwrap()
+            .get(&(contract_hash, entry_point_name.clone()))
+            .cloned()
+            .unwrap_or_else(|| {
+                panic!(
+                    "no entry point `{entry_point_name}` is registered for {contract_hash}, see \
+                     EnvBuilder::with_contract_entry_point"
+                )
+            });
+        let named_keys = match env.database().get(&Key::Hash(contract_hash.value())) {
+            Some(StoredValue::Contract(contract)) => contract
+                .named_keys()
+                .iter()
+                .map(|(name, key)| (name.clone(), *key))
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+        (entry_point, named_keys)
+    });
+    let args = named_args(runtime_args);
+
+    install_quiet_panic_hook();
+    let outcome = {
+        let _guard = caller.enter_call(args, named_keys);
+        panic::catch_unwind(AssertUnwindSafe(|| (entry_point.0)()))
+    };
+    let value = match outcome.map_err(|payload| payload.downcast::<RevertError>()) {
+        Ok(()) => CLValue::unit(),
+        // The callee's revert reverts the caller too
+        Err(Ok(revert)) => panic::resume_unwind(revert),
+        Err(Err(payload)) => match payload.downcast::<RetSignal>() {
+            Ok(ret) => ret.value,
+            Err(payload) => panic::resume_unwind(payload),
+        },
+    };
+
+    with_current_env(|env| {
+        let size = value.inner_bytes().len();
+        unsafe {
+            *result_size = size;
+        }
+        // Nothing is read back from the host buffer for an empty result
+        if size > 0 {
+            let old_host_buffer = env.host_buffer.replace(value);
+            if let Some(old_host_buffer) = &old_host_buffer {
+                panic!("Host buffer should be empty before writing to it: {old_host_buffer:?}");
+            }
+        }
+        0 // Success
+    })
 }
+
+/// Runs the entry point of the given version of a package created in the call stack, or of its
+/// newest enabled version for `None`, like [`casper_call_contract`] does for a contract hash.
+///
+/// The engine traps when the package is missing or the version doesn't exist or is disabled,
+/// which the shim turns into a revert with [`ApiError::ValueNotFound`] and
+/// `contracts::Error::ContractNotFound` respectively. The version number is that of the
+/// protocol's major version, as for calls of the engine.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_call_versioned_contract(
+pub unsafe extern "C-unwind" fn casper_call_versioned_contract(
     contract_package_hash_ptr: *const u8,
     contract_package_hash_size: usize,
     contract_version_ptr: *const u8,
@@ -730,7 +2737,61 @@ pub unsafe extern "C" fn casper_call_versioned_contract(
     runtime_args_size: usize,
     result_size: *mut usize,
 ) -> i32 {
-    todo!()
+    let package_hash = unsafe {
+        core::slice::from_raw_parts(contract_package_hash_ptr, contract_package_hash_size)
+    };
+    let package_hash: ContractPackageHash = bytesrepr::deserialize_from_slice(package_hash)
+        .expect("Failed to deserialize ContractPackageHash");
+    let contract_version =
+        unsafe { core::slice::from_raw_parts(contract_version_ptr, contract_version_size) };
+    let contract_version: Option<ContractVersion> =
+        bytesrepr::deserialize_from_slice(contract_version)
+            .expect("Failed to deserialize contract version");
+    let entry_point_name =
+        unsafe { core::slice::from_raw_parts(entry_point_name_ptr, entry_point_name_size) };
+    let entry_point_name: String = bytesrepr::deserialize_from_slice(entry_point_name)
+        .expect("Failed to deserialize entry point name");
+    let runtime_args = unsafe { core::slice::from_raw_parts(runtime_args_ptr, runtime_args_size) };
+    let runtime_args: RuntimeArgs =
+        bytesrepr::deserialize_from_slice(runtime_args).expect("Failed to deserialize RuntimeArgs");
+
+    let contract_hash = with_current_env(|env| {
+        env.record(
+            HostFunction::CasperCallVersionedContract,
+            &[
+                0,
+                contract_package_hash_size,
+                0,
+                contract_version_size,
+                0,
+                entry_point_name_size,
+                0,
+                runtime_args_size,
+                0,
+            ],
+        );
+        let packages = env.packages();
+        let package = packages
+            .get(&package_hash.value())
+            .ok_or(ApiError::ValueNotFound)?;
+        let version = match contract_version {
+            Some(contract_version) => {
+                let key =
+                    ContractVersionKey::new(env.protocol_version.value().major, contract_version);
+                package
+                    .versions
+                    .get(&key)
+                    .filter(|version| version.enabled)
+                    .map(|version| version.contract_hash)
+            }
+            None => package
+                .current_version()
+                .map(|(_, contract_hash)| contract_hash),
+        };
+        version.ok_or(ApiError::from(contracts::Error::ContractNotFound))
+    })
+    .unwrap_or_else(|api_error| host_revert(api_error));
+    unsafe { call_entry_point(contract_hash, entry_point_name, &runtime_args, result_size) }
 }
 
 #[unsafe(no_mangle)]
@@ -742,7 +2803,7 @@ pub unsafe extern "C" fn casper_get_named_arg_size(
     let name: &[u8] = unsafe { core::slice::from_raw_parts(name_ptr, name_size) };
     let name: &str = core::str::from_utf8(name).expect("Failed to convert bytes to str");
     with_current_env(|env| {
-        env.trace.push(HostFunction::CasperGetNamedArgSize);
+        env.record(HostFunction::CasperGetNamedArgSize, &[0, name_size, 0]);
         match env.args.get(name) {
             Some(value) => {
                 let size = value.inner_bytes().len();
@@ -771,7 +2832,10 @@ pub unsafe extern "C" fn casper_get_named_arg(
     let name: &[u8] = unsafe { core::slice::from_raw_parts(name_ptr, name_size) };
     let name: &str = core::str::from_utf8(name).expect("Failed to convert bytes to str");
     let result = with_current_env(|env| {
-        env.trace.push(HostFunction::CasperGetNamedArg);
+        env.record(
+            HostFunction::CasperGetNamedArg,
+            &[0, name_size, 0, dest_size],
+        );
         match env.args.get(name) {
             Some(value) => {
                 let bytes = value.inner_bytes();
@@ -840,28 +2904,45 @@ pub unsafe extern "C" fn casper_load_call_stack(
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_print(text_ptr: *const u8, text_size: usize) {
-    let text: &[u8] = unsafe { core::slice::from_raw_parts(text_ptr, text_size) };
-    let text: String =
-        bytesrepr::deserialize_from_slice(text).expect("Failed to deserialize text for printing");
-
-    eprintln!("Print: {text}");
+    let bytes: &[u8] = unsafe { core::slice::from_raw_parts(text_ptr, text_size) };
+    // Raw prints are not length prefixed, so they are rendered instead of rejected
+    let text = bytesrepr::deserialize_from_slice::<_, String>(bytes)
+        .unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned());
+
+    let verbose = match EnvStack::current().top() {
+        Some(env) => {
+            let env_impl = env.env_impl.read().unwrap();
+            env_impl.record(HostFunction::CasperPrint, &[0, text_size]);
+            env_impl.prints.write().unwrap().push(text.clone());
+            env_impl.verbose_prints
+        }
+        // Code printing outside of an environment has nowhere else to print to
+        None => true,
+    };
+    if verbose || std::env::var_os(VERBOSE_PRINTS_VAR).is_some() {
+        eprintln!("Print: {text}");
+    }
 }
 
 /// Creates a new dictionary and returns its URef in the host buffer.
 ///
 /// # Safety
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_new_dictionary(output_size_ptr: *mut usize) -> i32 {
+pub unsafe extern "C-unwind" fn casper_new_dictionary(output_size_ptr: *mut usize) -> i32 {
     with_current_env(|env| {
-        let uref = URef::new(env.next_address(), AccessRights::READ_ADD_WRITE);
-        let key = Key::URef(uref);
+        env.record(
+            HostFunction::CasperNewDictionary,
+            &[0, 0, UREF_SERIALIZED_LENGTH],
+        );
+        let uref = env.new_uref();
+        let key = Key::URef(uref).normalize();
 
         let cl_value = CLValue::unit();
 
-        env.database
+        env.database()
             .insert(key, StoredValue::CLValue(cl_value.clone()));
 
-        env.dictionaries.entry(uref.addr()).or_default();
+        env.dictionaries().entry(uref.addr()).or_default();
 
         let old_host_buffer = env
             .host_buffer
@@ -884,7 +2965,33 @@ pub unsafe extern "C" fn casper_dictionary_get(
     key_bytes_size: usize,
     output_size: *mut usize,
 ) -> i32 {
-    todo!()
+    let uref_bytes = unsafe { core::slice::from_raw_parts(uref_ptr, uref_size) };
+    let uref: URef =
+        bytesrepr::deserialize_from_slice(uref_bytes).expect("Failed to deserialize URef");
+    let key_bytes = unsafe { core::slice::from_raw_parts(key_bytes_ptr, key_bytes_size) };
+    let key = core::str::from_utf8(key_bytes).expect("Failed to convert key bytes to str");
+    let mut output_size = NonNull::new(output_size).expect("output_size pointer must not be null");
+
+    let result = with_current_env(|env| -> Result<(), ApiError> {
+        env.record(HostFunction::CasperDictionaryGet, &[0, key_bytes_size, 0]);
+        env.check_uref(&uref, AccessRights::READ)?;
+        let value = env
+            .dictionaries()
+            .get(&uref.addr())
+            .and_then(|dict| dict.get(key))
+            .cloned()
+            .ok_or(ApiError::ValueNotFound)?;
+
+        unsafe {
+            *output_size.as_mut() = value.inner_bytes().len();
+        }
+        let old_host_buffer = env.host_buffer.replace(value);
+        if let Some(old_host_buffer) = &old_host_buffer {
+            panic!("Host buffer should be empty before writing to it: {old_host_buffer:?}");
+        }
+        Ok(())
+    });
+    api_error::i32_from(result)
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_dictionary_read(
@@ -919,7 +3026,14 @@ pub unsafe extern "C" fn casper_dictionary_put(
         let value: CLValue =
             bytesrepr::deserialize_from_slice(value_bytes).expect("Failed to deserialize value");
 
-        if let Some(dict) = env.dictionaries.get_mut(&uref.addr()) {
+        env.record(
+            HostFunction::CasperDictionaryPut,
+            &[0, key_size, 0, value_size],
+        );
+        if let Err(error) = env.check_uref(&uref, AccessRights::WRITE) {
+            return api_error::i32_from(Err(error));
+        }
+        if let Some(dict) = env.dictionaries().get_mut(&uref.addr()) {
             dict.insert(key, value);
             0 // Success
         } else {
@@ -932,13 +3046,22 @@ pub unsafe extern "C" fn casper_random_bytes(out_ptr: *mut u8, out_size: usize)
     todo!()
 }
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn casper_enable_contract_version(
+pub unsafe extern "C-unwind" fn casper_enable_contract_version(
     contract_package_hash_ptr: *const u8,
     contract_package_hash_size: usize,
     contract_hash_ptr: *const u8,
     contract_hash_size: usize,
 ) -> i32 {
-    todo!()
+    unsafe {
+        set_contract_version_enabled(
+            HostFunction::CasperEnableContractVersion,
+            contract_package_hash_ptr,
+            contract_package_hash_size,
+            contract_hash_ptr,
+            contract_hash_size,
+            true,
+        )
+    }
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_manage_message_topic(
@@ -947,7 +3070,34 @@ pub unsafe extern "C" fn casper_manage_message_topic(
     operation_ptr: *const u8,
     operation_size: usize,
 ) -> i32 {
-    todo!()
+    let topic_name = unsafe { core::slice::from_raw_parts(topic_name_ptr, topic_name_size) };
+    let topic_name = String::from_utf8(topic_name.to_vec()).expect("Topic name should be UTF-8");
+    let operation: MessageTopicOperation =
+        unsafe { deserialize_arg(operation_ptr, operation_size, "message topic operation") };
+
+    let result = with_current_env(|env| {
+        env.record(
+            HostFunction::CasperManageMessageTopic,
+            &[0, topic_name_size, 0, operation_size],
+        );
+        // Checked like the engine, against its default limits
+        let limits = casper_types::MessageLimits::default();
+        if topic_name_size > limits.max_topic_name_size as usize {
+            return Err(ApiError::MaxTopicNameSizeExceeded);
+        }
+        match operation {
+            MessageTopicOperation::Add => {
+                if env.message_topics.len() >= limits.max_topics_per_contract as usize {
+                    return Err(ApiError::MaxTopicsNumberExceeded);
+                }
+                if !env.message_topics.insert(topic_name) {
+                    return Err(ApiError::MessageTopicAlreadyRegistered);
+                }
+            }
+        }
+        Ok(())
+    });
+    api_error::i32_from(result)
 }
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_emit_message(
@@ -956,7 +3106,24 @@ pub unsafe extern "C" fn casper_emit_message(
     message_ptr: *const u8,
     message_size: usize,
 ) -> i32 {
-    todo!()
+    let topic_name = unsafe { core::slice::from_raw_parts(topic_name_ptr, topic_name_size) };
+    let topic_name = String::from_utf8(topic_name.to_vec()).expect("Topic name should be UTF-8");
+    let message = unsafe { core::slice::from_raw_parts(message_ptr, message_size) };
+    let payload: MessagePayload =
+        bytesrepr::deserialize_from_slice(message).expect("Failed to deserialize message");
+
+    with_current_env(|env| {
+        env.record(
+            HostFunction::CasperEmitMessage,
+            &[0, topic_name_size, 0, message_size],
+        );
+        env.messages.write().unwrap().push(EmittedMessage {
+            depth: env.depth,
+            topic_name,
+            payload,
+        });
+    });
+    0
 }
 
 #[unsafe(no_mangle)]
@@ -965,16 +3132,66 @@ pub unsafe extern "C" fn casper_load_caller_information(
     call_stack_len_ptr: *mut usize,
     result_size_ptr: *mut usize,
 ) -> i32 {
-    unimplemented_ffi!("casper_load_caller_information")
+    let result = with_current_env(|env| {
+        env.record(
+            HostFunction::CasperLoadCallerInformation,
+            &[action.into(), 0, 0],
+        );
+        // Without contract identities in the shim, the configured account is the whole stack
+        // unless one is configured.
+        let call_stack = if env.call_stack.is_empty() {
+            vec![Caller::initiator(env.account.as_ref()?.account_hash)]
+        } else {
+            env.call_stack.clone()
+        };
+        // Mirrors the actions of `veles_casper_contract_api::utils::CallerAction`.
+        let callers = match action {
+            0 => call_stack[..1].to_vec(),
+            1 => call_stack[call_stack.len() - 1..].to_vec(),
+            _ => call_stack,
+        };
+        let callers = callers
+            .into_iter()
+            .map(|caller| CallerInfo::try_from(caller).expect("Failed to convert caller"))
+            .collect::<Vec<_>>();
+        let value = CLValue::from_t(callers).expect("Failed to serialize caller information");
+        unsafe {
+            *call_stack_len_ptr = env.call_stack.len().max(1);
+            *result_size_ptr = value.inner_bytes().len();
+        }
+        let old_host_buffer = env.host_buffer.replace(value);
+        if let Some(old_host_buffer) = &old_host_buffer {
+            panic!("Host buffer should be empty before writing to it: {old_host_buffer:?}");
+        }
+        Some(())
+    });
+    match result {
+        Some(()) => 0,
+        None => unimplemented_ffi!("casper_load_caller_information"),
+    }
 }
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn casper_get_block_info(field_idx: u8, dest_ptr: *const u8) {
-    todo!("casper_get_block_info")
+    /// Mirrors `casper_contract::contract_api::runtime::PROTOCOL_VERSION_FIELD_IDX`.
+    const PROTOCOL_VERSION_FIELD_IDX: u8 = 4;
+
+    let bytes = with_current_env(|env| {
+        env.record(HostFunction::CasperGetBlockInfo, &[field_idx.into(), 0]);
+        match field_idx {
+            PROTOCOL_VERSION_FIELD_IDX => env
+                .protocol_version
+                .to_bytes()
+                .expect("Failed to serialize protocol version"),
+            _ => todo!("casper_get_block_info field {field_idx}"),
+        }
+    });
+    unsafe {
+        core::ptr::copy_nonoverlapping(bytes.as_ptr(), dest_ptr.cast_mut(), bytes.len());
+    }
 }
 
 /// The 32-byte digest keccak256 hash function
-#[allow(dead_code)]
 fn keccak256<T: AsRef<[u8]>>(data: T) -> [u8; 32] {
     use keccak_asm::Digest as KeccakDigest;
     use keccak_asm::Keccak256;
@@ -995,13 +3212,32 @@ pub unsafe extern "C" fn casper_generic_hash(
     out_ptr: *const u8,
     out_size: usize,
 ) -> i32 {
-    let result = {
+    const BLAKE2B: u8 = 0;
+    const KECCAK256: u8 = 3;
+
+    with_current_env(|env| {
+        env.record(
+            HostFunction::CasperGenericHash,
+            &[0, in_size, hash_algo_type.into(), 0, out_size],
+        )
+    });
+
+    let input = unsafe { core::slice::from_raw_parts(in_ptr, in_size) };
+    let digest = match hash_algo_type {
+        BLAKE2B => Digest::hash(input).value(),
+        KECCAK256 => keccak256(input),
         // For allowing fallback in the code that uses this FFI function we'll report InvalidArgument as if given algorithm is not supported instead of failing.
         // This allows production code to fallback gracefully instead of panicking.
-        Err(ApiError::InvalidArgument)
+        _ => return api_error::i32_from(Err(ApiError::InvalidArgument)),
     };
-
-    api_error::i32_from(result)
+    // The engine writes the fixed-size digest into larger outputs as well
+    if out_size < digest.len() {
+        return api_error::i32_from(Err(ApiError::BufferTooSmall));
+    }
+    unsafe {
+        core::ptr::copy_nonoverlapping(digest.as_ptr(), out_ptr.cast_mut(), digest.len());
+    }
+    api_error::i32_from::<ApiError>(Ok(()))
 }
 
 #[unsafe(no_mangle)]
@@ -1025,7 +3261,22 @@ pub unsafe extern "C" fn casper_verify_signature(
     public_key_ptr: *const u8,
     public_key_size: usize,
 ) -> i32 {
-    todo!()
+    let message = unsafe { core::slice::from_raw_parts(message_ptr, message_size) };
+    let signature: Signature =
+        unsafe { deserialize_arg(signature_ptr, signature_size, "signature") };
+    let public_key: PublicKey =
+        unsafe { deserialize_arg(public_key_ptr, public_key_size, "public key") };
+
+    with_current_env(|env| {
+        env.record(
+            HostFunction::CasperVerifySignature,
+            &[0, message_size, 0, signature_size, 0, public_key_size],
+        );
+    });
+    // Like the engine, which doesn't tell why verification failed
+    let result = casper_types::crypto::verify(message, &signature, &public_key)
+        .map_err(|_| ApiError::InvalidArgument);
+    api_error::i32_from(result)
 }
 
 #[unsafe(no_mangle)]
@@ -0,0 +1,352 @@
+//! Assertions on the host function trace of an [`Env`](crate::Env).
+//!
+//! Comparing a trace against an exact `Vec<HostFunction>` breaks whenever library code makes an
+//! extra call the test doesn't care about. [`Env::assert_trace`](crate::Env::assert_trace) instead
+//! runs a [`TraceAssert`] over the trace, checking only the calls the test names:
+//!
+//! ```ignore
+//! env.assert_trace(|t| {
+//!     t.contains(HostFunctionPattern::PutKey(Some("counter"), None))
+//!         .times(1)
+//!         .before(HostFunction::CasperWrite)
+//!         .never(HostFunctionPattern::RemoveKey(None))
+//! });
+//! ```
+//!
+//! A failed assertion panics with the whole trace, marking the calls it is about.
+use std::fmt::{self, Write};
+
+use casper_types::Key;
+
+use crate::{HostFunction, TraceRecord};
+
+/// A host function call to look for in a trace.
+///
+/// `None` payloads match any value, so `PutKey(Some("name"), None)` matches putting any key under
+/// `name`. Host functions converted into a pattern match only equal calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostFunctionPattern<'a> {
+    /// Any call.
+    Any,
+    /// Calls equal to this one, payloads included.
+    Is(HostFunction),
+    GetKey(Option<&'a str>),
+    HasKey(Option<&'a str>),
+    PutKey(Option<&'a str>, Option<Key>),
+    RemoveKey(Option<&'a str>),
+}
+
+impl HostFunctionPattern<'_> {
+    /// Returns whether `host_function` matches the pattern.
+    pub fn matches(&self, host_function: &HostFunction) -> bool {
+        fn field<T: PartialEq + ?Sized>(pattern: Option<&T>, value: &T) -> bool {
+            pattern.is_none_or(|pattern| pattern == value)
+        }
+
+        match (self, host_function) {
+            (HostFunctionPattern::Any, _) => true,
+            (HostFunctionPattern::Is(expected), host_function) => expected == host_function,
+            (HostFunctionPattern::GetKey(name), HostFunction::CasperGetKey(actual))
+            | (HostFunctionPattern::HasKey(name), HostFunction::CasperHasKey(actual))
+            | (HostFunctionPattern::RemoveKey(name), HostFunction::CasperRemoveKey(actual)) => {
+                field(*name, actual.as_str())
+            }
+            (
+                HostFunctionPattern::PutKey(name, key),
+                HostFunction::CasperPutKey(actual_name, actual_key),
+            ) => field(*name, actual_name.as_str()) && field(key.as_ref(), actual_key),
+            _ => false,
+        }
+    }
+}
+
+impl From<HostFunction> for HostFunctionPattern<'_> {
+    fn from(host_function: HostFunction) -> Self {
+        HostFunctionPattern::Is(host_function)
+    }
+}
+
+impl fmt::Display for HostFunctionPattern<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn field<T: fmt::Debug>(value: Option<T>) -> String {
+            value.map_or_else(|| String::from("_"), |value| format!("{value:?}"))
+        }
+
+        match self {
+            HostFunctionPattern::Any => write!(f, "_"),
+            HostFunctionPattern::Is(host_function) => write!(f, "{host_function:?}"),
+            HostFunctionPattern::GetKey(name) => write!(f, "CasperGetKey({})", field(*name)),
+            HostFunctionPattern::HasKey(name) => write!(f, "CasperHasKey({})", field(*name)),
+            HostFunctionPattern::PutKey(name, key) => {
+                write!(f, "CasperPutKey({}, {})", field(*name), field(key.as_ref()))
+            }
+            HostFunctionPattern::RemoveKey(name) => {
+                write!(f, "CasperRemoveKey({})", field(*name))
+            }
+        }
+    }
+}
+
+/// The calls matched by the last `contains` or `in_order` of a [`TraceAssert`].
+#[derive(Debug, Clone)]
+struct Selection {
+    description: String,
+    indices: Vec<usize>,
+}
+
+/// Assertions on a trace, chained from [`Env::assert_trace`](crate::Env::assert_trace).
+///
+/// [`contains`](Self::contains) and [`in_order`](Self::in_order) select the calls they matched,
+/// which [`times`](Self::times) and [`before`](Self::before) then refine.
+#[derive(Debug, Clone)]
+pub struct TraceAssert {
+    trace: Vec<TraceRecord>,
+    selection: Option<Selection>,
+}
+
+impl TraceAssert {
+    pub fn new(trace: Vec<TraceRecord>) -> Self {
+        Self {
+            trace,
+            selection: None,
+        }
+    }
+
+    /// Returns the trace under assertion.
+    pub fn records(&self) -> &[TraceRecord] {
+        &self.trace
+    }
+
+    /// Asserts that some call matches `pattern`, selecting the calls that do.
+    #[track_caller]
+    pub fn contains<'p>(mut self, pattern: impl Into<HostFunctionPattern<'p>>) -> Self {
+        let pattern = pattern.into();
+        let indices = self.indices_of(&pattern, 0);
+        if indices.is_empty() {
+            self.fail(format_args!("no call matches {pattern}"), &[]);
+        }
+        self.selection = Some(Selection {
+            description: pattern.to_string(),
+            indices,
+        });
+        self
+    }
+
+    /// Asserts that exactly `count` calls were selected.
+    #[track_caller]
+    pub fn times(self, count: usize) -> Self {
+        let selection = self.selection("times");
+        if selection.indices.len() != count {
+            self.fail(
+                format_args!(
+                    "expected {count} calls matching {}, found {}",
+                    selection.description,
+                    selection.indices.len()
+                ),
+                &selection.indices,
+            );
+        }
+        self
+    }
+
+    /// Asserts that no call matches `pattern`.
+    #[track_caller]
+    pub fn never<'p>(mut self, pattern: impl Into<HostFunctionPattern<'p>>) -> Self {
+        let pattern = pattern.into();
+        let indices = self.indices_of(&pattern, 0);
+        if !indices.is_empty() {
+            self.fail(
+                format_args!(
+                    "expected no call matching {pattern}, found {}",
+                    indices.len()
+                ),
+                &indices,
+            );
+        }
+        self.selection = None;
+        self
+    }
+
+    /// Asserts that calls matching `patterns` were made in this order, not necessarily one right
+    /// after the other, selecting the first such calls.
+    #[track_caller]
+    pub fn in_order<'p, P>(mut self, patterns: impl IntoIterator<Item = P>) -> Self
+    where
+        P: Into<HostFunctionPattern<'p>>,
+    {
+        let patterns: Vec<HostFunctionPattern<'p>> = patterns.into_iter().map(Into::into).collect();
+        let mut indices = Vec::with_capacity(patterns.len());
+        for pattern in &patterns {
+            let start = indices.last().map_or(0, |index| index + 1);
+            match self.indices_of(pattern, start).first() {
+                Some(index) => indices.push(*index),
+                None if indices.is_empty() => {
+                    self.fail(format_args!("no call matches {pattern}"), &[])
+                }
+                None => self.fail(
+                    format_args!("no call matches {pattern} after call {}", start - 1),
+                    &indices,
+                ),
+            }
+        }
+        let description = patterns
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.selection = Some(Selection {
+            description: format!("[{description}]"),
+            indices,
+        });
+        self
+    }
+
+    /// Asserts that a call matching `pattern` follows every selected call.
+    #[track_caller]
+    pub fn before<'p>(self, pattern: impl Into<HostFunctionPattern<'p>>) -> Self {
+        let pattern = pattern.into();
+        let selection = self.selection("before");
+        let start = selection.indices.last().map_or(0, |index| index + 1);
+        if self.indices_of(&pattern, start).is_empty() {
+            self.fail(
+                format_args!(
+                    "expected a call matching {pattern} after the calls matching {}",
+                    selection.description
+                ),
+                &selection.indices,
+            );
+        }
+        self
+    }
+
+    fn indices_of(&self, pattern: &HostFunctionPattern<'_>, start: usize) -> Vec<usize> {
+        self.trace
+            .iter()
+            .enumerate()
+            .skip(start)
+            .filter(|(_index, record)| pattern.matches(&record.host_function))
+            .map(|(index, _record)| index)
+            .collect()
+    }
+
+    #[track_caller]
+    fn selection(&self, method: &str) -> Selection {
+        match &self.selection {
+            Some(selection) => selection.clone(),
+            None => panic!("`{method}` must follow `contains` or `in_order`"),
+        }
+    }
+
+    #[track_caller]
+    fn fail(&self, message: fmt::Arguments<'_>, highlighted: &[usize]) -> ! {
+        panic!("{message}\n{}", render(&self.trace, highlighted))
+    }
+}
+
+/// Renders `trace` one call per line, marking the calls at `highlighted` with `>`.
+fn render(trace: &[TraceRecord], highlighted: &[usize]) -> String {
+    if trace.is_empty() {
+        return String::from("trace: (empty)");
+    }
+    let width = (trace.len() - 1).to_string().len();
+    let mut rendered = String::from("trace:");
+    for (index, record) in trace.iter().enumerate() {
+        let marker = if highlighted.contains(&index) {
+            '>'
+        } else {
+            ' '
+        };
+        let indent = "  ".repeat(record.depth);
+        let _ = write!(
+            rendered,
+            "\n{marker} {index:>width$}: {indent}{:?}",
+            record.host_function
+        );
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: Key = Key::Hash([1; 32]);
+
+    fn trace() -> TraceAssert {
+        let record = |depth, host_function| TraceRecord {
+            depth,
+            host_function,
+            arguments: Vec::new(),
+        };
+        TraceAssert::new(vec![
+            record(0, HostFunction::CasperGetKey("counter".into())),
+            record(0, HostFunction::CasperPutKey("counter".into(), KEY)),
+            record(1, HostFunction::CasperWrite),
+            record(0, HostFunction::CasperGetKey("owner".into())),
+        ])
+    }
+
+    #[test]
+    fn patterns_ignore_wildcard_payloads() {
+        let put_key = HostFunction::CasperPutKey("counter".into(), KEY);
+        assert!(HostFunctionPattern::PutKey(None, None).matches(&put_key));
+        assert!(HostFunctionPattern::PutKey(Some("counter"), None).matches(&put_key));
+        assert!(HostFunctionPattern::PutKey(None, Some(KEY)).matches(&put_key));
+        assert!(!HostFunctionPattern::PutKey(Some("owner"), None).matches(&put_key));
+        assert!(!HostFunctionPattern::GetKey(None).matches(&put_key));
+        assert!(HostFunctionPattern::from(put_key.clone()).matches(&put_key));
+        assert!(HostFunctionPattern::Any.matches(&HostFunction::CasperRevert));
+        assert_eq!(
+            HostFunctionPattern::PutKey(Some("counter"), None).to_string(),
+            r#"CasperPutKey("counter", _)"#
+        );
+    }
+
+    #[test]
+    fn passing_assertions_chain() {
+        trace()
+            .contains(HostFunctionPattern::GetKey(None))
+            .times(2)
+            .contains(HostFunctionPattern::PutKey(Some("counter"), None))
+            .times(1)
+            .before(HostFunction::CasperWrite)
+            .never(HostFunctionPattern::RemoveKey(None))
+            .in_order([
+                HostFunctionPattern::GetKey(Some("counter")),
+                HostFunction::CasperWrite.into(),
+                HostFunctionPattern::GetKey(Some("owner")),
+            ]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "expected 1 calls matching CasperGetKey(_), found 2\ntrace:\n> 0: \
+                               CasperGetKey(\"counter\")\n  1: CasperPutKey(\"counter\""
+    )]
+    fn failures_highlight_the_matched_calls() {
+        trace().contains(HostFunctionPattern::GetKey(None)).times(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "\n  2:   CasperWrite\n> 3: CasperGetKey(\"owner\")")]
+    fn failures_render_the_whole_trace() {
+        trace().never(HostFunctionPattern::GetKey(Some("owner")));
+    }
+
+    #[test]
+    #[should_panic(expected = "no call matches CasperGetKey(\"counter\") after call 2")]
+    fn in_order_rejects_reordered_calls() {
+        trace().in_order([
+            HostFunction::CasperWrite.into(),
+            HostFunctionPattern::GetKey(Some("counter")),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a call matching CasperReadValue after the calls matching")]
+    fn before_requires_a_later_call() {
+        trace()
+            .contains(HostFunctionPattern::PutKey(None, None))
+            .before(HostFunction::CasperReadValue);
+    }
+}
@@ -5,15 +5,19 @@ use casper_binary_port::Command;
 use casper_binary_port::CommandHeader;
 use casper_binary_port::PayloadEntity;
 use casper_types::bytesrepr::{self, FromBytes, ToBytes};
+use std::collections::HashMap;
 use std::io;
 use std::sync::atomic::AtomicU16;
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
-    time::timeout,
+    sync::Semaphore,
+    time::{Instant, timeout},
 };
+use tracing::{Instrument, debug, debug_span, field, trace};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -31,17 +35,230 @@ pub enum Error {
 
 pub const LENGTH_FIELD_SIZE: usize = 4;
 const TIMEOUT_DURATION: Duration = Duration::from_secs(5);
+#[deprecated(note = "request ids are assigned by `BinaryPortClient`")]
 pub static COUNTER: AtomicU16 = AtomicU16::new(0);
 
+/// Default number of persistent connections kept by a [`BinaryPortClient`].
+pub const DEFAULT_POOL_SIZE: usize = 4;
+/// Default number of times a [`BinaryPortClient`] retries a request on a broken connection.
+pub const DEFAULT_RETRIES: usize = 2;
+
 /// Initializes the internal request id counter to the specified value.
 ///
 /// The request ids are ordinal; by default, starting at 0. This function sets
 /// the counter value to the provided id. The subsequent requests IDs will continue
-/// being ordinally numbered, starting from the provided value.
+/// being ordinally numbered, starting from the provided value and wrapping around to 0 after
+/// `u16::MAX`.
+#[deprecated(note = "use `BinaryPortClient::initialize_request_id` instead")]
+#[allow(deprecated)]
 pub fn initialize_request_id(id: u16) {
     COUNTER.store(id, Ordering::SeqCst);
 }
 
+/// A binary port client keeping a pool of persistent connections to a single node.
+///
+/// Each request checks out an idle connection (or opens a new one while fewer than the pool size
+/// are in use), writes the framed request, reads the framed response and returns the connection
+/// to the pool. A connection that fails with an IO error or times out is discarded and the
+/// request is retried on a fresh connection, up to the configured retry budget. Note that this
+/// means a request may be delivered to the node more than once.
+///
+/// Request ids are assigned by the client, ordinally starting at 0. The binary port header
+/// carries them as a `u16`, so after 65535 the ids wrap around to 0 and are reused. This is safe:
+/// a pooled connection carries a single request at a time and its response is read from the same
+/// connection, so the id check only has to tell the response apart from a stale or foreign one.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> Result<(), veles_casper_contract_api::binary_port::Error> {
+/// use veles_casper_contract_api::binary_port::BinaryPortClient;
+/// use veles_casper_contract_api::casper_binary_port::{Command, GetRequest, InformationRequest};
+///
+/// let client = BinaryPortClient::new("localhost:28101").with_pool_size(8);
+/// let request = GetRequest::try_from(InformationRequest::Uptime).unwrap();
+/// let response = client.send(Command::Get(request)).await?;
+/// # let _ = response;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BinaryPortClient {
+    node_address: String,
+    idle_connections: Mutex<Vec<TcpStream>>,
+    permits: Semaphore,
+    retries: usize,
+    next_request_id: AtomicU16,
+}
+
+impl BinaryPortClient {
+    /// Creates a client for the node at `node_address`, e.g. "localhost:28101".
+    ///
+    /// No connection is opened until the first request is sent.
+    pub fn new(node_address: impl Into<String>) -> Self {
+        Self {
+            node_address: node_address.into(),
+            idle_connections: Mutex::new(Vec::new()),
+            permits: Semaphore::new(DEFAULT_POOL_SIZE),
+            retries: DEFAULT_RETRIES,
+            next_request_id: AtomicU16::new(0),
+        }
+    }
+
+    /// Sets the maximum number of connections opened to the node.
+    ///
+    /// Requests sent while all connections are in use wait for one to be returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pool_size` is zero.
+    pub fn with_pool_size(mut self, pool_size: usize) -> Self {
+        assert!(pool_size > 0, "pool size must be greater than zero");
+        self.permits = Semaphore::new(pool_size);
+        self
+    }
+
+    /// Sets how many times a request is retried after its connection broke.
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Returns the address of the node this client connects to.
+    pub fn node_address(&self) -> &str {
+        &self.node_address
+    }
+
+    /// Initializes the request id counter of this client to the specified value.
+    ///
+    /// The subsequent request ids will continue being ordinally numbered, starting from the
+    /// provided value and wrapping around to 0 after `u16::MAX`.
+    pub fn initialize_request_id(&self, id: u16) {
+        self.next_request_id.store(id, Ordering::SeqCst);
+    }
+
+    /// Sends a command to the node and waits for the response.
+    ///
+    /// This assigns the next request id and fails with `Error::Response` if the node answers with
+    /// a different one.
+    pub async fn send(&self, command: Command) -> Result<BinaryResponseAndRequest, Error> {
+        let request_id = self.next_request_id();
+        let raw_bytes =
+            encode_request(&command, request_id).expect("should always serialize a request");
+        self.send_raw(raw_bytes, Some(request_id)).await
+    }
+
+    /// Returns the next request id, wrapping around to 0 after `u16::MAX`.
+    fn next_request_id(&self) -> u16 {
+        // `fetch_add` wraps on overflow, so concurrent senders still get consecutive ids.
+        self.next_request_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Sends an already encoded request to the node and waits for the response.
+    ///
+    /// When `request_id` is `None`, the response is expected to carry request id 0.
+    ///
+    /// The request runs in a `binary_port_request` span; its elapsed time and outcome are logged
+    /// at debug level.
+    pub async fn send_raw(
+        &self,
+        bytes: Vec<u8>,
+        request_id: Option<u16>,
+    ) -> Result<BinaryResponseAndRequest, Error> {
+        let request_id = request_id.unwrap_or_default();
+        let span = debug_span!(
+            "binary_port_request",
+            node_address = %self.node_address,
+            request_id,
+            payload_size = bytes.len(),
+            response_size = field::Empty,
+        );
+        async move {
+            let start = Instant::now();
+            let result = self.send_payload_with_retries(bytes, request_id).await;
+            match &result {
+                Ok(_) => debug!(elapsed = ?start.elapsed(), "request succeeded"),
+                Err(error) => debug!(elapsed = ?start.elapsed(), %error, "request failed"),
+            }
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn send_payload_with_retries(
+        &self,
+        bytes: Vec<u8>,
+        request_id: u16,
+    ) -> Result<BinaryResponseAndRequest, Error> {
+        let payload = BinaryMessage::new(bytes);
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("connection pool semaphore is never closed");
+
+        let mut retries_left = self.retries;
+        let response_buf = loop {
+            match self.exchange(&payload).await {
+                Ok(response_buf) => break response_buf,
+                Err(error @ (Error::Io(_) | Error::Timeout)) if retries_left > 0 => {
+                    debug!(%error, retries_left, "retrying on a fresh connection");
+                    retries_left -= 1;
+                }
+                Err(error) => return Err(error),
+            }
+        };
+        tracing::Span::current().record("response_size", response_buf.len());
+        process_response(response_buf, request_id).await
+    }
+
+    /// Sends the payload over a pooled connection and reads the response.
+    ///
+    /// The connection is returned to the pool only if the exchange succeeded, so that a broken
+    /// connection is never reused.
+    async fn exchange(&self, payload: &BinaryMessage) -> Result<Vec<u8>, Error> {
+        let idle_connection = self
+            .idle_connections
+            .lock()
+            .expect("connection pool lock poisoned")
+            .pop();
+        let mut connection = match idle_connection {
+            Some(connection) => {
+                trace!("reusing a pooled connection");
+                connection
+            }
+            None => {
+                trace!("opening a connection");
+                connect_to_node(&self.node_address).await?
+            }
+        };
+
+        send_payload(&mut connection, payload).await?;
+        let response_buf = read_response(&mut connection).await?;
+
+        self.idle_connections
+            .lock()
+            .expect("connection pool lock poisoned")
+            .push(connection);
+        Ok(response_buf)
+    }
+}
+
+/// Returns the lazily created client used by the free functions for `node_address`.
+fn default_client(node_address: &str) -> Arc<BinaryPortClient> {
+    static DEFAULT_CLIENTS: OnceLock<Mutex<HashMap<String, Arc<BinaryPortClient>>>> =
+        OnceLock::new();
+    let mut clients = DEFAULT_CLIENTS
+        .get_or_init(Default::default)
+        .lock()
+        .expect("default clients lock poisoned");
+    clients
+        .entry(node_address.to_string())
+        .or_insert_with(|| Arc::new(BinaryPortClient::new(node_address)))
+        .clone()
+}
+
 /// Establishes an asynchronous TCP connection to a specified node address.
 ///
 /// This function attempts to connect to a node using a TCP stream. It is only
@@ -99,7 +316,8 @@ async fn connect_to_node(node_address: &str) -> Result<TcpStream, std::io::Error
 ///
 /// This function may return an error if:
 /// - The write operations timeout, resulting in a `TimeoutError`.
-/// - There are issues with the TCP stream that prevent data from being sent.
+/// - There are issues with the TCP stream that prevent data from being sent, resulting in an
+///   `Error::Io`.
 ///
 /// # Notes
 ///
@@ -110,17 +328,17 @@ async fn connect_to_node(node_address: &str) -> Result<TcpStream, std::io::Error
 async fn send_payload(client: &mut TcpStream, message: &BinaryMessage) -> Result<(), Error> {
     let payload_length = message.payload().len() as u32;
     let length_bytes = payload_length.to_le_bytes();
-    let _ = timeout(TIMEOUT_DURATION, client.write_all(&length_bytes))
+    timeout(TIMEOUT_DURATION, client.write_all(&length_bytes))
         .await
-        .map_err(|_| Error::Timeout)?;
+        .map_err(|_| Error::Timeout)??;
 
-    let _ = timeout(TIMEOUT_DURATION, client.write_all(message.payload()))
+    timeout(TIMEOUT_DURATION, client.write_all(message.payload()))
         .await
-        .map_err(|_| Error::Timeout)?;
+        .map_err(|_| Error::Timeout)??;
 
-    let _ = timeout(TIMEOUT_DURATION, client.flush())
+    timeout(TIMEOUT_DURATION, client.flush())
         .await
-        .map_err(|_| Error::Timeout)?;
+        .map_err(|_| Error::Timeout)??;
     Ok(())
 }
 
@@ -156,15 +374,15 @@ async fn send_payload(client: &mut TcpStream, message: &BinaryMessage) -> Result
 /// indefinitely on slow or unresponsive clients.
 async fn read_response(client: &mut TcpStream) -> Result<Vec<u8>, Error> {
     let mut length_buf = [0u8; LENGTH_FIELD_SIZE];
-    let _ = timeout(TIMEOUT_DURATION, client.read_exact(&mut length_buf))
+    timeout(TIMEOUT_DURATION, client.read_exact(&mut length_buf))
         .await
-        .map_err(|_| Error::Timeout)?;
+        .map_err(|_| Error::Timeout)??;
 
     let response_length = u32::from_le_bytes(length_buf) as usize;
     let mut response_buf = vec![0u8; response_length];
-    let _ = timeout(TIMEOUT_DURATION, client.read_exact(&mut response_buf))
+    timeout(TIMEOUT_DURATION, client.read_exact(&mut response_buf))
         .await
-        .map_err(|_| Error::Timeout)?;
+        .map_err(|_| Error::Timeout)??;
     Ok(response_buf)
 }
 
@@ -202,32 +420,31 @@ async fn read_response(client: &mut TcpStream) -> Result<Vec<u8>, Error> {
 /// After sending the request, it waits for the response and processes it accordingly.
 /// This function is designed to be used in non-WebAssembly (Wasm) environments, typically
 /// on servers or local applications.
+///
+/// The request is sent over the pooled connections of a default [`BinaryPortClient`] created
+/// lazily for `node_address`. Prefer owning a [`BinaryPortClient`] instead.
 pub async fn send_request(
     node_address: &str,
     request: Command,
 ) -> Result<BinaryResponseAndRequest, Error> {
+    #[allow(deprecated)]
     let request_id = COUNTER.fetch_add(1, Ordering::SeqCst); // Atomically increment the counter
     let raw_bytes =
         encode_request(&request, request_id).expect("should always serialize a request");
     send_raw(node_address, raw_bytes, Some(request_id)).await
 }
 
+/// Sends an already encoded request to a node and waits for the response.
+///
+/// See [`BinaryPortClient::send_raw`]; this uses the same default client as [`send_request`].
 pub async fn send_raw(
     node_address: &str,
     bytes: Vec<u8>,
     request_id: Option<u16>,
 ) -> Result<BinaryResponseAndRequest, Error> {
-    let payload = BinaryMessage::new(bytes);
-
-    let mut client = connect_to_node(node_address).await?;
-
-    // Send the payload length and data
-    send_payload(&mut client, &payload).await?;
-
-    // Read and process the response
-    let response_buf = read_response(&mut client).await?;
-    // Now process the response using the request_id
-    process_response(response_buf, request_id.unwrap_or_default()).await
+    default_client(node_address)
+        .send_raw(bytes, request_id)
+        .await
 }
 
 /// Encodes a binary request into a byte vector for transmission.
@@ -308,11 +525,8 @@ pub fn parse_response<A: FromBytes + PayloadEntity>(
 ) -> Result<Option<A>, Error> {
     match response.returned_data_type_tag() {
         Some(found) if found == u8::from(A::RESPONSE_TYPE) => {
-            // Verbose: Print length of payload
             let payload = response.payload();
-            let _payload_length = payload.len();
-            // TODO[GR] use tracing::info! instead of dbg!
-            // dbg!(_payload_length);
+            trace!(payload_size = payload.len(), "parsing response payload");
 
             Ok(Some(bytesrepr::deserialize_from_slice(payload)?))
         }
@@ -353,35 +567,237 @@ pub async fn process_response(
     response_buf: Vec<u8>,
     request_id: u16,
 ) -> Result<BinaryResponseAndRequest, Error> {
-    const REQUEST_ID_START: usize = 7;
-    const REQUEST_ID_END: usize = REQUEST_ID_START + 2;
-
     // Deserialize the remaining response data
     let response: BinaryResponseAndRequest = bytesrepr::deserialize_from_slice(response_buf)?;
-    let request = response.request();
+    let response_request_id = echoed_request_id(response.request())?;
 
-    // Check if the request buffer is at least the size of the request ID
-    if request.len() < REQUEST_ID_END {
+    // Ids are compared in the same wrapping `u16` space they are assigned in, so a reused id
+    // matches as long as the node answers the request sent over this connection.
+    if request_id != response_request_id {
         return Err(Error::Response(format!(
+            "Request ID mismatch: expected {request_id}, got {response_request_id}"
+        )));
+    }
+    Ok(response)
+}
+
+/// Extracts the request id of the request echoed back in a response.
+///
+/// The echoed request starts with its length prefix, followed by the `CommandHeader`.
+fn echoed_request_id(request: &[u8]) -> Result<u16, Error> {
+    let header = request.get(LENGTH_FIELD_SIZE..).unwrap_or_default();
+    let (header, _) = CommandHeader::from_bytes(header).map_err(|_| {
+        Error::Response(format!(
             "Response buffer is too small: expected at least {} bytes, got {}. Buffer contents: {:?}",
-            REQUEST_ID_END,
+            // The header version, type tag and request id.
+            LENGTH_FIELD_SIZE + 2 + 1 + 2,
             request.len(),
             request
-        )));
+        ))
+    })?;
+    Ok(header.id())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use casper_binary_port::{GetRequest, InformationRequest};
+    use casper_types::bytesrepr::Bytes;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// A local node stand-in that echoes every framed request back in a `BinaryResponseAndRequest`.
+    ///
+    /// Connections are closed after `requests_per_connection` requests.
+    struct EchoServer {
+        address: String,
+        accepted: Arc<AtomicUsize>,
     }
 
-    // Extract Request ID from the request
-    let response_request_id = u16::from_le_bytes(
-        request[REQUEST_ID_START..REQUEST_ID_END]
-            .try_into()
-            .expect("Failed to extract Request ID"),
-    );
+    impl EchoServer {
+        async fn start(requests_per_connection: usize) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let accepted = Arc::new(AtomicUsize::new(0));
+            let accepted_counter = Arc::clone(&accepted);
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    accepted_counter.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(async move {
+                        for _ in 0..requests_per_connection {
+                            let Ok(request) = read_response(&mut stream).await else {
+                                return;
+                            };
+                            // The node echoes the request including its length prefix.
+                            let mut echoed = (request.len() as u32).to_le_bytes().to_vec();
+                            echoed.extend_from_slice(&request);
+                            let response = BinaryResponseAndRequest::new(
+                                BinaryResponse::new_empty(),
+                                Bytes::from(echoed),
+                            );
+                            let message = BinaryMessage::new(response.to_bytes().unwrap());
+                            if send_payload(&mut stream, &message).await.is_err() {
+                                return;
+                            }
+                        }
+                    });
+                }
+            });
+            Self { address, accepted }
+        }
 
-    // Check if request_id matches response_request_id and return an error if not
-    if request_id != response_request_id {
-        return Err(Error::Response(format!(
-            "Request ID mismatch: expected {request_id}, got {response_request_id}"
-        )));
+        fn accepted(&self) -> usize {
+            self.accepted.load(Ordering::SeqCst)
+        }
+    }
+
+    fn uptime_command() -> Command {
+        Command::Get(GetRequest::try_from(InformationRequest::Uptime).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_connection_is_reused() {
+        let server = EchoServer::start(usize::MAX).await;
+        let client = BinaryPortClient::new(&server.address);
+        for _ in 0..10 {
+            client.send(uptime_command()).await.unwrap();
+        }
+        assert_eq!(server.accepted(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_pool_size_limits_connections() {
+        let server = EchoServer::start(usize::MAX).await;
+        let client = Arc::new(BinaryPortClient::new(&server.address).with_pool_size(2));
+        let requests: Vec<_> = (0..16)
+            .map(|_| {
+                let client = Arc::clone(&client);
+                tokio::spawn(async move { client.send(uptime_command()).await })
+            })
+            .collect();
+        for request in requests {
+            request.await.unwrap().unwrap();
+        }
+        assert!(server.accepted() <= 2, "accepted {}", server.accepted());
+    }
+
+    #[tokio::test]
+    async fn test_broken_connection_is_reestablished() {
+        let server = EchoServer::start(1).await;
+        let client = BinaryPortClient::new(&server.address);
+        for _ in 0..3 {
+            client.send(uptime_command()).await.unwrap();
+        }
+        assert_eq!(server.accepted(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_exhausted() {
+        let server = EchoServer::start(0).await;
+        let client = BinaryPortClient::new(&server.address).with_retries(1);
+        let error = client.send(uptime_command()).await.unwrap_err();
+        assert!(matches!(error, Error::Io(_)), "{error:?}");
+        assert_eq!(server.accepted(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_ids_are_assigned_by_client() {
+        let server = EchoServer::start(usize::MAX).await;
+        let client = BinaryPortClient::new(&server.address);
+        client.initialize_request_id(41);
+        // `send` rejects responses with a mismatched request id.
+        client.send(uptime_command()).await.unwrap();
+        client.send(uptime_command()).await.unwrap();
+
+        let error = client
+            .send_raw(encode_request(&uptime_command(), 7).unwrap(), Some(8))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Response(_)), "{error:?}");
+    }
+
+    #[tokio::test]
+    async fn test_request_ids_wrap_around() {
+        let server = EchoServer::start(usize::MAX).await;
+        let client = BinaryPortClient::new(&server.address);
+        client.initialize_request_id(u16::MAX - 1);
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            let response = client.send(uptime_command()).await.unwrap();
+            ids.push(echoed_request_id(response.request()).unwrap());
+        }
+        assert_eq!(ids, [u16::MAX - 1, u16::MAX, 0, 1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_requests_correlate_across_the_wrap() {
+        let server = EchoServer::start(usize::MAX).await;
+        let client = Arc::new(BinaryPortClient::new(&server.address).with_pool_size(4));
+        client.initialize_request_id(u16::MAX - 31);
+        let requests: Vec<_> = (0..4)
+            .map(|_| {
+                let client = Arc::clone(&client);
+                tokio::spawn(async move {
+                    let mut ids = Vec::new();
+                    for _ in 0..16 {
+                        let response = client.send(uptime_command()).await?;
+                        ids.push(echoed_request_id(response.request())?);
+                    }
+                    Ok::<_, Error>(ids)
+                })
+            })
+            .collect();
+        let mut ids = Vec::new();
+        for request in requests {
+            ids.extend(request.await.unwrap().unwrap());
+        }
+        ids.sort_unstable();
+        let expected: Vec<u16> = (0..32).chain(u16::MAX - 31..=u16::MAX).collect();
+        assert_eq!(ids, expected);
+    }
+
+    /// Collects the output of a `tracing_subscriber::fmt` subscriber.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_traced() {
+        let server = EchoServer::start(usize::MAX).await;
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let client = BinaryPortClient::new(&server.address);
+        client.initialize_request_id(5);
+        client.send(uptime_command()).await.unwrap();
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("binary_port_request{"), "{logs}");
+        assert!(
+            logs.contains(&format!("node_address={}", server.address)),
+            "{logs}"
+        );
+        assert!(logs.contains("request_id=5"), "{logs}");
+        assert!(logs.contains("response_size="), "{logs}");
+        assert!(logs.contains("request succeeded"), "{logs}");
     }
-    Ok(response)
 }
@@ -0,0 +1,452 @@
+//! Fixed-capacity string and byte arguments.
+//!
+//! [`BoundedString<N>`] and [`BoundedBytes<N>`] hold at most `N` bytes inline, so contracts that
+//! only accept short identifiers (symbols, names) can take them as entry point arguments without
+//! the allocations of `String` and `Vec<u8>`. They serialize exactly like those types, as a
+//! `CLType::String` and a `CLType::List(U8)`, and deserializing a longer value fails with
+//! `bytesrepr::Error::Formatting` rather than truncating it:
+//!
+//! ```ignore
+//! #[casper(export)]
+//! pub fn register(symbol: BoundedString<8>) {
+//!     SYMBOLS.insert(&*symbol, true).unwrap_or_revert();
+//! }
+//! ```
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::{fmt, ops::Deref};
+
+use crate::{
+    casper_types::{
+        CLType, CLTyped,
+        bytesrepr::{self, FromBytes, ToBytes, U32_SERIALIZED_LENGTH},
+    },
+    collections::{
+        base128,
+        dictionary_key::{DictionaryKey, write_str_part},
+    },
+};
+
+/// Error returned when a value doesn't fit in a bounded type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError {
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "length {} exceeds the capacity of {}",
+            self.len, self.capacity
+        )
+    }
+}
+
+/// Inline storage of up to `N` bytes, zeroed past `len` so derived comparisons follow the bytes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Buffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Buffer<N> {
+    const EMPTY: Self = Self {
+        bytes: [0; N],
+        len: 0,
+    };
+
+    fn new(value: &[u8]) -> Result<Self, CapacityError> {
+        if value.len() > N {
+            return Err(CapacityError {
+                len: value.len(),
+                capacity: N,
+            });
+        }
+        let mut buffer = Self::EMPTY;
+        buffer.bytes[..value.len()].copy_from_slice(value);
+        buffer.len = value.len();
+        Ok(buffer)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    fn write_bytes(&self, writer: &mut Vec<u8>) -> Result<(), bytesrepr::Error> {
+        // `N` fits the u32 length prefix of any value the engine accepts
+        let len = u32::try_from(self.len).map_err(|_| bytesrepr::Error::NotRepresentable)?;
+        len.write_bytes(writer)?;
+        writer.extend_from_slice(self.as_slice());
+        Ok(())
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut bytes = Vec::with_capacity(U32_SERIALIZED_LENGTH + self.len);
+        self.write_bytes(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Splits a length-prefixed value of at most `N` bytes off `bytes`.
+    fn split(bytes: &[u8]) -> Result<(&[u8], &[u8]), bytesrepr::Error> {
+        let (len, remainder) = u32::from_bytes(bytes)?;
+        let len = usize::try_from(len).map_err(|_| bytesrepr::Error::Formatting)?;
+        if len > N {
+            return Err(bytesrepr::Error::Formatting);
+        }
+        if remainder.len() < len {
+            return Err(bytesrepr::Error::EarlyEndOfStream);
+        }
+        Ok(remainder.split_at(len))
+    }
+}
+
+/// A UTF-8 string of at most `N` bytes, serialized as a `String`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoundedString<const N: usize>(Buffer<N>);
+
+impl<const N: usize> BoundedString<N> {
+    /// The empty string.
+    pub const EMPTY: Self = Self(Buffer::EMPTY);
+
+    /// Copies `value`, failing if it is longer than `N` bytes.
+    pub fn new(value: &str) -> Result<Self, CapacityError> {
+        Buffer::new(value.as_bytes()).map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the buffer is only ever filled from a `str` or from validated UTF-8
+        unsafe { core::str::from_utf8_unchecked(self.0.as_slice()) }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Default for BoundedString<N> {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl<const N: usize> Deref for BoundedString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for BoundedString<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for BoundedString<N> {
+    type Error = CapacityError;
+
+    fn try_from(value: &str) -> Result<Self, CapacityError> {
+        Self::new(value)
+    }
+}
+
+impl<const N: usize> fmt::Debug for BoundedString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Display for BoundedString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> CLTyped for BoundedString<N> {
+    fn cl_type() -> CLType {
+        CLType::String
+    }
+}
+
+impl<const N: usize> ToBytes for BoundedString<N> {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        self.0.serialize()
+    }
+
+    fn serialized_length(&self) -> usize {
+        U32_SERIALIZED_LENGTH + self.0.len
+    }
+
+    fn write_bytes(&self, writer: &mut Vec<u8>) -> Result<(), bytesrepr::Error> {
+        self.0.write_bytes(writer)
+    }
+}
+
+impl<const N: usize> FromBytes for BoundedString<N> {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (value, remainder) = Buffer::<N>::split(bytes)?;
+        let value = core::str::from_utf8(value).map_err(|_| bytesrepr::Error::Formatting)?;
+        // `split` checked the length
+        let value = Self::new(value).map_err(|_| bytesrepr::Error::Formatting)?;
+        Ok((value, remainder))
+    }
+}
+
+impl<'a, const N: usize> DictionaryKey<'a> for BoundedString<N> {
+    fn write_key(&self, out: &mut String) {
+        out.push_str(self.as_str());
+    }
+
+    fn write_part(&self, out: &mut String) {
+        write_str_part(self.as_str(), out);
+    }
+
+    fn dictionary_key(&'a self) -> Cow<'a, str> {
+        Cow::Borrowed(self.as_str())
+    }
+}
+
+/// Bytes of at most `N` bytes, serialized as a `Vec<u8>`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoundedBytes<const N: usize>(Buffer<N>);
+
+impl<const N: usize> BoundedBytes<N> {
+    /// No bytes.
+    pub const EMPTY: Self = Self(Buffer::EMPTY);
+
+    /// Copies `value`, failing if it is longer than `N` bytes.
+    pub fn new(value: &[u8]) -> Result<Self, CapacityError> {
+        Buffer::new(value).map(Self)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Default for BoundedBytes<N> {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl<const N: usize> Deref for BoundedBytes<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for BoundedBytes<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for BoundedBytes<N> {
+    type Error = CapacityError;
+
+    fn try_from(value: &[u8]) -> Result<Self, CapacityError> {
+        Self::new(value)
+    }
+}
+
+impl<const N: usize> fmt::Debug for BoundedBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+impl<const N: usize> CLTyped for BoundedBytes<N> {
+    fn cl_type() -> CLType {
+        CLType::List(alloc::boxed::Box::new(CLType::U8))
+    }
+}
+
+impl<const N: usize> ToBytes for BoundedBytes<N> {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        self.0.serialize()
+    }
+
+    fn serialized_length(&self) -> usize {
+        U32_SERIALIZED_LENGTH + self.0.len
+    }
+
+    fn write_bytes(&self, writer: &mut Vec<u8>) -> Result<(), bytesrepr::Error> {
+        self.0.write_bytes(writer)
+    }
+}
+
+impl<const N: usize> FromBytes for BoundedBytes<N> {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (value, remainder) = Buffer::<N>::split(bytes)?;
+        let value = Self::new(value).map_err(|_| bytesrepr::Error::Formatting)?;
+        Ok((value, remainder))
+    }
+}
+
+impl<const N: usize> DictionaryKey<'_> for BoundedBytes<N> {
+    fn write_key(&self, out: &mut String) {
+        base128::encode_bytes_into(self.as_slice(), out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use casper_types::bytesrepr::Bytes;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn boundary_lengths() {
+        let full = "a".repeat(32);
+        assert_eq!(BoundedString::<32>::new(&full).unwrap().as_str(), full);
+        assert_eq!(
+            BoundedString::<32>::new(&"a".repeat(33)),
+            Err(CapacityError {
+                len: 33,
+                capacity: 32
+            })
+        );
+        assert_eq!(BoundedString::<32>::new("").unwrap(), BoundedString::EMPTY);
+        assert_eq!(BoundedString::<0>::new(""), Ok(BoundedString::EMPTY));
+        assert!(BoundedString::<0>::new("a").is_err());
+        // The capacity counts bytes, not characters
+        assert!(BoundedString::<3>::new("żż").is_err());
+
+        assert_eq!(
+            &*BoundedBytes::<4>::new(&[1, 2, 3, 4]).unwrap(),
+            [1, 2, 3, 4]
+        );
+        assert!(BoundedBytes::<4>::new(&[0; 5]).is_err());
+        assert_eq!(
+            CapacityError {
+                len: 5,
+                capacity: 4
+            }
+            .to_string(),
+            "length 5 exceeds the capacity of 4"
+        );
+    }
+
+    #[test]
+    fn serializes_like_owned_types() {
+        let symbol = BoundedString::<8>::new("CSPR").unwrap();
+        assert_eq!(BoundedString::<8>::cl_type(), String::cl_type());
+        assert_eq!(symbol.to_bytes().unwrap(), "CSPR".to_bytes().unwrap());
+        assert_eq!(symbol.serialized_length(), "CSPR".serialized_length());
+        assert_eq!(
+            bytesrepr::deserialize::<BoundedString<8>>(String::from("CSPR").to_bytes().unwrap()),
+            Ok(symbol)
+        );
+
+        let bytes = BoundedBytes::<4>::new(&[1, 2]).unwrap();
+        assert_eq!(BoundedBytes::<4>::cl_type(), Bytes::cl_type());
+        assert_eq!(
+            bytes.to_bytes().unwrap(),
+            Bytes::from(vec![1u8, 2]).to_bytes().unwrap()
+        );
+        assert_eq!(
+            bytesrepr::deserialize::<BoundedBytes<4>>(
+                Bytes::from(vec![1u8, 2]).to_bytes().unwrap()
+            ),
+            Ok(bytes)
+        );
+    }
+
+    #[test]
+    fn deserializing_rejects_values_over_capacity() {
+        let long = String::from("CSPR-X").to_bytes().unwrap();
+        assert_eq!(
+            bytesrepr::deserialize::<BoundedString<4>>(long.clone()),
+            Err(bytesrepr::Error::Formatting)
+        );
+        assert_eq!(
+            bytesrepr::deserialize::<BoundedBytes<4>>(long.clone()),
+            Err(bytesrepr::Error::Formatting)
+        );
+        assert!(bytesrepr::deserialize::<BoundedString<6>>(long.clone()).is_ok());
+        assert_eq!(
+            bytesrepr::deserialize::<BoundedString<6>>(long[..long.len() - 1].to_vec()),
+            Err(bytesrepr::Error::EarlyEndOfStream)
+        );
+
+        let invalid_utf8 = Bytes::from(vec![0xffu8, 0xfe]).to_bytes().unwrap();
+        assert_eq!(
+            bytesrepr::deserialize::<BoundedString<4>>(invalid_utf8.clone()),
+            Err(bytesrepr::Error::Formatting)
+        );
+        assert!(bytesrepr::deserialize::<BoundedBytes<4>>(invalid_utf8).is_ok());
+    }
+
+    #[test]
+    fn dictionary_keys_match_the_unbounded_keys() {
+        let name = BoundedString::<16>::new("alice").unwrap();
+        assert_eq!(name.dictionary_key(), "alice".dictionary_key());
+        assert!(matches!(name.dictionary_key(), Cow::Borrowed("alice")));
+
+        let hash = BoundedBytes::<32>::new(&[7; 32]).unwrap();
+        assert_eq!(hash.dictionary_key(), [7u8; 32].dictionary_key());
+    }
+
+    #[test]
+    fn ordering_follows_the_contents() {
+        let key = |value: &str| BoundedString::<8>::new(value).unwrap();
+        assert!(key("a") < key("a\0"));
+        assert!(key("a") < key("ab"));
+        assert!(key("b") > key("ab"));
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_from_bytes_never_panics(bytes in proptest::collection::vec(any::<u8>(), 0..48)) {
+            if let Ok((value, remainder)) = BoundedString::<16>::from_bytes(&bytes) {
+                prop_assert!(value.len() <= 16);
+                prop_assert_eq!(value.serialized_length() + remainder.len(), bytes.len());
+            }
+            if let Ok((value, remainder)) = BoundedBytes::<16>::from_bytes(&bytes) {
+                prop_assert!(value.len() <= 16);
+                prop_assert_eq!(value.serialized_length() + remainder.len(), bytes.len());
+            }
+        }
+
+        #[test]
+        fn proptest_strings_round_trip(value in ".{0,24}") {
+            let bytes = value.to_bytes().unwrap();
+            match BoundedString::<16>::new(&value) {
+                Ok(bounded) => {
+                    prop_assert_eq!(bounded.to_bytes().unwrap(), bytes.clone());
+                    prop_assert_eq!(bytesrepr::deserialize::<BoundedString<16>>(bytes), Ok(bounded));
+                }
+                Err(_) => {
+                    prop_assert!(value.len() > 16);
+                    prop_assert_eq!(
+                        bytesrepr::deserialize::<BoundedString<16>>(bytes),
+                        Err(bytesrepr::Error::Formatting)
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn proptest_bytes_round_trip(value in proptest::collection::vec(any::<u8>(), 0..24)) {
+            let bytes = Bytes::from(value.clone()).to_bytes().unwrap();
+            let decoded = bytesrepr::deserialize::<BoundedBytes<16>>(bytes);
+            if value.len() <= 16 {
+                prop_assert_eq!(decoded.map(|decoded| decoded.to_vec()), Ok(value));
+            } else {
+                prop_assert_eq!(decoded, Err(bytesrepr::Error::Formatting));
+            }
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! Human readable rendering of `CLValue`s and stored values for debugging.
+//!
+//! [`render`] walks the `CLType` of a value and decodes its bytes accordingly, so nested options,
+//! lists, maps and tuples render like their Rust counterparts. Byte arrays render as hex and the
+//! big integers as decimals. Values that can't be decoded, including those of type `Any`, render
+//! as `<opaque N bytes>`.
+//!
+//! ```
+//! use veles_casper_contract_api::{casper_types::CLValue, clvalue_debug};
+//!
+//! let value = CLValue::from_t((Some(7u8), vec!["a".to_string()])).unwrap();
+//! assert_eq!(clvalue_debug::render(&value), r#"(Some(7), ["a"])"#);
+//! ```
+use std::{fmt::Write as _, iter};
+
+use casper_types::{
+    CLType, CLValue, Key, PublicKey, StoredValue, U128, U256, U512, URef,
+    bytesrepr::{self, FromBytes, OPTION_NONE_TAG, OPTION_SOME_TAG, RESULT_ERR_TAG, RESULT_OK_TAG},
+};
+
+/// Renders `cl_value` by decoding its bytes according to its `CLType`.
+pub fn render(cl_value: &CLValue) -> String {
+    let bytes = cl_value.inner_bytes();
+    match render_bytes(cl_value.cl_type(), bytes) {
+        Ok((rendered, [])) => rendered,
+        _ => opaque(bytes),
+    }
+}
+
+/// Renders `stored_value`, summarizing accounts, contracts and packages.
+///
+/// Other kinds of stored values only render their type name.
+pub fn render_stored_value(stored_value: &StoredValue) -> String {
+    match stored_value {
+        StoredValue::CLValue(cl_value) => render(cl_value),
+        StoredValue::Account(account) => format!(
+            "Account {} {{ main_purse: {}, named_keys: {} }}",
+            account.account_hash().to_formatted_string(),
+            account.main_purse().to_formatted_string(),
+            account.named_keys().len()
+        ),
+        StoredValue::Contract(contract) => format!(
+            "Contract {{ package: {}, wasm: {}, entry_points: {}, named_keys: {} }}",
+            contract.contract_package_hash().to_formatted_string(),
+            contract.contract_wasm_hash().to_formatted_string(),
+            contract.entry_points().len(),
+            contract.named_keys().len()
+        ),
+        StoredValue::ContractPackage(package) => format!(
+            "ContractPackage {{ access_key: {}, versions: {}, disabled_versions: {} }}",
+            package.access_key().to_formatted_string(),
+            package.versions().len(),
+            package.disabled_versions().len()
+        ),
+        StoredValue::ContractWasm(contract_wasm) => {
+            format!(
+                "ContractWasm <{} bytes>",
+                contract_wasm.clone().take_bytes().len()
+            )
+        }
+        other => format!("<{}>", other.type_name()),
+    }
+}
+
+fn opaque(bytes: &[u8]) -> String {
+    format!("<opaque {} bytes>", bytes.len())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Renders a value of a type implementing `Display`, returning the remaining bytes.
+fn display<T: FromBytes + ToString>(bytes: &[u8]) -> Result<(String, &[u8]), bytesrepr::Error> {
+    let (value, rest) = T::from_bytes(bytes)?;
+    Ok((value.to_string(), rest))
+}
+
+/// Renders consecutive values of `cl_types` joined by commas, returning the remaining bytes.
+fn render_all<'a, 'b>(
+    cl_types: impl IntoIterator<Item = &'b CLType>,
+    mut bytes: &'a [u8],
+) -> Result<(String, &'a [u8]), bytesrepr::Error> {
+    let mut items = Vec::new();
+    for cl_type in cl_types {
+        let (item, rest) = render_bytes(cl_type, bytes)?;
+        items.push(item);
+        bytes = rest;
+    }
+    Ok((items.join(", "), bytes))
+}
+
+/// Renders the value of type `cl_type` at the start of `bytes`, returning the remaining bytes.
+fn render_bytes<'a>(
+    cl_type: &CLType,
+    bytes: &'a [u8],
+) -> Result<(String, &'a [u8]), bytesrepr::Error> {
+    match cl_type {
+        CLType::Bool => display::<bool>(bytes),
+        CLType::I32 => display::<i32>(bytes),
+        CLType::I64 => display::<i64>(bytes),
+        CLType::U8 => display::<u8>(bytes),
+        CLType::U32 => display::<u32>(bytes),
+        CLType::U64 => display::<u64>(bytes),
+        CLType::U128 => display::<U128>(bytes),
+        CLType::U256 => display::<U256>(bytes),
+        CLType::U512 => display::<U512>(bytes),
+        CLType::Unit => Ok(("()".to_string(), bytes)),
+        CLType::String => {
+            let (value, rest) = String::from_bytes(bytes)?;
+            Ok((format!("{value:?}"), rest))
+        }
+        CLType::Key => {
+            let (key, rest) = Key::from_bytes(bytes)?;
+            Ok((key.to_formatted_string(), rest))
+        }
+        CLType::URef => {
+            let (uref, rest) = URef::from_bytes(bytes)?;
+            Ok((uref.to_formatted_string(), rest))
+        }
+        CLType::PublicKey => {
+            let (public_key, rest) = PublicKey::from_bytes(bytes)?;
+            Ok((public_key.to_hex_string(), rest))
+        }
+        CLType::Option(inner) => {
+            let (tag, rest) = u8::from_bytes(bytes)?;
+            match tag {
+                OPTION_NONE_TAG => Ok(("None".to_string(), rest)),
+                OPTION_SOME_TAG => {
+                    let (value, rest) = render_bytes(inner, rest)?;
+                    Ok((format!("Some({value})"), rest))
+                }
+                _ => Err(bytesrepr::Error::Formatting),
+            }
+        }
+        CLType::List(inner) => {
+            let (count, rest) = u32::from_bytes(bytes)?;
+            let count = usize::try_from(count).map_err(|_| bytesrepr::Error::Formatting)?;
+            let (items, rest) = render_all(iter::repeat_n(inner.as_ref(), count), rest)?;
+            Ok((format!("[{items}]"), rest))
+        }
+        CLType::ByteArray(length) => {
+            let length = usize::try_from(*length).map_err(|_| bytesrepr::Error::Formatting)?;
+            if bytes.len() < length {
+                return Err(bytesrepr::Error::EarlyEndOfStream);
+            }
+            let (array, rest) = bytes.split_at(length);
+            Ok((format!("0x{}", hex(array)), rest))
+        }
+        CLType::Result { ok, err } => {
+            let (tag, rest) = u8::from_bytes(bytes)?;
+            let (variant, inner) = match tag {
+                RESULT_OK_TAG => ("Ok", ok),
+                RESULT_ERR_TAG => ("Err", err),
+                _ => return Err(bytesrepr::Error::Formatting),
+            };
+            let (value, rest) = render_bytes(inner, rest)?;
+            Ok((format!("{variant}({value})"), rest))
+        }
+        CLType::Map { key, value } => {
+            let (count, mut rest) = u32::from_bytes(bytes)?;
+            let mut entries = Vec::new();
+            for _ in 0..count {
+                let (rendered_key, after_key) = render_bytes(key, rest)?;
+                let (rendered_value, after_value) = render_bytes(value, after_key)?;
+                entries.push(format!("{rendered_key}: {rendered_value}"));
+                rest = after_value;
+            }
+            Ok((format!("{{{}}}", entries.join(", ")), rest))
+        }
+        CLType::Tuple1([first]) => {
+            let (first, rest) = render_bytes(first, bytes)?;
+            Ok((format!("({first},)"), rest))
+        }
+        CLType::Tuple2(types) => {
+            let (items, rest) = render_all(types.iter().map(Box::as_ref), bytes)?;
+            Ok((format!("({items})"), rest))
+        }
+        CLType::Tuple3(types) => {
+            let (items, rest) = render_all(types.iter().map(Box::as_ref), bytes)?;
+            Ok((format!("({items})"), rest))
+        }
+        CLType::Any => Ok((opaque(bytes), &[])),
+    }
+}
+
+/// Renders every key of the shim database and every dictionary item, one per line and sorted.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait DumpStorage {
+    /// Returns the listing, meant for the output of failing tests.
+    fn dump_storage(&self) -> String;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DumpStorage for veles_casper_ffi_shim::Env {
+    fn dump_storage(&self) -> String {
+        let mut lines: Vec<String> = self
+            .storage()
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{} = {}",
+                    key.to_formatted_string(),
+                    render_stored_value(value)
+                )
+            })
+            .collect();
+        for (address, items) in self.dictionaries() {
+            let seed = hex(&address);
+            lines.extend(items.iter().map(|(item_key, value)| {
+                format!("dictionary-{seed}[{item_key:?}] = {}", render(value))
+            }));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use casper_types::{
+        AccessRights, CLTyped, ContractWasmHash, NamedKeys, SecretKey,
+        account::{Account, AccountHash},
+        bytesrepr::{Bytes, ToBytes},
+        contracts::{Contract, ContractPackage, ContractPackageHash, EntryPoints},
+    };
+    use veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+
+    use super::*;
+    use crate::casper_contract::contract_api::storage;
+
+    fn render_t<T: CLTyped + ToBytes>(value: T) -> String {
+        render(&CLValue::from_t(value).unwrap())
+    }
+
+    #[test]
+    fn renders_primitives() {
+        assert_eq!(render_t(true), "true");
+        assert_eq!(render_t(-5i32), "-5");
+        assert_eq!(render_t(i64::MIN), "-9223372036854775808");
+        assert_eq!(render_t(255u8), "255");
+        assert_eq!(render_t(7u32), "7");
+        assert_eq!(render_t(u64::MAX), "18446744073709551615");
+        assert_eq!(
+            render_t(U128::from(10u64).pow(20.into())),
+            "100000000000000000000"
+        );
+        assert_eq!(render_t(U256::MAX), U256::MAX.to_string());
+        assert_eq!(
+            render_t(U512::from(10u64).pow(40.into())),
+            format!("1{}", "0".repeat(40))
+        );
+        assert_eq!(render_t(()), "()");
+        assert_eq!(render_t("hi \"there\"".to_string()), r#""hi \"there\"""#);
+    }
+
+    #[test]
+    fn renders_keys() {
+        let uref = URef::new([1; 32], AccessRights::READ);
+        assert_eq!(render_t(uref), uref.to_formatted_string());
+        let key = Key::Account(AccountHash::new([2; 32]));
+        assert_eq!(render_t(key), key.to_formatted_string());
+        let secret_key = SecretKey::ed25519_from_bytes([3; 32]).unwrap();
+        let public_key = PublicKey::from(&secret_key);
+        assert_eq!(render_t(public_key.clone()), public_key.to_hex_string());
+    }
+
+    #[test]
+    fn renders_containers() {
+        assert_eq!(render_t(Some(1u8)), "Some(1)");
+        assert_eq!(render_t(Option::<u8>::None), "None");
+        assert_eq!(render_t(Vec::<u32>::new()), "[]");
+        assert_eq!(render_t(vec![1u64, 2, 3]), "[1, 2, 3]");
+        assert_eq!(render_t([0xabu8, 0x01, 0xff]), "0xab01ff");
+        assert_eq!(render_t(Result::<u8, String>::Ok(4)), "Ok(4)");
+        assert_eq!(
+            render_t(Result::<u8, String>::Err("no".into())),
+            r#"Err("no")"#
+        );
+        let map = BTreeMap::from([("a".to_string(), 1u8), ("b".to_string(), 2u8)]);
+        assert_eq!(render_t(map), r#"{"a": 1, "b": 2}"#);
+        assert_eq!(render_t((5u8,)), "(5,)");
+        assert_eq!(render_t((5u8, true)), "(5, true)");
+        assert_eq!(render_t((5u8, true, ())), "(5, true, ())");
+    }
+
+    type Entry = (Result<[u8; 2], u32>, Vec<Option<String>>);
+
+    #[test]
+    fn renders_deeply_nested_values() {
+        let value: Vec<Option<BTreeMap<u8, Entry>>> = vec![
+            None,
+            Some(BTreeMap::from([(
+                1,
+                (Ok([0xde, 0xad]), vec![Some("x".to_string()), None]),
+            )])),
+            Some(BTreeMap::from([(2, (Err(9), vec![]))])),
+        ];
+        assert_eq!(
+            render_t(value),
+            r#"[None, Some({1: (Ok(0xdead), [Some("x"), None])}), Some({2: (Err(9), [])})]"#
+        );
+        assert_eq!(render_t(Some(Some(Some(0u8)))), "Some(Some(Some(0)))");
+    }
+
+    #[test]
+    fn falls_back_to_opaque() {
+        let any = CLValue::from_components(CLType::Any, vec![1, 2, 3]);
+        assert_eq!(render(&any), "<opaque 3 bytes>");
+        // Bytes that don't match the type, and trailing bytes, can't be rendered either.
+        let truncated = CLValue::from_components(CLType::U64, vec![1, 2]);
+        assert_eq!(render(&truncated), "<opaque 2 bytes>");
+        let trailing = CLValue::from_components(CLType::U8, vec![1, 2]);
+        assert_eq!(render(&trailing), "<opaque 2 bytes>");
+        let bad_tag = CLValue::from_components(CLType::Option(Box::new(CLType::U8)), vec![7, 1]);
+        assert_eq!(render(&bad_tag), "<opaque 2 bytes>");
+        assert_eq!(render_t(Bytes::from(vec![1, 2])), "[1, 2]");
+    }
+
+    #[test]
+    fn summarizes_stored_values() {
+        let account_hash = AccountHash::new([4; 32]);
+        let main_purse = URef::new([5; 32], AccessRights::READ_ADD_WRITE);
+        let account = Account::create(account_hash, NamedKeys::new(), main_purse);
+        assert_eq!(
+            render_stored_value(&StoredValue::Account(account)),
+            format!(
+                "Account {} {{ main_purse: {}, named_keys: 0 }}",
+                account_hash.to_formatted_string(),
+                main_purse.to_formatted_string()
+            )
+        );
+
+        let contract = Contract::new(
+            ContractPackageHash::new([6; 32]),
+            ContractWasmHash::new([7; 32]),
+            NamedKeys::new(),
+            EntryPoints::new_with_default_entry_point(),
+            Default::default(),
+        );
+        assert_eq!(
+            render_stored_value(&StoredValue::Contract(contract)),
+            format!(
+                "Contract {{ package: {}, wasm: {}, entry_points: 1, named_keys: 0 }}",
+                ContractPackageHash::new([6; 32]).to_formatted_string(),
+                ContractWasmHash::new([7; 32]).to_formatted_string()
+            )
+        );
+
+        let package = ContractPackage::new(
+            main_purse,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        assert_eq!(
+            render_stored_value(&StoredValue::ContractPackage(package)),
+            format!(
+                "ContractPackage {{ access_key: {}, versions: 0, disabled_versions: 0 }}",
+                main_purse.to_formatted_string()
+            )
+        );
+        assert_eq!(
+            render_stored_value(&StoredValue::RawBytes(vec![1])),
+            "<RawBytes>"
+        );
+    }
+
+    #[test]
+    fn dumps_sorted_storage() {
+        let env = EnvBuilder::new().build();
+        dispatch_with(env.clone(), |_env| {
+            storage::new_uref(Some(3u64));
+            storage::new_uref("named".to_string());
+            let dictionary = storage::new_dictionary("dictionary").unwrap();
+            storage::dictionary_put(dictionary, "b", 2u8);
+            storage::dictionary_put(dictionary, "a", vec![1u32]);
+        });
+        let address = |index: u8| {
+            let mut address = [0; 32];
+            address[0] = index;
+            address
+        };
+        let uref = |index| Key::URef(URef::new(address(index), AccessRights::NONE));
+        let seed = hex(&address(3));
+        assert_eq!(
+            env.dump_storage(),
+            [
+                format!("{} = Some(3)", uref(1).to_formatted_string()),
+                format!(r#"{} = "named""#, uref(2).to_formatted_string()),
+                format!("{} = ()", uref(3).to_formatted_string()),
+                format!(r#"dictionary-{seed}["a"] = [1]"#),
+                format!(r#"dictionary-{seed}["b"] = 2"#),
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -1,5 +1,8 @@
 pub mod base128;
 pub mod dictionary_key;
+pub mod indexed_set;
 pub mod mapping;
+pub mod page;
+pub mod ring_buffer;
 pub mod set;
 pub mod vector;
@@ -18,14 +18,24 @@ pub enum DecodeError {
     InvalidDigit(u8),
     Overflow,
     NonZeroPadding,
+    /// The last digit only holds padding, which [`encode_bytes`] never produces.
+    ExcessPadding,
 }
 
 pub fn encode_bytes(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    encode_bytes_into(bytes, &mut result);
+    result
+}
+
+/// Appends the encoding of `bytes` to `out`, as returned by [`encode_bytes`].
+pub fn encode_bytes_into(bytes: &[u8], out: &mut String) {
     if bytes.is_empty() {
-        return String::from("\0");
+        out.push('\0');
+        return;
     }
 
-    let mut result = Vec::new();
+    out.reserve((bytes.len() * 8).div_ceil(7));
     let mut bit_buffer: u32 = 0;
     let mut bits_in_buffer = 0usize;
 
@@ -36,7 +46,7 @@ pub fn encode_bytes(bytes: &[u8]) -> String {
         while bits_in_buffer >= 7 {
             bits_in_buffer -= 7;
             let digit = ((bit_buffer >> bits_in_buffer) & 0x7F) as u8;
-            result.push(digit);
+            out.push(char::from(digit));
             if bits_in_buffer == 0 {
                 bit_buffer = 0;
             } else {
@@ -47,11 +57,8 @@ pub fn encode_bytes(bytes: &[u8]) -> String {
 
     if bits_in_buffer > 0 {
         let digit = (bit_buffer << (7 - bits_in_buffer)) as u8 & 0x7F;
-        result.push(digit);
+        out.push(char::from(digit));
     }
-
-    debug_assert!(str::from_utf8(&result).is_ok());
-    unsafe { String::from_utf8_unchecked(result) }
 }
 
 pub fn decode_bytes(digits: &str) -> Result<Vec<u8>, DecodeError> {
@@ -87,6 +94,11 @@ pub fn decode_bytes(digits: &str) -> Result<Vec<u8>, DecodeError> {
         }
     }
 
+    // Up to 6 padding bits complete the last byte, a whole digit of padding would make the
+    // encoding of the bytes ambiguous
+    if bits_in_buffer >= 7 {
+        return Err(DecodeError::ExcessPadding);
+    }
     if bits_in_buffer > 0 {
         let mask = (1u32 << bits_in_buffer) - 1;
         if bit_buffer & mask != 0 {
@@ -140,7 +152,82 @@ mod tests {
         assert_eq!(super::decode_bytes(&encoded).unwrap(), blob);
     }
 
+    #[test]
+    fn empty_bytes_roundtrip_through_sentinel() {
+        assert_eq!(super::encode_bytes(&[]), "\0");
+        assert_eq!(super::decode_bytes("\0"), Ok(vec![]));
+        assert_eq!(super::decode_bytes(""), Err(super::DecodeError::EmptyInput));
+    }
+
+    #[test]
+    fn whole_padding_digit_is_rejected() {
+        // 7 bytes encode to 8 digits, a 9th digit would only hold padding.
+        let mut encoded = super::encode_bytes(&[0xAB; 7]);
+        assert_eq!(encoded.len(), 8);
+        encoded.push('\0');
+        assert_eq!(
+            super::decode_bytes(&encoded),
+            Err(super::DecodeError::ExcessPadding)
+        );
+        assert_eq!(
+            super::decode_bytes("\u{1}"),
+            Err(super::DecodeError::ExcessPadding)
+        );
+    }
+
+    /// Number of padding bits in the last digit of the encoding of `len` bytes.
+    fn padding_bits(len: usize) -> usize {
+        (7 - (len * 8) % 7) % 7
+    }
+
     proptest! {
+        #[test]
+        fn proptest_nonzero_padding_is_rejected(
+            bytes in proptest::collection::vec(any::<u8>(), 1..64),
+            padding in 1u8..0x40,
+        ) {
+            let padding_bits = padding_bits(bytes.len());
+            prop_assume!(padding_bits > 0);
+            let padding = padding & ((1u8 << padding_bits) - 1);
+            prop_assume!(padding != 0);
+
+            let mut digits = super::encode_bytes(&bytes).into_bytes();
+            *digits.last_mut().unwrap() |= padding;
+            let digits = String::from_utf8(digits).unwrap();
+            prop_assert_eq!(
+                super::decode_bytes(&digits),
+                Err(super::DecodeError::NonZeroPadding)
+            );
+        }
+
+        #[test]
+        fn proptest_digits_above_0x7f_are_rejected(
+            bytes in proptest::collection::vec(any::<u8>(), 0..64),
+            index in any::<prop::sample::Index>(),
+            invalid in any::<char>().prop_filter("non-ASCII", |c| !c.is_ascii()),
+        ) {
+            let mut digits = super::encode_bytes(&bytes);
+            let at = index.index(digits.len() + 1);
+            digits.insert(at, invalid);
+
+            let mut utf8 = [0; 4];
+            let first_byte = invalid.encode_utf8(&mut utf8).as_bytes()[0];
+            prop_assert_eq!(
+                super::decode_bytes(&digits),
+                Err(super::DecodeError::InvalidDigit(first_byte))
+            );
+        }
+
+        #[test]
+        fn proptest_decoded_digits_are_canonical(
+            digits in proptest::collection::vec(0u8..=0x7F, 1..64),
+        ) {
+            let digits = String::from_utf8(digits).unwrap();
+            if let Ok(bytes) = super::decode_bytes(&digits) {
+                prop_assert_eq!(super::encode_bytes(&bytes), digits);
+            }
+        }
+
         #[test]
         fn proptest_u64_roundtrip(value in any::<u64>()) {
             let encoded = super::encode_bytes(&value.to_le_bytes());
@@ -1,7 +1,9 @@
 use alloc::{borrow::Cow, string::String};
-use casper_types::{U256, account::AccountHash, contracts::ContractHash};
+use casper_types::{
+    ApiError, Key, U256, account::AccountHash, bytesrepr::ToBytes, contracts::ContractHash,
+};
 
-use crate::collections::base128;
+use crate::{casper_contract::unwrap_or_revert::UnwrapOrRevert, collections::base128};
 
 /// A trait for types that can be used as dictionary keys.
 ///
@@ -14,84 +16,252 @@ use crate::collections::base128;
 /// use cases where dictionary keys need to be compact.
 ///
 /// This may not be backwards-compatible with pre-existing code using base64 or other encoding.
+///
+/// Implementations append their key to a string with [`DictionaryKey::write_key`], so compound
+/// keys written through a [`DictionaryKeyWriter`] are built in a single buffer without
+/// intermediate strings for their parts.
+///
+/// # Storage layout change
+///
+/// String parts of compound keys, and compound parts nested in other compound keys, are
+/// length-prefixed (see [`write_str_part`]) so that keys like `("a:b", "c")` and `("a", "b:c")`,
+/// or `(("x", "y"), "z")` and `("x", ("y", "z"))`, no longer collide. This changes the dictionary
+/// item key of every compound key with such a part: a contract installed with an earlier version
+/// of this crate that upgrades would no longer find its existing `Mapping`, `Set` or `IndexedSet`
+/// entries under them. Such contracts keep their layout by wrapping the key type in
+/// [`LegacyKey`], e.g. `Mapping<LegacyKey<(String, AccountHash)>, U256>`, or migrate the entries
+/// they can enumerate (see [`crate::migrations`]) from the `LegacyKey` to the new key. Keys
+/// without string or nested compound parts are unchanged.
 pub trait DictionaryKey<'a> {
-    fn dictionary_key(&'a self) -> Cow<'a, str>;
+    /// Appends the key to `out`.
+    fn write_key(&self, out: &mut String);
+
+    /// Appends the key as a part of a compound key, see [`DictionaryKeyWriter`].
+    ///
+    /// Defaults to [`DictionaryKey::write_key`]. Strings and compound keys, which may contain the
+    /// delimiter of the parts, are length-prefixed instead with [`write_str_part`].
+    fn write_part(&self, out: &mut String) {
+        self.write_key(out);
+    }
+
+    /// Appends the key as written before string and nested compound parts were length-prefixed,
+    /// see [`LegacyKey`].
+    ///
+    /// Defaults to [`DictionaryKey::write_key`], which is the same for keys other than compound
+    /// ones.
+    fn write_legacy_key(&self, out: &mut String) {
+        self.write_key(out);
+    }
+
+    /// Returns the key, written into a new string unless the implementation can borrow it.
+    fn dictionary_key(&'a self) -> Cow<'a, str> {
+        let mut key = String::new();
+        self.write_key(&mut key);
+        Cow::Owned(key)
+    }
 }
 
 impl<'a, T> DictionaryKey<'a> for &'a T
 where
-    T: DictionaryKey<'a>,
+    T: DictionaryKey<'a> + ?Sized,
 {
+    fn write_key(&self, out: &mut String) {
+        (**self).write_key(out);
+    }
+
+    fn write_part(&self, out: &mut String) {
+        (**self).write_part(out);
+    }
+
+    fn write_legacy_key(&self, out: &mut String) {
+        (**self).write_legacy_key(out);
+    }
+
     fn dictionary_key(&'a self) -> Cow<'a, str> {
         (**self).dictionary_key()
     }
 }
 
 impl<'a> DictionaryKey<'a> for str {
+    fn write_key(&self, out: &mut String) {
+        out.push_str(self);
+    }
+
+    fn write_part(&self, out: &mut String) {
+        write_str_part(self, out);
+    }
+
     fn dictionary_key(&'a self) -> Cow<'a, str> {
         Cow::Borrowed(self)
     }
 }
 
 impl DictionaryKey<'_> for u32 {
-    fn dictionary_key(&self) -> Cow<'_, str> {
-        Cow::Owned(base128::encode_bytes(&self.to_le_bytes()))
+    fn write_key(&self, out: &mut String) {
+        base128::encode_bytes_into(&self.to_le_bytes(), out);
     }
 }
 
 impl DictionaryKey<'_> for u64 {
-    fn dictionary_key(&self) -> Cow<'_, str> {
-        Cow::Owned(base128::encode_bytes(&self.to_le_bytes()))
+    fn write_key(&self, out: &mut String) {
+        base128::encode_bytes_into(&self.to_le_bytes(), out);
     }
 }
 
 impl<const N: usize> DictionaryKey<'_> for [u8; N] {
-    fn dictionary_key(&self) -> Cow<'_, str> {
-        Cow::Owned(base128::encode_bytes(self))
+    fn write_key(&self, out: &mut String) {
+        base128::encode_bytes_into(self, out);
     }
 }
 
 impl DictionaryKey<'_> for AccountHash {
-    fn dictionary_key(&self) -> Cow<'_, str> {
-        Cow::Owned(base128::encode_bytes(self.as_bytes()))
+    fn write_key(&self, out: &mut String) {
+        base128::encode_bytes_into(self.as_bytes(), out);
     }
 }
 
 impl DictionaryKey<'_> for ContractHash {
-    fn dictionary_key(&self) -> Cow<'_, str> {
-        Cow::Owned(base128::encode_bytes(self.as_bytes()))
+    fn write_key(&self, out: &mut String) {
+        base128::encode_bytes_into(self.as_bytes(), out);
+    }
+}
+
+impl DictionaryKey<'_> for Key {
+    fn write_key(&self, out: &mut String) {
+        let bytes = self.to_bytes().unwrap_or_revert();
+        base128::encode_bytes_into(&bytes, out);
     }
 }
 
 impl DictionaryKey<'_> for U256 {
-    fn dictionary_key(&self) -> Cow<'_, str> {
+    fn write_key(&self, out: &mut String) {
         let mut bytes = [0u8; 32];
         self.to_little_endian(&mut bytes);
-        Cow::Owned(base128::encode_bytes(&bytes))
+        base128::encode_bytes_into(&bytes, out);
     }
 }
+
 const TUPLE_DELIMITER: char = ':';
 
+/// Appends `part` as a part of a compound key: the base128 encoding of its length in bytes, as a
+/// little-endian `u32`, followed by the string itself.
+///
+/// Without the prefix, `("a:b", "c")` and `("a", "b:c")` would both be written as `a:b:c`. Nested
+/// compound keys are written to a string first and prefixed the same way.
+pub fn write_str_part(part: &str, out: &mut String) {
+    let len = u32::try_from(part.len())
+        .ok()
+        .unwrap_or_revert_with(ApiError::DictionaryItemKeyExceedsLength);
+    base128::encode_bytes_into(&len.to_le_bytes(), out);
+    out.push_str(part);
+}
+
+/// Writes the parts of a compound key into one string, separated by `:`.
+///
+/// Used by the tuple implementations and `#[derive(DictionaryKey)]`. Parts are written with
+/// [`DictionaryKey::write_part`], so string parts are length-prefixed:
+///
+/// ```
+/// use veles_casper_contract_api::collections::dictionary_key::{DictionaryKey, DictionaryKeyWriter};
+///
+/// let mut key = String::new();
+/// DictionaryKeyWriter::new(&mut key).part("balances").part(&7u32);
+/// assert_eq!(key, ("balances", 7u32).dictionary_key());
+/// assert_ne!(("a:b", "c").dictionary_key(), ("a", "b:c").dictionary_key());
+/// ```
+pub struct DictionaryKeyWriter<'s> {
+    out: &'s mut String,
+    is_first: bool,
+}
+
+impl<'s> DictionaryKeyWriter<'s> {
+    /// Starts a compound key at the end of `out`.
+    pub fn new(out: &'s mut String) -> Self {
+        Self {
+            out,
+            is_first: true,
+        }
+    }
+
+    /// Appends `part`, preceded by the delimiter unless it is the first part.
+    pub fn part<'a, T>(&mut self, part: &T) -> &mut Self
+    where
+        T: DictionaryKey<'a> + ?Sized,
+    {
+        self.delimit();
+        part.write_part(self.out);
+        self
+    }
+
+    /// Appends `part` as [`DictionaryKey::write_legacy_key`] writes it, without a length prefix.
+    pub fn legacy_part<'a, T>(&mut self, part: &T) -> &mut Self
+    where
+        T: DictionaryKey<'a> + ?Sized,
+    {
+        self.delimit();
+        part.write_legacy_key(self.out);
+        self
+    }
+
+    fn delimit(&mut self) {
+        if !self.is_first {
+            self.out.push(TUPLE_DELIMITER);
+        }
+        self.is_first = false;
+    }
+}
+
+/// Appends the compound key written by `write_key` as a length-prefixed part of another compound
+/// key, see [`write_str_part`].
+///
+/// Used by the tuple implementations and `#[derive(DictionaryKey)]` for
+/// [`DictionaryKey::write_part`].
+pub fn write_compound_part(write_key: impl FnOnce(&mut String), out: &mut String) {
+    let mut part = String::new();
+    write_key(&mut part);
+    write_str_part(&part, out);
+}
+
+/// A dictionary key written in the layout of earlier versions of this crate, in which string and
+/// nested compound parts of compound keys weren't length-prefixed.
+///
+/// Contracts installed with such a version keep finding their existing entries by keying their
+/// collections with it:
+///
+/// ```
+/// use veles_casper_contract_api::collections::dictionary_key::{DictionaryKey, LegacyKey};
+///
+/// assert_eq!(LegacyKey(("a:b", "c")).dictionary_key(), "a:b:c");
+/// assert_eq!(LegacyKey(("a", "b:c")).dictionary_key(), "a:b:c");
+/// assert_ne!(("a:b", "c").dictionary_key(), "a:b:c");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LegacyKey<K>(pub K);
+
+impl<'a, K: DictionaryKey<'a>> DictionaryKey<'a> for LegacyKey<K> {
+    fn write_key(&self, out: &mut String) {
+        self.0.write_legacy_key(out);
+    }
+}
+
 macro_rules! impl_dictionary_key_for_tuple {
     ( $( ($idx:tt, $T:ident) ),+ ) => {
         impl<'a, $($T),+> DictionaryKey<'a> for ($( $T, )+)
         where
             $($T: DictionaryKey<'a>,)+
         {
-            fn dictionary_key(&'a self) -> Cow<'a, str> {
-                let parts = [
-                    $(self.$idx.dictionary_key(),)+
-                ];
-                let separators = parts.len().saturating_sub(1);
-                let capacity = parts.iter().map(|part| part.len()).sum::<usize>() + separators;
-                let mut combined = String::with_capacity(capacity);
-                for (idx, part) in parts.iter().enumerate() {
-                    if idx != 0 {
-                        combined.push(TUPLE_DELIMITER);
-                    }
-                    combined.push_str(part);
-                }
-                Cow::Owned(combined)
+            fn write_key(&self, out: &mut String) {
+                DictionaryKeyWriter::new(out)
+                    $(.part(&self.$idx))+;
+            }
+
+            fn write_part(&self, out: &mut String) {
+                write_compound_part(|part| self.write_key(part), out);
+            }
+
+            fn write_legacy_key(&self, out: &mut String) {
+                DictionaryKeyWriter::new(out)
+                    $(.legacy_part(&self.$idx))+;
             }
         }
     };
@@ -163,17 +333,75 @@ mod tests {
         assert_eq!(base128::decode_bytes(toks[1]), Ok(b.to_le_bytes().to_vec()));
     }
 
+    #[test]
+    fn test_dictionary_key_key() {
+        let key = Key::Account(AccountHash::new([7u8; 32]));
+        let dictionary_key = key.dictionary_key();
+        assert_eq!(
+            base128::decode_bytes(&dictionary_key),
+            Ok(key.to_bytes().unwrap())
+        );
+        // 33 bytes of a serialized account key fit in 38 base128 digits.
+        assert_eq!(dictionary_key.len(), 38);
+    }
+
+    #[test]
+    fn str_parts_are_length_prefixed() {
+        let key = ("a:b", 7u32).dictionary_key();
+        let (prefix, rest) = key.split_at(5);
+        assert_eq!(
+            base128::decode_bytes(prefix),
+            Ok(3u32.to_le_bytes().to_vec())
+        );
+        assert!(rest.starts_with("a:b:"));
+
+        assert_ne!(("a:b", "c").dictionary_key(), ("a", "b:c").dictionary_key());
+        // Keys of a single string are left as they are.
+        assert_eq!("a:b".dictionary_key(), "a:b");
+    }
+
     #[test]
     fn triple_key() {
         let a = U256::MAX;
         let b = u64::MAX;
         let c = 123u32;
-        let compound = ((a, b), c);
-        let key = compound.dictionary_key();
+        let legacy = LegacyKey(((a, b), c));
+        let key = legacy.dictionary_key();
         let toks = key.split(TUPLE_DELIMITER).collect::<Vec<_>>();
         assert_eq!(toks.len(), 3);
         assert_eq!(base128::decode_bytes(toks[0]), Ok(vec![255u8; 32]));
         assert_eq!(base128::decode_bytes(toks[1]), Ok(b.to_le_bytes().to_vec()));
         assert_eq!(base128::decode_bytes(toks[2]), Ok(c.to_le_bytes().to_vec()));
+
+        // Nested, the pair is length-prefixed like a string part
+        let mut expected = String::new();
+        write_str_part(&(a, b).dictionary_key(), &mut expected);
+        expected.push(TUPLE_DELIMITER);
+        c.write_key(&mut expected);
+        assert_eq!(((a, b), c).dictionary_key(), expected);
+    }
+
+    #[test]
+    fn nested_compound_parts_do_not_collide() {
+        assert_ne!(
+            (("x", "y"), "z").dictionary_key(),
+            ("x", ("y", "z")).dictionary_key()
+        );
+        assert_eq!(
+            LegacyKey((("x", "y"), "z")).dictionary_key(),
+            LegacyKey(("x", ("y", "z"))).dictionary_key()
+        );
+    }
+
+    #[test]
+    fn legacy_and_current_keys_are_pinned() {
+        // Changing either breaks the storage of deployed contracts
+        let key = ("alice", 7u32);
+        assert_eq!(LegacyKey(key).dictionary_key(), "alice:\u{3}@\0\0\0");
+        assert_eq!(key.dictionary_key(), "\u{2}@\0\0\0alice:\u{3}@\0\0\0");
+        // Keys without string or compound parts are the same in both layouts
+        let key = (AccountHash::new([7; 32]), 7u32);
+        assert_eq!(LegacyKey(key).dictionary_key(), key.dictionary_key());
+        assert_eq!(LegacyKey("a:b").dictionary_key(), "a:b".dictionary_key());
     }
 }
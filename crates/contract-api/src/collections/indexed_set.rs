@@ -0,0 +1,237 @@
+use casper_types::{
+    ApiError, CLTyped,
+    bytesrepr::{FromBytes, ToBytes},
+};
+
+use crate::{
+    collections::{dictionary_key::DictionaryKey, mapping::Mapping, page::Page, vector::Vector},
+    named_key::NamedKey,
+};
+
+/// A set of unique keys of type `K` that can be enumerated by index.
+///
+/// Elements are stored densely in a [`Vector`] and their positions in a [`Mapping`], so
+/// membership checks, insertions and removals take a constant number of dictionary accesses.
+/// Removal moves the last element into the freed slot, hence indices are not stable across
+/// removals.
+///
+/// Every element is stored twice, as a [`Vector`] item and as the key of its index, next to the
+/// vector's length item. Dictionary items can't be deleted, so a removed key keeps a `None` index
+/// and the vacated last slot of the vector keeps its stale element until a later insertion
+/// overwrites it.
+pub struct IndexedSet<K> {
+    elements: Vector<K>,
+    indices: Mapping<K, Option<u64>>,
+}
+
+impl<K> IndexedSet<K> {
+    /// Creates a set storing its elements under `elements` and their indices under `indices`.
+    pub const fn from_named_keys(elements: NamedKey, indices: NamedKey) -> Self {
+        Self {
+            elements: Vector::from_named_key(elements),
+            indices: Mapping::from_named_key(indices),
+        }
+    }
+
+    /// Returns the named keys of the two dictionaries backing the set.
+    pub fn named_urefs(&self) -> [&NamedKey; 2] {
+        [self.elements.named_uref(), self.indices.named_uref()]
+    }
+
+    pub fn len(&self) -> Result<u64, ApiError> {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> Result<bool, ApiError> {
+        self.elements.is_empty()
+    }
+
+    /// Returns the element at `index`, or `None` past the end of the set.
+    pub fn get(&self, index: u64) -> Result<Option<K>, ApiError>
+    where
+        K: FromBytes + CLTyped,
+    {
+        if index >= self.len()? {
+            return Ok(None);
+        }
+        self.elements.get(index)
+    }
+
+    /// Returns the elements of the set in index order.
+    ///
+    /// The length is read once up front, so the set shouldn't be modified while iterating.
+    pub fn iter(&self) -> Result<impl Iterator<Item = Result<K, ApiError>> + '_, ApiError>
+    where
+        K: FromBytes + CLTyped,
+    {
+        Ok((0..self.len()?).map(|index| self.elements.get(index)?.ok_or(ApiError::MissingKey)))
+    }
+
+    /// Returns the up to `limit` elements from index `cursor` in index order, see
+    /// [`page`](crate::collections::page::page).
+    ///
+    /// Removals move elements between indices, so pages read across removals may skip or repeat
+    /// elements.
+    pub fn page(&self, cursor: Option<u64>, limit: u64) -> Result<Page<K>, ApiError>
+    where
+        K: FromBytes + CLTyped,
+    {
+        self.elements.page(cursor, limit)
+    }
+
+    pub fn contains(&self, key: &K) -> Result<bool, ApiError>
+    where
+        K: for<'a> DictionaryKey<'a>,
+    {
+        Ok(self.index_of(key)?.is_some())
+    }
+
+    /// Adds `key` to the set, returning whether it was not already present.
+    pub fn insert(&self, key: &K) -> Result<bool, ApiError>
+    where
+        K: for<'a> DictionaryKey<'a> + ToBytes + CLTyped + Clone,
+    {
+        if self.contains(key)? {
+            return Ok(false);
+        }
+        let index = self.len()?;
+        self.elements.push(key.clone())?;
+        self.indices.insert(key, Some(index))?;
+        Ok(true)
+    }
+
+    /// Removes `key` from the set, returning whether it was present.
+    pub fn remove(&self, key: &K) -> Result<bool, ApiError>
+    where
+        K: for<'a> DictionaryKey<'a> + ToBytes + FromBytes + CLTyped,
+    {
+        let Some(index) = self.index_of(key)? else {
+            return Ok(false);
+        };
+        let last_index = self.len()? - 1;
+        if index != last_index {
+            let last = self.elements.get(last_index)?.ok_or(ApiError::MissingKey)?;
+            self.indices.insert(&last, Some(index))?;
+            self.elements.set(index, last)?;
+        }
+        self.elements.set_len(last_index)?;
+        self.indices.insert(key, None)?;
+        Ok(true)
+    }
+
+    fn index_of(&self, key: &K) -> Result<Option<u64>, ApiError>
+    where
+        K: for<'a> DictionaryKey<'a>,
+    {
+        Ok(self.indices.get(key)?.flatten())
+    }
+}
+
+unsafe impl<K: Sync> Sync for IndexedSet<K> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+
+    use super::*;
+    use crate::utils;
+
+    fn elements(set: &IndexedSet<u64>) -> Vec<u64> {
+        (0..set.len().unwrap())
+            .map(|index| set.get(index).unwrap().unwrap())
+            .collect()
+    }
+
+    fn iterated(set: &IndexedSet<u64>) -> Vec<u64> {
+        set.iter().unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn insert_and_remove_keep_elements_dense() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let set: IndexedSet<u64> = IndexedSet::from_named_keys(
+                NamedKey::from_name("holders"),
+                NamedKey::from_name("holder_indices"),
+            );
+            for named_key in set.named_urefs() {
+                named_key.get_or_init(utils::new_dictionary_key).unwrap();
+            }
+            assert_eq!(set.is_empty(), Ok(true));
+
+            for key in [10, 20, 30] {
+                assert_eq!(set.insert(&key), Ok(true));
+            }
+            assert_eq!(set.insert(&20), Ok(false));
+            assert_eq!(elements(&set), [10, 20, 30]);
+            assert_eq!(set.get(3), Ok(None));
+
+            // Removing from the middle moves the last element into its slot.
+            assert_eq!(set.remove(&10), Ok(true));
+            assert_eq!(elements(&set), [30, 20]);
+            assert_eq!(set.contains(&10), Ok(false));
+            assert_eq!(set.remove(&10), Ok(false));
+
+            assert_eq!(set.remove(&20), Ok(true));
+            assert_eq!(set.remove(&30), Ok(true));
+            assert_eq!(set.is_empty(), Ok(true));
+            assert_eq!(set.get(0), Ok(None));
+
+            assert_eq!(set.insert(&20), Ok(true));
+            assert_eq!(elements(&set), [20]);
+        });
+    }
+
+    #[test]
+    fn iter_follows_the_indices() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let set: IndexedSet<u64> = IndexedSet::from_named_keys(
+                NamedKey::from_name("members"),
+                NamedKey::from_name("member_indices"),
+            );
+            for named_key in set.named_urefs() {
+                named_key.get_or_init(utils::new_dictionary_key).unwrap();
+            }
+            assert!(iterated(&set).is_empty());
+
+            for key in [1, 2, 3, 4] {
+                set.insert(&key).unwrap();
+            }
+            assert_eq!(iterated(&set), [1, 2, 3, 4]);
+
+            // The stale last slot is past the length and not yielded.
+            set.remove(&2).unwrap();
+            assert_eq!(iterated(&set), [1, 4, 3]);
+            assert_eq!(iterated(&set), elements(&set));
+
+            set.insert(&2).unwrap();
+            assert_eq!(iterated(&set), [1, 4, 3, 2]);
+        });
+    }
+
+    #[test]
+    fn page_follows_the_indices() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let set: IndexedSet<u64> = IndexedSet::from_named_keys(
+                NamedKey::from_name("paged"),
+                NamedKey::from_name("paged_indices"),
+            );
+            for named_key in set.named_urefs() {
+                named_key.get_or_init(utils::new_dictionary_key).unwrap();
+            }
+            for key in [1, 2, 3, 4, 5] {
+                set.insert(&key).unwrap();
+            }
+            set.remove(&2).unwrap();
+
+            let first = set.page(None, 3).unwrap();
+            assert_eq!(first.items, [1, 5, 3]);
+            assert_eq!(first.next_cursor, Some(3));
+            // The stale last slot is past the length and not read.
+            let last = set.page(first.next_cursor, 3).unwrap();
+            assert_eq!(last.items, [4]);
+            assert_eq!(last.next_cursor, None);
+        });
+    }
+}
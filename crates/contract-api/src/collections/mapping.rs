@@ -1,6 +1,10 @@
 use core::marker::PhantomData;
 
-use crate::{collections::dictionary_key::DictionaryKey, named_key::NamedKey};
+use crate::{
+    collections::dictionary_key::DictionaryKey,
+    math::{CheckedStorageAdd, CheckedStorageSub},
+    named_key::NamedKey,
+};
 use casper_types::{
     ApiError, CLTyped,
     bytesrepr::{FromBytes, ToBytes},
@@ -51,6 +55,166 @@ impl<K, V> Mapping<K, V> {
         let value: Option<V> = self.named_key.get_dict(&key_preimage)?;
         Ok(value)
     }
+
+    /// Returns the value stored under `key`, or stores and returns the value computed by `f`.
+    pub fn get_or_insert_with<'a, F>(&self, key: &'a K, f: F) -> Result<V, ApiError>
+    where
+        K: DictionaryKey<'a>,
+        V: ToBytes + FromBytes + CLTyped,
+        F: FnOnce() -> V,
+    {
+        let key_preimage = key.dictionary_key();
+        if let Some(value) = self.named_key.get_dict(&key_preimage)? {
+            return Ok(value);
+        }
+        let value = f();
+        self.named_key.put_dict(&key_preimage, &value)?;
+        Ok(value)
+    }
+
+    /// Adds `delta` to the value stored under `key` and returns the sum, or `overflow_err` if it
+    /// overflows.
+    ///
+    /// A missing value counts as zero. Storage failures are converted into `E`.
+    pub fn increase<'a, E>(&self, key: &'a K, delta: V, overflow_err: E) -> Result<V, E>
+    where
+        K: DictionaryKey<'a>,
+        V: ToBytes + FromBytes + CLTyped + Default + CheckedStorageAdd,
+        E: From<ApiError>,
+    {
+        let key_preimage = key.dictionary_key();
+        let value = self
+            .named_key
+            .get_dict::<_, V>(&key_preimage)?
+            .unwrap_or_default()
+            .try_add_or(delta, overflow_err)?;
+        self.named_key.put_dict(&key_preimage, &value)?;
+        Ok(value)
+    }
+
+    /// Subtracts `delta` from the value stored under `key` and returns the difference, or
+    /// `underflow_err` if `delta` is greater, see [`Mapping::increase`].
+    pub fn decrease<'a, E>(&self, key: &'a K, delta: V, underflow_err: E) -> Result<V, E>
+    where
+        K: DictionaryKey<'a>,
+        V: ToBytes + FromBytes + CLTyped + Default + CheckedStorageSub,
+        E: From<ApiError>,
+    {
+        let key_preimage = key.dictionary_key();
+        let value = self
+            .named_key
+            .get_dict::<_, V>(&key_preimage)?
+            .unwrap_or_default()
+            .try_sub_or(delta, underflow_err)?;
+        self.named_key.put_dict(&key_preimage, &value)?;
+        Ok(value)
+    }
 }
 
 unsafe impl<K: Sync, V: Sync> Sync for Mapping<K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+
+    use casper_types::U256;
+
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn test_get_or_insert_with_runs_closure_once() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let mapping: Mapping<u64, u64> =
+                Mapping::from_named_key(NamedKey::from_name("counters"));
+            mapping
+                .named_uref()
+                .get_or_init(utils::new_dictionary_key)
+                .unwrap();
+
+            let calls = Cell::new(0);
+            let init = |value| {
+                calls.set(calls.get() + 1);
+                value
+            };
+
+            assert_eq!(mapping.get_or_insert_with(&1, || init(10)), Ok(10));
+            assert_eq!(mapping.get_or_insert_with(&1, || init(20)), Ok(10));
+            assert_eq!(calls.get(), 1);
+            assert_eq!(mapping.get(&1), Ok(Some(10)));
+
+            assert_eq!(mapping.get_or_insert_with(&2, || init(30)), Ok(30));
+            assert_eq!(calls.get(), 2);
+        });
+    }
+
+    #[test]
+    fn test_increase_and_decrease_check_bounds() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let balances: Mapping<u64, U256> =
+                Mapping::from_named_key(NamedKey::from_name("balances"));
+            balances
+                .named_uref()
+                .get_or_init(utils::new_dictionary_key)
+                .unwrap();
+
+            // Missing values count as zero.
+            assert_eq!(
+                balances.decrease(&1, U256::one(), ApiError::User(2)),
+                Err(ApiError::User(2))
+            );
+            assert_eq!(balances.get(&1), Ok(None));
+            assert_eq!(
+                balances.increase(&1, U256::from(10), ApiError::User(1)),
+                Ok(U256::from(10))
+            );
+            assert_eq!(
+                balances.decrease(&1, U256::from(4), ApiError::User(2)),
+                Ok(U256::from(6))
+            );
+            assert_eq!(balances.get(&1), Ok(Some(U256::from(6))));
+
+            // A failed update leaves the stored value as it is.
+            assert_eq!(
+                balances.increase(&1, U256::MAX, ApiError::User(1)),
+                Err(ApiError::User(1))
+            );
+            assert_eq!(
+                balances.decrease(&1, U256::from(7), ApiError::User(2)),
+                Err(ApiError::User(2))
+            );
+            assert_eq!(balances.get(&1), Ok(Some(U256::from(6))));
+            assert_eq!(
+                balances.decrease(&1, U256::from(6), ApiError::User(2)),
+                Ok(U256::zero())
+            );
+        });
+    }
+
+    #[test]
+    fn test_storage_failures_convert_into_the_error() {
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            Overflow,
+            Storage(ApiError),
+        }
+
+        impl From<ApiError> for Error {
+            fn from(error: ApiError) -> Self {
+                Error::Storage(error)
+            }
+        }
+
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            // The dictionary was never created.
+            let balances: Mapping<u64, u64> =
+                Mapping::from_named_key(NamedKey::from_name("balances"));
+            assert_eq!(
+                balances.increase(&1, 1, Error::Overflow),
+                Err(Error::Storage(ApiError::MissingKey))
+            );
+        });
+    }
+}
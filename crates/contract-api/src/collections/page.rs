@@ -0,0 +1,191 @@
+//! Cursor pagination for entry points listing the contents of a collection.
+//!
+//! An entry point returning every element of a growing collection eventually runs out of gas.
+//! [`page`] reads at most `limit` elements from a cursor instead, and the [`Page`] it returns
+//! carries the cursor to call the entry point with for the next page.
+use alloc::vec::Vec;
+
+use crate::{
+    casper_types::{
+        ApiError, CLType, CLTyped,
+        bytesrepr::{self, FromBytes, ToBytes},
+    },
+    error::UniversalError,
+};
+
+/// The largest number of elements [`page`] reads at once.
+pub const MAX_PAGE_LIMIT: u64 = 100;
+
+/// Consecutive elements of a collection read by [`page`].
+///
+/// Serializes like the tuple `(items, next_cursor)`, which is also its `CLType`, so clients can
+/// decode the pages returned by an entry point without this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The index of the first element of the next page, `None` on the last page.
+    pub next_cursor: Option<u64>,
+}
+
+impl<T: ToBytes> ToBytes for Page<T> {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = bytesrepr::allocate_buffer(self)?;
+        self.write_bytes(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.items.serialized_length() + self.next_cursor.serialized_length()
+    }
+
+    fn write_bytes(&self, writer: &mut Vec<u8>) -> Result<(), bytesrepr::Error> {
+        self.items.write_bytes(writer)?;
+        self.next_cursor.write_bytes(writer)
+    }
+}
+
+impl<T: FromBytes> FromBytes for Page<T> {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (items, remainder) = Vec::<T>::from_bytes(bytes)?;
+        let (next_cursor, remainder) = Option::<u64>::from_bytes(remainder)?;
+        Ok((Self { items, next_cursor }, remainder))
+    }
+}
+
+impl<T: CLTyped> CLTyped for Page<T> {
+    fn cl_type() -> CLType {
+        <(Vec<T>, Option<u64>)>::cl_type()
+    }
+}
+
+/// Reads the up to `limit` elements of a collection of `len` elements starting at index `cursor`,
+/// or at the first element for `None`.
+///
+/// `read` is called with the indices of the page, all below `len`. A cursor at or past the end
+/// gives an empty last page. Fails with [`UniversalError::PageLimitExceeded`] for a `limit` above
+/// [`MAX_PAGE_LIMIT`], and with [`ApiError::InvalidArgument`] for a `limit` of zero, whose pages
+/// would never advance.
+pub fn page<T, F>(len: u64, cursor: Option<u64>, limit: u64, read: F) -> Result<Page<T>, ApiError>
+where
+    F: FnMut(u64) -> Result<T, ApiError>,
+{
+    if limit > MAX_PAGE_LIMIT {
+        return Err(UniversalError::PageLimitExceeded.into());
+    }
+    if limit == 0 {
+        return Err(ApiError::InvalidArgument);
+    }
+    let start = cursor.unwrap_or(0).min(len);
+    let end = start.saturating_add(limit).min(len);
+    let items = (start..end).map(read).collect::<Result<_, _>>()?;
+    Ok(Page {
+        items,
+        next_cursor: (end < len).then_some(end),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+
+    use super::*;
+    use crate::{collections::vector::Vector, named_key::NamedKey, utils};
+
+    fn doubled(indices: core::ops::Range<u64>) -> Vec<u64> {
+        indices.map(|index| index * 2).collect()
+    }
+
+    #[test]
+    fn page_serializes_like_a_tuple() {
+        let page = Page {
+            items: vec![1u64, 2, 3],
+            next_cursor: Some(3),
+        };
+        let tuple = (page.items.clone(), page.next_cursor);
+        assert_eq!(page.to_bytes().unwrap(), tuple.to_bytes().unwrap());
+        assert_eq!(page.serialized_length(), tuple.serialized_length());
+        assert_eq!(Page::<u64>::cl_type(), <(Vec<u64>, Option<u64>)>::cl_type());
+        assert_eq!(bytesrepr::deserialize(page.to_bytes().unwrap()), Ok(page));
+    }
+
+    #[test]
+    fn pages_follow_the_cursors_through_a_vector() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let vector: Vector<u64> = Vector::from_named_key(NamedKey::from_name("values"));
+            vector
+                .named_uref()
+                .get_or_init(utils::new_dictionary_key)
+                .unwrap();
+            for value in doubled(0..1000) {
+                vector.push(value).unwrap();
+            }
+
+            let first = vector.page(None, 30).unwrap();
+            assert_eq!(first.items, doubled(0..30));
+            assert_eq!(first.next_cursor, Some(30));
+            let second = vector.page(first.next_cursor, 30).unwrap();
+            assert_eq!(second.items, doubled(30..60));
+            assert_eq!(second.next_cursor, Some(60));
+
+            // Following the cursors reads every element once, the last page being short.
+            let (mut items, mut pages, mut cursor) = (Vec::new(), 0, None);
+            loop {
+                let page = vector.page(cursor, 30).unwrap();
+                if page.next_cursor.is_none() {
+                    assert_eq!(page.items, doubled(990..1000));
+                }
+                items.extend(page.items);
+                pages += 1;
+                cursor = page.next_cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+            assert_eq!(items, doubled(0..1000));
+            assert_eq!(pages, 34);
+
+            // A page ending exactly at the end is the last one.
+            let last = vector.page(Some(900), MAX_PAGE_LIMIT).unwrap();
+            assert_eq!(last.items, doubled(900..1000));
+            assert_eq!(last.next_cursor, None);
+
+            let empty = Page {
+                items: Vec::new(),
+                next_cursor: None,
+            };
+            assert_eq!(vector.page(Some(1000), 10), Ok(empty.clone()));
+            assert_eq!(vector.page(Some(u64::MAX), 10), Ok(empty));
+        });
+    }
+
+    #[test]
+    fn page_limits_are_enforced() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let vector: Vector<u64> = Vector::from_named_key(NamedKey::from_name("limited"));
+            vector
+                .named_uref()
+                .get_or_init(utils::new_dictionary_key)
+                .unwrap();
+            for value in doubled(0..1000) {
+                vector.push(value).unwrap();
+            }
+
+            // A limit past the end is clamped to the remaining elements.
+            let page = vector.page(Some(995), MAX_PAGE_LIMIT).unwrap();
+            assert_eq!(page.items, doubled(995..1000));
+            assert_eq!(vector.page(None, MAX_PAGE_LIMIT).unwrap().items.len(), 100);
+
+            assert_eq!(
+                vector.page(None, MAX_PAGE_LIMIT + 1),
+                Err(UniversalError::PageLimitExceeded.into())
+            );
+            assert_eq!(
+                vector.page(Some(1000), u64::MAX),
+                Err(UniversalError::PageLimitExceeded.into())
+            );
+            assert_eq!(vector.page(None, 0), Err(ApiError::InvalidArgument));
+        });
+    }
+}
@@ -0,0 +1,251 @@
+//! A dictionary-backed ring buffer keeping the last `capacity` values pushed to it.
+//!
+//! Unlike a [`Vector`](super::vector::Vector) or the CES events dictionary, a [`RingBuffer`]
+//! doesn't grow: once full, every push overwrites the oldest slot. Values keep the logical index
+//! they were pushed at, counting from zero since the buffer's creation, so readers can tell which
+//! values were evicted.
+use core::marker::PhantomData;
+
+use crate::{
+    casper_types::{
+        ApiError, CLTyped,
+        bytesrepr::{FromBytes, ToBytes},
+    },
+    error::UniversalError,
+    keyspace,
+    named_key::NamedKey,
+};
+
+/// Dictionary item key under which the capacity of a [`RingBuffer`] is stored.
+pub const RING_BUFFER_CAPACITY_KEY: &str = "capacity";
+/// Dictionary item key under which the logical index of the next push is stored.
+pub const RING_BUFFER_HEAD_KEY: &str = "head";
+/// Dictionary item key under which the number of retained values is stored.
+pub const RING_BUFFER_LENGTH_KEY: &str = "length";
+
+/// A fixed-capacity collection of the last values of type `T` pushed to it.
+///
+/// The capacity is set once by [`init`](Self::init), and slots are stored under the base128
+/// encoding of their logical index modulo the capacity, next to the capacity, head and length
+/// items, which never collide with slot keys.
+pub struct RingBuffer<T> {
+    named_key: NamedKey,
+    marker: PhantomData<T>,
+}
+
+impl<T> RingBuffer<T> {
+    pub const fn from_named_key(named_key: NamedKey) -> Self {
+        Self {
+            named_key,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn named_uref(&self) -> &NamedKey {
+        &self.named_key
+    }
+
+    /// Sets the capacity of a new buffer, whose dictionary must exist.
+    ///
+    /// Fails with [`ApiError::InvalidArgument`] for a capacity of zero, and with
+    /// [`UniversalError::AlreadyInitialized`] if the buffer already has a capacity.
+    pub fn init(&self, capacity: u64) -> Result<(), ApiError> {
+        if capacity == 0 {
+            return Err(ApiError::InvalidArgument);
+        }
+        if self.stored_capacity()?.is_some() {
+            return Err(UniversalError::AlreadyInitialized.into());
+        }
+        self.named_key
+            .put_dict(RING_BUFFER_CAPACITY_KEY, capacity)?;
+        self.named_key.put_dict(RING_BUFFER_HEAD_KEY, 0u64)?;
+        self.named_key.put_dict(RING_BUFFER_LENGTH_KEY, 0u64)
+    }
+
+    /// Returns the capacity set by [`init`](Self::init).
+    ///
+    /// Fails with [`ApiError::MissingKey`] if the buffer wasn't initialized.
+    pub fn capacity(&self) -> Result<u64, ApiError> {
+        self.stored_capacity()?.ok_or(ApiError::MissingKey)
+    }
+
+    /// Returns the number of retained values, at most the capacity.
+    pub fn len(&self) -> Result<u64, ApiError> {
+        Ok(self
+            .named_key
+            .get_dict(RING_BUFFER_LENGTH_KEY)?
+            .unwrap_or(0))
+    }
+
+    pub fn is_empty(&self) -> Result<bool, ApiError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns the logical index the next value will be pushed at, i.e. the number of values
+    /// pushed so far.
+    pub fn head(&self) -> Result<u64, ApiError> {
+        Ok(self.named_key.get_dict(RING_BUFFER_HEAD_KEY)?.unwrap_or(0))
+    }
+
+    /// Appends `value`, overwriting the oldest value if the buffer is full, and returns its
+    /// logical index.
+    pub fn push(&self, value: T) -> Result<u64, ApiError>
+    where
+        T: ToBytes + CLTyped,
+    {
+        let capacity = self.capacity()?;
+        let head = self.head()?;
+        self.named_key.put_dict(slot_key(head % capacity), value)?;
+
+        let next_head = head.checked_add(1).ok_or(ApiError::Unhandled)?;
+        self.named_key.put_dict(RING_BUFFER_HEAD_KEY, next_head)?;
+        let length = self.len()?;
+        if length < capacity {
+            self.named_key
+                .put_dict(RING_BUFFER_LENGTH_KEY, length + 1)?;
+        }
+        Ok(head)
+    }
+
+    /// Returns the value pushed at `logical_index`, or `None` if it was evicted or not pushed yet.
+    pub fn get(&self, logical_index: u64) -> Result<Option<T>, ApiError>
+    where
+        T: FromBytes + CLTyped,
+    {
+        let head = self.head()?;
+        let oldest = head - self.len()?;
+        if !(oldest..head).contains(&logical_index) {
+            return Ok(None);
+        }
+        self.named_key
+            .get_dict(slot_key(logical_index % self.capacity()?))
+    }
+
+    /// Returns the last value pushed, if any.
+    pub fn latest(&self) -> Result<Option<T>, ApiError>
+    where
+        T: FromBytes + CLTyped,
+    {
+        match self.head()?.checked_sub(1) {
+            Some(logical_index) => self.get(logical_index),
+            None => Ok(None),
+        }
+    }
+
+    fn stored_capacity(&self) -> Result<Option<u64>, ApiError> {
+        self.named_key.get_dict(RING_BUFFER_CAPACITY_KEY)
+    }
+}
+
+unsafe impl<T: Sync> Sync for RingBuffer<T> {}
+
+/// Returns the dictionary item key of `slot`.
+fn slot_key(slot: u64) -> alloc::string::String {
+    keyspace::vector_item_key(slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+
+    use super::*;
+    use crate::utils;
+
+    fn buffer(name: &'static str, capacity: u64) -> RingBuffer<u64> {
+        let buffer = RingBuffer::from_named_key(NamedKey::from_name(name));
+        buffer
+            .named_uref()
+            .get_or_init(utils::new_dictionary_key)
+            .unwrap();
+        buffer.init(capacity).unwrap();
+        buffer
+    }
+
+    /// Returns the retained values, oldest first.
+    fn retained(buffer: &RingBuffer<u64>) -> Vec<u64> {
+        let head = buffer.head().unwrap();
+        (head - buffer.len().unwrap()..head)
+            .map(|index| buffer.get(index).unwrap().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn pushes_fill_up_to_the_capacity() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let buffer = buffer("history", 3);
+            assert_eq!(buffer.capacity(), Ok(3));
+            assert_eq!(buffer.is_empty(), Ok(true));
+            assert_eq!(buffer.latest(), Ok(None));
+            assert_eq!(buffer.get(0), Ok(None));
+
+            for value in [10, 20, 30] {
+                buffer.push(value).unwrap();
+            }
+            assert_eq!(buffer.len(), Ok(3));
+            assert_eq!(retained(&buffer), [10, 20, 30]);
+            assert_eq!(buffer.latest(), Ok(Some(30)));
+            assert_eq!(buffer.get(3), Ok(None));
+
+            // The first push past the capacity evicts the oldest value only.
+            assert_eq!(buffer.push(40), Ok(3));
+            assert_eq!(buffer.len(), Ok(3));
+            assert_eq!(buffer.get(0), Ok(None));
+            assert_eq!(retained(&buffer), [20, 30, 40]);
+        });
+    }
+
+    #[test]
+    fn wrapping_around_twice_keeps_the_last_values() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let buffer = buffer("log", 3);
+            for value in 0..8 {
+                assert_eq!(buffer.push(value * 10), Ok(value));
+            }
+            assert_eq!(buffer.head(), Ok(8));
+            assert_eq!(buffer.len(), Ok(3));
+            assert_eq!(retained(&buffer), [50, 60, 70]);
+            assert_eq!(buffer.latest(), Ok(Some(70)));
+            for evicted in 0..5 {
+                assert_eq!(buffer.get(evicted), Ok(None));
+            }
+            assert_eq!(buffer.get(8), Ok(None));
+            assert_eq!(buffer.get(u64::MAX), Ok(None));
+        });
+    }
+
+    #[test]
+    fn a_capacity_of_one_keeps_the_latest_value() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let buffer = buffer("last", 1);
+            assert_eq!(buffer.push(1), Ok(0));
+            assert_eq!(buffer.push(2), Ok(1));
+            assert_eq!(buffer.len(), Ok(1));
+            assert_eq!(buffer.get(0), Ok(None));
+            assert_eq!(buffer.get(1), Ok(Some(2)));
+            assert_eq!(buffer.latest(), Ok(Some(2)));
+        });
+    }
+
+    #[test]
+    fn the_capacity_is_set_once() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let buffer: RingBuffer<u64> = RingBuffer::from_named_key(NamedKey::from_name("ring"));
+            buffer
+                .named_uref()
+                .get_or_init(utils::new_dictionary_key)
+                .unwrap();
+            assert_eq!(buffer.capacity(), Err(ApiError::MissingKey));
+            assert_eq!(buffer.push(1), Err(ApiError::MissingKey));
+            assert_eq!(buffer.init(0), Err(ApiError::InvalidArgument));
+
+            buffer.init(2).unwrap();
+            assert_eq!(
+                buffer.init(5),
+                Err(UniversalError::AlreadyInitialized.into())
+            );
+            assert_eq!(buffer.capacity(), Ok(2));
+        });
+    }
+}
@@ -21,6 +21,10 @@ impl<K> Set<K> {
         }
     }
 
+    pub fn named_uref(&self) -> &NamedKey {
+        self.mapping.named_uref()
+    }
+
     pub fn insert<'a>(&self, key: &'a K) -> Result<(), ApiError>
     where
         K: DictionaryKey<'a>,
@@ -1,15 +1,17 @@
 use core::marker::PhantomData;
 
-use super::base128;
 use crate::{
     casper_types::{
         ApiError, CLTyped,
         bytesrepr::{FromBytes, ToBytes},
     },
+    collections::page::{self, Page},
+    keyspace,
     named_key::NamedKey,
 };
 
-const VEC_LENGTH_KEY: &str = "length";
+/// Dictionary item key under which the length of a [`Vector`] is stored.
+pub const VEC_LENGTH_KEY: &str = "length";
 
 /// A vector collection that stores elements of type `T` in a sequential manner.
 pub struct Vector<T> {
@@ -25,13 +27,17 @@ impl<T> Vector<T> {
         }
     }
 
+    pub fn named_uref(&self) -> &NamedKey {
+        &self.named_key
+    }
+
     pub fn push(&self, value: T) -> Result<(), ApiError>
     where
         T: ToBytes + CLTyped,
     {
         let length: u64 = self.len()?;
 
-        let key = base128::encode_bytes(&length.to_le_bytes());
+        let key = keyspace::vector_item_key(length);
         self.named_key.put_dict(&key, value)?;
 
         // Update length
@@ -57,16 +63,26 @@ impl<T> Vector<T> {
     where
         T: FromBytes + CLTyped,
     {
-        let key = base128::encode_bytes(&index.to_le_bytes());
+        let key = keyspace::vector_item_key(index);
         let value: Option<T> = self.named_key.get_dict(&key)?;
         Ok(value)
     }
 
+    /// Returns the up to `limit` elements from index `cursor`, see [`page::page`].
+    pub fn page(&self, cursor: Option<u64>, limit: u64) -> Result<Page<T>, ApiError>
+    where
+        T: FromBytes + CLTyped,
+    {
+        page::page(self.len()?, cursor, limit, |index| {
+            self.get(index)?.ok_or(ApiError::MissingKey)
+        })
+    }
+
     pub fn set(&self, index: u64, value: T) -> Result<(), ApiError>
     where
         T: ToBytes + CLTyped,
     {
-        let key = base128::encode_bytes(&index.to_le_bytes());
+        let key = keyspace::vector_item_key(index);
         self.named_key.put_dict(&key, value)?;
         Ok(())
     }
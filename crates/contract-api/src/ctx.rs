@@ -0,0 +1,169 @@
+//! Static context for reverts, without the formatting machinery of `expect`.
+//!
+//! `expect("...")` keeps its message in the wasm along with the `core::fmt` code rendering the
+//! panic. A [`StaticContext`] is a `&'static str`, optionally with the error to revert with, and
+//! [`UnwrapOrRevertCtx::unwrap_or_revert_ctx`] reverts like
+//! [`UnwrapOrRevert`](crate::casper_contract::unwrap_or_revert::UnwrapOrRevert), printing the
+//! context only when `enable_casper_log` is set. Without it, the context doesn't keep the message,
+//! so the default build carries neither the string nor any formatting:
+//!
+//! ```ignore
+//! const NAME_UNSET: StaticContext = StaticContext::new("the name should be initialized")
+//!     .with_user_error(Cep18Error::FailedToReadFromStorage as u16);
+//!
+//! let name = NAME_KEY.read()?.unwrap_or_revert_ctx(NAME_UNSET);
+//! ```
+use casper_types::ApiError;
+
+use crate::utils;
+
+/// A static message describing what failed, and optionally the error to revert with.
+///
+/// The message is only kept with `enable_casper_log`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticContext {
+    #[cfg(enable_casper_log)]
+    message: &'static str,
+    error: Option<ApiError>,
+}
+
+impl StaticContext {
+    /// Creates a context reverting with the error of the unwrapped value.
+    #[cfg_attr(not(enable_casper_log), allow(unused_variables))]
+    pub const fn new(message: &'static str) -> Self {
+        Self {
+            #[cfg(enable_casper_log)]
+            message,
+            error: None,
+        }
+    }
+
+    /// Reverts with `error` instead of the error of the unwrapped value.
+    pub const fn with_error(mut self, error: ApiError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Reverts with `ApiError::User(code)`, e.g. `Cep18Error::InvalidContext as u16`, instead of
+    /// the error of the unwrapped value.
+    pub const fn with_user_error(self, code: u16) -> Self {
+        self.with_error(ApiError::User(code))
+    }
+
+    /// Returns the message printed on revert, `None` without `enable_casper_log`.
+    pub const fn message(&self) -> Option<&'static str> {
+        #[cfg(enable_casper_log)]
+        return Some(self.message);
+        #[cfg(not(enable_casper_log))]
+        None
+    }
+
+    /// Returns the error the context reverts with, if it overrides that of the unwrapped value.
+    pub const fn error(&self) -> Option<ApiError> {
+        self.error
+    }
+
+    /// Prints the message when `enable_casper_log` is set, and reverts with the error of the
+    /// context or else `error`.
+    fn revert(self, error: ApiError) -> ! {
+        #[cfg(enable_casper_log)]
+        crate::utils::print(self.message);
+        utils::revert(self.error.unwrap_or(error))
+    }
+}
+
+/// Unwrapping a `Result` or `Option`, reverting with a [`StaticContext`] on failure.
+pub trait UnwrapOrRevertCtx<T> {
+    /// Unwraps the value, or reverts with the error of `ctx` or else that of the value: the `Err`
+    /// of a `Result` and `ApiError::None` for an `Option`, like `unwrap_or_revert`.
+    fn unwrap_or_revert_ctx(self, ctx: StaticContext) -> T;
+}
+
+impl<T, E: Into<ApiError>> UnwrapOrRevertCtx<T> for Result<T, E> {
+    fn unwrap_or_revert_ctx(self, ctx: StaticContext) -> T {
+        match self {
+            Ok(value) => value,
+            Err(error) => ctx.revert(error.into()),
+        }
+    }
+}
+
+impl<T> UnwrapOrRevertCtx<T> for Option<T> {
+    fn unwrap_or_revert_ctx(self, ctx: StaticContext) -> T {
+        match self {
+            Some(value) => value,
+            None => ctx.revert(ApiError::None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use veles_casper_ffi_shim::{EnvBuilder, check_revert, clear_revert, dispatch_with};
+
+    use super::*;
+
+    const MISSING_NAME: StaticContext = StaticContext::new("the name should be initialized");
+
+    /// Returns the error `f` reverted with.
+    fn reverted_with(f: impl FnOnce()) -> ApiError {
+        clear_revert();
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            dispatch_with(EnvBuilder::new().build(), |_env| f());
+        }));
+        assert!(outcome.is_err());
+        check_revert().expect("should revert").api_error
+    }
+
+    #[test]
+    fn contexts_are_const_and_keep_their_message() {
+        const OVERRIDDEN: StaticContext = MISSING_NAME.with_user_error(7);
+        #[cfg(enable_casper_log)]
+        assert_eq!(
+            MISSING_NAME.message(),
+            Some("the name should be initialized")
+        );
+        #[cfg(not(enable_casper_log))]
+        assert_eq!(MISSING_NAME.message(), None);
+        assert_eq!(MISSING_NAME.error(), None);
+        assert_eq!(OVERRIDDEN.message(), MISSING_NAME.message());
+        assert_eq!(OVERRIDDEN.error(), Some(ApiError::User(7)));
+    }
+
+    #[test]
+    fn unwrapping_returns_the_value() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            assert_eq!(Ok::<_, ApiError>(1).unwrap_or_revert_ctx(MISSING_NAME), 1);
+            assert_eq!(Some(2).unwrap_or_revert_ctx(MISSING_NAME), 2);
+        });
+    }
+
+    #[test]
+    fn reverts_with_the_error_of_the_context_or_the_value() {
+        assert_eq!(
+            reverted_with(|| {
+                Err::<(), _>(ApiError::MissingKey).unwrap_or_revert_ctx(MISSING_NAME);
+            }),
+            ApiError::MissingKey
+        );
+        assert_eq!(
+            reverted_with(|| {
+                None::<()>.unwrap_or_revert_ctx(MISSING_NAME);
+            }),
+            ApiError::None
+        );
+        assert_eq!(
+            reverted_with(|| {
+                Err::<(), _>(ApiError::MissingKey)
+                    .unwrap_or_revert_ctx(MISSING_NAME.with_error(ApiError::InvalidArgument));
+            }),
+            ApiError::InvalidArgument
+        );
+        assert_eq!(
+            reverted_with(|| {
+                None::<()>.unwrap_or_revert_ctx(MISSING_NAME.with_user_error(60027));
+            }),
+            ApiError::User(60027)
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Diffing the entry points of two contract versions, to check an upgrade keeps the interface.
+//!
+//! [`diff`] compares the installed entry points with those of the new version, usually the
+//! `contract::entry_points()` generated by `#[casper(contract)]`:
+//!
+//! ```
+//! use veles_casper_contract_api::{
+//!     casper_types::{
+//!         CLType, EntityEntryPoint, EntryPointAccess, EntryPointPayment, EntryPointType,
+//!         EntryPoints, Parameter,
+//!     },
+//!     entry_points_diff,
+//! };
+//!
+//! let entry_point = |args| {
+//!     EntityEntryPoint::new(
+//!         "pay",
+//!         args,
+//!         CLType::Unit,
+//!         EntryPointAccess::Public,
+//!         EntryPointType::Called,
+//!         EntryPointPayment::Caller,
+//!     )
+//! };
+//! let old = EntryPoints::from(vec![entry_point(vec![Parameter::new("amount", CLType::U64)])]);
+//! let new = EntryPoints::from(vec![entry_point(vec![
+//!     Parameter::new("amount", CLType::U64),
+//!     Parameter::new("memo", CLType::Option(Box::new(CLType::String))),
+//! ])]);
+//!
+//! let diff = entry_points_diff::diff(&old, &new);
+//! assert_eq!(diff.changed.len(), 1);
+//! assert!(diff.is_backwards_compatible());
+//! ```
+use std::collections::BTreeMap;
+
+use casper_types::{CLType, EntityEntryPoint, EntryPointAccess, EntryPoints, Parameter};
+
+/// The differences between two sets of entry points, sorted by entry point name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryPointsDiff {
+    /// Entry points only the new version has.
+    pub added: Vec<String>,
+    /// Entry points only the old version has.
+    pub removed: Vec<String>,
+    /// Entry points both versions have, with different parameters, return types or access.
+    pub changed: Vec<EntryPointChange>,
+}
+
+impl EntryPointsDiff {
+    /// Returns `true` if the interface is unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns `true` if callers of the old entry points keep working with the new ones.
+    ///
+    /// Adding entry points is compatible, removing one is not. Changed entry points must be
+    /// compatible according to [`EntryPointChange::is_backwards_compatible`].
+    pub fn is_backwards_compatible(&self) -> bool {
+        self.removed.is_empty()
+            && self
+                .changed
+                .iter()
+                .all(EntryPointChange::is_backwards_compatible)
+    }
+}
+
+/// How an entry point present in both versions changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPointChange {
+    pub name: String,
+    /// Parameter changes, sorted by parameter name.
+    pub parameters: Vec<ParameterChange>,
+    /// The old and new return types, if they differ.
+    pub ret: Option<(CLType, CLType)>,
+    /// The old and new access, if they differ.
+    pub access: Option<(EntryPointAccess, EntryPointAccess)>,
+}
+
+impl EntryPointChange {
+    /// Returns `true` if callers of the old entry point keep working with the new one.
+    ///
+    /// - Each parameter change must be compatible, see [`ParameterChange::is_backwards_compatible`].
+    /// - The return type may only change from `Unit`, whose value callers can't rely on.
+    /// - The access may only change to `Public`.
+    pub fn is_backwards_compatible(&self) -> bool {
+        let ret_compatible = match &self.ret {
+            None => true,
+            Some((old, _new)) => *old == CLType::Unit,
+        };
+        let access_compatible = match &self.access {
+            None => true,
+            Some((_old, new)) => *new == EntryPointAccess::Public,
+        };
+        ret_compatible
+            && access_compatible
+            && self
+                .parameters
+                .iter()
+                .all(ParameterChange::is_backwards_compatible)
+    }
+}
+
+/// A parameter change of an [`EntryPointChange`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterChange {
+    /// The new version takes a parameter the old one doesn't.
+    Added { name: String, cl_type: CLType },
+    /// The new version no longer takes the parameter.
+    Removed { name: String, cl_type: CLType },
+    /// The parameter type changed.
+    TypeChanged {
+        name: String,
+        old: CLType,
+        new: CLType,
+    },
+}
+
+impl ParameterChange {
+    /// Returns `true` if callers of the old entry point are unaffected by the change.
+    ///
+    /// Added parameters must be `Option`s, which callers omit to pass `None`. Removed parameters
+    /// are compatible since the engine ignores arguments an entry point doesn't read. Any type
+    /// change is breaking, the arguments of old callers would no longer deserialize.
+    pub fn is_backwards_compatible(&self) -> bool {
+        match self {
+            ParameterChange::Added { cl_type, .. } => matches!(cl_type, CLType::Option(_)),
+            ParameterChange::Removed { .. } => true,
+            ParameterChange::TypeChanged { .. } => false,
+        }
+    }
+}
+
+/// Compares the `old` entry points of a contract with the `new` ones.
+pub fn diff(old: &EntryPoints, new: &EntryPoints) -> EntryPointsDiff {
+    let old = by_name(old);
+    let new = by_name(new);
+
+    let mut result = EntryPointsDiff::default();
+    for (name, old_entry_point) in &old {
+        match new.get(name) {
+            None => result.removed.push(name.to_string()),
+            Some(new_entry_point) => {
+                if let Some(change) = diff_entry_point(old_entry_point, new_entry_point) {
+                    result.changed.push(change);
+                }
+            }
+        }
+    }
+    result.added = new
+        .keys()
+        .filter(|name| !old.contains_key(*name))
+        .map(|name| name.to_string())
+        .collect();
+    result
+}
+
+fn by_name(entry_points: &EntryPoints) -> BTreeMap<&str, &EntityEntryPoint> {
+    entry_points
+        .keys()
+        .filter_map(|name| entry_points.get(name))
+        .map(|entry_point| (entry_point.name(), entry_point))
+        .collect()
+}
+
+fn diff_entry_point(old: &EntityEntryPoint, new: &EntityEntryPoint) -> Option<EntryPointChange> {
+    let parameters = diff_parameters(old.args(), new.args());
+    let ret = (old.ret() != new.ret()).then(|| (old.ret().clone(), new.ret().clone()));
+    let access =
+        (old.access() != new.access()).then(|| (old.access().clone(), new.access().clone()));
+    if parameters.is_empty() && ret.is_none() && access.is_none() {
+        return None;
+    }
+    Some(EntryPointChange {
+        name: old.name().to_string(),
+        parameters,
+        ret,
+        access,
+    })
+}
+
+fn diff_parameters(old: &[Parameter], new: &[Parameter]) -> Vec<ParameterChange> {
+    let types = |parameters: &[Parameter]| -> BTreeMap<String, CLType> {
+        parameters
+            .iter()
+            .map(|parameter| (parameter.name().to_string(), parameter.cl_type().clone()))
+            .collect()
+    };
+    let old = types(old);
+    let mut new = types(new);
+
+    let mut changes = Vec::new();
+    for (name, old_type) in old {
+        match new.remove(&name) {
+            None => changes.push(ParameterChange::Removed {
+                name,
+                cl_type: old_type,
+            }),
+            Some(new_type) if new_type != old_type => changes.push(ParameterChange::TypeChanged {
+                name,
+                old: old_type,
+                new: new_type,
+            }),
+            Some(_) => {}
+        }
+    }
+    changes.extend(
+        new.into_iter()
+            .map(|(name, cl_type)| ParameterChange::Added { name, cl_type }),
+    );
+    changes.sort_by(|a, b| parameter_name(a).cmp(parameter_name(b)));
+    changes
+}
+
+fn parameter_name(change: &ParameterChange) -> &str {
+    match change {
+        ParameterChange::Added { name, .. }
+        | ParameterChange::Removed { name, .. }
+        | ParameterChange::TypeChanged { name, .. } => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{EntryPointPayment, EntryPointType};
+
+    use super::*;
+
+    fn entry_point(name: &str, args: &[(&str, CLType)], ret: CLType) -> EntityEntryPoint {
+        EntityEntryPoint::new(
+            name,
+            args.iter()
+                .map(|(name, cl_type)| Parameter::new(*name, cl_type.clone()))
+                .collect(),
+            ret,
+            EntryPointAccess::Public,
+            EntryPointType::Called,
+            EntryPointPayment::Caller,
+        )
+    }
+
+    fn entry_points(entry_points: Vec<EntityEntryPoint>) -> EntryPoints {
+        entry_points.into()
+    }
+
+    fn token() -> Vec<EntityEntryPoint> {
+        vec![
+            entry_point(
+                "transfer",
+                &[("recipient", CLType::Key), ("amount", CLType::U256)],
+                CLType::Unit,
+            ),
+            entry_point("balance_of", &[("address", CLType::Key)], CLType::U256),
+        ]
+    }
+
+    fn optional(cl_type: CLType) -> CLType {
+        CLType::Option(Box::new(cl_type))
+    }
+
+    #[test]
+    fn identical_entry_points_have_no_diff() {
+        let result = diff(&entry_points(token()), &entry_points(token()));
+        assert!(result.is_empty());
+        assert!(result.is_backwards_compatible());
+    }
+
+    #[test]
+    fn added_and_removed_entry_points() {
+        let mut new = token();
+        new.retain(|entry_point| entry_point.name() != "balance_of");
+        new.push(entry_point(
+            "burn",
+            &[("amount", CLType::U256)],
+            CLType::Unit,
+        ));
+
+        let result = diff(&entry_points(token()), &entry_points(new.clone()));
+        assert_eq!(result.added, ["burn"]);
+        assert_eq!(result.removed, ["balance_of"]);
+        assert!(result.changed.is_empty());
+        assert!(!result.is_backwards_compatible());
+
+        let mut new = token();
+        new.push(entry_point(
+            "burn",
+            &[("amount", CLType::U256)],
+            CLType::Unit,
+        ));
+        let result = diff(&entry_points(token()), &entry_points(new));
+        assert_eq!(result.added, ["burn"]);
+        assert!(result.is_backwards_compatible());
+    }
+
+    #[test]
+    fn parameter_changes() {
+        let new = vec![
+            entry_point(
+                "transfer",
+                &[
+                    ("amount", CLType::U512),
+                    ("memo", optional(CLType::String)),
+                    ("to", CLType::Key),
+                ],
+                CLType::Unit,
+            ),
+            entry_point("balance_of", &[("address", CLType::Key)], CLType::U256),
+        ];
+        let result = diff(&entry_points(token()), &entry_points(new));
+        assert_eq!(
+            result.changed,
+            [EntryPointChange {
+                name: "transfer".into(),
+                parameters: vec![
+                    ParameterChange::TypeChanged {
+                        name: "amount".into(),
+                        old: CLType::U256,
+                        new: CLType::U512,
+                    },
+                    ParameterChange::Added {
+                        name: "memo".into(),
+                        cl_type: optional(CLType::String),
+                    },
+                    ParameterChange::Removed {
+                        name: "recipient".into(),
+                        cl_type: CLType::Key,
+                    },
+                    ParameterChange::Added {
+                        name: "to".into(),
+                        cl_type: CLType::Key,
+                    },
+                ],
+                ret: None,
+                access: None,
+            }]
+        );
+        let compatible = result.changed[0]
+            .parameters
+            .iter()
+            .map(ParameterChange::is_backwards_compatible)
+            .collect::<Vec<_>>();
+        assert_eq!(compatible, [false, true, true, false]);
+        assert!(!result.is_backwards_compatible());
+    }
+
+    #[test]
+    fn optional_parameters_and_removed_parameters_are_compatible() {
+        let new = vec![
+            entry_point(
+                "transfer",
+                &[
+                    ("recipient", CLType::Key),
+                    ("memo", optional(CLType::String)),
+                ],
+                CLType::Unit,
+            ),
+            entry_point("balance_of", &[("address", CLType::Key)], CLType::U256),
+        ];
+        let result = diff(&entry_points(token()), &entry_points(new));
+        assert_eq!(result.changed.len(), 1);
+        assert!(result.is_backwards_compatible());
+    }
+
+    #[test]
+    fn return_type_changes() {
+        let mut new = token();
+        new[0] = entry_point(
+            "transfer",
+            &[("recipient", CLType::Key), ("amount", CLType::U256)],
+            CLType::Bool,
+        );
+        let result = diff(&entry_points(token()), &entry_points(new));
+        assert_eq!(result.changed[0].ret, Some((CLType::Unit, CLType::Bool)));
+        assert!(result.is_backwards_compatible());
+
+        let mut new = token();
+        new[1] = entry_point("balance_of", &[("address", CLType::Key)], CLType::U64);
+        let result = diff(&entry_points(token()), &entry_points(new));
+        assert_eq!(result.changed[0].ret, Some((CLType::U256, CLType::U64)));
+        assert!(!result.is_backwards_compatible());
+    }
+
+    #[test]
+    fn access_changes() {
+        let restricted = |access| {
+            EntityEntryPoint::new(
+                "balance_of",
+                vec![Parameter::new("address", CLType::Key)],
+                CLType::U256,
+                access,
+                EntryPointType::Called,
+                EntryPointPayment::Caller,
+            )
+        };
+        let mut new = token();
+        new[1] = restricted(EntryPointAccess::Template);
+        let result = diff(&entry_points(token()), &entry_points(new.clone()));
+        assert_eq!(
+            result.changed[0].access,
+            Some((EntryPointAccess::Public, EntryPointAccess::Template))
+        );
+        assert!(!result.is_backwards_compatible());
+
+        let result = diff(&entry_points(new), &entry_points(token()));
+        assert!(result.is_backwards_compatible());
+    }
+}
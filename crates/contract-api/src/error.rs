@@ -1,13 +1,68 @@
 use casper_types::{ApiError, CLValueError};
 
+use crate::utils;
+
 const USER_BASE: u16 = 56900;
 
+/// Result type returned by contract entry points and helpers.
+pub type ContractResult<T> = Result<T, ApiError>;
+
+/// Extension methods reverting the execution on `Err`.
+///
+/// ```no_run
+/// use veles_casper_contract_api::prelude::*;
+///
+/// fn read_counter() -> ContractResult<u64> {
+///     Ok(42)
+/// }
+///
+/// let counter: u64 = read_counter().revert_on_err();
+/// let parsed: u64 = "42".parse::<u64>().or_revert_with(ApiError::User(1));
+/// ```
+pub trait ResultExt<T, E> {
+    /// Returns the `Ok` value, or reverts with the error.
+    fn revert_on_err(self) -> T
+    where
+        E: Into<ApiError>;
+
+    /// Returns the `Ok` value, or reverts with `error` discarding the original one.
+    fn or_revert_with(self, error: impl Into<ApiError>) -> T;
+}
+
+impl<T, E> ResultExt<T, E> for Result<T, E> {
+    fn revert_on_err(self) -> T
+    where
+        E: Into<ApiError>,
+    {
+        match self {
+            Ok(value) => value,
+            Err(error) => utils::revert(error),
+        }
+    }
+
+    fn or_revert_with(self, error: impl Into<ApiError>) -> T {
+        match self {
+            Ok(value) => value,
+            Err(_) => utils::revert(error),
+        }
+    }
+}
+
 #[repr(u16)]
 #[derive(Debug, PartialEq, Eq)]
 pub enum UniversalError {
     Panic = USER_BASE,
     InvalidContext,
     URefAlreadyInitialized,
+    /// The migrations passed to `migrations::run_migrations` are not ordered by version.
+    MigrationsOutOfOrder,
+    /// An `init_once` entry point was called again.
+    AlreadyInitialized,
+    /// An `only_installer` entry point was called by another caller than the installer.
+    CallerNotInstaller,
+    /// More than `collections::page::MAX_PAGE_LIMIT` elements were requested from
+    /// `collections::page::page`.
+    PageLimitExceeded,
     Other(ApiError) = 0,
 }
 
@@ -18,6 +73,21 @@ impl From<ApiError> for UniversalError {
 }
 
 impl UniversalError {
+    /// Returns the variant reverted with the given user error code, if the code is within the
+    /// range reserved for [`UniversalError`].
+    pub fn from_user_code(code: u16) -> Option<Self> {
+        match code.checked_sub(USER_BASE)? {
+            0 => Some(UniversalError::Panic),
+            1 => Some(UniversalError::InvalidContext),
+            2 => Some(UniversalError::URefAlreadyInitialized),
+            3 => Some(UniversalError::MigrationsOutOfOrder),
+            4 => Some(UniversalError::AlreadyInitialized),
+            5 => Some(UniversalError::CallerNotInstaller),
+            6 => Some(UniversalError::PageLimitExceeded),
+            _ => None,
+        }
+    }
+
     fn discriminant(&self) -> u16 {
         // SAFETY: Because `Self` is marked `repr(u8)`, its layout is a `repr(C)` `union`
         // between `repr(C)` structs, each of which has the `u8` discriminant as its first
@@ -46,14 +116,19 @@ impl From<UniversalError> for crate::casper_types::ApiError {
 
 #[cfg(test)]
 mod tests {
-    use super::UniversalError;
+    use super::{ContractResult, ResultExt, UniversalError};
     use casper_types::ApiError;
+    use veles_casper_ffi_shim::{EnvBuilder, check_revert, clear_revert, dispatch_with};
 
     #[test]
     fn test_discriminants() {
         assert_eq!(UniversalError::Panic.discriminant(), 56900);
         assert_eq!(UniversalError::InvalidContext.discriminant(), 56901);
         assert_eq!(UniversalError::URefAlreadyInitialized.discriminant(), 56902);
+        assert_eq!(UniversalError::MigrationsOutOfOrder.discriminant(), 56903);
+        assert_eq!(UniversalError::AlreadyInitialized.discriminant(), 56904);
+        assert_eq!(UniversalError::CallerNotInstaller.discriminant(), 56905);
+        assert_eq!(UniversalError::PageLimitExceeded.discriminant(), 56906);
         assert_eq!(
             UniversalError::Other(ApiError::User(12345)).discriminant(),
             0
@@ -66,4 +141,49 @@ mod tests {
         let api_error: ApiError = error.into();
         assert_eq!(api_error, ApiError::User(56901));
     }
+
+    #[test]
+    fn test_from_user_code() {
+        for error in [
+            UniversalError::Panic,
+            UniversalError::InvalidContext,
+            UniversalError::URefAlreadyInitialized,
+            UniversalError::MigrationsOutOfOrder,
+            UniversalError::AlreadyInitialized,
+            UniversalError::CallerNotInstaller,
+            UniversalError::PageLimitExceeded,
+        ] {
+            assert_eq!(
+                UniversalError::from_user_code(error.discriminant()),
+                Some(error)
+            );
+        }
+        assert_eq!(UniversalError::from_user_code(56899), None);
+        assert_eq!(UniversalError::from_user_code(56907), None);
+    }
+
+    #[test]
+    fn test_result_ext_returns_ok_value() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let result: ContractResult<u64> = Ok(42);
+            assert_eq!(result.revert_on_err(), 42);
+            let result: Result<u64, ()> = Ok(43);
+            assert_eq!(result.or_revert_with(ApiError::User(1)), 43);
+        });
+        assert!(check_revert().is_none());
+    }
+
+    #[test]
+    fn test_result_ext_reverts_on_err() {
+        clear_revert();
+        let outcome = std::panic::catch_unwind(|| {
+            dispatch_with(EnvBuilder::new().build(), |_env| {
+                let result: Result<u64, ()> = Err(());
+                result.or_revert_with(UniversalError::InvalidContext);
+            });
+        });
+        assert!(outcome.is_err());
+        let revert = check_revert().expect("should revert");
+        assert_eq!(revert.api_error, ApiError::User(56901));
+    }
 }
@@ -0,0 +1,179 @@
+//! Host-side transactions calling the entry points of a stored contract.
+//!
+//! With the `host_client` feature, `#[casper(contract)]` generates a
+//! `<entry_point>::transaction` function per exported function, building a ready-to-submit
+//! transaction from the same `Args` the on-chain `Client` uses, and a `HostClient` submitting
+//! them through a [`SubmitTransaction`] implementation such as the rust-sdk's `CasperClient`:
+//!
+//! ```ignore
+//! let host_client = contract::HostClient::new(&casper_client, contract_hash, "casper-net-1", &signer);
+//! host_client.with_payment_amount(5_000_000_000).delegate(amount).await?;
+//! ```
+use core::future::Future;
+
+use casper_client::cli::TransactionV1Builder;
+use casper_types::{
+    AddressableEntityHash, PricingMode, RuntimeArgs, SecretKey, TimeDiff, Timestamp, Transaction,
+    TransactionRuntimeParams, contracts::ContractHash, execution::ExecutionResult,
+};
+
+/// Payment and validity settings of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionOptions {
+    /// The amount of motes paid for the execution, i.e. the gas limit at a gas price of 1.
+    pub payment_amount: u64,
+    /// The maximum gas price the transaction accepts.
+    pub gas_price_tolerance: u8,
+    /// How long the transaction is valid for after its timestamp.
+    pub ttl: TimeDiff,
+    /// The timestamp of the transaction, the time it is built at when `None`.
+    pub timestamp: Option<Timestamp>,
+}
+
+impl SessionOptions {
+    /// The default payment of 2.5 CSPR.
+    pub const DEFAULT_PAYMENT_AMOUNT: u64 = 2_500_000_000;
+    /// The default gas price tolerance.
+    pub const DEFAULT_GAS_PRICE_TOLERANCE: u8 = 1;
+    /// The default time to live of 30 minutes.
+    pub const DEFAULT_TTL: TimeDiff = TimeDiff::from_seconds(30 * 60);
+
+    /// Sets the amount of motes paid for the execution.
+    pub fn with_payment_amount(mut self, payment_amount: u64) -> Self {
+        self.payment_amount = payment_amount;
+        self
+    }
+
+    /// Sets the maximum gas price the transaction accepts.
+    pub fn with_gas_price_tolerance(mut self, gas_price_tolerance: u8) -> Self {
+        self.gas_price_tolerance = gas_price_tolerance;
+        self
+    }
+
+    /// Sets how long the transaction is valid for.
+    pub fn with_ttl(mut self, ttl: TimeDiff) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the timestamp of the transaction, e.g. to build reproducible transactions.
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            payment_amount: Self::DEFAULT_PAYMENT_AMOUNT,
+            gas_price_tolerance: Self::DEFAULT_GAS_PRICE_TOLERANCE,
+            ttl: Self::DEFAULT_TTL,
+            timestamp: None,
+        }
+    }
+}
+
+/// Builds a signed transaction calling `entry_point` of the stored contract.
+///
+/// The contract is targeted by its hash and runs in the Casper 1.x VM, paid with the standard
+/// payment of `options`.
+pub fn stored_contract_transaction(
+    contract_hash: ContractHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+    options: &SessionOptions,
+    chain_name: &str,
+    signer: &SecretKey,
+) -> Transaction {
+    let builder = TransactionV1Builder::new_targeting_invocable_entity(
+        AddressableEntityHash::new(contract_hash.value()),
+        entry_point,
+        TransactionRuntimeParams::VmCasperV1,
+    )
+    .with_runtime_args(args)
+    .with_chain_name(chain_name)
+    .with_ttl(options.ttl)
+    .with_pricing_mode(PricingMode::PaymentLimited {
+        payment_amount: options.payment_amount,
+        gas_price_tolerance: options.gas_price_tolerance,
+        standard_payment: true,
+    })
+    .with_secret_key(signer);
+    let builder = match options.timestamp {
+        Some(timestamp) => builder.with_timestamp(timestamp),
+        None => builder,
+    };
+    Transaction::V1(
+        builder
+            .build()
+            .expect("should always build a transaction with a chain name and signer"),
+    )
+}
+
+/// Submits transactions to a network, e.g. the rust-sdk's `CasperClient`.
+pub trait SubmitTransaction {
+    type Error;
+
+    /// Submits `transaction` and waits until it is executed.
+    fn submit_and_wait(
+        &self,
+        transaction: Transaction,
+    ) -> impl Future<Output = Result<ExecutionResult, Self::Error>> + Send;
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{
+        TransactionEntryPoint, TransactionInvocationTarget, TransactionTarget, runtime_args,
+    };
+
+    use super::*;
+
+    #[test]
+    fn builds_a_stored_contract_call() {
+        let signer = SecretKey::ed25519_from_bytes([1; 32]).unwrap();
+        let options = SessionOptions::default()
+            .with_payment_amount(5_000_000_000)
+            .with_timestamp(Timestamp::from(1_000));
+        let transaction = stored_contract_transaction(
+            ContractHash::new([7; 32]),
+            "transfer",
+            runtime_args! { "amount" => 42u64 },
+            &options,
+            "casper-net-1",
+            &signer,
+        );
+        let Transaction::V1(transaction) = transaction else {
+            panic!("expected a V1 transaction");
+        };
+
+        assert_eq!(transaction.chain_name(), "casper-net-1");
+        assert_eq!(transaction.timestamp(), Timestamp::from(1_000));
+        assert_eq!(transaction.ttl(), SessionOptions::DEFAULT_TTL);
+        assert_eq!(
+            *transaction.pricing_mode(),
+            PricingMode::PaymentLimited {
+                payment_amount: 5_000_000_000,
+                gas_price_tolerance: 1,
+                standard_payment: true,
+            }
+        );
+        assert_eq!(
+            transaction
+                .deserialize_field::<TransactionTarget>(1)
+                .unwrap(),
+            TransactionTarget::Stored {
+                id: TransactionInvocationTarget::ByHash([7; 32]),
+                runtime: TransactionRuntimeParams::VmCasperV1,
+            }
+        );
+        assert_eq!(
+            transaction
+                .deserialize_field::<TransactionEntryPoint>(2)
+                .unwrap(),
+            TransactionEntryPoint::Custom(String::from("transfer"))
+        );
+        assert!(transaction.verify().is_ok());
+    }
+}
@@ -0,0 +1,131 @@
+//! Dictionary item keys of the kit's storage layouts, for off-chain indexers.
+//!
+//! Each function is the one the contract-side code calls to key its dictionaries, so a key
+//! computed here is the key a contract wrote:
+//!
+//! - [`Mapping`](crate::collections::mapping::Mapping), [`Set`](crate::collections::set::Set) and
+//!   [`IndexedSet`](crate::collections::indexed_set::IndexedSet) items are keyed by
+//!   [`collections_item_key`], as are CEP-18 balances and allowances of tokens installed with the
+//!   base128 key encoding (`collections_item_key(&owner)` and `collections_item_key(&(owner,
+//!   spender))`).
+//! - [`Vector`](crate::collections::vector::Vector) items, and the slots of a
+//!   [`RingBuffer`](crate::collections::ring_buffer::RingBuffer), are keyed by
+//!   [`vector_item_key`].
+//! - CEP-18 balances and allowances of tokens installed with the default base64 key encoding are
+//!   keyed by [`cep18_balance_item_key`] and [`cep18_allowance_item_key`].
+//!
+//! The functions don't call into the host. Combined with
+//! [`utils::dictionary_item_key`](crate::utils::dictionary_item_key), they give the global state
+//! key of an item:
+//!
+//! ```ignore
+//! let item_key = keyspace::cep18_balance_item_key(&owner);
+//! let key = utils::dictionary_item_key(&balances_uref, item_key.as_bytes());
+//! ```
+use alloc::string::String;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use casper_types::{Digest, Key, bytesrepr::ToBytes};
+
+use crate::{
+    casper_contract::unwrap_or_revert::UnwrapOrRevert,
+    collections::{base128, dictionary_key::DictionaryKey},
+};
+
+/// Returns the dictionary item key of `key` in a `Mapping`, `Set` or `IndexedSet`.
+pub fn collections_item_key<'a, K: DictionaryKey<'a> + ?Sized>(key: &'a K) -> String {
+    key.dictionary_key().into_owned()
+}
+
+/// Returns the dictionary item key of the item at `index` in a `Vector`.
+pub fn vector_item_key(index: u64) -> String {
+    base128::encode_bytes(&index.to_le_bytes())
+}
+
+/// Returns the dictionary item key of the balance of `owner` on a CEP-18 token: its serialized
+/// `Key`, base64 encoded.
+pub fn cep18_balance_item_key(owner: &Key) -> String {
+    // Dictionary item keys are limited to 64 characters, and the 33 bytes of account and hash
+    // keys encode to 44 characters, so no hashing is needed.
+    STANDARD.encode(owner.to_bytes().unwrap_or_revert())
+}
+
+/// Returns the dictionary item key of the allowance of `spender` from `owner` on a CEP-18 token:
+/// the hex encoded hash of both serialized `Key`s.
+pub fn cep18_allowance_item_key(owner: &Key, spender: &Key) -> String {
+    let mut preimage = owner.to_bytes().unwrap_or_revert();
+    spender.write_bytes(&mut preimage).unwrap_or_revert();
+    hex::encode(Digest::hash(preimage))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{collections::BTreeSet, vec::Vec};
+    use casper_types::{AccessRights, URef, account::AccountHash};
+    use veles_casper_ffi_shim::{Env, EnvBuilder, dispatch_with};
+
+    use super::*;
+    use crate::{
+        casper_contract::contract_api::storage,
+        collections::{
+            mapping::Mapping,
+            vector::{VEC_LENGTH_KEY, Vector},
+        },
+        named_key::NamedKey,
+    };
+
+    static ITEMS: Vector<u64> = Vector::from_named_key(NamedKey::from_name("items"));
+    static OWNERS: Mapping<(AccountHash, u64), bool> =
+        Mapping::from_named_key(NamedKey::from_name("owners"));
+
+    fn item_keys(env: &Env, dictionary: URef) -> BTreeSet<String> {
+        env.dictionary(dictionary).unwrap().into_keys().collect()
+    }
+
+    #[test]
+    fn vector_item_keys_match_the_stored_keys() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary("items").unwrap();
+            for value in [0, 1, 300, u64::from(u32::MAX) + 1] {
+                ITEMS.push(value).unwrap();
+            }
+
+            let mut expected: BTreeSet<String> = (0..4).map(vector_item_key).collect();
+            expected.insert(String::from(VEC_LENGTH_KEY));
+            assert_eq!(item_keys(env, dictionary), expected);
+        });
+    }
+
+    #[test]
+    fn collections_item_keys_match_the_stored_keys() {
+        let keys: Vec<(AccountHash, u64)> = (0..4)
+            .map(|index| (AccountHash::new([index; 32]), u64::from(index) << 40))
+            .collect();
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary("owners").unwrap();
+            for key in &keys {
+                OWNERS.insert(key, true).unwrap();
+            }
+
+            let expected: BTreeSet<String> = keys.iter().map(collections_item_key).collect();
+            assert_eq!(item_keys(env, dictionary), expected);
+        });
+    }
+
+    #[test]
+    fn cep18_item_keys_fit_in_dictionary_item_keys() {
+        let owner = Key::Account(AccountHash::new([1; 32]));
+        let spender = Key::URef(URef::new([2; 32], AccessRights::READ));
+        for key in [
+            cep18_balance_item_key(&owner),
+            cep18_balance_item_key(&spender),
+            cep18_allowance_item_key(&owner, &spender),
+        ] {
+            assert!(key.len() <= casper_types::DICTIONARY_ITEM_KEY_MAX_LENGTH);
+        }
+        assert_ne!(
+            cep18_allowance_item_key(&owner, &spender),
+            cep18_allowance_item_key(&spender, &owner)
+        );
+    }
+}
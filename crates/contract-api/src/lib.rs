@@ -24,9 +24,27 @@ pub use veles_casper_ffi_shim;
 #[cfg(feature = "wasm_allocator")]
 pub use lol_alloc;
 
+pub mod bounded;
+#[cfg(feature = "std")]
+pub mod clvalue_debug;
 pub mod collections;
+pub mod ctx;
+#[cfg(feature = "std")]
+pub mod entry_points_diff;
+#[cfg(all(feature = "host_client", not(target_arch = "wasm32")))]
+pub mod host_client;
+pub mod keyspace;
+pub mod limits;
 pub mod macro_support;
+pub mod math;
+pub mod migrations;
 pub mod named_key;
 pub mod prelude;
+pub mod sdk;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod state_spec;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod test_support;
 pub mod typed_uref;
 pub mod utils;
+pub mod versioning;
@@ -0,0 +1,169 @@
+//! Network limits that contracts are validated against.
+//!
+//! Unlike the 64-byte dictionary item key limit of `casper-types`, these limits are chainspec
+//! parameters that differ between networks. [`NetworkLimits::DEFAULT`] matches the Casper mainnet
+//! production chainspec. Host tools load the limits of another network from its chainspec, e.g.
+//! the one returned by `CasperClient::get_chainspec`:
+//!
+//! ```ignore
+//! let chainspec = client.get_chainspec().await?;
+//! let limits = NetworkLimits::from_chainspec_toml(&chainspec)?;
+//! ```
+//!
+//! `utils::emit_message` and `utils::put_key` debug-assert against [`NetworkLimits::DEFAULT`], so
+//! oversized messages and names fail in tests rather than on-chain.
+
+/// Limits of a network, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkLimits {
+    /// Maximum length of a named key name, `None` when the network sets no limit.
+    ///
+    /// The execution engine doesn't limit named key names, so no chainspec parameter exists and
+    /// [`NetworkLimits::from_chainspec_toml`] leaves the field as it is.
+    pub max_named_key_name_length: Option<u32>,
+    /// Maximum size of a serialized message payload (`wasm.messages_limits.max_message_size`).
+    pub max_message_size: u32,
+    /// Maximum length of a message topic name (`wasm.messages_limits.max_topic_name_size`).
+    pub max_topic_name_size: u32,
+    /// Maximum size of the serialized runtime args of a deploy's session
+    /// (`transactions.deploy.session_args_max_length`).
+    pub max_runtime_args_size: u32,
+}
+
+impl NetworkLimits {
+    /// The limits of the Casper mainnet production chainspec.
+    pub const DEFAULT: NetworkLimits = NetworkLimits {
+        max_named_key_name_length: None,
+        max_message_size: 1_024,
+        max_topic_name_size: 256,
+        max_runtime_args_size: 1_024,
+    };
+}
+
+impl Default for NetworkLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use loader::LimitsError;
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+mod loader {
+    use std::fmt;
+
+    use super::NetworkLimits;
+
+    /// An error loading [`NetworkLimits`] from a chainspec.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum LimitsError {
+        /// The chainspec entry at this dotted path is not an integer fitting in a `u32`.
+        InvalidEntry(String),
+    }
+
+    impl fmt::Display for LimitsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                LimitsError::InvalidEntry(path) => write!(f, "Invalid chainspec entry: {path}"),
+            }
+        }
+    }
+
+    impl std::error::Error for LimitsError {}
+
+    impl NetworkLimits {
+        /// Loads the limits of a chainspec, keeping [`NetworkLimits::DEFAULT`] for missing entries.
+        ///
+        /// Message limits are read from `[wasm.messages_limits]`, or `[wasm.v1.messages_limits]`.
+        pub fn from_chainspec_toml(chainspec: &toml::Value) -> Result<Self, LimitsError> {
+            let mut limits = Self::DEFAULT;
+            let messages_limits = ["wasm.messages_limits", "wasm.v1.messages_limits"]
+                .into_iter()
+                .find(|path| lookup(chainspec, path).is_some())
+                .unwrap_or("wasm.messages_limits");
+
+            for (path, field) in [
+                (
+                    format!("{messages_limits}.max_message_size"),
+                    &mut limits.max_message_size,
+                ),
+                (
+                    format!("{messages_limits}.max_topic_name_size"),
+                    &mut limits.max_topic_name_size,
+                ),
+                (
+                    String::from("transactions.deploy.session_args_max_length"),
+                    &mut limits.max_runtime_args_size,
+                ),
+            ] {
+                let Some(value) = lookup(chainspec, &path) else {
+                    continue;
+                };
+                *field = value
+                    .as_integer()
+                    .and_then(|value| u32::try_from(value).ok())
+                    .ok_or(LimitsError::InvalidEntry(path))?;
+            }
+            Ok(limits)
+        }
+    }
+
+    fn lookup<'a>(value: &'a toml::Value, path: &str) -> Option<&'a toml::Value> {
+        path.split('.')
+            .try_fold(value, |value, segment| value.as_table()?.get(segment))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const CHAINSPEC: &str = include_str!("../tests/fixtures/chainspec.toml");
+
+        fn parse(document: &str) -> toml::Value {
+            toml::from_str(document).unwrap()
+        }
+
+        #[test]
+        fn loads_limits_from_chainspec() {
+            let limits = NetworkLimits::from_chainspec_toml(&parse(CHAINSPEC)).unwrap();
+            assert_eq!(
+                limits,
+                NetworkLimits {
+                    max_named_key_name_length: None,
+                    max_message_size: 2_048,
+                    max_topic_name_size: 128,
+                    max_runtime_args_size: 4_096,
+                }
+            );
+        }
+
+        #[test]
+        fn missing_entries_keep_defaults() {
+            let chainspec = parse("[wasm.v1.messages_limits]\nmax_message_size = 4096\n");
+            let limits = NetworkLimits::from_chainspec_toml(&chainspec).unwrap();
+            assert_eq!(
+                limits,
+                NetworkLimits {
+                    max_message_size: 4_096,
+                    ..NetworkLimits::DEFAULT
+                }
+            );
+            assert_eq!(
+                NetworkLimits::from_chainspec_toml(&parse("")),
+                Ok(NetworkLimits::DEFAULT)
+            );
+        }
+
+        #[test]
+        fn rejects_invalid_entries() {
+            let chainspec = parse("[transactions.deploy]\nsession_args_max_length = -1\n");
+            assert_eq!(
+                NetworkLimits::from_chainspec_toml(&chainspec),
+                Err(LimitsError::InvalidEntry(String::from(
+                    "transactions.deploy.session_args_max_length"
+                )))
+            );
+        }
+    }
+}
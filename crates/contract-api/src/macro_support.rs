@@ -1,10 +1,132 @@
-use casper_types::{ApiError, RuntimeArgs, contract_messages::MessagePayload};
+use casper_types::{
+    ApiError, CLTyped, EntityAddr, Key, RuntimeArgs,
+    account::AccountHash,
+    bytesrepr::{FromBytes, ToBytes},
+    contract_messages::MessagePayload,
+    contracts::{ContractHash, ContractPackageHash, ContractVersion, NamedKeys},
+};
+
+use crate::{
+    casper_contract::unwrap_or_revert::UnwrapOrRevert,
+    error::{ResultExt, UniversalError},
+    utils::{self, CallerAction},
+};
 
 /// A trait for types that can be converted into runtime arguments.
 pub trait IntoRuntimeArgs {
+    /// Converts into runtime arguments, panicking when an argument can't be converted into a
+    /// `CLValue`.
     fn into_runtime_args(self) -> RuntimeArgs;
+
+    /// Converts into runtime arguments, returning the error of the first argument that can't be
+    /// converted into a `CLValue`.
+    fn try_into_runtime_args(self) -> Result<RuntimeArgs, ApiError>
+    where
+        Self: Sized,
+    {
+        Ok(self.into_runtime_args())
+    }
+}
+
+/// Seeds the args of a shim environment from the `Args` of a generated entry point module.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait WithTypedArgs {
+    /// Adds the runtime arguments `args` converts into, see
+    /// [`EnvBuilder::with_runtime_args`](veles_casper_ffi_shim::EnvBuilder::with_runtime_args).
+    fn with_typed_args<T: IntoRuntimeArgs>(self, args: T) -> Self;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WithTypedArgs for veles_casper_ffi_shim::EnvBuilder {
+    fn with_typed_args<T: IntoRuntimeArgs>(self, args: T) -> Self {
+        self.with_runtime_args(args.into_runtime_args())
+    }
+}
+
+/// Converts `args` into runtime arguments without panicking, see
+/// [`IntoRuntimeArgs::try_into_runtime_args`].
+pub fn try_into_runtime_args<T: IntoRuntimeArgs>(args: T) -> Result<RuntimeArgs, ApiError> {
+    args.try_into_runtime_args()
+}
+
+/// Calls `entry_point_name` of the contract and deserializes the returned value, reverting when
+/// the call fails.
+///
+/// Used by the generated `Client`. Like
+/// [`runtime::call_contract`](crate::casper_contract::contract_api::runtime::call_contract), but
+/// through [`utils::call_contract_typed`], whose host call the shim may unwind out of when the
+/// callee reverts.
+pub fn call_contract<T: CLTyped + FromBytes>(
+    contract_hash: ContractHash,
+    entry_point_name: &str,
+    runtime_args: RuntimeArgs,
+) -> T {
+    utils::call_contract_typed(contract_hash, entry_point_name, runtime_args).revert_on_err()
 }
 
+/// Calls `entry_point_name` of `contract_version` of the package, or of its latest enabled version
+/// with `None`, and deserializes the returned value, reverting when the call fails.
+///
+/// Used by the generated `PackageClient`, see [`call_contract`].
+pub fn call_versioned_contract<T: CLTyped + FromBytes>(
+    contract_package_hash: ContractPackageHash,
+    contract_version: Option<ContractVersion>,
+    entry_point_name: &str,
+    runtime_args: RuntimeArgs,
+) -> T {
+    utils::call_versioned_contract_typed(
+        contract_package_hash,
+        contract_version,
+        entry_point_name,
+        runtime_args,
+    )
+    .revert_on_err()
+}
+
+/// Calls `entry_point_name` of the contract for its effects, reverting when the call fails.
+///
+/// Used by the generated `Client` for entry points returning `()`. Unlike
+/// [`runtime::call_contract::<()>`](crate::casper_contract::contract_api::runtime::call_contract),
+/// the result is never deserialized, so the call succeeds whether the callee returned nothing, an
+/// explicit unit or, like contracts built with older kit versions, any other value. An empty host
+/// buffer is not read.
+pub fn call_contract_no_ret(
+    contract_hash: ContractHash,
+    entry_point_name: &str,
+    runtime_args: RuntimeArgs,
+) {
+    let runtime_args = runtime_args.into_bytes().revert_on_err();
+    utils::call_contract_with_serialized_args(contract_hash, entry_point_name, &runtime_args)
+        .revert_on_err();
+}
+
+/// Calls `entry_point_name` of `contract_version` of the package, or of its latest enabled version
+/// with `None`, for its effects, reverting when the call fails.
+///
+/// Used by the generated `PackageClient` for entry points returning `()`, see
+/// [`call_contract_no_ret`].
+pub fn call_versioned_contract_no_ret(
+    contract_package_hash: ContractPackageHash,
+    contract_version: Option<ContractVersion>,
+    entry_point_name: &str,
+    runtime_args: RuntimeArgs,
+) {
+    let runtime_args = runtime_args.into_bytes().revert_on_err();
+    utils::call_versioned_contract_with_serialized_args(
+        contract_package_hash,
+        contract_version,
+        entry_point_name,
+        &runtime_args,
+    )
+    .revert_on_err();
+}
+
+/// State of a required argument that was not set yet on a generated `ArgsBuilder`.
+pub struct Unset;
+
+/// State of a required argument that was set on a generated `ArgsBuilder`.
+pub struct Set<T>(pub T);
+
 /// A trait for types that can be converted into Casper messages.
 pub trait CasperMessage: Sized {
     const TOPIC_NAME: &'static str;
@@ -13,9 +135,170 @@ pub trait CasperMessage: Sized {
     fn into_message_payload(self) -> Result<MessagePayload, ApiError>;
 }
 
+/// Reports panics of wasm entry points as a [`Panic`](crate::error::UniversalError::Panic) revert.
+///
+/// Off wasm the host shim reverts by unwinding, so panics keep unwinding out of the entry point.
+/// With `std` the hook there records where and why the entry point panicked, with a backtrace
+/// when `RUST_BACKTRACE` is set, for [`take_panic_report`], then hands the panic on to the
+/// previous hook. The unwinding of shim reverts and returns isn't recorded.
 pub fn set_panic_hook() {
-    #[cfg(feature = "std")]
+    #[cfg(all(feature = "std", target_arch = "wasm32"))]
     std::panic::set_hook(alloc::boxed::Box::new(|_info| {
-        crate::casper_contract::contract_api::runtime::revert(crate::error::UniversalError::Panic);
+        crate::utils::revert(crate::error::UniversalError::Panic);
     }));
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    host_panics::install_recording_hook();
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use host_panics::{PanicReport, take_panic_report};
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+mod host_panics {
+    use std::{backtrace::Backtrace, cell::RefCell, fmt, panic, sync::Once};
+
+    use veles_casper_ffi_shim::{RetSignal, RevertError};
+
+    /// A panic of an entry point recorded by the hook of
+    /// [`set_panic_hook`](super::set_panic_hook) off wasm.
+    #[derive(Debug)]
+    pub struct PanicReport {
+        /// The panic message, or a placeholder for payloads that aren't strings.
+        pub message: String,
+        /// The `file:line:column` the panic came from.
+        pub location: String,
+        /// The backtrace of the panic, disabled unless `RUST_BACKTRACE` is set.
+        pub backtrace: Backtrace,
+    }
+
+    impl fmt::Display for PanicReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "panicked at {}: {}", self.location, self.message)
+        }
+    }
+
+    thread_local! {
+        static LAST_PANIC: RefCell<Option<PanicReport>> = const { RefCell::new(None) };
+    }
+
+    /// Takes the last panic recorded on this thread since the hook was installed.
+    pub fn take_panic_report() -> Option<PanicReport> {
+        LAST_PANIC.with(|last_panic| last_panic.borrow_mut().take())
+    }
+
+    pub(super) fn install_recording_hook() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let previous_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                let payload = info.payload();
+                if !payload.is::<RevertError>() && !payload.is::<RetSignal>() {
+                    let report = PanicReport {
+                        message: info.payload_as_str().unwrap_or("Box<dyn Any>").to_string(),
+                        location: info
+                            .location()
+                            .map_or_else(|| "an unknown location".to_string(), ToString::to_string),
+                        backtrace: Backtrace::capture(),
+                    };
+                    LAST_PANIC.with(|last_panic| *last_panic.borrow_mut() = Some(report));
+                }
+                previous_hook(info);
+            }));
+        });
+    }
+}
+
+/// Name of the named key holding the installer of a contract with `only_installer` entry points.
+pub const INSTALLER_KEY_NAME: &str = "__installer";
+
+/// Inserts the account installing the contract, i.e. the initiator of the installing session,
+/// into `named_keys` under [`INSTALLER_KEY_NAME`].
+///
+/// Called through the `insert_installer_key` function generated for contract modules with
+/// `#[casper(export, only_installer)]` entry points.
+pub fn insert_installer_key(named_keys: &mut NamedKeys) {
+    let initiator = utils::get_initiator_or_immediate(CallerAction::Initiator).unwrap_or_revert();
+    let installer = initiator
+        .get_field_by_index(0)
+        .and_then(|field| field.to_t::<Option<AccountHash>>().ok())
+        .flatten()
+        .unwrap_or_revert_with(UniversalError::InvalidContext);
+    named_keys.insert(INSTALLER_KEY_NAME.into(), Key::Account(installer));
+}
+
+/// Reverts with [`UniversalError::CallerNotInstaller`] unless the immediate caller is the
+/// installer stored under [`INSTALLER_KEY_NAME`].
+///
+/// Guards `#[casper(export, only_installer)]` entry points. Reverts with
+/// [`ApiError::MissingKey`] if the contract was installed without the key.
+pub fn ensure_installer() {
+    let installer = utils::get_key(INSTALLER_KEY_NAME)
+        .unwrap_or_revert()
+        .unwrap_or_revert_with(ApiError::MissingKey);
+    let caller = utils::get_immediate_entity_addr().unwrap_or_revert();
+    let is_installer = match (caller, installer) {
+        (Some(EntityAddr::Account(caller)), Key::Account(installer)) => caller == installer.value(),
+        _ => false,
+    };
+    if !is_installer {
+        utils::revert(UniversalError::CallerNotInstaller);
+    }
+}
+
+/// Reverts with [`UniversalError::AlreadyInitialized`] if the named key `init_done_key_name`
+/// exists, and creates it otherwise.
+///
+/// Guards `#[casper(export, init_once)]` entry points. The key is only kept when the entry point
+/// succeeds, as a revert discards it with every other write of the call.
+pub fn init_once(init_done_key_name: &str) {
+    if utils::get_key(init_done_key_name)
+        .unwrap_or_revert()
+        .is_some()
+    {
+        utils::revert(UniversalError::AlreadyInitialized);
+    }
+    let init_done = utils::new_uref_key(true).unwrap_or_revert();
+    utils::put_key(init_done_key_name, init_done).unwrap_or_revert();
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use veles_casper_ffi_shim::{EnvBuilder, clear_revert, dispatch_with};
+
+    use super::*;
+
+    #[test]
+    fn panic_hook_records_the_panics_of_entry_points() {
+        set_panic_hook();
+        let line = line!() + 2;
+        let outcome = std::panic::catch_unwind(|| {
+            panic!("balance of {} overflowed", "alice");
+        });
+        assert!(outcome.is_err());
+        let report = take_panic_report().expect("the panic should be recorded");
+        assert_eq!(report.message, "balance of alice overflowed");
+        assert!(
+            report
+                .location
+                .starts_with(&std::format!("{}:{line}:", file!()))
+        );
+        assert_eq!(
+            report.to_string(),
+            std::format!(
+                "panicked at {}: balance of alice overflowed",
+                report.location
+            )
+        );
+        assert!(take_panic_report().is_none());
+
+        // Reverts unwind too, but aren't panics of the entry point
+        let outcome = std::panic::catch_unwind(|| {
+            dispatch_with(EnvBuilder::new().build(), |_env| {
+                utils::revert(ApiError::User(1));
+            });
+        });
+        assert!(outcome.is_err());
+        clear_revert();
+        assert!(take_panic_report().is_none());
+    }
 }
@@ -0,0 +1,72 @@
+//! Checked arithmetic on stored amounts, failing with the caller's error.
+//!
+//! Balance math is mostly `checked_add(...).ok_or(...)` and `checked_sub(...).ok_or(...)`.
+//! [`CheckedStorageAdd::try_add_or`] and [`CheckedStorageSub::try_sub_or`] shorten it, and
+//! [`TypedURef::increase`](crate::typed_uref::TypedURef::increase) and
+//! [`Mapping::increase`](crate::collections::mapping::Mapping::increase) (and their `decrease`
+//! counterparts) read, update and write a stored amount in one call:
+//!
+//! ```ignore
+//! let new_balance = BALANCES.decrease(&owner, amount, Error::InsufficientBalance)?;
+//! TOTAL_SUPPLY.decrease(amount, Error::Underflow)?;
+//! ```
+use casper_types::{U256, U512};
+
+/// Addition failing with a given error on overflow.
+pub trait CheckedStorageAdd: Sized {
+    /// Returns `self + rhs`, or `err` if the sum overflows.
+    fn try_add_or<E>(self, rhs: Self, err: E) -> Result<Self, E>;
+}
+
+/// Subtraction failing with a given error on underflow.
+pub trait CheckedStorageSub: Sized {
+    /// Returns `self - rhs`, or `err` if `rhs` is greater than `self`.
+    fn try_sub_or<E>(self, rhs: Self, err: E) -> Result<Self, E>;
+}
+
+macro_rules! impl_checked_storage_ops {
+    ($($ty:ty),+) => {
+        $(
+            impl CheckedStorageAdd for $ty {
+                fn try_add_or<E>(self, rhs: Self, err: E) -> Result<Self, E> {
+                    self.checked_add(rhs).ok_or(err)
+                }
+            }
+
+            impl CheckedStorageSub for $ty {
+                fn try_sub_or<E>(self, rhs: Self, err: E) -> Result<Self, E> {
+                    self.checked_sub(rhs).ok_or(err)
+                }
+            }
+        )+
+    };
+}
+
+impl_checked_storage_ops!(u32, u64, U256, U512);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_ops_map_to_the_given_error() {
+        assert_eq!(
+            U256::from(2).try_add_or(U256::from(3), "overflow"),
+            Ok(U256::from(5))
+        );
+        assert_eq!(
+            U256::MAX.try_add_or(U256::one(), "overflow"),
+            Err("overflow")
+        );
+        assert_eq!(
+            U512::from(3).try_sub_or(U512::from(3), "underflow"),
+            Ok(U512::zero())
+        );
+        assert_eq!(
+            U512::zero().try_sub_or(U512::one(), "underflow"),
+            Err("underflow")
+        );
+        assert_eq!(u64::MAX.try_add_or(1, "overflow"), Err("overflow"));
+        assert_eq!(5u64.try_sub_or(6, "underflow"), Err("underflow"));
+    }
+}
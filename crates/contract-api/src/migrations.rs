@@ -0,0 +1,329 @@
+//! Storage migrations run by the upgrade entry point of a contract.
+//!
+//! A contract stores the version of its storage schema under a named key. Each [`Migration`]
+//! upgrades the storage to its [`Migration::version`], and [`run_migrations`] runs the ones newer
+//! than the stored version in order before recording the new version:
+//!
+//! ```ignore
+//! static SCHEMA_VERSION: NamedKey = NamedKey::from_name("schema_version");
+//! static SCHEMA_VERSION_TUREF: TypedURef<u32> = TypedURef::from_named_key(&SCHEMA_VERSION);
+//!
+//! struct AddPausedFlag;
+//!
+//! impl Migration for AddPausedFlag {
+//!     fn version(&self) -> u32 {
+//!         2
+//!     }
+//!
+//!     fn migrate(&self) -> Result<(), ApiError> {
+//!         migrate_value(&CONFIG_TUREF, |fee: u64| (fee, false))
+//!     }
+//! }
+//!
+//! run_migrations(&SCHEMA_VERSION_TUREF, &[&AddPausedFlag]).revert_on_err();
+//! ```
+//!
+//! Migrations are all-or-nothing as long as the upgrade reverts on their error: the revert
+//! discards every write of the execution, including those of the migrations that already ran,
+//! and the stored version is only written once all of them succeeded.
+use crate::{
+    casper_types::{
+        ApiError, CLTyped,
+        bytesrepr::{FromBytes, ToBytes},
+    },
+    collections::vector::Vector,
+    error::UniversalError,
+    typed_uref::TypedURef,
+    utils,
+};
+
+/// A step upgrading the storage of a contract to a schema version.
+pub trait Migration {
+    /// The schema version the storage is at once the migration ran.
+    fn version(&self) -> u32;
+
+    /// Rewrites the storage of the previous schema version.
+    fn migrate(&self) -> Result<(), ApiError>;
+}
+
+/// Runs the migrations newer than the version stored under `current_version`, returning the
+/// version the storage is at.
+///
+/// A missing version counts as version 0, and the URef is created and put into the named keys of
+/// the current context once there's a version to record. The migrations must be ordered by
+/// strictly increasing versions, otherwise none run and
+/// [`UniversalError::MigrationsOutOfOrder`] is returned.
+pub fn run_migrations(
+    current_version: &TypedURef<u32>,
+    migrations: &[&dyn Migration],
+) -> Result<u32, ApiError> {
+    if migrations
+        .windows(2)
+        .any(|pair| pair[0].version() >= pair[1].version())
+    {
+        return Err(UniversalError::MigrationsOutOfOrder.into());
+    }
+
+    let stored_version = current_version.read()?;
+    let mut version = stored_version.unwrap_or(0);
+    for migration in migrations {
+        if migration.version() > version {
+            migration.migrate()?;
+            version = migration.version();
+        }
+    }
+
+    match stored_version {
+        Some(stored_version) if stored_version == version => {}
+        Some(_) => current_version.write(version)?,
+        None => {
+            current_version
+                .named_uref()
+                .get_or_init(|| utils::new_uref_key(version))?
+                .put_to_named_keys()?;
+        }
+    }
+    Ok(version)
+}
+
+/// Re-encodes the value stored under `key` from an `Old` to a `New` value.
+///
+/// Returns [`ApiError::MissingKey`] if no value is stored under the key.
+pub fn migrate_value<Old, New>(
+    key: &TypedURef<New>,
+    migrate: impl FnOnce(Old) -> New,
+) -> Result<(), ApiError>
+where
+    Old: FromBytes,
+    New: ToBytes + CLTyped,
+{
+    let old: Old = key.named_uref().read()?.ok_or(ApiError::MissingKey)?;
+    key.write(migrate(old))
+}
+
+/// Re-encodes every element of `vector` from an `Old` to a `New` element, in index order.
+pub fn migrate_vector<Old, New>(
+    vector: &Vector<New>,
+    mut migrate: impl FnMut(Old) -> New,
+) -> Result<(), ApiError>
+where
+    Old: FromBytes + CLTyped,
+    New: ToBytes + CLTyped,
+{
+    let old_vector: Vector<Old> = Vector::from_named_key(vector.named_uref().clone());
+    for index in 0..vector.len()? {
+        let old = old_vector.get(index)?.ok_or(ApiError::MissingKey)?;
+        vector.set(index, migrate(old))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+
+    use casper_types::{CLValue, Key, StoredValue, URef};
+    use veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+
+    use super::*;
+    use crate::named_key::NamedKey;
+
+    const VERSION_UREF: URef = URef::new([1; 32], casper_types::AccessRights::READ_ADD_WRITE);
+    const CONFIG_UREF: URef = URef::new([2; 32], casper_types::AccessRights::READ_ADD_WRITE);
+
+    thread_local! {
+        static SCHEMA_VERSION: NamedKey = const { NamedKey::from_name("schema_version") };
+        static CONFIG: NamedKey = const { NamedKey::from_name("config") };
+    }
+
+    /// v2 adds a paused flag to the fee of v1.
+    struct AddPausedFlag;
+
+    impl Migration for AddPausedFlag {
+        fn version(&self) -> u32 {
+            2
+        }
+
+        fn migrate(&self) -> Result<(), ApiError> {
+            CONFIG.with(|config| {
+                migrate_value(&TypedURef::from_named_key(config), |fee: u64| (fee, false))
+            })
+        }
+    }
+
+    /// v3 widens the `u32` amounts of v2 to `u64`.
+    struct WidenAmounts<'a> {
+        amounts: &'a Vector<u64>,
+    }
+
+    impl Migration for WidenAmounts<'_> {
+        fn version(&self) -> u32 {
+            3
+        }
+
+        fn migrate(&self) -> Result<(), ApiError> {
+            migrate_vector(self.amounts, |amount: u32| u64::from(amount) * 1_000)
+        }
+    }
+
+    struct Counting<'a> {
+        version: u32,
+        runs: &'a Cell<u32>,
+    }
+
+    impl Migration for Counting<'_> {
+        fn version(&self) -> u32 {
+            self.version
+        }
+
+        fn migrate(&self) -> Result<(), ApiError> {
+            self.runs.set(self.runs.get() + 1);
+            Ok(())
+        }
+    }
+
+    fn v1_env() -> veles_casper_ffi_shim::Env {
+        EnvBuilder::new()
+            .with_named_key("schema_version", Key::URef(VERSION_UREF))
+            .with_storage(
+                Key::URef(VERSION_UREF),
+                StoredValue::CLValue(CLValue::from_t(1u32).unwrap()),
+            )
+            .with_named_key("config", Key::URef(CONFIG_UREF))
+            .with_storage(
+                Key::URef(CONFIG_UREF),
+                StoredValue::CLValue(CLValue::from_t(250u64).unwrap()),
+            )
+            .build()
+    }
+
+    #[test]
+    fn chained_migrations_upgrade_the_layout() {
+        dispatch_with(v1_env(), |_env| {
+            let amounts: Vector<u64> = Vector::from_named_key(NamedKey::from_name("amounts"));
+            amounts
+                .named_uref()
+                .get_or_init(utils::new_dictionary_key)
+                .unwrap();
+            let v1_amounts: Vector<u32> = Vector::from_named_key(amounts.named_uref().clone());
+            for amount in [1, 2, 3] {
+                v1_amounts.push(amount).unwrap();
+            }
+
+            let widen_amounts = WidenAmounts { amounts: &amounts };
+            SCHEMA_VERSION.with(|schema_version| {
+                let schema_version = TypedURef::from_named_key(schema_version);
+                assert_eq!(
+                    run_migrations(&schema_version, &[&AddPausedFlag, &widen_amounts]),
+                    Ok(3)
+                );
+                assert_eq!(schema_version.read(), Ok(Some(3)));
+            });
+
+            let config: Option<(u64, bool)> = CONFIG.with(|config| config.read()).unwrap();
+            assert_eq!(config, Some((250, false)));
+            let amounts: Vec<u64> = (0..amounts.len().unwrap())
+                .map(|index| amounts.get(index).unwrap().unwrap())
+                .collect();
+            assert_eq!(amounts, [1_000, 2_000, 3_000]);
+        });
+    }
+
+    #[test]
+    fn only_pending_migrations_run() {
+        dispatch_with(v1_env(), |_env| {
+            let runs = Cell::new(0);
+            let v1 = Counting {
+                version: 1,
+                runs: &runs,
+            };
+            let v2 = Counting {
+                version: 2,
+                runs: &runs,
+            };
+            SCHEMA_VERSION.with(|schema_version| {
+                let schema_version = TypedURef::from_named_key(schema_version);
+                assert_eq!(run_migrations(&schema_version, &[&v1, &v2]), Ok(2));
+                assert_eq!(runs.get(), 1);
+                assert_eq!(run_migrations(&schema_version, &[&v1, &v2]), Ok(2));
+                assert_eq!(runs.get(), 1);
+            });
+        });
+    }
+
+    #[test]
+    fn a_missing_version_is_created() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let runs = Cell::new(0);
+            let v1 = Counting {
+                version: 1,
+                runs: &runs,
+            };
+            let schema_version = NamedKey::from_name("schema_version");
+            assert_eq!(
+                run_migrations(&TypedURef::from_named_key(&schema_version), &[&v1]),
+                Ok(1)
+            );
+            assert_eq!(runs.get(), 1);
+            assert!(env.named_keys().contains_key("schema_version"));
+            assert_eq!(schema_version.read::<u32>(), Ok(Some(1)));
+        });
+    }
+
+    #[test]
+    fn misordered_migrations_do_not_run() {
+        dispatch_with(v1_env(), |_env| {
+            let runs = Cell::new(0);
+            let v3 = Counting {
+                version: 3,
+                runs: &runs,
+            };
+            let v2 = Counting {
+                version: 2,
+                runs: &runs,
+            };
+            SCHEMA_VERSION.with(|schema_version| {
+                let schema_version = TypedURef::from_named_key(schema_version);
+                assert_eq!(
+                    run_migrations(&schema_version, &[&v3, &v2]),
+                    Err(UniversalError::MigrationsOutOfOrder.into())
+                );
+                assert_eq!(schema_version.read(), Ok(Some(1)));
+            });
+            assert_eq!(runs.get(), 0);
+        });
+    }
+
+    struct Failing;
+
+    impl Migration for Failing {
+        fn version(&self) -> u32 {
+            3
+        }
+
+        fn migrate(&self) -> Result<(), ApiError> {
+            Err(ApiError::User(7))
+        }
+    }
+
+    #[test]
+    fn a_failing_migration_keeps_the_stored_version() {
+        dispatch_with(v1_env(), |_env| {
+            SCHEMA_VERSION.with(|schema_version| {
+                let schema_version = TypedURef::from_named_key(schema_version);
+                assert_eq!(
+                    run_migrations(&schema_version, &[&AddPausedFlag, &Failing]),
+                    Err(ApiError::User(7))
+                );
+                assert_eq!(schema_version.read(), Ok(Some(1)));
+            });
+
+            let missing = NamedKey::from_name("missing");
+            assert_eq!(
+                migrate_value(&TypedURef::from_named_key(&missing), |fee: u64| fee),
+                Err(ApiError::MissingKey)
+            );
+        });
+    }
+}
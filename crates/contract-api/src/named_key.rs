@@ -1,5 +1,5 @@
-use alloc::vec::Vec;
-use core::cell::Cell;
+use alloc::{format, string::String, vec::Vec};
+use core::cell::{Cell, OnceCell};
 
 use crate::{
     casper_contract::unwrap_or_revert::UnwrapOrRevert,
@@ -19,17 +19,49 @@ use crate::{
 /// See also it's companion [`TypedURef`] for working with URefs in a type-safe manner.
 #[derive(Clone)]
 pub struct NamedKey {
+    prefix: Option<&'static str>,
     name: &'static str,
+    full_name: OnceCell<String>,
     key: Cell<Option<Result<Option<Key>, ApiError>>>,
 }
 
 unsafe impl Sync for NamedKey {}
 
+/// Creates a [`NamedKey`] named `<prefix>_<name>`, joining the literals at compile time.
+///
+/// ```
+/// use veles_casper_contract_api::{named_key, named_key::NamedKey};
+///
+/// static BALANCES: NamedKey = named_key!("cep18", "balances");
+/// assert_eq!(BALANCES.name(), "cep18_balances");
+/// ```
+#[macro_export]
+macro_rules! named_key {
+    ($prefix:literal, $name:literal) => {
+        $crate::named_key::NamedKey::from_name(::core::concat!($prefix, "_", $name))
+    };
+}
+
 impl NamedKey {
     /// Creates a new `NamedKey` instance from the given name.
     pub const fn from_name(name: &'static str) -> NamedKey {
         NamedKey {
+            prefix: None,
             name,
+            full_name: OnceCell::new(),
+            key: Cell::new(None),
+        }
+    }
+
+    /// Creates a new `NamedKey` instance named `<prefix>_<name>`.
+    ///
+    /// The full name is built on first use and cached. Use [`named_key!`](crate::named_key!)
+    /// to build the name at compile time when both parts are literals.
+    pub const fn namespaced(prefix: &'static str, name: &'static str) -> NamedKey {
+        NamedKey {
+            prefix: Some(prefix),
+            name,
+            full_name: OnceCell::new(),
             key: Cell::new(None),
         }
     }
@@ -73,7 +105,7 @@ impl NamedKey {
             }
         }
 
-        let result = utils::get_key(self.name);
+        let result = utils::get_key(self.name());
         match result {
             Ok(opt_key) => {
                 self.key.set(Some(Ok(opt_key)));
@@ -95,9 +127,20 @@ impl NamedKey {
         }
     }
 
-    /// Returns the name of this named key.
-    pub const fn name(&self) -> &str {
-        self.name
+    /// Returns the name of this named key, including the namespace prefix.
+    pub fn name(&self) -> &str {
+        match self.prefix {
+            Some(prefix) => self
+                .full_name
+                .get_or_init(|| format!("{prefix}_{}", self.name)),
+            None => self.name,
+        }
+    }
+
+    /// Returns the name `<name>_<instance>` of the key of a contract instance, for contracts
+    /// installed multiple times under a single account.
+    pub fn instance_name(&self, instance: &str) -> String {
+        format!("{}_{instance}", self.name())
     }
 
     /// Retrieves the key from the global state under this named key.
@@ -123,7 +166,7 @@ impl NamedKey {
     /// This is useful when initializing the named keys of a contract or account (in session).
     pub fn put_to_named_keys(&self) -> Result<&NamedKey, ApiError> {
         let key = self.resolve_key()?.ok_or(ApiError::MissingKey)?;
-        utils::put_key(self.name, key)?;
+        utils::put_key(self.name(), key)?;
         Ok(self)
     }
 
@@ -133,7 +176,7 @@ impl NamedKey {
     /// deploying it.
     pub fn append_to_named_keys(&self, named_keys: &mut NamedKeys) -> Result<&NamedKey, ApiError> {
         let key = self.resolve_key()?.ok_or(ApiError::MissingKey)?;
-        named_keys.insert(self.name.into(), key);
+        named_keys.insert(self.name().into(), key);
         Ok(self)
     }
 
@@ -141,15 +184,21 @@ impl NamedKey {
     ///
     /// This is equivaelnt to `put_key`. Due to high-level nature of this API it is named `set` to
     /// better reflect its purpose.
+    ///
+    /// The cached key only changes once the key is put.
     pub fn set(&self, key: Key) -> Result<(), ApiError> {
+        utils::put_key(self.name(), key)?;
         let _old_value = self.key.replace(Some(Ok(Some(key))));
-        utils::put_key(self.name, key)?;
         Ok(())
     }
 
     /// Removes the key from the global state under this named key.
-    pub fn clear(&self) {
-        utils::remove_key(self.name);
+    ///
+    /// The named key resolves to no key afterwards, like one that was never put.
+    pub fn clear(&self) -> Result<(), ApiError> {
+        utils::try_remove_key(self.name())?;
+        let _old_value = self.key.replace(Some(Ok(None)));
+        Ok(())
     }
 
     /// Reads the value stored under this named key.
@@ -166,6 +215,18 @@ impl NamedKey {
         }
     }
 
+    /// Reads the value stored under this named key like [`NamedKey::read`], failing with
+    /// `ApiError::CLTypeMismatch` if it is not a `T`, see [`utils::read_key_typed`].
+    pub fn read_typed<T>(&self) -> Result<Option<T>, ApiError>
+    where
+        T: FromBytes + CLTyped,
+    {
+        match self.get()? {
+            Some(key) => utils::read_key_typed(&key),
+            None => Ok(None),
+        }
+    }
+
     /// Writes the value under this named key.
     ///
     pub fn write<T>(&self, value: &T) -> Result<(), ApiError>
@@ -176,6 +237,35 @@ impl NamedKey {
         utils::write_key(value, key)
     }
 
+    /// Writes the value under this named key unless it already holds one, returning whether it
+    /// wrote.
+    ///
+    /// Unlike [`get_or_init`](Self::get_or_init), which creates the key itself, this fills the
+    /// value of an existing URef, e.g. one cleared with [`clear_value`](Self::clear_value), whose
+    /// tombstone counts as no value. A `()` value can't be told apart from the tombstone, so it is
+    /// always written.
+    pub fn write_if_absent<T>(&self, value: &T) -> Result<bool, ApiError>
+    where
+        T: ToBytes + CLTyped,
+    {
+        let key = self.resolve_key()?.ok_or(ApiError::MissingKey)?;
+        if utils::has_value(&key)? {
+            return Ok(false);
+        }
+        utils::write_key(value, key)?;
+        Ok(true)
+    }
+
+    /// Clears the value under this named key, keeping the named key and its URef.
+    ///
+    /// The value is overwritten with a `CLValue::unit()` tombstone, which [`read`](Self::read),
+    /// [`read_typed`](Self::read_typed) and [`write_if_absent`](Self::write_if_absent) treat as no
+    /// value, see [`utils::clear_value`]. Use [`clear`](Self::clear) to remove the named key.
+    pub fn clear_value(&self) -> Result<(), ApiError> {
+        let key = self.resolve_key()?.ok_or(ApiError::MissingKey)?;
+        utils::clear_value(key)
+    }
+
     /// Writes the value under the given dictionary item key.
     pub fn put_dict<K, V>(&self, dictionary_item_key: K, value: V) -> Result<(), ApiError>
     where
@@ -224,9 +314,10 @@ impl NamedKey {
 #[cfg(test)]
 mod tests {
     use alloc::vec;
-    use veles_casper_ffi_shim::{EnvBuilder, HostFunction, dispatch_with};
+    use veles_casper_ffi_shim::{EnvBuilder, HostFunction, HostFunctionPattern, dispatch_with};
 
     use super::*;
+    use crate::typed_uref::TypedURef;
 
     const NAME: &str = "test_key";
     const EXPECTED_KEY: Key = Key::Hash([42u8; 32]);
@@ -254,13 +345,13 @@ mod tests {
         dispatch_with(EnvBuilder::new().build(), |env| {
             with_named_key(|named_key| {
                 assert_eq!(named_key.get().unwrap(), None);
-                assert_eq!(env.trace(), vec![HostFunction::CasperGetKey(NAME.into()),]);
+                env.assert_trace(|t| t.contains(HostFunctionPattern::GetKey(Some(NAME))).times(1));
 
                 named_key.set(EXPECTED_KEY).unwrap();
-                assert_eq!(
-                    env.trace(),
-                    vec![HostFunction::CasperPutKey(NAME.into(), EXPECTED_KEY),]
-                );
+                env.assert_trace(|t| {
+                    t.contains(HostFunctionPattern::PutKey(Some(NAME), Some(EXPECTED_KEY)))
+                        .times(1)
+                });
 
                 let retrieved_key = named_key.get().unwrap().unwrap();
                 assert_eq!(retrieved_key, EXPECTED_KEY);
@@ -283,7 +374,7 @@ mod tests {
         dispatch_with(env, |env| {
             with_named_key(|named_key| {
                 assert_eq!(named_key.get().unwrap().unwrap(), EXPECTED_KEY);
-                assert_eq!(env.trace(), vec![HostFunction::CasperGetKey(NAME.into()),]);
+                env.assert_trace(|t| t.contains(HostFunctionPattern::GetKey(Some(NAME))).times(1));
             });
         });
     }
@@ -296,7 +387,7 @@ mod tests {
                 let result = named_key.get_or_init(|| Ok(EXPECTED_KEY));
                 assert!(result.is_ok());
 
-                assert_eq!(env.trace(), vec![HostFunction::CasperGetKey(NAME.into()),]);
+                env.assert_trace(|t| t.contains(HostFunctionPattern::GetKey(Some(NAME))).times(1));
 
                 // Get or init should not create a named key (yet)
                 assert!(!env.named_keys().contains_key(NAME));
@@ -307,10 +398,10 @@ mod tests {
 
                 named_key.put_to_named_keys().unwrap();
 
-                assert_eq!(
-                    env.trace(),
-                    vec![HostFunction::CasperPutKey(NAME.into(), EXPECTED_KEY),]
-                );
+                env.assert_trace(|t| {
+                    t.contains(HostFunctionPattern::PutKey(Some(NAME), Some(EXPECTED_KEY)))
+                        .never(HostFunctionPattern::GetKey(None))
+                });
             });
         });
     }
@@ -328,7 +419,7 @@ mod tests {
                 let retrieved_key = named_key.get().unwrap().unwrap();
                 assert_eq!(retrieved_key, EXPECTED_KEY);
 
-                assert_eq!(env.trace(), vec![HostFunction::CasperGetKey(NAME.into()),]);
+                env.assert_trace(|t| t.contains(HostFunctionPattern::GetKey(Some(NAME))).times(1));
             });
         });
     }
@@ -340,12 +431,13 @@ mod tests {
 
         dispatch_with(env, |env| {
             with_named_key(|named_key| {
-                named_key.clear();
+                assert_eq!(named_key.get().unwrap(), Some(EXPECTED_KEY));
+                named_key.clear().unwrap();
 
-                assert_eq!(
-                    env.trace(),
-                    vec![HostFunction::CasperRemoveKey(NAME.into()),]
-                );
+                env.assert_trace(|t| t.contains(HostFunctionPattern::RemoveKey(Some(NAME))));
+                // The cached key is gone along with the named key
+                assert_eq!(named_key.get().unwrap(), None);
+                assert_eq!(utils::try_has_key(NAME), Ok(false));
             });
         });
     }
@@ -390,13 +482,12 @@ mod tests {
                 let result = named_key.put_to_named_keys();
                 assert!(result.is_ok());
 
-                assert_eq!(
-                    env.trace(),
-                    vec![
-                        HostFunction::CasperGetKey(NAME.into()),
-                        HostFunction::CasperPutKey(NAME.into(), EXPECTED_KEY),
-                    ]
-                );
+                env.assert_trace(|t| {
+                    t.in_order([
+                        HostFunctionPattern::GetKey(Some(NAME)),
+                        HostFunctionPattern::PutKey(Some(NAME), Some(EXPECTED_KEY)),
+                    ])
+                });
             });
         });
     }
@@ -440,4 +531,91 @@ mod tests {
             });
         });
     }
+
+    #[test]
+    fn test_namespaced_named_key() {
+        let named_key = NamedKey::namespaced("cep18", "contract_hash");
+        assert_eq!(named_key.name(), "cep18_contract_hash");
+        assert_eq!(
+            named_key.instance_name("Token"),
+            "cep18_contract_hash_Token"
+        );
+        assert_eq!(
+            crate::named_key!("cep18", "contract_hash").name(),
+            named_key.name()
+        );
+
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            named_key.set(EXPECTED_KEY).unwrap();
+            env.assert_trace(|t| {
+                t.contains(HostFunctionPattern::PutKey(
+                    Some("cep18_contract_hash"),
+                    Some(EXPECTED_KEY),
+                ))
+            });
+            assert_eq!(
+                env.named_keys().get("cep18_contract_hash"),
+                Some(&EXPECTED_KEY)
+            );
+        });
+    }
+
+    #[test]
+    fn test_named_key_read_typed_reports_mismatches() {
+        let uref = URef::new([7; 32], casper_types::AccessRights::READ_ADD_WRITE);
+        let env = EnvBuilder::new()
+            .with_named_key("counter", Key::URef(uref))
+            .with_storage(
+                Key::URef(uref),
+                casper_types::StoredValue::CLValue(CLValue::from_t(42u32).unwrap()),
+            )
+            .build();
+
+        dispatch_with(env, |_env| {
+            let named_key = NamedKey::from_name("counter");
+            assert_eq!(named_key.read::<u64>(), Err(ApiError::EarlyEndOfStream));
+            assert_eq!(named_key.read_typed::<u64>(), Err(ApiError::CLTypeMismatch));
+            assert_eq!(named_key.read_typed::<u32>(), Ok(Some(42)));
+
+            let typed_uref: TypedURef<u64> = TypedURef::from_named_key(&named_key);
+            assert_eq!(typed_uref.read_typed(), Err(ApiError::CLTypeMismatch));
+            assert_eq!(NamedKey::from_name("missing").read_typed::<u32>(), Ok(None));
+        });
+    }
+
+    #[test]
+    fn test_cleared_values_read_as_absent_until_rewritten() {
+        let uref = URef::new([8; 32], casper_types::AccessRights::READ_ADD_WRITE);
+        let env = EnvBuilder::new()
+            .with_named_key("supply", Key::URef(uref))
+            .with_storage(
+                Key::URef(uref),
+                casper_types::StoredValue::CLValue(CLValue::from_t(5u64).unwrap()),
+            )
+            .build();
+
+        dispatch_with(env, |env| {
+            let named_key = NamedKey::from_name("supply");
+            let typed_uref: TypedURef<u64> = TypedURef::from_named_key(&named_key);
+            assert_eq!(named_key.write_if_absent(&7u64), Ok(false));
+            assert_eq!(typed_uref.read(), Ok(Some(5)));
+
+            typed_uref.clear_value().unwrap();
+            assert_eq!(
+                env.storage().get(&Key::URef(uref).normalize()),
+                Some(&casper_types::StoredValue::CLValue(CLValue::unit()))
+            );
+            assert_eq!(env.named_keys().get("supply"), Some(&Key::URef(uref)));
+            assert_eq!(typed_uref.read(), Ok(None));
+            assert_eq!(typed_uref.read_typed(), Ok(None));
+            assert_eq!(named_key.read::<()>(), Ok(Some(())));
+
+            assert_eq!(named_key.write_if_absent(&7u64), Ok(true));
+            assert_eq!(typed_uref.read(), Ok(Some(7)));
+            assert_eq!(
+                NamedKey::from_name("missing").write_if_absent(&7u64),
+                Err(ApiError::MissingKey)
+            );
+        });
+    }
 }
@@ -1,12 +1,16 @@
 //! A prelude for the contract-api crate.
 //!
 //! It re-exports commonly used items for easy import.
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::macro_support::WithTypedArgs;
 pub use crate::{
     casper_contract::contract_api::{runtime, storage},
     casper_types::{ApiError, Key, U512, contract_messages::MessageTopicOperation},
-    macro_support::CasperMessage,
+    ctx::{StaticContext, UnwrapOrRevertCtx},
+    error::{ContractResult, ResultExt},
+    macro_support::{CasperMessage, IntoRuntimeArgs},
     named_key::NamedKey,
     typed_uref::TypedURef,
     utils,
-    veles_casper_contract_macros::{CasperMessage, casper},
+    veles_casper_contract_macros::{CasperMessage, CasperSerialize, IntoRuntimeArgs, casper},
 };
@@ -0,0 +1,153 @@
+//! Helpers for off-chain tooling that inspects the outcome of executed transactions.
+//!
+//! Nodes report a failed execution as an error message only. The functions here turn the message
+//! of a reverted execution back into the [`ApiError`] it was reverted with, so that tooling can
+//! match on errors semantically, e.g. `ApiError::User(Cep18Error::InsufficientBalance as u16)`.
+use crate::{
+    casper_types::{ApiError, execution::ExecutionResult},
+    error::UniversalError,
+};
+
+/// Decodes the error an execution was reverted with.
+///
+/// Returns `None` if the execution succeeded or failed for a reason other than a revert, e.g.
+/// running out of gas.
+pub fn decode_execution_error(result: &ExecutionResult) -> Option<ApiError> {
+    parse_revert_message(&result.error_message()?)
+}
+
+/// Decodes the error an execution was reverted with as a [`UniversalError`].
+///
+/// User errors within the range reserved for [`UniversalError`] are mapped to their variant,
+/// every other error is returned as [`UniversalError::Other`].
+pub fn decode_universal_error(result: &ExecutionResult) -> Option<UniversalError> {
+    let api_error = decode_execution_error(result)?;
+    let universal_error = match api_error {
+        ApiError::User(code) => UniversalError::from_user_code(code),
+        _ => None,
+    };
+    Some(universal_error.unwrap_or(UniversalError::Other(api_error)))
+}
+
+/// Parses the error message of a reverted execution back into an [`ApiError`].
+///
+/// The execution engine reports a revert using the `Display` representation of the error, i.e.
+/// `User error: 50000` for user errors, `Mint error: 2` (and similar) for system contract errors
+/// and `ApiError::MissingArgument [2]` for everything else.
+pub fn parse_revert_message(message: &str) -> Option<ApiError> {
+    let message = message.trim();
+
+    if let Some(code) = message.strip_prefix("User error: ") {
+        return code.parse().ok().map(ApiError::User);
+    }
+    if let Some(code) = message.strip_prefix("Mint error: ") {
+        return code.parse().ok().map(ApiError::Mint);
+    }
+    if let Some(code) = message.strip_prefix("Handle Payment error: ") {
+        return code.parse().ok().map(ApiError::HandlePayment);
+    }
+    if let Some(code) = message.strip_prefix("Contract header error: ") {
+        return code.parse().ok().map(ApiError::ContractHeader);
+    }
+
+    // Everything else is formatted with `Debug` and ends with the numeric code, e.g. `[2]`.
+    if !message.starts_with("ApiError::") {
+        return None;
+    }
+    let code = message.strip_suffix(']')?.rsplit_once(" [")?.1;
+    code.parse::<u32>().ok().map(ApiError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        boxed::Box,
+        string::{String, ToString},
+    };
+
+    use casper_types::{
+        Gas, InitiatorAddr, U512,
+        account::AccountHash,
+        execution::{Effects, ExecutionResultV2},
+    };
+
+    use super::*;
+
+    fn execution_result(error_message: Option<String>) -> ExecutionResult {
+        ExecutionResult::V2(Box::new(ExecutionResultV2 {
+            initiator: InitiatorAddr::AccountHash(AccountHash::new([1; 32])),
+            error_message,
+            current_price: 1,
+            limit: Gas::new(1_000_000u64),
+            consumed: Gas::new(1_000u64),
+            cost: U512::from(1_000u64),
+            refund: U512::zero(),
+            transfers: Default::default(),
+            size_estimate: 0,
+            effects: Effects::new(),
+        }))
+    }
+
+    #[test]
+    fn test_decode_success() {
+        assert_eq!(decode_execution_error(&execution_result(None)), None);
+    }
+
+    #[test]
+    fn test_decode_user_error() {
+        let result = execution_result(Some(ApiError::User(60001).to_string()));
+        assert_eq!(decode_execution_error(&result), Some(ApiError::User(60001)));
+    }
+
+    #[test]
+    fn test_decode_system_error() {
+        for api_error in [
+            ApiError::MissingArgument,
+            ApiError::EarlyEndOfStream,
+            ApiError::Mint(2),
+            ApiError::HandlePayment(1),
+            ApiError::ContractHeader(3),
+            ApiError::AuctionError(4),
+        ] {
+            let result = execution_result(Some(api_error.to_string()));
+            assert_eq!(decode_execution_error(&result), Some(api_error));
+        }
+    }
+
+    #[test]
+    fn test_decode_non_revert_failure() {
+        let result = execution_result(Some("Out of gas error".into()));
+        assert_eq!(decode_execution_error(&result), None);
+    }
+
+    #[test]
+    fn test_decode_universal_error() {
+        for universal_error in [
+            UniversalError::Panic,
+            UniversalError::InvalidContext,
+            UniversalError::URefAlreadyInitialized,
+            UniversalError::MigrationsOutOfOrder,
+            UniversalError::AlreadyInitialized,
+            UniversalError::CallerNotInstaller,
+            UniversalError::PageLimitExceeded,
+        ] {
+            let api_error = ApiError::from(universal_error);
+            let result = execution_result(Some(api_error.to_string()));
+            assert_eq!(
+                decode_universal_error(&result).map(ApiError::from),
+                Some(api_error)
+            );
+        }
+
+        let result = execution_result(Some(ApiError::User(56899).to_string()));
+        assert_eq!(
+            decode_universal_error(&result),
+            Some(UniversalError::Other(ApiError::User(56899)))
+        );
+        let result = execution_result(Some(ApiError::MissingKey.to_string()));
+        assert_eq!(
+            decode_universal_error(&result),
+            Some(UniversalError::Other(ApiError::MissingKey))
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Whole-state assertions for engine tests.
+//!
+//! Statics marked `#[casper(named_key)]` inside a `#[casper(contract)]` module are listed by the
+//! generated `named_keys_manifest()`, and `state_spec()` collects them into a [`StateSpec`].
+//! [`verify_state`] then checks an entity's named keys against it, so keys a test forgot to query
+//! are still compared:
+//!
+//! ```ignore
+//! let spec = contract::state_spec().with_named_key(PACKAGE_HASH_KEY_NAME, NamedKeyKind::Any);
+//! verify_state(&builder, EntityAddr::Account(account_hash.value()), &spec).assert_ok();
+//! ```
+//!
+//! The kind of a key follows the type of its static: a [`NamedKey`] may hold any key, a
+//! [`TypedURef<T>`] a URef to a `T`, and collections URefs seeding dictionaries.
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use casper_engine_test_support::LmdbWasmTestBuilder;
+
+use crate::{
+    casper_types::{CLType, CLTyped, EntityAddr, Key, StoredValue},
+    collections::{indexed_set::IndexedSet, mapping::Mapping, set::Set, vector::Vector},
+    named_key::NamedKey,
+    typed_uref::TypedURef,
+};
+
+/// What a named key is expected to refer to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamedKeyKind {
+    /// Any key, only its presence is checked.
+    Any,
+    /// A URef to a value of this type.
+    Value(CLType),
+    /// A URef seeding a dictionary.
+    Dictionary,
+}
+
+impl fmt::Display for NamedKeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamedKeyKind::Any => write!(f, "any key"),
+            NamedKeyKind::Value(cl_type) => write!(f, "a URef to {cl_type:?}"),
+            NamedKeyKind::Dictionary => write!(f, "a dictionary"),
+        }
+    }
+}
+
+/// A named key of a contract module's manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedKeySpec {
+    pub name: String,
+    pub kind: NamedKeyKind,
+}
+
+/// Statics that can be marked `#[casper(named_key)]`, describing the named keys they use.
+pub trait NamedKeyManifest {
+    fn named_key_specs(&self) -> Vec<NamedKeySpec>;
+}
+
+impl NamedKeyManifest for NamedKey {
+    fn named_key_specs(&self) -> Vec<NamedKeySpec> {
+        vec![NamedKeySpec {
+            name: self.name().into(),
+            kind: NamedKeyKind::Any,
+        }]
+    }
+}
+
+impl<T: CLTyped> NamedKeyManifest for TypedURef<'_, T> {
+    fn named_key_specs(&self) -> Vec<NamedKeySpec> {
+        vec![NamedKeySpec {
+            name: self.named_uref().name().into(),
+            kind: NamedKeyKind::Value(T::cl_type()),
+        }]
+    }
+}
+
+fn dictionary_specs<'a>(named_keys: impl IntoIterator<Item = &'a NamedKey>) -> Vec<NamedKeySpec> {
+    named_keys
+        .into_iter()
+        .map(|named_key| NamedKeySpec {
+            name: named_key.name().into(),
+            kind: NamedKeyKind::Dictionary,
+        })
+        .collect()
+}
+
+impl<K, V> NamedKeyManifest for Mapping<K, V> {
+    fn named_key_specs(&self) -> Vec<NamedKeySpec> {
+        dictionary_specs([self.named_uref()])
+    }
+}
+
+impl<K> NamedKeyManifest for Set<K> {
+    fn named_key_specs(&self) -> Vec<NamedKeySpec> {
+        dictionary_specs([self.named_uref()])
+    }
+}
+
+impl<T> NamedKeyManifest for Vector<T> {
+    fn named_key_specs(&self) -> Vec<NamedKeySpec> {
+        dictionary_specs([self.named_uref()])
+    }
+}
+
+impl<K> NamedKeyManifest for IndexedSet<K> {
+    fn named_key_specs(&self) -> Vec<NamedKeySpec> {
+        dictionary_specs(self.named_urefs())
+    }
+}
+
+/// The named keys an entity is expected to have, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSpec {
+    named_keys: BTreeMap<String, NamedKeyKind>,
+}
+
+impl StateSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects a named key `name` of `kind`, replacing an earlier expectation of that name.
+    pub fn with_named_key(mut self, name: impl Into<String>, kind: NamedKeyKind) -> Self {
+        self.named_keys.insert(name.into(), kind);
+        self
+    }
+
+    /// Stops expecting the named key `name`, e.g. one the entity under test never creates.
+    pub fn without_named_key(mut self, name: &str) -> Self {
+        self.named_keys.remove(name);
+        self
+    }
+
+    /// Returns the expected named keys, ordered by name.
+    pub fn named_keys(&self) -> &BTreeMap<String, NamedKeyKind> {
+        &self.named_keys
+    }
+}
+
+impl FromIterator<NamedKeySpec> for StateSpec {
+    fn from_iter<I: IntoIterator<Item = NamedKeySpec>>(iter: I) -> Self {
+        Self {
+            named_keys: iter
+                .into_iter()
+                .map(|spec| (spec.name, spec.kind))
+                .collect(),
+        }
+    }
+}
+
+/// A named key whose value is not of the expected kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MistypedNamedKey {
+    pub name: String,
+    pub expected: NamedKeyKind,
+    /// Description of what the key refers to instead, e.g. `a URef to U32`.
+    pub found: String,
+}
+
+/// The differences between an entity's named keys and a [`StateSpec`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateReport {
+    /// Expected named keys the entity doesn't have.
+    pub missing: Vec<NamedKeySpec>,
+    /// Named keys of the entity the spec doesn't expect.
+    pub unexpected: Vec<(String, Key)>,
+    pub mistyped: Vec<MistypedNamedKey>,
+}
+
+impl StateReport {
+    /// Returns whether the entity matches the spec.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.mistyped.is_empty()
+    }
+
+    /// Panics with the rendered report unless the entity matches the spec.
+    #[track_caller]
+    pub fn assert_ok(&self) {
+        assert!(self.is_ok(), "{self}");
+    }
+}
+
+impl fmt::Display for StateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "State matches the spec");
+        }
+        write!(f, "State differs from the spec:")?;
+        for NamedKeySpec { name, kind } in &self.missing {
+            write!(f, "\n- missing    `{name}`: expected {kind}")?;
+        }
+        for (name, key) in &self.unexpected {
+            write!(f, "\n+ unexpected `{name}`: {}", key.to_formatted_string())?;
+        }
+        for MistypedNamedKey {
+            name,
+            expected,
+            found,
+        } in &self.mistyped
+        {
+            write!(
+                f,
+                "\n~ mistyped   `{name}`: expected {expected}, found {found}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the named keys of `entity` against `spec`.
+///
+/// Every named key is queried in the builder's post state, so URefs the spec expects to hold a
+/// value or seed a dictionary are checked for it. Panics if the entity doesn't exist.
+pub fn verify_state(
+    builder: &LmdbWasmTestBuilder,
+    entity: EntityAddr,
+    spec: &StateSpec,
+) -> StateReport {
+    let mut named_keys: BTreeMap<String, Key> = builder
+        .get_named_keys(entity)
+        .iter()
+        .map(|(name, key)| (name.clone(), *key))
+        .collect();
+    let mut report = StateReport::default();
+
+    for (name, expected) in &spec.named_keys {
+        let Some(key) = named_keys.remove(name) else {
+            report.missing.push(NamedKeySpec {
+                name: name.clone(),
+                kind: expected.clone(),
+            });
+            continue;
+        };
+        if *expected == NamedKeyKind::Any {
+            continue;
+        }
+        let found = describe(builder, key);
+        if found.as_ref() != Some(expected) {
+            report.mistyped.push(MistypedNamedKey {
+                name: name.clone(),
+                expected: expected.clone(),
+                found: found.map_or_else(
+                    || match key {
+                        Key::URef(_) => format!("{} without a value", key.to_formatted_string()),
+                        key => key.to_formatted_string(),
+                    },
+                    |kind| kind.to_string(),
+                ),
+            });
+        }
+    }
+    report.unexpected = named_keys.into_iter().collect();
+    report
+}
+
+/// Returns the kind of a URef holding a `CLValue`, `None` for anything else.
+fn describe(builder: &LmdbWasmTestBuilder, key: Key) -> Option<NamedKeyKind> {
+    let Key::URef(_) = key else {
+        return None;
+    };
+    match builder.query(None, key, &[]) {
+        // Dictionaries are seeded with a unit value
+        Ok(StoredValue::CLValue(cl_value)) if *cl_value.cl_type() == CLType::Unit => {
+            Some(NamedKeyKind::Dictionary)
+        }
+        Ok(StoredValue::CLValue(cl_value)) => Some(NamedKeyKind::Value(cl_value.cl_type().clone())),
+        Ok(_) | Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{AccessRights, URef};
+
+    use super::*;
+
+    static BALANCES: Mapping<Key, u64> = Mapping::from_named_key(NamedKey::from_name("balances"));
+    static COUNT: NamedKey = NamedKey::from_name("count");
+    static COUNT_TUREF: TypedURef<u64> = TypedURef::from_named_key(&COUNT);
+    static HOLDERS: IndexedSet<Key> = IndexedSet::from_named_keys(
+        NamedKey::from_name("holders"),
+        NamedKey::from_name("holder_indices"),
+    );
+
+    #[test]
+    fn collects_statics_into_a_spec() {
+        let spec: StateSpec = [
+            BALANCES.named_key_specs(),
+            COUNT_TUREF.named_key_specs(),
+            HOLDERS.named_key_specs(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let spec = spec.with_named_key("owner", NamedKeyKind::Any);
+
+        assert_eq!(
+            spec.named_keys().iter().collect::<Vec<_>>(),
+            [
+                (&String::from("balances"), &NamedKeyKind::Dictionary),
+                (&String::from("count"), &NamedKeyKind::Value(CLType::U64)),
+                (&String::from("holder_indices"), &NamedKeyKind::Dictionary),
+                (&String::from("holders"), &NamedKeyKind::Dictionary),
+                (&String::from("owner"), &NamedKeyKind::Any),
+            ]
+        );
+        assert!(
+            !spec
+                .without_named_key("owner")
+                .named_keys()
+                .contains_key("owner")
+        );
+    }
+
+    #[test]
+    fn renders_the_differences() {
+        let report = StateReport {
+            missing: vec![NamedKeySpec {
+                name: String::from("balances"),
+                kind: NamedKeyKind::Dictionary,
+            }],
+            unexpected: vec![(String::from("owner"), Key::Hash([1; 32]))],
+            mistyped: vec![MistypedNamedKey {
+                name: String::from("count"),
+                expected: NamedKeyKind::Value(CLType::U64),
+                found: NamedKeyKind::Value(CLType::String).to_string(),
+            }],
+        };
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "State differs from the spec:\n\
+                 - missing    `balances`: expected a dictionary\n\
+                 + unexpected `owner`: hash-{}\n\
+                 ~ mistyped   `count`: expected a URef to U64, found a URef to String",
+                "01".repeat(32)
+            )
+        );
+        assert_eq!(StateReport::default().to_string(), "State matches the spec");
+    }
+
+    #[test]
+    #[should_panic(expected = "+ unexpected `purse`: uref-")]
+    fn assert_ok_panics_with_the_report() {
+        StateReport {
+            unexpected: vec![(
+                String::from("purse"),
+                Key::URef(URef::new([0; 32], AccessRights::READ_ADD_WRITE)),
+            )],
+            ..StateReport::default()
+        }
+        .assert_ok();
+    }
+}
@@ -0,0 +1,175 @@
+//! Typed reads of an engine test's post state.
+//!
+//! Reading a stored value out of an [`LmdbWasmTestBuilder`] takes finding the named key of the
+//! entity, querying it and unwrapping the `StoredValue` and `CLValue`. [`WasmTestBuilderExt`]
+//! does it in one call, panicking with the entity, the name and the named keys the entity has when
+//! the value isn't there:
+//!
+//! ```ignore
+//! let total_supply: U256 = builder.read_named_value(token, "total_supply");
+//! let balance: Option<U256> = builder.read_dictionary_value(token, "balances", &item_key);
+//! ```
+//!
+//! [`read_wasm`] reads the Wasm of a contract of the workspace to install it, from the directory
+//! `cargo xtask build-examples` builds it to.
+extern crate std;
+
+use alloc::{format, string::String, vec::Vec};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+use casper_engine_test_support::LmdbWasmTestBuilder;
+
+use crate::casper_types::{
+    CLTyped, EntityAddr, Key, NamedKeys, StoredValue, account::AccountHash,
+    addressable_entity::MessageTopics, bytesrepr::FromBytes, contracts::ContractHash,
+};
+
+/// Returns the directory the Wasm of the workspace's contracts is built to: `wasm32v1-none/release`
+/// in the target directory reported by `cargo metadata`, as `cargo xtask` resolves it.
+///
+/// `cargo metadata` honours `CARGO_TARGET_DIR` and `build.target-dir`. It runs in the directory
+/// the tests run in, the package's, which a relative `CARGO_TARGET_DIR` is resolved against;
+/// `cargo xtask test-examples` passes an absolute one.
+pub fn wasm_dir() -> &'static Path {
+    static WASM_DIR: OnceLock<PathBuf> = OnceLock::new();
+    WASM_DIR.get_or_init(|| target_dir().join("wasm32v1-none").join("release"))
+}
+
+/// Reads the Wasm file `file_name`, e.g. `counter.wasm`, from [`wasm_dir`].
+pub fn read_wasm(file_name: &str) -> Vec<u8> {
+    let path = wasm_dir().join(file_name);
+    fs::read(&path).unwrap_or_else(|error| {
+        panic!(
+            "should read {file_name} from {}: {error}; build it with `cargo xtask build-examples`",
+            wasm_dir().display()
+        )
+    })
+}
+
+/// Returns the target directory of the workspace `cargo metadata` reports.
+fn target_dir() -> PathBuf {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output()
+        .unwrap_or_else(|error| panic!("should run cargo metadata: {error}"));
+    assert!(
+        output.status.success(),
+        "cargo metadata failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|error| panic!("cargo metadata should print JSON: {error}"));
+    metadata["target_directory"]
+        .as_str()
+        .map(PathBuf::from)
+        .expect("cargo metadata should report the target directory")
+}
+
+/// Typed reads of the post state of a test builder, see the [module documentation](self).
+pub trait WasmTestBuilderExt {
+    /// Returns the key named `name` in the named keys of `entity`.
+    fn expect_named_key(&self, entity: EntityAddr, name: &str) -> Key;
+
+    /// Reads the `T` stored under the URef named `name` in the named keys of `entity`.
+    fn read_named_value<T: FromBytes + CLTyped>(&self, entity: EntityAddr, name: &str) -> T;
+
+    /// Reads the item `item_key` of the dictionary named `dictionary_name` in the named keys of
+    /// `entity`, `None` if the dictionary has no such item.
+    fn read_dictionary_value<T: FromBytes + CLTyped>(
+        &self,
+        entity: EntityAddr,
+        dictionary_name: &str,
+        item_key: &str,
+    ) -> Option<T>;
+
+    /// Returns the message topics `entity` registered.
+    fn read_message_topics(&self, entity: EntityAddr) -> MessageTopics;
+
+    /// Returns the contract hash an installer stored under `key_name` in the named keys of the
+    /// `installer` account.
+    fn installed_contract_hash(&self, installer: AccountHash, key_name: &str) -> ContractHash;
+}
+
+impl WasmTestBuilderExt for LmdbWasmTestBuilder {
+    fn expect_named_key(&self, entity: EntityAddr, name: &str) -> Key {
+        let named_keys = self.get_named_keys(entity);
+        match named_keys.get(name) {
+            Some(key) => *key,
+            None => panic!(
+                "{entity} has no named key `{name}`, its named keys are {}",
+                names(&named_keys)
+            ),
+        }
+    }
+
+    fn read_named_value<T: FromBytes + CLTyped>(&self, entity: EntityAddr, name: &str) -> T {
+        let key = self.expect_named_key(entity, name);
+        let stored_value = self.query(None, key, &[]).unwrap_or_else(|error| {
+            panic!("named key `{name}` of {entity} should hold a value: {error}")
+        });
+        into_t(stored_value)
+            .unwrap_or_else(|error| panic!("named key `{name}` of {entity} {error}"))
+    }
+
+    fn read_dictionary_value<T: FromBytes + CLTyped>(
+        &self,
+        entity: EntityAddr,
+        dictionary_name: &str,
+        item_key: &str,
+    ) -> Option<T> {
+        let Key::URef(seed_uref) = self.expect_named_key(entity, dictionary_name) else {
+            panic!("named key `{dictionary_name}` of {entity} should be a dictionary seed URef");
+        };
+        let stored_value = self.query_dictionary_item(None, seed_uref, item_key).ok()?;
+        Some(into_t(stored_value).unwrap_or_else(|error| {
+            panic!("item `{item_key}` of dictionary `{dictionary_name}` of {entity} {error}")
+        }))
+    }
+
+    fn read_message_topics(&self, entity: EntityAddr) -> MessageTopics {
+        self.message_topics(None, entity)
+            .unwrap_or_else(|error| panic!("should read the message topics of {entity}: {error}"))
+    }
+
+    fn installed_contract_hash(&self, installer: AccountHash, key_name: &str) -> ContractHash {
+        let account = self
+            .get_account(installer)
+            .unwrap_or_else(|| panic!("installer {installer} should exist"));
+        match account.named_keys().get(key_name) {
+            Some(Key::Hash(contract_hash)) => ContractHash::new(*contract_hash),
+            Some(key) => {
+                panic!("named key `{key_name}` of {installer} should be a hash, found {key}")
+            }
+            None => panic!(
+                "{installer} has no named key `{key_name}`, its named keys are {}",
+                names(account.named_keys())
+            ),
+        }
+    }
+}
+
+/// Unwraps the `T` of a stored `CLValue`, describing what was found instead.
+fn into_t<T: FromBytes + CLTyped>(stored_value: StoredValue) -> Result<T, String> {
+    let StoredValue::CLValue(cl_value) = stored_value else {
+        return Err(format!(
+            "should hold a CLValue, found a {}",
+            stored_value.type_name()
+        ));
+    };
+    let cl_type = cl_value.cl_type().clone();
+    cl_value
+        .into_t()
+        .map_err(|_| format!("should hold a {:?}, found a {cl_type:?}", T::cl_type()))
+}
+
+/// Lists the names of `named_keys` for panic messages.
+fn names(named_keys: &NamedKeys) -> String {
+    let names: Vec<String> = named_keys.names().map(|name| format!("`{name}`")).collect();
+    format!("[{}]", names.join(", "))
+}
@@ -5,6 +5,7 @@ use crate::{
         ApiError, CLTyped, URef,
         bytesrepr::{FromBytes, ToBytes},
     },
+    math::{CheckedStorageAdd, CheckedStorageSub},
     named_key::NamedKey,
 };
 
@@ -23,6 +24,11 @@ impl<'a, T> TypedURef<'a, T> {
         }
     }
 
+    /// Returns the named key the URef is stored under.
+    pub fn named_uref(&self) -> &'a NamedKey {
+        self.named_key
+    }
+
     /// Retrieves the underlying `URef` if it exists.
     pub fn uref(&self) -> Result<Option<URef>, ApiError> {
         let key = self.named_key.get()?;
@@ -37,6 +43,15 @@ impl<'a, T> TypedURef<'a, T> {
         self.named_key.read()
     }
 
+    /// Reads the value stored under this TypedURef, failing with `ApiError::CLTypeMismatch` if it
+    /// is not a `T`, e.g. a value of an older schema, see [`NamedKey::read_typed`].
+    pub fn read_typed(&self) -> Result<Option<T>, ApiError>
+    where
+        T: CLTyped + FromBytes,
+    {
+        self.named_key.read_typed()
+    }
+
     /// Writes the value under this TypedURef.
     pub fn write(&self, value: T) -> Result<(), ApiError>
     where
@@ -44,6 +59,45 @@ impl<'a, T> TypedURef<'a, T> {
     {
         self.named_key.write(&value)
     }
+
+    /// Clears the value under this TypedURef, so it reads as `None` until written again.
+    ///
+    /// The URef keeps a `CLValue::unit()` tombstone, as contracts can't delete values from the
+    /// global state, see [`NamedKey::clear_value`].
+    pub fn clear_value(&self) -> Result<(), ApiError> {
+        self.named_key.clear_value()
+    }
+
+    /// Adds `delta` to the stored value and returns the sum, or `overflow_err` if it overflows.
+    ///
+    /// A missing value counts as zero. Storage failures are converted into `E`.
+    pub fn increase<E>(&self, delta: T, overflow_err: E) -> Result<T, E>
+    where
+        T: CLTyped + FromBytes + ToBytes + Default + Clone + CheckedStorageAdd,
+        E: From<ApiError>,
+    {
+        let value = self
+            .read()?
+            .unwrap_or_default()
+            .try_add_or(delta, overflow_err)?;
+        self.write(value.clone())?;
+        Ok(value)
+    }
+
+    /// Subtracts `delta` from the stored value and returns the difference, or `underflow_err` if
+    /// `delta` is greater, see [`TypedURef::increase`].
+    pub fn decrease<E>(&self, delta: T, underflow_err: E) -> Result<T, E>
+    where
+        T: CLTyped + FromBytes + ToBytes + Default + Clone + CheckedStorageSub,
+        E: From<ApiError>,
+    {
+        let value = self
+            .read()?
+            .unwrap_or_default()
+            .try_sub_or(delta, underflow_err)?;
+        self.write(value.clone())?;
+        Ok(value)
+    }
 }
 
 unsafe impl<T: Sync> Sync for TypedURef<'_, T> {}
@@ -1,14 +1,21 @@
 use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 use casper_types::bytesrepr::{Bytes, FromBytes, U8_SERIALIZED_LENGTH};
-use casper_types::contracts::ContractHash;
+use casper_types::contracts::{ContractHash, ContractPackageHash, ContractVersion};
+use casper_types::crypto::Signature;
 use casper_types::global_state::TrieMerkleProofStep;
 use casper_types::system::CallerInfo;
-use casper_types::{BLAKE2B_DIGEST_LENGTH, CLTyped, Digest, Key, Pointer};
+use casper_types::{
+    BLAKE2B_DIGEST_LENGTH, CLTyped, Digest, Key, NamedKeys, Phase, Pointer, PublicKey,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use casper_types::{CLType, Package, PackageHash, StoredValue, account::Account};
 use core::mem::MaybeUninit;
 use core::num::NonZeroU64;
 
 use crate::error::UniversalError;
+use crate::limits::NetworkLimits;
 
 use crate::macro_support::CasperMessage;
 use crate::{
@@ -18,13 +25,25 @@ use crate::{
         unwrap_or_revert::UnwrapOrRevert,
     },
     casper_types::{
-        ApiError, CLValue, DICTIONARY_ITEM_KEY_MAX_LENGTH, EntityAddr, URef,
+        ApiError, CLValue, DICTIONARY_ITEM_KEY_MAX_LENGTH, EntityAddr, RuntimeArgs, URef,
         account::AccountHash,
         api_error,
         bytesrepr::{self, ToBytes},
+        contract_messages::MessageTopicOperation,
     },
 };
 
+/// Upper bound for the serialized caller information returned by the host.
+///
+/// Even a full call stack serializes to a few kilobytes at most, anything larger indicates a
+/// misbehaving host.
+pub const MAX_CALLER_INFO_SIZE: usize = 8 * 1024;
+
+/// Upper bound for the serialized named keys returned by the host, see [`list_named_keys`].
+///
+/// That's several thousand named keys, more than a contract should keep in its context.
+pub const MAX_NAMED_KEYS_SIZE: usize = 1024 * 1024;
+
 #[repr(u8)]
 pub enum CallerAction {
     Initiator = 0,
@@ -58,7 +77,7 @@ pub fn get_initiator_or_immediate(action: CallerAction) -> Result<CallerInfo, Un
         return Err(ApiError::InvalidCallerInfoRequest.into());
     }
     crate::log!("Call stack length: {call_stack_len}, result size: {result_size}");
-    let bytes = read_host_buffer(result_size).unwrap_or_revert();
+    let bytes = read_host_buffer_bounded(result_size, MAX_CALLER_INFO_SIZE)?;
     let caller: Vec<CallerInfo> = bytesrepr::deserialize(bytes).unwrap_or_revert();
 
     if caller.len() != 1 {
@@ -135,6 +154,53 @@ pub fn get_immediate_account() -> Result<AccountHash, ApiError> {
     Ok(AccountHash::new(account_hash))
 }
 
+unsafe extern "C-unwind" {
+    // The shim's `casper_revert` and `casper_ret` unwind, and so do its contract calls when the
+    // callee reverts, which the `extern "C"` declarations of `casper_contract::ext_ffi` don't
+    // allow.
+    #[link_name = "casper_revert"]
+    fn casper_revert_unwind(status: u32) -> !;
+    #[link_name = "casper_ret"]
+    fn casper_ret_unwind(value_ptr: *const u8, value_size: usize) -> !;
+    #[link_name = "casper_call_contract"]
+    fn casper_call_contract_unwind(
+        contract_hash_ptr: *const u8,
+        contract_hash_size: usize,
+        entry_point_name_ptr: *const u8,
+        entry_point_name_size: usize,
+        runtime_args_ptr: *const u8,
+        runtime_args_size: usize,
+        result_size: *mut usize,
+    ) -> i32;
+    #[link_name = "casper_call_versioned_contract"]
+    fn casper_call_versioned_contract_unwind(
+        contract_package_hash_ptr: *const u8,
+        contract_package_hash_size: usize,
+        contract_version_ptr: *const u8,
+        contract_version_size: usize,
+        entry_point_name_ptr: *const u8,
+        entry_point_name_size: usize,
+        runtime_args_ptr: *const u8,
+        runtime_args_size: usize,
+        result_size: *mut usize,
+    ) -> i32;
+}
+
+/// Reverts the execution with `error`, like `runtime::revert`.
+///
+/// Unlike `runtime::revert`, it calls the host through an `extern "C-unwind"` declaration, so the
+/// shim's `casper_revert` may unwind out of it in host tests. The kit's own reverts go through it.
+pub fn revert(error: impl Into<ApiError>) -> ! {
+    unsafe { casper_revert_unwind(error.into().into()) }
+}
+
+/// Returns `value` to the caller and ends the execution, like `runtime::ret`, through an
+/// `extern "C-unwind"` declaration as [`revert`] does.
+pub fn ret(value: CLValue) -> ! {
+    let (ptr, size, _bytes) = to_ptr(&value);
+    unsafe { casper_ret_unwind(ptr, size) }
+}
+
 pub(crate) fn to_ptr<T: ToBytes>(t: &T) -> (*const u8, usize, Vec<u8>) {
     let bytes = t.into_bytes().unwrap_or_revert();
     let ptr = bytes.as_ptr();
@@ -153,7 +219,7 @@ pub fn dictionary_put_clvalue(
     let dictionary_item_key_size = dictionary_item_key.len();
 
     if dictionary_item_key_size > DICTIONARY_ITEM_KEY_MAX_LENGTH {
-        runtime::revert(ApiError::DictionaryItemKeyExceedsLength);
+        revert(ApiError::DictionaryItemKeyExceedsLength);
     }
 
     let (cl_value_ptr, cl_value_size, _bytes) = to_ptr(&cl_value);
@@ -192,7 +258,7 @@ pub fn dictionary_get_bytes(
     let dictionary_item_key_size = dictionary_item_key.len();
 
     if dictionary_item_key_size > DICTIONARY_ITEM_KEY_MAX_LENGTH {
-        runtime::revert(ApiError::DictionaryItemKeyExceedsLength)
+        revert(ApiError::DictionaryItemKeyExceedsLength)
     }
 
     let value_size = {
@@ -209,7 +275,7 @@ pub fn dictionary_get_bytes(
         match api_error::result_from(ret) {
             Ok(_) => unsafe { value_size.assume_init() },
             Err(ApiError::ValueNotFound) => return Ok(None),
-            Err(e) => runtime::revert(e),
+            Err(e) => revert(e),
         }
     };
 
@@ -245,6 +311,70 @@ pub fn new_uref_key<T: ToBytes + CLTyped>(value: T) -> Result<Key, ApiError> {
     Ok(Key::URef(uref))
 }
 
+/// Adds the user group `label` to the contract package at `package_hash`, with `num_urefs` new
+/// URefs which are returned.
+///
+/// Entry points whose access is `EntryPointAccess::groups(&[label])` can then only be called with
+/// one of the URefs, e.g. by an account the installer handed one to. The calling code must hold
+/// the access URef of the package. Unlike `storage::create_contract_user_group`, an existing
+/// label or too many groups or URefs (`ApiError::ContractHeader`) are returned rather than
+/// reverting.
+pub fn create_user_group(
+    package_hash: ContractPackageHash,
+    label: &str,
+    num_urefs: u8,
+) -> Result<Vec<URef>, ApiError> {
+    let (package_hash_ptr, package_hash_size, _bytes1) = to_ptr(&package_hash);
+    let (label_ptr, label_size, _bytes2) = to_ptr(&label);
+    let (existing_urefs_ptr, existing_urefs_size, _bytes3) = to_ptr(&BTreeSet::<URef>::new());
+    let value_size = {
+        let mut value_size = MaybeUninit::uninit();
+        let ret = unsafe {
+            ext_ffi::casper_create_contract_user_group(
+                package_hash_ptr,
+                package_hash_size,
+                label_ptr,
+                label_size,
+                num_urefs,
+                existing_urefs_ptr,
+                existing_urefs_size,
+                value_size.as_mut_ptr(),
+            )
+        };
+        api_error::result_from(ret)?;
+        unsafe { value_size.assume_init() }
+    };
+    let value_bytes = read_host_buffer(value_size)?;
+    Ok(bytesrepr::deserialize(value_bytes)?)
+}
+
+/// Verifies that `signature`, a bytesrepr encoded `Signature` such as one passed in a `Bytes`
+/// argument, signs `message` with `public_key`.
+///
+/// Fails with `ApiError::InvalidArgument`, like the host does for signatures that don't verify,
+/// also when `signature` isn't exactly one encoded `Signature`.
+pub fn verify_signature(
+    message: &[u8],
+    signature: &[u8],
+    public_key: &PublicKey,
+) -> Result<(), ApiError> {
+    if !matches!(Signature::from_bytes(signature), Ok((_, []))) {
+        return Err(ApiError::InvalidArgument);
+    }
+    let public_key_bytes = public_key.to_bytes()?;
+    let ret = unsafe {
+        ext_ffi::casper_verify_signature(
+            message.as_ptr(),
+            message.len(),
+            signature.as_ptr(),
+            signature.len(),
+            public_key_bytes.as_ptr(),
+            public_key_bytes.len(),
+        )
+    };
+    api_error::result_from(ret)
+}
+
 pub(crate) fn read_host_buffer(size: usize) -> Result<Vec<u8>, ApiError> {
     let mut dest: Vec<u8> = if size == 0 {
         Vec::new()
@@ -256,7 +386,74 @@ pub(crate) fn read_host_buffer(size: usize) -> Result<Vec<u8>, ApiError> {
     Ok(dest)
 }
 
-pub fn get_key(name: &'static str) -> Result<Option<casper_types::Key>, ApiError> {
+/// Reads the host buffer like [`read_host_buffer`], but refuses to allocate more than `max` bytes.
+///
+/// Returns [`ApiError::OutOfMemory`] if the host reports a `size` larger than `max`. The host buffer
+/// is discarded in that case, so that it doesn't linger for the next host call.
+pub fn read_host_buffer_bounded(size: usize, max: usize) -> Result<Vec<u8>, ApiError> {
+    if size > max {
+        crate::log!("Host buffer size {size} exceeds the limit of {max} bytes");
+        // The host takes a buffer that doesn't fit, failing with `BufferTooSmall`
+        let _ = read_host_buffer_into(&mut []);
+        return Err(ApiError::OutOfMemory);
+    }
+    read_host_buffer(size)
+}
+
+/// Reads the named runtime argument `name`, returning `None` if it was not passed.
+///
+/// Unlike `runtime::get_named_arg` this does not revert, both a failing host call and a value
+/// that does not deserialize into `T` are reported as an error.
+pub fn try_get_named_arg<T: FromBytes + CLTyped>(name: &str) -> Result<Option<T>, ApiError> {
+    let arg_size = {
+        let mut arg_size: usize = 0;
+        let ret = unsafe {
+            ext_ffi::casper_get_named_arg_size(
+                name.as_bytes().as_ptr(),
+                name.len(),
+                &mut arg_size as *mut usize,
+            )
+        };
+        match api_error::result_from(ret) {
+            Ok(_) => arg_size,
+            Err(ApiError::MissingArgument) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+    };
+
+    let arg_bytes = if arg_size > 0 {
+        let bytes_non_null_ptr = contract_api::alloc_bytes(arg_size);
+        let mut dest =
+            unsafe { Vec::from_raw_parts(bytes_non_null_ptr.as_ptr(), arg_size, arg_size) };
+        let ret = unsafe {
+            ext_ffi::casper_get_named_arg(
+                name.as_bytes().as_ptr(),
+                name.len(),
+                dest.as_mut_ptr(),
+                dest.len(),
+            )
+        };
+        api_error::result_from(ret)?;
+        dest
+    } else {
+        // Avoids allocation with 0 bytes and a call to get_named_arg
+        Vec::new()
+    };
+
+    let value: T = bytesrepr::deserialize(arg_bytes)?;
+    Ok(Some(value))
+}
+
+/// Reads the named runtime argument `name`, falling back to `default` if it was not passed.
+///
+/// Reverts if the argument is present but cannot be read or deserialized.
+pub fn get_named_arg_or<T: FromBytes + CLTyped>(name: &str, default: T) -> T {
+    try_get_named_arg(name)
+        .unwrap_or_revert()
+        .unwrap_or(default)
+}
+
+pub fn get_key(name: &str) -> Result<Option<casper_types::Key>, ApiError> {
     let name = length_prefixed_string(name);
     let mut key_bytes = [0u8; 64];
     let mut total_bytes: usize = 0;
@@ -280,7 +477,18 @@ pub fn get_key(name: &'static str) -> Result<Option<casper_types::Key>, ApiError
     }
 }
 
-pub fn put_key(name: &'static str, key: Key) -> Result<(), ApiError> {
+/// Puts `key` under `name` in the named keys of the current context.
+///
+/// `casper_put_key` returns no error code, the host traps instead, so only serializing `key` can
+/// fail here.
+pub fn put_key(name: &str, key: Key) -> Result<(), ApiError> {
+    if let Some(max_length) = NetworkLimits::DEFAULT.max_named_key_name_length {
+        debug_assert!(
+            name.len() <= max_length as usize,
+            "named key name `{name}` is {} bytes long, the limit is {max_length}",
+            name.len()
+        );
+    }
     let name = length_prefixed_string(name);
     let key_bytes = key.into_bytes()?;
     unsafe {
@@ -294,8 +502,77 @@ pub fn put_key(name: &'static str, key: Key) -> Result<(), ApiError> {
     Ok(())
 }
 
+/// Returns the named keys of the current context.
+///
+/// Unlike `casper_contract::contract_api::runtime::list_named_keys`, returns
+/// [`ApiError::OutOfMemory`] rather than allocating a buffer larger than [`MAX_NAMED_KEYS_SIZE`].
+pub fn list_named_keys() -> Result<NamedKeys, ApiError> {
+    let mut total_keys: usize = 0;
+    let mut result_size: usize = 0;
+    let ret = unsafe {
+        ext_ffi::casper_load_named_keys(
+            &mut total_keys as *mut usize,
+            &mut result_size as *mut usize,
+        )
+    };
+    api_error::result_from(ret)?;
+    if total_keys == 0 {
+        return Ok(NamedKeys::new());
+    }
+    let bytes = read_host_buffer_bounded(result_size, MAX_NAMED_KEYS_SIZE)?;
+    Ok(bytesrepr::deserialize(bytes)?)
+}
+
 /// Reads value under `key` in the global state.
+///
+/// The `CLValue::unit()` tombstone [`clear_value`] leaves reads as no value, unless `T` is `()`.
+/// Any other value that doesn't deserialize into `T` fails. Off-chain the tombstone is told apart
+/// by its `CLType::Unit`; the engine only hands contracts the bytes of a value, so on-chain any
+/// empty value is taken for it.
 pub fn read_key<T: FromBytes>(key: &Key) -> Result<Option<T>, ApiError> {
+    match read_key_bytes(key)? {
+        Some(value_bytes) if is_tombstone(key, &value_bytes) => Ok(read_tombstone()),
+        Some(value_bytes) => Ok(Some(bytesrepr::deserialize(value_bytes)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns whether `key` holds a value, i.e. a value other than the tombstone of [`clear_value`].
+pub fn has_value(key: &Key) -> Result<bool, ApiError> {
+    Ok(read_key_bytes(key)?.is_some_and(|value_bytes| !is_tombstone(key, &value_bytes)))
+}
+
+/// Returns whether `value_bytes`, read under `key`, are the `CLValue::unit()` tombstone of
+/// [`clear_value`].
+///
+/// On-chain only the bytes are known, and of the values contracts store only `()` serializes to
+/// no bytes.
+fn is_tombstone(key: &Key, value_bytes: &[u8]) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        value_bytes.is_empty() && veles_casper_ffi_shim::stored_cl_type(key) == Some(CLType::Unit)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = key;
+        value_bytes.is_empty()
+    }
+}
+
+/// Reads the tombstone of [`clear_value`] as a `T`: `()` for `()`, no value for any other type.
+fn read_tombstone<T: FromBytes>() -> Option<T> {
+    bytesrepr::deserialize(Vec::new()).ok()
+}
+
+/// Clears the value under `key` by overwriting it with a `CLValue::unit()` tombstone.
+///
+/// Contracts can't delete values from the global state, so the URef stays readable and holds the
+/// unit value; [`read_key`], [`read_key_typed`] and [`has_value`] read the tombstone as no value.
+pub fn clear_value(key: Key) -> Result<(), ApiError> {
+    write_key(&(), key)
+}
+
+fn read_key_bytes(key: &Key) -> Result<Option<Vec<u8>>, ApiError> {
     let key_bytes = key.into_bytes()?;
 
     let value_size = {
@@ -310,9 +587,79 @@ pub fn read_key<T: FromBytes>(key: &Key) -> Result<Option<T>, ApiError> {
         }
     };
 
-    let value_bytes = read_host_buffer(value_size)?;
-    let value: T = bytesrepr::deserialize(value_bytes)?;
-    Ok(Some(value))
+    read_host_buffer(value_size).map(Some)
+}
+
+/// Reads the value stored under `key` like [`read_key`], but reports a value of another type than
+/// `T` as `ApiError::CLTypeMismatch` rather than a deserialization error.
+///
+/// Off-chain the `CLType` of the stored `CLValue` is compared with `T::cl_type()` before
+/// deserializing. The engine only hands contracts the bytes of a value, so on-chain a value that
+/// doesn't deserialize into `T` is reported as a mismatch instead. Like `read_key`, the tombstone
+/// of [`clear_value`] reads as no value.
+pub fn read_key_typed<T: FromBytes + CLTyped>(key: &Key) -> Result<Option<T>, ApiError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        match read_stored_value(key)? {
+            Some(StoredValue::CLValue(cl_value)) if *cl_value.cl_type() == CLType::Unit => {
+                Ok(read_tombstone())
+            }
+            Some(StoredValue::CLValue(cl_value)) => Ok(Some(cl_value.into_t()?)),
+            Some(_) => Err(ApiError::CLTypeMismatch),
+            None => Ok(None),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        match read_key_bytes(key)? {
+            Some(value_bytes) if is_tombstone(key, &value_bytes) => Ok(read_tombstone()),
+            Some(value_bytes) => bytesrepr::deserialize(value_bytes)
+                .map(Some)
+                .map_err(|_| ApiError::CLTypeMismatch),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Reads the value stored under `key` without converting it to a `CLValue`, e.g. an account or
+/// a package.
+///
+/// Only available off-chain, under the shim: the engine's `casper_read_value` traps on stored
+/// values that aren't `CLValue`s and no host function reads them whole.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_stored_value(key: &Key) -> Result<Option<StoredValue>, ApiError> {
+    veles_casper_ffi_shim::read_stored_value(key)
+}
+
+/// Reads the account stored under `Key::Account(account_hash)`, see [`read_stored_value`].
+///
+/// Fails with `ApiError::CLTypeMismatch` when the key holds anything but an account.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_account(account_hash: AccountHash) -> Result<Option<Account>, ApiError> {
+    match read_stored_value(&Key::Account(account_hash))? {
+        Some(StoredValue::Account(account)) => Ok(Some(account)),
+        Some(_) => Err(ApiError::CLTypeMismatch),
+        None => Ok(None),
+    }
+}
+
+/// Reads the package stored under `Key::SmartContract(package_hash)`, or a 1.x contract package
+/// stored under `Key::Hash(package_hash)`, see [`read_stored_value`].
+///
+/// Fails with `ApiError::CLTypeMismatch` when the key holds anything but a package.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_package(package_hash: PackageHash) -> Result<Option<Package>, ApiError> {
+    if let Some(stored_value) = read_stored_value(&Key::SmartContract(package_hash.value()))? {
+        return match stored_value {
+            StoredValue::SmartContract(package) => Ok(Some(package)),
+            _ => Err(ApiError::CLTypeMismatch),
+        };
+    }
+    match read_stored_value(&Key::Hash(package_hash.value()))? {
+        Some(StoredValue::ContractPackage(contract_package)) => Ok(Some(contract_package.into())),
+        Some(_) => Err(ApiError::CLTypeMismatch),
+        None => Ok(None),
+    }
 }
 
 /// Writes `value` under `key` in the global state.
@@ -329,27 +676,50 @@ pub fn write_key<T: ToBytes + CLTyped>(value: &T, key: Key) -> Result<(), ApiErr
     Ok(())
 }
 
-fn length_prefixed_string(name: &'static str) -> Vec<u8> {
+fn length_prefixed_string(name: &str) -> Vec<u8> {
     let mut len_prefixed = Vec::with_capacity(U8_SERIALIZED_LENGTH + name.len());
     len_prefixed.extend_from_slice(&(name.len() as u32).to_le_bytes());
     len_prefixed.extend_from_slice(name.as_bytes());
     len_prefixed
 }
 
-pub fn has_key(name: &'static str) -> bool {
+#[deprecated(note = "treats host errors as a missing key, use `try_has_key` instead")]
+pub fn has_key(name: &str) -> bool {
+    try_has_key(name).unwrap_or(false)
+}
+
+/// Returns whether the named keys of the current context contain `name`.
+///
+/// `casper_has_key` returns 0 for a present key and 1 for a missing one; other codes are host
+/// errors.
+pub fn try_has_key(name: &str) -> Result<bool, ApiError> {
     let len_prefixed = length_prefixed_string(name);
     let ret = unsafe { ext_ffi::casper_has_key(len_prefixed.as_ptr(), len_prefixed.len()) };
-    ret == 0
+    match ret {
+        0 => Ok(true),
+        1 => Ok(false),
+        _ => Err(ApiError::from(ret as u32)),
+    }
 }
 
 /// Removes the key from the global state.
-pub fn remove_key(name: &'static str) {
+#[deprecated(note = "use `try_remove_key` instead")]
+pub fn remove_key(name: &str) {
+    let _ = try_remove_key(name);
+}
+
+/// Removes `name` from the named keys of the current context, if it is there.
+///
+/// Like `casper_put_key`, `casper_remove_key` returns no error code and the host traps instead;
+/// the `Result` keeps the named key functions uniform.
+pub fn try_remove_key(name: &str) -> Result<(), ApiError> {
     let len_prefixed = length_prefixed_string(name);
     unsafe { ext_ffi::casper_remove_key(len_prefixed.as_ptr(), len_prefixed.len()) };
+    Ok(())
 }
 
 /// Retrieves the URef associated with the given name from the global state.
-pub fn get_uref(name: &'static str) -> Result<Option<URef>, ApiError> {
+pub fn get_uref(name: &str) -> Result<Option<URef>, ApiError> {
     let uref = get_key(name)?
         .and_then(|key| key.into_uref())
         .ok_or(ApiError::UnexpectedKeyVariant)?;
@@ -362,6 +732,22 @@ pub fn emit_message<E: CasperMessage>(event: E) -> Result<(), ApiError> {
         let topic_name = E::TOPIC_NAME.as_bytes();
         let message_bytes = payload.into_bytes()?;
 
+        let limits = NetworkLimits::DEFAULT;
+        debug_assert!(
+            topic_name.len() <= limits.max_topic_name_size as usize,
+            "topic name `{}` is {} bytes long, the limit is {}",
+            E::TOPIC_NAME,
+            topic_name.len(),
+            limits.max_topic_name_size
+        );
+        debug_assert!(
+            message_bytes.len() <= limits.max_message_size as usize,
+            "message on topic `{}` is {} bytes long, the limit is {}",
+            E::TOPIC_NAME,
+            message_bytes.len(),
+            limits.max_message_size
+        );
+
         let result = unsafe {
             ext_ffi::casper_emit_message(
                 topic_name.as_ptr(),
@@ -376,6 +762,135 @@ pub fn emit_message<E: CasperMessage>(event: E) -> Result<(), ApiError> {
     Ok(())
 }
 
+/// Registers the topic of `E` for the calling contract.
+///
+/// Like the engine's `casper_manage_message_topic`, this only works from a stored contract, and
+/// fails with [`ApiError::MessageTopicAlreadyRegistered`] if the contract has the topic already.
+/// Topics known at install time are better passed to `new_contract`, which costs no extra call.
+pub fn register_message_topic<E: CasperMessage>() -> Result<(), ApiError> {
+    runtime::manage_message_topic(E::TOPIC_NAME, MessageTopicOperation::Add)
+}
+
+/// Emits `event` like [`emit_message`], registering its topic first if the contract hasn't yet.
+pub fn emit_typed<E: CasperMessage>(event: E) -> Result<(), ApiError> {
+    match register_message_topic::<E>() {
+        Ok(()) | Err(ApiError::MessageTopicAlreadyRegistered) => {}
+        Err(error) => return Err(error),
+    }
+    emit_message(event)
+}
+
+/// Calls `entry_point_name` of the contract and deserializes the returned value.
+///
+/// Unlike the generated `Client`, the target doesn't need to be known at compile time, which is
+/// useful for router or proxy contracts. Unlike [`runtime::call_contract`], a result that doesn't
+/// deserialize into `T` is returned as an error instead of reverting. A revert of the callee still
+/// aborts the caller: the engine doesn't hand it back to the calling contract.
+pub fn call_contract_typed<T: FromBytes + CLTyped>(
+    contract_hash: ContractHash,
+    entry_point_name: &str,
+    runtime_args: RuntimeArgs,
+) -> Result<T, ApiError> {
+    let runtime_args = runtime_args.into_bytes()?;
+    let bytes = call_contract_with_serialized_args(contract_hash, entry_point_name, &runtime_args)?;
+    Ok(bytesrepr::deserialize(bytes)?)
+}
+
+/// Calls `entry_point_name` of `contract_version` of the package, or of its latest enabled
+/// version with `None`, and deserializes the returned value, like [`call_contract_typed`] does for
+/// a contract hash.
+pub fn call_versioned_contract_typed<T: FromBytes + CLTyped>(
+    contract_package_hash: ContractPackageHash,
+    contract_version: Option<ContractVersion>,
+    entry_point_name: &str,
+    runtime_args: RuntimeArgs,
+) -> Result<T, ApiError> {
+    let runtime_args = runtime_args.into_bytes()?;
+    let bytes = call_versioned_contract_with_serialized_args(
+        contract_package_hash,
+        contract_version,
+        entry_point_name,
+        &runtime_args,
+    )?;
+    Ok(bytesrepr::deserialize(bytes)?)
+}
+
+/// Calls `entry_point_name` of the contract with already serialized `RuntimeArgs` and returns the
+/// serialized result.
+///
+/// Unlike [`runtime::call_contract`], the result is not deserialized, which is useful when the
+/// return type of the called entry point is not known, e.g. when the call is stored for later.
+pub fn call_contract_with_serialized_args(
+    contract_hash: ContractHash,
+    entry_point_name: &str,
+    runtime_args: &[u8],
+) -> Result<Vec<u8>, ApiError> {
+    let (contract_hash_ptr, contract_hash_size, _bytes1) = to_ptr(&contract_hash);
+    let (entry_point_name_ptr, entry_point_name_size, _bytes2) = to_ptr(&entry_point_name);
+
+    let result_size = {
+        let mut result_size = MaybeUninit::uninit();
+        let ret = unsafe {
+            casper_call_contract_unwind(
+                contract_hash_ptr,
+                contract_hash_size,
+                entry_point_name_ptr,
+                entry_point_name_size,
+                runtime_args.as_ptr(),
+                runtime_args.len(),
+                result_size.as_mut_ptr(),
+            )
+        };
+        api_error::result_from(ret)?;
+        unsafe { result_size.assume_init() }
+    };
+
+    read_call_result(result_size)
+}
+
+/// Calls `entry_point_name` of a package version with already serialized `RuntimeArgs` and
+/// returns the serialized result, see [`call_contract_with_serialized_args`].
+pub fn call_versioned_contract_with_serialized_args(
+    contract_package_hash: ContractPackageHash,
+    contract_version: Option<ContractVersion>,
+    entry_point_name: &str,
+    runtime_args: &[u8],
+) -> Result<Vec<u8>, ApiError> {
+    let (contract_package_hash_ptr, contract_package_hash_size, _bytes1) =
+        to_ptr(&contract_package_hash);
+    let (contract_version_ptr, contract_version_size, _bytes2) = to_ptr(&contract_version);
+    let (entry_point_name_ptr, entry_point_name_size, _bytes3) = to_ptr(&entry_point_name);
+
+    let result_size = {
+        let mut result_size = MaybeUninit::uninit();
+        let ret = unsafe {
+            casper_call_versioned_contract_unwind(
+                contract_package_hash_ptr,
+                contract_package_hash_size,
+                contract_version_ptr,
+                contract_version_size,
+                entry_point_name_ptr,
+                entry_point_name_size,
+                runtime_args.as_ptr(),
+                runtime_args.len(),
+                result_size.as_mut_ptr(),
+            )
+        };
+        api_error::result_from(ret)?;
+        unsafe { result_size.assume_init() }
+    };
+    read_call_result(result_size)
+}
+
+/// Reads the `result_size` bytes a contract call returned.
+fn read_call_result(result_size: usize) -> Result<Vec<u8>, ApiError> {
+    if result_size == 0 {
+        // Nothing was written to the host buffer, so it must not be read.
+        return Ok(Vec::new());
+    }
+    read_host_buffer(result_size)
+}
+
 pub fn get_block_time() -> NonZeroU64 {
     let block_time: MaybeUninit<[u8; 8]> = MaybeUninit::uninit();
     unsafe {
@@ -387,6 +902,16 @@ pub fn get_block_time() -> NonZeroU64 {
     unsafe { NonZeroU64::new_unchecked(block_time) }
 }
 
+/// Returns the phase the current code is executed in.
+pub fn get_phase() -> Phase {
+    let mut phase: MaybeUninit<[u8; 1]> = MaybeUninit::uninit();
+    unsafe {
+        ext_ffi::casper_get_phase(phase.as_mut_ptr().cast());
+    }
+    let phase = unsafe { phase.assume_init() };
+    bytesrepr::deserialize_from_slice(phase).unwrap_or_revert()
+}
+
 pub fn get_block_height() -> u64 {
     let block_height: MaybeUninit<[u8; 8]> = MaybeUninit::uninit();
     unsafe {
@@ -412,20 +937,46 @@ pub enum HashAlgorithm {
     Keccak256 = 3,
 }
 
+/// Hashes `data` with `algo` into a 32-byte digest.
 pub fn generic_hash<T: AsRef<[u8]>>(algo: HashAlgorithm, data: T) -> Result<[u8; 32], ApiError> {
-    let mut ret: MaybeUninit<[u8; BLAKE2B_DIGEST_LENGTH]> = MaybeUninit::uninit();
+    generic_hash_sized::<BLAKE2B_DIGEST_LENGTH>(algo, data)
+}
+
+/// Hashes `data` with `algo`, passing `N` as the size of the output to the host.
+///
+/// The host writes the digest at the start of the output and fails with
+/// `ApiError::BufferTooSmall` when it doesn't fit. The execution engine computes 32-byte digests
+/// for every algorithm, so an `N` below 32 fails on-chain rather than shortening the digest, and
+/// bytes past the digest stay zero.
+pub fn generic_hash_sized<const N: usize>(
+    algo: HashAlgorithm,
+    data: impl AsRef<[u8]>,
+) -> Result<[u8; N], ApiError> {
+    let mut ret = [0u8; N];
     let asref = data.as_ref();
     let result = unsafe {
-        ext_ffi::casper_generic_hash(
-            asref.as_ptr(),
-            asref.len(),
-            algo as u8,
-            ret.as_mut_ptr().cast(),
-            BLAKE2B_DIGEST_LENGTH,
-        )
+        ext_ffi::casper_generic_hash(asref.as_ptr(), asref.len(), algo as u8, ret.as_mut_ptr(), N)
     };
     api_error::result_from(result)?;
-    Ok(unsafe { ret.assume_init() })
+    Ok(ret)
+}
+
+/// Returns the address of the item `item_key` of the dictionary seeded by `seed`, i.e. the
+/// `Key::Dictionary` address off-chain tools read the item under.
+///
+/// This matches the node's derivation, `blake2b(seed address ++ item key)`, without calling
+/// into the host, so off-chain tools need no shim `Env`. String item keys pass their UTF-8 bytes.
+pub fn dictionary_item_addr(seed: &URef, item_key: &[u8]) -> [u8; 32] {
+    match Key::dictionary(*seed, item_key) {
+        Key::Dictionary(addr) => addr,
+        _ => unreachable!("Key::dictionary returns a dictionary key"),
+    }
+}
+
+/// Returns the `Key::Dictionary` of the item `item_key` of the dictionary seeded by `seed`, see
+/// [`dictionary_item_addr`].
+pub fn dictionary_item_key(seed: &URef, item_key: &[u8]) -> Key {
+    Key::dictionary(*seed, item_key)
 }
 
 pub(crate) const RADIX: usize = 256;
@@ -662,3 +1213,1038 @@ macro_rules! log_assert_ne {
     ($left:expr, $right:expr) => {};
     ($left:expr, $right:expr, $($args:tt)*) => {};
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::{BTreeMap, BTreeSet};
+    use alloc::string::String;
+    use alloc::{format, vec};
+    use casper_types::{
+        AccessRights, CLType, StoredValue, U512, UREF_SERIALIZED_LENGTH, URef,
+        account::{Account, AccountHash},
+        contract_messages::MessagePayload,
+        contracts::ContractPackage,
+        system::Caller,
+    };
+    use veles_casper_ffi_shim::{
+        AccountConfig, DEFAULT_MAIN_PURSE, EnvBuilder, HostFunction, dispatch_with,
+        dispatch_with_global, run_entry_point,
+    };
+
+    use super::*;
+
+    const VALUE_KEY: Key = Key::Hash([7u8; 32]);
+
+    #[test]
+    fn test_try_get_named_arg() {
+        let env = EnvBuilder::new()
+            .with_arg("amount", 42u64)
+            .with_arg("name", "token")
+            .build();
+        dispatch_with(env, |_env| {
+            assert_eq!(try_get_named_arg::<u64>("amount"), Ok(Some(42)));
+            assert_eq!(
+                try_get_named_arg::<alloc::string::String>("name"),
+                Ok(Some("token".into()))
+            );
+            assert_eq!(try_get_named_arg::<u64>("missing"), Ok(None));
+        });
+    }
+
+    #[test]
+    fn test_try_get_named_arg_invalid_type() {
+        dispatch_with(EnvBuilder::new().with_arg("flag", true).build(), |_env| {
+            assert_eq!(
+                try_get_named_arg::<u64>("flag"),
+                Err(ApiError::EarlyEndOfStream)
+            );
+        });
+    }
+
+    #[test]
+    fn test_pre_serialized_args() {
+        let amount = CLValue::from_t(U512::from(1_000u64)).unwrap();
+        let env = EnvBuilder::new()
+            .with_arg_clvalue("amount", amount.clone())
+            .with_arg_bytes("recipient", CLType::String, "alice".to_bytes().unwrap())
+            .with_arg_bytes("truncated", CLType::U64, vec![1, 2])
+            .build();
+        assert_eq!(
+            env.args(),
+            BTreeMap::from([
+                (String::from("amount"), amount),
+                (
+                    String::from("recipient"),
+                    CLValue::from_t(String::from("alice")).unwrap()
+                ),
+                (
+                    String::from("truncated"),
+                    CLValue::from_components(CLType::U64, vec![1, 2])
+                ),
+            ])
+        );
+        dispatch_with(env, |_env| {
+            assert_eq!(
+                try_get_named_arg::<U512>("amount"),
+                Ok(Some(U512::from(1_000u64)))
+            );
+            assert_eq!(
+                try_get_named_arg::<String>("recipient"),
+                Ok(Some(String::from("alice")))
+            );
+            assert_eq!(
+                try_get_named_arg::<u64>("truncated"),
+                Err(ApiError::EarlyEndOfStream)
+            );
+        });
+    }
+
+    #[test]
+    fn test_runtime_args_keep_their_clvalues() {
+        let truncated = CLValue::from_components(CLType::U64, vec![1, 2]);
+        let mut runtime_args = casper_types::runtime_args! { "decimals" => 9u8 };
+        runtime_args.insert_cl_value("truncated", truncated.clone());
+        let env = EnvBuilder::new().with_runtime_args(runtime_args).build();
+        assert_eq!(
+            env.args(),
+            BTreeMap::from([
+                (String::from("decimals"), CLValue::from_t(9u8).unwrap()),
+                (String::from("truncated"), truncated),
+            ])
+        );
+
+        env.set_arg("decimals", 18u8);
+        dispatch_with(env, |_env| {
+            assert_eq!(try_get_named_arg::<u8>("decimals"), Ok(Some(18)));
+            assert_eq!(
+                try_get_named_arg::<u64>("truncated"),
+                Err(ApiError::EarlyEndOfStream)
+            );
+        });
+    }
+
+    #[test]
+    fn test_named_key_functions_report_presence() {
+        let env = EnvBuilder::new()
+            .with_named_key("seeded", VALUE_KEY)
+            .build();
+        dispatch_with(env, |env| {
+            assert_eq!(try_has_key("seeded"), Ok(true));
+            assert_eq!(try_has_key("missing"), Ok(false));
+
+            put_key("added", VALUE_KEY).unwrap();
+            assert_eq!(try_has_key("added"), Ok(true));
+            assert_eq!(try_remove_key("added"), Ok(()));
+            assert_eq!(try_has_key("added"), Ok(false));
+            // Removing a missing key is not an error, like in the engine
+            assert_eq!(try_remove_key("added"), Ok(()));
+            assert_eq!(get_key("added"), Ok(None));
+            assert!(!env.named_keys().contains_key("added"));
+        });
+    }
+
+    #[test]
+    fn test_named_key_host_functions_reject_malformed_names() {
+        // A length prefix promising more bytes than follow
+        let malformed = [9u8, 0, 0, 0, b'a'];
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let ret = unsafe { ext_ffi::casper_has_key(malformed.as_ptr(), malformed.len()) };
+            assert_eq!(api_error::result_from(ret), Err(ApiError::Deserialize));
+
+            let mut key_bytes = [0u8; 64];
+            let mut total_bytes = 0usize;
+            let ret = unsafe {
+                ext_ffi::casper_get_key(
+                    malformed.as_ptr(),
+                    malformed.len(),
+                    key_bytes.as_mut_ptr(),
+                    key_bytes.len(),
+                    &mut total_bytes,
+                )
+            };
+            assert_eq!(api_error::result_from(ret), Err(ApiError::Deserialize));
+        });
+
+        // The functions without an error code revert like a trap of the engine
+        let key_bytes = VALUE_KEY.to_bytes().unwrap();
+        let put_key: Result<(), _> = run_entry_point(EnvBuilder::new().build(), |_env| unsafe {
+            ext_ffi::casper_put_key(
+                malformed.as_ptr(),
+                malformed.len(),
+                key_bytes.as_ptr(),
+                key_bytes.len(),
+            )
+        });
+        assert_eq!(put_key.unwrap_err().api_error, ApiError::Deserialize);
+        let name = length_prefixed_string("name");
+        let put_malformed_key: Result<(), _> =
+            run_entry_point(EnvBuilder::new().build(), |_env| unsafe {
+                ext_ffi::casper_put_key(name.as_ptr(), name.len(), [0xff].as_ptr(), 1)
+            });
+        assert_eq!(
+            put_malformed_key.unwrap_err().api_error,
+            ApiError::Deserialize
+        );
+        let remove_key: Result<(), _> = run_entry_point(EnvBuilder::new().build(), |_env| unsafe {
+            ext_ffi::casper_remove_key(malformed.as_ptr(), malformed.len())
+        });
+        assert_eq!(remove_key.unwrap_err().api_error, ApiError::Deserialize);
+    }
+
+    #[test]
+    fn test_get_named_arg_or() {
+        dispatch_with(
+            EnvBuilder::new().with_arg("decimals", 9u8).build(),
+            |_env| {
+                assert_eq!(get_named_arg_or("decimals", 18u8), 9);
+                assert_eq!(get_named_arg_or("missing", 18u8), 18);
+            },
+        );
+    }
+
+    #[test]
+    fn test_enter_call_uses_callee_context() {
+        let caller_env = EnvBuilder::new()
+            .with_arg("caller_arg", 1u64)
+            .with_named_key("caller_key", Key::Hash([1u8; 32]))
+            .build();
+        dispatch_with(caller_env, |env| {
+            {
+                let args = BTreeMap::from([("callee_arg".into(), CLValue::from_t(2u64).unwrap())]);
+                let named_keys = BTreeMap::from([("callee_key".into(), Key::Hash([2u8; 32]))]);
+                let call = env.enter_call(args, named_keys);
+                assert_eq!(call.env().depth(), 1);
+
+                assert_eq!(try_get_named_arg::<u64>("callee_arg"), Ok(Some(2)));
+                assert_eq!(try_get_named_arg::<u64>("caller_arg"), Ok(None));
+                assert_eq!(get_key("callee_key"), Ok(Some(Key::Hash([2u8; 32]))));
+                assert_eq!(get_key("caller_key"), Ok(None));
+
+                write_key(&7u64, VALUE_KEY).unwrap();
+            }
+
+            assert_eq!(try_get_named_arg::<u64>("caller_arg"), Ok(Some(1)));
+            assert_eq!(get_key("callee_key"), Ok(None));
+            assert_eq!(read_key::<u64>(&VALUE_KEY), Ok(Some(7)));
+
+            assert_eq!(
+                env.trace_with_depth(),
+                vec![
+                    (1, HostFunction::CasperGetNamedArgSize),
+                    (1, HostFunction::CasperGetNamedArg),
+                    (1, HostFunction::CasperGetNamedArgSize),
+                    (1, HostFunction::CasperGetKey("callee_key".into())),
+                    (1, HostFunction::CasperGetKey("caller_key".into())),
+                    (1, HostFunction::CasperWrite),
+                    (0, HostFunction::CasperGetNamedArgSize),
+                    (0, HostFunction::CasperGetNamedArg),
+                    (0, HostFunction::CasperGetKey("callee_key".into())),
+                    (0, HostFunction::CasperReadValue),
+                    (0, HostFunction::CasperReadHostBuffer),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_is_valid_uref_accepts_known_urefs() {
+        let seeded = URef::new([1u8; 32], AccessRights::READ);
+        let named = URef::new([2u8; 32], AccessRights::READ_ADD_WRITE);
+        let env = EnvBuilder::new()
+            .with_known_uref(seeded)
+            .with_named_key("named", Key::URef(named))
+            .build();
+        dispatch_with(env, |env| {
+            assert!(runtime::is_valid_uref(seeded));
+            assert!(runtime::is_valid_uref(named.into_read()));
+            assert!(!runtime::is_valid_uref(seeded.into_read_write()));
+            assert!(!runtime::is_valid_uref(URef::new(
+                [3u8; 32],
+                AccessRights::READ
+            )));
+
+            let created = new_uref_key(1u64).unwrap();
+            assert!(runtime::is_valid_uref(*created.as_uref().unwrap()));
+
+            assert_eq!(
+                env.trace()
+                    .into_iter()
+                    .filter(|host_function| *host_function == HostFunction::CasperIsValidUref)
+                    .count(),
+                5
+            );
+        });
+    }
+
+    #[test]
+    fn test_spawned_thread_adopts_env() {
+        let env = EnvBuilder::new().with_arg("amount", 42u64).build();
+        dispatch_with(env, |env| {
+            let worker_env = env.clone();
+            let amount = std::thread::spawn(move || {
+                let _guard = worker_env.activate_on_thread();
+                write_key(&7u64, VALUE_KEY).unwrap();
+                try_get_named_arg::<u64>("amount")
+            })
+            .join()
+            .unwrap();
+            assert_eq!(amount, Ok(Some(42)));
+            assert_eq!(read_key::<u64>(&VALUE_KEY), Ok(Some(7)));
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_global_env_is_visible_from_runtime_workers() {
+        let env = EnvBuilder::new().with_arg("amount", 42u64).build();
+        let amounts = tokio::task::block_in_place(|| {
+            dispatch_with_global(env, |_env| {
+                tokio::runtime::Handle::current().block_on(async {
+                    let tasks = (0..4)
+                        .map(|_| tokio::spawn(async { try_get_named_arg::<u64>("amount") }))
+                        .collect::<Vec<_>>();
+                    let mut amounts = Vec::new();
+                    for task in tasks {
+                        amounts.push(task.await.unwrap());
+                    }
+                    amounts
+                })
+            })
+        });
+        assert_eq!(amounts, vec![Ok(Some(42)); 4]);
+    }
+
+    #[test]
+    fn test_storage_checks_uref_access_rights() {
+        // A contract exposes its counter read-only under a named key, then writes through it.
+        let counter = URef::new([8u8; 32], AccessRights::READ);
+        let env = EnvBuilder::new()
+            .with_named_key("counter", Key::URef(counter))
+            .with_storage(
+                Key::URef(counter),
+                StoredValue::CLValue(CLValue::from_t(1u64).unwrap()),
+            )
+            .build();
+        let result: Result<(), _> = run_entry_point(env.clone(), |_env| {
+            assert_eq!(read_key::<u64>(&Key::URef(counter)), Ok(Some(1)));
+            write_key(&2u64, Key::URef(counter)).unwrap();
+        });
+        assert_eq!(result.unwrap_err().api_error, ApiError::NoAccessRights);
+
+        // Setting the missing bits forges rights that were never granted.
+        let result: Result<(), _> = run_entry_point(env.clone(), |_env| {
+            write_key(&2u64, Key::URef(counter.into_read_write())).unwrap();
+        });
+        assert_eq!(result.unwrap_err().api_error, ApiError::PermissionDenied);
+
+        dispatch_with(env, |env| {
+            assert_eq!(read_key::<u64>(&Key::URef(counter)), Ok(Some(1)));
+
+            let created = new_uref_key(3u64).unwrap();
+            let created_uref = *created.as_uref().unwrap();
+            write_key(&4u64, created).unwrap();
+            env.set_access_rights(created_uref.addr(), AccessRights::READ);
+            assert_eq!(read_key::<u64>(&created), Err(ApiError::PermissionDenied));
+            assert_eq!(
+                read_key::<u64>(&Key::URef(created_uref.into_read())),
+                Ok(Some(4))
+            );
+        });
+    }
+
+    /// Returns the `n`th address of a generator starting at zero.
+    fn generated_address(n: u8) -> [u8; 32] {
+        let mut address = [0u8; 32];
+        address[0] = n;
+        address
+    }
+
+    #[test]
+    fn test_new_urefs_skip_seeded_addresses() {
+        // The seeded URef sits where the generator starts, so the first new URef used to alias it
+        let seeded = URef::new(generated_address(1), AccessRights::READ_ADD_WRITE);
+        let env = EnvBuilder::new()
+            .with_known_uref(seeded)
+            .with_storage(
+                Key::URef(seeded),
+                StoredValue::CLValue(CLValue::from_t(7u64).unwrap()),
+            )
+            .build();
+        dispatch_with(env, |_env| {
+            let created = new_uref_key(9u64).unwrap();
+            assert_eq!(
+                created.as_uref().map(URef::addr),
+                Some(generated_address(2))
+            );
+            assert_eq!(read_key::<u64>(&Key::URef(seeded)), Ok(Some(7)));
+            assert_eq!(read_key::<u64>(&created), Ok(Some(9)));
+        });
+    }
+
+    #[test]
+    fn test_new_urefs_skip_reserved_addresses() {
+        let env = EnvBuilder::new()
+            .with_reserved_addresses([generated_address(1), generated_address(2)])
+            .build();
+        dispatch_with(env, |_env| {
+            let created = new_uref_key(9u64).unwrap();
+            assert_eq!(
+                created.as_uref().map(URef::addr),
+                Some(generated_address(3))
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "is already in use")]
+    fn test_new_uref_panics_instead_of_aliasing() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            write_key(&7u64, Key::Hash(generated_address(1))).unwrap();
+            // Called through its own declaration, as casper-contract's doesn't unwind
+            let value = CLValue::from_t(9u64).unwrap().to_bytes().unwrap();
+            let mut uref = [0u8; UREF_SERIALIZED_LENGTH];
+            unsafe {
+                veles_casper_ffi_shim::casper_new_uref(
+                    uref.as_mut_ptr(),
+                    value.as_ptr(),
+                    value.len(),
+                )
+            };
+        });
+    }
+
+    #[test]
+    fn test_main_purse_balance_is_readable() {
+        use crate::casper_contract::contract_api::{account, system};
+
+        let main_purse = URef::new([6u8; 32], AccessRights::READ_ADD_WRITE);
+        let account = AccountConfig::new(AccountHash::new([5u8; 32]), DEFAULT_MAIN_PURSE);
+        let env = EnvBuilder::new()
+            .with_account(account)
+            .with_main_purse(main_purse)
+            .with_balance(main_purse, U512::from(1_000u64))
+            .build();
+        dispatch_with(env, |env| {
+            assert_eq!(env.account().unwrap().main_purse, main_purse);
+            let purse = account::get_main_purse();
+            assert_eq!(purse, main_purse);
+            assert_eq!(system::get_purse_balance(purse), Some(U512::from(1_000u64)));
+            assert_eq!(
+                system::get_purse_balance(URef::new([9u8; 32], AccessRights::READ)),
+                None
+            );
+            assert_eq!(
+                env.trace(),
+                vec![
+                    HostFunction::CasperGetMainPurse,
+                    HostFunction::CasperGetBalance,
+                    HostFunction::CasperReadHostBuffer,
+                    HostFunction::CasperGetBalance,
+                ]
+            );
+        });
+
+        // Without any configuration the main purse is a default one holding nothing.
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            assert_eq!(account::get_main_purse(), DEFAULT_MAIN_PURSE);
+            assert_eq!(system::get_balance(), Some(U512::zero()));
+        });
+    }
+
+    #[test]
+    fn test_transfer_from_purse_to_purse_moves_balances() {
+        use crate::casper_contract::contract_api::system;
+        use casper_types::system::mint;
+
+        let env = EnvBuilder::new()
+            .with_balance(DEFAULT_MAIN_PURSE, U512::from(100u64))
+            .build();
+        dispatch_with(env, |env| {
+            let purse = system::create_purse();
+            assert_eq!(env.purse_balance(purse), Some(U512::zero()));
+            let transfer = |source, target, amount: u64| {
+                system::transfer_from_purse_to_purse(source, target, U512::from(amount), None)
+            };
+
+            env.assert_balance_delta(DEFAULT_MAIN_PURSE, -40, || {
+                env.assert_balance_delta(purse, 40, || {
+                    transfer(DEFAULT_MAIN_PURSE, purse, 40).unwrap();
+                });
+            });
+            env.assert_balance_delta(purse, 0, || {
+                assert_eq!(
+                    transfer(DEFAULT_MAIN_PURSE, purse, 61),
+                    Err(mint::Error::InsufficientFunds.into())
+                );
+                assert_eq!(
+                    transfer(DEFAULT_MAIN_PURSE, purse.into_read(), 1),
+                    Err(mint::Error::InvalidAccessRights.into())
+                );
+                let missing = URef::new([0xab; 32], AccessRights::READ_ADD_WRITE);
+                assert_eq!(
+                    transfer(DEFAULT_MAIN_PURSE, missing, 1),
+                    Err(mint::Error::DestNotFound.into())
+                );
+                assert_eq!(env.purse_balance(missing), None);
+            });
+            assert_eq!(
+                env.purse_balance(DEFAULT_MAIN_PURSE),
+                Some(U512::from(60u64))
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a delta of 5")]
+    fn test_assert_balance_delta_reports_the_balances() {
+        let env = EnvBuilder::new()
+            .with_balance(DEFAULT_MAIN_PURSE, U512::from(100u64))
+            .build();
+        env.assert_balance_delta(DEFAULT_MAIN_PURSE, 5, || {});
+    }
+
+    #[test]
+    fn test_get_main_purse_returns_account_purse() {
+        let main_purse = URef::new([4u8; 32], AccessRights::READ_ADD_WRITE);
+        let account = AccountConfig::new(AccountHash::new([5u8; 32]), main_purse);
+        dispatch_with(EnvBuilder::new().with_account(account).build(), |env| {
+            assert_eq!(
+                crate::casper_contract::contract_api::account::get_main_purse(),
+                main_purse
+            );
+            assert!(runtime::is_valid_uref(main_purse));
+            assert_eq!(
+                env.trace(),
+                vec![
+                    HostFunction::CasperGetMainPurse,
+                    HostFunction::CasperIsValidUref
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_immediate_account_is_configured_account() {
+        let account_hash = AccountHash::new([5u8; 32]);
+        let main_purse = URef::new([4u8; 32], AccessRights::READ_ADD_WRITE);
+        let env = EnvBuilder::new()
+            .with_account(AccountConfig::new(account_hash, main_purse))
+            .build();
+        dispatch_with(env, |_env| {
+            assert_eq!(get_immediate_account(), Ok(account_hash));
+        });
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            assert_eq!(get_immediate_entity_addr(), Ok(None));
+        });
+    }
+
+    #[test]
+    fn test_system_contracts_come_from_the_registry() {
+        use crate::casper_contract::contract_api::system;
+        use casper_types::system::SystemEntityType;
+        use veles_casper_ffi_shim::{DEFAULT_AUCTION_HASH, DEFAULT_MINT_HASH};
+
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            assert_eq!(system::get_mint(), ContractHash::new(DEFAULT_MINT_HASH));
+            assert_eq!(
+                system::get_auction(),
+                ContractHash::new(DEFAULT_AUCTION_HASH)
+            );
+            assert_eq!(
+                env.trace(),
+                vec![
+                    HostFunction::CasperGetSystemContract(SystemEntityType::Mint.into()),
+                    HostFunction::CasperGetSystemContract(SystemEntityType::Auction.into()),
+                ]
+            );
+        });
+
+        let mint = [0x11; 32];
+        let env = EnvBuilder::new()
+            .with_system_contract(SystemEntityType::Mint.into(), mint)
+            .build();
+        dispatch_with(env, |_env| {
+            assert_eq!(system::get_mint(), ContractHash::new(mint));
+
+            let mut hash = [0u8; 32];
+            let mut get_system_contract = |index, dest_size| {
+                let ret = unsafe {
+                    ext_ffi::casper_get_system_contract(index, hash.as_mut_ptr(), dest_size)
+                };
+                api_error::result_from(ret)
+            };
+            assert_eq!(
+                get_system_contract(4, 32),
+                Err(ApiError::InvalidSystemContract)
+            );
+            assert_eq!(get_system_contract(0, 31), Err(ApiError::BufferTooSmall));
+        });
+    }
+
+    #[test]
+    fn test_create_user_group_adds_a_group_to_the_package() {
+        use crate::casper_contract::contract_api::storage;
+        use casper_types::{Group, contracts};
+
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let (package_hash, _access_uref) = storage::create_contract_package_at_hash();
+
+            let urefs = create_user_group(package_hash, "admins", 2).unwrap();
+            assert_eq!(urefs.len(), 2);
+            for uref in &urefs {
+                assert_eq!(uref.access_rights(), AccessRights::READ_ADD_WRITE);
+                assert_eq!(read_key_typed::<()>(&Key::URef(*uref)), Ok(Some(())));
+            }
+            let package = env.package(package_hash).unwrap();
+            assert_eq!(
+                package.groups.get(&Group::new("admins")),
+                Some(&urefs.iter().copied().collect())
+            );
+            let Some(StoredValue::ContractPackage(contract_package)) =
+                env.storage().get(&Key::Hash(package_hash.value())).cloned()
+            else {
+                panic!("the package should be stored");
+            };
+            assert_eq!(contract_package.groups(), &package.groups);
+
+            assert_eq!(
+                create_user_group(package_hash, "admins", 1),
+                Err(contracts::Error::GroupAlreadyExists.into())
+            );
+            assert_eq!(
+                create_user_group(package_hash, "everyone", 99),
+                Err(contracts::Error::MaxTotalURefsExceeded.into())
+            );
+            assert_eq!(create_user_group(package_hash, "none", 0), Ok(Vec::new()));
+            assert!(
+                env.trace()
+                    .contains(&HostFunction::CasperCreateContractUserGroup)
+            );
+        });
+    }
+
+    #[test]
+    fn test_verify_signature_checks_encoded_signatures() {
+        use casper_types::{SecretKey, crypto};
+
+        let secret_key = SecretKey::ed25519_from_bytes([7; 32]).unwrap();
+        let public_key = PublicKey::from(&secret_key);
+        let signature = crypto::sign(b"message", &secret_key, &public_key)
+            .to_bytes()
+            .unwrap();
+        let mut trailing = signature.clone();
+        trailing.push(0);
+
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            assert_eq!(
+                verify_signature(b"message", &signature, &public_key),
+                Ok(())
+            );
+            assert_eq!(
+                verify_signature(b"other message", &signature, &public_key),
+                Err(ApiError::InvalidArgument)
+            );
+            // Malformed signatures never reach the host
+            for malformed in [&trailing[..], &signature[..10], &[]] {
+                assert_eq!(
+                    verify_signature(b"message", malformed, &public_key),
+                    Err(ApiError::InvalidArgument)
+                );
+            }
+            assert_eq!(
+                env.trace(),
+                vec![
+                    HostFunction::CasperVerifySignature,
+                    HostFunction::CasperVerifySignature
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_get_phase_reads_seeded_phase() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            assert_eq!(get_phase(), Phase::Session);
+            assert_eq!(env.trace(), vec![HostFunction::CasperGetPhase]);
+        });
+        dispatch_with(
+            EnvBuilder::new().with_phase(Phase::Payment).build(),
+            |_env| {
+                assert_eq!(get_phase(), Phase::Payment);
+                assert_eq!(runtime::get_phase(), Phase::Payment);
+            },
+        );
+    }
+
+    struct Noted;
+
+    impl CasperMessage for Noted {
+        const TOPIC_NAME: &'static str = "noted";
+        const TOPIC_NAME_HASH: [u8; 32] = [0u8; 32];
+
+        fn into_message_payload(self) -> Result<MessagePayload, ApiError> {
+            Ok(MessagePayload::Bytes(Bytes::from(vec![1, 2, 3])))
+        }
+    }
+
+    #[test]
+    fn test_emit_message_is_recorded() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            emit_message(Noted).unwrap();
+            let messages = env.messages();
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].topic_name, Noted::TOPIC_NAME);
+            assert_eq!(
+                messages[0].payload,
+                MessagePayload::Bytes(Bytes::from(vec![1, 2, 3]))
+            );
+            assert_eq!(env.trace(), vec![HostFunction::CasperEmitMessage]);
+        });
+    }
+
+    #[test]
+    fn test_register_message_topic_refuses_duplicates() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            assert_eq!(register_message_topic::<Noted>(), Ok(()));
+            assert_eq!(
+                register_message_topic::<Noted>(),
+                Err(ApiError::MessageTopicAlreadyRegistered)
+            );
+            assert_eq!(
+                env.message_topics(),
+                BTreeSet::from([String::from("noted")])
+            );
+            assert_eq!(env.trace(), vec![HostFunction::CasperManageMessageTopic; 2]);
+        });
+    }
+
+    #[test]
+    fn test_emit_typed_registers_the_topic_if_needed() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            emit_typed(Noted).unwrap();
+            emit_typed(Noted).unwrap();
+            assert_eq!(
+                env.message_topics(),
+                BTreeSet::from([String::from("noted")])
+            );
+            assert_eq!(env.messages().len(), 2);
+        });
+
+        // Topics the contract was installed with aren't registered again
+        let env = EnvBuilder::new().with_message_topic("noted").build();
+        dispatch_with(env, |env| {
+            emit_typed(Noted).unwrap();
+            assert_eq!(env.messages()[0].topic_name, Noted::TOPIC_NAME);
+        });
+    }
+
+    struct Oversized;
+
+    impl CasperMessage for Oversized {
+        const TOPIC_NAME: &'static str = "oversized";
+        const TOPIC_NAME_HASH: [u8; 32] = [0u8; 32];
+
+        fn into_message_payload(self) -> Result<MessagePayload, ApiError> {
+            let size = NetworkLimits::DEFAULT.max_message_size as usize;
+            Ok(MessagePayload::Bytes(Bytes::from(vec![0; size])))
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "message on topic `oversized` is 1029 bytes long, the limit is 1024")]
+    fn test_emit_message_asserts_message_size() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let _ = emit_message(Oversized);
+        });
+    }
+
+    fn shim_print(bytes: &[u8]) {
+        unsafe { veles_casper_ffi_shim::casper_print(bytes.as_ptr(), bytes.len()) };
+    }
+
+    #[test]
+    fn test_prints_are_captured() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            shim_print(&String::from("balance: 42").to_bytes().unwrap());
+            let callee = env.enter_call(BTreeMap::new(), BTreeMap::new());
+            shim_print(&String::from("nested call").to_bytes().unwrap());
+            drop(callee);
+
+            assert_eq!(env.prints(), ["balance: 42", "nested call"]);
+            veles_casper_ffi_shim::assert_printed!(env, "balance");
+            veles_casper_ffi_shim::assert_printed!(env, regex = r"^balance: \d+$");
+            assert_eq!(
+                env.trace(),
+                vec![HostFunction::CasperPrint, HostFunction::CasperPrint]
+            );
+        });
+    }
+
+    #[test]
+    fn test_raw_prints_are_captured_lossily() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            shim_print(b"raw text");
+            shim_print(&[b'o', b'k', 0xff]);
+            assert_eq!(env.prints(), ["raw text", "ok\u{fffd}"]);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "no print contains \"missing\"")]
+    fn test_assert_printed_lists_prints() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            shim_print(&String::from("present").to_bytes().unwrap());
+            veles_casper_ffi_shim::assert_printed!(env, "missing");
+        });
+    }
+
+    #[test]
+    fn test_read_stored_value_reads_accounts_and_packages() {
+        let account_hash = AccountHash::new([3; 32]);
+        let account = Account::create(account_hash, Default::default(), DEFAULT_MAIN_PURSE);
+
+        let mut package = Package::default();
+        package.insert_entity_version(2, EntityAddr::SmartContract([4; 32]));
+        let package_hash = PackageHash::new([5; 32]);
+
+        let mut contract_package = ContractPackage::new(
+            URef::new([6; 32], AccessRights::READ_ADD_WRITE),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        contract_package.insert_contract_version(1, ContractHash::new([8; 32]));
+        let contract_package_hash = PackageHash::new([9; 32]);
+
+        let env = EnvBuilder::new()
+            .with_storage(
+                Key::Account(account_hash),
+                StoredValue::Account(account.clone()),
+            )
+            .with_storage(
+                Key::SmartContract(package_hash.value()),
+                StoredValue::SmartContract(package.clone()),
+            )
+            .with_storage(
+                Key::Hash(contract_package_hash.value()),
+                StoredValue::ContractPackage(contract_package.clone()),
+            )
+            .build();
+        dispatch_with(env, |_env| {
+            assert_eq!(read_account(account_hash), Ok(Some(account)));
+            assert_eq!(read_package(package_hash), Ok(Some(package)));
+            let read_contract_package = read_package(contract_package_hash).unwrap().unwrap();
+            assert_eq!(read_contract_package, Package::from(contract_package));
+            assert_eq!(read_contract_package.enabled_versions().version_count(), 1);
+
+            assert_eq!(read_account(AccountHash::new([0; 32])), Ok(None));
+            assert_eq!(read_package(PackageHash::new([0; 32])), Ok(None));
+        });
+    }
+
+    #[test]
+    fn test_read_key_only_reads_the_unit_value_as_the_tombstone() {
+        // A `Tuple1(Unit)` serializes to no bytes too, but isn't the tombstone
+        let env = EnvBuilder::new()
+            .with_storage(
+                VALUE_KEY,
+                StoredValue::CLValue(CLValue::from_t(((),)).unwrap()),
+            )
+            .build();
+        dispatch_with(env, |_env| {
+            assert_eq!(read_key::<u64>(&VALUE_KEY), Err(ApiError::EarlyEndOfStream));
+            assert_eq!(
+                read_key_typed::<u64>(&VALUE_KEY),
+                Err(ApiError::CLTypeMismatch)
+            );
+            assert_eq!(has_value(&VALUE_KEY), Ok(true));
+
+            clear_value(VALUE_KEY).unwrap();
+            assert_eq!(read_key::<u64>(&VALUE_KEY), Ok(None));
+            assert_eq!(read_key_typed::<u64>(&VALUE_KEY), Ok(None));
+            assert_eq!(read_key::<()>(&VALUE_KEY), Ok(Some(())));
+            assert_eq!(has_value(&VALUE_KEY), Ok(false));
+        });
+    }
+
+    #[test]
+    fn test_read_key_rejects_stored_values_that_are_not_cl_values() {
+        let account_hash = AccountHash::new([3; 32]);
+        let account = Account::create(account_hash, Default::default(), DEFAULT_MAIN_PURSE);
+        let env = EnvBuilder::new()
+            .with_storage(Key::Account(account_hash), StoredValue::Account(account))
+            .with_storage(
+                Key::Account(AccountHash::new([4; 32])),
+                StoredValue::CLValue(CLValue::from_t(1u64).unwrap()),
+            )
+            .build();
+        dispatch_with(env, |_env| {
+            assert_eq!(
+                read_key::<Bytes>(&Key::Account(account_hash)),
+                Err(ApiError::CLTypeMismatch)
+            );
+            assert!(matches!(
+                read_stored_value(&Key::Account(account_hash)),
+                Ok(Some(StoredValue::Account(_)))
+            ));
+            assert_eq!(
+                read_account(AccountHash::new([4; 32])),
+                Err(ApiError::CLTypeMismatch)
+            );
+        });
+    }
+
+    #[test]
+    fn test_call_contract_typed_runs_registered_entry_points() {
+        let callee = ContractHash::new([5; 32]);
+        let env = EnvBuilder::new()
+            .with_contract_entry_point(callee, "add", || {
+                let a: u64 = runtime::get_named_arg("a");
+                let b: u64 = runtime::get_named_arg("b");
+                ret(CLValue::from_t(a + b).unwrap_or_revert())
+            })
+            .with_contract_entry_point(callee, "fail", || revert(ApiError::User(7)))
+            .with_contract_entry_point(callee, "noop", || {})
+            .build();
+
+        dispatch_with(env.clone(), |env| {
+            let args = casper_types::runtime_args! { "a" => 2u64, "b" => 3u64 };
+            assert_eq!(call_contract_typed::<u64>(callee, "add", args), Ok(5));
+            assert_eq!(
+                call_contract_typed::<()>(callee, "noop", RuntimeArgs::new()),
+                Ok(())
+            );
+
+            let trace = env.trace_with_depth();
+            assert!(trace.contains(&(1, HostFunction::CasperGetNamedArg)));
+            assert_eq!(
+                trace
+                    .iter()
+                    .filter(|(depth, host_function)| *depth == 0
+                        && *host_function == HostFunction::CasperCallContract)
+                    .count(),
+                2
+            );
+        });
+
+        // Like on-chain, the callee's revert reverts the calling entry point and its writes
+        let result: Result<(), _> = run_entry_point(env.clone(), |_env| {
+            runtime::put_key("written", Key::Hash([1; 32]));
+            let _ = call_contract_typed::<()>(callee, "fail", RuntimeArgs::new());
+            unreachable!("the callee's revert should unwind through the caller");
+        });
+        assert_eq!(result.unwrap_err().api_error, ApiError::User(7));
+        dispatch_with(env, |_env| assert!(!runtime::has_key("written")));
+    }
+
+    #[test]
+    fn test_dictionary_item_addr_matches_the_node() {
+        // No `Env` is active: indexers derive keys without the shim
+        let seed = URef::new([7; 32], casper_types::AccessRights::READ_ADD_WRITE);
+        let item_key = dictionary_item_key(&seed, b"balance");
+        assert_eq!(item_key, Key::dictionary(seed, b"balance"));
+        assert_eq!(
+            item_key,
+            Key::Dictionary(dictionary_item_addr(&seed, b"balance"))
+        );
+        assert_ne!(
+            dictionary_item_addr(&seed, b"balances"),
+            dictionary_item_addr(&seed, b"balance")
+        );
+
+        let mut preimage = seed.addr().to_vec();
+        preimage.extend_from_slice(b"balance");
+        assert_eq!(
+            dictionary_item_addr(&seed, b"balance"),
+            Digest::hash(preimage).value()
+        );
+    }
+
+    #[test]
+    fn test_generic_hash_sized_passes_its_size() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let digest = generic_hash_sized::<32>(HashAlgorithm::Blake2b, b"identifier").unwrap();
+            assert_eq!(digest, Digest::hash(b"identifier").value());
+            assert_eq!(
+                generic_hash(HashAlgorithm::Blake2b, b"identifier").unwrap(),
+                digest
+            );
+
+            // Like the engine, the shim only computes 32-byte digests
+            assert_eq!(
+                generic_hash_sized::<16>(HashAlgorithm::Blake2b, b"identifier"),
+                Err(ApiError::BufferTooSmall)
+            );
+            let out_sizes: Vec<u64> = env
+                .trace_records()
+                .into_iter()
+                .filter(|record| record.host_function == HostFunction::CasperGenericHash)
+                .map(|record| record.arguments[4])
+                .collect();
+            assert_eq!(out_sizes, [32, 32, 16]);
+        });
+    }
+
+    #[test]
+    fn test_caller_info_is_read_within_limit() {
+        let account = AccountConfig::new(AccountHash::new([1u8; 32]), DEFAULT_MAIN_PURSE);
+        dispatch_with(EnvBuilder::new().with_account(account).build(), |_env| {
+            let caller = get_initiator_or_immediate(CallerAction::Initiator).unwrap();
+            assert_eq!(caller.kind(), 0);
+        });
+    }
+
+    #[test]
+    fn test_oversized_caller_info_is_rejected() {
+        let call_stack = (0..=u8::MAX)
+            .map(|n| Caller::initiator(AccountHash::new([n; 32])))
+            .collect();
+        dispatch_with(
+            EnvBuilder::new().with_call_stack(call_stack).build(),
+            |_env| {
+                assert_eq!(
+                    get_initiator_or_immediate(CallerAction::FullStack),
+                    Err(ApiError::OutOfMemory.into())
+                );
+                // The rejected buffer was discarded, the next host call fills it again
+                let initiator = get_initiator_or_immediate(CallerAction::Initiator).unwrap();
+                assert_eq!(initiator.kind(), 0);
+            },
+        );
+    }
+
+    #[test]
+    fn test_named_keys_are_listed_within_limit() {
+        let named_keys = BTreeMap::from([
+            ("a".into(), Key::Hash([1u8; 32])),
+            ("b".into(), Key::Account(AccountHash::new([2u8; 32]))),
+        ]);
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            assert_eq!(list_named_keys(), Ok(NamedKeys::new()));
+        });
+        dispatch_with(
+            EnvBuilder::new()
+                .with_named_keys(named_keys.clone())
+                .build(),
+            |_env| {
+                assert_eq!(list_named_keys(), Ok(NamedKeys::from(named_keys)));
+            },
+        );
+    }
+
+    #[test]
+    fn test_oversized_named_keys_are_rejected() {
+        let name_suffix = "x".repeat(1024);
+        let named_keys = (0..MAX_NAMED_KEYS_SIZE / 1024)
+            .map(|n| (format!("{n}{name_suffix}"), Key::Hash([0u8; 32])))
+            .collect();
+        dispatch_with(
+            EnvBuilder::new().with_named_keys(named_keys).build(),
+            |_env| {
+                assert_eq!(list_named_keys(), Err(ApiError::OutOfMemory));
+                // The rejected buffer was discarded, the next host call fills it again
+                assert!(new_dictionary_key().is_ok());
+            },
+        );
+    }
+}
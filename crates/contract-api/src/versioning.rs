@@ -0,0 +1,164 @@
+//! Helpers for tracking the installed version of an upgradable contract.
+//!
+//! The version of a contract is identified by a [`ContractVersionKey`], which combines the major
+//! protocol version with the contract version. Upgradable contracts usually store it as a string
+//! under a named key so that an upgrade can detect which version it replaces. This module keeps
+//! the format of that string in one place.
+use alloc::string::{String, ToString};
+
+use crate::{
+    casper_contract::contract_api::runtime,
+    casper_types::{
+        ApiError,
+        contracts::{ContractVersion, ContractVersionKey},
+    },
+    named_key::NamedKey,
+    utils,
+};
+
+/// Major protocol version assumed for legacy version strings that only store the contract version.
+///
+/// Such strings were written by contracts installed before protocol 2.0.
+pub const LEGACY_PROTOCOL_VERSION_MAJOR: u32 = 1;
+
+/// Returns the version key of `contract_version` under the current major protocol version.
+pub fn current_version_key(contract_version: ContractVersion) -> ContractVersionKey {
+    let (major, _, _) = runtime::get_protocol_version().destructure();
+    ContractVersionKey::new(major, contract_version)
+}
+
+/// Formats the version key as it is stored under a named key, i.e. `"major.version"`.
+pub fn format_version_key(version: ContractVersionKey) -> String {
+    version.to_string()
+}
+
+/// Parses a version key previously stored under a named key.
+///
+/// Besides the current `"major.version"` format this accepts the legacy formats already found on
+/// chain: `"major:version"` and a bare `"version"`, which is assumed to be installed under
+/// [`LEGACY_PROTOCOL_VERSION_MAJOR`].
+pub fn parse_version_key(value: &str) -> Option<ContractVersionKey> {
+    let value = value.trim();
+    match value.split_once(['.', ':']) {
+        Some((major, contract_version)) => {
+            let major = major.parse().ok()?;
+            let contract_version = contract_version.parse().ok()?;
+            Some(ContractVersionKey::new(major, contract_version))
+        }
+        None => {
+            let contract_version = value.parse().ok()?;
+            Some(ContractVersionKey::new(
+                LEGACY_PROTOCOL_VERSION_MAJOR,
+                contract_version,
+            ))
+        }
+    }
+}
+
+/// Stores the version key under the given named key.
+///
+/// Writes into the existing `URef` if the named key is already present, otherwise creates a new
+/// `URef` and puts it into the named keys of the current context.
+pub fn write_version_named_key(
+    named_key: &NamedKey,
+    version: ContractVersionKey,
+) -> Result<(), ApiError> {
+    let value = format_version_key(version);
+    match named_key.get()? {
+        Some(_) => named_key.write(&value),
+        None => {
+            named_key
+                .get_or_init(|| utils::new_uref_key(value))?
+                .put_to_named_keys()?;
+            Ok(())
+        }
+    }
+}
+
+/// Reads the version key stored under the given named key.
+///
+/// Returns `None` if the named key is missing or the stored value is not a version key in any of
+/// the formats accepted by [`parse_version_key`].
+pub fn read_version_named_key(named_key: &NamedKey) -> Option<ContractVersionKey> {
+    let value: String = named_key.read().ok()??;
+    parse_version_key(&value)
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::ProtocolVersion;
+    use veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+
+    use super::*;
+
+    const NAME: &str = "contract_version";
+
+    thread_local! {
+        static VERSION_KEY: NamedKey = const { NamedKey::from_name(NAME) };
+    }
+
+    #[test]
+    fn test_parse_current_format() {
+        assert_eq!(
+            parse_version_key("2.5"),
+            Some(ContractVersionKey::new(2, 5))
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy_colon_format() {
+        assert_eq!(
+            parse_version_key("1:3"),
+            Some(ContractVersionKey::new(1, 3))
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy_bare_version() {
+        assert_eq!(
+            parse_version_key("7"),
+            Some(ContractVersionKey::new(LEGACY_PROTOCOL_VERSION_MAJOR, 7))
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse_version_key(""), None);
+        assert_eq!(parse_version_key("v2"), None);
+        assert_eq!(parse_version_key("2.x"), None);
+        assert_eq!(parse_version_key("2.3.4"), None);
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let version = ContractVersionKey::new(2, 11);
+        assert_eq!(format_version_key(version), "2.11");
+        assert_eq!(
+            parse_version_key(&format_version_key(version)),
+            Some(version)
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_version_named_key() {
+        let env = EnvBuilder::new()
+            .with_protocol_version(ProtocolVersion::from_parts(2, 0, 0))
+            .build();
+        dispatch_with(env, |env| {
+            VERSION_KEY.with(|named_key| {
+                assert_eq!(read_version_named_key(named_key), None);
+
+                let version = current_version_key(1);
+                assert_eq!(version, ContractVersionKey::new(2, 1));
+                write_version_named_key(named_key, version).unwrap();
+                assert!(env.named_keys().contains_key(NAME));
+                assert_eq!(read_version_named_key(named_key), Some(version));
+
+                // Upgrades overwrite the stored value in place.
+                let upgraded = current_version_key(2);
+                write_version_named_key(named_key, upgraded).unwrap();
+                assert_eq!(read_version_named_key(named_key), Some(upgraded));
+            });
+        });
+    }
+}
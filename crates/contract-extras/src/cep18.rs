@@ -1,10 +1,35 @@
+//! CEP-18 fungible token, installed with [`install_contract`].
+//!
+//! Besides the standard `name`, `symbol`, `decimals` and `total_supply`, the installer reads
+//! optional arguments enabling features that cost nothing on tokens installed without them:
+//!
+//! - `enable_mint_burn`: admin-only `mint` and `burn`, paused independently of transfers with
+//!   `set_mint_paused` and `set_burn_paused`.
+//! - `enable_enumeration`: tracks holders in the `holders` dictionary, paged through with
+//!   `holder_count` and `holder_at`.
+//! - `enable_allowance_index`: indexes the `(owner, spender)` pairs holding a nonzero allowance,
+//!   see [`allowances::ALLOWANCE_PAIRS`].
+//! - `enable_allowance_expiry`: `approve` takes an optional `expiry` block time after which the
+//!   allowance reads as zero.
+//! - `enable_freezing`: admin-only `freeze` and `unfreeze`, see [`frozen`].
+//! - `enable_transfer_hook`: transfers to contracts call their `on_token_received`, see
+//!   [`transfer_hook`].
+//! - `icon_url` and `website`: stored under named keys of the same names and returned by a
+//!   `metadata` entry point.
+//!
+//! Every token accepts gasless approvals signed off-chain through `permit`, see [`permit`], including
+//! tokens installed before it once upgraded with [`upgrade`].
 pub mod constants;
 #[cfg(test)]
 pub mod entry_points;
 pub mod error;
 pub mod events;
+pub mod frozen;
+pub mod hooks;
 pub mod modalities;
+pub mod permit;
 pub mod security;
+pub mod transfer_hook;
 
 pub mod allowances;
 
@@ -12,53 +37,82 @@ pub mod balances;
 
 pub mod utils;
 
+pub use allowances::{allowance_item_key, base128_allowance_item_key};
+pub use balances::{balance_item_key, base128_balance_item_key};
+
 use alloc::{
     collections::BTreeMap,
-    format,
     string::{String, ToString},
     vec,
     vec::Vec,
 };
-use core::convert::TryFrom;
+use core::{cell::Cell, convert::TryFrom};
 use veles_casper_contract_api::{
     casper_contract::{
         contract_api::{
-            runtime::{self, put_key, revert},
+            runtime::{self, put_key},
             storage::{self, read},
         },
         unwrap_or_revert::UnwrapOrRevert,
     },
     casper_types::{
-        AddressableEntityHash, EntityAddr, Key, NamedKeys, U256, bytesrepr::ToBytes,
-        contract_messages::MessageTopicOperation, contracts::ContractPackageHash, runtime_args,
+        AddressableEntityHash, EntityAddr, EntityEntryPoint, Key, NamedKeys, PublicKey, U256,
+        bytesrepr::{Bytes, ToBytes},
+        contract_messages::MessageTopicOperation,
+        contracts::{ContractPackageHash, ContractVersion},
+        runtime_args,
     },
+    ctx::{StaticContext, UnwrapOrRevertCtx},
+    math::{CheckedStorageAdd, CheckedStorageSub},
     named_key::NamedKey,
     typed_uref::TypedURef,
+    utils::revert,
     veles_casper_contract_macros::casper,
+    versioning::{current_version_key, format_version_key},
 };
 use {
-    allowances::{read_allowance_from, write_allowance_to},
-    balances::{read_balance_from, transfer_balance, write_balance_to},
+    allowances::{
+        ALLOWANCE_PAIRS, TimedAllowance, read_allowance_from, read_timed_allowance_from,
+        write_allowance_to, write_timed_allowance_to,
+    },
+    balances::{HOLDERS, read_balance_from, transfer_balance, write_balance_to},
     constants::{
-        ADMIN_LIST, ARG_CONTRACT_HASH, ARG_DECIMALS, ARG_ENABLE_MINT_BURN, ARG_EVENTS,
-        ARG_EVENTS_MODE, ARG_NAME, ARG_PACKAGE_HASH, ARG_SYMBOL, ARG_TOTAL_SUPPLY, DICT_ALLOWANCES,
-        DICT_BALANCES, DICT_SECURITY_BADGES, ENTRY_POINT_INIT, MINTER_LIST, NONE_LIST,
-        PREFIX_ACCESS_KEY_NAME, PREFIX_CEP18, PREFIX_CONTRACT_NAME, PREFIX_CONTRACT_PACKAGE_NAME,
-        PREFIX_CONTRACT_VERSION,
+        ADMIN_LIST, ARG_CONTRACT_HASH, ARG_DECIMALS, ARG_ENABLE_ALLOWANCE_EXPIRY,
+        ARG_ENABLE_ALLOWANCE_INDEX, ARG_ENABLE_ENUMERATION, ARG_ENABLE_FREEZING,
+        ARG_ENABLE_MINT_BURN, ARG_ENABLE_TRANSFER_HOOK, ARG_EVENTS, ARG_EVENTS_MODE, ARG_EXPIRY,
+        ARG_ICON_URL, ARG_KEY_ENCODING, ARG_NAME, ARG_PACKAGE_HASH, ARG_SYMBOL, ARG_TOTAL_SUPPLY,
+        ARG_WEBSITE, DICT_ALLOWANCES, DICT_BALANCES, DICT_PERMIT_NONCES, DICT_SECURITY_BADGES,
+        ENTRY_POINT_APPROVE, ENTRY_POINT_FREEZE, ENTRY_POINT_INIT, ENTRY_POINT_METADATA,
+        ENTRY_POINT_SET_BURN_PAUSED, ENTRY_POINT_SET_MINT_PAUSED, ENTRY_POINT_UNFREEZE,
+        KEY_BURN_PAUSED, KEY_MINT_PAUSED, MINTER_LIST, NONE_LIST, PREFIX_ACCESS_KEY_NAME,
+        PREFIX_CEP18, PREFIX_CONTRACT_NAME, PREFIX_CONTRACT_PACKAGE_NAME, PREFIX_CONTRACT_VERSION,
     },
     error::Cep18Error,
     events::{
         Burn, ChangeEventsMode, ChangeSecurity, DecreaseAllowance, Event, IncreaseAllowance, Mint,
         SetAllowance, Transfer, TransferFrom, init_events,
     },
-    modalities::EventsMode,
+    frozen::{FROZEN, change_frozen, ensure_not_frozen},
+    hooks::hooks,
+    modalities::{EventsMode, KeyEncoding},
+    permit::PERMIT_NONCES,
     security::{SecurityBadge, change_sec_badge, sec_check},
+    transfer_hook::notify_recipient,
     utils::{
-        base64_encode, get_contract_version_key, get_immediate_caller,
-        get_optional_named_arg_with_user_errors, get_uref_with_user_errors,
+        base64_encode, get_immediate_caller, get_optional_named_arg_with_user_errors,
+        get_uref_with_user_errors,
     },
 };
 
+// Per-instance keys in the installing account, suffixed with the token name.
+static CONTRACT_HASH_KEY_PREFIX: NamedKey =
+    NamedKey::namespaced(PREFIX_CEP18, PREFIX_CONTRACT_NAME);
+static CONTRACT_VERSION_KEY_PREFIX: NamedKey =
+    NamedKey::namespaced(PREFIX_CEP18, PREFIX_CONTRACT_VERSION);
+static CONTRACT_PACKAGE_KEY_PREFIX: NamedKey =
+    NamedKey::namespaced(PREFIX_CEP18, PREFIX_CONTRACT_PACKAGE_NAME);
+static ACCESS_KEY_PREFIX: NamedKey = NamedKey::namespaced(PREFIX_CEP18, PREFIX_ACCESS_KEY_NAME);
+
 static NAME: NamedKey = NamedKey::from_name(ARG_NAME);
 pub static NAME_KEY: TypedURef<String> = TypedURef::from_named_key(&NAME);
 static SYMBOL: NamedKey = NamedKey::from_name(ARG_SYMBOL);
@@ -71,11 +125,43 @@ static EVENTS_MODE: NamedKey = NamedKey::from_name(ARG_EVENTS_MODE);
 pub static EVENTS_MODE_KEY: TypedURef<u8> = TypedURef::from_named_key(&EVENTS_MODE);
 static ENABLE_MINT_BURN: NamedKey = NamedKey::from_name(ARG_ENABLE_MINT_BURN);
 pub static ENABLE_MINT_BURN_KEY: TypedURef<u8> = TypedURef::from_named_key(&ENABLE_MINT_BURN);
+static KEY_ENCODING: NamedKey = NamedKey::from_name(ARG_KEY_ENCODING);
+pub static KEY_ENCODING_KEY: TypedURef<u8> = TypedURef::from_named_key(&KEY_ENCODING);
+static ENABLE_ENUMERATION: NamedKey = NamedKey::from_name(ARG_ENABLE_ENUMERATION);
+pub static ENABLE_ENUMERATION_KEY: TypedURef<bool> = TypedURef::from_named_key(&ENABLE_ENUMERATION);
+static ENABLE_ALLOWANCE_INDEX: NamedKey = NamedKey::from_name(ARG_ENABLE_ALLOWANCE_INDEX);
+pub static ENABLE_ALLOWANCE_INDEX_KEY: TypedURef<bool> =
+    TypedURef::from_named_key(&ENABLE_ALLOWANCE_INDEX);
+static ENABLE_ALLOWANCE_EXPIRY: NamedKey = NamedKey::from_name(ARG_ENABLE_ALLOWANCE_EXPIRY);
+pub static ENABLE_ALLOWANCE_EXPIRY_KEY: TypedURef<bool> =
+    TypedURef::from_named_key(&ENABLE_ALLOWANCE_EXPIRY);
+static ENABLE_FREEZING: NamedKey = NamedKey::from_name(ARG_ENABLE_FREEZING);
+pub static ENABLE_FREEZING_KEY: TypedURef<bool> = TypedURef::from_named_key(&ENABLE_FREEZING);
+static ENABLE_TRANSFER_HOOK: NamedKey = NamedKey::from_name(ARG_ENABLE_TRANSFER_HOOK);
+pub static ENABLE_TRANSFER_HOOK_KEY: TypedURef<bool> =
+    TypedURef::from_named_key(&ENABLE_TRANSFER_HOOK);
+static MINT_PAUSED: NamedKey = NamedKey::from_name(KEY_MINT_PAUSED);
+pub static MINT_PAUSED_KEY: TypedURef<bool> = TypedURef::from_named_key(&MINT_PAUSED);
+static BURN_PAUSED: NamedKey = NamedKey::from_name(KEY_BURN_PAUSED);
+pub static BURN_PAUSED_KEY: TypedURef<bool> = TypedURef::from_named_key(&BURN_PAUSED);
+
+static ICON_URL: NamedKey = NamedKey::from_name(ARG_ICON_URL);
+pub static ICON_URL_KEY: TypedURef<String> = TypedURef::from_named_key(&ICON_URL);
+static WEBSITE: NamedKey = NamedKey::from_name(ARG_WEBSITE);
+pub static WEBSITE_KEY: TypedURef<String> = TypedURef::from_named_key(&WEBSITE);
 
 pub static ALLOWANCES_DICT: NamedKey = NamedKey::from_name(DICT_ALLOWANCES);
 pub static BALANCES_DICT: NamedKey = NamedKey::from_name(DICT_BALANCES);
 pub static SECURITY_BADGES_DICT: NamedKey = NamedKey::from_name(DICT_SECURITY_BADGES);
 
+const fn unset(message: &'static str) -> StaticContext {
+    StaticContext::new(message).with_user_error(Cep18Error::FailedToReadFromStorage as u16)
+}
+const NAME_UNSET: StaticContext = unset("the name should be initialized");
+const SYMBOL_UNSET: StaticContext = unset("the symbol should be initialized");
+const DECIMALS_UNSET: StaticContext = unset("the decimals should be initialized");
+const TOTAL_SUPPLY_UNSET: StaticContext = unset("the total supply should be initialized");
+
 #[casper(contract)]
 pub mod cep18 {
     use alloc::collections::BTreeMap;
@@ -88,7 +174,7 @@ pub mod cep18 {
         Ok(NAME_KEY
             .read()
             .map_err(|_| Cep18Error::FailedToReturnEntryPointResult)?
-            .expect("Name should be initialized"))
+            .unwrap_or_revert_ctx(NAME_UNSET))
     }
 
     #[casper(export)]
@@ -96,7 +182,7 @@ pub mod cep18 {
         Ok(SYMBOL_KEY
             .read()
             .map_err(|_| Cep18Error::FailedToReturnEntryPointResult)?
-            .expect("Symbol should be initialized"))
+            .unwrap_or_revert_ctx(SYMBOL_UNSET))
     }
 
     #[casper(export)]
@@ -104,7 +190,7 @@ pub mod cep18 {
         Ok(DECIMALS_KEY
             .read()
             .map_err(|_| Cep18Error::FailedToReturnEntryPointResult)?
-            .expect("Decimals should be initialized"))
+            .unwrap_or_revert_ctx(DECIMALS_UNSET))
     }
 
     #[casper(export)]
@@ -112,7 +198,25 @@ pub mod cep18 {
         Ok(TOTAL_SUPPLY_KEY
             .read()
             .map_err(|_| Cep18Error::FailedToReturnEntryPointResult)?
-            .expect("Total supply should be initialized"))
+            .unwrap_or_revert_ctx(TOTAL_SUPPLY_UNSET))
+    }
+
+    /// Returns the `icon_url` and `website` of the token, keyed by those names, for wallets.
+    ///
+    /// Only available on tokens installed with at least one of them. Upgraded tokens installed
+    /// without metadata return an empty map.
+    #[casper(export)]
+    pub fn metadata() -> Result<BTreeMap<String, String>, Cep18Error> {
+        let mut metadata = BTreeMap::new();
+        for (name, key) in [(ARG_ICON_URL, &ICON_URL_KEY), (ARG_WEBSITE, &WEBSITE_KEY)] {
+            if let Some(value) = key
+                .read()
+                .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+            {
+                metadata.insert(name.to_string(), value);
+            }
+        }
+        Ok(metadata)
     }
 
     #[casper(export)]
@@ -120,26 +224,73 @@ pub mod cep18 {
         read_balance_from(address)
     }
 
+    /// Returns the number of holders with a nonzero balance.
+    ///
+    /// Only available on tokens installed with `enable_enumeration`.
+    #[casper(export)]
+    pub fn holder_count() -> Result<u64, Cep18Error> {
+        ensure_enumeration_enabled()?;
+        HOLDERS
+            .len()
+            .map_err(|_| Cep18Error::FailedToReadFromStorage)
+    }
+
+    /// Returns the holder at `index`, which off-chain tools combine with `balance_of` to page
+    /// through all balances.
+    ///
+    /// Indices are only stable while no holder drops to a zero balance, as the last holder then
+    /// takes its index. Only available on tokens installed with `enable_enumeration`.
+    #[casper(export)]
+    pub fn holder_at(index: u64) -> Result<Key, Cep18Error> {
+        ensure_enumeration_enabled()?;
+        HOLDERS
+            .get(index)
+            .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+            .ok_or(Cep18Error::HolderIndexOutOfRange)
+    }
+
     #[casper(export)]
     pub fn allowance(owner: Key, spender: Key) -> Result<U256, Cep18Error> {
         read_allowance_from(owner, spender)
     }
 
+    /// Approves `spender` to transfer `amount` of the caller's tokens.
+    ///
+    /// Tokens installed with `enable_allowance_expiry` accept an `expiry` block time in
+    /// milliseconds from which the allowance can't be spent, approvals without one never expire.
     #[casper(export)]
-    pub fn approve(spender: Key, amount: U256) -> Result<(), Cep18Error> {
-        let caller = get_immediate_caller();
-        if spender == caller {
-            return Err(Cep18Error::CannotTargetSelfUser);
-        }
+    pub fn approve(spender: Key, amount: U256, expiry: Option<u64>) -> Result<(), Cep18Error> {
+        set_allowance(get_immediate_caller(), spender, amount, expiry)
+    }
 
-        write_allowance_to(caller, spender, amount)?;
+    /// Sets the allowance of `spender` from the account of `owner` like `approve`, with a
+    /// signature of the permit by `owner` instead of a call from its account, see the
+    /// [`permit`](super::permit) module for the signed message.
+    ///
+    /// Anyone can submit the permit before the `deadline` block time in milliseconds, and only
+    /// once, optionally with the `nonce` it signs to fail with `PermitNonceMismatch` when it was
+    /// used or signed ahead. On tokens installed with `enable_allowance_expiry`, the allowance
+    /// never expires.
+    #[casper(export)]
+    pub fn permit(
+        owner: PublicKey,
+        spender: Key,
+        amount: U256,
+        deadline: u64,
+        nonce: Option<u64>,
+        signature: Bytes,
+    ) -> Result<(), Cep18Error> {
+        let token = runtime::get_key(ARG_PACKAGE_HASH).ok_or(Cep18Error::FailedToGetPackageKey)?;
+        let owner = super::permit::consume_permit(
+            token, &owner, spender, amount, deadline, nonce, &signature,
+        )?;
+        set_allowance(owner, spender, amount, None)
+    }
 
-        events::record_event_dictionary(Event::SetAllowance(SetAllowance {
-            owner: caller,
-            spender,
-            allowance: amount,
-        }));
-        Ok(())
+    /// Returns the nonce the next permit of the account `owner` signs.
+    #[casper(export)]
+    pub fn permit_nonce(owner: Key) -> Result<u64, Cep18Error> {
+        super::permit::permit_nonce(owner)
     }
 
     #[casper(export)]
@@ -168,6 +319,7 @@ pub mod cep18 {
         if spender == caller {
             return Err(Cep18Error::CannotTargetSelfUser);
         }
+        ensure_not_frozen(&[caller, spender])?;
 
         let current_allowance = read_allowance_from(caller, spender)?;
         let new_allowance = current_allowance.saturating_add(amount);
@@ -188,15 +340,22 @@ pub mod cep18 {
         if caller == recipient {
             return Err(Cep18Error::CannotTargetSelfUser);
         }
+        ensure_not_frozen(&[caller, recipient])?;
 
+        hooks()
+            .before_transfer(caller, recipient, amount)
+            .unwrap_or_revert();
         transfer_balance(caller, recipient, amount)?;
+        hooks()
+            .after_transfer(caller, recipient, amount)
+            .unwrap_or_revert();
 
         events::record_event_dictionary(Event::Transfer(Transfer {
             sender: caller,
             recipient,
             amount,
         }));
-        Ok(())
+        notify_recipient(caller, recipient, amount)
     }
 
     #[casper(export)]
@@ -205,18 +364,40 @@ pub mod cep18 {
         if owner == recipient {
             return Err(Cep18Error::CannotTargetSelfUser);
         }
+        ensure_not_frozen(&[owner, recipient, caller])?;
 
         if amount.is_zero() {
             return Ok(());
         }
 
-        let spender_allowance = read_allowance_from(owner, caller)?;
-        let new_spender_allowance = spender_allowance
-            .checked_sub(amount)
-            .ok_or(Cep18Error::InsufficientAllowance)?;
+        let spender_allowance = if allowance_expiry_enabled()? {
+            let allowance = read_timed_allowance_from(owner, caller)?;
+            match allowance {
+                Some(allowance)
+                    if !allowance.amount.is_zero()
+                        && allowance.is_expired_at(
+                            veles_casper_contract_api::utils::get_block_time().get(),
+                        ) =>
+                {
+                    return Err(Cep18Error::AllowanceExpired);
+                }
+                Some(allowance) => allowance.amount,
+                None => U256::zero(),
+            }
+        } else {
+            read_allowance_from(owner, caller)?
+        };
+        let new_spender_allowance =
+            spender_allowance.try_sub_or(amount, Cep18Error::InsufficientAllowance)?;
 
+        hooks()
+            .before_transfer(owner, recipient, amount)
+            .unwrap_or_revert();
         transfer_balance(owner, recipient, amount)?;
         write_allowance_to(owner, caller, new_spender_allowance)?;
+        hooks()
+            .after_transfer(owner, recipient, amount)
+            .unwrap_or_revert();
 
         events::record_event_dictionary(Event::TransferFrom(TransferFrom {
             spender: caller,
@@ -224,7 +405,7 @@ pub mod cep18 {
             recipient,
             amount,
         }));
-        Ok(())
+        notify_recipient(owner, recipient, amount)
     }
 
     #[casper(export)]
@@ -232,27 +413,13 @@ pub mod cep18 {
         ensure_mint_burn_enabled()?;
 
         sec_check(vec![SecurityBadge::Admin, SecurityBadge::Minter])?;
+        ensure_not_paused(&MINT_PAUSED_KEY, Cep18Error::MintPaused)?;
+        hooks().before_mint(owner, amount).unwrap_or_revert();
 
-        let new_balance = {
-            let balance = read_balance_from(owner)?;
-            balance.checked_add(amount).ok_or(Cep18Error::Overflow)?
-        };
-
-        let new_total_supply = {
-            let total_supply = TOTAL_SUPPLY_KEY
-                .read()
-                .map_err(|_| Cep18Error::FailedToReadFromStorage)?
-                .expect("Total supply should be initialized");
-
-            total_supply
-                .checked_add(amount)
-                .ok_or(Cep18Error::Overflow)?
-        };
-
+        let new_balance = read_balance_from(owner)?.try_add_or(amount, Cep18Error::Overflow)?;
+        TOTAL_SUPPLY_KEY.increase(amount, Cep18Error::Overflow)?;
         write_balance_to(owner, new_balance)?;
-        TOTAL_SUPPLY_KEY
-            .write(new_total_supply)
-            .map_err(|_| Cep18Error::FailedToReadFromStorage)?;
+        hooks().after_mint(owner, amount).unwrap_or_revert();
 
         events::record_event_dictionary(Event::Mint(Mint {
             recipient: owner,
@@ -266,39 +433,50 @@ pub mod cep18 {
         ensure_mint_burn_enabled()?;
 
         sec_check(vec![SecurityBadge::Admin, SecurityBadge::Minter])?;
+        ensure_not_paused(&BURN_PAUSED_KEY, Cep18Error::BurnPaused)?;
+        hooks().before_burn(owner, amount).unwrap_or_revert();
 
-        let new_balance = {
-            let balance = read_balance_from(owner)?;
-            balance
-                .checked_sub(amount)
-                .ok_or(Cep18Error::InsufficientBalance)?
-        };
-
-        let new_total_supply = {
-            let total_supply = TOTAL_SUPPLY_KEY
-                .read()
-                .map_err(|_| Cep18Error::FailedToReadFromStorage)?
-                .expect("Total supply should be initialized");
-            total_supply
-                .checked_sub(amount)
-                .ok_or(Cep18Error::FailedToChangeTotalSupply)?
-        };
-
+        let new_balance =
+            read_balance_from(owner)?.try_sub_or(amount, Cep18Error::InsufficientBalance)?;
+        TOTAL_SUPPLY_KEY.decrease(amount, Cep18Error::FailedToChangeTotalSupply)?;
         write_balance_to(owner, new_balance)?;
-        TOTAL_SUPPLY_KEY
-            .write(new_total_supply)
-            .map_err(|_| Cep18Error::FailedToReadFromStorage)?;
+        hooks().after_burn(owner, amount).unwrap_or_revert();
 
         events::record_event_dictionary(Event::Burn(Burn { owner, amount }));
         Ok(())
     }
 
+    /// Pauses minting if `paused`, or resumes it, leaving burns and transfers running.
+    ///
+    /// Reserved to admins of tokens installed with `enable_mint_burn`.
     #[casper(export)]
-    pub fn init() -> Result<(), Cep18Error> {
-        if veles_casper_contract_api::utils::get_key(DICT_ALLOWANCES).is_ok() {
-            return Err(Cep18Error::AlreadyInitialized);
-        }
+    pub fn set_mint_paused(paused: bool) -> Result<(), Cep18Error> {
+        change_paused(&MINT_PAUSED, paused)
+    }
+
+    /// Pauses burning if `paused`, or resumes it, see [`set_mint_paused`].
+    #[casper(export)]
+    pub fn set_burn_paused(paused: bool) -> Result<(), Cep18Error> {
+        change_paused(&BURN_PAUSED, paused)
+    }
+
+    /// Freezes `account`, which can then neither send nor receive tokens, approve spenders nor
+    /// spend allowances.
+    ///
+    /// Reserved to admins of tokens installed with `enable_freezing`.
+    #[casper(export)]
+    pub fn freeze(account: Key) -> Result<(), Cep18Error> {
+        change_frozen(account, true)
+    }
+
+    /// Unfreezes `account`, see [`freeze`].
+    #[casper(export)]
+    pub fn unfreeze(account: Key) -> Result<(), Cep18Error> {
+        change_frozen(account, false)
+    }
 
+    #[casper(export, init_once)]
+    pub fn init() -> Result<(), Cep18Error> {
         let package_hash: Key = runtime::get_named_arg(ARG_PACKAGE_HASH);
         veles_casper_contract_api::utils::put_key(ARG_PACKAGE_HASH, package_hash)
             .unwrap_or_revert();
@@ -316,6 +494,35 @@ pub mod cep18 {
             .get_or_init(veles_casper_contract_api::utils::new_dictionary_key)
             .and_then(|named_key| named_key.put_to_named_keys())
             .map_err(|_| Cep18Error::FailedToCreateDictionary)?;
+
+        if enumeration_enabled()? {
+            for named_key in HOLDERS.named_urefs() {
+                named_key
+                    .get_or_init(veles_casper_contract_api::utils::new_dictionary_key)
+                    .and_then(|named_key| named_key.put_to_named_keys())
+                    .map_err(|_| Cep18Error::FailedToCreateDictionary)?;
+            }
+        }
+        if allowance_index_enabled()? {
+            for named_key in ALLOWANCE_PAIRS.named_urefs() {
+                named_key
+                    .get_or_init(veles_casper_contract_api::utils::new_dictionary_key)
+                    .and_then(|named_key| named_key.put_to_named_keys())
+                    .map_err(|_| Cep18Error::FailedToCreateDictionary)?;
+            }
+        }
+        if freezing_enabled()? {
+            FROZEN
+                .named_uref()
+                .get_or_init(veles_casper_contract_api::utils::new_dictionary_key)
+                .and_then(|named_key| named_key.put_to_named_keys())
+                .map_err(|_| Cep18Error::FailedToCreateDictionary)?;
+        }
+        PERMIT_NONCES
+            .named_uref()
+            .get_or_init(veles_casper_contract_api::utils::new_dictionary_key)
+            .and_then(|named_key| named_key.put_to_named_keys())
+            .map_err(|_| Cep18Error::FailedToCreateDictionary)?;
         let initial_supply: U256 = runtime::get_named_arg(ARG_TOTAL_SUPPLY);
 
         let caller = get_immediate_caller();
@@ -462,11 +669,223 @@ pub(crate) fn ensure_mint_burn_enabled() -> Result<(), Cep18Error> {
     Ok(())
 }
 
+/// Returns `error` if the operation `flag` pauses is paused.
+///
+/// Tokens installed before the flags existed run the operation until an admin pauses it.
+fn ensure_not_paused(flag: &TypedURef<bool>, error: Cep18Error) -> Result<(), Cep18Error> {
+    let paused = flag
+        .read()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default();
+    if paused {
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Stores `paused` under `flag` on behalf of the calling admin, creating the flag of tokens
+/// installed before it existed.
+fn change_paused(flag: &NamedKey, paused: bool) -> Result<(), Cep18Error> {
+    ensure_mint_burn_enabled()?;
+    sec_check(vec![SecurityBadge::Admin])?;
+
+    let stored = flag
+        .get()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .is_some();
+    if stored {
+        flag.write(&paused)
+    } else {
+        flag.get_or_init(|| veles_casper_contract_api::utils::new_uref_key(paused))
+            .and_then(NamedKey::put_to_named_keys)
+            .map(|_| ())
+    }
+    .map_err(|_| Cep18Error::FailedToReadFromStorage)
+}
+
+pub(crate) fn ensure_enumeration_enabled() -> Result<(), Cep18Error> {
+    if !enumeration_enabled()? {
+        return Err(Cep18Error::EnumerationDisabled);
+    }
+    Ok(())
+}
+
+/// Caches a modality for the rest of the execution, as modalities are read on every balance and
+/// allowance access but never change after installation.
+#[cfg(target_arch = "wasm32")]
+struct ModalityCache<T>(Cell<Option<T>>);
+
+// Contracts run single-threaded on-chain
+#[cfg(target_arch = "wasm32")]
+unsafe impl<T> Sync for ModalityCache<T> {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: Copy> ModalityCache<T> {
+    fn get(&self) -> Option<T> {
+        self.0.get()
+    }
+
+    fn set(&self, modality: Option<T>) {
+        self.0.set(modality)
+    }
+}
+
+/// Declares the modality caches, statics on-chain and thread locals off-chain, where each test
+/// thread runs its own tokens through the shim.
+macro_rules! modality_caches {
+    ($($name:ident: $modality:ty),* $(,)?) => {
+        $(
+            #[cfg(target_arch = "wasm32")]
+            static $name: ModalityCache<$modality> = ModalityCache(Cell::new(None));
+        )*
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread_local! {
+            $(static $name: Cell<Option<$modality>> = const { Cell::new(None) };)*
+        }
+    };
+}
+
+modality_caches!(
+    KEY_ENCODING_CACHE: KeyEncoding,
+    ENUMERATION_CACHE: bool,
+    ALLOWANCE_INDEX_CACHE: bool,
+    ALLOWANCE_EXPIRY_CACHE: bool,
+    FREEZING_CACHE: bool,
+    TRANSFER_HOOK_CACHE: bool,
+);
+
+/// Returns the encoding of the balances and allowances dictionary item keys of this token.
+pub fn key_encoding() -> Result<KeyEncoding, Cep18Error> {
+    if let Some(key_encoding) = KEY_ENCODING_CACHE.get() {
+        return Ok(key_encoding);
+    }
+    let key_encoding = KEY_ENCODING_KEY
+        .read()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .map(KeyEncoding::try_from)
+        .transpose()?
+        .unwrap_or_default();
+    KEY_ENCODING_CACHE.set(Some(key_encoding));
+    Ok(key_encoding)
+}
+
+/// Returns whether this token keeps track of its holders, see [`cep18::holder_at`].
+///
+/// Tokens installed before the `enable_enumeration` argument existed don't track holders.
+pub fn enumeration_enabled() -> Result<bool, Cep18Error> {
+    if let Some(enabled) = ENUMERATION_CACHE.get() {
+        return Ok(enabled);
+    }
+    let enabled = ENABLE_ENUMERATION_KEY
+        .read()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default();
+    ENUMERATION_CACHE.set(Some(enabled));
+    Ok(enabled)
+}
+
+/// Returns whether this token indexes its allowances, see
+/// [`ALLOWANCE_PAIRS`](allowances::ALLOWANCE_PAIRS).
+///
+/// Tokens installed before the `enable_allowance_index` argument existed don't index allowances.
+pub fn allowance_index_enabled() -> Result<bool, Cep18Error> {
+    if let Some(enabled) = ALLOWANCE_INDEX_CACHE.get() {
+        return Ok(enabled);
+    }
+    let enabled = ENABLE_ALLOWANCE_INDEX_KEY
+        .read()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default();
+    ALLOWANCE_INDEX_CACHE.set(Some(enabled));
+    Ok(enabled)
+}
+
+/// Returns whether allowances of this token can expire, see [`TimedAllowance`].
+///
+/// Tokens installed before the `enable_allowance_expiry` argument existed store bare amounts.
+pub fn allowance_expiry_enabled() -> Result<bool, Cep18Error> {
+    if let Some(enabled) = ALLOWANCE_EXPIRY_CACHE.get() {
+        return Ok(enabled);
+    }
+    let enabled = ENABLE_ALLOWANCE_EXPIRY_KEY
+        .read()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default();
+    ALLOWANCE_EXPIRY_CACHE.set(Some(enabled));
+    Ok(enabled)
+}
+
+/// Sets the allowance of `spender` from `owner` for `approve` and `permit`, recording a
+/// [`SetAllowance`] event.
+fn set_allowance(
+    owner: Key,
+    spender: Key,
+    amount: U256,
+    expiry: Option<u64>,
+) -> Result<(), Cep18Error> {
+    if spender == owner {
+        return Err(Cep18Error::CannotTargetSelfUser);
+    }
+    ensure_not_frozen(&[owner, spender])?;
+
+    if allowance_expiry_enabled()? {
+        let expiry = expiry.unwrap_or(TimedAllowance::NEVER);
+        write_timed_allowance_to(owner, spender, TimedAllowance { amount, expiry })?;
+    } else if expiry.is_some() {
+        return Err(Cep18Error::AllowanceExpiryDisabled);
+    } else {
+        write_allowance_to(owner, spender, amount)?;
+    }
+
+    events::record_event_dictionary(Event::SetAllowance(SetAllowance {
+        owner,
+        spender,
+        allowance: amount,
+    }));
+    Ok(())
+}
+
+/// Returns whether admins of this token can freeze accounts, see [`frozen`].
+///
+/// Tokens installed before the `enable_freezing` argument existed can't freeze accounts.
+pub fn freezing_enabled() -> Result<bool, Cep18Error> {
+    if let Some(enabled) = FREEZING_CACHE.get() {
+        return Ok(enabled);
+    }
+    let enabled = ENABLE_FREEZING_KEY
+        .read()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default();
+    FREEZING_CACHE.set(Some(enabled));
+    Ok(enabled)
+}
+
+/// Returns whether transfers to contracts notify the recipient, see [`transfer_hook`].
+///
+/// Tokens installed before the `enable_transfer_hook` argument existed notify no recipient.
+pub fn transfer_hook_enabled() -> Result<bool, Cep18Error> {
+    if let Some(enabled) = TRANSFER_HOOK_CACHE.get() {
+        return Ok(enabled);
+    }
+    let enabled = ENABLE_TRANSFER_HOOK_KEY
+        .read()
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default();
+    TRANSFER_HOOK_CACHE.set(Some(enabled));
+    Ok(enabled)
+}
+
+/// Returns whether the calling account installed a token named `name`, which [`upgrade`] then
+/// upgrades.
+pub fn is_installed(name: &str) -> bool {
+    runtime::has_key(&CONTRACT_PACKAGE_KEY_PREFIX.instance_name(name))
+}
+
 pub fn upgrade(name: &str) {
     let entry_points = cep18::entry_points();
 
-    let package_key_name = &format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_PACKAGE_NAME}_{name}");
-    let contract_key_name = &format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_NAME}_{name}");
+    let package_key_name = &CONTRACT_PACKAGE_KEY_PREFIX.instance_name(name);
+    let contract_key_name = &CONTRACT_HASH_KEY_PREFIX.instance_name(name);
 
     let old_contract_package_hash = match runtime::get_key(package_key_name)
         .unwrap_or_revert_with(Cep18Error::FailedToGetOldPackageKey)
@@ -493,7 +912,7 @@ pub fn upgrade(name: &str) {
     );
 
     let version_value_uref = get_uref_with_user_errors(
-        &format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_VERSION}_{name}"),
+        &CONTRACT_VERSION_KEY_PREFIX.instance_name(name),
         Cep18Error::MissingVersionContractKey,
         Cep18Error::InvalidVersionContractKey,
     );
@@ -508,7 +927,20 @@ pub fn upgrade(name: &str) {
         BTreeMap::from([(ARG_EVENTS.to_string(), MessageTopicOperation::Add)])
     };
 
-    let named_keys = NamedKeys::new();
+    // Tokens installed before `init` was `init_once` are marked initialized, their `init` guarded
+    // itself with the allowances dictionary
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(
+        cep18::init::INIT_DONE_KEY_NAME.into(),
+        storage::new_uref(true).into(),
+    );
+    // Tokens installed before `permit` get their nonces dictionary, the engine keeps the named keys
+    // of the previous version over these
+    named_keys.insert(
+        DICT_PERMIT_NONCES.into(),
+        veles_casper_contract_api::utils::new_dictionary_key()
+            .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary),
+    );
 
     let (contract_hash, contract_version) = storage::add_contract_version(
         contract_package_hash,
@@ -523,15 +955,7 @@ pub fn upgrade(name: &str) {
     )
     .unwrap_or_revert_with(Cep18Error::FailedToDisableContractVersion);
 
-    runtime::put_key(
-        &format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_NAME}_{name}"),
-        Key::Hash(contract_hash.value()),
-    );
-
-    runtime::put_key(
-        &format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_VERSION}_{name}"),
-        storage::new_uref(get_contract_version_key(contract_version).to_string()).into(),
-    );
+    put_contract_keys(name, Key::Hash(contract_hash.value()), contract_version);
 
     if let Some(events_mode_u8) = events_mode {
         let wrapped_testnet_token = cep18::Client::new(contract_hash);
@@ -539,7 +963,47 @@ pub fn upgrade(name: &str) {
     }
 }
 
+/// Returns `entry_point` without its `arg` parameter.
+fn without_arg(entry_point: &EntityEntryPoint, arg: &str) -> EntityEntryPoint {
+    EntityEntryPoint::new(
+        entry_point.name(),
+        entry_point
+            .args()
+            .iter()
+            .filter(|parameter| parameter.name() != arg)
+            .cloned()
+            .collect(),
+        entry_point.ret().clone(),
+        entry_point.access().clone(),
+        entry_point.entry_point_type(),
+        entry_point.entry_point_payment(),
+    )
+}
+
+/// Stores the contract hash and version of the `name` instance in the installing account.
+fn put_contract_keys(name: &str, contract_hash_key: Key, contract_version: ContractVersion) {
+    runtime::put_key(
+        &CONTRACT_HASH_KEY_PREFIX.instance_name(name),
+        contract_hash_key,
+    );
+    runtime::put_key(
+        &CONTRACT_VERSION_KEY_PREFIX.instance_name(name),
+        storage::new_uref(format_version_key(current_version_key(contract_version))).into(),
+    );
+}
+
 pub fn install_contract(name: &str) {
+    install_contract_with(name, Vec::new(), NamedKeys::new());
+}
+
+/// Installs the `name` token like [`install_contract`], adding `entry_points` and `named_keys` of
+/// the wrapping contract, e.g. those of the [`ownable`](crate::ownable) and
+/// [`pausable`](crate::pausable) modules backing [`hooks::PausableHooks`].
+pub fn install_contract_with(
+    name: &str,
+    entry_points: Vec<EntityEntryPoint>,
+    named_keys: NamedKeys,
+) {
     let symbol: String = runtime::get_named_arg(ARG_SYMBOL);
     let decimals: u8 = runtime::get_named_arg(ARG_DECIMALS);
     let total_supply: U256 = runtime::get_named_arg(ARG_TOTAL_SUPPLY);
@@ -558,7 +1022,48 @@ pub fn install_contract(name: &str) {
     )
     .unwrap_or(0);
 
-    let mut named_keys = NamedKeys::new();
+    let key_encoding: u8 =
+        get_optional_named_arg_with_user_errors(ARG_KEY_ENCODING, Cep18Error::InvalidKeyEncoding)
+            .unwrap_or(KeyEncoding::Base64 as u8);
+    KeyEncoding::try_from(key_encoding).unwrap_or_revert();
+
+    let enable_enumeration: bool = get_optional_named_arg_with_user_errors(
+        ARG_ENABLE_ENUMERATION,
+        Cep18Error::InvalidEnableEnumerationFlag,
+    )
+    .unwrap_or(false);
+
+    let enable_allowance_index: bool = get_optional_named_arg_with_user_errors(
+        ARG_ENABLE_ALLOWANCE_INDEX,
+        Cep18Error::InvalidEnableAllowanceIndexFlag,
+    )
+    .unwrap_or(false);
+
+    let enable_allowance_expiry: bool = get_optional_named_arg_with_user_errors(
+        ARG_ENABLE_ALLOWANCE_EXPIRY,
+        Cep18Error::InvalidEnableAllowanceExpiryFlag,
+    )
+    .unwrap_or(false);
+
+    let enable_freezing: bool = get_optional_named_arg_with_user_errors(
+        ARG_ENABLE_FREEZING,
+        Cep18Error::InvalidEnableFreezingFlag,
+    )
+    .unwrap_or(false);
+
+    let enable_transfer_hook: bool = get_optional_named_arg_with_user_errors(
+        ARG_ENABLE_TRANSFER_HOOK,
+        Cep18Error::InvalidEnableTransferHookFlag,
+    )
+    .unwrap_or(false);
+
+    let icon_url: Option<String> =
+        get_optional_named_arg_with_user_errors(ARG_ICON_URL, Cep18Error::InvalidMetadata);
+    let website: Option<String> =
+        get_optional_named_arg_with_user_errors(ARG_WEBSITE, Cep18Error::InvalidMetadata);
+    let metadata_enabled = icon_url.is_some() || website.is_some();
+
+    let mut named_keys = named_keys;
 
     NAME.get_or_init(|| veles_casper_contract_api::utils::new_uref_key(name))
         .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
@@ -589,17 +1094,91 @@ pub fn install_contract(name: &str) {
         .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
         .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
 
-    let entry_points = cep18::entry_points();
+    KEY_ENCODING
+        .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(key_encoding))
+        .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+        .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+
+    ENABLE_ENUMERATION
+        .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(enable_enumeration))
+        .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+        .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+
+    ENABLE_ALLOWANCE_INDEX
+        .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(enable_allowance_index))
+        .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+        .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+
+    ENABLE_ALLOWANCE_EXPIRY
+        .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(enable_allowance_expiry))
+        .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+        .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+
+    ENABLE_FREEZING
+        .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(enable_freezing))
+        .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+        .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+
+    ENABLE_TRANSFER_HOOK
+        .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(enable_transfer_hook))
+        .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+        .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+
+    if enable_mint_burn != 0 {
+        for named_key in [&MINT_PAUSED, &BURN_PAUSED] {
+            named_key
+                .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(false))
+                .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+                .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+        }
+    }
+
+    for (named_key, value) in [(&ICON_URL, icon_url), (&WEBSITE, website)] {
+        if let Some(value) = value {
+            named_key
+                .get_or_init(|| veles_casper_contract_api::utils::new_uref_key(value))
+                .and_then(|named_key| named_key.append_to_named_keys(&mut named_keys))
+                .unwrap_or_revert_with(Cep18Error::FailedToCreateDictionary);
+        }
+    }
+
+    let entry_points = {
+        let mut all_entry_points = cep18::entry_points_vec();
+        // Tokens without metadata keep the entry points of the standard
+        if !metadata_enabled {
+            all_entry_points.retain(|entry_point| entry_point.name() != ENTRY_POINT_METADATA);
+        }
+        if !enable_freezing {
+            all_entry_points.retain(|entry_point| {
+                ![ENTRY_POINT_FREEZE, ENTRY_POINT_UNFREEZE].contains(&entry_point.name())
+            });
+        }
+        if enable_mint_burn == 0 {
+            all_entry_points.retain(|entry_point| {
+                ![ENTRY_POINT_SET_MINT_PAUSED, ENTRY_POINT_SET_BURN_PAUSED]
+                    .contains(&entry_point.name())
+            });
+        }
+        if !enable_allowance_expiry {
+            for entry_point in &mut all_entry_points {
+                if entry_point.name() == ENTRY_POINT_APPROVE {
+                    *entry_point = without_arg(entry_point, ARG_EXPIRY);
+                }
+            }
+        }
+        all_entry_points.extend(entry_points);
+        all_entry_points.into()
+    };
 
     let message_topics = BTreeMap::from([(ARG_EVENTS.to_string(), MessageTopicOperation::Add)]);
 
-    let package_hash_name = format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_PACKAGE_NAME}_{name}");
+    let package_hash_name = CONTRACT_PACKAGE_KEY_PREFIX.instance_name(name);
 
     let (contract_hash, contract_version) = storage::new_contract(
         entry_points,
         Some(named_keys),
         Some(package_hash_name.clone()),
-        Some(format!("{PREFIX_CEP18}_{PREFIX_ACCESS_KEY_NAME}_{name}")),
+        Some(ACCESS_KEY_PREFIX.instance_name(name)),
         Some(message_topics),
     );
 
@@ -608,15 +1187,7 @@ pub fn install_contract(name: &str) {
 
     let contract_hash_key = Key::Hash(contract_hash.value());
 
-    runtime::put_key(
-        &format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_NAME}_{name}"),
-        contract_hash_key,
-    );
-
-    runtime::put_key(
-        &format!("{PREFIX_CEP18}_{PREFIX_CONTRACT_VERSION}_{name}"),
-        storage::new_uref(get_contract_version_key(contract_version).to_string()).into(),
-    );
+    put_contract_keys(name, contract_hash_key, contract_version);
 
     let mut init_args = runtime_args! {
         ARG_TOTAL_SUPPLY => total_supply,
@@ -641,13 +1212,43 @@ pub fn install_contract(name: &str) {
 
 #[cfg(test)]
 mod tests {
-    use super::{cep18, entry_points::generate_entry_points};
+    use super::{
+        allowance_item_key,
+        allowances::{base128_allowance_item_key, read_allowance_with, write_allowance_with},
+        balance_item_key,
+        balances::{base128_balance_item_key, read_balance_with, write_balance_with},
+        cep18,
+        constants::{DICT_ALLOWANCES, DICT_BALANCES},
+        entry_points::generate_entry_points_with,
+        modalities::KeyEncoding,
+        put_contract_keys, write_allowance_to, write_balance_to,
+    };
     use alloc::{
         collections::{BTreeMap, BTreeSet},
         string::{String, ToString},
         vec::Vec,
     };
-    use veles_casper_contract_api::casper_types::{EntityEntryPoint, EntryPoints};
+    use veles_casper_contract_api::{
+        casper_contract::contract_api::storage,
+        casper_types::{
+            EntityAddr, EntityEntryPoint, EntryPoints, Key, U256, account::AccountHash,
+            contracts::ContractPackageHash,
+        },
+        utils,
+        veles_casper_ffi_shim::{EnvBuilder, dispatch_with},
+    };
+
+    const OWNER: Key = Key::Account(AccountHash::new([1; 32]));
+    const SPENDER: Key = Key::Hash([2; 32]);
+
+    fn parity_keys() -> [Key; 4] {
+        [
+            OWNER,
+            SPENDER,
+            Key::from(ContractPackageHash::new([3; 32])),
+            Key::AddressableEntity(EntityAddr::new_smart_contract([4; 32])),
+        ]
+    }
 
     fn as_map(entry_points: EntryPoints) -> BTreeMap<String, EntityEntryPoint> {
         entry_points
@@ -660,7 +1261,7 @@ mod tests {
     #[test]
     fn generate_entry_points_match() {
         let macro_entry_points = as_map(cep18::entry_points());
-        let manual_entry_points = as_map(generate_entry_points());
+        let manual_entry_points = as_map(generate_entry_points_with(true, true, true, true));
 
         let manual_keys: BTreeSet<_> = manual_entry_points.keys().cloned().collect();
         let macro_keys: BTreeSet<_> = macro_entry_points.keys().cloned().collect();
@@ -705,4 +1306,168 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn allowance_item_key_matches_stored_key() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+            write_allowance_to(OWNER, SPENDER, U256::from(42)).unwrap();
+
+            let items = env.dictionary(dictionary).unwrap();
+            let expected_key = allowance_item_key(&OWNER, &SPENDER);
+            assert_eq!(items.len(), 1);
+            assert_eq!(
+                items[&expected_key].clone().into_t::<U256>().unwrap(),
+                U256::from(42)
+            );
+            assert_ne!(expected_key, allowance_item_key(&SPENDER, &OWNER));
+        });
+    }
+
+    #[test]
+    fn balance_item_key_matches_stored_key() {
+        let package = Key::from(ContractPackageHash::new([3; 32]));
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary(DICT_BALANCES).unwrap();
+            write_balance_to(OWNER, U256::from(7)).unwrap();
+            write_balance_to(package, U256::from(9)).unwrap();
+
+            let items = env.dictionary(dictionary).unwrap();
+            assert_eq!(items.len(), 2);
+            assert_eq!(
+                items[&balance_item_key(&OWNER)]
+                    .clone()
+                    .into_t::<U256>()
+                    .unwrap(),
+                U256::from(7)
+            );
+            assert_eq!(
+                items[&balance_item_key(&package)]
+                    .clone()
+                    .into_t::<U256>()
+                    .unwrap(),
+                U256::from(9)
+            );
+        });
+    }
+
+    #[test]
+    fn base128_balances_match_base64_balances() {
+        let unmigrated = Key::Account(AccountHash::new([5; 32]));
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary(DICT_BALANCES).unwrap();
+            let keys = parity_keys();
+            for (index, owner) in keys.iter().enumerate() {
+                let amount = U256::from(1_000 * (index + 1));
+                write_balance_with(KeyEncoding::Base64, *owner, amount).unwrap();
+                write_balance_with(KeyEncoding::Base128, *owner, amount).unwrap();
+            }
+            write_balance_with(KeyEncoding::Base64, unmigrated, U256::from(1)).unwrap();
+
+            let items = env.dictionary(dictionary).unwrap();
+            assert_eq!(items.len(), 2 * keys.len() + 1);
+            for (index, owner) in keys.iter().enumerate() {
+                let amount = U256::from(1_000 * (index + 1));
+                assert_eq!(read_balance_with(KeyEncoding::Base64, *owner), Ok(amount));
+                assert_eq!(read_balance_with(KeyEncoding::Base128, *owner), Ok(amount));
+
+                let base128_key = base128_balance_item_key(owner);
+                assert_eq!(
+                    items[&base128_key].clone().into_t::<U256>().unwrap(),
+                    amount
+                );
+                assert!(base128_key.len() < balance_item_key(owner).len());
+            }
+            // Balances are not visible under the other encoding until migrated.
+            assert_eq!(
+                read_balance_with(KeyEncoding::Base128, unmigrated),
+                Ok(U256::zero())
+            );
+        });
+    }
+
+    #[test]
+    fn base128_allowances_match_base64_allowances() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+            let keys = parity_keys();
+            for owner in &keys {
+                for spender in keys.iter().filter(|spender| *spender != owner) {
+                    write_allowance_with(KeyEncoding::Base64, *owner, *spender, U256::from(7))
+                        .unwrap();
+                    write_allowance_with(KeyEncoding::Base128, *owner, *spender, U256::from(7))
+                        .unwrap();
+                }
+            }
+
+            let items = env.dictionary(dictionary).unwrap();
+            for owner in &keys {
+                for spender in keys.iter().filter(|spender| *spender != owner) {
+                    for key_encoding in [KeyEncoding::Base64, KeyEncoding::Base128] {
+                        assert_eq!(
+                            read_allowance_with(key_encoding, *owner, *spender),
+                            Ok(U256::from(7))
+                        );
+                    }
+                    let base128_key = base128_allowance_item_key(owner, spender);
+                    assert!(items.contains_key(&base128_key));
+                    assert!(items.contains_key(&allowance_item_key(owner, spender)));
+                    assert_ne!(base128_key, base128_allowance_item_key(spender, owner));
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn metadata_reads_seeded_keys() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            assert_eq!(cep18::metadata(), Ok(BTreeMap::new()));
+
+            super::WEBSITE
+                .get_or_init(|| utils::new_uref_key(String::from("https://example.com")))
+                .and_then(|named_key| named_key.put_to_named_keys())
+                .unwrap();
+            assert_eq!(
+                cep18::metadata(),
+                Ok(BTreeMap::from([(
+                    "website".to_string(),
+                    "https://example.com".to_string()
+                )]))
+            );
+
+            super::ICON_URL
+                .get_or_init(|| utils::new_uref_key(String::from("ipfs://icon")))
+                .and_then(|named_key| named_key.put_to_named_keys())
+                .unwrap();
+            let metadata = cep18::metadata().unwrap();
+            assert_eq!(metadata.keys().collect::<Vec<_>>(), ["icon_url", "website"]);
+            assert_eq!(metadata["icon_url"], "ipfs://icon");
+        });
+    }
+
+    #[test]
+    fn contract_key_names_match_legacy_format() {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let contract_hash_key = Key::Hash([5; 32]);
+            put_contract_keys("Token", contract_hash_key, 1);
+
+            let named_keys = env.named_keys();
+            assert_eq!(
+                named_keys.keys().collect::<Vec<_>>(),
+                ["cep18_contract_hash_Token", "cep18_contract_version_Token"]
+            );
+            assert_eq!(
+                named_keys.get("cep18_contract_hash_Token"),
+                Some(&contract_hash_key)
+            );
+        });
+        assert_eq!(
+            super::CONTRACT_PACKAGE_KEY_PREFIX.instance_name("Token"),
+            "cep18_contract_package_Token"
+        );
+        assert_eq!(
+            super::ACCESS_KEY_PREFIX.instance_name("Token"),
+            "cep18_contract_package_access_Token"
+        );
+    }
 }
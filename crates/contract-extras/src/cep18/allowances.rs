@@ -1,21 +1,201 @@
 //! Implementation of allowances.
-use super::{ALLOWANCES_DICT, error::Cep18Error, utils::make_dictionary_item_key};
-use veles_casper_contract_api::casper_types::{Key, U256};
+use super::{
+    ALLOWANCES_DICT, allowance_expiry_enabled, allowance_index_enabled,
+    constants::{DICT_ALLOWANCE_PAIR_INDICES, DICT_ALLOWANCE_PAIRS, DICT_ALLOWANCES},
+    error::Cep18Error,
+    key_encoding,
+    modalities::KeyEncoding,
+    utils::make_dictionary_item_key,
+};
+use alloc::string::String;
+use veles_casper_contract_api::{
+    casper_types::{CLType, CLTyped, Key, U256},
+    collections::{indexed_set::IndexedSet, mapping::Mapping},
+    keyspace,
+    named_key::NamedKey,
+    veles_casper_contract_macros::CasperSerialize,
+};
+
+/// Allowances of tokens installed with [`KeyEncoding::Base128`], keyed by `(owner, spender)`.
+pub static ALLOWANCES: Mapping<(Key, Key), U256> =
+    Mapping::from_named_key(NamedKey::from_name(DICT_ALLOWANCES));
+
+/// [`TimedAllowance`]s of tokens installed with [`KeyEncoding::Base128`] and
+/// `enable_allowance_expiry`, stored in the same dictionary as [`ALLOWANCES`].
+pub static TIMED_ALLOWANCES: Mapping<(Key, Key), TimedAllowance> =
+    Mapping::from_named_key(NamedKey::from_name(DICT_ALLOWANCES));
+
+/// An allowance of a token installed with `enable_allowance_expiry`, stored in the allowances
+/// dictionary in place of the bare amount.
+///
+/// `approve` sets the expiry, while `increase_allowance`, `decrease_allowance` and
+/// `transfer_from` keep it. Allowances approved without an expiry never expire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CasperSerialize)]
+pub struct TimedAllowance {
+    pub amount: U256,
+    /// Block time in milliseconds from which the allowance can no longer be spent.
+    pub expiry: u64,
+}
+
+impl CLTyped for TimedAllowance {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl TimedAllowance {
+    /// Expiry of allowances approved without one.
+    pub const NEVER: u64 = u64::MAX;
+
+    /// Returns whether the allowance can no longer be spent at block time `now`.
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        now >= self.expiry
+    }
+
+    /// Returns the spendable amount at block time `now`, zero once expired.
+    pub fn amount_at(&self, now: u64) -> U256 {
+        if self.is_expired_at(now) {
+            U256::zero()
+        } else {
+            self.amount
+        }
+    }
+}
+
+/// `(owner, spender)` pairs with a nonzero allowance of tokens installed with
+/// `enable_allowance_index`.
+///
+/// Dictionaries can't be enumerated, so this index lets off-chain tools export every allowance.
+/// Reading the contract's `allowance_pairs` dictionary at a single state root hash gives a
+/// consistent snapshot:
+///
+/// 1. The item [`VEC_LENGTH_KEY`] holds the number of pairs as a `u64`. It is missing until the
+///    first approval, meaning there are no pairs.
+/// 2. For each index below it, the item `index.dictionary_key()` holds an `(owner, spender)` pair
+///    of [`Key`]s.
+/// 3. The amount of each pair is stored in the `allowances` dictionary under
+///    [`allowance_item_key`], or [`base128_allowance_item_key`] with [`KeyEncoding::Base128`].
+///    Tokens installed with `enable_allowance_expiry` store a [`TimedAllowance`] instead, whose
+///    amount still counts after its expiry until the pair is approved again.
+///
+/// Indices are only stable while no allowance drops to zero, as the last pair then takes its index.
+///
+/// [`VEC_LENGTH_KEY`]: veles_casper_contract_api::collections::vector::VEC_LENGTH_KEY
+pub static ALLOWANCE_PAIRS: IndexedSet<(Key, Key)> = IndexedSet::from_named_keys(
+    NamedKey::from_name(DICT_ALLOWANCE_PAIRS),
+    NamedKey::from_name(DICT_ALLOWANCE_PAIR_INDICES),
+);
+
+/// Returns the dictionary item key under which the allowance of `spender` from `owner` is stored.
+///
+/// It is [`keyspace::cep18_allowance_item_key`], which off-chain code can use to query the
+/// allowances dictionary directly.
+pub fn allowance_item_key(owner: &Key, spender: &Key) -> String {
+    keyspace::cep18_allowance_item_key(owner, spender)
+}
+
+/// Returns the dictionary item key under which the allowance of `spender` from `owner` is stored
+/// by tokens installed with [`KeyEncoding::Base128`].
+pub fn base128_allowance_item_key(owner: &Key, spender: &Key) -> String {
+    keyspace::collections_item_key(&(*owner, *spender))
+}
 
 /// Writes an allowance for owner and spender for a specific amount.
+///
+/// Tokens with the allowance index enabled also add the pair to [`ALLOWANCE_PAIRS`] or remove it
+/// when the allowance drops to zero. Tokens with allowance expiry enabled keep the expiry of the
+/// current allowance.
 pub fn write_allowance_to(owner: Key, spender: Key, amount: U256) -> Result<(), Cep18Error> {
-    let dictionary_item_key = make_dictionary_item_key(&owner, &spender);
-    ALLOWANCES_DICT
-        .put_dict(dictionary_item_key, amount)
-        .map_err(|_| Cep18Error::FailedToReadFromStorage)
+    if allowance_expiry_enabled()? {
+        let expiry = read_timed_allowance_from(owner, spender)?
+            .map_or(TimedAllowance::NEVER, |allowance| allowance.expiry);
+        return write_timed_allowance_to(owner, spender, TimedAllowance { amount, expiry });
+    }
+    write_allowance_with(key_encoding()?, owner, spender, amount)?;
+    index_allowance(owner, spender, amount)
 }
 
 /// Reads an allowance for a owner and spender
+///
+/// Expired allowances of tokens with allowance expiry enabled read as zero.
 pub fn read_allowance_from(owner: Key, spender: Key) -> Result<U256, Cep18Error> {
-    let dictionary_item_key = make_dictionary_item_key(&owner, &spender);
-    let value = ALLOWANCES_DICT
-        .get_dict(&dictionary_item_key)
-        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
-        .unwrap_or_default();
+    if allowance_expiry_enabled()? {
+        let now = veles_casper_contract_api::utils::get_block_time().get();
+        return Ok(read_timed_allowance_from(owner, spender)?
+            .map_or(U256::zero(), |allowance| allowance.amount_at(now)));
+    }
+    read_allowance_with(key_encoding()?, owner, spender)
+}
+
+/// Writes the allowance of a token installed with `enable_allowance_expiry`.
+pub fn write_timed_allowance_to(
+    owner: Key,
+    spender: Key,
+    allowance: TimedAllowance,
+) -> Result<(), Cep18Error> {
+    match key_encoding()? {
+        KeyEncoding::Base64 => {
+            ALLOWANCES_DICT.put_dict(make_dictionary_item_key(&owner, &spender), allowance)
+        }
+        KeyEncoding::Base128 => TIMED_ALLOWANCES.insert(&(owner, spender), allowance),
+    }
+    .map_err(|_| Cep18Error::FailedToReadFromStorage)?;
+    index_allowance(owner, spender, allowance.amount)
+}
+
+/// Reads the allowance of a token installed with `enable_allowance_expiry`, expired or not.
+pub fn read_timed_allowance_from(
+    owner: Key,
+    spender: Key,
+) -> Result<Option<TimedAllowance>, Cep18Error> {
+    match key_encoding()? {
+        KeyEncoding::Base64 => ALLOWANCES_DICT.get_dict(make_dictionary_item_key(&owner, &spender)),
+        KeyEncoding::Base128 => TIMED_ALLOWANCES.get(&(owner, spender)),
+    }
+    .map_err(|_| Cep18Error::FailedToReadFromStorage)
+}
+
+/// Keeps [`ALLOWANCE_PAIRS`] in sync with an allowance write, if the index is enabled.
+fn index_allowance(owner: Key, spender: Key, amount: U256) -> Result<(), Cep18Error> {
+    if allowance_index_enabled()? {
+        let pair = (owner, spender);
+        if amount.is_zero() {
+            ALLOWANCE_PAIRS.remove(&pair)
+        } else {
+            ALLOWANCE_PAIRS.insert(&pair)
+        }
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?;
+    }
+    Ok(())
+}
+
+/// Writes an allowance for owner and spender using the given key encoding.
+pub fn write_allowance_with(
+    key_encoding: KeyEncoding,
+    owner: Key,
+    spender: Key,
+    amount: U256,
+) -> Result<(), Cep18Error> {
+    match key_encoding {
+        KeyEncoding::Base64 => {
+            ALLOWANCES_DICT.put_dict(make_dictionary_item_key(&owner, &spender), amount)
+        }
+        KeyEncoding::Base128 => ALLOWANCES.insert(&(owner, spender), amount),
+    }
+    .map_err(|_| Cep18Error::FailedToReadFromStorage)
+}
+
+/// Reads an allowance for owner and spender using the given key encoding.
+pub fn read_allowance_with(
+    key_encoding: KeyEncoding,
+    owner: Key,
+    spender: Key,
+) -> Result<U256, Cep18Error> {
+    let value = match key_encoding {
+        KeyEncoding::Base64 => ALLOWANCES_DICT.get_dict(make_dictionary_item_key(&owner, &spender)),
+        KeyEncoding::Base128 => ALLOWANCES.get(&(owner, spender)),
+    }
+    .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+    .unwrap_or_default();
     Ok(value)
 }
@@ -1,44 +1,92 @@
 //! Implementation of balances.
-use super::{BALANCES_DICT, error::Cep18Error, utils::base64_encode};
+use super::{
+    BALANCES_DICT,
+    constants::{DICT_BALANCES, DICT_HOLDER_INDICES, DICT_HOLDERS},
+    enumeration_enabled,
+    error::Cep18Error,
+    key_encoding,
+    modalities::KeyEncoding,
+};
 use alloc::string::String;
 use veles_casper_contract_api::{
-    casper_contract::unwrap_or_revert::UnwrapOrRevert,
-    casper_types::{Key, U256, bytesrepr::ToBytes},
+    casper_types::{Key, U256},
+    collections::{indexed_set::IndexedSet, mapping::Mapping},
+    keyspace,
+    math::{CheckedStorageAdd, CheckedStorageSub},
+    named_key::NamedKey,
 };
 
-/// Creates a dictionary item key for a dictionary item, by base64 encoding the Key argument
-/// since stringified Keys are too long to be used as dictionary keys.
+/// Balances of tokens installed with [`KeyEncoding::Base128`].
+pub static BALANCES: Mapping<Key, U256> =
+    Mapping::from_named_key(NamedKey::from_name(DICT_BALANCES));
+
+/// Holders with a nonzero balance of tokens installed with `enable_enumeration`.
+pub static HOLDERS: IndexedSet<Key> = IndexedSet::from_named_keys(
+    NamedKey::from_name(DICT_HOLDERS),
+    NamedKey::from_name(DICT_HOLDER_INDICES),
+);
+
+/// Returns the dictionary item key under which the balance of `owner` is stored.
+///
+/// The key is the base64 encoded Key argument since stringified Keys are too long to be used as
+/// dictionary keys. It is [`keyspace::cep18_balance_item_key`], which off-chain code can use to
+/// query the balances dictionary directly.
 #[inline]
-fn make_dictionary_item_key(owner: &Key) -> String {
-    let preimage = owner
-        .to_bytes()
-        .unwrap_or_revert_with(Cep18Error::FailedToConvertBytes);
-    // NOTE: As for now dictionary item keys are limited to 64 characters only. Instead of using
-    // hashing (which will effectively hash a hash) we'll use base64. Preimage is 33 bytes for
-    // both used Key variants, and approximated base64-encoded length will be 4 * (33 / 3) ~ 44
-    // characters.
-    // Even if the preimage increased in size we still have extra space but even in case of much
-    // larger preimage we can switch to base85 which has ratio of 4:5.
-    base64_encode(preimage)
+pub fn balance_item_key(owner: &Key) -> String {
+    keyspace::cep18_balance_item_key(owner)
+}
+
+/// Returns the dictionary item key under which the balance of `owner` is stored by tokens
+/// installed with [`KeyEncoding::Base128`].
+pub fn base128_balance_item_key(owner: &Key) -> String {
+    keyspace::collections_item_key(owner)
 }
 
 /// Writes token balance of a specified account into a dictionary.
+///
+/// Tokens with enumeration enabled also add the account to [`HOLDERS`] or remove it when the
+/// balance drops to zero.
 pub fn write_balance_to(address: Key, amount: U256) -> Result<(), Cep18Error> {
-    let dictionary_item_key = make_dictionary_item_key(&address);
-    BALANCES_DICT
-        .put_dict(dictionary_item_key, amount)
-        .map_err(|_| Cep18Error::FailedToReadFromStorage)
+    write_balance_with(key_encoding()?, address, amount)?;
+    if enumeration_enabled()? {
+        if amount.is_zero() {
+            HOLDERS.remove(&address)
+        } else {
+            HOLDERS.insert(&address)
+        }
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?;
+    }
+    Ok(())
 }
 
 /// Reads token balance of a specified account.
 ///
 /// If a given account does not have balances in the system, then a 0 is returned.
 pub fn read_balance_from(address: Key) -> Result<U256, Cep18Error> {
-    let dictionary_item_key = make_dictionary_item_key(&address);
-    let value = BALANCES_DICT
-        .get_dict::<_, U256>(&dictionary_item_key)
-        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
-        .unwrap_or_default();
+    read_balance_with(key_encoding()?, address)
+}
+
+/// Writes token balance of a specified account using the given key encoding.
+pub fn write_balance_with(
+    key_encoding: KeyEncoding,
+    address: Key,
+    amount: U256,
+) -> Result<(), Cep18Error> {
+    match key_encoding {
+        KeyEncoding::Base64 => BALANCES_DICT.put_dict(balance_item_key(&address), amount),
+        KeyEncoding::Base128 => BALANCES.insert(&address, amount),
+    }
+    .map_err(|_| Cep18Error::FailedToReadFromStorage)
+}
+
+/// Reads token balance of a specified account using the given key encoding.
+pub fn read_balance_with(key_encoding: KeyEncoding, address: Key) -> Result<U256, Cep18Error> {
+    let value = match key_encoding {
+        KeyEncoding::Base64 => BALANCES_DICT.get_dict::<_, U256>(&balance_item_key(&address)),
+        KeyEncoding::Base128 => BALANCES.get(&address),
+    }
+    .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+    .unwrap_or_default();
     Ok(value)
 }
 
@@ -50,19 +98,10 @@ pub fn transfer_balance(sender: Key, recipient: Key, amount: U256) -> Result<(),
     if sender == recipient || amount.is_zero() {
         return Ok(());
     }
-    let new_sender_balance = {
-        let sender_balance = read_balance_from(sender)?;
-        sender_balance
-            .checked_sub(amount)
-            .ok_or(Cep18Error::InsufficientBalance)?
-    };
-
-    let new_recipient_balance = {
-        let recipient_balance = read_balance_from(recipient)?;
-        recipient_balance
-            .checked_add(amount)
-            .ok_or(Cep18Error::Overflow)?
-    };
+    let new_sender_balance =
+        read_balance_from(sender)?.try_sub_or(amount, Cep18Error::InsufficientBalance)?;
+    let new_recipient_balance =
+        read_balance_from(recipient)?.try_add_or(amount, Cep18Error::Overflow)?;
 
     write_balance_to(sender, new_sender_balance)?;
     write_balance_to(recipient, new_recipient_balance)?;
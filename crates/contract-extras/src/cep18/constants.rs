@@ -13,40 +13,71 @@ pub const ENTRY_POINT_BURN: &str = "burn";
 pub const ENTRY_POINT_CHANGE_EVENTS_MODE: &str = "change_events_mode";
 pub const ENTRY_POINT_CHANGE_SECURITY: &str = "change_security";
 pub const ENTRY_POINT_DECIMALS: &str = "decimals";
+pub const ENTRY_POINT_FREEZE: &str = "freeze";
 pub const ENTRY_POINT_DECREASE_ALLOWANCE: &str = "decrease_allowance";
 pub const ENTRY_POINT_INCREASE_ALLOWANCE: &str = "increase_allowance";
 pub const ENTRY_POINT_INIT: &str = "init";
+pub const ENTRY_POINT_METADATA: &str = "metadata";
 pub const ENTRY_POINT_MINT: &str = "mint";
 pub const ENTRY_POINT_NAME: &str = "name";
+pub const ENTRY_POINT_ON_TOKEN_RECEIVED: &str = "on_token_received";
+pub const ENTRY_POINT_PERMIT: &str = "permit";
+pub const ENTRY_POINT_PERMIT_NONCE: &str = "permit_nonce";
+pub const ENTRY_POINT_SET_BURN_PAUSED: &str = "set_burn_paused";
+pub const ENTRY_POINT_SET_MINT_PAUSED: &str = "set_mint_paused";
 pub const ENTRY_POINT_SYMBOL: &str = "symbol";
 pub const ENTRY_POINT_TOTAL_SUPPLY: &str = "total_supply";
 pub const ENTRY_POINT_TRANSFER: &str = "transfer";
 pub const ENTRY_POINT_TRANSFER_FROM: &str = "transfer_from";
+pub const ENTRY_POINT_UNFREEZE: &str = "unfreeze";
 
+pub const ARG_ACCOUNT: &str = "account";
 pub const ARG_ADDRESS: &str = "address";
 pub const ARG_AMOUNT: &str = "amount";
 pub const ARG_CONTRACT_HASH: &str = "contract_hash";
 pub const ARG_DATA: &str = "data";
+pub const ARG_DEADLINE: &str = "deadline";
 pub const ARG_DECIMALS: &str = "decimals";
+pub const ARG_ENABLE_ALLOWANCE_EXPIRY: &str = "enable_allowance_expiry";
+pub const ARG_ENABLE_ALLOWANCE_INDEX: &str = "enable_allowance_index";
+pub const ARG_ENABLE_ENUMERATION: &str = "enable_enumeration";
+pub const ARG_ENABLE_FREEZING: &str = "enable_freezing";
 pub const ARG_ENABLE_MINT_BURN: &str = "enable_mint_burn";
+pub const ARG_ENABLE_TRANSFER_HOOK: &str = "enable_transfer_hook";
 pub const ARG_EVENTS: &str = "events";
 pub const ARG_EVENTS_MODE: &str = "events_mode";
+pub const ARG_EXPIRY: &str = "expiry";
 pub const ARG_FROM: &str = "from";
+pub const ARG_ICON_URL: &str = "icon_url";
 pub const ARG_ID: &str = "id";
+pub const ARG_KEY_ENCODING: &str = "key_encoding";
 pub const ARG_NAME: &str = "name";
+pub const ARG_NONCE: &str = "nonce";
 pub const ARG_OPERATOR: &str = "operator";
 pub const ARG_OWNER: &str = "owner";
 pub const ARG_PACKAGE_HASH: &str = "package_hash";
+pub const ARG_PAUSED: &str = "paused";
 pub const ARG_RECIPIENT: &str = "recipient";
+pub const ARG_SIGNATURE: &str = "signature";
 pub const ARG_SPENDER: &str = "spender";
 pub const ARG_SYMBOL: &str = "symbol";
 pub const ARG_TO: &str = "to";
 pub const ARG_TOTAL_SUPPLY: &str = "total_supply";
+pub const ARG_WEBSITE: &str = "website";
 
 pub const ADMIN_LIST: &str = "admin_list";
 pub const MINTER_LIST: &str = "minter_list";
 pub const NONE_LIST: &str = "none_list";
 
+pub const KEY_BURN_PAUSED: &str = "burn_paused";
+pub const KEY_MINT_PAUSED: &str = "mint_paused";
+
+pub const DICT_ALLOWANCE_PAIR_INDICES: &str = "allowance_pair_indices";
+pub const DICT_ALLOWANCE_PAIRS: &str = "allowance_pairs";
 pub const DICT_ALLOWANCES: &str = "allowances";
 pub const DICT_BALANCES: &str = "balances";
+pub const DICT_FROZEN: &str = "frozen";
+pub const DICT_HOLDER_INDICES: &str = "holder_indices";
+pub const DICT_HOLDERS: &str = "holders";
+pub const DICT_PERMIT_NONCES: &str = "permit_nonces";
 pub const DICT_SECURITY_BADGES: &str = "security_badges";
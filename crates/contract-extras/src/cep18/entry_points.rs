@@ -1,17 +1,20 @@
 //! Contains definition of the entry points.
-use alloc::{string::String, vec, vec::Vec};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
 use veles_casper_contract_api::casper_types::{
     CLType, CLTyped, EntityEntryPoint as EntryPoint, EntryPointAccess, EntryPointPayment,
-    EntryPointType, EntryPoints, Key, Parameter, U256,
+    EntryPointType, EntryPoints, Key, Parameter, PublicKey, U256, bytesrepr::Bytes,
 };
 
 use super::constants::{
-    ARG_ADDRESS, ARG_AMOUNT, ARG_EVENTS_MODE, ARG_OWNER, ARG_RECIPIENT, ARG_SPENDER,
-    ENTRY_POINT_ALLOWANCE, ENTRY_POINT_APPROVE, ENTRY_POINT_BALANCE_OF, ENTRY_POINT_BURN,
-    ENTRY_POINT_CHANGE_EVENTS_MODE, ENTRY_POINT_CHANGE_SECURITY, ENTRY_POINT_DECIMALS,
-    ENTRY_POINT_DECREASE_ALLOWANCE, ENTRY_POINT_INCREASE_ALLOWANCE, ENTRY_POINT_INIT,
-    ENTRY_POINT_MINT, ENTRY_POINT_NAME, ENTRY_POINT_SYMBOL, ENTRY_POINT_TOTAL_SUPPLY,
-    ENTRY_POINT_TRANSFER, ENTRY_POINT_TRANSFER_FROM,
+    ARG_ACCOUNT, ARG_ADDRESS, ARG_AMOUNT, ARG_DEADLINE, ARG_EVENTS_MODE, ARG_EXPIRY, ARG_NONCE,
+    ARG_OWNER, ARG_PAUSED, ARG_RECIPIENT, ARG_SIGNATURE, ARG_SPENDER, ENTRY_POINT_ALLOWANCE,
+    ENTRY_POINT_APPROVE, ENTRY_POINT_BALANCE_OF, ENTRY_POINT_BURN, ENTRY_POINT_CHANGE_EVENTS_MODE,
+    ENTRY_POINT_CHANGE_SECURITY, ENTRY_POINT_DECIMALS, ENTRY_POINT_DECREASE_ALLOWANCE,
+    ENTRY_POINT_FREEZE, ENTRY_POINT_INCREASE_ALLOWANCE, ENTRY_POINT_INIT, ENTRY_POINT_METADATA,
+    ENTRY_POINT_MINT, ENTRY_POINT_NAME, ENTRY_POINT_PERMIT, ENTRY_POINT_PERMIT_NONCE,
+    ENTRY_POINT_SET_BURN_PAUSED, ENTRY_POINT_SET_MINT_PAUSED, ENTRY_POINT_SYMBOL,
+    ENTRY_POINT_TOTAL_SUPPLY, ENTRY_POINT_TRANSFER, ENTRY_POINT_TRANSFER_FROM,
+    ENTRY_POINT_UNFREEZE,
 };
 
 /// Returns the `name` entry point.
@@ -84,6 +87,22 @@ pub fn approve() -> EntryPoint {
     )
 }
 
+/// Returns the `approve` entry point of tokens installed with `enable_allowance_expiry`.
+pub fn approve_with_expiry() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_APPROVE),
+        vec![
+            Parameter::new(ARG_SPENDER, Key::cl_type()),
+            Parameter::new(ARG_AMOUNT, U256::cl_type()),
+            Parameter::new(ARG_EXPIRY, u64::cl_type()),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
 /// Returns the `increase_allowance` entry point.
 pub fn increase_allowance() -> EntryPoint {
     EntryPoint::new(
@@ -238,6 +257,126 @@ pub fn init() -> EntryPoint {
     )
 }
 
+/// Returns the `permit` entry point.
+pub fn permit() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_PERMIT),
+        vec![
+            Parameter::new(ARG_OWNER, PublicKey::cl_type()),
+            Parameter::new(ARG_SPENDER, Key::cl_type()),
+            Parameter::new(ARG_AMOUNT, U256::cl_type()),
+            Parameter::new(ARG_DEADLINE, u64::cl_type()),
+            Parameter::new(ARG_NONCE, u64::cl_type()),
+            Parameter::new(ARG_SIGNATURE, Bytes::cl_type()),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
+/// Returns the `permit_nonce` entry point.
+pub fn permit_nonce() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_PERMIT_NONCE),
+        vec![Parameter::new(ARG_OWNER, Key::cl_type())],
+        u64::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
+/// Returns the `metadata` entry point.
+pub fn metadata() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_METADATA),
+        Vec::new(),
+        BTreeMap::<String, String>::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
+/// Returns the `freeze` entry point.
+pub fn freeze() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_FREEZE),
+        vec![Parameter::new(ARG_ACCOUNT, Key::cl_type())],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
+/// Returns the `unfreeze` entry point.
+pub fn unfreeze() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_UNFREEZE),
+        vec![Parameter::new(ARG_ACCOUNT, Key::cl_type())],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
+/// Returns the `set_mint_paused` entry point.
+pub fn set_mint_paused() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_SET_MINT_PAUSED),
+        vec![Parameter::new(ARG_PAUSED, bool::cl_type())],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
+/// Returns the `set_burn_paused` entry point.
+pub fn set_burn_paused() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ENTRY_POINT_SET_BURN_PAUSED),
+        vec![Parameter::new(ARG_PAUSED, bool::cl_type())],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Called,
+        EntryPointPayment::Caller,
+    )
+}
+
+/// Returns the default set of CEP-18 token entry points, with `metadata` for tokens installed
+/// with an icon URL or website, an `expiry` argument of `approve` for tokens installed with
+/// `enable_allowance_expiry`, `freeze` and `unfreeze` for tokens installed with
+/// `enable_freezing`, and `set_mint_paused` and `set_burn_paused` for tokens installed with
+/// `enable_mint_burn`.
+pub fn generate_entry_points_with(
+    metadata_enabled: bool,
+    allowance_expiry_enabled: bool,
+    freezing_enabled: bool,
+    mint_burn_enabled: bool,
+) -> EntryPoints {
+    let mut entry_points = generate_entry_points();
+    if metadata_enabled {
+        entry_points.add_entry_point(metadata());
+    }
+    if allowance_expiry_enabled {
+        entry_points.add_entry_point(approve_with_expiry());
+    }
+    if freezing_enabled {
+        entry_points.add_entry_point(freeze());
+        entry_points.add_entry_point(unfreeze());
+    }
+    if mint_burn_enabled {
+        entry_points.add_entry_point(set_mint_paused());
+        entry_points.add_entry_point(set_burn_paused());
+    }
+    entry_points
+}
+
 /// Returns the default set of CEP-18 token entry points.
 pub fn generate_entry_points() -> EntryPoints {
     let mut entry_points = EntryPoints::new();
@@ -257,5 +396,7 @@ pub fn generate_entry_points() -> EntryPoints {
     entry_points.add_entry_point(burn());
     entry_points.add_entry_point(mint());
     entry_points.add_entry_point(change_events_mode());
+    entry_points.add_entry_point(permit());
+    entry_points.add_entry_point(permit_nonce());
     entry_points
 }
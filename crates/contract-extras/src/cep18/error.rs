@@ -9,7 +9,7 @@ use veles_casper_contract_api::casper_types::ApiError;
 /// return those via the [`Error::User`] variant or equivalently via the [`ApiError::User`]
 /// variant.
 #[repr(u16)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Cep18Error {
     /// CEP-18 contract called from within an invalid context.
     InvalidContext = 60000,
@@ -42,6 +42,8 @@ pub enum Cep18Error {
     /// The flag to enable the mint and burn mode is invalid.
     InvalidEnableMBFlag = 60014,
     /// This contract instance cannot be initialized again.
+    ///
+    /// No longer returned, `init` reverts with `UniversalError::AlreadyInitialized` instead.
     AlreadyInitialized = 60015,
     /// The mint and burn mode is disabled.
     MintBurnDisabled = 60016,
@@ -89,6 +91,53 @@ pub enum Cep18Error {
     MissingVersionContractKey = 60037,
     /// The provided version contract key is invalid.
     InvalidVersionContractKey = 60038,
+    /// The provided key encoding is invalid.
+    InvalidKeyEncoding = 60039,
+    /// The provided enable enumeration flag is invalid.
+    InvalidEnableEnumerationFlag = 60040,
+    /// Holder enumeration was not enabled at installation.
+    EnumerationDisabled = 60041,
+    /// The holder index is past the number of holders.
+    HolderIndexOutOfRange = 60042,
+    /// The provided enable allowance index flag is invalid.
+    InvalidEnableAllowanceIndexFlag = 60043,
+    /// The provided icon URL or website is invalid.
+    InvalidMetadata = 60044,
+    /// The provided enable allowance expiry flag is invalid.
+    InvalidEnableAllowanceExpiryFlag = 60045,
+    /// The allowance expired before it was spent.
+    AllowanceExpired = 60046,
+    /// An allowance expiry was passed to a token installed without `enable_allowance_expiry`.
+    AllowanceExpiryDisabled = 60047,
+    /// The provided enable freezing flag is invalid.
+    InvalidEnableFreezingFlag = 60048,
+    /// Freezing accounts was not enabled at installation.
+    FreezingDisabled = 60049,
+    /// The sender, recipient or spender is frozen.
+    AccountFrozen = 60050,
+    /// The provided enable transfer hook flag is invalid.
+    InvalidEnableTransferHookFlag = 60051,
+    /// The result of the recipient contract's `on_token_received` couldn't be read. A recipient
+    /// refusing the tokens reverts the transfer with its own error instead.
+    TransferHookFailed = 60052,
+    /// Minting is paused by an admin.
+    MintPaused = 60053,
+    /// Burning is paused by an admin.
+    BurnPaused = 60054,
+    /// The signature of a permit isn't a signature of the permit by its owner.
+    InvalidSignature = 60055,
+    /// The deadline of a permit has passed.
+    PermitExpired = 60056,
+    /// The nonce submitted with a permit isn't the next nonce of its owner.
+    PermitNonceMismatch = 60057,
+}
+
+/// Storage failures of the [`math`](veles_casper_contract_api::math) helpers, such as
+/// `TypedURef::increase`.
+impl From<ApiError> for Cep18Error {
+    fn from(_error: ApiError) -> Self {
+        Cep18Error::FailedToReadFromStorage
+    }
 }
 
 impl From<Cep18Error> for ApiError {
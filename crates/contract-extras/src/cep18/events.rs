@@ -49,6 +49,7 @@ pub enum Event {
     TransferFrom(TransferFrom),
     ChangeSecurity(ChangeSecurity),
     ChangeEventsMode(ChangeEventsMode),
+    ChangeFrozen(ChangeFrozen),
 }
 
 impl Event {
@@ -128,6 +129,13 @@ pub struct ChangeEventsMode {
     pub events_mode: u8,
 }
 
+/// Accounts an admin froze (`true`) or unfroze (`false`).
+#[derive(Serialize, Deserialize, Event, Debug, PartialEq, Eq)]
+pub struct ChangeFrozen {
+    pub admin: Key,
+    pub frozen_change_map: BTreeMap<Key, bool>,
+}
+
 fn ces(event: Event) {
     match event {
         Event::Mint(ev) => emit(ev),
@@ -139,6 +147,7 @@ fn ces(event: Event) {
         Event::TransferFrom(ev) => emit(ev),
         Event::ChangeSecurity(ev) => emit(ev),
         Event::ChangeEventsMode(ev) => emit(ev),
+        Event::ChangeFrozen(ev) => emit(ev),
     }
 }
 
@@ -161,7 +170,8 @@ pub fn init_events() -> Result<(), Cep18Error> {
             .with::<Transfer>()
             .with::<TransferFrom>()
             .with::<ChangeSecurity>()
-            .with::<ChangeEventsMode>();
+            .with::<ChangeEventsMode>()
+            .with::<ChangeFrozen>();
         init(schemas);
     }
 
@@ -0,0 +1,61 @@
+//! Accounts frozen by the admins of tokens installed with `enable_freezing`.
+use alloc::{collections::BTreeMap, vec};
+use veles_casper_contract_api::{
+    casper_types::Key, collections::mapping::Mapping, named_key::NamedKey,
+};
+
+use super::{
+    constants::DICT_FROZEN,
+    error::Cep18Error,
+    events::{self, ChangeFrozen, Event},
+    freezing_enabled,
+    security::{SecurityBadge, sec_check},
+    utils::get_immediate_caller,
+};
+
+/// Whether an account is frozen, keyed by the account.
+///
+/// Unfreezing stores `false`, as dictionary items can't be removed.
+pub static FROZEN: Mapping<Key, bool> = Mapping::from_named_key(NamedKey::from_name(DICT_FROZEN));
+
+/// Returns whether `account` is frozen.
+pub fn is_frozen(account: &Key) -> Result<bool, Cep18Error> {
+    Ok(FROZEN
+        .get(account)
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default())
+}
+
+/// Returns [`Cep18Error::AccountFrozen`] if any of `accounts` is frozen.
+///
+/// Tokens installed without `enable_freezing` don't read the dictionary.
+pub fn ensure_not_frozen(accounts: &[Key]) -> Result<(), Cep18Error> {
+    if !freezing_enabled()? {
+        return Ok(());
+    }
+    for account in accounts {
+        if is_frozen(account)? {
+            return Err(Cep18Error::AccountFrozen);
+        }
+    }
+    Ok(())
+}
+
+/// Freezes or unfreezes `account` on behalf of the calling admin, recording a
+/// [`ChangeFrozen`] event.
+pub(crate) fn change_frozen(account: Key, frozen: bool) -> Result<(), Cep18Error> {
+    if !freezing_enabled()? {
+        return Err(Cep18Error::FreezingDisabled);
+    }
+    sec_check(vec![SecurityBadge::Admin])?;
+
+    FROZEN
+        .insert(&account, frozen)
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?;
+
+    events::record_event_dictionary(Event::ChangeFrozen(ChangeFrozen {
+        admin: get_immediate_caller(),
+        frozen_change_map: BTreeMap::from([(account, frozen)]),
+    }));
+    Ok(())
+}
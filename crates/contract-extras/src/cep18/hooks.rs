@@ -0,0 +1,126 @@
+//! Hooks extending the CEP-18 entry points without forking the module.
+//!
+//! A contract wrapping the token links `veles-casper-contract-extras` with its `as_dependency`
+//! feature and exports the CEP-18 entry points itself, installing its hooks before each of them:
+//!
+//! ```ignore
+//! static HOOKS: MyHooks = MyHooks;
+//!
+//! veles_casper_contract_extras::export_cep18_symbols!(
+//!     veles_casper_contract_extras::cep18::hooks::set_hooks(&HOOKS)
+//! );
+//! ```
+//!
+//! Tokens that don't install hooks run [`NoHooks`], whose methods make no host calls, so the hooks
+//! leave the host gas of the entry points unchanged. See `examples/cep18-hooked` for a token taking
+//! a fee on transfers that can be paused.
+use core::cell::Cell;
+
+use veles_casper_contract_api::casper_types::{ApiError, Key, U256};
+
+use crate::pausable;
+
+/// Callbacks around the balance changes of the CEP-18 entry points.
+///
+/// `transfer` and `transfer_from` call the transfer hooks, `mint` and `burn` their own hooks. The
+/// `before_` hooks run once the arguments are validated and the `after_` hooks once the balances
+/// are written, before the event is recorded. An error returned by a hook reverts the entry point
+/// with it. All methods do nothing by default.
+pub trait Cep18Hooks {
+    fn before_transfer(&self, sender: Key, recipient: Key, amount: U256) -> Result<(), ApiError> {
+        let _ = (sender, recipient, amount);
+        Ok(())
+    }
+
+    fn after_transfer(&self, sender: Key, recipient: Key, amount: U256) -> Result<(), ApiError> {
+        let _ = (sender, recipient, amount);
+        Ok(())
+    }
+
+    fn before_mint(&self, owner: Key, amount: U256) -> Result<(), ApiError> {
+        let _ = (owner, amount);
+        Ok(())
+    }
+
+    fn after_mint(&self, owner: Key, amount: U256) -> Result<(), ApiError> {
+        let _ = (owner, amount);
+        Ok(())
+    }
+
+    fn before_burn(&self, owner: Key, amount: U256) -> Result<(), ApiError> {
+        let _ = (owner, amount);
+        Ok(())
+    }
+
+    fn after_burn(&self, owner: Key, amount: U256) -> Result<(), ApiError> {
+        let _ = (owner, amount);
+        Ok(())
+    }
+}
+
+/// The default hooks, doing nothing.
+pub struct NoHooks;
+
+impl Cep18Hooks for NoHooks {}
+
+/// Blocks transfers, mints and burns while the [`pausable`] module is paused.
+///
+/// The token needs the `paused` named key created by [`pausable::install`] and the pausable entry
+/// points to be paused by its owner.
+pub struct PausableHooks;
+
+impl Cep18Hooks for PausableHooks {
+    fn before_transfer(
+        &self,
+        _sender: Key,
+        _recipient: Key,
+        _amount: U256,
+    ) -> Result<(), ApiError> {
+        pausable::require_unpaused()
+    }
+
+    fn before_mint(&self, _owner: Key, _amount: U256) -> Result<(), ApiError> {
+        pausable::require_unpaused()
+    }
+
+    fn before_burn(&self, _owner: Key, _amount: U256) -> Result<(), ApiError> {
+        pausable::require_unpaused()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+struct HooksCell(Cell<&'static dyn Cep18Hooks>);
+
+// Contracts run single-threaded on-chain
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for HooksCell {}
+
+#[cfg(target_arch = "wasm32")]
+impl HooksCell {
+    fn get(&self) -> &'static dyn Cep18Hooks {
+        self.0.get()
+    }
+
+    fn set(&self, hooks: &'static dyn Cep18Hooks) {
+        self.0.set(hooks)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+static HOOKS: HooksCell = HooksCell(Cell::new(&NoHooks));
+
+// Off-chain each test thread runs its own tokens through the shim
+#[cfg(not(target_arch = "wasm32"))]
+std::thread_local! {
+    static HOOKS: Cell<&'static dyn Cep18Hooks> = const { Cell::new(&NoHooks) };
+}
+
+/// Makes the CEP-18 entry points call `hooks` for the rest of the execution.
+pub fn set_hooks(hooks: &'static dyn Cep18Hooks) {
+    HOOKS.set(hooks);
+}
+
+/// Returns the hooks installed with [`set_hooks`], [`NoHooks`] by default.
+pub fn hooks() -> &'static dyn Cep18Hooks {
+    HOOKS.get()
+}
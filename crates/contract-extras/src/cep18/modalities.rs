@@ -45,3 +45,45 @@ impl TryFrom<u8> for MintBurn {
         }
     }
 }
+
+/// Encoding of the dictionary item keys of the balances and allowances dictionaries.
+///
+/// The encoding is chosen at installation time through the `key_encoding` argument and cannot be
+/// changed afterwards. Tokens installed before the argument existed have no `key_encoding` named
+/// key and keep using [`KeyEncoding::Base64`].
+///
+/// # Migration
+///
+/// Both encodings store the same values under different item keys, so switching an existing
+/// token requires rewriting every balance and allowance under its [`KeyEncoding::Base128`] key.
+/// Dictionary items cannot be enumerated on chain, hence the set of holders has to be collected
+/// off-chain (i.e. from the `Mint`, `Transfer` and allowance events) and the migration is best done
+/// by installing a new token with the desired encoding and minting the collected balances into it.
+/// Off-chain readers should compute item keys with
+/// [`balance_item_key`](super::balances::balance_item_key) and
+/// [`allowance_item_key`](super::allowances::allowance_item_key) for [`KeyEncoding::Base64`], and
+/// with their `base128_` counterparts otherwise.
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Default, Copy, Clone)]
+pub enum KeyEncoding {
+    /// Legacy encoding: base64 encoded balance keys and hex encoded hashes for allowances.
+    #[default]
+    Base64 = 0,
+    /// Keys encoded with [`DictionaryKey`], as used by the [`Mapping`] collection.
+    ///
+    /// [`DictionaryKey`]: veles_casper_contract_api::collections::dictionary_key::DictionaryKey
+    /// [`Mapping`]: veles_casper_contract_api::collections::mapping::Mapping
+    Base128 = 1,
+}
+
+impl TryFrom<u8> for KeyEncoding {
+    type Error = Cep18Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(KeyEncoding::Base64),
+            1 => Ok(KeyEncoding::Base128),
+            _ => Err(Cep18Error::InvalidKeyEncoding),
+        }
+    }
+}
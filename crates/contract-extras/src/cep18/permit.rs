@@ -0,0 +1,101 @@
+//! Approvals signed off-chain by the owner of the tokens and submitted by anyone, such as a
+//! relayer paying for the transaction.
+//!
+//! The owner signs the 32 bytes returned by [`permit_message`]: the blake2b hash of the
+//! concatenated bytesrepr encodings of
+//!
+//! | field      | type        | value                                                  |
+//! |------------|-------------|--------------------------------------------------------|
+//! | domain     | `String`    | [`PERMIT_DOMAIN`]                                      |
+//! | token      | `Key`       | the `package_hash` named key of the token              |
+//! | owner      | `PublicKey` | the key signing the permit                             |
+//! | spender    | `Key`       | the account or contract allowed to spend the tokens    |
+//! | amount     | `U256`      | the allowance to set                                   |
+//! | nonce      | `u64`       | the [`permit_nonce`] of the owner, starting at zero    |
+//! | deadline   | `u64`       | block time in milliseconds from which it can't be used |
+//!
+//! Each accepted permit increments the nonce of its owner in the [`PERMIT_NONCES`] dictionary,
+//! keyed by the account hash `Key` of the owner, so it can't be replayed. The token hash binds it
+//! to one token, as permits of another token or network sign a different hash.
+//!
+//! Submitting the nonce along with the permit is optional: it only tells a permit signed over a
+//! used or future nonce ([`Cep18Error::PermitNonceMismatch`]) from one that doesn't verify
+//! ([`Cep18Error::InvalidSignature`]).
+use veles_casper_contract_api::{
+    casper_contract::unwrap_or_revert::UnwrapOrRevert,
+    casper_types::{Digest, Key, PublicKey, U256, bytesrepr::ToBytes},
+    collections::mapping::Mapping,
+    named_key::NamedKey,
+    utils::{get_block_time, verify_signature},
+};
+
+use super::{constants::DICT_PERMIT_NONCES, error::Cep18Error};
+
+/// Domain separating permits from other messages signed by the same key.
+pub const PERMIT_DOMAIN: &str = "cep18_permit";
+
+/// The number of permits each owner had accepted, keyed by the account hash `Key` of the owner.
+pub static PERMIT_NONCES: Mapping<Key, u64> =
+    Mapping::from_named_key(NamedKey::from_name(DICT_PERMIT_NONCES));
+
+/// Returns the message `owner` signs to let `spender` spend `amount` of the tokens of `token`,
+/// see the [module documentation](self).
+///
+/// It doesn't call into the host, so off-chain signers compute the same message.
+pub fn permit_message(
+    token: Key,
+    owner: &PublicKey,
+    spender: Key,
+    amount: U256,
+    nonce: u64,
+    deadline: u64,
+) -> [u8; 32] {
+    let mut preimage = PERMIT_DOMAIN
+        .to_bytes()
+        .unwrap_or_revert_with(Cep18Error::FailedToConvertBytes);
+    (token, owner.clone(), spender, amount, nonce, deadline)
+        .write_bytes(&mut preimage)
+        .unwrap_or_revert_with(Cep18Error::FailedToConvertBytes);
+    Digest::hash(preimage).value()
+}
+
+/// Returns the nonce the next permit of `owner` signs.
+pub fn permit_nonce(owner: Key) -> Result<u64, Cep18Error> {
+    Ok(PERMIT_NONCES
+        .get(&owner)
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?
+        .unwrap_or_default())
+}
+
+/// Checks a permit of `owner` signed over the current nonce and consumes the nonce, returning
+/// the account of the owner.
+///
+/// Fails with [`Cep18Error::PermitExpired`] from the `deadline` on, with
+/// [`Cep18Error::PermitNonceMismatch`] if `signed_nonce` is given and isn't the current nonce,
+/// and with [`Cep18Error::InvalidSignature`] if `signature` isn't a signature of the permit by
+/// `owner`.
+pub(crate) fn consume_permit(
+    token: Key,
+    owner: &PublicKey,
+    spender: Key,
+    amount: U256,
+    deadline: u64,
+    signed_nonce: Option<u64>,
+    signature: &[u8],
+) -> Result<Key, Cep18Error> {
+    if get_block_time().get() >= deadline {
+        return Err(Cep18Error::PermitExpired);
+    }
+    let owner_key = Key::Account(owner.to_account_hash());
+    let nonce = permit_nonce(owner_key)?;
+    if signed_nonce.is_some_and(|signed_nonce| signed_nonce != nonce) {
+        return Err(Cep18Error::PermitNonceMismatch);
+    }
+    let message = permit_message(token, owner, spender, amount, nonce, deadline);
+    verify_signature(&message, signature, owner).map_err(|_| Cep18Error::InvalidSignature)?;
+
+    PERMIT_NONCES
+        .insert(&owner_key, nonce + 1)
+        .map_err(|_| Cep18Error::FailedToReadFromStorage)?;
+    Ok(owner_key)
+}
@@ -0,0 +1,70 @@
+//! Notifying recipient contracts of the tokens they receive, on tokens installed with
+//! `enable_transfer_hook`.
+//!
+//! After a `transfer` or `transfer_from` to a contract, the token calls the
+//! `on_token_received(from: Key, amount: U256)` entry point of the recipient: of the latest
+//! version of the package for a `Key::Hash`, of the contract itself for a
+//! `Key::AddressableEntity`. A recipient refuses the tokens by reverting, which reverts the whole
+//! transfer with the recipient's error. Recipients that are accounts aren't called.
+use veles_casper_contract_api::{
+    casper_types::{
+        EntityAddr, Key, U256,
+        contracts::{ContractHash, ContractPackageHash},
+        runtime_args,
+    },
+    utils::{call_contract_typed, call_versioned_contract_typed},
+};
+
+use super::{
+    constants::{ARG_AMOUNT, ARG_FROM, ENTRY_POINT_ON_TOKEN_RECEIVED},
+    error::Cep18Error,
+    transfer_hook_enabled,
+};
+
+/// A recipient that is a contract.
+enum RecipientContract {
+    /// A contract package, as CEP-18 holders are identified, whose latest version is called.
+    Package(ContractPackageHash),
+    /// A single version of a contract.
+    Contract(ContractHash),
+}
+
+/// Returns the contract `recipient` is, if any.
+fn recipient_contract(recipient: &Key) -> Option<RecipientContract> {
+    match recipient {
+        Key::Hash(addr) => Some(RecipientContract::Package(ContractPackageHash::new(*addr))),
+        Key::AddressableEntity(EntityAddr::SmartContract(addr)) => {
+            Some(RecipientContract::Contract(ContractHash::new(*addr)))
+        }
+        _ => None,
+    }
+}
+
+/// Calls the `on_token_received` entry point of `recipient` if it is a contract, see the
+/// [module documentation](self).
+///
+/// Tokens installed without `enable_transfer_hook` call no recipient.
+pub fn notify_recipient(from: Key, recipient: Key, amount: U256) -> Result<(), Cep18Error> {
+    if !transfer_hook_enabled()? {
+        return Ok(());
+    }
+    let Some(contract) = recipient_contract(&recipient) else {
+        return Ok(());
+    };
+    let runtime_args = runtime_args! {
+        ARG_FROM => from,
+        ARG_AMOUNT => amount,
+    };
+    match contract {
+        RecipientContract::Package(package_hash) => call_versioned_contract_typed::<()>(
+            package_hash,
+            None,
+            ENTRY_POINT_ON_TOKEN_RECEIVED,
+            runtime_args,
+        ),
+        RecipientContract::Contract(contract_hash) => {
+            call_contract_typed::<()>(contract_hash, ENTRY_POINT_ON_TOKEN_RECEIVED, runtime_args)
+        }
+    }
+    .map_err(|_| Cep18Error::TransferHookFailed)
+}
@@ -6,18 +6,15 @@ use veles_casper_contract_api::casper_types::{
     account::AccountHash,
     api_error,
     bytesrepr::{self, FromBytes, ToBytes},
-    contracts::{ContractPackageHash, ContractVersionKey},
+    contracts::ContractPackageHash,
 };
 use veles_casper_contract_api::{
     casper_contract::{
-        contract_api::{
-            self,
-            runtime::{blake2b, get_protocol_version, revert},
-        },
+        contract_api::{self, runtime::blake2b},
         ext_ffi::{casper_get_key, casper_get_named_arg, casper_get_named_arg_size},
         unwrap_or_revert::UnwrapOrRevert,
     },
-    utils::CallerAction,
+    utils::{CallerAction, revert},
 };
 
 pub fn get_immediate_caller() -> Key {
@@ -56,11 +53,6 @@ pub fn get_immediate_caller() -> Key {
     }
 }
 
-pub fn get_contract_version_key(contract_version: u32) -> ContractVersionKey {
-    let (major, _, _) = get_protocol_version().destructure();
-    ContractVersionKey::new(major, contract_version)
-}
-
 pub fn get_named_arg_with_user_errors<T: FromBytes>(
     name: &str,
     missing: Cep18Error,
@@ -0,0 +1,218 @@
+//! Minimal CEP-78-style non-fungible tokens, tracking the owner of every token id.
+//!
+//! Tokens get sequential `u64` ids and are minted by the contract owner, see
+//! [`ownable`](crate::ownable). Token metadata is out of scope for now.
+//!
+//! The entry points are `mint_token`, `transfer_token`, `owner_of` and `token_balance_of`. They
+//! are prefixed where the CEP-78 names would clash with the [`cep18`](crate::cep18) exports of
+//! this crate. Use [`install`] in the installing session to create the named keys of the contract
+//! and [`message_topics`] to register the events.
+use alloc::{collections::BTreeMap, string::String, string::ToString};
+use veles_casper_contract_api::{
+    casper_types::{ApiError, Key, NamedKeys, contract_messages::MessageTopicOperation},
+    collections::mapping::Mapping,
+    macro_support::CasperMessage as _,
+    named_key::NamedKey,
+    typed_uref::TypedURef,
+    utils,
+    veles_casper_contract_macros::{CasperMessage, CasperSerialize, casper},
+};
+
+use crate::ownable;
+
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cep78Error {
+    TokenNotFound = 64000,
+    NotTokenOwner = 64001,
+}
+
+impl From<Cep78Error> for ApiError {
+    fn from(value: Cep78Error) -> Self {
+        ApiError::User(value as u16)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "cep78_token_minted")]
+pub struct TokenMinted {
+    pub token_id: u64,
+    pub owner: Key,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "cep78_token_transferred")]
+pub struct TokenTransferred {
+    pub token_id: u64,
+    pub sender: Key,
+    pub recipient: Key,
+}
+
+pub const OWNERS_DICT: &str = "cep78_owners";
+pub const BALANCES_DICT: &str = "cep78_balances";
+
+pub static OWNERS: Mapping<u64, Key> = Mapping::from_named_key(NamedKey::from_name(OWNERS_DICT));
+/// Number of tokens held by each owner.
+pub static BALANCES: Mapping<Key, u64> =
+    Mapping::from_named_key(NamedKey::from_name(BALANCES_DICT));
+
+static TOKEN_COUNT: NamedKey = NamedKey::from_name("cep78_token_count");
+pub static TOKEN_COUNT_TUREF: TypedURef<u64> = TypedURef::from_named_key(&TOKEN_COUNT);
+
+#[casper(contract)]
+pub mod cep78 {
+    use super::*;
+
+    /// Mints a new token to `to`, returning its id. Only the contract owner can mint.
+    #[casper(export)]
+    pub fn mint_token(to: Key) -> Result<u64, ApiError> {
+        ownable::ensure_owner()?;
+        let token_id = mint_to(to)?;
+
+        utils::emit_message(TokenMinted {
+            token_id,
+            owner: to,
+        })?;
+        Ok(token_id)
+    }
+
+    /// Transfers a token owned by the caller to `to`.
+    #[casper(export)]
+    pub fn transfer_token(token_id: u64, to: Key) -> Result<(), ApiError> {
+        let caller = Key::Account(utils::get_immediate_account()?);
+        transfer_from(caller, token_id, to)?;
+
+        utils::emit_message(TokenTransferred {
+            token_id,
+            sender: caller,
+            recipient: to,
+        })?;
+        Ok(())
+    }
+
+    #[casper(export)]
+    pub fn owner_of(token_id: u64) -> Result<Key, ApiError> {
+        get_owner(token_id)
+    }
+
+    #[casper(export)]
+    pub fn token_balance_of(owner: Key) -> Result<u64, ApiError> {
+        balance_of(&owner)
+    }
+}
+
+/// Creates the named keys of the token contract, to be called by the installing session before
+/// creating the contract with `named_keys`.
+pub fn install(named_keys: &mut NamedKeys) -> Result<(), ApiError> {
+    for named_key in [OWNERS.named_uref(), BALANCES.named_uref()] {
+        named_key
+            .get_or_init(utils::new_dictionary_key)?
+            .append_to_named_keys(named_keys)?;
+    }
+    TOKEN_COUNT
+        .get_or_init(|| utils::new_uref_key(0u64))?
+        .append_to_named_keys(named_keys)?;
+    Ok(())
+}
+
+/// Returns the message topics of the token events.
+pub fn message_topics() -> BTreeMap<String, MessageTopicOperation> {
+    [TokenMinted::TOPIC_NAME, TokenTransferred::TOPIC_NAME]
+        .into_iter()
+        .map(|topic_name| (topic_name.to_string(), MessageTopicOperation::Add))
+        .collect()
+}
+
+pub fn balance_of(owner: &Key) -> Result<u64, ApiError> {
+    Ok(BALANCES.get(owner)?.unwrap_or_default())
+}
+
+fn get_owner(token_id: u64) -> Result<Key, ApiError> {
+    OWNERS
+        .get(&token_id)?
+        .ok_or_else(|| Cep78Error::TokenNotFound.into())
+}
+
+fn mint_to(owner: Key) -> Result<u64, ApiError> {
+    let token_id = TOKEN_COUNT_TUREF.read()?.unwrap_or_default();
+    TOKEN_COUNT_TUREF.write(token_id + 1)?;
+    OWNERS.insert(&token_id, owner)?;
+    BALANCES.insert(&owner, balance_of(&owner)? + 1)?;
+    Ok(token_id)
+}
+
+fn transfer_from(sender: Key, token_id: u64, recipient: Key) -> Result<(), ApiError> {
+    if get_owner(token_id)? != sender {
+        return Err(Cep78Error::NotTokenOwner.into());
+    }
+    OWNERS.insert(&token_id, recipient)?;
+    BALANCES.insert(&sender, balance_of(&sender)? - 1)?;
+    BALANCES.insert(&recipient, balance_of(&recipient)? + 1)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{collections::BTreeSet, vec::Vec};
+    use veles_casper_contract_api::{
+        casper_types::account::AccountHash,
+        veles_casper_ffi_shim::{EnvBuilder, dispatch_with},
+    };
+
+    use super::*;
+
+    const ALICE: Key = Key::Account(AccountHash::new([1; 32]));
+    const BOB: Key = Key::Account(AccountHash::new([2; 32]));
+
+    #[test]
+    fn entry_points_are_exported() {
+        let names: BTreeSet<_> = cep78::entry_points_vec()
+            .iter()
+            .map(|entry_point| entry_point.name().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            BTreeSet::from(
+                [
+                    "mint_token",
+                    "transfer_token",
+                    "owner_of",
+                    "token_balance_of"
+                ]
+                .map(ToString::to_string)
+            )
+        );
+    }
+
+    #[test]
+    fn mint_and_transfer_track_owners_and_balances() {
+        dispatch_with(EnvBuilder::new().build(), |_env| {
+            let mut named_keys = NamedKeys::new();
+            install(&mut named_keys).unwrap();
+            let names: Vec<_> = named_keys.names().cloned().collect();
+            assert_eq!(names, [BALANCES_DICT, OWNERS_DICT, "cep78_token_count"]);
+
+            assert_eq!(mint_to(ALICE), Ok(0));
+            assert_eq!(mint_to(ALICE), Ok(1));
+            assert_eq!(TOKEN_COUNT_TUREF.read(), Ok(Some(2)));
+            assert_eq!(get_owner(1), Ok(ALICE));
+            assert_eq!(balance_of(&ALICE), Ok(2));
+
+            assert_eq!(
+                transfer_from(BOB, 1, ALICE),
+                Err(Cep78Error::NotTokenOwner.into())
+            );
+            transfer_from(ALICE, 1, BOB).unwrap();
+            assert_eq!(get_owner(0), Ok(ALICE));
+            assert_eq!(get_owner(1), Ok(BOB));
+            assert_eq!(balance_of(&ALICE), Ok(1));
+            assert_eq!(balance_of(&BOB), Ok(1));
+
+            assert_eq!(get_owner(2), Err(Cep78Error::TokenNotFound.into()));
+            assert_eq!(
+                transfer_from(ALICE, 2, BOB),
+                Err(Cep78Error::TokenNotFound.into())
+            );
+        });
+    }
+}
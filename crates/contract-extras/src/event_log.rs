@@ -0,0 +1,48 @@
+//! A bounded on-chain event log, keeping the last events of a contract in a [`RingBuffer`].
+//!
+//! The CES standard stores every event in a dictionary that grows forever. Contracts that only
+//! need the recent history install a [`RingBuffer`] of a fixed capacity instead, and record into
+//! it with [`record_bounded_event`], which evicts the oldest event once the log is full. Like
+//! the cep18 `EventsMode::NoEvents`, contracts installed without a log record nothing:
+//!
+//! ```ignore
+//! static HISTORY: RingBuffer<Transfer> = RingBuffer::from_named_key(NamedKey::from_name("history"));
+//!
+//! // In `call`:
+//! event_log::install(&HISTORY, 100, &mut named_keys)?;
+//! // In an entry point:
+//! event_log::record_bounded_event(&HISTORY, Transfer { from, to, amount })?;
+//! ```
+use veles_casper_contract_api::{
+    casper_types::{ApiError, CLTyped, NamedKeys, bytesrepr::ToBytes},
+    collections::ring_buffer::RingBuffer,
+    utils,
+};
+
+/// Creates the dictionary of a log keeping the last `capacity` events and adds its named key to
+/// `named_keys`.
+pub fn install<E>(
+    buffer: &RingBuffer<E>,
+    capacity: u64,
+    named_keys: &mut NamedKeys,
+) -> Result<(), ApiError> {
+    buffer
+        .named_uref()
+        .get_or_init(utils::new_dictionary_key)?
+        .append_to_named_keys(named_keys)?;
+    buffer.init(capacity)
+}
+
+/// Appends `event` to the log, evicting the oldest event if it is full, and returns the index of
+/// the event.
+///
+/// Returns `None` without recording anything if the contract was installed without the log.
+pub fn record_bounded_event<E: ToBytes + CLTyped>(
+    buffer: &RingBuffer<E>,
+    event: E,
+) -> Result<Option<u64>, ApiError> {
+    if buffer.named_uref().get()?.is_none() {
+        return Ok(None);
+    }
+    buffer.push(event).map(Some)
+}
@@ -3,5 +3,10 @@
 extern crate alloc;
 
 pub mod cep18;
+pub mod cep78;
+pub mod event_log;
+pub mod multisig;
 pub mod ownable;
 pub mod pausable;
+pub mod vesting;
+pub mod voting;
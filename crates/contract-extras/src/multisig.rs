@@ -0,0 +1,367 @@
+//! Application-level M-of-N approval of contract calls.
+//!
+//! Signers propose a call to an entry point of another contract, which can be executed once the
+//! number of approvals reaches the threshold in effect when it was proposed. Unlike account
+//! associated keys, the calls are authorized by the contract itself, so they work the same for
+//! every kind of signer. Signers and the threshold are managed by the contract owner, see
+//! [`ownable`](crate::ownable).
+//!
+//! The entry points are `propose`, `approve_proposal`, `execute_proposal` and `cancel_proposal`,
+//! plus `add_signer`, `remove_signer` and `set_threshold` for the owner. Use [`install`] in the
+//! installing session to create the named keys of the contract and [`message_topics`] to register
+//! the lifecycle events.
+use alloc::{collections::BTreeMap, string::String, string::ToString};
+use veles_casper_contract_api::{
+    casper_types::{
+        ApiError, CLType, CLTyped, Key, NamedKeys, RuntimeArgs,
+        bytesrepr::{self, Bytes},
+        contract_messages::MessageTopicOperation,
+        contracts::ContractHash,
+    },
+    collections::{mapping::Mapping, set::Set},
+    macro_support::CasperMessage as _,
+    named_key::NamedKey,
+    typed_uref::TypedURef,
+    utils,
+    veles_casper_contract_macros::{CasperMessage, CasperSerialize, casper},
+};
+
+use crate::ownable;
+
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultisigError {
+    NotSigner = 63000,
+    ProposalNotFound = 63001,
+    AlreadyApproved = 63002,
+    BelowThreshold = 63003,
+    AlreadyExecuted = 63004,
+    ProposalCancelled = 63005,
+    ProposalExpired = 63006,
+    NotProposer = 63007,
+    InvalidThreshold = 63008,
+    InvalidArgs = 63009,
+    AlreadySigner = 63010,
+}
+
+impl From<MultisigError> for ApiError {
+    fn from(value: MultisigError) -> Self {
+        ApiError::User(value as u16)
+    }
+}
+
+/// A call awaiting approval of the signers.
+#[derive(Debug, Clone, PartialEq, Eq, CasperSerialize)]
+pub struct Proposal {
+    pub proposer: Key,
+    pub target: ContractHash,
+    pub entry_point: String,
+    /// Serialized `RuntimeArgs` passed to the entry point.
+    pub args: Bytes,
+    pub approvals: u32,
+    /// Number of approvals required to execute the proposal.
+    pub threshold: u32,
+    /// Block time in milliseconds from which the proposal can no longer be approved or executed.
+    pub expiry: u64,
+    pub executed: bool,
+    pub cancelled: bool,
+}
+
+impl CLTyped for Proposal {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "multisig_proposal_created")]
+pub struct ProposalCreated {
+    pub proposal_id: u64,
+    pub proposer: Key,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "multisig_proposal_approved")]
+pub struct ProposalApproved {
+    pub proposal_id: u64,
+    pub signer: Key,
+    pub approvals: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "multisig_proposal_executed")]
+pub struct ProposalExecuted {
+    pub proposal_id: u64,
+    pub executor: Key,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "multisig_proposal_cancelled")]
+pub struct ProposalCancelled {
+    pub proposal_id: u64,
+}
+
+pub const SIGNERS_DICT: &str = "multisig_signers";
+pub const PROPOSALS_DICT: &str = "multisig_proposals";
+pub const APPROVALS_DICT: &str = "multisig_approvals";
+
+pub static SIGNERS: Mapping<Key, bool> = Mapping::from_named_key(NamedKey::from_name(SIGNERS_DICT));
+pub static PROPOSALS: Mapping<u64, Proposal> =
+    Mapping::from_named_key(NamedKey::from_name(PROPOSALS_DICT));
+/// Signers that approved a proposal, keyed by `(proposal_id, signer)`.
+pub static APPROVALS: Set<(u64, Key)> = Set::from_named_key(NamedKey::from_name(APPROVALS_DICT));
+
+static SIGNER_COUNT: NamedKey = NamedKey::from_name("multisig_signer_count");
+pub static SIGNER_COUNT_TUREF: TypedURef<u32> = TypedURef::from_named_key(&SIGNER_COUNT);
+static THRESHOLD: NamedKey = NamedKey::from_name("multisig_threshold");
+pub static THRESHOLD_TUREF: TypedURef<u32> = TypedURef::from_named_key(&THRESHOLD);
+static PROPOSAL_COUNT: NamedKey = NamedKey::from_name("multisig_proposal_count");
+pub static PROPOSAL_COUNT_TUREF: TypedURef<u64> = TypedURef::from_named_key(&PROPOSAL_COUNT);
+
+#[casper(contract)]
+pub mod multisig {
+    use super::*;
+
+    /// Proposes to call `entry_point` of `target` with `args`, returning the proposal id.
+    #[casper(export)]
+    pub fn propose(
+        target: ContractHash,
+        entry_point: String,
+        args: Bytes,
+        expiry: u64,
+    ) -> Result<u64, ApiError> {
+        let proposer = ensure_signer()?;
+        bytesrepr::deserialize_from_slice::<_, RuntimeArgs>(&args)
+            .map_err(|_| MultisigError::InvalidArgs)?;
+        if expiry <= utils::get_block_time().get() {
+            return Err(MultisigError::ProposalExpired.into());
+        }
+
+        let proposal_id = PROPOSAL_COUNT_TUREF.read()?.unwrap_or_default();
+        PROPOSAL_COUNT_TUREF.write(proposal_id + 1)?;
+        let proposal = Proposal {
+            proposer,
+            target,
+            entry_point,
+            args,
+            approvals: 0,
+            threshold: THRESHOLD_TUREF.read()?.ok_or(ApiError::MissingKey)?,
+            expiry,
+            executed: false,
+            cancelled: false,
+        };
+        PROPOSALS.insert(&proposal_id, proposal)?;
+
+        utils::emit_message(ProposalCreated {
+            proposal_id,
+            proposer,
+        })?;
+        Ok(proposal_id)
+    }
+
+    #[casper(export)]
+    pub fn approve_proposal(proposal_id: u64) -> Result<(), ApiError> {
+        let signer = ensure_signer()?;
+        let mut proposal = get_pending_proposal(proposal_id)?;
+        if APPROVALS.contains(&(proposal_id, signer))? {
+            return Err(MultisigError::AlreadyApproved.into());
+        }
+
+        APPROVALS.insert(&(proposal_id, signer))?;
+        proposal.approvals += 1;
+        let approvals = proposal.approvals;
+        PROPOSALS.insert(&proposal_id, proposal)?;
+
+        utils::emit_message(ProposalApproved {
+            proposal_id,
+            signer,
+            approvals,
+        })?;
+        Ok(())
+    }
+
+    /// Executes an approved proposal.
+    ///
+    /// If the called entry point reverts, the whole execution reverts and the proposal stays
+    /// pending.
+    #[casper(export)]
+    pub fn execute_proposal(proposal_id: u64) -> Result<(), ApiError> {
+        let executor = ensure_signer()?;
+        let mut proposal = get_pending_proposal(proposal_id)?;
+        if proposal.approvals < proposal.threshold {
+            return Err(MultisigError::BelowThreshold.into());
+        }
+
+        // Mark the proposal as executed before the call, so it can't be executed again through
+        // reentrancy.
+        proposal.executed = true;
+        PROPOSALS.insert(&proposal_id, proposal.clone())?;
+        utils::call_contract_with_serialized_args(
+            proposal.target,
+            &proposal.entry_point,
+            &proposal.args,
+        )?;
+
+        utils::emit_message(ProposalExecuted {
+            proposal_id,
+            executor,
+        })?;
+        Ok(())
+    }
+
+    #[casper(export)]
+    pub fn cancel_proposal(proposal_id: u64) -> Result<(), ApiError> {
+        let caller = caller_key()?;
+        let mut proposal = get_proposal(proposal_id)?;
+        if proposal.proposer != caller {
+            return Err(MultisigError::NotProposer.into());
+        }
+        ensure_pending(&proposal)?;
+
+        proposal.cancelled = true;
+        PROPOSALS.insert(&proposal_id, proposal)?;
+
+        utils::emit_message(ProposalCancelled { proposal_id })?;
+        Ok(())
+    }
+
+    #[casper(export)]
+    pub fn add_signer(signer: Key) -> Result<(), ApiError> {
+        ownable::ensure_owner()?;
+        if is_signer(&signer)? {
+            return Err(MultisigError::AlreadySigner.into());
+        }
+        SIGNERS.insert(&signer, true)?;
+        let signer_count = SIGNER_COUNT_TUREF.read()?.unwrap_or_default();
+        SIGNER_COUNT_TUREF.write(signer_count + 1)
+    }
+
+    /// Removes a signer, as long as the remaining signers can still reach the threshold.
+    ///
+    /// Approvals the signer already gave to pending proposals are kept.
+    #[casper(export)]
+    pub fn remove_signer(signer: Key) -> Result<(), ApiError> {
+        ownable::ensure_owner()?;
+        if !is_signer(&signer)? {
+            return Err(MultisigError::NotSigner.into());
+        }
+        let signer_count = SIGNER_COUNT_TUREF.read()?.unwrap_or_default() - 1;
+        ensure_valid_threshold(THRESHOLD_TUREF.read()?.unwrap_or_default(), signer_count)?;
+        SIGNERS.insert(&signer, false)?;
+        SIGNER_COUNT_TUREF.write(signer_count)
+    }
+
+    /// Sets the threshold of proposals created from now on.
+    #[casper(export)]
+    pub fn set_threshold(threshold: u32) -> Result<(), ApiError> {
+        ownable::ensure_owner()?;
+        ensure_valid_threshold(threshold, SIGNER_COUNT_TUREF.read()?.unwrap_or_default())?;
+        THRESHOLD_TUREF.write(threshold)
+    }
+
+    #[casper(export)]
+    pub fn proposal(proposal_id: u64) -> Result<Proposal, ApiError> {
+        get_proposal(proposal_id)
+    }
+}
+
+/// Creates the named keys of the multisig, to be called by the installing session before creating
+/// the contract with `named_keys`.
+pub fn install(
+    named_keys: &mut NamedKeys,
+    signers: &[Key],
+    threshold: u32,
+) -> Result<(), ApiError> {
+    for named_key in [
+        SIGNERS.named_uref(),
+        PROPOSALS.named_uref(),
+        APPROVALS.named_uref(),
+    ] {
+        named_key
+            .get_or_init(utils::new_dictionary_key)?
+            .append_to_named_keys(named_keys)?;
+    }
+
+    let mut signer_count = 0;
+    for signer in signers {
+        if SIGNERS.get(signer)?.unwrap_or_default() {
+            return Err(MultisigError::AlreadySigner.into());
+        }
+        SIGNERS.insert(signer, true)?;
+        signer_count += 1;
+    }
+    ensure_valid_threshold(threshold, signer_count)?;
+
+    SIGNER_COUNT
+        .get_or_init(|| utils::new_uref_key(signer_count))?
+        .append_to_named_keys(named_keys)?;
+    THRESHOLD
+        .get_or_init(|| utils::new_uref_key(threshold))?
+        .append_to_named_keys(named_keys)?;
+    PROPOSAL_COUNT
+        .get_or_init(|| utils::new_uref_key(0u64))?
+        .append_to_named_keys(named_keys)?;
+    Ok(())
+}
+
+/// Returns the message topics of the multisig lifecycle events.
+pub fn message_topics() -> BTreeMap<String, MessageTopicOperation> {
+    [
+        ProposalCreated::TOPIC_NAME,
+        ProposalApproved::TOPIC_NAME,
+        ProposalExecuted::TOPIC_NAME,
+        ProposalCancelled::TOPIC_NAME,
+    ]
+    .into_iter()
+    .map(|topic_name| (topic_name.to_string(), MessageTopicOperation::Add))
+    .collect()
+}
+
+pub fn is_signer(key: &Key) -> Result<bool, ApiError> {
+    Ok(SIGNERS.get(key)?.unwrap_or_default())
+}
+
+fn caller_key() -> Result<Key, ApiError> {
+    Ok(Key::Account(utils::get_immediate_account()?))
+}
+
+fn ensure_signer() -> Result<Key, ApiError> {
+    let caller = caller_key()?;
+    if !is_signer(&caller)? {
+        return Err(MultisigError::NotSigner.into());
+    }
+    Ok(caller)
+}
+
+fn ensure_valid_threshold(threshold: u32, signer_count: u32) -> Result<(), ApiError> {
+    if threshold == 0 || threshold > signer_count {
+        return Err(MultisigError::InvalidThreshold.into());
+    }
+    Ok(())
+}
+
+fn get_proposal(proposal_id: u64) -> Result<Proposal, ApiError> {
+    PROPOSALS
+        .get(&proposal_id)?
+        .ok_or_else(|| MultisigError::ProposalNotFound.into())
+}
+
+fn ensure_pending(proposal: &Proposal) -> Result<(), ApiError> {
+    if proposal.executed {
+        return Err(MultisigError::AlreadyExecuted.into());
+    }
+    if proposal.cancelled {
+        return Err(MultisigError::ProposalCancelled.into());
+    }
+    Ok(())
+}
+
+/// Returns the proposal if it can still be approved or executed.
+fn get_pending_proposal(proposal_id: u64) -> Result<Proposal, ApiError> {
+    let proposal = get_proposal(proposal_id)?;
+    ensure_pending(&proposal)?;
+    if utils::get_block_time().get() >= proposal.expiry {
+        return Err(MultisigError::ProposalExpired.into());
+    }
+    Ok(proposal)
+}
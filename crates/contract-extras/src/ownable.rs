@@ -39,8 +39,7 @@ pub mod ownable {
     #[casper(export)]
     pub fn renounce_ownership() -> Result<(), ApiError> {
         ownable::ensure_owner()?;
-        OWNER_KEY_NAME.clear();
-        Ok(())
+        OWNER_KEY_NAME.clear()
     }
 
     #[casper(export)]
@@ -1,5 +1,8 @@
 use veles_casper_contract_api::{
-    casper_types::ApiError, named_key::NamedKey, typed_uref::TypedURef,
+    casper_types::{ApiError, NamedKeys},
+    named_key::NamedKey,
+    typed_uref::TypedURef,
+    utils,
     veles_casper_contract_macros::casper,
 };
 
@@ -52,6 +55,14 @@ pub mod pausable {
     }
 }
 
+/// Adds the `paused` named key of an unpaused contract to `named_keys`.
+pub fn install(named_keys: &mut NamedKeys) -> Result<(), ApiError> {
+    PAUSED_NAMED_KEY
+        .get_or_init(|| utils::new_uref_key(false))?
+        .append_to_named_keys(named_keys)?;
+    Ok(())
+}
+
 pub fn require_unpaused() -> Result<(), ApiError> {
     if PAUSED_TUREF.read()?.unwrap_or(false) {
         Err(PausableError::ContractPaused.into())
@@ -0,0 +1,257 @@
+//! Linear vesting of CEP-18 tokens.
+//!
+//! The contract owner (see [`ownable`](crate::ownable)) creates a schedule per beneficiary, which
+//! vests `amount` tokens linearly over `duration` milliseconds from `start`. The beneficiary calls
+//! `release` to receive the vested tokens through a CEP-18 `transfer` from this contract, so the
+//! contract must hold enough tokens to cover its schedules.
+//!
+//! The entry points are `create_schedule`, `releasable` and `release`. Use [`install`] in the
+//! installing session to create the named keys of the contract and [`message_topics`] to register
+//! the events.
+use alloc::{collections::BTreeMap, string::String, string::ToString};
+use veles_casper_contract_api::{
+    casper_types::{
+        ApiError, CLType, CLTyped, Key, NamedKeys, U256, contract_messages::MessageTopicOperation,
+        contracts::ContractHash,
+    },
+    collections::mapping::Mapping,
+    macro_support::CasperMessage as _,
+    named_key::NamedKey,
+    typed_uref::TypedURef,
+    utils,
+    veles_casper_contract_macros::{CasperMessage, CasperSerialize, casper},
+};
+
+use crate::{cep18, ownable};
+
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VestingError {
+    ScheduleNotFound = 65000,
+    ScheduleExists = 65001,
+    InvalidSchedule = 65002,
+    NothingToRelease = 65003,
+    OverRelease = 65004,
+}
+
+impl From<VestingError> for ApiError {
+    fn from(value: VestingError) -> Self {
+        ApiError::User(value as u16)
+    }
+}
+
+/// Tokens vesting linearly to a beneficiary.
+#[derive(Debug, Clone, PartialEq, Eq, CasperSerialize)]
+pub struct Schedule {
+    pub total: U256,
+    pub released: U256,
+    /// Block time in milliseconds at which vesting starts.
+    pub start: u64,
+    /// Milliseconds after `start` at which everything is vested.
+    pub duration: u64,
+}
+
+impl CLTyped for Schedule {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl Schedule {
+    /// Returns the amount vested at block time `now`, released or not.
+    pub fn vested_at(&self, now: u64) -> U256 {
+        let elapsed = now.saturating_sub(self.start);
+        if elapsed >= self.duration {
+            return self.total;
+        }
+        // Split `total * elapsed / duration` so the product can't overflow.
+        let (elapsed, duration) = (U256::from(elapsed), U256::from(self.duration));
+        self.total / duration * elapsed + self.total % duration * elapsed / duration
+    }
+
+    /// Returns the amount that can be released at block time `now`.
+    pub fn releasable_at(&self, now: u64) -> U256 {
+        self.vested_at(now).saturating_sub(self.released)
+    }
+
+    /// Records the release of `amount` at block time `now`.
+    pub fn release(&mut self, amount: U256, now: u64) -> Result<(), VestingError> {
+        if amount > self.releasable_at(now) {
+            return Err(VestingError::OverRelease);
+        }
+        self.released += amount;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "vesting_schedule_created")]
+pub struct ScheduleCreated {
+    pub beneficiary: Key,
+    pub amount: U256,
+    pub start: u64,
+    pub duration: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "vesting_tokens_released")]
+pub struct TokensReleased {
+    pub beneficiary: Key,
+    pub amount: U256,
+}
+
+pub const SCHEDULES_DICT: &str = "vesting_schedules";
+
+pub static SCHEDULES: Mapping<Key, Schedule> =
+    Mapping::from_named_key(NamedKey::from_name(SCHEDULES_DICT));
+
+static TOKEN: NamedKey = NamedKey::from_name("vesting_token");
+/// The CEP-18 contract releases are paid from.
+pub static TOKEN_TUREF: TypedURef<ContractHash> = TypedURef::from_named_key(&TOKEN);
+
+#[casper(contract)]
+pub mod vesting {
+    use super::*;
+
+    /// Vests `amount` tokens to `beneficiary` over `duration` milliseconds from `start`.
+    #[casper(export)]
+    pub fn create_schedule(
+        beneficiary: Key,
+        amount: U256,
+        start: u64,
+        duration: u64,
+    ) -> Result<(), ApiError> {
+        ownable::ensure_owner()?;
+        if amount.is_zero() || duration == 0 {
+            return Err(VestingError::InvalidSchedule.into());
+        }
+        if SCHEDULES.get(&beneficiary)?.is_some() {
+            return Err(VestingError::ScheduleExists.into());
+        }
+
+        let schedule = Schedule {
+            total: amount,
+            released: U256::zero(),
+            start,
+            duration,
+        };
+        SCHEDULES.insert(&beneficiary, schedule)?;
+
+        utils::emit_message(ScheduleCreated {
+            beneficiary,
+            amount,
+            start,
+            duration,
+        })?;
+        Ok(())
+    }
+
+    #[casper(export)]
+    pub fn releasable(beneficiary: Key) -> Result<U256, ApiError> {
+        Ok(get_schedule(&beneficiary)?.releasable_at(utils::get_block_time().get()))
+    }
+
+    /// Transfers everything vested so far to the calling beneficiary.
+    #[casper(export)]
+    pub fn release() -> Result<(), ApiError> {
+        let beneficiary = Key::Account(utils::get_immediate_account()?);
+        let mut schedule = get_schedule(&beneficiary)?;
+        let now = utils::get_block_time().get();
+        let amount = schedule.releasable_at(now);
+        if amount.is_zero() {
+            return Err(VestingError::NothingToRelease.into());
+        }
+
+        // Record the release before the transfer, so it can't be released again through
+        // reentrancy.
+        schedule.release(amount, now)?;
+        SCHEDULES.insert(&beneficiary, schedule)?;
+        let token = TOKEN_TUREF.read()?.ok_or(ApiError::MissingKey)?;
+        cep18::cep18::Client::new(token).transfer(beneficiary, amount);
+
+        utils::emit_message(TokensReleased {
+            beneficiary,
+            amount,
+        })?;
+        Ok(())
+    }
+}
+
+/// Creates the named keys of the vesting contract paying out `token`, to be called by the
+/// installing session before creating the contract with `named_keys`.
+pub fn install(named_keys: &mut NamedKeys, token: ContractHash) -> Result<(), ApiError> {
+    SCHEDULES
+        .named_uref()
+        .get_or_init(utils::new_dictionary_key)?
+        .append_to_named_keys(named_keys)?;
+    TOKEN
+        .get_or_init(|| utils::new_uref_key(token))?
+        .append_to_named_keys(named_keys)?;
+    Ok(())
+}
+
+/// Returns the message topics of the vesting events.
+pub fn message_topics() -> BTreeMap<String, MessageTopicOperation> {
+    [ScheduleCreated::TOPIC_NAME, TokensReleased::TOPIC_NAME]
+        .into_iter()
+        .map(|topic_name| (topic_name.to_string(), MessageTopicOperation::Add))
+        .collect()
+}
+
+fn get_schedule(beneficiary: &Key) -> Result<Schedule, ApiError> {
+    SCHEDULES
+        .get(beneficiary)?
+        .ok_or_else(|| VestingError::ScheduleNotFound.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_000;
+    const DURATION: u64 = 4_000;
+
+    fn schedule(total: U256) -> Schedule {
+        Schedule {
+            total,
+            released: U256::zero(),
+            start: START,
+            duration: DURATION,
+        }
+    }
+
+    #[test]
+    fn vests_linearly_between_start_and_end() {
+        let schedule = schedule(U256::from(1_000));
+        assert_eq!(schedule.vested_at(0), U256::zero());
+        assert_eq!(schedule.vested_at(START), U256::zero());
+        assert_eq!(schedule.vested_at(START + 1_000), U256::from(250));
+        assert_eq!(schedule.vested_at(START + 3_999), U256::from(999));
+        assert_eq!(schedule.vested_at(START + DURATION), U256::from(1_000));
+        assert_eq!(schedule.vested_at(u64::MAX), U256::from(1_000));
+    }
+
+    #[test]
+    fn large_totals_do_not_overflow() {
+        let schedule = schedule(U256::MAX);
+        assert_eq!(schedule.vested_at(START + DURATION / 2), U256::MAX / 2);
+    }
+
+    #[test]
+    fn release_is_bounded_by_vested_amount() {
+        let mut schedule = schedule(U256::from(1_000));
+        let now = START + 2_000;
+        assert_eq!(schedule.releasable_at(now), U256::from(500));
+
+        schedule.release(U256::from(300), now).unwrap();
+        assert_eq!(schedule.releasable_at(now), U256::from(200));
+        assert_eq!(
+            schedule.release(U256::from(201), now),
+            Err(VestingError::OverRelease)
+        );
+
+        schedule.release(U256::from(200), now).unwrap();
+        assert_eq!(schedule.releasable_at(now), U256::zero());
+        assert_eq!(schedule.releasable_at(START + DURATION), U256::from(500));
+    }
+}
@@ -0,0 +1,318 @@
+//! Proposals voted on with the balances of a CEP-18 token.
+//!
+//! The contract owner (see [`ownable`](crate::ownable)) creates proposals identified by the hash of
+//! their description, open for `voting_period` milliseconds. Every holder votes once per proposal,
+//! for or against, with a weight read from the token's `balance_of` when voting. Once the period
+//! has elapsed anyone can finalize the proposal, which passes if more weight voted for it than
+//! against it.
+//!
+//! The token can't report past balances, so the weight is the balance at the time of the vote
+//! rather than at the creation of the proposal: tokens transferred after voting can vote again
+//! from another account. Use a token whose balances are locked during votes where that matters.
+//!
+//! The entry points are `create_proposal`, `vote`, `tally` and `finalize`. Use [`install`] in the
+//! installing session to create the named keys of the contract and [`message_topics`] to register
+//! the events.
+use alloc::{collections::BTreeMap, string::String, string::ToString};
+use veles_casper_contract_api::{
+    casper_types::{
+        ApiError, CLType, CLTyped, Key, NamedKeys, U256, contract_messages::MessageTopicOperation,
+        contracts::ContractHash,
+    },
+    collections::mapping::Mapping,
+    macro_support::CasperMessage as _,
+    named_key::NamedKey,
+    typed_uref::TypedURef,
+    utils,
+    veles_casper_contract_macros::{CasperMessage, CasperSerialize, casper},
+};
+
+use crate::{cep18, ownable};
+
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VotingError {
+    ProposalNotFound = 61000,
+    AlreadyVoted = 61001,
+    VotingPeriodNotElapsed = 61002,
+    VotingClosed = 61003,
+    AlreadyFinalized = 61004,
+    InvalidVotingPeriod = 61005,
+    NoVotingPower = 61006,
+}
+
+impl From<VotingError> for ApiError {
+    fn from(value: VotingError) -> Self {
+        ApiError::User(value as u16)
+    }
+}
+
+/// A proposal and the weight voted for and against it.
+#[derive(Debug, Clone, PartialEq, Eq, CasperSerialize)]
+pub struct Proposal {
+    /// Hash of the description of the proposal, which is kept off-chain.
+    pub description_hash: [u8; 32],
+    /// Block time in milliseconds from which the proposal can no longer be voted on.
+    pub ends_at: u64,
+    pub votes_for: U256,
+    pub votes_against: U256,
+    pub finalized: bool,
+}
+
+impl CLTyped for Proposal {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl Proposal {
+    /// Returns whether the proposal can be voted on at block time `now`.
+    pub fn is_open_at(&self, now: u64) -> bool {
+        now < self.ends_at
+    }
+
+    /// Adds `weight` to the votes for the proposal if `support`, against it otherwise.
+    pub fn record_vote(&mut self, weight: U256, support: bool) {
+        let votes = if support {
+            &mut self.votes_for
+        } else {
+            &mut self.votes_against
+        };
+        *votes = votes.saturating_add(weight);
+    }
+
+    /// Returns whether more weight voted for the proposal than against it.
+    pub fn passed(&self) -> bool {
+        self.votes_for > self.votes_against
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "voting_proposal_created")]
+pub struct ProposalCreated {
+    pub proposal_id: u64,
+    pub description_hash: [u8; 32],
+    pub ends_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "voting_voted")]
+pub struct Voted {
+    pub proposal_id: u64,
+    pub voter: Key,
+    pub support: bool,
+    pub weight: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "voting_proposal_finalized")]
+pub struct Finalized {
+    pub proposal_id: u64,
+    pub passed: bool,
+    pub votes_for: U256,
+    pub votes_against: U256,
+}
+
+pub const PROPOSALS_DICT: &str = "voting_proposals";
+pub const VOTES_DICT: &str = "voting_votes";
+
+pub static PROPOSALS: Mapping<u64, Proposal> =
+    Mapping::from_named_key(NamedKey::from_name(PROPOSALS_DICT));
+/// Weight each voter voted with, keyed by `(proposal_id, voter)`.
+pub static VOTES: Mapping<(u64, Key), U256> =
+    Mapping::from_named_key(NamedKey::from_name(VOTES_DICT));
+
+static PROPOSAL_COUNT: NamedKey = NamedKey::from_name("voting_proposal_count");
+pub static PROPOSAL_COUNT_TUREF: TypedURef<u64> = TypedURef::from_named_key(&PROPOSAL_COUNT);
+static TOKEN: NamedKey = NamedKey::from_name("voting_token");
+/// The CEP-18 contract whose balances weigh the votes.
+pub static TOKEN_TUREF: TypedURef<ContractHash> = TypedURef::from_named_key(&TOKEN);
+
+#[casper(contract)]
+pub mod voting {
+    use super::*;
+
+    /// Opens a proposal to votes for `voting_period` milliseconds, returning the proposal id.
+    #[casper(export)]
+    pub fn create_proposal(
+        description_hash: [u8; 32],
+        voting_period: u64,
+    ) -> Result<u64, ApiError> {
+        ownable::ensure_owner()?;
+        if voting_period == 0 {
+            return Err(VotingError::InvalidVotingPeriod.into());
+        }
+        let ends_at = utils::get_block_time()
+            .get()
+            .checked_add(voting_period)
+            .ok_or(VotingError::InvalidVotingPeriod)?;
+
+        let proposal_id = PROPOSAL_COUNT_TUREF.read()?.unwrap_or_default();
+        PROPOSAL_COUNT_TUREF.write(proposal_id + 1)?;
+        let proposal = Proposal {
+            description_hash,
+            ends_at,
+            votes_for: U256::zero(),
+            votes_against: U256::zero(),
+            finalized: false,
+        };
+        PROPOSALS.insert(&proposal_id, proposal)?;
+
+        utils::emit_message(ProposalCreated {
+            proposal_id,
+            description_hash,
+            ends_at,
+        })?;
+        Ok(proposal_id)
+    }
+
+    /// Votes for the proposal if `support`, against it otherwise, with the token balance of the
+    /// calling account.
+    #[casper(export)]
+    pub fn vote(proposal_id: u64, support: bool) -> Result<(), ApiError> {
+        let voter = Key::Account(utils::get_immediate_account()?);
+        let mut proposal = get_proposal(proposal_id)?;
+        if !proposal.is_open_at(utils::get_block_time().get()) {
+            return Err(VotingError::VotingClosed.into());
+        }
+        if VOTES.get(&(proposal_id, voter))?.is_some() {
+            return Err(VotingError::AlreadyVoted.into());
+        }
+
+        let token = TOKEN_TUREF.read()?.ok_or(ApiError::MissingKey)?;
+        let weight = cep18::cep18::Client::new(token).balance_of(voter);
+        if weight.is_zero() {
+            return Err(VotingError::NoVotingPower.into());
+        }
+        VOTES.insert(&(proposal_id, voter), weight)?;
+        proposal.record_vote(weight, support);
+        PROPOSALS.insert(&proposal_id, proposal)?;
+
+        utils::emit_message(Voted {
+            proposal_id,
+            voter,
+            support,
+            weight,
+        })?;
+        Ok(())
+    }
+
+    /// Returns the weight voted for and against the proposal.
+    #[casper(export)]
+    pub fn tally(proposal_id: u64) -> Result<(U256, U256), ApiError> {
+        let proposal = get_proposal(proposal_id)?;
+        Ok((proposal.votes_for, proposal.votes_against))
+    }
+
+    /// Closes the proposal once its voting period has elapsed.
+    #[casper(export)]
+    pub fn finalize(proposal_id: u64) -> Result<(), ApiError> {
+        let mut proposal = get_proposal(proposal_id)?;
+        if proposal.finalized {
+            return Err(VotingError::AlreadyFinalized.into());
+        }
+        if proposal.is_open_at(utils::get_block_time().get()) {
+            return Err(VotingError::VotingPeriodNotElapsed.into());
+        }
+
+        proposal.finalized = true;
+        let finalized = Finalized {
+            proposal_id,
+            passed: proposal.passed(),
+            votes_for: proposal.votes_for,
+            votes_against: proposal.votes_against,
+        };
+        PROPOSALS.insert(&proposal_id, proposal)?;
+
+        utils::emit_message(finalized)?;
+        Ok(())
+    }
+}
+
+/// Creates the named keys of the voting contract weighing votes with the balances of `token`, to
+/// be called by the installing session before creating the contract with `named_keys`.
+pub fn install(named_keys: &mut NamedKeys, token: ContractHash) -> Result<(), ApiError> {
+    for named_key in [PROPOSALS.named_uref(), VOTES.named_uref()] {
+        named_key
+            .get_or_init(utils::new_dictionary_key)?
+            .append_to_named_keys(named_keys)?;
+    }
+    PROPOSAL_COUNT
+        .get_or_init(|| utils::new_uref_key(0u64))?
+        .append_to_named_keys(named_keys)?;
+    TOKEN
+        .get_or_init(|| utils::new_uref_key(token))?
+        .append_to_named_keys(named_keys)?;
+    Ok(())
+}
+
+/// Returns the message topics of the voting events.
+pub fn message_topics() -> BTreeMap<String, MessageTopicOperation> {
+    [
+        ProposalCreated::TOPIC_NAME,
+        Voted::TOPIC_NAME,
+        Finalized::TOPIC_NAME,
+    ]
+    .into_iter()
+    .map(|topic_name| (topic_name.to_string(), MessageTopicOperation::Add))
+    .collect()
+}
+
+fn get_proposal(proposal_id: u64) -> Result<Proposal, ApiError> {
+    PROPOSALS
+        .get(&proposal_id)?
+        .ok_or_else(|| VotingError::ProposalNotFound.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENDS_AT: u64 = 5_000;
+
+    fn proposal() -> Proposal {
+        Proposal {
+            description_hash: [7; 32],
+            ends_at: ENDS_AT,
+            votes_for: U256::zero(),
+            votes_against: U256::zero(),
+            finalized: false,
+        }
+    }
+
+    #[test]
+    fn votes_add_up_on_their_side() {
+        let mut proposal = proposal();
+        proposal.record_vote(U256::from(600), true);
+        proposal.record_vote(U256::from(300), false);
+        proposal.record_vote(U256::from(100), true);
+        assert_eq!(proposal.votes_for, U256::from(700));
+        assert_eq!(proposal.votes_against, U256::from(300));
+        assert!(proposal.passed());
+    }
+
+    #[test]
+    fn ties_do_not_pass() {
+        let mut proposal = proposal();
+        assert!(!proposal.passed());
+        proposal.record_vote(U256::from(500), true);
+        proposal.record_vote(U256::from(500), false);
+        assert!(!proposal.passed());
+    }
+
+    #[test]
+    fn large_weights_saturate() {
+        let mut proposal = proposal();
+        proposal.record_vote(U256::MAX, true);
+        proposal.record_vote(U256::one(), true);
+        assert_eq!(proposal.votes_for, U256::MAX);
+    }
+
+    #[test]
+    fn voting_closes_at_the_end_of_the_period() {
+        let proposal = proposal();
+        assert!(proposal.is_open_at(0));
+        assert!(proposal.is_open_at(ENDS_AT - 1));
+        assert!(!proposal.is_open_at(ENDS_AT));
+    }
+}
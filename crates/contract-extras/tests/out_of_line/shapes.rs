@@ -0,0 +1,16 @@
+//! Contract body kept out of line and included into `#[casper(contract)] mod shapes`.
+
+use super::*;
+
+#[casper(export)]
+pub fn area(width: u64, height: u64) -> u64 {
+    width * height
+}
+
+#[casper(export)]
+pub fn describe(name: &str) -> Result<String, ApiError> {
+    if name.is_empty() {
+        return Err(ApiError::InvalidArgument);
+    }
+    Ok(format!("shape {name}"))
+}
@@ -0,0 +1,91 @@
+//! CEP-18 hooks run through the shim.
+//!
+//! Kept in a separate test binary since the keys cached by the cep18 named keys live in statics for
+//! the rest of the process, which would make the gas depend on the other tests that ran before.
+use veles_casper_contract_api::casper_contract::contract_api::storage;
+use veles_casper_contract_api::casper_types::{
+    AccessRights, CLValue, Key, StoredValue, U256, URef, account::AccountHash,
+};
+use veles_casper_contract_api::veles_casper_ffi_shim::{
+    AccountConfig, CostModel, Env, EnvBuilder, RevertError, run_entry_point,
+};
+use veles_casper_contract_extras::cep18::{
+    balances::write_balance_to,
+    constants::{ARG_AMOUNT, ARG_EVENTS_MODE, ARG_RECIPIENT, DICT_BALANCES},
+    hooks::{Cep18Hooks, PausableHooks, set_hooks},
+    modalities::EventsMode,
+};
+use veles_casper_contract_extras::pausable::PausableError;
+
+const SENDER: AccountHash = AccountHash::new([1; 32]);
+const RECIPIENT: Key = Key::Account(AccountHash::new([2; 32]));
+
+unsafe extern "C-unwind" {
+    #[link_name = "transfer"]
+    fn transfer_entry_point();
+}
+
+/// Returns an environment in which `SENDER` transfers 40 tokens to `RECIPIENT`.
+fn transfer_env(paused: bool) -> Env {
+    let events_mode = URef::new([0xee; 32], AccessRights::READ_ADD_WRITE);
+    let paused_uref = URef::new([0xdd; 32], AccessRights::READ_ADD_WRITE);
+    EnvBuilder::new()
+        .with_account(AccountConfig::new(
+            SENDER,
+            URef::new([0xff; 32], AccessRights::READ_ADD_WRITE),
+        ))
+        .with_storage(
+            Key::URef(events_mode),
+            StoredValue::CLValue(CLValue::from_t(EventsMode::NoEvents as u8).unwrap()),
+        )
+        .with_named_key(ARG_EVENTS_MODE, Key::URef(events_mode))
+        .with_storage(
+            Key::URef(paused_uref),
+            StoredValue::CLValue(CLValue::from_t(paused).unwrap()),
+        )
+        .with_named_key("paused", Key::URef(paused_uref))
+        .with_arg(ARG_RECIPIENT, RECIPIENT)
+        .with_arg(ARG_AMOUNT, U256::from(40))
+        .build()
+}
+
+/// Runs `transfer` from a balance of 100, returning its result and the estimated host gas of the
+/// entry point.
+fn run_transfer(paused: bool) -> (Result<(), RevertError>, u64) {
+    let env = transfer_env(paused);
+    let result = run_entry_point(env.clone(), |env| {
+        storage::new_dictionary(DICT_BALANCES).unwrap();
+        write_balance_to(Key::Account(SENDER), U256::from(100)).unwrap();
+        env.trace_records();
+        unsafe { transfer_entry_point() };
+    });
+    (result, env.estimated_gas(&CostModel::default()))
+}
+
+struct Noop;
+
+impl Cep18Hooks for Noop {}
+
+static NOOP: Noop = Noop;
+
+#[test]
+fn hooks_run_around_transfers() {
+    // The first run caches the named keys, so that the runs below make the same host calls.
+    assert_eq!(run_transfer(false).0, Ok(()));
+    let (result, default_gas) = run_transfer(false);
+    assert_eq!(result, Ok(()));
+
+    // No-op hooks make no host calls, hence they leave the host gas unchanged.
+    set_hooks(&NOOP);
+    assert_eq!(run_transfer(false), (Ok(()), default_gas));
+
+    set_hooks(&PausableHooks);
+    let (result, pausable_gas) = run_transfer(false);
+    assert_eq!(result, Ok(()));
+    assert!(pausable_gas > default_gas);
+    let (result, _) = run_transfer(true);
+    assert_eq!(
+        result.unwrap_err().api_error,
+        PausableError::ContractPaused.into()
+    );
+}
@@ -0,0 +1,164 @@
+//! CEP-18 permits signed off-chain and submitted through the `permit` entry point under the shim.
+use veles_casper_contract_api::casper_contract::contract_api::storage;
+use veles_casper_contract_api::casper_types::{
+    AccessRights, CLValue, Key, PublicKey, SecretKey, StoredValue, U256, URef,
+    account::AccountHash,
+    bytesrepr::{Bytes, ToBytes},
+    crypto,
+};
+use veles_casper_contract_api::veles_casper_ffi_shim::{
+    Env, EnvBuilder, HostFunction, RevertError, dispatch_with, run_entry_point,
+};
+use veles_casper_contract_extras::cep18::{
+    allowances::read_allowance_from,
+    constants::{
+        ARG_AMOUNT, ARG_DEADLINE, ARG_EVENTS_MODE, ARG_NONCE, ARG_OWNER, ARG_PACKAGE_HASH,
+        ARG_SIGNATURE, ARG_SPENDER, DICT_ALLOWANCES, DICT_PERMIT_NONCES,
+    },
+    error::Cep18Error,
+    modalities::EventsMode,
+    permit::{permit_message, permit_nonce},
+};
+
+const TOKEN: Key = Key::Hash([9; 32]);
+const SPENDER: Key = Key::Account(AccountHash::new([2; 32]));
+const NOW: u64 = 1_000_000;
+
+unsafe extern "C-unwind" {
+    #[link_name = "permit"]
+    fn permit_entry_point();
+}
+
+fn signer(seed: u8) -> (SecretKey, PublicKey) {
+    let secret_key = SecretKey::ed25519_from_bytes([seed; 32]).unwrap();
+    let public_key = PublicKey::from(&secret_key);
+    (secret_key, public_key)
+}
+
+/// Returns an environment of a token with its allowances and nonces dictionaries.
+fn token_env() -> Env {
+    let events_mode = URef::new([0xee; 32], AccessRights::READ_ADD_WRITE);
+    let env = EnvBuilder::new()
+        .with_storage(
+            Key::URef(events_mode),
+            StoredValue::CLValue(CLValue::from_t(EventsMode::NoEvents as u8).unwrap()),
+        )
+        .with_named_key(ARG_EVENTS_MODE, Key::URef(events_mode))
+        .with_named_key(ARG_PACKAGE_HASH, TOKEN)
+        .with_block_time(NOW)
+        .build();
+    dispatch_with(env.clone(), |_env| {
+        storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+        storage::new_dictionary(DICT_PERMIT_NONCES).unwrap();
+    });
+    env
+}
+
+/// Submits a permit of `owner` with the given signature.
+fn submit(
+    env: &Env,
+    owner: &PublicKey,
+    amount: u64,
+    deadline: u64,
+    signature: Bytes,
+) -> Result<(), RevertError> {
+    env.set_arg(ARG_OWNER, owner.clone());
+    env.set_arg(ARG_SPENDER, SPENDER);
+    env.set_arg(ARG_AMOUNT, U256::from(amount));
+    env.set_arg(ARG_DEADLINE, deadline);
+    env.set_arg(ARG_SIGNATURE, signature);
+    run_entry_point(env.clone(), |_env| unsafe { permit_entry_point() })
+}
+
+/// Signs the permit of `amount` for `nonce` with `secret_key`.
+fn sign(secret_key: &SecretKey, token: Key, amount: u64, nonce: u64, deadline: u64) -> Bytes {
+    let public_key = PublicKey::from(secret_key);
+    let message = permit_message(
+        token,
+        &public_key,
+        SPENDER,
+        U256::from(amount),
+        nonce,
+        deadline,
+    );
+    let signature = crypto::sign(message, secret_key, &public_key);
+    Bytes::from(signature.to_bytes().unwrap())
+}
+
+/// Returns the allowance of `SPENDER` from `owner` and the permit nonce of `owner`.
+fn state(env: &Env, owner: &PublicKey) -> (U256, u64) {
+    let owner = Key::Account(owner.to_account_hash());
+    let mut state = (U256::zero(), 0);
+    dispatch_with(env.clone(), |_env| {
+        state = (
+            read_allowance_from(owner, SPENDER).unwrap(),
+            permit_nonce(owner).unwrap(),
+        );
+    });
+    state
+}
+
+#[test]
+fn permits_set_allowances_once_before_their_deadline() {
+    let env = token_env();
+    let (secret_key, owner) = signer(7);
+    let deadline = NOW + 1;
+
+    let signature = sign(&secret_key, TOKEN, 40, 0, deadline);
+    assert_eq!(
+        submit(&env, &owner, 40, deadline, signature.clone()),
+        Ok(())
+    );
+    assert_eq!(state(&env, &owner), (U256::from(40), 1));
+    env.assert_trace(|trace| trace.contains(HostFunction::CasperVerifySignature).times(1));
+
+    // The nonce moved on, so the same permit can't be replayed.
+    assert_eq!(
+        submit(&env, &owner, 40, deadline, signature)
+            .unwrap_err()
+            .api_error,
+        Cep18Error::InvalidSignature.into()
+    );
+    let signature = sign(&secret_key, TOKEN, 25, 1, deadline);
+    assert_eq!(submit(&env, &owner, 25, deadline, signature), Ok(()));
+    assert_eq!(state(&env, &owner), (U256::from(25), 2));
+
+    // Permits of another amount, signer or token don't verify, nor do malformed signatures.
+    let (other_key, _) = signer(8);
+    for signature in [
+        sign(&secret_key, TOKEN, 26, 2, deadline),
+        sign(&other_key, TOKEN, 30, 2, deadline),
+        sign(&secret_key, Key::Hash([10; 32]), 30, 2, deadline),
+        Bytes::from(vec![1, 2, 3]),
+    ] {
+        assert_eq!(
+            submit(&env, &owner, 30, deadline, signature)
+                .unwrap_err()
+                .api_error,
+            Cep18Error::InvalidSignature.into()
+        );
+    }
+
+    // A permit can't be used from its deadline on.
+    let signature = sign(&secret_key, TOKEN, 30, 2, NOW);
+    assert_eq!(
+        submit(&env, &owner, 30, NOW, signature)
+            .unwrap_err()
+            .api_error,
+        Cep18Error::PermitExpired.into()
+    );
+    assert_eq!(state(&env, &owner), (U256::from(25), 2));
+
+    // Submitting the signed nonce tells used and future nonces from bad signatures.
+    for (nonce, expected) in [
+        (1, Err(Cep18Error::PermitNonceMismatch.into())),
+        (3, Err(Cep18Error::PermitNonceMismatch.into())),
+        (2, Ok(())),
+    ] {
+        let signature = sign(&secret_key, TOKEN, 30, nonce, deadline);
+        env.set_arg(ARG_NONCE, nonce);
+        let result = submit(&env, &owner, 30, deadline, signature);
+        assert_eq!(result.map_err(|error| error.api_error), expected);
+    }
+    assert_eq!(state(&env, &owner), (U256::from(30), 3));
+}
@@ -0,0 +1,193 @@
+//! The CEP-18 transfer hook notifying a mock receiver contract through the shim.
+extern crate alloc;
+
+use std::cell::RefCell;
+
+use veles_casper_contract_api::casper_contract::contract_api::{runtime, storage};
+use veles_casper_contract_api::casper_types::{
+    AccessRights, ApiError, CLValue, EntityAddr, Key, StoredValue, U256, URef,
+    account::AccountHash,
+    contracts::{ContractHash, EntryPoints},
+};
+use veles_casper_contract_api::prelude::*;
+use veles_casper_contract_api::veles_casper_ffi_shim::{
+    AccountConfig, Env, EnvBuilder, HostFunction, RevertError, dispatch_with, run_entry_point,
+};
+use veles_casper_contract_extras::cep18::{
+    allowances::write_allowance_to,
+    balances::{read_balance_from, write_balance_to},
+    constants::{
+        ARG_AMOUNT, ARG_ENABLE_TRANSFER_HOOK, ARG_EVENTS_MODE, ARG_OWNER, ARG_RECIPIENT,
+        DICT_ALLOWANCES, DICT_BALANCES, ENTRY_POINT_ON_TOKEN_RECEIVED,
+    },
+    modalities::EventsMode,
+};
+
+const SENDER: AccountHash = AccountHash::new([1; 32]);
+const OWNER: AccountHash = AccountHash::new([2; 32]);
+const RECEIVER: [u8; 32] = [3; 32];
+
+thread_local! {
+    static RECEIVED: RefCell<Vec<(Key, U256)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A receiver accepting up to 50 tokens per transfer.
+#[casper(contract)]
+pub mod receiver {
+    use super::*;
+
+    #[casper(export)]
+    pub fn on_token_received(from: Key, amount: U256) -> Result<(), ApiError> {
+        if amount > U256::from(50) {
+            return Err(ApiError::User(1));
+        }
+        RECEIVED.with(|received| received.borrow_mut().push((from, amount)));
+        Ok(())
+    }
+}
+
+unsafe extern "C-unwind" {
+    #[link_name = "transfer"]
+    fn transfer_entry_point();
+    #[link_name = "transfer_from"]
+    fn transfer_from_entry_point();
+    #[link_name = "on_token_received"]
+    fn on_token_received_entry_point();
+}
+
+/// Returns an environment of a token installed with `enable_transfer_hook`, in which `SENDER`
+/// moves `amount` tokens, from balances of 100 and an allowance of `OWNER` to `SENDER` of 100.
+fn hook_env(amount: u64) -> Env {
+    let events_mode = URef::new([0xee; 32], AccessRights::READ_ADD_WRITE);
+    let transfer_hook = URef::new([0xdd; 32], AccessRights::READ_ADD_WRITE);
+    let env = EnvBuilder::new()
+        .with_account(AccountConfig::new(
+            SENDER,
+            URef::new([0xff; 32], AccessRights::READ_ADD_WRITE),
+        ))
+        .with_storage(
+            Key::URef(events_mode),
+            StoredValue::CLValue(CLValue::from_t(EventsMode::NoEvents as u8).unwrap()),
+        )
+        .with_named_key(ARG_EVENTS_MODE, Key::URef(events_mode))
+        .with_storage(
+            Key::URef(transfer_hook),
+            StoredValue::CLValue(CLValue::from_t(true).unwrap()),
+        )
+        .with_named_key(ARG_ENABLE_TRANSFER_HOOK, Key::URef(transfer_hook))
+        .with_contract_entry_point(
+            ContractHash::new(RECEIVER),
+            ENTRY_POINT_ON_TOKEN_RECEIVED,
+            || unsafe { on_token_received_entry_point() },
+        )
+        .with_arg(ARG_OWNER, Key::Account(OWNER))
+        .with_arg(ARG_AMOUNT, U256::from(amount))
+        .build();
+    dispatch_with(env.clone(), |_env| {
+        storage::new_dictionary(DICT_BALANCES).unwrap();
+        storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+        write_balance_to(Key::Account(SENDER), U256::from(100)).unwrap();
+        write_balance_to(Key::Account(OWNER), U256::from(100)).unwrap();
+        write_allowance_to(Key::Account(OWNER), Key::Account(SENDER), U256::from(100)).unwrap();
+    });
+    env
+}
+
+/// Installs the receiver as a contract package, returning the key CEP-18 holds its tokens under.
+fn install_receiver(env: &Env) -> Key {
+    let mut installed = None;
+    dispatch_with(env.clone(), |_env| {
+        let (contract_hash, _version) = storage::new_contract(
+            EntryPoints::new().into(),
+            None,
+            Some("receiver_package".into()),
+            None,
+            None,
+        );
+        installed = Some((contract_hash, runtime::get_key("receiver_package").unwrap()));
+    });
+    let (contract_hash, package_key) = installed.unwrap();
+    env.set_contract_entry_point(contract_hash, ENTRY_POINT_ON_TOKEN_RECEIVED, || unsafe {
+        on_token_received_entry_point()
+    });
+    package_key
+}
+
+/// Runs `entry_point` moving the tokens to `recipient`.
+fn run(
+    env: &Env,
+    recipient: Key,
+    entry_point: unsafe extern "C-unwind" fn(),
+) -> Result<(), RevertError> {
+    RECEIVED.with(|received| received.borrow_mut().clear());
+    env.set_arg(ARG_RECIPIENT, recipient);
+    run_entry_point(env.clone(), |_env| unsafe { entry_point() })
+}
+
+fn balance_of(env: &Env, owner: Key) -> U256 {
+    let mut balance = U256::zero();
+    dispatch_with(env.clone(), |_env| {
+        balance = read_balance_from(owner).unwrap()
+    });
+    balance
+}
+
+fn received() -> Vec<(Key, U256)> {
+    RECEIVED.with(|received| received.borrow().clone())
+}
+
+#[test]
+fn transfers_to_contracts_notify_the_recipient() {
+    let env = hook_env(40);
+    let recipient = install_receiver(&env);
+    assert_eq!(run(&env, recipient, transfer_entry_point), Ok(()));
+    assert_eq!(balance_of(&env, recipient), U256::from(40));
+    assert_eq!(received(), [(Key::Account(SENDER), U256::from(40))]);
+}
+
+#[test]
+fn transfer_from_notifies_the_owner_of_the_tokens() {
+    // Not the spender.
+    let env = hook_env(30);
+    let recipient = install_receiver(&env);
+    assert_eq!(run(&env, recipient, transfer_from_entry_point), Ok(()));
+    assert_eq!(balance_of(&env, recipient), U256::from(30));
+    assert_eq!(received(), [(Key::Account(OWNER), U256::from(30))]);
+}
+
+#[test]
+fn contract_entities_are_called_directly() {
+    let env = hook_env(20);
+    let recipient = Key::AddressableEntity(EntityAddr::SmartContract(RECEIVER));
+    assert_eq!(run(&env, recipient, transfer_entry_point), Ok(()));
+    assert_eq!(balance_of(&env, recipient), U256::from(20));
+    assert_eq!(received(), [(Key::Account(SENDER), U256::from(20))]);
+}
+
+#[test]
+fn refusing_recipients_revert_the_transfer() {
+    // The receiver refuses more than 50 tokens.
+    for entry_point in [transfer_entry_point, transfer_from_entry_point] {
+        let env = hook_env(60);
+        let recipient = install_receiver(&env);
+        let result = run(&env, recipient, entry_point);
+        assert_eq!(result.unwrap_err().api_error, ApiError::User(1));
+        assert!(received().is_empty());
+        assert_eq!(balance_of(&env, recipient), U256::zero());
+        assert_eq!(balance_of(&env, Key::Account(SENDER)), U256::from(100));
+        assert_eq!(balance_of(&env, Key::Account(OWNER)), U256::from(100));
+    }
+}
+
+#[test]
+fn accounts_are_not_called() {
+    let env = hook_env(60);
+    let recipient = Key::Account(AccountHash::new([4; 32]));
+    assert_eq!(run(&env, recipient, transfer_entry_point), Ok(()));
+    assert_eq!(balance_of(&env, recipient), U256::from(60));
+    env.assert_trace(|trace| {
+        trace
+            .never(HostFunction::CasperCallContract)
+            .never(HostFunction::CasperCallVersionedContract)
+    });
+}
@@ -0,0 +1,138 @@
+extern crate alloc;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use veles_casper_contract_api::casper_types::{U256, account::AccountHash};
+use veles_casper_contract_api::collections::{
+    base128,
+    dictionary_key::{DictionaryKey, LegacyKey},
+};
+use veles_casper_contract_api::veles_casper_contract_macros::DictionaryKey;
+
+/// Counts the allocations made by the current thread, so tests running in parallel don't skew
+/// each other's counts.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the value of `f` with the number of fresh allocations it made, not counting reallocs.
+fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[derive(DictionaryKey)]
+struct Allowance {
+    owner: AccountHash,
+    spender: AccountHash,
+}
+
+#[derive(DictionaryKey)]
+struct Named<'a>(&'a str);
+
+#[derive(DictionaryKey)]
+struct Singleton;
+
+#[test]
+fn tuple_keys_allocate_once() {
+    let parts = (U256::MAX, u64::MAX, "total");
+    let (key, allocations) = count_allocations(|| parts.dictionary_key());
+    assert_eq!(allocations, 1);
+    assert_eq!(
+        key,
+        [
+            base128::encode_bytes(&[255; 32]),
+            base128::encode_bytes(&u64::MAX.to_le_bytes()),
+            base128::encode_bytes(&5u32.to_le_bytes()) + "total",
+        ]
+        .join(":")
+    );
+}
+
+#[test]
+fn derived_keys_allocate_once() {
+    let allowance = Allowance {
+        owner: AccountHash::new([1; 32]),
+        spender: AccountHash::new([2; 32]),
+    };
+    let (key, allocations) = count_allocations(|| allowance.dictionary_key());
+    assert_eq!(allocations, 1);
+    assert_eq!(key, (allowance.owner, allowance.spender).dictionary_key());
+}
+
+#[test]
+fn derived_string_parts_do_not_collide() {
+    #[derive(DictionaryKey)]
+    struct Route<'a> {
+        from: &'a str,
+        to: &'a str,
+    }
+
+    let first = Route {
+        from: "a:b",
+        to: "c",
+    };
+    let second = Route {
+        from: "a",
+        to: "b:c",
+    };
+    assert_ne!(first.dictionary_key(), second.dictionary_key());
+    assert_eq!(first.dictionary_key(), ("a:b", "c").dictionary_key());
+    // Single-field keys are length-prefixed as parts too.
+    assert_eq!(
+        (Named("a:b"), "c").dictionary_key(),
+        ("a:b", "c").dictionary_key()
+    );
+}
+
+#[test]
+fn derived_keys_nest_like_tuples() {
+    let allowance = Allowance {
+        owner: AccountHash::new([1; 32]),
+        spender: AccountHash::new([2; 32]),
+    };
+    assert_eq!(
+        (&allowance, "total").dictionary_key(),
+        ((allowance.owner, allowance.spender), "total").dictionary_key()
+    );
+    assert_eq!(
+        LegacyKey((&allowance, Named("total"))).dictionary_key(),
+        LegacyKey((allowance.owner, allowance.spender, "total")).dictionary_key()
+    );
+}
+
+#[test]
+fn borrowed_keys_do_not_allocate() {
+    let named = Named("balances");
+    let (key, allocations) = count_allocations(|| named.dictionary_key());
+    assert_eq!(allocations, 0);
+    assert_eq!(key, "balances");
+
+    let (key, allocations) = count_allocations(|| Singleton.dictionary_key());
+    assert_eq!(allocations, 0);
+    assert_eq!(key, "");
+}
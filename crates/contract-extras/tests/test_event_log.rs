@@ -0,0 +1,71 @@
+//! A contract keeping its last transfers in a bounded event log, under the shim.
+extern crate alloc;
+
+use veles_casper_contract_api::casper_contract::contract_api::runtime;
+use veles_casper_contract_api::casper_types::{
+    CLType, CLTyped, Key, NamedKeys, U256, account::AccountHash,
+};
+use veles_casper_contract_api::collections::ring_buffer::RingBuffer;
+use veles_casper_contract_api::prelude::*;
+use veles_casper_contract_api::veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+use veles_casper_contract_extras::event_log::{self, record_bounded_event};
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperSerialize)]
+pub struct Transfer {
+    recipient: Key,
+    amount: U256,
+}
+
+impl CLTyped for Transfer {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+fn transfer(amount: u64) -> Transfer {
+    Transfer {
+        recipient: Key::Account(AccountHash::new([1; 32])),
+        amount: U256::from(amount),
+    }
+}
+
+/// Installs the log of `buffer` with `capacity`, as an installer would in `call`.
+fn install(buffer: &RingBuffer<Transfer>, capacity: u64) {
+    let mut named_keys = NamedKeys::new();
+    event_log::install(buffer, capacity, &mut named_keys).unwrap();
+    for (name, key) in named_keys.iter() {
+        runtime::put_key(name, *key);
+    }
+}
+
+#[test]
+fn the_log_keeps_the_last_events() {
+    static HISTORY: RingBuffer<Transfer> =
+        RingBuffer::from_named_key(NamedKey::from_name("history"));
+
+    dispatch_with(EnvBuilder::new().build(), |_env| {
+        install(&HISTORY, 2);
+        assert_eq!(HISTORY.capacity(), Ok(2));
+        for (index, amount) in [10, 20, 30].into_iter().enumerate() {
+            assert_eq!(
+                record_bounded_event(&HISTORY, transfer(amount)),
+                Ok(Some(index as u64))
+            );
+        }
+        assert_eq!(HISTORY.len(), Ok(2));
+        assert_eq!(HISTORY.get(0), Ok(None));
+        assert_eq!(HISTORY.get(1), Ok(Some(transfer(20))));
+        assert_eq!(HISTORY.latest(), Ok(Some(transfer(30))));
+    });
+}
+
+#[test]
+fn contracts_without_a_log_record_nothing() {
+    static NO_HISTORY: RingBuffer<Transfer> =
+        RingBuffer::from_named_key(NamedKey::from_name("no_history"));
+
+    dispatch_with(EnvBuilder::new().build(), |_env| {
+        assert_eq!(record_bounded_event(&NO_HISTORY, transfer(10)), Ok(None));
+        assert!(!runtime::has_key(NO_HISTORY.named_uref().name()));
+    });
+}
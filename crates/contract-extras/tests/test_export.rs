@@ -0,0 +1,534 @@
+extern crate alloc;
+
+use std::cell::RefCell;
+
+use veles_casper_contract_api::bounded::{BoundedBytes, BoundedString};
+use veles_casper_contract_api::casper_types::bytesrepr::{self, Bytes, FromBytes, ToBytes};
+use veles_casper_contract_api::casper_types::contracts::ContractHash;
+use veles_casper_contract_api::casper_types::{ApiError, CLType, CLTyped, CLValue, RuntimeArgs};
+use veles_casper_contract_api::macro_support::{IntoRuntimeArgs, Unset};
+use veles_casper_contract_api::prelude::*;
+use veles_casper_contract_api::veles_casper_ffi_shim::{
+    EnvBuilder, dispatch_with, run_entry_point,
+};
+
+thread_local! {
+    static LAST_CALL: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn record(value: String) {
+    LAST_CALL.with(|last_call| *last_call.borrow_mut() = Some(value));
+}
+
+fn take_last_call() -> Option<String> {
+    LAST_CALL.with(|last_call| last_call.borrow_mut().take())
+}
+
+#[casper(contract)]
+pub mod borrowed {
+    use super::*;
+
+    #[casper(export)]
+    pub fn greet(who: &str) {
+        record(format!("Hello, {who}!"));
+    }
+
+    #[casper(export)]
+    pub fn checksum(data: &[u8], seed: u8) {
+        let sum = data.iter().fold(seed, |acc, byte| acc.wrapping_add(*byte));
+        record(format!("{sum}"));
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum VaultError {
+    UnknownAccount,
+    InsufficientFunds,
+}
+
+impl From<VaultError> for ApiError {
+    fn from(error: VaultError) -> Self {
+        ApiError::User(100 + error as u16)
+    }
+}
+
+#[casper(contract)]
+pub mod vault {
+    use super::*;
+
+    #[casper(export)]
+    pub fn withdraw(account: &str, amount: u64) -> Result<u64, VaultError> {
+        let balance = match account {
+            "alice" => 100u64,
+            _ => return Err(VaultError::UnknownAccount),
+        };
+        balance
+            .checked_sub(amount)
+            .ok_or(VaultError::InsufficientFunds)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BankError {
+    NoSuchCustomer,
+    Overdrawn,
+}
+
+impl From<VaultError> for BankError {
+    fn from(error: VaultError) -> Self {
+        match error {
+            VaultError::UnknownAccount => BankError::NoSuchCustomer,
+            VaultError::InsufficientFunds => BankError::Overdrawn,
+        }
+    }
+}
+
+impl From<BankError> for ApiError {
+    fn from(error: BankError) -> Self {
+        ApiError::User(200 + error as u16)
+    }
+}
+
+#[casper(contract)]
+pub mod bank {
+    use super::*;
+
+    #[casper(export)]
+    pub fn cash_out(customer: String, amount: u64) -> Result<u64, BankError> {
+        let vault = vault::Client::new(ContractHash::default());
+        let remaining = vault.try_withdraw(&customer, amount)?;
+        Ok(remaining)
+    }
+}
+
+/// Serializes as a `u64` unless `fail` is set, in which case the `CLValue` conversion fails.
+#[derive(Debug, Clone, Copy)]
+pub struct Flaky {
+    fail: bool,
+}
+
+impl CLTyped for Flaky {
+    fn cl_type() -> CLType {
+        CLType::U64
+    }
+}
+
+impl ToBytes for Flaky {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        if self.fail {
+            return Err(bytesrepr::Error::Formatting);
+        }
+        0u64.to_bytes()
+    }
+
+    fn serialized_length(&self) -> usize {
+        0u64.serialized_length()
+    }
+}
+
+impl FromBytes for Flaky {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (_, rest) = u64::from_bytes(bytes)?;
+        Ok((Flaky { fail: false }, rest))
+    }
+}
+
+#[casper(contract)]
+pub mod flaky {
+    use super::*;
+
+    #[casper(export)]
+    pub fn store_flaky(value: Flaky, extra: Option<Flaky>) {
+        record(format!("{value:?} {extra:?}"));
+    }
+}
+
+#[casper(contract)]
+pub mod payments {
+    use super::*;
+
+    #[casper(export)]
+    pub fn pay(
+        recipient: String,
+        amount: u64,
+        memo: Option<String>,
+        #[casper(explicit_none)] reference: Option<u64>,
+    ) {
+        record(format!("{recipient} {amount} {memo:?} {reference:?}"));
+    }
+}
+
+#[casper(contract)]
+pub mod symbols {
+    use super::*;
+
+    #[casper(export)]
+    pub fn register(symbol: BoundedString<8>, tag: BoundedBytes<4>) {
+        record(format!("{symbol} {:?}", &*tag));
+    }
+}
+
+#[casper(contract)]
+pub mod renamed {
+    use super::*;
+
+    #[casper(export, name = "balance-of", rename(kind = "type"))]
+    pub fn balance_of(#[casper(arg = "owner-key")] owner: String, kind: u8) {
+        record(format!("{owner} {kind}"));
+    }
+}
+
+unsafe extern "C-unwind" {
+    #[link_name = "balance-of"]
+    fn balance_of_entry_point();
+    #[link_name = "register"]
+    fn register_entry_point();
+    #[link_name = "greet"]
+    fn greet_entry_point();
+    #[link_name = "checksum"]
+    fn checksum_entry_point();
+    #[link_name = "pay"]
+    fn pay_entry_point();
+}
+
+#[test]
+fn borrowed_str_argument_end_to_end() {
+    let env = EnvBuilder::new().with_arg("who", "Casper").build();
+    dispatch_with(env, |_env| {
+        unsafe { greet_entry_point() };
+    });
+    assert_eq!(take_last_call().as_deref(), Some("Hello, Casper!"));
+}
+
+#[test]
+fn borrowed_slice_argument_end_to_end() {
+    let env = EnvBuilder::new()
+        .with_arg("data", Bytes::from(vec![1u8, 2, 3]))
+        .with_arg("seed", 10u8)
+        .build();
+    dispatch_with(env, |_env| {
+        unsafe { checksum_entry_point() };
+    });
+    assert_eq!(take_last_call().as_deref(), Some("16"));
+}
+
+#[test]
+fn borrowed_arguments_use_owned_types() {
+    let args = borrowed::greet::Args {
+        who: String::from("Casper"),
+    }
+    .into_runtime_args();
+    assert_eq!(args.get("who").unwrap().cl_type(), &String::cl_type());
+
+    let entry_points = borrowed::entry_points_vec();
+    let greet = entry_points
+        .iter()
+        .find(|entry_point| entry_point.name() == "greet")
+        .unwrap();
+    assert_eq!(greet.args()[0].cl_type(), &CLType::String);
+
+    let checksum = entry_points
+        .iter()
+        .find(|entry_point| entry_point.name() == "checksum")
+        .unwrap();
+    assert_eq!(
+        checksum.args()[0].cl_type(),
+        &CLType::List(Box::new(CLType::U8))
+    );
+}
+
+#[test]
+fn try_method_returns_typed_error() {
+    let vault = vault::Client::new(ContractHash::default());
+    assert_eq!(vault.try_withdraw("alice", 40), Ok(60));
+    assert_eq!(
+        vault.try_withdraw("alice", 101),
+        Err(VaultError::InsufficientFunds)
+    );
+    assert_eq!(
+        vault.try_withdraw("bob", 1),
+        Err(VaultError::UnknownAccount)
+    );
+}
+
+#[test]
+fn outer_entry_point_maps_inner_error() {
+    assert_eq!(bank::cash_out("alice".into(), 25), Ok(75));
+    assert_eq!(
+        bank::cash_out("alice".into(), 500),
+        Err(BankError::Overdrawn)
+    );
+    assert_eq!(
+        bank::cash_out("carol".into(), 1),
+        Err(BankError::NoSuchCustomer)
+    );
+    assert_eq!(ApiError::from(BankError::Overdrawn), ApiError::User(201));
+}
+
+#[test]
+fn args_conversion_errors_are_returned() {
+    const OK: Flaky = Flaky { fail: false };
+    const FAIL: Flaky = Flaky { fail: true };
+
+    let args = flaky::store_flaky::Args {
+        value: OK,
+        extra: None,
+    };
+    assert_eq!(args.try_into_runtime_args().unwrap().len(), 1);
+
+    for (value, extra) in [(FAIL, None), (OK, Some(FAIL))] {
+        let args = flaky::store_flaky::Args { value, extra };
+        assert_eq!(
+            veles_casper_contract_api::macro_support::try_into_runtime_args(args),
+            Err(ApiError::Formatting)
+        );
+    }
+
+    let args = flaky::store_flaky::Args {
+        value: FAIL,
+        extra: None,
+    };
+    assert!(std::panic::catch_unwind(|| args.into_runtime_args()).is_err());
+}
+
+fn arg_names(args: &RuntimeArgs) -> Vec<&str> {
+    let mut names: Vec<&str> = args.named_args().map(|arg| arg.name()).collect();
+    names.sort_unstable();
+    names
+}
+
+#[test]
+fn unset_optional_arguments_are_omitted() {
+    // The builder can start in a constant, leaving every argument unset.
+    const PAY: payments::pay::ArgsBuilder<Unset, Unset> = payments::pay::Args::builder();
+
+    let args = PAY
+        .amount(5)
+        .recipient("bob".into())
+        .build()
+        .into_runtime_args();
+    assert_eq!(arg_names(&args), ["amount", "recipient", "reference"]);
+    assert_eq!(
+        args.get("reference"),
+        Some(&CLValue::from_t(None::<u64>).unwrap())
+    );
+
+    let args = payments::pay::Args::builder()
+        .recipient("bob".into())
+        .memo("rent".into())
+        .amount(5)
+        .build()
+        .into_runtime_args();
+    assert_eq!(
+        arg_names(&args),
+        ["amount", "memo", "recipient", "reference"]
+    );
+    assert_eq!(args.get("memo"), Some(&CLValue::from_t("rent").unwrap()));
+
+    let args = payments::pay::Args::builder()
+        .reference(7)
+        .recipient("bob".into())
+        .amount(5)
+        .build()
+        .into_runtime_args();
+    assert_eq!(arg_names(&args), ["amount", "recipient", "reference"]);
+    assert_eq!(
+        args.get("reference"),
+        Some(&CLValue::from_t(Some(7u64)).unwrap())
+    );
+
+    let args = payments::pay::Args {
+        recipient: "bob".into(),
+        amount: 5,
+        memo: Some("rent".into()),
+        reference: Some(7),
+    }
+    .into_runtime_args();
+    assert_eq!(
+        arg_names(&args),
+        ["amount", "memo", "recipient", "reference"]
+    );
+}
+
+#[test]
+fn explicit_none_arguments_are_required_options() {
+    let entry_points = payments::entry_points_vec();
+    let pay = entry_points
+        .iter()
+        .find(|entry_point| entry_point.name() == "pay")
+        .unwrap();
+    let cl_types: Vec<(&str, &CLType)> = pay
+        .args()
+        .iter()
+        .map(|parameter| (parameter.name(), parameter.cl_type()))
+        .collect();
+    assert_eq!(
+        cl_types,
+        [
+            ("recipient", &CLType::String),
+            ("amount", &CLType::U64),
+            ("memo", &CLType::String),
+            ("reference", &CLType::Option(Box::new(CLType::U64))),
+        ]
+    );
+
+    let env = EnvBuilder::new()
+        .with_arg("recipient", "bob")
+        .with_arg("amount", 5u64)
+        .with_arg("reference", None::<u64>)
+        .build();
+    dispatch_with(env, |_env| {
+        unsafe { pay_entry_point() };
+    });
+    assert_eq!(take_last_call().as_deref(), Some("bob 5 None None"));
+
+    let env = EnvBuilder::new()
+        .with_arg("recipient", "bob")
+        .with_arg("amount", 5u64)
+        .build();
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { pay_entry_point() });
+    assert_eq!(result.unwrap_err().api_error, ApiError::MissingArgument);
+}
+
+/// A hand-modelled bundle of the arguments of `payments::pay`.
+#[derive(IntoRuntimeArgs)]
+struct Payment {
+    recipient: String,
+    amount: u64,
+    memo: Option<String>,
+    #[casper(explicit_none)]
+    reference: Option<u64>,
+}
+
+/// The arguments of `renamed::balance_of`, under their argument names.
+#[derive(IntoRuntimeArgs)]
+struct BalanceQuery {
+    #[casper(arg = "owner-key")]
+    owner: String,
+    #[casper(arg = "type")]
+    kind: u8,
+}
+
+#[derive(IntoRuntimeArgs)]
+struct Wrapped<T> {
+    value: T,
+}
+
+#[derive(IntoRuntimeArgs)]
+struct NoArgs;
+
+#[test]
+fn derived_args_match_the_generated_args() {
+    let args = Payment {
+        recipient: "bob".into(),
+        amount: 5,
+        memo: None,
+        reference: None,
+    }
+    .into_runtime_args();
+    assert_eq!(
+        args,
+        payments::pay::Args {
+            recipient: "bob".into(),
+            amount: 5,
+            memo: None,
+            reference: None,
+        }
+        .into_runtime_args()
+    );
+    assert_eq!(arg_names(&args), ["amount", "recipient", "reference"]);
+
+    let env = EnvBuilder::new()
+        .with_args(
+            args.named_args()
+                .map(|arg| (arg.name().to_string(), arg.cl_value().clone()))
+                .collect(),
+        )
+        .build();
+    dispatch_with(env, |_env| {
+        unsafe { pay_entry_point() };
+    });
+    assert_eq!(take_last_call().as_deref(), Some("bob 5 None None"));
+
+    let args = Wrapped { value: 7u32 }.into_runtime_args();
+    assert_eq!(args.get("value"), Some(&CLValue::from_t(7u32).unwrap()));
+    assert_eq!(
+        Wrapped {
+            value: Flaky { fail: true }
+        }
+        .try_into_runtime_args(),
+        Err(ApiError::Formatting)
+    );
+    assert!(NoArgs.into_runtime_args().is_empty());
+}
+
+#[test]
+fn renamed_entry_points_use_their_wire_names() {
+    assert_eq!(renamed::balance_of::NAME, "balance-of");
+    let entry_points = renamed::entry_points_vec();
+    assert_eq!(entry_points[0].name(), "balance-of");
+    let parameters: Vec<&str> = entry_points[0]
+        .args()
+        .iter()
+        .map(|parameter| parameter.name())
+        .collect();
+    assert_eq!(parameters, ["owner-key", "type"]);
+
+    let args = renamed::balance_of::Args {
+        owner: "alice".into(),
+        kind: 2,
+    }
+    .into_runtime_args();
+    assert_eq!(arg_names(&args), ["owner-key", "type"]);
+    assert_eq!(
+        args,
+        BalanceQuery {
+            owner: "alice".into(),
+            kind: 2,
+        }
+        .into_runtime_args()
+    );
+
+    let env = EnvBuilder::new()
+        .with_args(
+            args.named_args()
+                .map(|arg| (arg.name().to_string(), arg.cl_value().clone()))
+                .collect(),
+        )
+        .build();
+    dispatch_with(env, |_env| {
+        unsafe { balance_of_entry_point() };
+    });
+    assert_eq!(take_last_call().as_deref(), Some("alice 2"));
+
+    // The Rust parameter names aren't read.
+    let env = EnvBuilder::new()
+        .with_arg("owner", "alice")
+        .with_arg("kind", 2u8)
+        .build();
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { balance_of_entry_point() });
+    assert_eq!(result.unwrap_err().api_error, ApiError::MissingArgument);
+}
+
+#[test]
+fn bounded_arguments_end_to_end() {
+    let entry_points = symbols::entry_points_vec();
+    assert_eq!(entry_points[0].args()[0].cl_type(), &CLType::String);
+    assert_eq!(entry_points[0].args()[1].cl_type(), &Bytes::cl_type());
+
+    let env = EnvBuilder::new()
+        .with_arg("symbol", "CSPR")
+        .with_arg("tag", Bytes::from(vec![1u8, 2]))
+        .build();
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { register_entry_point() });
+    assert_eq!(result, Ok(()));
+    assert_eq!(take_last_call().as_deref(), Some("CSPR [1, 2]"));
+
+    let env = EnvBuilder::new()
+        .with_arg("symbol", "NOT-A-SYMBOL")
+        .with_arg("tag", Bytes::from(vec![1u8, 2]))
+        .build();
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { register_entry_point() });
+    assert_eq!(result.unwrap_err().api_error, ApiError::InvalidArgument);
+    assert_eq!(take_last_call(), None);
+}
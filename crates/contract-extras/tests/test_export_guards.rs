@@ -0,0 +1,137 @@
+//! Tests of the `init_once` and `only_installer` guards of exported entry points.
+extern crate alloc;
+
+use std::cell::Cell;
+
+use veles_casper_contract_api::casper_types::{
+    AccessRights, CLType, Key, URef, account::AccountHash, contracts::NamedKeys,
+};
+use veles_casper_contract_api::error::UniversalError;
+use veles_casper_contract_api::macro_support::INSTALLER_KEY_NAME;
+use veles_casper_contract_api::prelude::*;
+use veles_casper_contract_api::state_spec::NamedKeyKind;
+use veles_casper_contract_api::veles_casper_ffi_shim::{
+    AccountConfig, Env, EnvBuilder, dispatch_with, run_entry_point,
+};
+
+const INSTALLER: AccountHash = AccountHash::new([1; 32]);
+const STRANGER: AccountHash = AccountHash::new([2; 32]);
+
+thread_local! {
+    static SETUPS: Cell<u32> = const { Cell::new(0) };
+}
+
+#[casper(contract)]
+pub mod guarded {
+    use super::*;
+
+    #[casper(export, init_once)]
+    pub fn setup(fee: u64) {
+        assert_eq!(fee, 25);
+        SETUPS.with(|setups| setups.set(setups.get() + 1));
+    }
+
+    #[casper(export, only_installer)]
+    pub fn set_fee(fee: u64) -> Result<(), ApiError> {
+        if fee > 100 {
+            return Err(ApiError::InvalidArgument);
+        }
+        Ok(())
+    }
+}
+
+unsafe extern "C-unwind" {
+    #[link_name = "setup"]
+    fn setup_entry_point();
+    #[link_name = "set_fee"]
+    fn set_fee_entry_point();
+}
+
+fn account(account_hash: AccountHash) -> AccountConfig {
+    AccountConfig::new(
+        account_hash,
+        URef::new([0xff; 32], AccessRights::READ_ADD_WRITE),
+    )
+}
+
+/// Returns an environment in which `caller` calls a contract installed by `INSTALLER`.
+fn installed_env(caller: AccountHash, fee: u64) -> Env {
+    let mut named_keys = NamedKeys::new();
+    dispatch_with(
+        EnvBuilder::new().with_account(account(INSTALLER)).build(),
+        |_env| guarded::insert_installer_key(&mut named_keys),
+    );
+    let mut builder = EnvBuilder::new()
+        .with_account(account(caller))
+        .with_arg("fee", fee);
+    for (name, key) in named_keys.iter() {
+        builder = builder.with_named_key(name, *key);
+    }
+    builder.build()
+}
+
+#[test]
+fn init_once_rejects_a_second_call() {
+    let env = EnvBuilder::new().with_arg("fee", 25u64).build();
+    let result: Result<(), _> = run_entry_point(env.clone(), |_env| unsafe { setup_entry_point() });
+    assert_eq!(result, Ok(()));
+    assert!(
+        env.named_keys()
+            .contains_key(guarded::setup::INIT_DONE_KEY_NAME)
+    );
+
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { setup_entry_point() });
+    let revert = result.expect_err("a second setup should revert");
+    assert_eq!(revert.api_error, UniversalError::AlreadyInitialized.into());
+    assert_eq!(SETUPS.with(Cell::get), 1);
+}
+
+#[test]
+fn only_installer_rejects_other_callers() {
+    let env = installed_env(INSTALLER, 50);
+    assert_eq!(
+        env.named_keys().get(INSTALLER_KEY_NAME),
+        Some(&Key::Account(INSTALLER))
+    );
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { set_fee_entry_point() });
+    assert_eq!(result, Ok(()));
+
+    // The guard composes with the errors of the entry point
+    let result: Result<(), _> = run_entry_point(installed_env(INSTALLER, 500), |_env| unsafe {
+        set_fee_entry_point()
+    });
+    assert_eq!(result.unwrap_err().api_error, ApiError::InvalidArgument);
+
+    let result: Result<(), _> = run_entry_point(installed_env(STRANGER, 50), |_env| unsafe {
+        set_fee_entry_point()
+    });
+    let revert = result.expect_err("a stranger should be rejected");
+    assert_eq!(revert.api_error, UniversalError::CallerNotInstaller.into());
+
+    // A contract installed without the key rejects every caller
+    let env = EnvBuilder::new()
+        .with_account(account(INSTALLER))
+        .with_arg("fee", 50u64)
+        .build();
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { set_fee_entry_point() });
+    assert_eq!(result.unwrap_err().api_error, ApiError::MissingKey);
+}
+
+#[test]
+fn guards_are_unchanged_entry_points_with_manifested_keys() {
+    let entry_points = guarded::entry_points_vec();
+    for entry_point in &entry_points {
+        assert_eq!(entry_point.args().len(), 1);
+        assert_eq!(entry_point.args()[0].name(), "fee");
+    }
+
+    let spec = guarded::state_spec();
+    assert_eq!(
+        spec.named_keys().get("__init_setup_done"),
+        Some(&NamedKeyKind::Value(CLType::Bool))
+    );
+    assert_eq!(
+        spec.named_keys().get(INSTALLER_KEY_NAME),
+        Some(&NamedKeyKind::Any)
+    );
+}
@@ -0,0 +1,45 @@
+//! Gas estimates of cep18 flows, based on the host function trace of the shim.
+//!
+//! Kept in a separate test binary since the cep18 named keys cache their keys in statics, which
+//! would make the trace depend on the other tests that ran before.
+use veles_casper_contract_api::casper_contract::contract_api::{runtime, storage};
+use veles_casper_contract_api::casper_types::{Key, U256, account::AccountHash};
+use veles_casper_contract_api::veles_casper_ffi_shim::{
+    CostModel, EnvBuilder, assert_gas_under, dispatch_with,
+};
+use veles_casper_contract_extras::cep18::{
+    balances::{read_balance_from, transfer_balance, write_balance_to},
+    constants::{ARG_AMOUNT, ARG_RECIPIENT, DICT_BALANCES},
+};
+
+const SENDER: Key = Key::Account(AccountHash::new([1; 32]));
+const RECIPIENT: Key = Key::Account(AccountHash::new([2; 32]));
+
+#[test]
+fn transfer_gas_estimate() {
+    let env = EnvBuilder::new()
+        .with_arg(ARG_RECIPIENT, RECIPIENT)
+        .with_arg(ARG_AMOUNT, U256::from(40))
+        .build();
+    dispatch_with(env, |env| {
+        storage::new_dictionary(DICT_BALANCES).unwrap();
+        write_balance_to(SENDER, U256::from(100)).unwrap();
+        env.trace_records();
+
+        let recipient: Key = runtime::get_named_arg(ARG_RECIPIENT);
+        let amount: U256 = runtime::get_named_arg(ARG_AMOUNT);
+        transfer_balance(SENDER, recipient, amount).unwrap();
+
+        let model = CostModel::default();
+        let report = env.gas_report(&model);
+        assert_eq!(report.host_functions["dictionary_get"].calls, 2);
+        assert_eq!(report.host_functions["dictionary_put"].calls, 2);
+        assert_eq!(env.estimated_gas(&model), report.total);
+        assert_eq!(report.total, 6_252_520);
+        assert_gas_under!(env, model, 7_000_000);
+
+        // The estimate does not drain the trace.
+        assert!(!env.trace().is_empty());
+        assert_eq!(read_balance_from(RECIPIENT), Ok(U256::from(40)));
+    });
+}
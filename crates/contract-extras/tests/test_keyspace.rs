@@ -0,0 +1,66 @@
+//! The host-side `keyspace` item keys against the keys CEP-18 storage writes under the shim.
+use std::collections::BTreeSet;
+
+use veles_casper_contract_api::casper_contract::contract_api::storage;
+use veles_casper_contract_api::casper_types::{EntityAddr, Key, U256, account::AccountHash};
+use veles_casper_contract_api::keyspace;
+use veles_casper_contract_api::veles_casper_ffi_shim::{EnvBuilder, dispatch_with};
+use veles_casper_contract_extras::cep18::{
+    allowances::write_allowance_with,
+    balances::write_balance_with,
+    constants::{DICT_ALLOWANCES, DICT_BALANCES},
+    modalities::KeyEncoding,
+};
+
+const HOLDERS: [Key; 4] = [
+    Key::Account(AccountHash::new([1; 32])),
+    Key::Hash([2; 32]),
+    Key::Hash([3; 32]),
+    Key::AddressableEntity(EntityAddr::SmartContract([4; 32])),
+];
+
+#[test]
+fn balance_item_keys_match_the_stored_keys() {
+    for key_encoding in [KeyEncoding::Base64, KeyEncoding::Base128] {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary(DICT_BALANCES).unwrap();
+            for owner in &HOLDERS {
+                write_balance_with(key_encoding, *owner, U256::from(7)).unwrap();
+            }
+
+            let stored: BTreeSet<String> =
+                env.dictionary(dictionary).unwrap().into_keys().collect();
+            let expected: BTreeSet<String> = HOLDERS
+                .iter()
+                .map(|owner| match key_encoding {
+                    KeyEncoding::Base64 => keyspace::cep18_balance_item_key(owner),
+                    KeyEncoding::Base128 => keyspace::collections_item_key(owner),
+                })
+                .collect();
+            assert_eq!(stored, expected, "{key_encoding:?}");
+        });
+    }
+}
+
+#[test]
+fn allowance_item_keys_match_the_stored_keys() {
+    for key_encoding in [KeyEncoding::Base64, KeyEncoding::Base128] {
+        dispatch_with(EnvBuilder::new().build(), |env| {
+            let dictionary = storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+            let mut expected = BTreeSet::new();
+            for owner in &HOLDERS {
+                for spender in HOLDERS.iter().filter(|spender| *spender != owner) {
+                    write_allowance_with(key_encoding, *owner, *spender, U256::from(7)).unwrap();
+                    expected.insert(match key_encoding {
+                        KeyEncoding::Base64 => keyspace::cep18_allowance_item_key(owner, spender),
+                        KeyEncoding::Base128 => keyspace::collections_item_key(&(*owner, *spender)),
+                    });
+                }
+            }
+
+            let stored: BTreeSet<String> =
+                env.dictionary(dictionary).unwrap().into_keys().collect();
+            assert_eq!(stored, expected, "{key_encoding:?}");
+        });
+    }
+}
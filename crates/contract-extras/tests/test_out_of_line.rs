@@ -0,0 +1,34 @@
+extern crate alloc;
+
+use veles_casper_contract_api::casper_types::contracts::ContractHash;
+use veles_casper_contract_api::casper_types::{ApiError, CLType};
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod shapes {
+    include!("out_of_line/shapes.rs");
+}
+
+#[test]
+fn included_exports_generate_entry_points() {
+    let entry_points = shapes::entry_points_vec();
+    let names: Vec<_> = entry_points
+        .iter()
+        .map(|entry_point| entry_point.name())
+        .collect();
+    assert_eq!(names, ["area", "describe"]);
+
+    let area = &entry_points[0];
+    assert_eq!(area.args().len(), 2);
+    assert_eq!(area.ret(), &CLType::U64);
+    assert_eq!(entry_points[1].ret(), &CLType::String);
+    assert_eq!(shapes::area::NAME, "area");
+}
+
+#[test]
+fn included_exports_generate_client() {
+    let client = shapes::Client::new(ContractHash::default());
+    assert_eq!(client.try_describe("square").as_deref(), Ok("shape square"));
+    assert_eq!(client.try_describe(""), Err(ApiError::InvalidArgument));
+    assert_eq!(shapes::area(3, 4), 12);
+}
@@ -0,0 +1,122 @@
+//! Unit tests of cep18 entry points through `run_entry_point`, which catches their reverts.
+use veles_casper_contract_api::casper_contract::contract_api::storage;
+use veles_casper_contract_api::casper_types::{
+    AccessRights, CLValue, Key, StoredValue, U256, URef, account::AccountHash,
+};
+use veles_casper_contract_api::error::UniversalError;
+use veles_casper_contract_api::macro_support::{IntoRuntimeArgs, WithTypedArgs};
+use veles_casper_contract_api::veles_casper_ffi_shim::{
+    AccountConfig, Env, EnvBuilder, dispatch_with, run_entry_point,
+};
+use veles_casper_contract_extras::cep18::{
+    allowances::read_allowance_from,
+    cep18,
+    constants::{ARG_AMOUNT, ARG_EVENTS_MODE, DICT_ALLOWANCES},
+    error::Cep18Error,
+    modalities::EventsMode,
+};
+
+const OWNER: AccountHash = AccountHash::new([1; 32]);
+const SPENDER: Key = Key::Account(AccountHash::new([2; 32]));
+
+unsafe extern "C-unwind" {
+    #[link_name = "approve"]
+    fn approve_entry_point();
+    #[link_name = "init"]
+    fn init_entry_point();
+}
+
+/// Returns an environment in which `OWNER` calls `approve` with `spender` and `amount`.
+fn approve_env(spender: Key, amount: U256) -> Env {
+    let events_mode = URef::new([0xee; 32], AccessRights::READ_ADD_WRITE);
+    EnvBuilder::new()
+        .with_account(AccountConfig::new(
+            OWNER,
+            URef::new([0xff; 32], AccessRights::READ_ADD_WRITE),
+        ))
+        .with_storage(
+            Key::URef(events_mode),
+            StoredValue::CLValue(CLValue::from_t(EventsMode::NoEvents as u8).unwrap()),
+        )
+        .with_named_key(ARG_EVENTS_MODE, Key::URef(events_mode))
+        .with_typed_args(cep18::approve::Args {
+            spender,
+            amount,
+            expiry: None,
+        })
+        .build()
+}
+
+#[test]
+fn approve_sets_allowance() {
+    let env = approve_env(SPENDER, U256::from(50));
+    let result = run_entry_point(env.clone(), |_env| {
+        storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+        unsafe { approve_entry_point() };
+    });
+    assert_eq!(result, Ok(()));
+
+    // The environment keeps the state the entry point left behind.
+    dispatch_with(env, |_env| {
+        assert_eq!(
+            read_allowance_from(Key::Account(OWNER), SPENDER),
+            Ok(U256::from(50))
+        );
+    });
+}
+
+#[test]
+fn typed_args_are_read_as_inserted() {
+    let env = approve_env(SPENDER, U256::from(50));
+    let runtime_args = cep18::approve::Args {
+        spender: SPENDER,
+        amount: U256::from(50),
+        expiry: None,
+    }
+    .into_runtime_args();
+    assert_eq!(env.args().len(), runtime_args.len());
+    for arg in runtime_args.named_args() {
+        assert_eq!(env.args().get(arg.name()), Some(arg.cl_value()));
+    }
+
+    let result = run_entry_point(env.clone(), |_env| {
+        storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+        unsafe { approve_entry_point() };
+    });
+    assert_eq!(result, Ok(()));
+
+    // A second call sees the updated amount.
+    env.set_arg(ARG_AMOUNT, U256::from(70));
+    let result = run_entry_point(env.clone(), |_env| unsafe { approve_entry_point() });
+    assert_eq!(result, Ok(()));
+    dispatch_with(env, |_env| {
+        assert_eq!(
+            read_allowance_from(Key::Account(OWNER), SPENDER),
+            Ok(U256::from(70))
+        );
+    });
+}
+
+#[test]
+fn approve_reverts_on_self_approval() {
+    let result: Result<(), _> =
+        run_entry_point(approve_env(Key::Account(OWNER), U256::from(50)), |_env| {
+            storage::new_dictionary(DICT_ALLOWANCES).unwrap();
+            unsafe { approve_entry_point() };
+        });
+    let revert = result.expect_err("self approval should revert");
+    assert_eq!(revert.api_error, Cep18Error::CannotTargetSelfUser.into());
+}
+
+#[test]
+fn init_reverts_once_initialized() {
+    let env = EnvBuilder::new()
+        .with_named_key(
+            cep18::init::INIT_DONE_KEY_NAME,
+            Key::URef(URef::new([0xdd; 32], AccessRights::READ_ADD_WRITE)),
+        )
+        .build();
+    let result: Result<(), _> = run_entry_point(env, |_env| unsafe { init_entry_point() });
+    let revert = result.expect_err("a second init should revert");
+    assert_eq!(revert.api_error, UniversalError::AlreadyInitialized.into());
+}
@@ -0,0 +1,70 @@
+extern crate alloc;
+
+use veles_casper_contract_api::casper_types::{
+    Key, U256,
+    account::AccountHash,
+    bytesrepr::{self, Bytes, FromBytes, ToBytes},
+};
+use veles_casper_contract_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperSerialize)]
+struct Transfer {
+    sender: Key,
+    recipient: Key,
+    amount: U256,
+    memo: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperSerialize)]
+struct Tagged<T>(u8, T);
+
+#[derive(Debug, Clone, PartialEq, Eq, CasperSerialize)]
+struct Marker;
+
+/// Manual implementation following the pattern the derive replaces.
+fn manual_transfer_bytes(transfer: &Transfer) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    transfer.sender.write_bytes(&mut buffer).unwrap();
+    transfer.recipient.write_bytes(&mut buffer).unwrap();
+    transfer.amount.write_bytes(&mut buffer).unwrap();
+    transfer.memo.write_bytes(&mut buffer).unwrap();
+    buffer
+}
+
+#[test]
+fn named_fields_round_trip() {
+    let transfer = Transfer {
+        sender: Key::Account(AccountHash::new([1; 32])),
+        recipient: Key::Hash([2; 32]),
+        amount: U256::from(1_000),
+        memo: Some("rent".to_string()),
+    };
+    let bytes = transfer.to_bytes().unwrap();
+    assert_eq!(bytes, manual_transfer_bytes(&transfer));
+    assert_eq!(bytes.len(), transfer.serialized_length());
+    assert_eq!(bytesrepr::deserialize::<Transfer>(bytes), Ok(transfer));
+}
+
+#[test]
+fn unnamed_fields_round_trip() {
+    let tagged = Tagged(7, Bytes::from(vec![1, 2, 3]));
+    let bytes = tagged.to_bytes().unwrap();
+    assert_eq!(bytes, [7, 3, 0, 0, 0, 1, 2, 3]);
+    assert_eq!(bytesrepr::deserialize::<Tagged<Bytes>>(bytes), Ok(tagged));
+}
+
+#[test]
+fn unit_struct_is_empty() {
+    assert_eq!(Marker.to_bytes(), Ok(Vec::new()));
+    assert_eq!(Marker::from_bytes(&[9]), Ok((Marker, &[9][..])));
+}
+
+#[test]
+fn trailing_bytes_are_rejected() {
+    let mut bytes = Tagged(1u8, 2u64).to_bytes().unwrap();
+    bytes.push(0);
+    assert_eq!(
+        bytesrepr::deserialize::<Tagged<u64>>(bytes),
+        Err(bytesrepr::Error::LeftOverBytes)
+    );
+}
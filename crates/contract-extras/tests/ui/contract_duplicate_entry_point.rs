@@ -0,0 +1,14 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod duplicate {
+    use super::*;
+
+    #[casper(export)]
+    pub fn ping() {}
+
+    #[casper(export)]
+    pub fn r#ping() {}
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod too_long {
+    use super::*;
+
+    #[casper(export)]
+    pub fn aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa() {}
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod missing {
+    include!("does_not_exist.rs");
+}
+
+fn main() {}
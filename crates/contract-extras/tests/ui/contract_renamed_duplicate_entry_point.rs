@@ -0,0 +1,14 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod duplicates {
+    use super::*;
+
+    #[casper(export)]
+    pub fn transfer() {}
+
+    #[casper(export, name = "transfer")]
+    pub fn transfer_v2() {}
+}
+
+fn main() {}
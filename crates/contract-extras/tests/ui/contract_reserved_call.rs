@@ -0,0 +1,11 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod reserved {
+    use super::*;
+
+    #[casper(export)]
+    pub fn call() {}
+}
+
+fn main() {}
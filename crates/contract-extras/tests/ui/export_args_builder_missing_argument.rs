@@ -0,0 +1,13 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(export)]
+pub fn pay(recipient: String, amount: u64, memo: Option<String>) {
+    let _ = (recipient, amount, memo);
+}
+
+fn main() {
+    let _ = pay::Args::builder()
+        .recipient("bob".into())
+        .memo("rent".into())
+        .build();
+}
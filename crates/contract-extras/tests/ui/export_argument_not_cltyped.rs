@@ -0,0 +1,12 @@
+use veles_casper_contract_api::prelude::*;
+
+pub struct Config {
+    pub limit: u64,
+}
+
+#[casper(export)]
+pub fn configure(config: Config) {
+    let _ = config.limit;
+}
+
+fn main() {}
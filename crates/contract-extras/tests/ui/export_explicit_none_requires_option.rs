@@ -0,0 +1,13 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(export)]
+pub fn pay(#[casper(explicit_none)] amount: u64) {
+    let _ = amount;
+}
+
+#[casper(export)]
+pub fn refund(#[casper(explicit_nil)] amount: Option<u64>) {
+    let _ = amount;
+}
+
+fn main() {}
@@ -0,0 +1,40 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(export, name = "")]
+pub fn unnamed() {}
+
+#[casper(export, name = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa")]
+pub fn long_named() {}
+
+#[casper(export, name = balance_of)]
+pub fn not_a_string() {}
+
+#[casper(export)]
+pub fn empty_arg(#[casper(arg = "")] amount: u64) {
+    let _ = amount;
+}
+
+#[casper(export)]
+pub fn long_arg(
+    #[casper(arg = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa")]
+    amount: u64,
+) {
+    let _ = amount;
+}
+
+#[casper(export, rename(amount = "value"))]
+pub fn unknown_param(value: u64) {
+    let _ = value;
+}
+
+#[casper(export, rename(amount = "value"))]
+pub fn renamed_twice(#[casper(arg = "sum")] amount: u64) {
+    let _ = amount;
+}
+
+#[casper(export)]
+pub fn duplicate_args(#[casper(arg = "amount")] value: u64, amount: u64) {
+    let _ = (value, amount);
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(export, ret_unit)]
+pub fn total() -> u64 {
+    1
+}
+
+#[casper(export, ret_value)]
+pub fn reset() {}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use veles_casper_contract_api::prelude::*;
+
+pub struct Config {
+    pub limit: u64,
+}
+
+#[casper(export)]
+pub fn config() -> Result<Config, ApiError> {
+    Ok(Config { limit: 1 })
+}
+
+fn main() {}
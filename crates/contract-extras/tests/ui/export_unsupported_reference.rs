@@ -0,0 +1,13 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(export)]
+pub fn touch(counter: &mut u64) {
+    *counter += 1;
+}
+
+#[casper(export)]
+pub fn describe(value: &dyn core::fmt::Debug) {
+    let _ = value;
+}
+
+fn main() {}
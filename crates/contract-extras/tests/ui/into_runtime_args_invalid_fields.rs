@@ -0,0 +1,12 @@
+use veles_casper_contract_api::prelude::*;
+
+#[derive(IntoRuntimeArgs)]
+pub struct Transfer(String, u64);
+
+#[derive(IntoRuntimeArgs)]
+pub struct Refund {
+    #[casper(explicit_none)]
+    amount: u64,
+}
+
+fn main() {}
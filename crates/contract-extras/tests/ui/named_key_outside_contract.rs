@@ -0,0 +1,6 @@
+use veles_casper_contract_api::prelude::*;
+
+#[casper(named_key)]
+pub static OWNER: NamedKey = NamedKey::from_name("owner");
+
+fn main() {}
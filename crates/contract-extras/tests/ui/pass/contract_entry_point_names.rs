@@ -0,0 +1,22 @@
+extern crate alloc;
+
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod names {
+    use super::*;
+
+    #[casper(export)]
+    pub fn call_me() {}
+
+    #[casper(export)]
+    pub fn callback() {}
+
+    #[casper(export)]
+    pub fn aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa() {}
+
+    // Not exported, so it doesn't take part in the checks.
+    pub fn call() {}
+}
+
+fn main() {}
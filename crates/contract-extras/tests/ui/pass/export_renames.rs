@@ -0,0 +1,23 @@
+extern crate alloc;
+
+use veles_casper_contract_api::prelude::*;
+
+#[casper(contract)]
+pub mod renames {
+    use super::*;
+
+    #[casper(export, name = "balance-of", rename(kind = "type"))]
+    pub fn balance_of(#[casper(arg = "owner-key")] owner: String, kind: u8) {
+        let _ = (owner, kind);
+    }
+
+    // Names of up to 128 bytes are accepted, as is `call` as an argument name.
+    #[casper(export, name = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa")]
+    pub fn longest(#[casper(arg = "call")] value: u64) {
+        let _ = value;
+    }
+}
+
+fn main() {
+    let _ = renames::Client::new(Default::default());
+}
@@ -0,0 +1,9 @@
+use veles_casper_contract_api::prelude::*;
+
+#[derive(CasperSerialize)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+fn main() {}
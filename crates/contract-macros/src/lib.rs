@@ -1,30 +1,92 @@
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    Data, DeriveInput, Fields, FnArg, Ident, Item, ItemFn, ItemMod, LitStr, Pat, ReturnType, Type,
-    parse_macro_input, parse_quote,
+    Attribute, Data, DeriveInput, Fields, FnArg, Ident, Item, ItemFn, ItemMod, LitStr, Pat,
+    ReturnType, Type, parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned,
 };
 
 /// Top-level `#[casper(...)]` attribute entry point that dispatches to specific handlers like `contract` or `export`.
 ///
 /// Usage:
 /// - `#[casper(export)] fn entrypoint(arg1: String, arg2: u64) { ... }`
-///   Generates a `#[no_mangle] pub extern "C" fn entrypoint()` wrapper that fetches named args
+///   Generates a `#[no_mangle] pub extern "C-unwind" fn entrypoint()` wrapper that fetches named args
 ///   via `casper_contract::contract_api::runtime::get_named_arg("arg")` and calls `entrypoint_impl`.
+///   Borrowed parameters such as `&str` or `&[u8]` are fetched as their owned counterpart and
+///   passed by reference. `Option<T>` parameters are optional and are `None` when the argument is
+///   not passed. Mark them `#[casper(explicit_none)]` to take a required `Option<T>` argument
+///   instead, so callers pass `None` explicitly.
+///   The generated `Args` struct skips unset optional arguments and can also be built with
+///   `Args::builder()`, whose `build` only compiles once every required argument is set.
+///   Functions returning `()` or `Result<(), E>` don't call `utils::ret`; use
+///   `#[casper(export, ret_unit)]` to return an explicit unit `CLValue`, e.g. for session code.
+///   `#[casper(export, init_once)]` makes the entry point revert with
+///   `UniversalError::AlreadyInitialized` when called again, marking its first call with the
+///   `__init_<name>_done` named key. `#[casper(export, only_installer)]` makes it revert with
+///   `UniversalError::CallerNotInstaller` unless called by the account that installed the
+///   contract, stored by the `insert_installer_key` function of the contract module. Both
+///   guards run before the arguments are read.
+///   Entry points and arguments are named after the function and its parameters.
+///   `#[casper(export, name = "balance-of")]` exports the entry point under another name, and
+///   `#[casper(arg = "type")]` on a parameter, or `rename(param = "type")` in the export
+///   attribute, passes the parameter under another argument name. The generated module and the
+///   fields of `Args` keep the Rust names.
+///   On Wasm every export also exports a `__casper_export_<name>__enable_as_dependency_on_imported_contracts`
+///   static, so two crates exporting the same entry point into one Wasm, typically a contract
+///   depending on another one without its `as_dependency` feature, fail to link with a duplicate
+///   symbol naming the fix rather than only the entry point. A contract built with its own
+///   `as_dependency` feature enabled exports none of its entry points, which
+///   `cargo xtask check-exports` reports.
 /// - `#[casper(contract)] mod name { ... }`
 ///   Appends a `CallBuilder` with methods for each exported function, calling `*_impl` variants.
+///   Exported functions returning `Result<T, E>` also get a `try_<name>` method that calls the
+///   function in-process and returns `E` instead of reverting. It is meant for composing
+///   contracts linked into the same Wasm, e.g. through the `as_dependency` feature.
+///   Methods of unit-returning exports call `macro_support::call_contract_no_ret`, which doesn't
+///   read the callee's result.
+///   `PackageClient::new(package_hash, version)` has the same methods except `try_<name>`, and
+///   calls `version` of a contract package through `casper_call_versioned_contract`, or the
+///   latest enabled version of the package with `None`. Use `Client` to call one exact contract,
+///   and `PackageClient` to keep calling an upgradeable contract across its upgrades.
+///   To keep the contract in a separate file, include it into the module body with
+///   `mod name { include!("name.rs"); }`; its items are expanded as if they were written inline.
+///
+///   Statics of the module marked `#[casper(named_key)]` (a `NamedKey`, `TypedURef` or
+///   collection) are listed by a host-side `named_keys_manifest()`, and `state_spec()` collects
+///   them into a `StateSpec` for `veles_casper_contract_api::state_spec::verify_state`.
+///
+/// With the `host_client` feature, every export also gets a host-side `<name>::transaction`
+/// function building a transaction that calls the stored contract with its `Args`, and
+/// `#[casper(contract)]` a `HostClient` submitting them, see `veles_casper_contract_api::host_client`.
 #[proc_macro_attribute]
 pub fn casper(attr: TokenStream, item: TokenStream) -> TokenStream {
-    // Parse attribute as a simple path like `export` or `contract`
-    let path = parse_macro_input!(attr as syn::Path);
-    let kind = path
-        .get_ident()
-        .cloned()
+    // Parse attribute as a path like `export` or `contract`, followed by options
+    let metas =
+        parse_macro_input!(attr with Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated);
+    let mut metas = metas.into_iter();
+    let kind = metas
+        .next()
+        .and_then(|meta| match meta {
+            syn::Meta::Path(path) => path.get_ident().cloned(),
+            _ => None,
+        })
         .unwrap_or_else(|| Ident::new("", proc_macro2::Span::call_site()));
+    let options: Vec<syn::Meta> = metas.collect();
 
     match kind.to_string().as_str() {
-        "export" => export_impl(item),
+        "export" => match ExportOptions::parse(&options) {
+            Ok(options) => export_impl(item, options),
+            Err(err) => err.to_compile_error().into(),
+        },
         "contract" => contract_impl(item),
+        "named_key" => {
+            let mut output = syn::Error::new(
+                kind.span(),
+                "#[casper(named_key)] only marks statics declared inside a #[casper(contract)] module",
+            )
+            .to_compile_error();
+            output.extend(proc_macro2::TokenStream::from(item));
+            output.into()
+        }
         _ => {
             // Fallback: return item unchanged
             item
@@ -32,17 +94,137 @@ pub fn casper(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
-fn export_impl(item: TokenStream) -> TokenStream {
-    let input_fn = parse_macro_input!(item as ItemFn);
+/// Options following `export` in `#[casper(export, ...)]`.
+#[derive(Default)]
+struct ExportOptions {
+    ret_unit: bool,
+    init_once: bool,
+    only_installer: bool,
+    /// The entry point name of `name = "..."`, if not the function's.
+    name: Option<LitStr>,
+    /// The argument names of `rename(param = "...", ...)`, by parameter.
+    renames: Vec<(Ident, LitStr)>,
+}
+
+impl ExportOptions {
+    fn parse<'a>(options: impl IntoIterator<Item = &'a syn::Meta>) -> syn::Result<Self> {
+        let unknown = |option: &syn::Meta| {
+            syn::Error::new_spanned(
+                option,
+                "unknown #[casper(export)] option, expected `ret_unit`, `init_once`, \
+                 `only_installer`, `name = \"...\"` or `rename(param = \"...\")`",
+            )
+        };
+
+        let mut parsed = Self::default();
+        for option in options {
+            match option {
+                syn::Meta::Path(path) => {
+                    let flag = if path.is_ident("ret_unit") {
+                        &mut parsed.ret_unit
+                    } else if path.is_ident("init_once") {
+                        &mut parsed.init_once
+                    } else if path.is_ident("only_installer") {
+                        &mut parsed.only_installer
+                    } else {
+                        return Err(unknown(option));
+                    };
+                    *flag = true;
+                }
+                syn::Meta::NameValue(name_value) if name_value.path.is_ident("name") => {
+                    let syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(name),
+                        ..
+                    }) = &name_value.value
+                    else {
+                        return Err(syn::Error::new_spanned(
+                            &name_value.value,
+                            "expected the entry point name as a string, e.g. `name = \"balance-of\"`",
+                        ));
+                    };
+                    parsed.name = Some(name.clone());
+                }
+                syn::Meta::List(list) if list.path.is_ident("rename") => {
+                    list.parse_nested_meta(|meta| {
+                        let param = meta
+                            .path
+                            .get_ident()
+                            .cloned()
+                            .ok_or_else(|| meta.error("expected a parameter name"))?;
+                        parsed.renames.push((param, meta.value()?.parse()?));
+                        Ok(())
+                    })?;
+                }
+                _ => return Err(unknown(option)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns the tokens of the entry point name of the function `fn_name`.
+    fn entry_point_name(&self, fn_name: &Ident) -> LitStr {
+        self.name
+            .clone()
+            .unwrap_or_else(|| LitStr::new(&fn_name.to_string(), fn_name.span()))
+    }
+}
+
+fn export_impl(item: TokenStream, options: ExportOptions) -> TokenStream {
+    let mut input_fn = parse_macro_input!(item as ItemFn);
+
+    if let Some(name) = &options.name
+        && let Err(error) = validate_entry_point_name(&name.value())
+    {
+        return syn::Error::new(name.span(), error)
+            .to_compile_error()
+            .into();
+    }
+    let entry_point_name = options.entry_point_name(&input_fn.sig.ident);
+    let ExportOptions {
+        ret_unit,
+        init_once,
+        only_installer,
+        renames,
+        ..
+    } = options;
+
+    // Parameter attributes are consumed here, the compiler doesn't know them
+    let mut explicit_nones: Vec<bool> = Vec::new();
+    let mut arg_attrs: Vec<Option<LitStr>> = Vec::new();
+    for arg in &mut input_fn.sig.inputs {
+        let FnArg::Typed(pat_ty) = arg else {
+            continue;
+        };
+        let ParamOptions {
+            explicit_none: explicit,
+            arg: arg_name,
+        } = match param_options(&pat_ty.attrs) {
+            Ok(options) => options,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        if explicit && option_inner_type(&pat_ty.ty).is_none() {
+            return syn::Error::new_spanned(
+                &pat_ty.ty,
+                "#[casper(explicit_none)] requires an `Option<T>` parameter",
+            )
+            .to_compile_error()
+            .into();
+        }
+        pat_ty.attrs.retain(|attr| !attr.path().is_ident("casper"));
+        explicit_nones.push(explicit);
+        arg_attrs.push(arg_name);
+    }
 
     // Capture original signature and name
     let _vis = &input_fn.vis;
     let sig = &input_fn.sig;
     let fn_name = &sig.ident;
 
-    // Collect (arg_ident, arg_type) for wrapper
+    // Collect (arg_ident, arg_type, owned_arg_type) for wrapper
     let mut arg_idents: Vec<Ident> = Vec::new();
     let mut arg_types: Vec<Type> = Vec::new();
+    let mut borrowed_args: Vec<bool> = Vec::new();
+    let mut arg_spans: Vec<proc_macro2::Span> = Vec::new();
     for arg in &sig.inputs {
         match arg {
             FnArg::Receiver(_) => {
@@ -56,8 +238,16 @@ fn export_impl(item: TokenStream) -> TokenStream {
             FnArg::Typed(pat_ty) => {
                 // Pattern must be an identifier
                 if let Pat::Ident(pat_ident) = &*pat_ty.pat {
+                    // Borrowed parameters are fetched as their owned counterpart and passed by reference
+                    let (owned_ty, borrowed) = match owned_arg_type(&pat_ty.ty) {
+                        Ok(Some(owned_ty)) => (owned_ty, true),
+                        Ok(None) => ((*pat_ty.ty).clone(), false),
+                        Err(err) => return err.to_compile_error().into(),
+                    };
                     arg_idents.push(pat_ident.ident.clone());
-                    arg_types.push((*pat_ty.ty).clone());
+                    arg_types.push(owned_ty);
+                    borrowed_args.push(borrowed);
+                    arg_spans.push(pat_ty.ty.span());
                 } else {
                     return syn::Error::new_spanned(&pat_ty.pat, "unsupported pattern in argument")
                         .to_compile_error()
@@ -67,35 +257,109 @@ fn export_impl(item: TokenStream) -> TokenStream {
         }
     }
 
-    // Determine return type
+    let arg_names = match arg_names(&arg_idents, arg_attrs, &renames) {
+        Ok(arg_names) => arg_names,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
     // Determine return type and whether it's a Result<T, E>
     let (has_return, is_result) = match &sig.output {
         ReturnType::Default => (false, false),
+        ReturnType::Type(_, ty) => (true, is_result_type(ty)),
+    };
+    let unit_return = returns_unit(&sig.output);
+    if ret_unit && !unit_return {
+        return syn::Error::new_spanned(
+            &sig.output,
+            "#[casper(export, ret_unit)] requires a function returning `()` or `Result<(), E>`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // Argument and return types are checked on their own, so a type that can't cross the host
+    // boundary is reported at the signature rather than inside the generated entry point
+    let arg_assertions = arg_types.iter().zip(&arg_spans).map(|(ty, span)| {
+        quote_spanned! {*span=>
+            const _: fn() = || {
+                fn assert_argument_type<T: veles_casper_contract_api::casper_types::CLTyped + veles_casper_contract_api::casper_types::bytesrepr::FromBytes>() {}
+                assert_argument_type::<#ty>();
+            };
+        }
+    });
+    let ret_assertion = match &sig.output {
+        ReturnType::Default => quote! {},
         ReturnType::Type(_, ty) => {
-            // ty: Box<Type>
-            let is_result = if let Type::Path(type_path) = &**ty {
-                type_path
-                    .path
-                    .segments
-                    .last()
-                    .map(|s| s.ident == "Result")
-                    .unwrap_or(false)
+            let ret_ty = if is_result {
+                result_ok_type(ty).unwrap_or(ty)
             } else {
-                false
+                ty
             };
-            (true, is_result)
+            quote_spanned! {ret_ty.span()=>
+                const _: fn() = || {
+                    fn assert_return_type<T: veles_casper_contract_api::casper_types::CLTyped + veles_casper_contract_api::casper_types::bytesrepr::ToBytes>() {}
+                    assert_return_type::<#ret_ty>();
+                };
+            }
         }
     };
 
+    // Optional arguments are omitted when `None`, unless marked `#[casper(explicit_none)]`
+    let omittable_args: Vec<bool> = arg_types
+        .iter()
+        .zip(&explicit_nones)
+        .map(|(ty, explicit)| option_inner_type(ty).is_some() && !explicit)
+        .collect();
+
     // Generate code to read args using veles_casper_contract_api::casper_contract::contract_api::runtime::get_named_arg
-    let get_args = arg_idents.iter().zip(arg_types.iter()).map(|(ident, ty)| {
-        let name_str = ident.to_string();
-        quote! {
-            let #ident: #ty = veles_casper_contract_api::casper_contract::contract_api::runtime::get_named_arg(#name_str);
+    let get_args = arg_idents.iter().zip(arg_types.iter()).zip(&omittable_args).zip(&arg_names).map(|(((ident, ty), omittable), name_str)| {
+        if *omittable {
+            // Optional arguments may be omitted by the caller
+            quote! {
+                let #ident: #ty = veles_casper_contract_api::error::ResultExt::revert_on_err(
+                    veles_casper_contract_api::utils::try_get_named_arg(#name_str),
+                );
+            }
+        } else {
+            quote! {
+                let #ident: #ty = veles_casper_contract_api::casper_contract::contract_api::runtime::get_named_arg(#name_str);
+            }
         }
     });
 
-    let call_args = arg_idents.iter();
+    let insert_args =
+        arg_idents
+            .iter()
+            .zip(&omittable_args)
+            .zip(&arg_names)
+            .map(|((ident, omittable), name)| {
+                if *omittable {
+                    quote! {
+                        if let Some(value) = self.#ident {
+                            runtime_args
+                                .insert(#name, value)
+                                .map_err(veles_casper_contract_api::casper_types::ApiError::from)?;
+                        }
+                    }
+                } else {
+                    quote! {
+                        runtime_args
+                            .insert(#name, self.#ident)
+                            .map_err(veles_casper_contract_api::casper_types::ApiError::from)?;
+                    }
+                }
+            });
+
+    let call_args = arg_idents
+        .iter()
+        .zip(borrowed_args.iter())
+        .map(|(ident, borrowed)| {
+            if *borrowed {
+                quote! { &#ident }
+            } else {
+                quote! { #ident }
+            }
+        });
 
     let handle_ret = if is_result {
         quote! {
@@ -104,7 +368,7 @@ fn export_impl(item: TokenStream) -> TokenStream {
 
                 match ret {
                     Ok(value) => value,
-                    Err(err) => veles_casper_contract_api::casper_contract::contract_api::runtime::revert(err),
+                    Err(err) => veles_casper_contract_api::utils::revert(err),
                 }
             }
         }
@@ -114,19 +378,76 @@ fn export_impl(item: TokenStream) -> TokenStream {
         }
     };
 
-    let call_stmt = if has_return {
+    // Unit returns don't `ret` unless `ret_unit` asks for it, e.g. for session code whose caller
+    // inspects the result
+    let call_stmt = if ret_unit {
+        quote! {
+            let () = #handle_ret;
+            veles_casper_contract_api::utils::ret(veles_casper_contract_api::casper_types::CLValue::unit());
+        }
+    } else if has_return && !unit_return {
         quote! {
             let ret = #handle_ret;
-           veles_casper_contract_api::casper_contract::contract_api::runtime::ret(veles_casper_contract_api::casper_types::CLValue::from_t(ret).unwrap());
+           veles_casper_contract_api::utils::ret(veles_casper_contract_api::casper_types::CLValue::from_t(ret).unwrap());
         }
     } else {
         quote! { let () = #handle_ret; }
     };
 
+    // Guards run before the arguments are read, so a rejected call fails on them first
+    let mut guards = proc_macro2::TokenStream::new();
+    if only_installer {
+        guards.extend(quote! {
+            veles_casper_contract_api::macro_support::ensure_installer();
+        });
+    }
+    let init_done_key_name = if init_once {
+        guards.extend(quote! {
+            veles_casper_contract_api::macro_support::init_once(INIT_DONE_KEY_NAME);
+        });
+        quote! {
+            /// The named key marking the entry point as called, see `macro_support::init_once`.
+            pub const INIT_DONE_KEY_NAME: &'static str = concat!("__init_", stringify!(#fn_name), "_done");
+        }
+    } else {
+        quote! {}
+    };
+
     let mod_name = format_ident!("{}", fn_name);
 
+    let args_builder = args_builder(&arg_idents, &arg_types);
+
     let get_args_again = get_args.clone();
 
+    let host_transaction = if cfg!(feature = "host_client") {
+        quote! {
+            /// Builds a transaction calling the entry point of the stored contract from the host.
+            #[cfg(not(target_arch = "wasm32"))]
+            pub fn transaction(
+                contract_hash: veles_casper_contract_api::casper_types::contracts::ContractHash,
+                args: Args,
+                options: &veles_casper_contract_api::host_client::SessionOptions,
+                chain_name: &str,
+                signer: &veles_casper_contract_api::casper_types::SecretKey,
+            ) -> veles_casper_contract_api::casper_types::Transaction {
+                veles_casper_contract_api::host_client::stored_contract_transaction(
+                    contract_hash,
+                    NAME,
+                    veles_casper_contract_api::macro_support::IntoRuntimeArgs::into_runtime_args(args),
+                    options,
+                    chain_name,
+                    signer,
+                )
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let export_marker_name = LitStr::new(
+        &export_marker_name(&entry_point_name.value()),
+        entry_point_name.span(),
+    );
     let expanded = quote! {
        // Generated extern shim
 
@@ -138,19 +459,34 @@ fn export_impl(item: TokenStream) -> TokenStream {
         pub mod #mod_name {
             use super::*;
 
-            pub const NAME: &'static str = stringify!(#fn_name);
+            pub const NAME: &'static str = #entry_point_name;
+            #init_done_key_name
+
+            #(#arg_assertions)*
+            #ret_assertion
 
+            // The marker has the same name in every crate exporting the entry point, so linking
+            // two of them into one Wasm fails with a duplicate symbol naming the fix.
             #[cfg(not(feature = "as_dependency"))]
-            #[unsafe(export_name = stringify!(#fn_name))]
-            extern "C" fn entry_point() {
-                veles_casper_contract_api::macro_support::set_panic_hook();
+            const _: () = {
+                #[unsafe(export_name = #entry_point_name)]
+                extern "C-unwind" fn entry_point() {
+                    veles_casper_contract_api::macro_support::set_panic_hook();
+
+                    #guards
+                    #(#get_args)*
+                    { #call_stmt }
+                }
 
-                #(#get_args)*
-                { #call_stmt }
-            }
+                #[cfg(target_arch = "wasm32")]
+                #[used]
+                #[unsafe(export_name = #export_marker_name)]
+                static EXPORT_MARKER: u8 = 0;
+            };
 
             #[cfg(feature = "as_dependency")]
             pub fn entry_point() {
+                #guards
                 #(#get_args_again)*
                 { #call_stmt }
             }
@@ -162,23 +498,73 @@ fn export_impl(item: TokenStream) -> TokenStream {
                 )*
             }
 
+            #args_builder
+
             impl veles_casper_contract_api::macro_support::IntoRuntimeArgs for Args {
                 fn into_runtime_args(self) -> veles_casper_contract_api::casper_types::RuntimeArgs {
+                    veles_casper_contract_api::macro_support::IntoRuntimeArgs::try_into_runtime_args(self).unwrap()
+                }
+
+                fn try_into_runtime_args(self) -> core::result::Result<veles_casper_contract_api::casper_types::RuntimeArgs, veles_casper_contract_api::casper_types::ApiError> {
                     let mut runtime_args = veles_casper_contract_api::casper_types::RuntimeArgs::new();
-                    #(
-                        runtime_args.insert(stringify!(#arg_idents), self.#arg_idents).unwrap();
-                    )*
-                    runtime_args
+                    #(#insert_args)*
+                    Ok(runtime_args)
                 }
             }
 
+            fn call_args(args: Args) -> veles_casper_contract_api::casper_types::RuntimeArgs {
+                // Callers linking the contract as a dependency revert with the conversion error,
+                // the contract itself keeps the panic of `into_runtime_args`.
+                #[cfg(feature = "as_dependency")]
+                let runtime_args = veles_casper_contract_api::casper_contract::unwrap_or_revert::UnwrapOrRevert::unwrap_or_revert(
+                    veles_casper_contract_api::macro_support::try_into_runtime_args(args),
+                );
+                #[cfg(not(feature = "as_dependency"))]
+                let runtime_args = veles_casper_contract_api::macro_support::IntoRuntimeArgs::into_runtime_args(args);
+                runtime_args
+            }
+
             pub fn call_contract<T:  veles_casper_contract_api::casper_types::CLTyped + veles_casper_contract_api::casper_types::bytesrepr::FromBytes>(contract_hash: veles_casper_contract_api::casper_types::contracts::ContractHash, args: Args) -> T {
-                veles_casper_contract_api::casper_contract::contract_api::runtime::call_contract::<T>(
+                veles_casper_contract_api::macro_support::call_contract::<T>(
                     contract_hash,
                     NAME,
-                    veles_casper_contract_api::macro_support::IntoRuntimeArgs::into_runtime_args(args),
+                    call_args(args),
                 )
             }
+
+            /// Calls the entry point without reading its result, see
+            /// `macro_support::call_contract_no_ret`.
+            pub fn call_contract_no_ret(contract_hash: veles_casper_contract_api::casper_types::contracts::ContractHash, args: Args) {
+                veles_casper_contract_api::macro_support::call_contract_no_ret(
+                    contract_hash,
+                    NAME,
+                    call_args(args),
+                )
+            }
+
+            /// Calls the entry point of `contract_version` of the package, or of its latest
+            /// enabled version with `None`.
+            pub fn call_versioned_contract<T:  veles_casper_contract_api::casper_types::CLTyped + veles_casper_contract_api::casper_types::bytesrepr::FromBytes>(contract_package_hash: veles_casper_contract_api::casper_types::contracts::ContractPackageHash, contract_version: Option<veles_casper_contract_api::casper_types::contracts::ContractVersion>, args: Args) -> T {
+                veles_casper_contract_api::macro_support::call_versioned_contract::<T>(
+                    contract_package_hash,
+                    contract_version,
+                    NAME,
+                    call_args(args),
+                )
+            }
+
+            /// Calls the entry point of a package version without reading its result, see
+            /// `macro_support::call_versioned_contract_no_ret`.
+            pub fn call_versioned_contract_no_ret(contract_package_hash: veles_casper_contract_api::casper_types::contracts::ContractPackageHash, contract_version: Option<veles_casper_contract_api::casper_types::contracts::ContractVersion>, args: Args) {
+                veles_casper_contract_api::macro_support::call_versioned_contract_no_ret(
+                    contract_package_hash,
+                    contract_version,
+                    NAME,
+                    call_args(args),
+                )
+            }
+
+            #host_transaction
         }
 
 
@@ -187,161 +573,278 @@ fn export_impl(item: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Returns the symbol of the static exported next to the entry point `entry_point_name` on Wasm.
+fn export_marker_name(entry_point_name: &str) -> String {
+    format!("__casper_export_{entry_point_name}__enable_as_dependency_on_imported_contracts")
+}
+
 fn contract_impl(item: TokenStream) -> TokenStream {
     let input_mod = parse_macro_input!(item as ItemMod);
 
     let vis = &input_mod.vis;
     let mod_ident = &input_mod.ident;
-    let (brace, content) = match &input_mod.content {
-        Some((_, items)) => (true, items.clone()),
-        None => (false, Vec::new()),
+    let (inner_attrs, mut content, included_files) = match &input_mod.content {
+        Some((_, items)) => match expand_includes(items.clone()) {
+            Ok(expanded) => expanded,
+            Err(err) => return err.to_compile_error().into(),
+        },
+        None => {
+            // Out-of-line modules can't reach the macro on stable Rust, but report them nicely anyway
+            return syn::Error::new_spanned(
+                &input_mod,
+                format!(
+                    "#[casper(contract)] requires an inline module; to keep the contract in a separate file, \
+                     write `mod {mod_ident} {{ include!(\"{mod_ident}.rs\"); }}` instead"
+                ),
+            )
+            .to_compile_error()
+            .into();
+        }
     };
 
     // Collect exported functions to generate CallBuilder methods and an entry_points() function
     let mut client_methods = Vec::new();
+    let mut package_client_methods = Vec::new();
+    let mut host_client_methods = Vec::new();
     let mut entry_builders = Vec::new();
     let mut macro_symbols = Vec::new();
+    let mut macro_symbol_names = Vec::new();
     // let mut export_symbols = Vec::new();
 
-    if brace {
-        for it in &content {
-            if let Item::Fn(func) = it {
-                let mut is_export = false;
-                for attr in &func.attrs {
-                    if let syn::Meta::List(list) = &attr.meta
-                        && let Some(last) = list.path.segments.last()
-                    {
-                        if last.ident == "casper"
-                            && let Ok(p) = syn::parse2::<syn::Path>(list.tokens.clone())
-                        {
-                            if p.is_ident("export") {
-                                is_export = true;
-                                break;
-                            }
-                        } else if last.ident == "unsafe" {
-                            let s = list.tokens.to_string();
-                            if s.contains("casper") && s.contains("export") {
-                                is_export = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-                if is_export {
-                    // Build method sig mirroring function
-                    let name = func.sig.ident.clone();
+    if let Err(err) = validate_entry_point_names(&content) {
+        return err.to_compile_error().into();
+    }
 
-                    macro_symbols.push(quote! {
-                        #name
-                    });
+    // Statics listed by named_keys_manifest(), their marker attributes are consumed here
+    let named_key_statics: Vec<Ident> = content
+        .iter_mut()
+        .filter_map(|it| match it {
+            Item::Static(item_static) => {
+                take_named_key_attr(&mut item_static.attrs).then(|| item_static.ident.clone())
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Named keys of the export guards, listed by named_keys_manifest() too
+    let mut guard_key_specs = Vec::new();
+    let mut only_installer_exports = false;
+
+    for it in &content {
+        if let Item::Fn(func) = it
+            && is_export(func)
+        {
+            // Build method sig mirroring function
+            let name = func.sig.ident.clone();
+
+            let options = export_options(func);
+            if options.init_once {
+                guard_key_specs.push(quote! {
+                    veles_casper_contract_api::state_spec::NamedKeySpec {
+                        name: alloc::string::String::from(#name::INIT_DONE_KEY_NAME),
+                        kind: veles_casper_contract_api::state_spec::NamedKeyKind::Value(
+                            veles_casper_contract_api::casper_types::CLType::Bool,
+                        ),
+                    }
+                });
+            }
+            only_installer_exports |= options.only_installer;
 
-                    let mut arg_pats: Vec<Ident> = Vec::new();
-                    let mut arg_types: Vec<Type> = Vec::new();
-                    for arg in &func.sig.inputs {
-                        match arg {
-                            FnArg::Receiver(_) => {
-                                // skip methods with self
-                            }
-                            FnArg::Typed(pat_ty) => {
-                                if let Pat::Ident(pat_ident) = &*pat_ty.pat {
-                                    arg_pats.push(pat_ident.ident.clone());
+            macro_symbols.push(quote! {
+                #name
+            });
+            macro_symbol_names.push(options.entry_point_name(&name));
+
+            let mut arg_pats: Vec<Ident> = Vec::new();
+            let mut arg_types: Vec<Type> = Vec::new();
+            let mut explicit_nones: Vec<bool> = Vec::new();
+            let mut arg_attrs: Vec<Option<LitStr>> = Vec::new();
+            let mut client_arg_types: Vec<Type> = Vec::new();
+            let mut arg_values = Vec::new();
+            for arg in &func.sig.inputs {
+                match arg {
+                    FnArg::Receiver(_) => {
+                        // skip methods with self
+                    }
+                    FnArg::Typed(pat_ty) => {
+                        if let Pat::Ident(pat_ident) = &*pat_ty.pat {
+                            let ident = pat_ident.ident.clone();
+                            // Unsupported references are reported by `#[casper(export)]`
+                            match owned_arg_type(&pat_ty.ty) {
+                                Ok(Some(owned_ty)) => {
+                                    let client_ty = elided_reference(&pat_ty.ty);
+                                    arg_values.push(quote! { #ident: core::convert::Into::into(alloc::borrow::ToOwned::to_owned(#ident)) });
+                                    arg_types.push(owned_ty);
+                                    client_arg_types.push(client_ty);
+                                }
+                                _ => {
+                                    arg_values.push(quote! { #ident });
                                     arg_types.push((*pat_ty.ty).clone());
+                                    client_arg_types.push((*pat_ty.ty).clone());
                                 }
                             }
+                            arg_pats.push(ident);
+                            // Invalid parameter attributes are reported by `#[casper(export)]`
+                            let param_options = param_options(&pat_ty.attrs).unwrap_or_default();
+                            explicit_nones.push(param_options.explicit_none);
+                            arg_attrs.push(param_options.arg);
                         }
                     }
+                }
+            }
+            // Invalid argument names are reported by `#[casper(export)]` too
+            let arg_names =
+                arg_names(&arg_pats, arg_attrs, &options.renames).unwrap_or_else(|_| {
+                    arg_pats
+                        .iter()
+                        .map(|ident| LitStr::new(&ident.to_string(), ident.span()))
+                        .collect()
+                });
+
+            let ret_ty_tokens = match &func.sig.output {
+                ReturnType::Default => quote! { () },
+                ReturnType::Type(_, ty) => {
+                    // If the return type is Result<Ok, Err>, use Ok; otherwise use the whole type.
+                    let ok_type = if let Type::Path(type_path) = &**ty {
+                        type_path.path.segments.last().and_then(|seg| {
+                            if seg.ident == "Result"
+                                && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+                                && let Some(syn::GenericArgument::Type(ok_ty)) = args.args.first()
+                            {
+                                return Some(quote! { #ok_ty });
+                            }
 
-                    let ret_ty_tokens = match &func.sig.output {
-                        ReturnType::Default => quote! { () },
-                        ReturnType::Type(_, ty) => {
-                            // If the return type is Result<Ok, Err>, use Ok; otherwise use the whole type.
-                            let ok_type = if let Type::Path(type_path) = &**ty {
-                                type_path.path.segments.last().and_then(|seg| {
-                                    if seg.ident == "Result"
-                                        && let syn::PathArguments::AngleBracketed(args) =
-                                            &seg.arguments
-                                        && let Some(syn::GenericArgument::Type(ok_ty)) =
-                                            args.args.first()
-                                    {
-                                        return Some(quote! { #ok_ty });
-                                    }
+                            None
+                        })
+                    } else {
+                        None
+                    };
+                    ok_type.unwrap_or_else(|| quote! { #ty })
+                }
+            };
 
-                                    None
-                                })
-                            } else {
-                                None
-                            };
-                            ok_type.unwrap_or_else(|| quote! { #ty })
-                        }
+            let sym_name = format_ident!("{}", name);
+            if let ReturnType::Type(_, ty) = &func.sig.output
+                && is_result_type(ty)
+            {
+                let try_name = format_ident!("try_{}", name);
+                let try_doc = format!(
+                    "Calls `{name}` in-process and returns its error instead of reverting.\n\n\
+                         Only usable when the callee is linked into the same Wasm, i.e. from the same \
+                         crate or through the `as_dependency` feature. The stored contract is not called."
+                );
+                client_methods.push(quote! {
+                    #[doc = #try_doc]
+                    pub fn #try_name(&self, #(#arg_pats: #client_arg_types),*) -> #ty {
+                        self::#name(#(#arg_pats),*)
+                    }
+                });
+            }
+            // Unit calls don't read the host buffer, whatever the callee returned
+            let (call, versioned_call) = if returns_unit(&func.sig.output) {
+                (
+                    quote! { #mod_ident::#sym_name::call_contract_no_ret(self.0, args) },
+                    quote! { #mod_ident::#sym_name::call_versioned_contract_no_ret(self.0, self.1, args) },
+                )
+            } else {
+                (
+                    quote! { #mod_ident::#sym_name::call_contract::<#ret_ty_tokens>(self.0, args) },
+                    quote! { #mod_ident::#sym_name::call_versioned_contract::<#ret_ty_tokens>(self.0, self.1, args) },
+                )
+            };
+            client_methods.push(quote! {
+                pub fn #name(&self, #(#arg_pats: #client_arg_types),*) -> #ret_ty_tokens {
+                    let args = #mod_ident::#sym_name::Args {
+                        #(
+                            #arg_values,
+                        )*
                     };
 
-                    let sym_name = format_ident!("{}", name);
-                    client_methods.push(quote! {
-                        pub fn #name(&self, #(#arg_pats: #arg_types),*) -> #ret_ty_tokens {
-                            let args = #mod_ident::#sym_name::Args {
-                                #(
-                                    #arg_pats,
-                                )*
-                            };
+                    #call
+                }
+            });
+            package_client_methods.push(quote! {
+                pub fn #name(&self, #(#arg_pats: #client_arg_types),*) -> #ret_ty_tokens {
+                    let args = #mod_ident::#sym_name::Args {
+                        #(
+                            #arg_values,
+                        )*
+                    };
 
-                            #mod_ident::#sym_name::call_contract::<#ret_ty_tokens>(
-                                self.0,
-                                args,
-                            )
-                        }
-                    });
-
-                    // Build tokens to populate EntryPoints in generated function using CLTyped
-                    let name_lit =
-                        syn::LitStr::new(&name.to_string(), proc_macro2::Span::call_site());
-                    let params_list = arg_pats.iter().zip(arg_types.iter()).map(|(id, ty)| {
-                        let id_lit = syn::LitStr::new(&id.to_string(), proc_macro2::Span::call_site());
-                        quote! { veles_casper_contract_api::casper_types::Parameter::new(#id_lit, <#ty as veles_casper_contract_api::casper_types::CLTyped>::cl_type()) }
-                    });
-                    let ret_cl = match &func.sig.output {
-                        ReturnType::Default => {
-                            quote! { veles_casper_contract_api::casper_types::CLType::Unit }
-                        }
-                        ReturnType::Type(_, ty) => {
-                            // Try to extract the Ok type from Result<Ok, Err>, otherwise fall back to the whole type.
-                            let ok_type_cl = if let Type::Path(type_path) = &**ty {
-                                type_path
-                                    .path
-                                    .segments
-                                    .last()
-                                    .and_then(|seg| {
-                                        if seg.ident == "Result"
-                                           && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
-                                                && let Some(syn::GenericArgument::Type(ok_ty)) = args.args.first() {
-                                                    return Some(quote! { <#ok_ty as veles_casper_contract_api::casper_types::CLTyped>::cl_type() });
-                                                }
-
-
-                                        None
-                                    })
-                            } else {
-                                None
-                            };
+                    #versioned_call
+                }
+            });
+            host_client_methods.push(quote! {
+                pub async fn #name(&self, #(#arg_pats: #client_arg_types),*) -> core::result::Result<veles_casper_contract_api::casper_types::execution::ExecutionResult, S::Error> {
+                    let args = #mod_ident::#sym_name::Args {
+                        #(
+                            #arg_values,
+                        )*
+                    };
+                    let transaction = #mod_ident::#sym_name::transaction(
+                        self.contract_hash,
+                        args,
+                        &self.options,
+                        self.chain_name,
+                        self.signer,
+                    );
+                    veles_casper_contract_api::host_client::SubmitTransaction::submit_and_wait(self.submitter, transaction).await
+                }
+            });
 
-                            ok_type_cl.unwrap_or_else(|| quote! { <#ty as veles_casper_contract_api::casper_types::CLTyped>::cl_type() })
-                        }
+            // Build tokens to populate EntryPoints in generated function using CLTyped
+            let name_lit = options.entry_point_name(&name);
+            let params_list = arg_names.iter().zip(arg_types.iter()).zip(&explicit_nones).map(|((id_lit, ty), explicit)| {
+                    // Optional arguments are passed as the inner value when present
+                    let ty = if *explicit { ty } else { option_inner_type(ty).unwrap_or(ty) };
+                    quote! { veles_casper_contract_api::casper_types::Parameter::new(#id_lit, <#ty as veles_casper_contract_api::casper_types::CLTyped>::cl_type()) }
+                });
+            let ret_cl = match &func.sig.output {
+                ReturnType::Default => {
+                    quote! { veles_casper_contract_api::casper_types::CLType::Unit }
+                }
+                ReturnType::Type(_, ty) => {
+                    // Try to extract the Ok type from Result<Ok, Err>, otherwise fall back to the whole type.
+                    let ok_type_cl = if let Type::Path(type_path) = &**ty {
+                        type_path
+                                .path
+                                .segments
+                                .last()
+                                .and_then(|seg| {
+                                    if seg.ident == "Result"
+                                       && let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+                                            && let Some(syn::GenericArgument::Type(ok_ty)) = args.args.first() {
+                                                return Some(quote! { <#ok_ty as veles_casper_contract_api::casper_types::CLTyped>::cl_type() });
+                                            }
+
+
+                                    None
+                                })
+                    } else {
+                        None
                     };
-                    entry_builders.push(quote! {
-                        veles_casper_contract_api::casper_types::EntityEntryPoint::new(
-                            #name_lit,
-                            alloc::vec![ #(#params_list),* ],
-                            #ret_cl,
-                            veles_casper_contract_api::casper_types::EntryPointAccess::Public,
-                            veles_casper_contract_api::casper_types::EntryPointType::Called,
-                            veles_casper_contract_api::casper_types::EntryPointPayment::Caller,
-                        )
-                    });
+
+                    ok_type_cl.unwrap_or_else(|| quote! { <#ty as veles_casper_contract_api::casper_types::CLTyped>::cl_type() })
                 }
-            }
+            };
+            entry_builders.push(quote! {
+                veles_casper_contract_api::casper_types::EntityEntryPoint::new(
+                    #name_lit,
+                    alloc::vec![ #(#params_list),* ],
+                    #ret_cl,
+                    veles_casper_contract_api::casper_types::EntryPointAccess::Public,
+                    veles_casper_contract_api::casper_types::EntryPointType::Called,
+                    veles_casper_contract_api::casper_types::EntryPointPayment::Caller,
+                )
+            });
         }
     }
 
     let builder_struct = quote! {
+        /// Calls the entry points of the contract with the given contract hash.
+        ///
+        /// The hash names one version of the contract package, so the calls keep going to that
+        /// version after the package is upgraded; see `PackageClient` to follow upgrades.
         pub struct Client(veles_casper_contract_api::casper_types::contracts::ContractHash);
 
         impl Client {
@@ -353,6 +856,86 @@ fn contract_impl(item: TokenStream) -> TokenStream {
         impl Client {
             #(#client_methods)*
         }
+
+        /// Calls the entry points of a version of the contract package with the given hash, or of
+        /// its latest enabled version when the version is `None`.
+        pub struct PackageClient(
+            veles_casper_contract_api::casper_types::contracts::ContractPackageHash,
+            Option<veles_casper_contract_api::casper_types::contracts::ContractVersion>,
+        );
+
+        impl PackageClient {
+            pub fn new(
+                contract_package_hash: veles_casper_contract_api::casper_types::contracts::ContractPackageHash,
+                contract_version: Option<veles_casper_contract_api::casper_types::contracts::ContractVersion>,
+            ) -> Self {
+                Self(contract_package_hash, contract_version)
+            }
+        }
+
+        impl PackageClient {
+            #(#package_client_methods)*
+        }
+    };
+
+    let host_client_struct = if cfg!(feature = "host_client") {
+        quote! {
+            /// Calls the entry points of the stored contract from the host, submitting the
+            /// transactions and waiting for their execution.
+            #[cfg(not(target_arch = "wasm32"))]
+            pub struct HostClient<'a, S> {
+                submitter: &'a S,
+                contract_hash: veles_casper_contract_api::casper_types::contracts::ContractHash,
+                chain_name: &'a str,
+                signer: &'a veles_casper_contract_api::casper_types::SecretKey,
+                options: veles_casper_contract_api::host_client::SessionOptions,
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            impl<S> Clone for HostClient<'_, S> {
+                fn clone(&self) -> Self {
+                    *self
+                }
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            impl<S> Copy for HostClient<'_, S> {}
+
+            #[cfg(not(target_arch = "wasm32"))]
+            impl<'a, S: veles_casper_contract_api::host_client::SubmitTransaction> HostClient<'a, S> {
+                /// Creates a client signing its transactions with `signer`, using the default
+                /// session options.
+                pub fn new(
+                    submitter: &'a S,
+                    contract_hash: veles_casper_contract_api::casper_types::contracts::ContractHash,
+                    chain_name: &'a str,
+                    signer: &'a veles_casper_contract_api::casper_types::SecretKey,
+                ) -> Self {
+                    Self {
+                        submitter,
+                        contract_hash,
+                        chain_name,
+                        signer,
+                        options: core::default::Default::default(),
+                    }
+                }
+
+                /// Returns a copy of the client using `options` for its transactions.
+                pub fn with_session_options(&self, options: veles_casper_contract_api::host_client::SessionOptions) -> Self {
+                    Self { options, ..*self }
+                }
+
+                /// Returns a copy of the client paying `payment_amount` motes per call, e.g. for a
+                /// single expensive call.
+                pub fn with_payment_amount(&self, payment_amount: u64) -> Self {
+                    self.with_session_options(self.options.with_payment_amount(payment_amount))
+                }
+
+                #(#host_client_methods)*
+            }
+        }
+    } else {
+        quote! {}
     };
 
     // Generate entry_points() function that constructs EntryPoints using CLTyped
@@ -368,16 +951,61 @@ fn contract_impl(item: TokenStream) -> TokenStream {
         }
     };
 
+    let installer_key_fn = if only_installer_exports {
+        guard_key_specs.push(quote! {
+            veles_casper_contract_api::state_spec::NamedKeySpec {
+                name: alloc::string::String::from(veles_casper_contract_api::macro_support::INSTALLER_KEY_NAME),
+                kind: veles_casper_contract_api::state_spec::NamedKeyKind::Any,
+            }
+        });
+        quote! {
+            /// Inserts the installing account into the named keys of the contract, to be called
+            /// by the installer before creating it. The `only_installer` entry points only accept
+            /// calls from this account.
+            pub fn insert_installer_key(named_keys: &mut veles_casper_contract_api::casper_types::contracts::NamedKeys) {
+                veles_casper_contract_api::macro_support::insert_installer_key(named_keys);
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let manifest_fn = quote! {
+        /// Returns the named keys of the statics marked `#[casper(named_key)]` in this module,
+        /// followed by those of the `init_once` and `only_installer` guards.
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn named_keys_manifest() -> alloc::vec::Vec<veles_casper_contract_api::state_spec::NamedKeySpec> {
+            let mut manifest = alloc::vec::Vec::new();
+            #(manifest.extend(veles_casper_contract_api::state_spec::NamedKeyManifest::named_key_specs(&#named_key_statics));)*
+            #(manifest.push(#guard_key_specs);)*
+            manifest
+        }
+
+        /// Returns a spec expecting the named keys of [`named_keys_manifest`].
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn state_spec() -> veles_casper_contract_api::state_spec::StateSpec {
+            named_keys_manifest().into_iter().collect()
+        }
+    };
+
     let enumerate_symbols_macro_name = format_ident!("enumerate_{}_symbols", mod_ident);
     let export_symbols_macro_name = format_ident!("export_{}_symbols", mod_ident);
 
-    let output = if brace {
+    let output = {
         let items = content;
         quote! {
             #vis mod #mod_ident {
+                #(#inner_attrs)*
                 #(#items)*
+                #(
+                    // Rebuild the contract when an included file changes
+                    const _: &[u8] = core::include_bytes!(#included_files);
+                )*
                 #builder_struct
+                #host_client_struct
                 #entrypoints_fn
+                #installer_key_fn
+                #manifest_fn
 
                 pub struct Contract(());
 
@@ -391,15 +1019,31 @@ fn contract_impl(item: TokenStream) -> TokenStream {
                     };
                 }
 
+                /// Exports the entry points of a contract linked with its `as_dependency` feature.
+                ///
+                /// The optional expression is evaluated before each entry point, e.g. to configure
+                /// the contract.
                 #[macro_export]
                 macro_rules! #export_symbols_macro_name {
                     () => {
                         #(
                             #[cfg(not(feature = "as_dependency"))]
                             const _: () = {
-                                #[unsafe(export_name = stringify!(#macro_symbols))]
-                                extern "C" fn func() {
-                                    casper_contract_extras::#mod_ident::#mod_ident::#macro_symbols::entry_point();
+                                #[unsafe(export_name = #macro_symbol_names)]
+                                extern "C-unwind" fn func() {
+                                    $crate::#mod_ident::#mod_ident::#macro_symbols::entry_point();
+                                }
+                            };
+                        )*
+                    };
+                    ($before:expr) => {
+                        #(
+                            #[cfg(not(feature = "as_dependency"))]
+                            const _: () = {
+                                #[unsafe(export_name = #macro_symbol_names)]
+                                extern "C-unwind" fn func() {
+                                    $before;
+                                    $crate::#mod_ident::#mod_ident::#macro_symbols::entry_point();
                                 }
                             };
                         )*
@@ -407,14 +1051,516 @@ fn contract_impl(item: TokenStream) -> TokenStream {
                 }
             }
         }
-    } else {
-        // For "mod name;" style, we can't append items here. Return unchanged.
-        quote! { #vis mod #mod_ident; }
     };
 
     TokenStream::from(output)
 }
 
+/// Returns the options of the `#[casper(export, ...)]` attribute of the function.
+///
+/// Invalid options are reported by `#[casper(export)]` itself.
+fn export_options(func: &ItemFn) -> ExportOptions {
+    func.attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("casper"))
+        .find_map(|attr| {
+            let metas = attr
+                .parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)
+                .ok()?;
+            let mut metas = metas.iter();
+            metas
+                .next()
+                .is_some_and(|meta| meta.path().is_ident("export"))
+                .then(|| ExportOptions::parse(metas).ok())
+                .flatten()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns whether the function is marked with `#[casper(export)]`.
+fn is_export(func: &ItemFn) -> bool {
+    func.attrs.iter().any(|attr| {
+        let syn::Meta::List(list) = &attr.meta else {
+            return false;
+        };
+        match list.path.segments.last() {
+            Some(last) if last.ident == "casper" => list
+                .parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)
+                .is_ok_and(|metas| {
+                    metas
+                        .first()
+                        .is_some_and(|meta| meta.path().is_ident("export"))
+                }),
+            Some(last) if last.ident == "unsafe" => {
+                let s = list.tokens.to_string();
+                s.contains("casper") && s.contains("export")
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Removes the `#[casper(named_key)]` attributes of a static, returning whether it had one.
+fn take_named_key_attr(attrs: &mut Vec<Attribute>) -> bool {
+    let before = attrs.len();
+    attrs.retain(|attr| {
+        !(attr.path().is_ident("casper")
+            && attr
+                .parse_args::<syn::Path>()
+                .is_ok_and(|path| path.is_ident("named_key")))
+    });
+    attrs.len() != before
+}
+
+/// Longest entry point or argument name accepted by `#[casper(...)]`, in bytes.
+const MAX_NAME_LEN: usize = 128;
+
+/// Rejects entry point names the engine would refuse, and duplicates within a contract module.
+///
+/// Duplicates are reported on the second export with a note on the first one. `call` is reserved
+/// for the session entry point.
+fn validate_entry_point_names(items: &[Item]) -> syn::Result<()> {
+    use syn::ext::IdentExt;
+
+    let mut seen: std::collections::BTreeMap<String, proc_macro2::Span> =
+        std::collections::BTreeMap::new();
+    let mut errors: Option<syn::Error> = None;
+    let mut push = |error: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+
+    for item in items {
+        let Item::Fn(func) = item else {
+            continue;
+        };
+        if !is_export(func) {
+            continue;
+        }
+        let ident = &func.sig.ident;
+        // Renamed entry points are reported on their name
+        let (name, span) = match &export_options(func).name {
+            Some(name) => (name.value(), name.span()),
+            None => (ident.unraw().to_string(), ident.span()),
+        };
+
+        if let Err(error) = validate_entry_point_name(&name) {
+            push(syn::Error::new(span, error));
+            continue;
+        }
+        if let Some(first) = seen.get(&name) {
+            let mut error = syn::Error::new(
+                span,
+                format!("duplicate entry point name `{name}` in contract module"),
+            );
+            error.combine(syn::Error::new(
+                *first,
+                format!("entry point `{name}` first exported here"),
+            ));
+            push(error);
+            continue;
+        }
+        seen.insert(name, span);
+    }
+
+    errors.map_or(Ok(()), Err)
+}
+
+fn validate_entry_point_name(name: &str) -> Result<(), String> {
+    validate_name("entry point", name)?;
+    if name == "call" {
+        return Err(
+            "`call` is reserved for the session entry point and can't be exported from a contract module"
+                .into(),
+        );
+    }
+    Ok(())
+}
+
+/// Rejects empty, too long and NUL-containing names of the given `kind`.
+fn validate_name(kind: &str, name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("{kind} name must not be empty"));
+    }
+    if name.len() > MAX_NAME_LEN {
+        return Err(format!(
+            "{kind} name `{name}` is {} bytes long, the limit is {MAX_NAME_LEN}",
+            name.len()
+        ));
+    }
+    if name.contains('\0') {
+        return Err(format!("{kind} name must not contain NUL characters"));
+    }
+    Ok(())
+}
+
+/// Returns the argument names of the parameters `params` of an export: the `arg = "..."` of their
+/// `#[casper(...)]` attribute in `arg_attrs`, their entry in the `rename(...)` list of the export,
+/// or their identifiers.
+///
+/// Rejects invalid and duplicate names, renames of unknown parameters and parameters renamed
+/// twice.
+fn arg_names(
+    params: &[Ident],
+    arg_attrs: Vec<Option<LitStr>>,
+    renames: &[(Ident, LitStr)],
+) -> syn::Result<Vec<LitStr>> {
+    let mut names = arg_attrs;
+    for (param, name) in renames {
+        let Some(index) = params.iter().position(|ident| ident == param) else {
+            return Err(syn::Error::new_spanned(
+                param,
+                format!("no parameter `{param}` to rename"),
+            ));
+        };
+        if names[index].is_some() {
+            return Err(syn::Error::new_spanned(
+                param,
+                format!("parameter `{param}` is renamed twice"),
+            ));
+        }
+        names[index] = Some(name.clone());
+    }
+
+    let mut seen = std::collections::BTreeSet::new();
+    let mut arg_names = Vec::with_capacity(params.len());
+    for (ident, name) in params.iter().zip(names) {
+        let name = match name {
+            Some(name) => {
+                validate_name("argument", &name.value())
+                    .map_err(|error| syn::Error::new(name.span(), error))?;
+                name
+            }
+            None => LitStr::new(&ident.to_string(), ident.span()),
+        };
+        if !seen.insert(name.value()) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("duplicate argument name `{}`", name.value()),
+            ));
+        }
+        arg_names.push(name);
+    }
+    Ok(arg_names)
+}
+
+/// Splices the items of files included with `include!("file.rs")` into the module body.
+///
+/// This lets `#[casper(contract)]` see exported functions defined in a separate file. Paths are
+/// resolved relative to the file containing the `include!`, the same way `include!` does. Returns
+/// the inner attributes and items of the module along with the absolute paths of included files.
+fn expand_includes(items: Vec<Item>) -> syn::Result<(Vec<Attribute>, Vec<Item>, Vec<LitStr>)> {
+    let mut inner_attrs = Vec::new();
+    let mut expanded = Vec::new();
+    let mut included_files = Vec::new();
+    for item in items {
+        let Item::Macro(item_macro) = &item else {
+            expanded.push(item);
+            continue;
+        };
+        let is_include = item_macro
+            .mac
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "include");
+        if !is_include {
+            expanded.push(item);
+            continue;
+        }
+
+        let file_lit: LitStr = item_macro.mac.parse_body()?;
+        let path = std::path::PathBuf::from(file_lit.value());
+        let path = if path.is_absolute() {
+            path
+        } else {
+            let Some(source_file) = file_lit.span().unwrap().local_file() else {
+                return Err(syn::Error::new_spanned(
+                    &file_lit,
+                    "#[casper(contract)] could not determine the source file of this `include!`; use an absolute path",
+                ));
+            };
+            source_file
+                .parent()
+                .map(|dir| dir.join(&path))
+                .unwrap_or(path)
+        };
+        let source = std::fs::read_to_string(&path).map_err(|err| {
+            syn::Error::new_spanned(
+                &file_lit,
+                format!("couldn't read `{}`: {err}", file_lit.value()),
+            )
+        })?;
+        let file = syn::parse_file(&source).map_err(|err| {
+            syn::Error::new_spanned(
+                &file_lit,
+                format!("couldn't parse `{}`: {err}", file_lit.value()),
+            )
+        })?;
+        inner_attrs.extend(file.attrs);
+        expanded.extend(file.items);
+        // `include_bytes!` resolves relative paths against the invoking file, so pass it an absolute one
+        let path = std::fs::canonicalize(&path).unwrap_or(path);
+        included_files.push(LitStr::new(
+            &path.to_string_lossy(),
+            proc_macro2::Span::call_site(),
+        ));
+    }
+    Ok((inner_attrs, expanded, included_files))
+}
+
+/// Maps a borrowed parameter type to the owned type that is fetched from the runtime arguments.
+///
+/// Returns `Ok(None)` for parameters that are not references. Only an allow-list of borrowed types
+/// is supported: `&str` (fetched as `String`), `&[u8]` (fetched as `Bytes`), `&[T]` (fetched as
+/// `Vec<T>`) and `&T` for named or array types (fetched as `T`).
+fn owned_arg_type(ty: &Type) -> syn::Result<Option<Type>> {
+    let Type::Reference(reference) = ty else {
+        return Ok(None);
+    };
+
+    let unsupported = || {
+        syn::Error::new_spanned(
+            ty,
+            "unsupported reference type in #[casper(export)] argument; supported borrowed types \
+             are `&str` (passed as `String`), `&[u8]` (passed as `Bytes`), `&[T]` (passed as \
+             `Vec<T>`) and `&T` for named or array types (passed as `T`)",
+        )
+    };
+
+    if reference.mutability.is_some() {
+        return Err(unsupported());
+    }
+
+    let owned_ty = match &*reference.elem {
+        Type::Path(type_path) if type_path.qself.is_none() && type_path.path.is_ident("str") => {
+            parse_quote! { alloc::string::String }
+        }
+        Type::Path(type_path) => Type::Path(type_path.clone()),
+        Type::Slice(slice) if is_u8(&slice.elem) => {
+            // `Vec<u8>` is rejected by the bytesrepr efficient serialization checks
+            parse_quote! { veles_casper_contract_api::casper_types::bytesrepr::Bytes }
+        }
+        Type::Slice(slice) => {
+            let elem = &slice.elem;
+            parse_quote! { alloc::vec::Vec<#elem> }
+        }
+        Type::Array(array) => Type::Array(array.clone()),
+        _ => return Err(unsupported()),
+    };
+
+    Ok(Some(owned_ty))
+}
+
+/// Options of an export parameter, from its `#[casper(...)]` attributes.
+#[derive(Default)]
+struct ParamOptions {
+    /// Whether the parameter is marked `explicit_none`.
+    explicit_none: bool,
+    /// The argument name of `arg = "..."`, if not the parameter's.
+    arg: Option<LitStr>,
+}
+
+/// Returns the options of a parameter, rejecting unknown `#[casper(...)]` parameter attributes.
+fn param_options(attrs: &[Attribute]) -> syn::Result<ParamOptions> {
+    let mut options = ParamOptions::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("casper")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("explicit_none") {
+                options.explicit_none = true;
+                Ok(())
+            } else if meta.path.is_ident("arg") {
+                options.arg = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error(
+                    "unsupported #[casper] parameter attribute, expected `explicit_none` or `arg = \"...\"`",
+                ))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+/// Generates `Args::builder()` and the `ArgsBuilder` it returns.
+///
+/// Each required argument is a type parameter of the builder that is `Unset` until its setter
+/// turns it into `Set<T>`, and `build` is only implemented once all of them are set. `Option<T>`
+/// arguments are left `None` unless set. Only `builder` is `const`: moving the arguments out of a
+/// builder in a `const fn` needs precise drop tracking, which is not stable yet.
+fn args_builder(arg_idents: &[Ident], arg_types: &[Type]) -> proc_macro2::TokenStream {
+    let unset = quote! { veles_casper_contract_api::macro_support::Unset };
+    let set = quote! { veles_casper_contract_api::macro_support::Set };
+
+    let mut required: Vec<(&Ident, &Type)> = Vec::new();
+    let mut optional: Vec<(&Ident, &Type)> = Vec::new();
+    for (ident, ty) in arg_idents.iter().zip(arg_types) {
+        match option_inner_type(ty) {
+            Some(inner) => optional.push((ident, inner)),
+            None => required.push((ident, ty)),
+        }
+    }
+    let required_idents: Vec<&Ident> = required.iter().map(|(ident, _)| *ident).collect();
+    let required_types: Vec<&Type> = required.iter().map(|(_, ty)| *ty).collect();
+    let optional_idents: Vec<&Ident> = optional.iter().map(|(ident, _)| *ident).collect();
+    let optional_types: Vec<&Type> = optional.iter().map(|(_, ty)| *ty).collect();
+    let states: Vec<Ident> = (0..required.len())
+        .map(|index| format_ident!("__State{}", index))
+        .collect();
+
+    let unset_states = vec![&unset; required.len()];
+
+    let required_setters = required.iter().enumerate().map(|(index, (ident, ty))| {
+        let other_states = states
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, state)| state);
+        let before = states.iter().enumerate().map(|(other, state)| {
+            if other == index {
+                quote! { #unset }
+            } else {
+                quote! { #state }
+            }
+        });
+        let after = states.iter().enumerate().map(|(other, state)| {
+            if other == index {
+                quote! { #set<#ty> }
+            } else {
+                quote! { #state }
+            }
+        });
+        let other_idents = arg_idents.iter().filter(|other| other != ident);
+        let other_idents_again = other_idents.clone();
+        quote! {
+            #[allow(non_camel_case_types)]
+            impl<#(#other_states),*> ArgsBuilder<#(#before),*> {
+                pub fn #ident(self, #ident: #ty) -> ArgsBuilder<#(#after),*> {
+                    let ArgsBuilder { #ident: _unset, #(#other_idents),* } = self;
+                    ArgsBuilder { #ident: #set(#ident), #(#other_idents_again),* }
+                }
+            }
+        }
+    });
+
+    quote! {
+        /// Builds [`Args`] by name, leaving `Option` arguments `None` unless set.
+        #[allow(non_camel_case_types)]
+        pub struct ArgsBuilder<#(#states),*> {
+            #(#required_idents: #states,)*
+            #(#optional_idents: core::option::Option<#optional_types>,)*
+        }
+
+        impl Args {
+            pub const fn builder() -> ArgsBuilder<#(#unset_states),*> {
+                ArgsBuilder {
+                    #(#required_idents: #unset_states,)*
+                    #(#optional_idents: core::option::Option::None,)*
+                }
+            }
+        }
+
+        #(#required_setters)*
+
+        #[allow(non_camel_case_types)]
+        impl<#(#states),*> ArgsBuilder<#(#states),*> {
+            #(
+                pub fn #optional_idents(mut self, #optional_idents: #optional_types) -> Self {
+                    self.#optional_idents = core::option::Option::Some(#optional_idents);
+                    self
+                }
+            )*
+        }
+
+        impl ArgsBuilder<#(#set<#required_types>),*> {
+            pub fn build(self) -> Args {
+                let ArgsBuilder {
+                    #(#required_idents: #set(#required_idents),)*
+                    #(#optional_idents,)*
+                } = self;
+                Args { #(#arg_idents),* }
+            }
+        }
+    }
+}
+
+/// Returns true if the type is a `Result<T, E>`.
+fn is_result_type(ty: &Type) -> bool {
+    if let Type::Path(type_path) = ty {
+        type_path
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident == "Result")
+            .unwrap_or(false)
+    } else {
+        false
+    }
+}
+
+/// Returns the `T` of a `Result<T, E>` return type.
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(syn::GenericArgument::Type(ok)) => Some(ok),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether an export returns `()` or `Result<(), E>`, i.e. has no value to hand to its caller.
+fn returns_unit(output: &ReturnType) -> bool {
+    let ReturnType::Type(_, ty) = output else {
+        return true;
+    };
+    let ty = if is_result_type(ty) {
+        result_ok_type(ty).unwrap_or(ty)
+    } else {
+        ty
+    };
+    matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
+}
+
+/// Returns the inner type of an `Option<T>` parameter.
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first()
+        {
+            Some(syn::GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_u8(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.qself.is_none() && type_path.path.is_ident("u8"))
+}
+
+/// Drops an explicit lifetime from a reference type so it can be used in generated signatures.
+fn elided_reference(ty: &Type) -> Type {
+    match ty {
+        Type::Reference(reference) => {
+            let mut reference = reference.clone();
+            reference.lifetime = None;
+            Type::Reference(reference)
+        }
+        _ => ty.clone(),
+    }
+}
+
 #[proc_macro_derive(CasperMessage, attributes(casper))]
 pub fn derive_casper_event(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as syn::DeriveInput);
@@ -448,7 +1594,7 @@ pub fn derive_casper_event(input: TokenStream) -> TokenStream {
             const TOPIC_NAME_HASH: [u8; 32] = [#(#computed_topic_name_hash),*];
 
             fn into_message_payload(self) -> Result<veles_casper_contract_api::casper_types::contract_messages::MessagePayload, veles_casper_contract_api::casper_types::ApiError> {
-                let bytes = self.into_bytes()?;
+                let bytes = veles_casper_contract_api::casper_types::bytesrepr::ToBytes::into_bytes(self)?;
                 let payload = veles_casper_contract_api::casper_types::contract_messages::MessagePayload::Bytes(bytes.into());
                 Ok(payload)
             }
@@ -506,6 +1652,8 @@ pub fn derive_dictionary_key(input: TokenStream) -> TokenStream {
     let r#gen = if accessors.is_empty() {
         quote! {
             impl #impl_generics veles_casper_contract_api::collections::dictionary_key::DictionaryKey<'dict> for #ident #ty_generics #where_clause {
+                fn write_key(&self, _out: &mut alloc::string::String) {}
+
                 fn dictionary_key(&'dict self) -> alloc::borrow::Cow<'dict, str> {
                     alloc::borrow::Cow::Borrowed("")
                 }
@@ -515,6 +1663,21 @@ pub fn derive_dictionary_key(input: TokenStream) -> TokenStream {
         let acc = &accessors[0];
         quote! {
             impl #impl_generics veles_casper_contract_api::collections::dictionary_key::DictionaryKey<'dict> for #ident #ty_generics #where_clause {
+                fn write_key(&self, out: &mut alloc::string::String) {
+                    use veles_casper_contract_api::collections::dictionary_key::DictionaryKey as _;
+                    (#acc).write_key(out)
+                }
+
+                fn write_part(&self, out: &mut alloc::string::String) {
+                    use veles_casper_contract_api::collections::dictionary_key::DictionaryKey as _;
+                    (#acc).write_part(out)
+                }
+
+                fn write_legacy_key(&self, out: &mut alloc::string::String) {
+                    use veles_casper_contract_api::collections::dictionary_key::DictionaryKey as _;
+                    (#acc).write_legacy_key(out)
+                }
+
                 fn dictionary_key(&'dict self) -> alloc::borrow::Cow<'dict, str> {
                     use veles_casper_contract_api::collections::dictionary_key::DictionaryKey as _;
                     (#acc).dictionary_key()
@@ -524,20 +1687,22 @@ pub fn derive_dictionary_key(input: TokenStream) -> TokenStream {
     } else {
         quote! {
             impl #impl_generics veles_casper_contract_api::collections::dictionary_key::DictionaryKey<'dict> for #ident #ty_generics #where_clause {
-                // Build aggregated dictionary key string
-                fn dictionary_key(&'dict self) -> alloc::borrow::Cow<'dict, str> {
+                fn write_key(&self, out: &mut alloc::string::String) {
+                    veles_casper_contract_api::collections::dictionary_key::DictionaryKeyWriter::new(out)
+                        #(.part(#accessors))*;
+                }
+
+                fn write_part(&self, out: &mut alloc::string::String) {
                     use veles_casper_contract_api::collections::dictionary_key::DictionaryKey as _;
-                    let parts = [#((#accessors).dictionary_key()),*];
-                    let separators = parts.len().saturating_sub(1);
-                    let capacity = parts.iter().map(|part| part.len()).sum::<usize>() + separators;
-                    let mut combined = alloc::string::String::with_capacity(capacity);
-                    for (idx, part) in parts.iter().enumerate() {
-                        if idx != 0 {
-                            combined.push(':');
-                        }
-                        combined.push_str(part);
-                    }
-                    alloc::borrow::Cow::Owned(combined)
+                    veles_casper_contract_api::collections::dictionary_key::write_compound_part(
+                        |part| self.write_key(part),
+                        out,
+                    )
+                }
+
+                fn write_legacy_key(&self, out: &mut alloc::string::String) {
+                    veles_casper_contract_api::collections::dictionary_key::DictionaryKeyWriter::new(out)
+                        #(.legacy_part(#accessors))*;
                 }
             }
         }
@@ -546,6 +1711,220 @@ pub fn derive_dictionary_key(input: TokenStream) -> TokenStream {
     TokenStream::from(r#gen)
 }
 
+/// Derives `IntoRuntimeArgs` for a struct, inserting each field as the runtime argument of its
+/// name.
+///
+/// Mirrors the `Args` generated for exported functions: `Option` fields are omitted when `None`,
+/// unless marked `#[casper(explicit_none)]`, and `#[casper(arg = "...")]` renames the argument of a
+/// field. Type parameters are required to implement `CLTyped` and `ToBytes`.
+#[proc_macro_derive(IntoRuntimeArgs, attributes(casper))]
+pub fn derive_into_runtime_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+
+    let fields: Vec<&syn::Field> = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => named.named.iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return syn::Error::new_spanned(
+                    &input,
+                    "IntoRuntimeArgs needs named fields to name the runtime arguments",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(
+                &input,
+                "IntoRuntimeArgs can only be derived for structs",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let mut insert_args = Vec::with_capacity(fields.len());
+    let field_idents: Vec<Ident> = fields
+        .iter()
+        .map(|field| field.ident.clone().unwrap())
+        .collect();
+    let mut field_options = Vec::with_capacity(fields.len());
+    for field in &fields {
+        match param_options(&field.attrs) {
+            Ok(options) => field_options.push(options),
+            Err(error) => return error.to_compile_error().into(),
+        }
+    }
+    let arg_names = match arg_names(
+        &field_idents,
+        field_options
+            .iter()
+            .map(|options| options.arg.clone())
+            .collect(),
+        &[],
+    ) {
+        Ok(arg_names) => arg_names,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    for (((field, name), options), arg_name) in fields
+        .iter()
+        .zip(&field_idents)
+        .zip(&field_options)
+        .zip(&arg_names)
+    {
+        let explicit = options.explicit_none;
+        let optional = option_inner_type(&field.ty).is_some();
+        if explicit && !optional {
+            return syn::Error::new_spanned(
+                &field.ty,
+                "#[casper(explicit_none)] requires an `Option<T>` field",
+            )
+            .to_compile_error()
+            .into();
+        }
+        insert_args.push(if optional && !explicit {
+            quote! {
+                if let Some(value) = self.#name {
+                    runtime_args
+                        .insert(#arg_name, value)
+                        .map_err(veles_casper_contract_api::casper_types::ApiError::from)?;
+                }
+            }
+        } else {
+            quote! {
+                runtime_args
+                    .insert(#arg_name, self.#name)
+                    .map_err(veles_casper_contract_api::casper_types::ApiError::from)?;
+            }
+        });
+    }
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(
+            veles_casper_contract_api::casper_types::CLTyped
+        ));
+        param.bounds.push(parse_quote!(
+            veles_casper_contract_api::casper_types::bytesrepr::ToBytes
+        ));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics veles_casper_contract_api::macro_support::IntoRuntimeArgs for #ident #ty_generics #where_clause {
+            fn into_runtime_args(self) -> veles_casper_contract_api::casper_types::RuntimeArgs {
+                veles_casper_contract_api::macro_support::IntoRuntimeArgs::try_into_runtime_args(self).unwrap()
+            }
+
+            fn try_into_runtime_args(self) -> core::result::Result<veles_casper_contract_api::casper_types::RuntimeArgs, veles_casper_contract_api::casper_types::ApiError> {
+                let mut runtime_args = veles_casper_contract_api::casper_types::RuntimeArgs::new();
+                #(#insert_args)*
+                Ok(runtime_args)
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Derives `ToBytes` and `FromBytes` for a struct by serializing its fields in declaration order.
+///
+/// Generates the same code as a hand-written implementation delegating to each field, which makes
+/// it a natural companion of `CasperMessage`. Type parameters are required to implement the trait
+/// being derived.
+#[proc_macro_derive(CasperSerialize)]
+pub fn derive_casper_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return syn::Error::new_spanned(
+                &input,
+                "CasperSerialize can only be derived for structs",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let accessors: Vec<proc_macro2::TokenStream> = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|f| {
+                let name = f.ident.as_ref().unwrap();
+                quote! { #name }
+            })
+            .collect(),
+        Fields::Unnamed(unnamed) => unnamed
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(idx, _)| {
+                let index = syn::Index::from(idx);
+                quote! { #index }
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+    let locals: Vec<Ident> = (0..accessors.len())
+        .map(|idx| format_ident!("__field_{}", idx))
+        .collect();
+    let constructor = match fields {
+        Fields::Named(_) => quote! { Self { #(#accessors: #locals),* } },
+        Fields::Unnamed(_) => quote! { Self(#(#locals),*) },
+        Fields::Unit => quote! { Self },
+    };
+
+    let bytesrepr = quote! { veles_casper_contract_api::casper_types::bytesrepr };
+    let bounded_generics = |bound: proc_macro2::TokenStream| {
+        let mut generics = input.generics.clone();
+        for param in generics.type_params_mut() {
+            param.bounds.push(parse_quote!(#bound));
+        }
+        generics
+    };
+    let to_bytes_generics = bounded_generics(quote! { #bytesrepr::ToBytes });
+    let from_bytes_generics = bounded_generics(quote! { #bytesrepr::FromBytes });
+    let (to_bytes_impl_generics, ty_generics, to_bytes_where_clause) =
+        to_bytes_generics.split_for_impl();
+    let (from_bytes_impl_generics, _, from_bytes_where_clause) =
+        from_bytes_generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #to_bytes_impl_generics #bytesrepr::ToBytes for #ident #ty_generics #to_bytes_where_clause {
+            fn to_bytes(&self) -> Result<alloc::vec::Vec<u8>, #bytesrepr::Error> {
+                let mut buffer = #bytesrepr::allocate_buffer(self)?;
+                self.write_bytes(&mut buffer)?;
+                Ok(buffer)
+            }
+
+            fn serialized_length(&self) -> usize {
+                0 #(+ #bytesrepr::ToBytes::serialized_length(&self.#accessors))*
+            }
+
+            fn write_bytes(&self, writer: &mut alloc::vec::Vec<u8>) -> Result<(), #bytesrepr::Error> {
+                #(#bytesrepr::ToBytes::write_bytes(&self.#accessors, writer)?;)*
+                Ok(())
+            }
+        }
+
+        impl #from_bytes_impl_generics #bytesrepr::FromBytes for #ident #ty_generics #from_bytes_where_clause {
+            fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), #bytesrepr::Error> {
+                let remainder = bytes;
+                #(let (#locals, remainder) = #bytesrepr::FromBytes::from_bytes(remainder)?;)*
+                Ok((#constructor, remainder))
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
 #[proc_macro]
 pub fn blake2b256(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as LitStr);
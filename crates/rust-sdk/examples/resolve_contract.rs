@@ -0,0 +1,40 @@
+//! Resolves the newest enabled contract of a package installed by an account.
+//!
+//! After installing `do-nothing-stored`, run:
+//!
+//! ```sh
+//! cargo run -p veles-casper-rust-sdk --example resolve_contract -- \
+//!     http://localhost:11101 <installer public key hex>
+//! ```
+use casper_types::{AsymmetricType, PublicKey};
+use clap::Parser;
+
+use veles_casper_rust_sdk::jsonrpc::{AccountIdentifier, CasperClient};
+
+#[derive(Debug, Parser)]
+#[command(name = "resolve_contract")]
+#[command(about = "Resolve the latest contract of an installed package", long_about = None)]
+struct Cli {
+    endpoint: String,
+    public_key: String,
+    #[arg(long = "package-key", default_value = "do_nothing_package_hash")]
+    package_key: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    let client = CasperClient::new(cli.endpoint);
+    let account = AccountIdentifier::PublicKey(PublicKey::from_hex(&cli.public_key)?);
+    let (contract_hash, named_keys) = client
+        .resolve_latest_contract(account, &cli.package_key)
+        .await?;
+
+    println!("{contract_hash}");
+    for (name, key) in named_keys.iter() {
+        println!("  {name}: {}", key.to_formatted_string());
+    }
+
+    Ok(())
+}
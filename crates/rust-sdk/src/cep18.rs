@@ -0,0 +1,151 @@
+//! Helpers for CEP-18 token tooling.
+//!
+//! CEP-18 balances are raw `U256` amounts of the smallest unit; the `decimals` named key of the
+//! token tells how many of their digits are fractional. The conversions below shift the decimal
+//! point on the digit string, so they are exact for any `decimals`.
+use casper_types::U256;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TokenAmountError {
+    #[error("invalid token amount: {0:?}")]
+    InvalidFormat(String),
+    #[error("token amount has {found} fractional digits, the token has {decimals} decimals")]
+    TooManyDecimals { decimals: u8, found: usize },
+    #[error("token amount exceeds U256 range")]
+    Overflow,
+}
+
+/// Formats the raw amount `raw` of a token with `decimals` decimals for display.
+///
+/// Trailing fractional zeros are trimmed, so `1_500` with 3 decimals formats as `"1.5"` and
+/// `2_000` as `"2"`.
+pub fn format_token_amount(raw: U256, decimals: u8) -> String {
+    let digits = raw.to_string();
+    let decimals = usize::from(decimals);
+    if decimals == 0 {
+        return digits;
+    }
+
+    let digits = format!("{digits:0>width$}", width = decimals + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{integer}.{fraction}")
+    }
+}
+
+/// Parses a human readable amount such as `"1.5"` into the raw amount of a token with `decimals`
+/// decimals.
+///
+/// Fractional digits beyond `decimals` are only accepted when they are zeros; anything else
+/// can't be represented by the token and is rejected instead of rounded.
+pub fn parse_token_amount(human: &str, decimals: u8) -> Result<U256, TokenAmountError> {
+    let invalid = || TokenAmountError::InvalidFormat(human.to_string());
+    let (integer, fraction) = match human.split_once('.') {
+        Some((integer, fraction)) if !fraction.is_empty() => (integer, fraction),
+        Some(_) => return Err(invalid()),
+        None => (human, ""),
+    };
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if integer.is_empty() || !is_digits(integer) || !is_digits(fraction) {
+        return Err(invalid());
+    }
+
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > usize::from(decimals) {
+        return Err(TokenAmountError::TooManyDecimals {
+            decimals,
+            found: fraction.len(),
+        });
+    }
+
+    let digits = format!(
+        "{integer}{fraction:0<width$}",
+        width = usize::from(decimals)
+    );
+    U256::from_dec_str(&digits).map_err(|_| TokenAmountError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_with_decimal_shift() {
+        assert_eq!(format_token_amount(U256::zero(), 9), "0");
+        assert_eq!(format_token_amount(U256::zero(), 0), "0");
+        assert_eq!(format_token_amount(U256::from(42), 0), "42");
+        assert_eq!(format_token_amount(U256::from(1_500_000_000u64), 9), "1.5");
+        assert_eq!(format_token_amount(U256::from(2_000), 3), "2");
+        assert_eq!(format_token_amount(U256::from(1_234_567), 3), "1234.567");
+    }
+
+    #[test]
+    fn formats_values_smaller_than_one_unit() {
+        assert_eq!(format_token_amount(U256::from(1), 9), "0.000000001");
+        assert_eq!(format_token_amount(U256::from(120), 3), "0.12");
+        assert_eq!(format_token_amount(U256::from(5), 1), "0.5");
+    }
+
+    #[test]
+    fn formats_with_more_decimals_than_u256_digits() {
+        let formatted = format_token_amount(U256::MAX, u8::MAX);
+        assert!(formatted.starts_with("0.000"));
+        assert_eq!(parse_token_amount(&formatted, u8::MAX), Ok(U256::MAX));
+    }
+
+    #[test]
+    fn parses_with_decimal_shift() {
+        assert_eq!(parse_token_amount("0", 9), Ok(U256::zero()));
+        assert_eq!(parse_token_amount("0.0", 9), Ok(U256::zero()));
+        assert_eq!(
+            parse_token_amount("1.5", 9),
+            Ok(U256::from(1_500_000_000u64))
+        );
+        assert_eq!(parse_token_amount("2", 3), Ok(U256::from(2_000)));
+        assert_eq!(parse_token_amount("0.000000001", 9), Ok(U256::from(1)));
+        assert_eq!(parse_token_amount("007.10", 2), Ok(U256::from(710)));
+        assert_eq!(parse_token_amount("42", 0), Ok(U256::from(42)));
+        assert_eq!(parse_token_amount("1.500", 1), Ok(U256::from(15)));
+    }
+
+    #[test]
+    fn rejects_unrepresentable_amounts() {
+        for human in ["", ".", ".5", "1.", "1.2.3", "-1", "+1", "1e9", " 1", "1,5"] {
+            assert_eq!(
+                parse_token_amount(human, 9),
+                Err(TokenAmountError::InvalidFormat(human.to_string())),
+                "{human:?}"
+            );
+        }
+        assert_eq!(
+            parse_token_amount("0.0001", 3),
+            Err(TokenAmountError::TooManyDecimals {
+                decimals: 3,
+                found: 4
+            })
+        );
+        assert_eq!(
+            parse_token_amount("1.5", 0),
+            Err(TokenAmountError::TooManyDecimals {
+                decimals: 0,
+                found: 1
+            })
+        );
+        let max = U256::MAX.to_string();
+        assert_eq!(parse_token_amount(&max, 0), Ok(U256::MAX));
+        assert_eq!(parse_token_amount(&max, 1), Err(TokenAmountError::Overflow));
+    }
+
+    #[test]
+    fn parse_inverts_format() {
+        for (raw, decimals) in [(0u64, 9), (1, 9), (1_000, 3), (123_456_789, 4), (7, 0)] {
+            let raw = U256::from(raw);
+            let formatted = format_token_amount(raw, decimals);
+            assert_eq!(parse_token_amount(&formatted, decimals), Ok(raw));
+        }
+    }
+}
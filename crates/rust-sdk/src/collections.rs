@@ -0,0 +1,260 @@
+//! Off-chain readers of the kit's on-chain collections, for indexers.
+//!
+//! A [`Vector`](veles_casper_contract_api::collections::vector::Vector) stores its length under
+//! the [`VEC_LENGTH_KEY`] item of its dictionary and the item at `index` under
+//! [`keyspace::vector_item_key`]`(index)`, the base128 encoding of the little-endian index bytes.
+//! [`read_vector`] reads a range of items with up to [`DICTIONARY_READ_CONCURRENCY`] dictionary
+//! item queries in flight, all at the same state root hash so the items are consistent with the
+//! length they were clamped to.
+//!
+//! The elements of an
+//! [`IndexedSet`](veles_casper_contract_api::collections::indexed_set::IndexedSet) are a
+//! `Vector` too, so `read_vector` on the seed URef of its elements dictionary lists the set.
+use std::{future::Future, ops::Range};
+
+use casper_types::{CLTyped, CLValue, Digest, URef, bytesrepr::FromBytes};
+use futures::{StreamExt, TryStreamExt, stream};
+use veles_casper_contract_api::{collections::vector::VEC_LENGTH_KEY, keyspace};
+
+use crate::jsonrpc::{CasperClient, CasperClientError};
+
+/// How many dictionary item queries [`read_vector`] keeps in flight.
+pub const DICTIONARY_READ_CONCURRENCY: usize = 16;
+
+/// Reads dictionary items at a given state root hash, e.g. from a node through [`CasperClient`].
+pub trait DictionaryItemSource {
+    /// Returns the item stored under `item_key` in the dictionary of `seed_uref` at
+    /// `state_root_hash`.
+    fn dictionary_item(
+        &self,
+        state_root_hash: Digest,
+        seed_uref: URef,
+        item_key: &str,
+    ) -> impl Future<Output = Result<CLValue, CasperClientError>> + Send;
+}
+
+impl DictionaryItemSource for CasperClient {
+    async fn dictionary_item(
+        &self,
+        state_root_hash: Digest,
+        seed_uref: URef,
+        item_key: &str,
+    ) -> Result<CLValue, CasperClientError> {
+        self.query_dictionary_item_at(state_root_hash, seed_uref, item_key)
+            .await
+    }
+}
+
+/// Reads the length of the `Vector` stored in the dictionary of `seed_uref`.
+///
+/// A `Vector` that was never pushed to has no length item, which the node reports as a missing
+/// value: its length is 0.
+pub async fn read_vector_len(
+    source: &impl DictionaryItemSource,
+    state_root_hash: Digest,
+    seed_uref: URef,
+) -> Result<u64, CasperClientError> {
+    match source
+        .dictionary_item(state_root_hash, seed_uref, VEC_LENGTH_KEY)
+        .await
+    {
+        Ok(length) => Ok(length.into_t()?),
+        Err(error) if error.is_missing_value() => Ok(0),
+        Err(error) => Err(error),
+    }
+}
+
+/// Reads the items at `indices` of the `Vector` stored in the dictionary of `seed_uref`, in index
+/// order.
+///
+/// `indices` is clamped to the length of the vector, so `0..u64::MAX` reads the whole vector and
+/// the stale items a shrunk vector leaves behind are never read.
+pub async fn read_vector<T: CLTyped + FromBytes>(
+    source: &impl DictionaryItemSource,
+    state_root_hash: Digest,
+    seed_uref: URef,
+    indices: Range<u64>,
+) -> Result<Vec<T>, CasperClientError> {
+    let length = read_vector_len(source, state_root_hash, seed_uref).await?;
+    let indices = indices.start.min(length)..indices.end.min(length);
+    stream::iter(indices)
+        .map(|index| async move {
+            let item_key = keyspace::vector_item_key(index);
+            let item = source
+                .dictionary_item(state_root_hash, seed_uref, &item_key)
+                .await?;
+            Ok::<T, CasperClientError>(item.into_t()?)
+        })
+        .buffered(DICTIONARY_READ_CONCURRENCY)
+        .try_collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use casper_client::{Error as CasperClientRpcError, JsonRpcId};
+    use casper_types::{Key, U256};
+    use veles_casper_contract_api::{
+        casper_contract::contract_api::storage,
+        collections::{indexed_set::IndexedSet, vector::Vector},
+        named_key::NamedKey,
+        veles_casper_ffi_shim::{EnvBuilder, dispatch_with},
+    };
+
+    use super::*;
+
+    const STATE_ROOT_HASH: Digest = Digest::from_raw([3; 32]);
+
+    static AMOUNTS: Vector<U256> = Vector::from_named_key(NamedKey::from_name("amounts"));
+    static HOLDERS: IndexedSet<Key> = IndexedSet::from_named_keys(
+        NamedKey::from_name("holders"),
+        NamedKey::from_name("holder_indices"),
+    );
+
+    /// Serves the dictionaries a contract wrote under the shim, tracking concurrent queries.
+    #[derive(Default)]
+    struct MockResponder {
+        dictionaries: BTreeMap<[u8; 32], BTreeMap<String, CLValue>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+        queries: AtomicUsize,
+    }
+
+    impl DictionaryItemSource for MockResponder {
+        async fn dictionary_item(
+            &self,
+            state_root_hash: Digest,
+            seed_uref: URef,
+            item_key: &str,
+        ) -> Result<CLValue, CasperClientError> {
+            assert_eq!(state_root_hash, STATE_ROOT_HASH);
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            self.queries.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            self.dictionaries
+                .get(&seed_uref.addr())
+                .and_then(|items| items.get(item_key))
+                .cloned()
+                .ok_or_else(missing_value)
+        }
+    }
+
+    /// The error of a node queried for an item that was never written.
+    fn missing_value() -> CasperClientError {
+        CasperClientRpcError::ResponseIsRpcError {
+            rpc_id: JsonRpcId::from(1),
+            rpc_method: "state_get_dictionary_item",
+            error: serde_json::from_value(serde_json::json!({
+                "code": -32003,
+                "message": "Query failed",
+            }))
+            .unwrap(),
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn reads_vector_ranges_with_bounded_concurrency() {
+        let env = EnvBuilder::new().build();
+        let mut amounts = None;
+        dispatch_with(env.clone(), |_env| {
+            amounts = Some(storage::new_dictionary("amounts").unwrap());
+            for amount in 0..100u64 {
+                AMOUNTS.push(U256::from(amount * 10)).unwrap();
+            }
+        });
+        let amounts = amounts.unwrap();
+        let responder = MockResponder {
+            dictionaries: env.dictionaries(),
+            ..MockResponder::default()
+        };
+
+        let items: Vec<U256> = read_vector(&responder, STATE_ROOT_HASH, amounts, 0..u64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            items,
+            (0..100u64)
+                .map(|amount| U256::from(amount * 10))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(responder.queries.load(Ordering::SeqCst), 101);
+        assert_eq!(
+            responder.max_in_flight.load(Ordering::SeqCst),
+            DICTIONARY_READ_CONCURRENCY
+        );
+
+        let page: Vec<U256> = read_vector(&responder, STATE_ROOT_HASH, amounts, 95..105)
+            .await
+            .unwrap();
+        assert_eq!(
+            page,
+            (95..100u64)
+                .map(|amount| U256::from(amount * 10))
+                .collect::<Vec<_>>()
+        );
+        let past_the_end: Vec<U256> = read_vector(&responder, STATE_ROOT_HASH, amounts, 100..200)
+            .await
+            .unwrap();
+        assert!(past_the_end.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reads_the_elements_of_indexed_sets() {
+        let holders = [Key::Hash([1; 32]), Key::Hash([2; 32]), Key::Hash([3; 32])];
+        let env = EnvBuilder::new().build();
+        let mut elements = None;
+        dispatch_with(env.clone(), |_env| {
+            elements = Some(storage::new_dictionary("holders").unwrap());
+            storage::new_dictionary("holder_indices").unwrap();
+            for holder in &holders {
+                HOLDERS.insert(holder).unwrap();
+            }
+            // The last element moves into the freed slot and its old slot goes stale.
+            HOLDERS.remove(&holders[0]).unwrap();
+        });
+        let responder = MockResponder {
+            dictionaries: env.dictionaries(),
+            ..MockResponder::default()
+        };
+
+        let listed: Vec<Key> =
+            read_vector(&responder, STATE_ROOT_HASH, elements.unwrap(), 0..u64::MAX)
+                .await
+                .unwrap();
+        assert_eq!(listed, [holders[2], holders[1]]);
+    }
+
+    #[tokio::test]
+    async fn reads_empty_vectors() {
+        let env = EnvBuilder::new().build();
+        let mut amounts = None;
+        dispatch_with(env.clone(), |_env| {
+            amounts = Some(storage::new_dictionary("amounts").unwrap());
+        });
+        let responder = MockResponder {
+            dictionaries: env.dictionaries(),
+            ..MockResponder::default()
+        };
+
+        assert_eq!(
+            read_vector_len(&responder, STATE_ROOT_HASH, amounts.unwrap())
+                .await
+                .unwrap(),
+            0
+        );
+        let items: Vec<U256> =
+            read_vector(&responder, STATE_ROOT_HASH, amounts.unwrap(), 0..u64::MAX)
+                .await
+                .unwrap();
+        assert!(items.is_empty());
+    }
+}
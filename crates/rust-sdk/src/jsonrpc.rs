@@ -1,11 +1,12 @@
 //! JSONRPC client for interacting with a Casper network.
-use casper_client::{self, JsonRpcId, Verbosity};
+use casper_client::{self, JsonRpcId};
 pub use casper_client::{
-    Error as CasperClientRpcError,
+    Error as CasperClientRpcError, Verbosity,
     cli::TransactionV1BuilderError,
     rpcs::{
-        AccountIdentifier,
-        common::BlockIdentifier,
+        AccountIdentifier, AddressableEntity, DictionaryItemIdentifier, EntityIdentifier,
+        EntityOrAccount, GlobalStateIdentifier,
+        common::{BlockIdentifier, SpeculativeExecutionResult},
         results::{
             GetAccountResult, GetBlockResult, GetChainspecResult, GetStateRootHashResult,
             GetTransactionResult, PutTransactionResult, SpeculativeExecTxnResult,
@@ -13,10 +14,29 @@ pub use casper_client::{
     },
 };
 
-use casper_types::{Digest, Transaction, TransactionHash, U512, crypto::ErrorExt};
+use std::{fmt, future::Future, time::Duration};
+
+use casper_types::{
+    ApiError, BlockHash, CLTyped, CLValue, CLValueError, Digest, EntryPointValue, EntryPoints, Gas,
+    Key, NamedKeys, Package, RuntimeArgs, SecretKey, StoredValue, Transaction, TransactionHash,
+    U512, URef,
+    bytesrepr::{FromBytes, ToBytes},
+    contracts::{Contract, ContractHash, ContractPackage},
+    crypto::ErrorExt,
+    execution::ExecutionResult,
+};
 use rand::Rng;
 use thiserror::Error;
+use tokio::time::Instant;
 use toml::Value as TomlValue;
+use tracing::{Instrument, debug, debug_span, field, trace};
+use veles_casper_contract_api::{
+    clvalue_debug,
+    entry_points_diff::{self, EntryPointsDiff},
+    host_client::{SessionOptions, SubmitTransaction, stored_contract_transaction},
+    limits::{LimitsError, NetworkLimits},
+    sdk::{decode_execution_error, parse_revert_message},
+};
 
 /// JSONRPC client for interacting with a Casper network sidecar instance.
 #[derive(Clone, Debug)]
@@ -40,6 +60,23 @@ impl CasperClient {
         }
     }
 
+    /// Sets how much of the RPC requests and responses `casper_client` prints to stdout, `Low`
+    /// (nothing) by default.
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.set_verbosity(verbosity);
+        self
+    }
+
+    /// Sets the verbosity of the requests made from now on, see [`Self::with_verbosity`].
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    /// Returns the verbosity passed to `casper_client`.
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
     /// Returns the primary RPC endpoint configured for this client.
     pub fn rpc_endpoint(&self) -> &str {
         // safe: enforced in `new`.
@@ -51,14 +88,17 @@ impl CasperClient {
         &self,
         account_identifier: AccountIdentifier,
     ) -> Result<Option<GetAccountResult>, CasperClientError> {
-        match casper_client::get_account(
-            next_rpc_id(),
-            self.rpc_endpoint(),
-            self.verbosity,
-            None,
-            account_identifier,
-        )
-        .await
+        match self
+            .rpc("state_get_account_info", None, |rpc_id| {
+                casper_client::get_account(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    None,
+                    account_identifier,
+                )
+            })
+            .await
         {
             Ok(response) => Ok(Some(response.result)),
             Err(CasperClientRpcError::ResponseIsRpcError { error, .. })
@@ -72,13 +112,16 @@ impl CasperClient {
 
     /// Returns the latest state root hash as a `Digest`.
     pub async fn get_state_root_hash(&self) -> Result<Digest, CasperClientError> {
-        let response = casper_client::get_state_root_hash(
-            next_rpc_id(),
-            self.rpc_endpoint(),
-            self.verbosity,
-            None,
-        )
-        .await?;
+        let response = self
+            .rpc("chain_get_state_root_hash", None, |rpc_id| {
+                casper_client::get_state_root_hash(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    None,
+                )
+            })
+            .await?;
 
         {
             let result: &GetStateRootHashResult = &response.result;
@@ -101,14 +144,17 @@ impl CasperClient {
         let main_purse = account.account.main_purse();
         let state_root = self.get_state_root_hash().await?;
 
-        let response = casper_client::get_balance(
-            next_rpc_id(),
-            self.rpc_endpoint(),
-            self.verbosity,
-            state_root,
-            main_purse,
-        )
-        .await?;
+        let response = self
+            .rpc("state_get_balance", None, |rpc_id| {
+                casper_client::get_balance(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    state_root,
+                    main_purse,
+                )
+            })
+            .await?;
 
         let balance = response.result.balance_value;
         Ok(Some(balance))
@@ -119,30 +165,101 @@ impl CasperClient {
         &self,
         transaction: Transaction,
     ) -> Result<TransactionHash, CasperClientError> {
-        let response = casper_client::put_transaction(
-            next_rpc_id(),
-            self.rpc_endpoint(),
-            self.verbosity,
-            transaction,
-        )
-        .await?;
+        let response = self
+            .rpc(
+                "account_put_transaction",
+                Some(transaction.serialized_length()),
+                |rpc_id| {
+                    casper_client::put_transaction(
+                        rpc_id,
+                        self.rpc_endpoint(),
+                        self.verbosity,
+                        transaction,
+                    )
+                },
+            )
+            .await?;
         Ok(response.result.transaction_hash)
     }
 
+    /// Polls the transaction until it is executed and returns its execution result.
+    ///
+    /// Fails with [`CasperClientError::ExecutionTimeout`] when the transaction isn't executed
+    /// within `timeout`.
+    pub async fn wait_for_execution(
+        &self,
+        transaction_hash: TransactionHash,
+        timeout: Duration,
+    ) -> Result<ExecutionResult, CasperClientError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // The node doesn't know the transaction until it has been gossiped.
+            match self.get_transaction(transaction_hash, false).await {
+                Ok(result) => {
+                    let execution_result =
+                        result.execution_info.and_then(|info| info.execution_result);
+                    if let Some(execution_result) = execution_result {
+                        return Ok(execution_result);
+                    }
+                }
+                Err(CasperClientError::Client(_)) => {}
+                Err(error) => return Err(error),
+            }
+            if Instant::now() + EXECUTION_POLL_INTERVAL > deadline {
+                return Err(CasperClientError::ExecutionTimeout(transaction_hash));
+            }
+            trace!(%transaction_hash, "transaction not executed yet");
+            tokio::time::sleep(EXECUTION_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Calls `entry_point` of the stored contract, waits for the call to be executed and decodes
+    /// its outcome.
+    ///
+    /// The transaction is signed by `key` for the network the client is connected to and paid
+    /// with `opts`, then waited for up to [`DEFAULT_EXECUTION_TIMEOUT`]. A revert is decoded
+    /// into the [`ApiError`] the contract reverted with.
+    ///
+    /// Nodes don't report the values entry points return to transactions, so the `value` of a
+    /// successful outcome is `None` here, see [`ExecutionOutcome::Success`].
+    pub async fn call_and_wait<T: FromBytes + CLTyped>(
+        &self,
+        contract_hash: ContractHash,
+        entry_point: &str,
+        args: RuntimeArgs,
+        key: &SecretKey,
+        opts: &SessionOptions,
+    ) -> Result<ExecutionOutcome<T>, CasperClientError> {
+        let chain_name = self.get_network_name().await?;
+        let transaction =
+            stored_contract_transaction(contract_hash, entry_point, args, opts, &chain_name, key);
+        let transaction_hash = self.put_transaction(transaction).await?;
+        let execution_result = self
+            .wait_for_execution(transaction_hash, DEFAULT_EXECUTION_TIMEOUT)
+            .await?;
+        Ok(ExecutionOutcome::from_execution_result(
+            &execution_result,
+            None,
+        )?)
+    }
+
     /// Fetches the transaction status for the provided transaction hash.
     pub async fn get_transaction(
         &self,
         transaction_hash: TransactionHash,
         finalized_approvals: bool,
     ) -> Result<GetTransactionResult, CasperClientError> {
-        let response = casper_client::get_transaction(
-            next_rpc_id(),
-            self.rpc_endpoint(),
-            self.verbosity,
-            transaction_hash,
-            finalized_approvals,
-        )
-        .await?;
+        let response = self
+            .rpc("info_get_transaction", None, |rpc_id| {
+                casper_client::get_transaction(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    transaction_hash,
+                    finalized_approvals,
+                )
+            })
+            .await?;
         Ok(response.result)
     }
 
@@ -150,9 +267,11 @@ impl CasperClient {
     ///
     /// NOTE: This API may change in future and provide a deserialized `Chainspec` struct instead.
     pub async fn get_chainspec(&self) -> Result<TomlValue, CasperClientError> {
-        let response =
-            casper_client::get_chainspec(next_rpc_id(), self.rpc_endpoint(), self.verbosity)
-                .await?;
+        let response = self
+            .rpc("info_get_chainspec", None, |rpc_id| {
+                casper_client::get_chainspec(rpc_id, self.rpc_endpoint(), self.verbosity)
+            })
+            .await?;
         parse_chainspec(&response.result)
     }
 
@@ -167,34 +286,556 @@ impl CasperClient {
             .ok_or(CasperClientError::MissingNetworkName)
     }
 
+    /// Reads the limits contracts are validated against from the chainspec, see
+    /// [`NetworkLimits::from_chainspec_toml`].
+    pub async fn get_network_limits(&self) -> Result<NetworkLimits, CasperClientError> {
+        let chainspec = self.get_chainspec().await?;
+        Ok(NetworkLimits::from_chainspec_toml(&chainspec)?)
+    }
+
     /// Performs a speculative execution of the provided transaction.
     pub async fn speculative_exec_txn(
         &self,
         transaction: Transaction,
     ) -> Result<SpeculativeExecTxnResult, CasperClientError> {
-        let response = casper_client::speculative_exec_txn(
-            next_rpc_id(),
-            self.rpc_endpoint(),
-            self.verbosity,
-            transaction,
-        )
-        .await?;
+        let response = self
+            .rpc(
+                "speculative_exec_txn",
+                Some(transaction.serialized_length()),
+                |rpc_id| {
+                    casper_client::speculative_exec_txn(
+                        rpc_id,
+                        self.rpc_endpoint(),
+                        self.verbosity,
+                        transaction,
+                    )
+                },
+            )
+            .await?;
         Ok(response.result)
     }
 
+    /// Pre-flights a transaction with a speculative execution and decodes its outcome.
+    ///
+    /// Unlike [`speculative_exec_txn`](Self::speculative_exec_txn) this maps a revert back to the
+    /// [`ApiError`] the contract reverted with, so callers can match on it directly.
+    pub async fn dry_run(
+        &self,
+        transaction: Transaction,
+    ) -> Result<DryRunOutcome, CasperClientError> {
+        let result = self.speculative_exec_txn(transaction).await?;
+        Ok(DryRunOutcome::from_execution_result(
+            &result.execution_result,
+        ))
+    }
+
+    /// Looks up a named key of the provided account.
+    ///
+    /// Accounts migrated to addressable entities (2.x) are looked up via `state_get_entity`.
+    /// Returns `None` if either the account or the named key does not exist.
+    pub async fn get_account_named_key(
+        &self,
+        account_identifier: AccountIdentifier,
+        name: &str,
+    ) -> Result<Option<Key>, CasperClientError> {
+        if let Some(result) = self.get_account(account_identifier.clone()).await? {
+            return Ok(result.account.named_keys().get(name).copied());
+        }
+
+        let entity_identifier = match account_identifier {
+            AccountIdentifier::PublicKey(public_key) => EntityIdentifier::PublicKey(public_key),
+            AccountIdentifier::AccountHash(account_hash) => {
+                EntityIdentifier::AccountHash(account_hash)
+            }
+        };
+        match self
+            .rpc("state_get_entity", None, |rpc_id| {
+                casper_client::get_entity(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    None,
+                    entity_identifier,
+                )
+            })
+            .await
+        {
+            Ok(response) => {
+                let named_keys = match &response.result.entity_result {
+                    EntityOrAccount::AddressableEntity(entity) => &entity.named_keys,
+                    EntityOrAccount::LegacyAccount(account) => account.named_keys(),
+                };
+                Ok(named_keys.get(name).copied())
+            }
+            // 1.x nodes don't know `state_get_entity`, the account is simply missing there.
+            Err(CasperClientRpcError::ResponseIsRpcError { error, .. })
+                if error.code == METHOD_NOT_FOUND_CODE
+                    || is_missing_account_error(error.code, &error.message) =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Queries the value stored under `key` at the latest state root hash.
+    pub async fn query_stored_value(&self, key: Key) -> Result<StoredValue, CasperClientError> {
+        let state_root_hash = self.get_state_root_hash().await?;
+        let response = self
+            .rpc("query_global_state", None, |rpc_id| {
+                casper_client::query_global_state(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    GlobalStateIdentifier::StateRootHash(state_root_hash),
+                    key,
+                    Vec::new(),
+                )
+            })
+            .await?;
+        Ok(response.result.stored_value)
+    }
+
+    /// Queries the item stored under `item_key` in the dictionary of `seed_uref` at the latest
+    /// state root hash.
+    ///
+    /// The node unwraps the item, so this returns the `CLValue` the contract wrote rather than the
+    /// `DictionaryValue` stored on-chain.
+    pub async fn query_dictionary_item(
+        &self,
+        seed_uref: URef,
+        item_key: &str,
+    ) -> Result<CLValue, CasperClientError> {
+        let state_root_hash = self.get_state_root_hash().await?;
+        self.query_dictionary_item_at(state_root_hash, seed_uref, item_key)
+            .await
+    }
+
+    /// Queries the item stored under `item_key` in the dictionary of `seed_uref` at
+    /// `state_root_hash`, like [`Self::query_dictionary_item`].
+    pub async fn query_dictionary_item_at(
+        &self,
+        state_root_hash: Digest,
+        seed_uref: URef,
+        item_key: &str,
+    ) -> Result<CLValue, CasperClientError> {
+        let response = self
+            .rpc("state_get_dictionary_item", None, |rpc_id| {
+                casper_client::get_dictionary_item(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    state_root_hash,
+                    DictionaryItemIdentifier::URef {
+                        seed_uref,
+                        dictionary_item_key: item_key.to_string(),
+                    },
+                )
+            })
+            .await?;
+        match response.result.stored_value {
+            StoredValue::CLValue(cl_value) => Ok(cl_value),
+            other => Err(CasperClientError::UnexpectedStoredValue {
+                key: Key::dictionary(seed_uref, item_key.as_bytes()).to_string(),
+                expected: "CLValue",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    /// Queries the value stored under `key` like [`Self::query_stored_value`] and renders it for
+    /// humans with [`clvalue_debug::render_stored_value`].
+    pub async fn query_rendered(&self, key: Key) -> Result<String, CasperClientError> {
+        let stored_value = self.query_stored_value(key).await?;
+        Ok(clvalue_debug::render_stored_value(&stored_value))
+    }
+
+    /// Fetches the contract package stored under `package_key`.
+    ///
+    /// Accepts both a 1.x `ContractPackage` and a 2.x `Package`.
+    pub async fn get_contract_package(
+        &self,
+        package_key: Key,
+    ) -> Result<ContractPackageInfo, CasperClientError> {
+        let stored_value = self.query_stored_value(package_key).await?;
+        contract_package_from_stored_value(package_key, stored_value)
+    }
+
+    /// Fetches the contract stored under `contract_key`.
+    ///
+    /// `Key::AddressableEntity` keys are fetched via `state_get_entity` so the entity comes back
+    /// with its named keys; any other key is expected to hold a 1.x `Contract`.
+    pub async fn get_contract(&self, contract_key: Key) -> Result<ContractInfo, CasperClientError> {
+        let Key::AddressableEntity(entity_addr) = contract_key else {
+            let stored_value = self.query_stored_value(contract_key).await?;
+            return contract_from_stored_value(contract_key, stored_value);
+        };
+
+        let response = self
+            .rpc("state_get_entity", None, |rpc_id| {
+                casper_client::get_entity(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    None,
+                    EntityIdentifier::EntityAddr(entity_addr),
+                )
+            })
+            .await?;
+        match response.result.entity_result {
+            EntityOrAccount::AddressableEntity(entity) => {
+                Ok(ContractInfo::Entity(Box::new(entity)))
+            }
+            EntityOrAccount::LegacyAccount(_) => Err(CasperClientError::UnexpectedStoredValue {
+                key: contract_key.to_string(),
+                expected: "AddressableEntity",
+                found: "Account".into(),
+            }),
+        }
+    }
+
+    /// Compares the entry points of the contract stored under `contract_key` with `entry_points`,
+    /// typically the `contract::entry_points()` of the version about to be installed.
+    ///
+    /// Check [`EntryPointsDiff::is_backwards_compatible`] before submitting an upgrade.
+    pub async fn diff_entry_points(
+        &self,
+        contract_key: Key,
+        entry_points: &EntryPoints,
+    ) -> Result<EntryPointsDiff, CasperClientError> {
+        let contract = self.get_contract(contract_key).await?;
+        Ok(entry_points_diff::diff(
+            &contract.entry_points(),
+            entry_points,
+        ))
+    }
+
+    /// Resolves the newest enabled contract of the package stored under an account's named key.
+    ///
+    /// Follows account named key -> contract package -> contract, and returns the contract hash
+    /// together with the contract's named keys. Errors are wrapped in
+    /// [`CasperClientError::Resolve`] naming the hop that failed.
+    pub async fn resolve_latest_contract(
+        &self,
+        account_identifier: AccountIdentifier,
+        package_named_key: &str,
+    ) -> Result<(ContractHash, NamedKeys), CasperClientError> {
+        let step = ResolveStep::AccountNamedKey(package_named_key.into());
+        let package_key = self
+            .get_account_named_key(account_identifier, package_named_key)
+            .await
+            .and_then(|key| {
+                key.ok_or_else(|| CasperClientError::MissingNamedKey(package_named_key.into()))
+            })
+            .map_err(|error| error.at(step))?;
+
+        let step = ResolveStep::ContractPackage(package_key);
+        let contract_key = self
+            .get_contract_package(package_key)
+            .await
+            .and_then(|package| {
+                package
+                    .latest_contract_key()
+                    .ok_or(CasperClientError::NoEnabledVersion)
+            })
+            .map_err(|error| error.at(step))?;
+
+        let step = ResolveStep::Contract(contract_key);
+        let contract = self
+            .get_contract(contract_key)
+            .await
+            .map_err(|error| error.at(step))?;
+        Ok((contract_hash_of(contract_key), contract.into_named_keys()))
+    }
+
     pub async fn get_block(
         &self,
         block_identifier: Option<BlockIdentifier>,
     ) -> Result<GetBlockResult, CasperClientError> {
-        let response = casper_client::get_block(
-            next_rpc_id(),
-            self.rpc_endpoint(),
-            self.verbosity,
-            block_identifier,
-        )
-        .await?;
+        let response = self
+            .rpc("chain_get_block", None, |rpc_id| {
+                casper_client::get_block(
+                    rpc_id,
+                    self.rpc_endpoint(),
+                    self.verbosity,
+                    block_identifier,
+                )
+            })
+            .await?;
         Ok(response.result)
     }
+
+    /// Returns the block at `height`, for queries against historical state.
+    pub async fn get_block_by_height(
+        &self,
+        height: u64,
+    ) -> Result<GetBlockResult, CasperClientError> {
+        self.get_block(Some(height_identifier(height))).await
+    }
+
+    /// Returns the block with the given hash, for queries against historical state.
+    pub async fn get_block_by_hash(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<GetBlockResult, CasperClientError> {
+        self.get_block(Some(hash_identifier(block_hash))).await
+    }
+
+    /// Runs a JSON-RPC call with a fresh request id inside a `jsonrpc_request` span, logging its
+    /// elapsed time and outcome at debug level.
+    ///
+    /// `payload_size` is the serialized size of the transaction sent by the call, if any.
+    async fn rpc<T, F>(
+        &self,
+        method: &'static str,
+        payload_size: Option<usize>,
+        call: impl FnOnce(JsonRpcId) -> F,
+    ) -> Result<T, CasperClientRpcError>
+    where
+        F: Future<Output = Result<T, CasperClientRpcError>>,
+    {
+        let rpc_id = next_rpc_id();
+        let span = debug_span!(
+            "jsonrpc_request",
+            endpoint = %self.rpc_endpoint,
+            method,
+            rpc_id = %rpc_id,
+            payload_size = field::Empty,
+        );
+        if let Some(payload_size) = payload_size {
+            span.record("payload_size", payload_size);
+        }
+        async move {
+            trace!("sending request");
+            let start = Instant::now();
+            let result = call(rpc_id).await;
+            match &result {
+                Ok(_) => debug!(elapsed = ?start.elapsed(), "request succeeded"),
+                Err(error) => debug!(elapsed = ?start.elapsed(), %error, "request failed"),
+            }
+            result
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+fn height_identifier(height: u64) -> BlockIdentifier {
+    BlockIdentifier::Height(height)
+}
+
+fn hash_identifier(block_hash: BlockHash) -> BlockIdentifier {
+    BlockIdentifier::Hash(block_hash)
+}
+
+/// Submits the transactions of the generated `HostClient`s, waiting up to
+/// [`DEFAULT_EXECUTION_TIMEOUT`] for their execution.
+impl SubmitTransaction for CasperClient {
+    type Error = CasperClientError;
+
+    async fn submit_and_wait(
+        &self,
+        transaction: Transaction,
+    ) -> Result<ExecutionResult, CasperClientError> {
+        let transaction_hash = self.put_transaction(transaction).await?;
+        self.wait_for_execution(transaction_hash, DEFAULT_EXECUTION_TIMEOUT)
+            .await
+    }
+}
+
+/// How long [`CasperClient::submit_and_wait`](SubmitTransaction::submit_and_wait) waits for a
+/// transaction to be executed, about two minute-long eras worth of blocks on a local network.
+pub const DEFAULT_EXECUTION_TIMEOUT: Duration = Duration::from_secs(180);
+const EXECUTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Decoded outcome of a speculative execution, see [`CasperClient::dry_run`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DryRunOutcome {
+    /// The transaction executed successfully.
+    Success { gas: Gas },
+    /// The transaction reverted with the given error.
+    ///
+    /// `user_error` carries the raw code when `api_error` is [`ApiError::User`].
+    Reverted {
+        api_error: ApiError,
+        user_error: Option<u16>,
+        gas: Gas,
+    },
+    /// The transaction failed for a reason other than a revert, e.g. running out of gas.
+    Failed { error_message: String, gas: Gas },
+}
+
+impl DryRunOutcome {
+    /// Decodes the outcome of a speculative execution result.
+    pub fn from_execution_result(result: &SpeculativeExecutionResult) -> Self {
+        let gas = result.consumed;
+        match &result.error {
+            None => DryRunOutcome::Success { gas },
+            Some(error_message) => match parse_revert_message(error_message) {
+                Some(api_error) => DryRunOutcome::Reverted {
+                    api_error,
+                    user_error: match api_error {
+                        ApiError::User(code) => Some(code),
+                        _ => None,
+                    },
+                    gas,
+                },
+                None => DryRunOutcome::Failed {
+                    error_message: error_message.clone(),
+                    gas,
+                },
+            },
+        }
+    }
+
+    /// Returns the gas consumed by the execution.
+    pub fn gas(&self) -> Gas {
+        match self {
+            DryRunOutcome::Success { gas }
+            | DryRunOutcome::Reverted { gas, .. }
+            | DryRunOutcome::Failed { gas, .. } => *gas,
+        }
+    }
+}
+
+/// Decoded outcome of an executed contract call, see [`CasperClient::call_and_wait`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionOutcome<T> {
+    /// The call executed successfully.
+    ///
+    /// `value` is the value the entry point returned with `runtime::ret`, when known. The
+    /// execution results of nodes don't carry it, unlike those of the execution engine used by
+    /// engine tests.
+    Success { value: Option<T>, gas: Gas },
+    /// The call reverted with the given error.
+    ///
+    /// `user_error` carries the raw code when `api_error` is [`ApiError::User`].
+    Reverted {
+        api_error: ApiError,
+        user_error: Option<u16>,
+        gas: Gas,
+    },
+    /// The call failed for a reason other than a revert, e.g. running out of gas.
+    Failed { error_message: String, gas: Gas },
+}
+
+impl<T: FromBytes + CLTyped> ExecutionOutcome<T> {
+    /// Decodes the outcome of an execution result, along with the value `returned` by the entry
+    /// point, if known.
+    ///
+    /// Fails if `returned` doesn't hold a `T`.
+    pub fn from_execution_result(
+        result: &ExecutionResult,
+        returned: Option<CLValue>,
+    ) -> Result<Self, CLValueError> {
+        let gas = Gas::new(result.consumed());
+        let Some(error_message) = result.error_message() else {
+            let value = returned.map(CLValue::into_t).transpose()?;
+            return Ok(ExecutionOutcome::Success { value, gas });
+        };
+        Ok(match decode_execution_error(result) {
+            Some(api_error) => ExecutionOutcome::Reverted {
+                api_error,
+                user_error: match api_error {
+                    ApiError::User(code) => Some(code),
+                    _ => None,
+                },
+                gas,
+            },
+            None => ExecutionOutcome::Failed { error_message, gas },
+        })
+    }
+
+    /// Returns the gas consumed by the execution.
+    pub fn gas(&self) -> Gas {
+        match self {
+            ExecutionOutcome::Success { gas, .. }
+            | ExecutionOutcome::Reverted { gas, .. }
+            | ExecutionOutcome::Failed { gas, .. } => *gas,
+        }
+    }
+}
+
+/// A contract package as stored on either side of the 2.0 (Condor) upgrade.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContractPackageInfo {
+    /// A 1.x `ContractPackage`.
+    Legacy(Box<ContractPackage>),
+    /// A 2.x `Package`.
+    Package(Box<Package>),
+}
+
+impl ContractPackageInfo {
+    /// Returns the key of the newest enabled contract version, if any.
+    ///
+    /// This is a `Key::Hash` for 1.x packages and a `Key::AddressableEntity` for 2.x packages.
+    pub fn latest_contract_key(&self) -> Option<Key> {
+        match self {
+            ContractPackageInfo::Legacy(package) => package.current_contract_hash().map(Key::from),
+            ContractPackageInfo::Package(package) => package.current_entity_hash().map(Key::from),
+        }
+    }
+}
+
+/// A contract as stored on either side of the 2.0 (Condor) upgrade.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContractInfo {
+    /// A 1.x `Contract`.
+    Legacy(Box<Contract>),
+    /// A 2.x addressable entity along with its named keys.
+    Entity(Box<AddressableEntity>),
+}
+
+impl ContractInfo {
+    /// Returns the contract's named keys.
+    pub fn named_keys(&self) -> &NamedKeys {
+        match self {
+            ContractInfo::Legacy(contract) => contract.named_keys(),
+            ContractInfo::Entity(entity) => &entity.named_keys,
+        }
+    }
+
+    /// Returns the contract's entry points, converting those of a 1.x `Contract`.
+    pub fn entry_points(&self) -> EntryPoints {
+        match self {
+            ContractInfo::Legacy(contract) => contract.entry_points().clone().into(),
+            ContractInfo::Entity(entity) => entity
+                .entry_points
+                .iter()
+                .map(|EntryPointValue::V1CasperVm(entry_point)| entry_point.clone())
+                .collect::<Vec<_>>()
+                .into(),
+        }
+    }
+
+    /// Consumes the contract and returns its named keys.
+    pub fn into_named_keys(self) -> NamedKeys {
+        match self {
+            ContractInfo::Legacy(contract) => contract.take_named_keys(),
+            ContractInfo::Entity(entity) => entity.named_keys,
+        }
+    }
+}
+
+/// A hop of [`CasperClient::resolve_latest_contract`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveStep {
+    /// Reading the package key from the account's named keys.
+    AccountNamedKey(String),
+    /// Reading the contract package.
+    ContractPackage(Key),
+    /// Reading the newest enabled contract of the package.
+    Contract(Key),
+}
+
+impl fmt::Display for ResolveStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveStep::AccountNamedKey(name) => write!(f, "account named key \"{name}\""),
+            ResolveStep::ContractPackage(key) => write!(f, "contract package {key}"),
+            ResolveStep::Contract(key) => write!(f, "contract {key}"),
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -209,14 +850,60 @@ pub enum CasperClientError {
     MissingStateRootHash,
     #[error("missing network name in chainspec")]
     MissingNetworkName,
+    #[error("invalid network limits in chainspec: {0}")]
+    NetworkLimits(#[from] LimitsError),
     #[error("failed to load or parse secret key: {0}")]
     SecretKey(#[from] ErrorExt),
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("failed to encode or decode the state dump: {0}")]
+    StateDump(#[from] serde_json::Error),
     #[error("transaction builder error: {0}")]
     TransactionBuild(#[from] TransactionV1BuilderError),
     #[error("blocking task error: {0}")]
     TaskJoin(#[from] tokio::task::JoinError),
+    #[error("transaction {0} was not executed in time")]
+    ExecutionTimeout(TransactionHash),
+    #[error("failed to decode the returned value: {0}")]
+    ReturnedValue(CLValueError),
+    #[error("named key \"{0}\" not found")]
+    MissingNamedKey(String),
+    #[error("contract package has no enabled versions")]
+    NoEnabledVersion,
+    #[error("expected {expected} under {key}, found {found}")]
+    UnexpectedStoredValue {
+        key: String,
+        expected: &'static str,
+        found: String,
+    },
+    #[error("failed to resolve {step}: {source}")]
+    Resolve {
+        step: ResolveStep,
+        source: Box<CasperClientError>,
+    },
+}
+
+impl CasperClientError {
+    /// Wraps the error with the resolution hop it happened at.
+    fn at(self, step: ResolveStep) -> Self {
+        Self::Resolve {
+            step,
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns whether the node reported that the queried value doesn't exist, e.g. a dictionary
+    /// item that was never written.
+    pub fn is_missing_value(&self) -> bool {
+        matches!(
+            self,
+            Self::Client(error) if matches!(
+                **error,
+                CasperClientRpcError::ResponseIsRpcError { ref error, .. }
+                    if error.code == QUERY_FAILED_CODE
+            )
+        )
+    }
 }
 
 impl From<CasperClientRpcError> for CasperClientError {
@@ -225,6 +912,12 @@ impl From<CasperClientRpcError> for CasperClientError {
     }
 }
 
+impl From<CLValueError> for CasperClientError {
+    fn from(value: CLValueError) -> Self {
+        Self::ReturnedValue(value)
+    }
+}
+
 /// Generates the next JSONRPC ID.
 fn next_rpc_id() -> JsonRpcId {
     let value: i64 = rand::rng().random();
@@ -236,6 +929,57 @@ fn parse_chainspec(result: &GetChainspecResult) -> Result<TomlValue, CasperClien
     toml::de::from_slice(result.chainspec_bytes.chainspec_bytes()).map_err(Into::into)
 }
 
+/// Unwraps a contract package from a 1.x `ContractPackage` or a 2.x `SmartContract` value.
+fn contract_package_from_stored_value(
+    key: Key,
+    stored_value: StoredValue,
+) -> Result<ContractPackageInfo, CasperClientError> {
+    match stored_value {
+        StoredValue::ContractPackage(package) => Ok(ContractPackageInfo::Legacy(Box::new(package))),
+        StoredValue::SmartContract(package) => Ok(ContractPackageInfo::Package(Box::new(package))),
+        other => Err(CasperClientError::UnexpectedStoredValue {
+            key: key.to_string(),
+            expected: "ContractPackage or SmartContract",
+            found: other.type_name(),
+        }),
+    }
+}
+
+/// Unwraps a 1.x `Contract` value.
+///
+/// 2.x entities are fetched with their named keys via `state_get_entity` instead, see
+/// [`CasperClient::get_contract`].
+fn contract_from_stored_value(
+    key: Key,
+    stored_value: StoredValue,
+) -> Result<ContractInfo, CasperClientError> {
+    match stored_value {
+        StoredValue::Contract(contract) => Ok(ContractInfo::Legacy(Box::new(contract))),
+        other => Err(CasperClientError::UnexpectedStoredValue {
+            key: key.to_string(),
+            expected: "Contract",
+            found: other.type_name(),
+        }),
+    }
+}
+
+/// Returns the contract hash addressed by a key returned from
+/// [`ContractPackageInfo::latest_contract_key`].
+fn contract_hash_of(contract_key: Key) -> ContractHash {
+    match contract_key {
+        Key::AddressableEntity(entity_addr) => ContractHash::new(entity_addr.value()),
+        Key::Hash(hash) => ContractHash::new(hash),
+        other => unreachable!("{other} is not a contract key"),
+    }
+}
+
+/// JSON-RPC error code for an unknown method, returned by nodes predating an RPC.
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
+/// JSON-RPC error code of a global state query finding no value, see
+/// [`CasperClientError::is_missing_value`].
+const QUERY_FAILED_CODE: i64 = -32003;
+
 /// Determines if the provided error code and message indicate a missing account.
 ///
 /// Kind of hacky, but may be improved in the future with better error codes from the node.
@@ -285,10 +1029,262 @@ mod tests {
         assert!(!is_missing_account_error(0, "other error"));
     }
 
+    #[test]
+    fn test_block_identifiers() {
+        assert_eq!(height_identifier(42), BlockIdentifier::Height(42));
+        assert_eq!(
+            serde_json::to_value(height_identifier(42)).unwrap(),
+            serde_json::json!({ "Height": 42 })
+        );
+
+        let block_hash = BlockHash::new(Digest::from([7; Digest::LENGTH]));
+        assert_eq!(
+            hash_identifier(block_hash),
+            BlockIdentifier::Hash(block_hash)
+        );
+        assert_eq!(
+            serde_json::to_value(hash_identifier(block_hash)).unwrap(),
+            serde_json::json!({ "Hash": "07".repeat(Digest::LENGTH) })
+        );
+    }
+
+    /// Collects the output of a `tracing_subscriber::fmt` subscriber.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_calls_are_traced() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let client = CasperClient::new("http://localhost:11101");
+        client
+            .rpc("account_put_transaction", Some(42), |_| async { Ok(()) })
+            .await
+            .unwrap();
+        client
+            .rpc("info_get_status", None, |_| async {
+                Err::<(), _>(CasperClientRpcError::InvalidRpcResponse {
+                    rpc_id: JsonRpcId::from(1),
+                    rpc_method: "info_get_status",
+                    response_kind: "result",
+                    response: serde_json::Value::Null,
+                    source: None,
+                })
+            })
+            .await
+            .unwrap_err();
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            logs.contains(
+                "jsonrpc_request{endpoint=http://localhost:11101 method=\"account_put_transaction\""
+            ),
+            "{logs}"
+        );
+        assert!(logs.contains("payload_size=42"), "{logs}");
+        assert!(logs.contains("request succeeded"), "{logs}");
+        assert!(logs.contains("method=\"info_get_status\""), "{logs}");
+        assert!(logs.contains("request failed"), "{logs}");
+    }
+
     #[test]
     fn test_casper_client_new_success() {
         let client = CasperClient::new("http://localhost:11101");
         assert_eq!(client.rpc_endpoint(), "http://localhost:11101");
+        assert_eq!(client.verbosity(), Verbosity::Low);
+    }
+
+    #[test]
+    fn test_casper_client_verbosity() {
+        let mut client =
+            CasperClient::new("http://localhost:11101").with_verbosity(Verbosity::High);
+        assert_eq!(client.verbosity(), Verbosity::High);
+        client.set_verbosity(Verbosity::Medium);
+        assert_eq!(client.verbosity(), Verbosity::Medium);
+        assert_eq!(client.rpc_endpoint(), "http://localhost:11101");
+    }
+
+    fn speculative_result(error: Option<String>) -> SpeculativeExecutionResult {
+        SpeculativeExecutionResult {
+            block_hash: Default::default(),
+            transfers: Vec::new(),
+            limit: Gas::new(1_000_000u64),
+            consumed: Gas::new(12_345u64),
+            effects: Default::default(),
+            messages: Vec::new(),
+            error,
+        }
+    }
+
+    #[test]
+    fn test_dry_run_outcome_success() {
+        let outcome = DryRunOutcome::from_execution_result(&speculative_result(None));
+        assert_eq!(
+            outcome,
+            DryRunOutcome::Success {
+                gas: Gas::new(12_345u64)
+            }
+        );
+    }
+
+    #[test]
+    fn test_dry_run_outcome_user_error() {
+        let message = ApiError::User(60001).to_string();
+        let outcome = DryRunOutcome::from_execution_result(&speculative_result(Some(message)));
+        assert_eq!(
+            outcome,
+            DryRunOutcome::Reverted {
+                api_error: ApiError::User(60001),
+                user_error: Some(60001),
+                gas: Gas::new(12_345u64),
+            }
+        );
+    }
+
+    fn execution_result(error_message: Option<String>) -> ExecutionResult {
+        ExecutionResult::V2(Box::new(casper_types::execution::ExecutionResultV2 {
+            initiator: casper_types::InitiatorAddr::AccountHash(
+                casper_types::account::AccountHash::new([1; 32]),
+            ),
+            error_message,
+            current_price: 1,
+            limit: Gas::new(1_000_000u64),
+            consumed: Gas::new(12_345u64),
+            cost: U512::from(12_345u64),
+            refund: U512::zero(),
+            transfers: Vec::new(),
+            size_estimate: 0,
+            effects: Default::default(),
+        }))
+    }
+
+    #[test]
+    fn test_execution_outcome_success_decodes_the_returned_value() {
+        let returned = CLValue::from_t(42u64).unwrap();
+        let outcome =
+            ExecutionOutcome::<u64>::from_execution_result(&execution_result(None), Some(returned))
+                .unwrap();
+        assert_eq!(
+            outcome,
+            ExecutionOutcome::Success {
+                value: Some(42),
+                gas: Gas::new(12_345u64),
+            }
+        );
+
+        let outcome =
+            ExecutionOutcome::<u64>::from_execution_result(&execution_result(None), None).unwrap();
+        assert_eq!(
+            outcome,
+            ExecutionOutcome::Success {
+                value: None,
+                gas: Gas::new(12_345u64),
+            }
+        );
+
+        let returned = CLValue::from_t("not a u64").unwrap();
+        assert!(matches!(
+            ExecutionOutcome::<u64>::from_execution_result(&execution_result(None), Some(returned)),
+            Err(CLValueError::Type(_))
+        ));
+    }
+
+    #[test]
+    fn test_execution_outcome_revert_decodes_the_api_error() {
+        let message = ApiError::User(60001).to_string();
+        let outcome =
+            ExecutionOutcome::<u64>::from_execution_result(&execution_result(Some(message)), None)
+                .unwrap();
+        assert_eq!(
+            outcome,
+            ExecutionOutcome::Reverted {
+                api_error: ApiError::User(60001),
+                user_error: Some(60001),
+                gas: Gas::new(12_345u64),
+            }
+        );
+
+        let message = ApiError::MissingArgument.to_string();
+        let outcome =
+            ExecutionOutcome::<()>::from_execution_result(&execution_result(Some(message)), None)
+                .unwrap();
+        assert_eq!(
+            outcome,
+            ExecutionOutcome::Reverted {
+                api_error: ApiError::MissingArgument,
+                user_error: None,
+                gas: Gas::new(12_345u64),
+            }
+        );
+
+        let outcome = ExecutionOutcome::<()>::from_execution_result(
+            &execution_result(Some("Out of gas error".into())),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            outcome,
+            ExecutionOutcome::Failed {
+                error_message: "Out of gas error".into(),
+                gas: Gas::new(12_345u64),
+            }
+        );
+        assert_eq!(outcome.gas(), Gas::new(12_345u64));
+    }
+
+    #[test]
+    fn test_dry_run_outcome_system_error() {
+        for api_error in [
+            ApiError::MissingArgument,
+            ApiError::InvalidCallerInfoRequest,
+            ApiError::Mint(2),
+            ApiError::HandlePayment(1),
+            ApiError::AuctionError(3),
+        ] {
+            let outcome = DryRunOutcome::from_execution_result(&speculative_result(Some(
+                api_error.to_string(),
+            )));
+            assert_eq!(
+                outcome,
+                DryRunOutcome::Reverted {
+                    api_error,
+                    user_error: None,
+                    gas: Gas::new(12_345u64),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_dry_run_outcome_non_revert_failure() {
+        let outcome = DryRunOutcome::from_execution_result(&speculative_result(Some(
+            "Out of gas error".into(),
+        )));
+        assert_eq!(
+            outcome,
+            DryRunOutcome::Failed {
+                error_message: "Out of gas error".into(),
+                gas: Gas::new(12_345u64),
+            }
+        );
     }
 
     #[test]
@@ -302,4 +1298,129 @@ mod tests {
         let error = CasperClientError::MissingNetworkName;
         assert_eq!(error.to_string(), "missing network name in chainspec");
     }
+
+    macro_rules! stored_value_fixture {
+        ($name:literal) => {
+            serde_json::from_str::<StoredValue>(include_str!(concat!(
+                "../tests/fixtures/stored_value/",
+                $name
+            )))
+            .expect("fixture should decode")
+        };
+    }
+
+    #[test]
+    fn test_legacy_contract_package_resolves_newest_enabled_version() {
+        let key = Key::Hash([4; 32]);
+        let package =
+            contract_package_from_stored_value(key, stored_value_fixture!("contract_package.json"))
+                .unwrap();
+        assert!(matches!(package, ContractPackageInfo::Legacy(_)));
+        // Version 3 is disabled, so version 2 is the newest enabled one.
+        assert_eq!(package.latest_contract_key(), Some(Key::Hash([2; 32])));
+        assert_eq!(
+            contract_hash_of(package.latest_contract_key().unwrap()),
+            ContractHash::new([2; 32])
+        );
+    }
+
+    #[test]
+    fn test_package_resolves_newest_enabled_version() {
+        let key = Key::SmartContract([4; 32]);
+        let package =
+            contract_package_from_stored_value(key, stored_value_fixture!("package.json")).unwrap();
+        assert!(matches!(package, ContractPackageInfo::Package(_)));
+        let latest = package.latest_contract_key().unwrap();
+        assert_eq!(
+            latest,
+            Key::AddressableEntity(casper_types::EntityAddr::SmartContract([2; 32]))
+        );
+        assert_eq!(contract_hash_of(latest), ContractHash::new([2; 32]));
+    }
+
+    #[test]
+    fn test_legacy_contract_exposes_named_keys() {
+        let key = Key::Hash([2; 32]);
+        let contract =
+            contract_from_stored_value(key, stored_value_fixture!("contract.json")).unwrap();
+        assert!(contract.named_keys().contains("counter"));
+        assert_eq!(contract.into_named_keys().len(), 1);
+    }
+
+    #[test]
+    fn test_legacy_contract_entry_points_diff_against_local() {
+        use casper_types::{
+            CLType, EntityEntryPoint, EntryPointAccess, EntryPointPayment, EntryPointType,
+            Parameter, ProtocolVersion, contracts::EntryPoint,
+        };
+
+        let installed = Contract::new(
+            [4; 32].into(),
+            [5; 32].into(),
+            NamedKeys::new(),
+            vec![EntryPoint::new(
+                "increment",
+                vec![],
+                CLType::U64,
+                EntryPointAccess::Public,
+                EntryPointType::Called,
+            )]
+            .into(),
+            ProtocolVersion::V2_0_0,
+        );
+        let contract = ContractInfo::Legacy(Box::new(installed));
+        let local = EntryPoints::from(vec![EntityEntryPoint::new(
+            "increment",
+            vec![Parameter::new("by", CLType::U64)],
+            CLType::U64,
+            EntryPointAccess::Public,
+            EntryPointType::Called,
+            EntryPointPayment::Caller,
+        )]);
+
+        let diff = entry_points_diff::diff(&contract.entry_points(), &local);
+        assert_eq!(diff.changed.len(), 1);
+        assert!(!diff.is_backwards_compatible());
+        assert!(
+            entry_points_diff::diff(&contract.entry_points(), &contract.entry_points()).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_unexpected_stored_value_is_rejected() {
+        let key = Key::Hash([4; 32]);
+        let error = contract_package_from_stored_value(key, stored_value_fixture!("cl_value.json"))
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            CasperClientError::UnexpectedStoredValue { ref found, .. } if found == "U64"
+        ));
+
+        let error =
+            contract_from_stored_value(key, stored_value_fixture!("package.json")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("expected Contract under {key}, found SmartContract")
+        );
+    }
+
+    #[test]
+    fn test_resolve_error_names_failing_step() {
+        let error = CasperClientError::MissingNamedKey("do_nothing_package_hash".into()).at(
+            ResolveStep::AccountNamedKey("do_nothing_package_hash".into()),
+        );
+        assert_eq!(
+            error.to_string(),
+            "failed to resolve account named key \"do_nothing_package_hash\": named key \"do_nothing_package_hash\" not found"
+        );
+
+        let key = Key::Hash([4; 32]);
+        let error = CasperClientError::NoEnabledVersion.at(ResolveStep::ContractPackage(key));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "failed to resolve contract package {key}: contract package has no enabled versions"
+            )
+        );
+    }
 }
@@ -1,7 +1,13 @@
 //! Unofficial Casper Rust SDK
 //!
 //! This crate provides utilities to interact with the Casper blockchain,
-//! including JSON-RPC client and SSE (Server-Sent Events) listener.
+//! including JSON-RPC client, SSE (Server-Sent Events) listener, CEP-18
+//! token amount helpers, readers of on-chain collections and contract state
+//! dumps for the shim.
 pub use casper_client::cli::{TransactionV1Builder, TransactionV1BuilderError};
+pub use veles_casper_contract_api::host_client;
+pub mod cep18;
+pub mod collections;
 pub mod jsonrpc;
 pub mod sse;
+pub mod state_dump;
@@ -1,10 +1,13 @@
 //! SSE (Server-Sent Events) listener for Casper blockchain
 pub mod config;
 pub mod event;
+pub mod metrics;
+pub mod watch;
 
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 use async_stream::stream;
+use casper_types::Timestamp;
 use futures::StreamExt;
 use reqwest_eventsource::{Event, EventSource};
 use thiserror::Error;
@@ -12,7 +15,11 @@ use tokio::sync::mpsc;
 use tracing::{debug, error, info, trace, warn};
 use url::Url;
 
-use crate::sse::{config::ListenerConfig, event::SseEvent};
+use crate::sse::{
+    config::ListenerConfig,
+    event::SseEvent,
+    metrics::{ListenerMetrics, event_kind, event_lag},
+};
 
 #[derive(Debug, Error)]
 pub enum ListenerError {
@@ -30,6 +37,8 @@ pub enum ListenerError {
     EventSource(#[from] reqwest_eventsource::Error),
     #[error("blocking task error: {0}")]
     TaskJoin(#[from] tokio::task::JoinError),
+    #[error("SSE stream ended")]
+    StreamEnded,
 }
 
 pub async fn listener(
@@ -69,35 +78,29 @@ pub async fn listener(
 
     let endpoint_url = url.to_string();
     let (tx, mut rx) = mpsc::channel::<Result<SseEvent, ListenerError>>(256);
-    let (raw_tx, mut raw_rx) = mpsc::channel::<String>(256);
+    let (raw_tx, raw_rx) = mpsc::channel::<String>(256);
 
-    let parse_sender = tx.clone();
+    let metrics = config.metrics().clone();
 
     // Task to parse raw event data into SseEvent
-    tokio::spawn(async move {
-        while let Some(data) = raw_rx.recv().await {
-            let parse_result = match tokio::task::spawn_blocking(move || {
-                let head = data.chars().take(100).collect::<String>();
-                serde_json::from_str::<SseEvent>(&data)
-                    .map_err(|source| ListenerError::Decode { head, source })
-            })
-            .await
-            {
-                Ok(result) => result,
-                Err(err) => Err(ListenerError::TaskJoin(err)),
-            };
-            let _ = parse_sender.send(parse_result).await;
-        }
-    });
+    tokio::spawn(parse_messages(raw_rx, tx.clone(), metrics.clone()));
 
     // Task to receive events from the SSE endpoint
     tokio::spawn(async move {
         let mut es = EventSource::get(endpoint_url);
+        let mut opened = false;
+        let mut reconnects = 0;
         trace!("Starting to receive events");
 
         while let Some(event) = es.next().await {
             match event {
+                Ok(Event::Open) if opened => {
+                    reconnects += 1;
+                    info!("Connection reopened");
+                    metrics.on_reconnect(reconnects);
+                }
                 Ok(Event::Open) => {
+                    opened = true;
                     info!("Connection opened");
                 }
                 Ok(Event::Message(message)) => {
@@ -143,3 +146,134 @@ pub async fn listener(
         }
     })
 }
+
+/// Parses the raw messages of `raw_rx` in order, sending the results to `events`.
+///
+/// Every message is reported to `metrics` before it is decoded, so that the kinds of events are
+/// known even for those that fail to decode or that consumers skip.
+async fn parse_messages(
+    mut raw_rx: mpsc::Receiver<String>,
+    events: mpsc::Sender<Result<SseEvent, ListenerError>>,
+    metrics: Arc<dyn ListenerMetrics>,
+) {
+    while let Some(data) = raw_rx.recv().await {
+        let metrics = metrics.clone();
+        let parse_result = match tokio::task::spawn_blocking(move || {
+            parse_message(&data, metrics.as_ref()).map_err(|source| {
+                let head = data.chars().take(100).collect::<String>();
+                ListenerError::Decode { head, source }
+            })
+        })
+        .await
+        {
+            Ok(result) => result,
+            Err(err) => Err(ListenerError::TaskJoin(err)),
+        };
+        if events.send(parse_result).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Decodes the raw message `data`, reporting it to `metrics`.
+fn parse_message(data: &str, metrics: &dyn ListenerMetrics) -> serde_json::Result<SseEvent> {
+    metrics.on_event(event_kind(data), data.len());
+    let event = serde_json::from_str::<SseEvent>(data).inspect_err(|_| metrics.on_parse_error())?;
+    if let Some(lag) = event_lag(&event, Timestamp::now()) {
+        metrics.on_lag(lag);
+    }
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::sse::metrics::{AtomicMetrics, MetricsSnapshot};
+
+    macro_rules! fixture {
+        ($path:literal) => {
+            include_str!(concat!("../tests/fixtures/sse/", $path))
+        };
+    }
+
+    /// A metrics hook recording the kinds of events it was told about.
+    #[derive(Default)]
+    struct Kinds(std::sync::Mutex<Vec<String>>);
+
+    impl ListenerMetrics for Kinds {
+        fn on_event(&self, kind: &str, _bytes: usize) {
+            self.0.lock().unwrap().push(kind.to_string());
+        }
+    }
+
+    /// Runs the parse task over `messages`, returning the results it sent.
+    async fn parse_all(
+        messages: &[&str],
+        metrics: Arc<dyn ListenerMetrics>,
+    ) -> Vec<Result<SseEvent, ListenerError>> {
+        let (raw_tx, raw_rx) = mpsc::channel(messages.len());
+        let (tx, mut rx) = mpsc::channel(messages.len());
+        for message in messages {
+            raw_tx.send(message.to_string()).await.unwrap();
+        }
+        drop(raw_tx);
+        parse_messages(raw_rx, tx, metrics).await;
+        let mut results = Vec::new();
+        while let Some(result) = rx.recv().await {
+            results.push(result);
+        }
+        results
+    }
+
+    #[tokio::test]
+    async fn parse_task_reports_metrics() {
+        let messages = [
+            fixture!("v2/api_version.json"),
+            fixture!("v2/block_added.json"),
+            "{\"Step\": [",
+            fixture!("v2/transaction_processed.json"),
+            "not json",
+        ];
+        let metrics = Arc::new(AtomicMetrics::default());
+        let results = parse_all(&messages, metrics.clone()).await;
+
+        assert_eq!(results.len(), 5);
+        assert!(matches!(results[1], Ok(SseEvent::BlockAdded(_))));
+        assert!(matches!(results[2], Err(ListenerError::Decode { .. })));
+        assert!(matches!(results[4], Err(ListenerError::Decode { .. })));
+        let snapshot = metrics.snapshot();
+        assert_eq!(
+            snapshot,
+            MetricsSnapshot {
+                events: 5,
+                bytes: messages.iter().map(|message| message.len() as u64).sum(),
+                parse_errors: 2,
+                reconnects: 0,
+                lag: snapshot.lag,
+            }
+        );
+        // The last lag is that of the fixture's transaction, processed long ago.
+        assert!(snapshot.lag > Duration::from_secs(24 * 60 * 60));
+    }
+
+    #[tokio::test]
+    async fn parse_task_reports_kinds_of_undecodable_events() {
+        let kinds = Arc::new(Kinds::default());
+        parse_all(
+            &[
+                fixture!("v1/shutdown.json"),
+                "{\"Step\": [",
+                fixture!("v1/deploy_expired.json"),
+                "not json",
+            ],
+            kinds.clone(),
+        )
+        .await;
+        assert_eq!(
+            *kinds.0.lock().unwrap(),
+            ["Shutdown", "Step", "DeployExpired", "Unknown"]
+        );
+    }
+}
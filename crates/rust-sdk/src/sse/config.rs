@@ -1,7 +1,12 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use thiserror::Error;
 
+use crate::sse::metrics::{ListenerMetrics, MetricsHook};
+
 #[derive(Debug, Error)]
 pub enum ListenerConfigError {
     #[error("missing SSE endpoint URL")]
@@ -12,6 +17,7 @@ pub enum ListenerConfigError {
 pub struct ListenerConfig {
     endpoint: String,
     timestamp_path: Option<PathBuf>,
+    metrics: MetricsHook,
 }
 
 impl ListenerConfig {
@@ -26,12 +32,18 @@ impl ListenerConfig {
     pub fn timestamp_path(&self) -> Option<&Path> {
         self.timestamp_path.as_deref()
     }
+
+    /// Returns the metrics the listener reports to, which record nothing unless set.
+    pub fn metrics(&self) -> &Arc<dyn ListenerMetrics> {
+        &self.metrics.0
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct ListenerConfigBuilder {
     endpoint: Option<String>,
     timestamp_path: Option<PathBuf>,
+    metrics: MetricsHook,
 }
 
 impl ListenerConfigBuilder {
@@ -49,6 +61,13 @@ impl ListenerConfigBuilder {
         self
     }
 
+    /// Reports the events, parse errors, reconnects and lag of the listener to `metrics`, see
+    /// [`metrics`](crate::sse::metrics).
+    pub fn with_metrics(mut self, metrics: Arc<dyn ListenerMetrics>) -> Self {
+        self.metrics = MetricsHook(metrics);
+        self
+    }
+
     pub fn build(self) -> Result<ListenerConfig, ListenerConfigError> {
         let endpoint = self
             .endpoint
@@ -59,6 +78,7 @@ impl ListenerConfigBuilder {
         Ok(ListenerConfig {
             endpoint,
             timestamp_path: self.timestamp_path,
+            metrics: self.metrics,
         })
     }
 }
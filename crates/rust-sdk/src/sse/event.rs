@@ -1,21 +1,23 @@
 use casper_types::{
-    Block, BlockHash, EraId, FinalitySignature, InitiatorAddr, ProtocolVersion, PublicKey,
-    TimeDiff, Timestamp, Transaction, TransactionHash, contract_messages::Messages,
-    execution::ExecutionResult,
+    Block, BlockHash, BlockV1, Deploy, DeployHash, EraId, FinalitySignature, FinalitySignatureV1,
+    InitiatorAddr, ProtocolVersion, PublicKey, TimeDiff, Timestamp, Transaction, TransactionHash,
+    contract_messages::Messages,
+    execution::{ExecutionResult, ExecutionResultV1},
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use serde_json::value::RawValue;
 
 /// Represents an event received from the Casper SSE (Server-Sent Events) stream.
+///
+/// Events of both 1.x and 2.x nodes are accepted. The `Deploy*` events are only emitted by 1.x
+/// nodes; 2.x nodes report deploys through the `Transaction*` events instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SseEvent {
     ApiVersion(ProtocolVersion),
-    DeployAccepted(serde_json::Value),
-    BlockAdded {
-        block_hash: BlockHash,
-        block: Block,
-    },
-    DeployProcessed(serde_json::Value),
-    DeployExpired(serde_json::Value),
+    DeployAccepted(LegacyEvent<Deploy>),
+    BlockAdded(BlockAddedEvent),
+    DeployProcessed(LegacyEvent<DeployProcessed>),
+    DeployExpired(LegacyEvent<DeployExpired>),
     TransactionAccepted(Transaction),
     TransactionProcessed {
         transaction_hash: TransactionHash,
@@ -39,8 +41,349 @@ pub enum SseEvent {
         // This technically is not amorphic, but this field is potentially > 30MB of size. By not
         // parsing it we make the process of intaking these messages much quicker and less memory
         // consuming.
-        execution_effects: Box<serde_json::value::RawValue>,
+        #[serde(alias = "execution_effect")]
+        execution_effects: Box<RawValue>,
     },
     Shutdown,
+    #[serde(deserialize_with = "deserialize_finality_signature")]
     FinalitySignature(FinalitySignature),
 }
+
+/// Payload of a `BlockAdded` event.
+///
+/// 1.x nodes send the block without a version tag, while 2.x nodes send a versioned [`Block`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockAddedEvent {
+    V1 {
+        block_hash: BlockHash,
+        block: Box<BlockV1>,
+    },
+    V2 {
+        block_hash: BlockHash,
+        block: Box<Block>,
+    },
+}
+
+impl BlockAddedEvent {
+    pub fn block_hash(&self) -> &BlockHash {
+        match self {
+            BlockAddedEvent::V1 { block_hash, .. } | BlockAddedEvent::V2 { block_hash, .. } => {
+                block_hash
+            }
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        match self {
+            BlockAddedEvent::V1 { block, .. } => block.height(),
+            BlockAddedEvent::V2 { block, .. } => block.height(),
+        }
+    }
+
+    pub fn era_id(&self) -> EraId {
+        match self {
+            BlockAddedEvent::V1 { block, .. } => block.era_id(),
+            BlockAddedEvent::V2 { block, .. } => block.era_id(),
+        }
+    }
+
+    /// Converts the payload into a versioned [`Block`].
+    pub fn into_block(self) -> Block {
+        match self {
+            BlockAddedEvent::V1 { block, .. } => Block::from(*block),
+            BlockAddedEvent::V2 { block, .. } => *block,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BlockAddedRef<'a, B> {
+    block_hash: &'a BlockHash,
+    block: &'a B,
+}
+
+#[derive(Deserialize)]
+struct BlockAddedValue {
+    block_hash: BlockHash,
+    block: serde_json::Value,
+}
+
+impl Serialize for BlockAddedEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BlockAddedEvent::V1 { block_hash, block } => BlockAddedRef {
+                block_hash,
+                block: block.as_ref(),
+            }
+            .serialize(serializer),
+            BlockAddedEvent::V2 { block_hash, block } => BlockAddedRef {
+                block_hash,
+                block: block.as_ref(),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockAddedEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let BlockAddedValue { block_hash, block } = BlockAddedValue::deserialize(deserializer)?;
+        if is_versioned(&block, &["Version1", "Version2"]) {
+            let block = serde_json::from_value(block).map_err(serde::de::Error::custom)?;
+            Ok(BlockAddedEvent::V2 { block_hash, block })
+        } else {
+            let block = serde_json::from_value(block).map_err(serde::de::Error::custom)?;
+            Ok(BlockAddedEvent::V1 { block_hash, block })
+        }
+    }
+}
+
+/// Payload of an event only emitted by 1.x nodes.
+///
+/// Payloads not matching the legacy schema are kept as raw JSON, so that an unexpected shape
+/// doesn't fail decoding of the whole event.
+#[derive(Debug, Clone)]
+pub enum LegacyEvent<T> {
+    Typed(Box<T>),
+    Raw(Box<RawValue>),
+}
+
+impl<T> LegacyEvent<T> {
+    /// Returns the typed payload, if it matched the legacy schema.
+    pub fn typed(&self) -> Option<&T> {
+        match self {
+            LegacyEvent::Typed(payload) => Some(payload),
+            LegacyEvent::Raw(_) => None,
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for LegacyEvent<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            LegacyEvent::Typed(payload) => payload.serialize(serializer),
+            LegacyEvent::Raw(raw) => raw.serialize(serializer),
+        }
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for LegacyEvent<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = Box::<RawValue>::deserialize(deserializer)?;
+        match serde_json::from_str(raw.get()) {
+            Ok(payload) => Ok(LegacyEvent::Typed(payload)),
+            Err(_) => Ok(LegacyEvent::Raw(raw)),
+        }
+    }
+}
+
+/// Payload of a `DeployProcessed` event of a 1.x node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployProcessed {
+    pub deploy_hash: DeployHash,
+    pub account: PublicKey,
+    pub timestamp: Timestamp,
+    pub ttl: TimeDiff,
+    pub dependencies: Vec<DeployHash>,
+    pub block_hash: BlockHash,
+    pub execution_result: ExecutionResultV1,
+}
+
+/// Payload of a `DeployExpired` event of a 1.x node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployExpired {
+    pub deploy_hash: DeployHash,
+}
+
+fn is_versioned(value: &serde_json::Value, tags: &[&str]) -> bool {
+    value
+        .as_object()
+        .is_some_and(|object| object.len() == 1 && tags.iter().any(|tag| object.contains_key(*tag)))
+}
+
+/// Accepts both the versioned signature of 2.x nodes and the unversioned one of 1.x nodes.
+fn deserialize_finality_signature<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<FinalitySignature, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if is_versioned(&value, &["V1", "V2"]) {
+        serde_json::from_value(value).map_err(serde::de::Error::custom)
+    } else {
+        serde_json::from_value::<FinalitySignatureV1>(value)
+            .map(FinalitySignature::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($path:literal) => {
+            include_str!(concat!("../../tests/fixtures/sse/", $path))
+        };
+    }
+
+    fn parse(json: &str) -> SseEvent {
+        serde_json::from_str(json).expect("fixture should decode")
+    }
+
+    fn assert_roundtrip(json: &str) {
+        let event = parse(json);
+        let expected: serde_json::Value = serde_json::from_str(json).unwrap();
+        let actual = serde_json::to_value(&event).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn api_version() {
+        for (json, version) in [
+            (
+                fixture!("v1/api_version.json"),
+                ProtocolVersion::from_parts(1, 5, 6),
+            ),
+            (fixture!("v2/api_version.json"), ProtocolVersion::V2_0_0),
+        ] {
+            assert!(matches!(parse(json), SseEvent::ApiVersion(v) if v == version));
+        }
+    }
+
+    #[test]
+    fn block_added_v1() {
+        let SseEvent::BlockAdded(block_added) = parse(fixture!("v1/block_added.json")) else {
+            panic!("expected BlockAdded");
+        };
+        assert!(matches!(block_added, BlockAddedEvent::V1 { .. }));
+        assert_eq!(block_added.height(), 2_031_075);
+        assert_eq!(block_added.era_id(), EraId::new(10_421));
+        assert_eq!(block_added.into_block().height(), 2_031_075);
+    }
+
+    #[test]
+    fn block_added_v2() {
+        let SseEvent::BlockAdded(block_added) = parse(fixture!("v2/block_added.json")) else {
+            panic!("expected BlockAdded");
+        };
+        assert!(matches!(block_added, BlockAddedEvent::V2 { .. }));
+        assert_eq!(block_added.height(), 3_981_234);
+        assert_eq!(block_added.era_id(), EraId::new(15_678));
+        assert_roundtrip(fixture!("v2/block_added.json"));
+    }
+
+    #[test]
+    fn block_added_versioned_v1_block() {
+        let v1 = parse(fixture!("v1/block_added.json"));
+        let v2 = parse(fixture!("v2/block_added_version1.json"));
+        let (SseEvent::BlockAdded(v1), SseEvent::BlockAdded(v2)) = (v1, v2) else {
+            panic!("expected BlockAdded");
+        };
+        assert!(matches!(v2, BlockAddedEvent::V2 { .. }));
+        assert_eq!(v1.block_hash(), v2.block_hash());
+        assert_eq!(v1.height(), v2.height());
+        assert_eq!(v1.era_id(), v2.era_id());
+        assert_eq!(v1.into_block(), v2.into_block());
+    }
+
+    #[test]
+    fn deploy_accepted() {
+        let SseEvent::DeployAccepted(deploy) = parse(fixture!("v1/deploy_accepted.json")) else {
+            panic!("expected DeployAccepted");
+        };
+        let deploy = deploy
+            .typed()
+            .expect("deploy should match the legacy schema");
+        assert_eq!(deploy.chain_name(), "casper-test");
+        assert_roundtrip(fixture!("v1/deploy_accepted.json"));
+    }
+
+    #[test]
+    fn deploy_processed() {
+        for json in [
+            fixture!("v1/deploy_processed.json"),
+            fixture!("v1/deploy_processed_failure.json"),
+        ] {
+            let SseEvent::DeployProcessed(processed) = parse(json) else {
+                panic!("expected DeployProcessed");
+            };
+            let processed = processed.typed().expect("should match the legacy schema");
+            assert_eq!(processed.execution_result.cost(), 100_000_000.into());
+            assert_roundtrip(json);
+        }
+    }
+
+    #[test]
+    fn deploy_expired() {
+        let SseEvent::DeployExpired(expired) = parse(fixture!("v1/deploy_expired.json")) else {
+            panic!("expected DeployExpired");
+        };
+        assert!(expired.typed().is_some());
+        assert_roundtrip(fixture!("v1/deploy_expired.json"));
+    }
+
+    #[test]
+    fn unknown_legacy_shape_is_kept_raw() {
+        let json = r#"{"DeployProcessed":{"deploy_hash":"00","unexpected":[1,2,3]}}"#;
+        let SseEvent::DeployProcessed(LegacyEvent::Raw(raw)) = parse(json) else {
+            panic!("expected raw DeployProcessed");
+        };
+        assert_eq!(raw.get(), r#"{"deploy_hash":"00","unexpected":[1,2,3]}"#);
+        assert_roundtrip(json);
+    }
+
+    #[test]
+    fn transaction_events() {
+        assert!(matches!(
+            parse(fixture!("v2/transaction_accepted.json")),
+            SseEvent::TransactionAccepted(Transaction::Deploy(_))
+        ));
+        assert!(matches!(
+            parse(fixture!("v2/transaction_processed.json")),
+            SseEvent::TransactionProcessed {
+                execution_result: ExecutionResult::V2(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse(fixture!("v2/transaction_expired.json")),
+            SseEvent::TransactionExpired { .. }
+        ));
+        assert_roundtrip(fixture!("v2/transaction_accepted.json"));
+        assert_roundtrip(fixture!("v2/transaction_processed.json"));
+        assert_roundtrip(fixture!("v2/transaction_expired.json"));
+    }
+
+    #[test]
+    fn fault_step_and_shutdown() {
+        for json in [fixture!("v1/fault.json"), fixture!("v2/fault.json")] {
+            assert!(matches!(parse(json), SseEvent::Fault { .. }));
+            assert_roundtrip(json);
+        }
+        for (json, expected_era_id) in [
+            (fixture!("v1/step.json"), 10_421),
+            (fixture!("v2/step.json"), 15_678),
+        ] {
+            let SseEvent::Step { era_id, .. } = parse(json) else {
+                panic!("expected Step");
+            };
+            assert_eq!(era_id, EraId::new(expected_era_id));
+        }
+        for json in [fixture!("v1/shutdown.json"), fixture!("v2/shutdown.json")] {
+            assert!(matches!(parse(json), SseEvent::Shutdown));
+        }
+    }
+
+    #[test]
+    fn finality_signature() {
+        let SseEvent::FinalitySignature(v1) = parse(fixture!("v1/finality_signature.json")) else {
+            panic!("expected FinalitySignature");
+        };
+        let SseEvent::FinalitySignature(v2) = parse(fixture!("v2/finality_signature.json")) else {
+            panic!("expected FinalitySignature");
+        };
+        assert!(matches!(v1, FinalitySignature::V1(_)));
+        assert!(matches!(v2, FinalitySignature::V2(_)));
+        assert_eq!(v1.block_hash(), v2.block_hash());
+        assert_roundtrip(fixture!("v2/finality_signature.json"));
+    }
+}
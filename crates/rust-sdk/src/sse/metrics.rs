@@ -0,0 +1,201 @@
+//! Metrics hooks of the SSE listener.
+//!
+//! A [`ListenerMetrics`] set with
+//! [`ListenerConfigBuilder::with_metrics`](crate::sse::config::ListenerConfigBuilder::with_metrics)
+//! is told about every message the listener receives, undecodable ones included, about
+//! reconnects and about how far behind the node's head the listener is. [`AtomicMetrics`] keeps
+//! them in atomics, to be exported to whatever metrics system the consumer uses:
+//!
+//! ```ignore
+//! let metrics = Arc::new(AtomicMetrics::default());
+//! let config = ListenerConfig::builder()
+//!     .with_endpoint(endpoint)
+//!     .with_metrics(metrics.clone())
+//!     .build()?;
+//! // ...
+//! let snapshot = metrics.snapshot();
+//! ```
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use casper_types::Timestamp;
+
+use crate::sse::event::{BlockAddedEvent, SseEvent};
+
+/// Kind reported for messages that aren't an SSE event at all.
+pub const UNKNOWN_EVENT_KIND: &str = "Unknown";
+
+/// Receives the metrics of a listener.
+///
+/// Every method does nothing by default, so implementations only override those they record.
+/// Methods are called from the listener's tasks and should return quickly.
+pub trait ListenerMetrics: Send + Sync {
+    /// Called for every message received, before it is decoded, with the kind of the event (e.g.
+    /// `BlockAdded`, or [`UNKNOWN_EVENT_KIND`]) and the size of its payload.
+    fn on_event(&self, kind: &str, bytes: usize) {
+        let _ = (kind, bytes);
+    }
+
+    /// Called for every message that fails to decode.
+    fn on_parse_error(&self) {}
+
+    /// Called when the event source opens its connection again, `attempt` counting from 1.
+    fn on_reconnect(&self, attempt: u32) {
+        let _ = attempt;
+    }
+
+    /// Called for every `BlockAdded` and `TransactionProcessed` event with the time elapsed
+    /// since the timestamp it carries.
+    fn on_lag(&self, lag: Duration) {
+        let _ = lag;
+    }
+}
+
+/// Metrics that record nothing, the default of a listener.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl ListenerMetrics for NoopMetrics {}
+
+/// Metrics kept in atomics.
+///
+/// The fields are counters, except for `lag_millis`, a gauge of the last reported lag.
+#[derive(Debug, Default)]
+pub struct AtomicMetrics {
+    pub events: AtomicU64,
+    pub bytes: AtomicU64,
+    pub parse_errors: AtomicU64,
+    pub reconnects: AtomicU64,
+    pub lag_millis: AtomicU64,
+}
+
+/// The values of an [`AtomicMetrics`] at some point.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub events: u64,
+    pub bytes: u64,
+    pub parse_errors: u64,
+    pub reconnects: u64,
+    pub lag: Duration,
+}
+
+impl AtomicMetrics {
+    /// Returns the current values.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            events: self.events.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            lag: Duration::from_millis(self.lag_millis.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl ListenerMetrics for AtomicMetrics {
+    fn on_event(&self, _kind: &str, bytes: usize) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn on_parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_reconnect(&self, _attempt: u32) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_lag(&self, lag: Duration) {
+        let millis = u64::try_from(lag.as_millis()).unwrap_or(u64::MAX);
+        self.lag_millis.store(millis, Ordering::Relaxed);
+    }
+}
+
+/// The metrics of a [`ListenerConfig`](crate::sse::config::ListenerConfig).
+#[derive(Clone)]
+pub(crate) struct MetricsHook(pub(crate) Arc<dyn ListenerMetrics>);
+
+impl Default for MetricsHook {
+    fn default() -> Self {
+        MetricsHook(Arc::new(NoopMetrics))
+    }
+}
+
+impl fmt::Debug for MetricsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsHook")
+    }
+}
+
+/// Returns the kind of the event in the raw message `data`, without decoding it.
+///
+/// Events are externally tagged, so the kind is the only key of the JSON object, or the string
+/// itself for unit events like `"Shutdown"`.
+pub fn event_kind(data: &str) -> &str {
+    let data = data.trim_start();
+    let tagged = data.strip_prefix('{').map(str::trim_start).unwrap_or(data);
+    tagged
+        .strip_prefix('"')
+        .and_then(|rest| rest.split_once('"'))
+        .map(|(kind, _rest)| kind)
+        .filter(|kind| !kind.is_empty() && !kind.contains('\\'))
+        .unwrap_or(UNKNOWN_EVENT_KIND)
+}
+
+/// Returns the time elapsed between the timestamp `event` carries and `now`, for the events
+/// carrying one.
+pub fn event_lag(event: &SseEvent, now: Timestamp) -> Option<Duration> {
+    let timestamp = match event {
+        SseEvent::BlockAdded(BlockAddedEvent::V1 { block, .. }) => block.timestamp(),
+        SseEvent::BlockAdded(BlockAddedEvent::V2 { block, .. }) => block.timestamp(),
+        SseEvent::TransactionProcessed { timestamp, .. } => *timestamp,
+        _ => return None,
+    };
+    Some(Duration::from(now.saturating_diff(timestamp)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_are_read_without_decoding() {
+        assert_eq!(
+            event_kind(r#"{"BlockAdded":{"block_hash":"00"}}"#),
+            "BlockAdded"
+        );
+        assert_eq!(event_kind(" { \"Step\" : {} }"), "Step");
+        assert_eq!(event_kind(r#""Shutdown""#), "Shutdown");
+        assert_eq!(event_kind("{"), UNKNOWN_EVENT_KIND);
+        assert_eq!(event_kind("[1, 2]"), UNKNOWN_EVENT_KIND);
+        assert_eq!(event_kind(""), UNKNOWN_EVENT_KIND);
+    }
+
+    #[test]
+    fn atomic_metrics_snapshot() {
+        let metrics = AtomicMetrics::default();
+        metrics.on_event("BlockAdded", 10);
+        metrics.on_event("Step", 5);
+        metrics.on_parse_error();
+        metrics.on_reconnect(1);
+        metrics.on_lag(Duration::from_secs(3));
+        metrics.on_lag(Duration::from_millis(1500));
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                events: 2,
+                bytes: 15,
+                parse_errors: 1,
+                reconnects: 1,
+                lag: Duration::from_millis(1500),
+            }
+        );
+    }
+}
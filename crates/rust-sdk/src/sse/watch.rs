@@ -0,0 +1,329 @@
+//! Waiting for a transaction to be processed by following the SSE stream.
+//!
+//! [`watch_transaction`] correlates the `TransactionAccepted`, `TransactionProcessed` and
+//! `TransactionExpired` events of a transaction, or the `Deploy*` events of a deploy sent by a
+//! 1.x node, and [`submit_and_watch`] submits a transaction through the JSON-RPC client before
+//! watching it. The correlation itself is the pure [`correlate`] function.
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use casper_types::{BlockHash, Transaction, TransactionHash, execution::ExecutionResult};
+
+use crate::{
+    jsonrpc::{CasperClient, CasperClientError, DEFAULT_EXECUTION_TIMEOUT},
+    sse::{ListenerError, config::ListenerConfig, event::SseEvent, listener},
+};
+
+/// How a watched transaction ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    /// The transaction was executed in the block `block_hash`.
+    Processed {
+        block_hash: BlockHash,
+        execution_result: ExecutionResult,
+    },
+    /// The transaction expired without being executed.
+    Expired,
+    /// Neither happened before the watch timed out.
+    Timeout,
+}
+
+/// Where a watched transaction is in its lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchState {
+    /// No event of the transaction has been seen yet.
+    Pending,
+    /// The node accepted the transaction.
+    Accepted,
+    /// The transaction was processed or expired.
+    Finished(TransactionOutcome),
+}
+
+impl WatchState {
+    /// Returns the outcome of a finished transaction.
+    pub fn outcome(&self) -> Option<&TransactionOutcome> {
+        match self {
+            WatchState::Finished(outcome) => Some(outcome),
+            WatchState::Pending | WatchState::Accepted => None,
+        }
+    }
+}
+
+/// Returns the state of the transaction `hash` after `event`.
+///
+/// Events of other transactions and other kinds of events leave the state as it is, as do events
+/// once the transaction finished. A processed or expired transaction finishes even if it was
+/// never seen accepted, e.g. when the listener started after it was.
+pub fn correlate(hash: &TransactionHash, state: WatchState, event: SseEvent) -> WatchState {
+    if let WatchState::Finished(_) = state {
+        return state;
+    }
+    match event {
+        SseEvent::TransactionAccepted(transaction) if transaction.hash() == *hash => {
+            WatchState::Accepted
+        }
+        SseEvent::DeployAccepted(deploy)
+            if deploy
+                .typed()
+                .is_some_and(|deploy| TransactionHash::Deploy(*deploy.hash()) == *hash) =>
+        {
+            WatchState::Accepted
+        }
+        SseEvent::TransactionProcessed {
+            transaction_hash,
+            block_hash,
+            execution_result,
+            ..
+        } if transaction_hash == *hash => WatchState::Finished(TransactionOutcome::Processed {
+            block_hash,
+            execution_result,
+        }),
+        SseEvent::DeployProcessed(processed) => match processed.typed() {
+            Some(processed) if TransactionHash::Deploy(processed.deploy_hash) == *hash => {
+                WatchState::Finished(TransactionOutcome::Processed {
+                    block_hash: processed.block_hash,
+                    execution_result: ExecutionResult::V1(processed.execution_result.clone()),
+                })
+            }
+            _ => state,
+        },
+        SseEvent::TransactionExpired { transaction_hash } if transaction_hash == *hash => {
+            WatchState::Finished(TransactionOutcome::Expired)
+        }
+        SseEvent::DeployExpired(expired)
+            if expired
+                .typed()
+                .is_some_and(|expired| TransactionHash::Deploy(expired.deploy_hash) == *hash) =>
+        {
+            WatchState::Finished(TransactionOutcome::Expired)
+        }
+        _ => state,
+    }
+}
+
+/// Listens to the SSE stream of `config` until the transaction `hash` is processed or expires,
+/// resolving with [`TransactionOutcome::Timeout`] if neither happens within `timeout`.
+///
+/// Events that fail to decode are skipped, other listener errors end the watch. Fails with
+/// [`ListenerError::StreamEnded`] if the stream ends first.
+pub async fn watch_transaction(
+    config: ListenerConfig,
+    hash: TransactionHash,
+    timeout: Duration,
+) -> Result<TransactionOutcome, ListenerError> {
+    let events = listener(config).await?;
+    watch_events(events, hash, timeout).await
+}
+
+/// Drives [`correlate`] over `events`, see [`watch_transaction`].
+async fn watch_events(
+    events: impl Stream<Item = Result<SseEvent, ListenerError>>,
+    hash: TransactionHash,
+    timeout: Duration,
+) -> Result<TransactionOutcome, ListenerError> {
+    let watch = async {
+        let mut events = std::pin::pin!(events);
+        let mut state = WatchState::Pending;
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(error @ ListenerError::Decode { .. }) => {
+                    warn!(%hash, "skipping undecodable event: {error}");
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            state = correlate(&hash, state, event);
+            match state {
+                WatchState::Finished(outcome) => return Ok(outcome),
+                WatchState::Accepted => debug!(%hash, "transaction accepted"),
+                WatchState::Pending => {}
+            }
+        }
+        Err(ListenerError::StreamEnded)
+    };
+    tokio::time::timeout(timeout, watch)
+        .await
+        .unwrap_or(Ok(TransactionOutcome::Timeout))
+}
+
+/// An error submitting and watching a transaction, see [`submit_and_watch`].
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error(transparent)]
+    Client(#[from] CasperClientError),
+    #[error(transparent)]
+    Listener(#[from] ListenerError),
+}
+
+/// Submits `transaction` through `client` and watches it on the SSE stream of `sse_config`,
+/// waiting up to [`DEFAULT_EXECUTION_TIMEOUT`].
+///
+/// The listener is started before the transaction is submitted, so its events aren't missed.
+pub async fn submit_and_watch(
+    client: &CasperClient,
+    sse_config: ListenerConfig,
+    transaction: Transaction,
+) -> Result<TransactionOutcome, WatchError> {
+    let events = listener(sse_config).await?;
+    let hash = client.put_transaction(transaction).await?;
+    Ok(watch_events(events, hash, DEFAULT_EXECUTION_TIMEOUT).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{Digest, TransactionV1Hash};
+    use futures::stream;
+
+    use super::*;
+
+    macro_rules! fixture {
+        ($path:literal) => {
+            include_str!(concat!("../../tests/fixtures/sse/", $path))
+        };
+    }
+
+    fn parse(json: &str) -> SseEvent {
+        serde_json::from_str(json).expect("fixture should decode")
+    }
+
+    /// The hash shared by the deploy fixtures and the `TransactionProcessed` fixture.
+    fn deploy_hash() -> TransactionHash {
+        match parse(fixture!("v2/transaction_processed.json")) {
+            SseEvent::TransactionProcessed {
+                transaction_hash, ..
+            } => transaction_hash,
+            _ => panic!("expected TransactionProcessed"),
+        }
+    }
+
+    fn other_hash() -> TransactionHash {
+        TransactionHash::V1(TransactionV1Hash::from(Digest::from([9; 32])))
+    }
+
+    fn expired(transaction_hash: TransactionHash) -> SseEvent {
+        SseEvent::TransactionExpired { transaction_hash }
+    }
+
+    #[test]
+    fn processed_after_accepted() {
+        let SseEvent::TransactionAccepted(transaction) =
+            parse(fixture!("v2/transaction_accepted.json"))
+        else {
+            panic!("expected TransactionAccepted");
+        };
+        let hash = transaction.hash();
+
+        let accepted = SseEvent::TransactionAccepted(transaction.clone());
+        let state = correlate(&hash, WatchState::Pending, accepted);
+        assert_eq!(state, WatchState::Accepted);
+        let state = correlate(&hash, state, expired(other_hash()));
+        assert_eq!(state, WatchState::Accepted);
+
+        let SseEvent::TransactionProcessed {
+            block_hash,
+            execution_result,
+            ..
+        } = parse(fixture!("v2/transaction_processed.json"))
+        else {
+            panic!("expected TransactionProcessed");
+        };
+        let processed = SseEvent::TransactionProcessed {
+            transaction_hash: hash,
+            initiator_addr: transaction.initiator_addr(),
+            timestamp: transaction.timestamp(),
+            ttl: transaction.ttl(),
+            block_hash,
+            execution_result: execution_result.clone(),
+            messages: Vec::new(),
+        };
+        let state = correlate(&hash, state, processed);
+        assert_eq!(
+            state.outcome(),
+            Some(&TransactionOutcome::Processed {
+                block_hash,
+                execution_result
+            })
+        );
+
+        // A finished transaction stays finished.
+        assert_eq!(correlate(&hash, state.clone(), expired(hash)), state);
+    }
+
+    #[test]
+    fn processed_without_accepted() {
+        let state = correlate(
+            &deploy_hash(),
+            WatchState::Pending,
+            parse(fixture!("v2/transaction_processed.json")),
+        );
+        assert!(matches!(
+            state.outcome(),
+            Some(TransactionOutcome::Processed {
+                execution_result: ExecutionResult::V2(_),
+                ..
+            })
+        ));
+        assert_eq!(
+            correlate(
+                &other_hash(),
+                WatchState::Pending,
+                parse(fixture!("v2/transaction_processed.json"))
+            ),
+            WatchState::Pending
+        );
+    }
+
+    #[test]
+    fn legacy_deploy_events() {
+        let hash = deploy_hash();
+        let state = correlate(
+            &hash,
+            WatchState::Pending,
+            parse(fixture!("v1/deploy_processed.json")),
+        );
+        assert!(matches!(
+            state.outcome(),
+            Some(TransactionOutcome::Processed {
+                execution_result: ExecutionResult::V1(_),
+                ..
+            })
+        ));
+        assert_eq!(
+            correlate(
+                &hash,
+                WatchState::Accepted,
+                parse(fixture!("v1/deploy_expired.json"))
+            ),
+            WatchState::Finished(TransactionOutcome::Expired)
+        );
+    }
+
+    #[tokio::test]
+    async fn watch_resolves_on_expiry() {
+        let hash = other_hash();
+        let events = stream::iter([
+            Ok(parse(fixture!("v2/api_version.json"))),
+            Err(ListenerError::Decode {
+                head: String::from("{"),
+                source: serde_json::from_str::<SseEvent>("{").unwrap_err(),
+            }),
+            Ok(expired(hash)),
+        ]);
+        let outcome = watch_events(events, hash, Duration::from_secs(1)).await;
+        assert_eq!(outcome.unwrap(), TransactionOutcome::Expired);
+
+        let outcome = watch_events(stream::iter([]), hash, Duration::from_secs(1)).await;
+        assert!(matches!(outcome, Err(ListenerError::StreamEnded)));
+    }
+
+    #[tokio::test]
+    async fn watch_times_out() {
+        let outcome =
+            watch_events(stream::pending(), other_hash(), Duration::from_millis(10)).await;
+        assert_eq!(outcome.unwrap(), TransactionOutcome::Timeout);
+    }
+}
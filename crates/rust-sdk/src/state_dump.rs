@@ -0,0 +1,283 @@
+//! Snapshots of the state of a contract, for running its code against real state under the shim.
+//!
+//! [`export_contract_state`] reads the named keys of a contract, the values of its URef named keys
+//! and the given items of its dictionaries, and returns them as the global state entries
+//! `EnvBuilder::from_state_entries` of `veles-casper-ffi-shim` loads:
+//!
+//! - a `Key::NamedKey` holding a `StoredValue::NamedKey` for each named key,
+//! - the value stored under each URef named key, `CLValue::unit()` for dictionary seeds,
+//! - a `Key::Dictionary` holding a `DictionaryValue` for each dictionary item, as items are stored
+//!   on-chain.
+//!
+//! Dictionaries can't be listed, so the items to export are given by their item keys, which the
+//! [`keyspace`](veles_casper_contract_api::keyspace) functions compute for the kit's collections
+//! and CEP-18 tokens. [`save_state_dump`] and [`load_state_dump`] keep the entries in a JSON file
+//! for offline reuse:
+//!
+//! ```ignore
+//! let item_keys = vec![keyspace::cep18_balance_item_key(&owner)];
+//! let items = BTreeMap::from([("balances".to_string(), item_keys)]);
+//! let entries = export_contract_state(&client, entity, &items).await?;
+//! save_state_dump("incident.json", &entries)?;
+//!
+//! let env = EnvBuilder::from_state_entries(load_state_dump("incident.json")?).build();
+//! ```
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    future::Future,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use casper_types::{
+    CLValue, CLValueDictionary, CLValueError, EntityAddr, Key, NamedKeys, StoredValue, URef,
+    addressable_entity::{NamedKeyAddr, NamedKeyValue},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::jsonrpc::{CasperClient, CasperClientError};
+
+/// Reads the global state [`export_contract_state`] exports, e.g. the latest state of a network
+/// through [`CasperClient`].
+pub trait StateSource {
+    /// Returns the named keys of the contract at `entity`.
+    fn named_keys(
+        &self,
+        entity: EntityAddr,
+    ) -> impl Future<Output = Result<NamedKeys, CasperClientError>> + Send;
+
+    /// Returns the value stored under `key`.
+    fn stored_value(
+        &self,
+        key: Key,
+    ) -> impl Future<Output = Result<StoredValue, CasperClientError>> + Send;
+
+    /// Returns the item stored under `item_key` in the dictionary of `seed_uref`, unwrapped from
+    /// its `DictionaryValue`.
+    fn dictionary_item(
+        &self,
+        seed_uref: URef,
+        item_key: &str,
+    ) -> impl Future<Output = Result<CLValue, CasperClientError>> + Send;
+}
+
+/// Reads the state at the latest state root hash of each query, so values written while the
+/// export runs may come from different blocks.
+impl StateSource for CasperClient {
+    async fn named_keys(&self, entity: EntityAddr) -> Result<NamedKeys, CasperClientError> {
+        let contract = self.get_contract(Key::AddressableEntity(entity)).await?;
+        Ok(contract.into_named_keys())
+    }
+
+    async fn stored_value(&self, key: Key) -> Result<StoredValue, CasperClientError> {
+        self.query_stored_value(key).await
+    }
+
+    async fn dictionary_item(
+        &self,
+        seed_uref: URef,
+        item_key: &str,
+    ) -> Result<CLValue, CasperClientError> {
+        self.query_dictionary_item(seed_uref, item_key).await
+    }
+}
+
+/// Exports the state of the contract at `entity` from `source`, see the
+/// [module documentation](self).
+///
+/// `dictionary_items` lists the item keys to export by the named key of their dictionary; a name
+/// that isn't a URef named key of the contract fails with [`CasperClientError::MissingNamedKey`].
+pub async fn export_contract_state(
+    source: &impl StateSource,
+    entity: EntityAddr,
+    dictionary_items: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<(Key, StoredValue)>, CasperClientError> {
+    let named_keys = source.named_keys(entity).await?;
+    let mut entries = Vec::new();
+    for (name, key) in named_keys.iter() {
+        let named_key_addr = NamedKeyAddr::new_from_string(entity, name.clone())
+            .map_err(CLValueError::Serialization)?;
+        let named_key_value = NamedKeyValue::from_concrete_values(*key, name.clone())?;
+        entries.push((
+            Key::NamedKey(named_key_addr),
+            StoredValue::NamedKey(named_key_value),
+        ));
+        if let Key::URef(_) = key {
+            entries.push((*key, source.stored_value(*key).await?));
+        }
+    }
+
+    for (name, item_keys) in dictionary_items {
+        let Some(Key::URef(seed_uref)) = named_keys.get(name) else {
+            return Err(CasperClientError::MissingNamedKey(name.clone()));
+        };
+        for item_key in item_keys {
+            let item = source.dictionary_item(*seed_uref, item_key).await?;
+            let dictionary_value = CLValueDictionary::new(
+                item,
+                seed_uref.addr().to_vec(),
+                item_key.as_bytes().to_vec(),
+            );
+            entries.push((
+                Key::dictionary(*seed_uref, item_key.as_bytes()),
+                StoredValue::CLValue(CLValue::from_t(dictionary_value)?),
+            ));
+        }
+    }
+    Ok(entries)
+}
+
+/// An entry of a state dump file.
+#[derive(Serialize, Deserialize)]
+struct StateDumpEntry {
+    key: Key,
+    value: StoredValue,
+}
+
+/// Writes `entries` to a JSON file at `path`, to be read back with [`load_state_dump`].
+pub fn save_state_dump(
+    path: impl AsRef<Path>,
+    entries: &[(Key, StoredValue)],
+) -> Result<(), CasperClientError> {
+    let entries: Vec<StateDumpEntry> = entries
+        .iter()
+        .map(|(key, value)| StateDumpEntry {
+            key: *key,
+            value: value.clone(),
+        })
+        .collect();
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, &entries)?;
+    Ok(())
+}
+
+/// Reads the entries of a JSON file written by [`save_state_dump`].
+pub fn load_state_dump(
+    path: impl AsRef<Path>,
+) -> Result<Vec<(Key, StoredValue)>, CasperClientError> {
+    let reader = BufReader::new(File::open(path)?);
+    let entries: Vec<StateDumpEntry> = serde_json::from_reader(reader)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| (entry.key, entry.value))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use casper_types::{U256, account::AccountHash};
+    use veles_casper_contract_api::{
+        casper_contract::contract_api::{runtime, storage},
+        keyspace,
+        veles_casper_ffi_shim::{EnvBuilder, dispatch_with},
+    };
+
+    use super::*;
+
+    const ENTITY: EntityAddr = EntityAddr::SmartContract([7; 32]);
+    const HOLDERS: [(Key, u64); 2] = [
+        (Key::Account(AccountHash::new([1; 32])), 600),
+        (Key::Hash([2; 32]), 400),
+    ];
+
+    /// A "chain" holding the state a contract wrote under the shim.
+    struct ShimChain {
+        named_keys: BTreeMap<String, Key>,
+        storage: BTreeMap<Key, StoredValue>,
+        dictionaries: BTreeMap<[u8; 32], BTreeMap<String, CLValue>>,
+    }
+
+    fn not_found(key: Key) -> CasperClientError {
+        CasperClientError::UnexpectedStoredValue {
+            key: key.to_string(),
+            expected: "a stored value",
+            found: "nothing".into(),
+        }
+    }
+
+    impl StateSource for ShimChain {
+        async fn named_keys(&self, entity: EntityAddr) -> Result<NamedKeys, CasperClientError> {
+            assert_eq!(entity, ENTITY);
+            Ok(NamedKeys::from(self.named_keys.clone()))
+        }
+
+        async fn stored_value(&self, key: Key) -> Result<StoredValue, CasperClientError> {
+            self.storage
+                .get(&key.normalize())
+                .cloned()
+                .ok_or_else(|| not_found(key))
+        }
+
+        async fn dictionary_item(
+            &self,
+            seed_uref: URef,
+            item_key: &str,
+        ) -> Result<CLValue, CasperClientError> {
+            self.dictionaries
+                .get(&seed_uref.addr())
+                .and_then(|items| items.get(item_key))
+                .cloned()
+                .ok_or_else(|| not_found(Key::dictionary(seed_uref, item_key.as_bytes())))
+        }
+    }
+
+    #[tokio::test]
+    async fn exported_state_reloads_into_the_shim() {
+        let env = EnvBuilder::new().build();
+        let mut balances = None;
+        dispatch_with(env.clone(), |_env| {
+            let total_supply = storage::new_uref(U256::from(1_000));
+            runtime::put_key("total_supply", total_supply.into());
+            runtime::put_key("package_hash", Key::Hash([9; 32]));
+            let dictionary = storage::new_dictionary("balances").unwrap();
+            for (owner, amount) in HOLDERS {
+                let item_key = keyspace::cep18_balance_item_key(&owner);
+                storage::dictionary_put(dictionary, &item_key, U256::from(amount));
+            }
+            storage::new_dictionary("allowances").unwrap();
+            balances = Some(dictionary);
+        });
+        let balances = balances.unwrap();
+        let chain = ShimChain {
+            named_keys: env.named_keys(),
+            storage: env.storage(),
+            dictionaries: env.dictionaries(),
+        };
+
+        let items = BTreeMap::from([(
+            "balances".to_string(),
+            HOLDERS
+                .iter()
+                .map(|(owner, _)| keyspace::cep18_balance_item_key(owner))
+                .collect(),
+        )]);
+        let entries = export_contract_state(&chain, ENTITY, &items).await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        save_state_dump(&path, &entries).unwrap();
+        let loaded = load_state_dump(&path).unwrap();
+        assert_eq!(loaded, entries);
+
+        let reloaded = EnvBuilder::from_state_entries(loaded).build();
+        assert_eq!(reloaded.named_keys(), env.named_keys());
+        assert_eq!(reloaded.storage(), env.storage());
+        assert_eq!(reloaded.dictionaries(), env.dictionaries());
+        dispatch_with(reloaded, |_env| {
+            let (owner, amount) = HOLDERS[0];
+            let item_key = keyspace::cep18_balance_item_key(&owner);
+            assert_eq!(
+                storage::dictionary_get::<U256>(balances, &item_key),
+                Ok(Some(U256::from(amount)))
+            );
+            let allowances = runtime::get_key("allowances").unwrap().into_uref().unwrap();
+            storage::dictionary_put(allowances, "spender", U256::one());
+        });
+
+        let missing = BTreeMap::from([("supply".to_string(), Vec::new())]);
+        assert!(matches!(
+            export_contract_state(&chain, ENTITY, &missing).await,
+            Err(CasperClientError::MissingNamedKey(name)) if name == "supply"
+        ));
+    }
+}
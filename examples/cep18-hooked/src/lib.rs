@@ -0,0 +1,95 @@
+//! A CEP-18 token sending a fee on its transfers to a treasury, which its owner can pause.
+//!
+//! The token installs [`FeeHooks`] before each CEP-18 entry point. The pausable and ownable
+//! entry points are exported alongside, as the extras crate is linked with its `as_dependency`
+//! feature in the contract.
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+
+use veles_casper_contract_api::{
+    casper_types::{NamedKeys, U256},
+    prelude::*,
+};
+use veles_casper_contract_extras::{
+    cep18::{
+        self,
+        balances::transfer_balance,
+        hooks::{Cep18Hooks, PausableHooks},
+    },
+    ownable, pausable,
+};
+
+pub const ARG_NAME: &str = "name";
+pub const ARG_TREASURY: &str = "treasury";
+
+pub const TREASURY_KEY_NAME: &str = "treasury";
+
+static TREASURY: NamedKey = NamedKey::from_name(TREASURY_KEY_NAME);
+/// The account or contract receiving the transfer fees.
+pub static TREASURY_TUREF: TypedURef<Key> = TypedURef::from_named_key(&TREASURY);
+
+/// The share of each transferred amount sent to the treasury, 1%.
+pub const FEE_DIVISOR: u64 = 100;
+
+/// Blocks the token while paused and moves the fee of each transfer from its recipient to the
+/// treasury.
+pub struct FeeHooks;
+
+impl Cep18Hooks for FeeHooks {
+    fn before_transfer(&self, sender: Key, recipient: Key, amount: U256) -> Result<(), ApiError> {
+        PausableHooks.before_transfer(sender, recipient, amount)
+    }
+
+    fn after_transfer(&self, _sender: Key, recipient: Key, amount: U256) -> Result<(), ApiError> {
+        let treasury = TREASURY_TUREF.read()?.ok_or(ApiError::MissingKey)?;
+        let fee = amount / U256::from(FEE_DIVISOR);
+        transfer_balance(recipient, treasury, fee)?;
+        Ok(())
+    }
+
+    fn before_mint(&self, owner: Key, amount: U256) -> Result<(), ApiError> {
+        PausableHooks.before_mint(owner, amount)
+    }
+
+    fn before_burn(&self, owner: Key, amount: U256) -> Result<(), ApiError> {
+        PausableHooks.before_burn(owner, amount)
+    }
+}
+
+pub static HOOKS: FeeHooks = FeeHooks;
+
+#[cfg(target_arch = "wasm32")]
+veles_casper_contract_extras::export_cep18_symbols!(
+    veles_casper_contract_extras::cep18::hooks::set_hooks(&HOOKS)
+);
+#[cfg(target_arch = "wasm32")]
+veles_casper_contract_extras::export_ownable_symbols!();
+#[cfg(target_arch = "wasm32")]
+veles_casper_contract_extras::export_pausable_symbols!();
+
+/// Installs the token like `cep18_token`, with the `treasury` key receiving the transfer fees.
+///
+/// The installing account owns the token and can pause it.
+#[casper(export)]
+pub fn call() -> Result<(), ApiError> {
+    let name: String = runtime::get_named_arg(ARG_NAME);
+    let treasury: Key = runtime::get_named_arg(ARG_TREASURY);
+
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(
+        ownable::OWNER_KEY_NAME.name().into(),
+        Key::Account(runtime::get_caller()),
+    );
+    pausable::install(&mut named_keys)?;
+    TREASURY
+        .get_or_init(|| utils::new_uref_key(treasury))?
+        .append_to_named_keys(&mut named_keys)?;
+
+    let mut entry_points = ownable::ownable::entry_points_vec();
+    entry_points.extend(pausable::pausable::entry_points_vec());
+    cep18::install_contract_with(&name, entry_points, named_keys);
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use veles_casper_contract_api::macro_support::IntoRuntimeArgs;
+use veles_casper_contract_api::{
+    casper_engine_test_support::{
+        DEFAULT_ACCOUNT_ADDR, ExecuteRequestBuilder, LOCAL_GENESIS_REQUEST, LmdbWasmTestBuilder,
+        MINIMUM_ACCOUNT_CREATION_BALANCE, TransferRequestBuilder,
+    },
+    casper_execution_engine::{engine_state, execution},
+    casper_types::{
+        ApiError, Key, RuntimeArgs, U256, account::AccountHash, contracts::ContractHash,
+        runtime_args,
+    },
+    test_support::{WasmTestBuilderExt, read_wasm},
+};
+use veles_casper_contract_extras::{
+    cep18::{self, balance_item_key, constants::DICT_BALANCES},
+    ownable::OwnableError,
+    pausable::{self, PausableError},
+};
+
+const TOKEN_NAME: &str = "hooked";
+const ALICE: AccountHash = AccountHash::new([1; 32]);
+const TREASURY: Key = Key::Account(AccountHash::new([3; 32]));
+
+struct Fixture {
+    builder: LmdbWasmTestBuilder,
+    token_hash: ContractHash,
+}
+
+impl Fixture {
+    /// Installs a token with a supply of 1000 owned by the default account.
+    fn new() -> Self {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+        let transfer_request =
+            TransferRequestBuilder::new(MINIMUM_ACCOUNT_CREATION_BALANCE, ALICE).build();
+        builder
+            .transfer_and_commit(transfer_request)
+            .expect_success();
+
+        let install_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("cep18_hooked.wasm"),
+            runtime_args! {
+                cep18_hooked::ARG_NAME => TOKEN_NAME,
+                cep18_hooked::ARG_TREASURY => TREASURY,
+                "symbol" => "HOOK",
+                "decimals" => 9u8,
+                "total_supply" => U256::from(1_000u64),
+            },
+        )
+        .build();
+        builder.exec(install_request).expect_success().commit();
+
+        let token_hash = builder.installed_contract_hash(
+            *DEFAULT_ACCOUNT_ADDR,
+            &format!("cep18_contract_hash_{TOKEN_NAME}"),
+        );
+        Self {
+            builder,
+            token_hash,
+        }
+    }
+
+    fn call(
+        &mut self,
+        sender: AccountHash,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> Result<(), ApiError> {
+        let exec_request = ExecuteRequestBuilder::contract_call_by_hash(
+            sender,
+            self.token_hash.into(),
+            entry_point,
+            args,
+        )
+        .build();
+        self.builder.exec(exec_request).commit();
+        match self.builder.get_error() {
+            None => Ok(()),
+            Some(engine_state::Error::Exec(execution::ExecError::Revert(api_error))) => {
+                Err(api_error)
+            }
+            Some(error) => panic!("unexpected execution error: {error:?}"),
+        }
+    }
+
+    fn transfer(
+        &mut self,
+        sender: AccountHash,
+        recipient: Key,
+        amount: u64,
+    ) -> Result<(), ApiError> {
+        let args = cep18::cep18::transfer::Args {
+            recipient,
+            amount: U256::from(amount),
+        };
+        self.call(
+            sender,
+            cep18::cep18::transfer::NAME,
+            args.into_runtime_args(),
+        )
+    }
+
+    fn pause(&mut self, sender: AccountHash) -> Result<(), ApiError> {
+        self.call(
+            sender,
+            pausable::pausable::pause::NAME,
+            pausable::pausable::pause::Args {}.into_runtime_args(),
+        )
+    }
+
+    fn unpause(&mut self, sender: AccountHash) -> Result<(), ApiError> {
+        self.call(
+            sender,
+            pausable::pausable::unpause::NAME,
+            pausable::pausable::unpause::Args {}.into_runtime_args(),
+        )
+    }
+
+    fn balance_of(&self, owner: Key) -> U256 {
+        let contract = self
+            .builder
+            .get_contract(self.token_hash)
+            .expect("token contract should exist");
+        let uref = contract
+            .named_keys()
+            .get(DICT_BALANCES)
+            .and_then(Key::as_uref)
+            .expect("balances should be a dictionary");
+        self.builder
+            .query_dictionary_item(None, *uref, &balance_item_key(&owner))
+            .ok()
+            .map(|item| {
+                item.into_cl_value()
+                    .expect("should be a CLValue")
+                    .into_t()
+                    .expect("should be a U256")
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[test]
+fn transfers_pay_a_fee_to_the_treasury() {
+    let mut fixture = Fixture::new();
+
+    fixture
+        .transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 500)
+        .expect("should transfer");
+    assert_eq!(
+        fixture.balance_of(Key::Account(*DEFAULT_ACCOUNT_ADDR)),
+        U256::from(500)
+    );
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::from(495));
+    assert_eq!(fixture.balance_of(TREASURY), U256::from(5));
+
+    // Below the fee divisor the fee rounds down to nothing.
+    fixture
+        .transfer(ALICE, Key::Account(*DEFAULT_ACCOUNT_ADDR), 99)
+        .expect("should transfer");
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::from(396));
+    assert_eq!(fixture.balance_of(TREASURY), U256::from(5));
+}
+
+#[test]
+fn paused_token_blocks_transfers() {
+    let mut fixture = Fixture::new();
+
+    assert_eq!(fixture.pause(ALICE), Err(OwnableError::Unauthorized.into()));
+    fixture.pause(*DEFAULT_ACCOUNT_ADDR).expect("should pause");
+    assert_eq!(
+        fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 100),
+        Err(PausableError::ContractPaused.into())
+    );
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::zero());
+
+    fixture
+        .unpause(*DEFAULT_ACCOUNT_ADDR)
+        .expect("should unpause");
+    fixture
+        .transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 100)
+        .expect("should transfer");
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::from(99));
+    assert_eq!(fixture.balance_of(TREASURY), U256::from(1));
+}
@@ -0,0 +1,27 @@
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+
+use veles_casper_contract_api::prelude::*;
+use veles_casper_contract_extras::cep18;
+
+pub const ARG_NAME: &str = "name";
+
+/// Installs a CEP-18 token with the `name`, `symbol`, `decimals` and `total_supply` arguments.
+///
+/// The optional CEP-18 installation arguments such as `enable_mint_burn` and `enable_enumeration`
+/// are read by the token as well.
+///
+/// The contract hash is stored in the installing account under `cep18_contract_hash_<name>`. An
+/// account that already installed a token named `name` upgrades it to this version instead.
+#[casper(export)]
+pub fn call() {
+    let name: String = runtime::get_named_arg(ARG_NAME);
+    if cep18::is_installed(&name) {
+        cep18::upgrade(&name);
+    } else {
+        cep18::install_contract(&name);
+    }
+}
@@ -0,0 +1,941 @@
+use veles_casper_contract_api::macro_support::IntoRuntimeArgs;
+use veles_casper_contract_api::{
+    casper_engine_test_support::{
+        DEFAULT_ACCOUNT_ADDR, ExecuteRequestBuilder, LOCAL_GENESIS_REQUEST, LmdbWasmTestBuilder,
+        MINIMUM_ACCOUNT_CREATION_BALANCE, TransferRequestBuilder,
+    },
+    casper_execution_engine::{engine_state, execution},
+    casper_types::{
+        ApiError, CLTyped, EntityAddr, Key, PublicKey, RuntimeArgs, SecretKey, U256, URef,
+        account::AccountHash,
+        bytesrepr::{Bytes, FromBytes, ToBytes},
+        contracts::ContractHash,
+        crypto, runtime_args,
+    },
+    collections::{dictionary_key::DictionaryKey, vector::VEC_LENGTH_KEY},
+    keyspace::collections_item_key,
+    test_support::{WasmTestBuilderExt, read_wasm},
+};
+use veles_casper_contract_extras::cep18::{
+    self,
+    allowances::{TimedAllowance, allowance_item_key},
+    balance_item_key,
+    constants::{
+        ARG_ENABLE_ALLOWANCE_EXPIRY, ARG_ENABLE_FREEZING, ARG_ENABLE_MINT_BURN, ARG_ICON_URL,
+        ARG_PACKAGE_HASH, ARG_WEBSITE, DICT_ALLOWANCE_PAIRS, DICT_ALLOWANCES, DICT_BALANCES,
+        DICT_FROZEN, DICT_HOLDER_INDICES, DICT_HOLDERS, DICT_PERMIT_NONCES, ENTRY_POINT_APPROVE,
+        ENTRY_POINT_FREEZE, ENTRY_POINT_METADATA, ENTRY_POINT_SET_BURN_PAUSED,
+        ENTRY_POINT_SET_MINT_PAUSED, ENTRY_POINT_UNFREEZE, KEY_BURN_PAUSED, KEY_MINT_PAUSED,
+    },
+    error::Cep18Error,
+};
+
+const TOKEN_NAME: &str = "enumerable";
+const ALICE: AccountHash = AccountHash::new([1; 32]);
+const BOB: AccountHash = AccountHash::new([2; 32]);
+
+struct Fixture {
+    builder: LmdbWasmTestBuilder,
+    token_hash: ContractHash,
+}
+
+impl Fixture {
+    /// Installs a mintable token with a supply of 1000, tracking holders if `enable_enumeration`.
+    fn new(enable_enumeration: Option<bool>) -> Self {
+        let mut extra_args = RuntimeArgs::new();
+        if let Some(enable_enumeration) = enable_enumeration {
+            extra_args
+                .insert("enable_enumeration", enable_enumeration)
+                .unwrap();
+        }
+        Self::with_args(extra_args)
+    }
+
+    /// Installs a token with a supply of 1000 and the optional `extra_args`, mintable unless they
+    /// set `enable_mint_burn`.
+    fn with_args(extra_args: RuntimeArgs) -> Self {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+        for account in [ALICE, BOB] {
+            let transfer_request =
+                TransferRequestBuilder::new(MINIMUM_ACCOUNT_CREATION_BALANCE, account).build();
+            builder
+                .transfer_and_commit(transfer_request)
+                .expect_success();
+        }
+
+        let mut args = runtime_args! {
+            cep18_token::ARG_NAME => TOKEN_NAME,
+            "symbol" => "ENUM",
+            "decimals" => 9u8,
+            "total_supply" => U256::from(1_000u64),
+        };
+        if extra_args.get(ARG_ENABLE_MINT_BURN).is_none() {
+            args.insert(ARG_ENABLE_MINT_BURN, 1u8).unwrap();
+        }
+        for arg in extra_args.named_args() {
+            args.insert_cl_value(arg.name(), arg.cl_value().clone());
+        }
+        let install_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("cep18_token.wasm"),
+            args,
+        )
+        .build();
+        builder.exec(install_request).expect_success().commit();
+
+        let token_hash = builder.installed_contract_hash(
+            *DEFAULT_ACCOUNT_ADDR,
+            &format!("cep18_contract_hash_{TOKEN_NAME}"),
+        );
+        Self {
+            builder,
+            token_hash,
+        }
+    }
+
+    /// Upgrades the token to a new version of the same wasm.
+    fn upgrade(&mut self) {
+        let upgrade_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("cep18_token.wasm"),
+            runtime_args! { cep18_token::ARG_NAME => TOKEN_NAME },
+        )
+        .build();
+        self.builder.exec(upgrade_request).expect_success().commit();
+        self.token_hash = self.builder.installed_contract_hash(
+            *DEFAULT_ACCOUNT_ADDR,
+            &format!("cep18_contract_hash_{TOKEN_NAME}"),
+        );
+    }
+
+    fn call(
+        &mut self,
+        sender: AccountHash,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> Result<(), ApiError> {
+        let exec_request = ExecuteRequestBuilder::contract_call_by_hash(
+            sender,
+            self.token_hash.into(),
+            entry_point,
+            args,
+        )
+        .build();
+        self.builder.exec(exec_request).commit();
+        self.last_result()
+    }
+
+    /// Calls `entry_point` in a block with the given block time in milliseconds.
+    fn call_at(
+        &mut self,
+        block_time: u64,
+        sender: AccountHash,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> Result<(), ApiError> {
+        let exec_request = ExecuteRequestBuilder::contract_call_by_hash(
+            sender,
+            self.token_hash.into(),
+            entry_point,
+            args,
+        )
+        .with_block_time(block_time)
+        .build();
+        self.builder.exec(exec_request).commit();
+        self.last_result()
+    }
+
+    fn last_result(&self) -> Result<(), ApiError> {
+        match self.builder.get_error() {
+            None => Ok(()),
+            Some(engine_state::Error::Exec(execution::ExecError::Revert(api_error))) => {
+                Err(api_error)
+            }
+            Some(error) => panic!("unexpected execution error: {error:?}"),
+        }
+    }
+
+    fn mint(&mut self, owner: Key, amount: u64) {
+        let args = cep18::cep18::mint::Args {
+            owner,
+            amount: U256::from(amount),
+        };
+        self.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::mint::NAME,
+            args.into_runtime_args(),
+        )
+        .expect("should mint");
+    }
+
+    fn transfer(&mut self, sender: AccountHash, recipient: Key, amount: u64) {
+        let args = cep18::cep18::transfer::Args {
+            recipient,
+            amount: U256::from(amount),
+        };
+        self.call(
+            sender,
+            cep18::cep18::transfer::NAME,
+            args.into_runtime_args(),
+        )
+        .expect("should transfer");
+    }
+
+    fn approve(&mut self, owner: AccountHash, spender: Key, amount: u64) {
+        let args = cep18::cep18::approve::Args {
+            spender,
+            amount: U256::from(amount),
+            expiry: None,
+        };
+        self.call(owner, cep18::cep18::approve::NAME, args.into_runtime_args())
+            .expect("should approve");
+    }
+
+    fn holder_at(&mut self, index: u64) -> Result<(), ApiError> {
+        self.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::holder_at::NAME,
+            cep18::cep18::holder_at::Args { index }.into_runtime_args(),
+        )
+    }
+
+    fn contract_uref(&self, name: &str) -> Option<URef> {
+        let contract = self
+            .builder
+            .get_contract(self.token_hash)
+            .expect("token contract should exist");
+        contract
+            .named_keys()
+            .get(name)
+            .and_then(Key::as_uref)
+            .copied()
+    }
+
+    fn entry_point_args(&self, name: &str) -> Vec<String> {
+        self.builder
+            .get_contract(self.token_hash)
+            .expect("token contract should exist")
+            .entry_points()
+            .get(name)
+            .expect("entry point should exist")
+            .args()
+            .iter()
+            .map(|parameter| parameter.name().to_string())
+            .collect()
+    }
+
+    fn has_entry_point(&self, name: &str) -> bool {
+        self.builder
+            .get_contract(self.token_hash)
+            .expect("token contract should exist")
+            .entry_points()
+            .has_entry_point(name)
+    }
+
+    fn named_string(&self, name: &str) -> Option<String> {
+        let uref = self.contract_uref(name)?;
+        let value = self
+            .builder
+            .query(None, Key::URef(uref), &[])
+            .expect("should query the named key");
+        Some(
+            value
+                .as_cl_value()
+                .expect("should be a CLValue")
+                .clone()
+                .into_t()
+                .expect("should be a String"),
+        )
+    }
+
+    fn query_dictionary<T: CLTyped + FromBytes>(&self, dictionary: &str, item_key: &str) -> T {
+        self.try_query_dictionary(dictionary, item_key)
+            .expect("should query dictionary item")
+    }
+
+    fn try_query_dictionary<T: CLTyped + FromBytes>(
+        &self,
+        dictionary: &str,
+        item_key: &str,
+    ) -> Option<T> {
+        let uref = self
+            .contract_uref(dictionary)
+            .expect("dictionary should exist");
+        let item = self
+            .builder
+            .query_dictionary_item(None, uref, item_key)
+            .ok()?;
+        Some(
+            item.into_cl_value()
+                .expect("should be a CLValue")
+                .into_t()
+                .expect("should have the expected type"),
+        )
+    }
+
+    /// Reads the holders the way an off-chain indexer would, straight from the dictionaries.
+    fn holders(&self) -> Vec<Key> {
+        let count: u64 = self.query_dictionary(DICT_HOLDERS, VEC_LENGTH_KEY);
+        (0..count)
+            .map(|index| self.query_dictionary(DICT_HOLDERS, &index.dictionary_key()))
+            .collect()
+    }
+
+    fn balance_of(&self, owner: Key) -> U256 {
+        self.query_dictionary(DICT_BALANCES, &balance_item_key(&owner))
+    }
+
+    /// Exports `(owner, spender, amount)` triples following the read protocol of the index.
+    fn allowances_snapshot(&self) -> Vec<(Key, Key, U256)> {
+        let count: u64 = self
+            .try_query_dictionary(DICT_ALLOWANCE_PAIRS, VEC_LENGTH_KEY)
+            .unwrap_or(0);
+        (0..count)
+            .map(|index| {
+                let (owner, spender): (Key, Key) =
+                    self.query_dictionary(DICT_ALLOWANCE_PAIRS, &index.dictionary_key());
+                let amount =
+                    self.query_dictionary(DICT_ALLOWANCES, &allowance_item_key(&owner, &spender));
+                (owner, spender, amount)
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn builder_extension_reads_balances() {
+    let mut fixture = Fixture::new(None);
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 300);
+    let token = EntityAddr::SmartContract(fixture.token_hash.value());
+
+    let builder = &fixture.builder;
+    for (owner, balance) in [
+        (Key::Account(*DEFAULT_ACCOUNT_ADDR), 700u64),
+        (Key::Account(ALICE), 300),
+    ] {
+        assert_eq!(
+            builder.read_dictionary_value(token, DICT_BALANCES, &balance_item_key(&owner)),
+            Some(U256::from(balance))
+        );
+    }
+    assert_eq!(
+        builder.read_dictionary_value::<U256>(
+            token,
+            DICT_BALANCES,
+            &balance_item_key(&Key::Account(BOB))
+        ),
+        None
+    );
+    let name: String = builder.read_named_value(token, cep18_token::ARG_NAME);
+    assert_eq!(name, TOKEN_NAME);
+}
+
+#[test]
+fn enumeration_tracks_holders() {
+    let installer = Key::Account(*DEFAULT_ACCOUNT_ADDR);
+    let mut fixture = Fixture::new(Some(true));
+    assert_eq!(fixture.holders(), [installer]);
+
+    fixture.mint(Key::Account(ALICE), 100);
+    assert_eq!(fixture.holders(), [installer, Key::Account(ALICE)]);
+
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(BOB), 10);
+    assert_eq!(
+        fixture.holders(),
+        [installer, Key::Account(ALICE), Key::Account(BOB)]
+    );
+
+    // Transferring the full balance away removes the holder, moving the last one into its slot.
+    fixture.transfer(ALICE, Key::Account(BOB), 100);
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::zero());
+    assert_eq!(fixture.holders(), [installer, Key::Account(BOB)]);
+
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 990);
+    assert_eq!(fixture.holders(), [Key::Account(BOB), Key::Account(ALICE)]);
+    let total: U256 = fixture
+        .holders()
+        .into_iter()
+        .map(|holder| fixture.balance_of(holder))
+        .fold(U256::zero(), |total, balance| total + balance);
+    assert_eq!(total, U256::from(1_100u64));
+
+    assert_eq!(fixture.holder_at(1), Ok(()));
+    assert_eq!(
+        fixture.holder_at(2),
+        Err(Cep18Error::HolderIndexOutOfRange.into())
+    );
+}
+
+#[test]
+fn metadata_is_stored_when_passed_at_install() {
+    let mut extra_args = RuntimeArgs::new();
+    extra_args
+        .insert(ARG_ICON_URL, "ipfs://token-icon".to_string())
+        .unwrap();
+    extra_args
+        .insert(ARG_WEBSITE, "https://token.example".to_string())
+        .unwrap();
+    let mut fixture = Fixture::with_args(extra_args);
+
+    assert!(fixture.has_entry_point(ENTRY_POINT_METADATA));
+    assert_eq!(
+        fixture.named_string(ARG_ICON_URL).as_deref(),
+        Some("ipfs://token-icon")
+    );
+    assert_eq!(
+        fixture.named_string(ARG_WEBSITE).as_deref(),
+        Some("https://token.example")
+    );
+    assert_eq!(
+        fixture.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::metadata::NAME,
+            RuntimeArgs::default(),
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn metadata_is_off_by_default() {
+    let fixture = Fixture::new(None);
+
+    assert!(!fixture.has_entry_point(ENTRY_POINT_METADATA));
+    assert_eq!(fixture.contract_uref(ARG_ICON_URL), None);
+    assert_eq!(fixture.contract_uref(ARG_WEBSITE), None);
+}
+
+#[test]
+fn enumeration_is_off_by_default() {
+    let mut fixture = Fixture::new(None);
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 10);
+
+    assert_eq!(fixture.contract_uref(DICT_HOLDERS), None);
+    assert_eq!(fixture.contract_uref(DICT_HOLDER_INDICES), None);
+    assert_eq!(fixture.contract_uref(DICT_ALLOWANCE_PAIRS), None);
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::from(10u64));
+    assert_eq!(
+        fixture.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::holder_count::NAME,
+            RuntimeArgs::default(),
+        ),
+        Err(Cep18Error::EnumerationDisabled.into())
+    );
+}
+
+#[test]
+fn allowance_index_tracks_approvals() {
+    let installer = Key::Account(*DEFAULT_ACCOUNT_ADDR);
+    let mut extra_args = RuntimeArgs::new();
+    extra_args.insert("enable_allowance_index", true).unwrap();
+    let mut fixture = Fixture::with_args(extra_args);
+    assert_eq!(fixture.allowances_snapshot(), []);
+
+    fixture.approve(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 10);
+    fixture.approve(*DEFAULT_ACCOUNT_ADDR, Key::Account(BOB), 20);
+    fixture.approve(ALICE, Key::Account(BOB), 5);
+    assert_eq!(
+        fixture.allowances_snapshot(),
+        [
+            (installer, Key::Account(ALICE), U256::from(10u64)),
+            (installer, Key::Account(BOB), U256::from(20u64)),
+            (Key::Account(ALICE), Key::Account(BOB), U256::from(5u64)),
+        ]
+    );
+
+    // Changing an allowance keeps its index, revoking it moves the last pair into its slot.
+    fixture.approve(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 15);
+    fixture.approve(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 0);
+    assert_eq!(
+        fixture.allowances_snapshot(),
+        [
+            (Key::Account(ALICE), Key::Account(BOB), U256::from(5u64)),
+            (installer, Key::Account(BOB), U256::from(20u64)),
+        ]
+    );
+}
+
+#[test]
+fn allowance_expiry_limits_transfer_from() {
+    let installer = Key::Account(*DEFAULT_ACCOUNT_ADDR);
+    let mut extra_args = RuntimeArgs::new();
+    extra_args
+        .insert(ARG_ENABLE_ALLOWANCE_EXPIRY, true)
+        .unwrap();
+    let mut fixture = Fixture::with_args(extra_args);
+    assert_eq!(
+        fixture.entry_point_args(ENTRY_POINT_APPROVE),
+        ["spender", "amount", "expiry"]
+    );
+    let timed_allowance = |fixture: &Fixture| -> TimedAllowance {
+        fixture.query_dictionary(
+            DICT_ALLOWANCES,
+            &allowance_item_key(&installer, &Key::Account(ALICE)),
+        )
+    };
+    let transfer_from = |fixture: &mut Fixture, block_time: u64, amount: u64| {
+        let args = cep18::cep18::transfer_from::Args {
+            owner: installer,
+            recipient: Key::Account(BOB),
+            amount: U256::from(amount),
+        };
+        fixture.call_at(
+            block_time,
+            ALICE,
+            cep18::cep18::transfer_from::NAME,
+            args.into_runtime_args(),
+        )
+    };
+    let approve = |fixture: &mut Fixture, block_time: u64, amount: u64, expiry: Option<u64>| {
+        let args = cep18::cep18::approve::Args {
+            spender: Key::Account(ALICE),
+            amount: U256::from(amount),
+            expiry,
+        };
+        fixture.call_at(
+            block_time,
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::approve::NAME,
+            args.into_runtime_args(),
+        )
+    };
+
+    approve(&mut fixture, 1_000, 100, Some(2_000)).expect("should approve");
+    assert_eq!(transfer_from(&mut fixture, 1_500, 40), Ok(()));
+    assert_eq!(fixture.balance_of(Key::Account(BOB)), U256::from(40u64));
+
+    // Increasing a fresh allowance keeps its expiry
+    let args = cep18::cep18::increase_allowance::Args {
+        spender: Key::Account(ALICE),
+        amount: U256::from(10u64),
+    };
+    fixture
+        .call_at(
+            1_600,
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::increase_allowance::NAME,
+            args.into_runtime_args(),
+        )
+        .expect("should increase the allowance");
+    assert_eq!(
+        timed_allowance(&fixture),
+        TimedAllowance {
+            amount: U256::from(70u64),
+            expiry: 2_000,
+        }
+    );
+
+    assert_eq!(
+        transfer_from(&mut fixture, 2_000, 1),
+        Err(Cep18Error::AllowanceExpired.into())
+    );
+    assert_eq!(fixture.balance_of(Key::Account(BOB)), U256::from(40u64));
+
+    // Approving again without an expiry revives the allowance for good
+    approve(&mut fixture, 2_500, 30, None).expect("should approve");
+    assert_eq!(transfer_from(&mut fixture, 1_000_000, 30), Ok(()));
+    assert_eq!(
+        timed_allowance(&fixture),
+        TimedAllowance {
+            amount: U256::zero(),
+            expiry: TimedAllowance::NEVER,
+        }
+    );
+    assert_eq!(fixture.balance_of(Key::Account(BOB)), U256::from(70u64));
+}
+
+#[test]
+fn allowance_expiry_is_off_by_default() {
+    let mut fixture = Fixture::new(None);
+    assert_eq!(
+        fixture.entry_point_args(ENTRY_POINT_APPROVE),
+        ["spender", "amount"]
+    );
+
+    let args = cep18::cep18::approve::Args {
+        spender: Key::Account(ALICE),
+        amount: U256::from(10u64),
+        expiry: Some(2_000),
+    };
+    assert_eq!(
+        fixture.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::approve::NAME,
+            args.into_runtime_args(),
+        ),
+        Err(Cep18Error::AllowanceExpiryDisabled.into())
+    );
+
+    // Allowances stay bare amounts that never expire
+    fixture.approve(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 10);
+    let amount: U256 = fixture.query_dictionary(
+        DICT_ALLOWANCES,
+        &allowance_item_key(&Key::Account(*DEFAULT_ACCOUNT_ADDR), &Key::Account(ALICE)),
+    );
+    assert_eq!(amount, U256::from(10u64));
+}
+
+#[test]
+fn frozen_accounts_can_not_move_tokens() {
+    let installer = Key::Account(*DEFAULT_ACCOUNT_ADDR);
+    let mut extra_args = RuntimeArgs::new();
+    extra_args.insert(ARG_ENABLE_FREEZING, true).unwrap();
+    let mut fixture = Fixture::with_args(extra_args);
+    assert!(fixture.has_entry_point(ENTRY_POINT_FREEZE));
+    assert!(fixture.has_entry_point(ENTRY_POINT_UNFREEZE));
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 100);
+    fixture.approve(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 50);
+
+    let change_frozen = |fixture: &mut Fixture, admin: AccountHash, account: Key, frozen: bool| {
+        let entry_point = if frozen {
+            cep18::cep18::freeze::NAME
+        } else {
+            cep18::cep18::unfreeze::NAME
+        };
+        let args = cep18::cep18::freeze::Args { account };
+        fixture.call(admin, entry_point, args.into_runtime_args())
+    };
+    let transfer = |fixture: &mut Fixture, sender: AccountHash, recipient: AccountHash| {
+        let args = cep18::cep18::transfer::Args {
+            recipient: Key::Account(recipient),
+            amount: U256::from(10u64),
+        };
+        fixture.call(
+            sender,
+            cep18::cep18::transfer::NAME,
+            args.into_runtime_args(),
+        )
+    };
+    let transfer_from = |fixture: &mut Fixture| {
+        let args = cep18::cep18::transfer_from::Args {
+            owner: installer,
+            recipient: Key::Account(BOB),
+            amount: U256::from(10u64),
+        };
+        fixture.call(
+            ALICE,
+            cep18::cep18::transfer_from::NAME,
+            args.into_runtime_args(),
+        )
+    };
+    let approve = |fixture: &mut Fixture, owner: AccountHash, spender: AccountHash| {
+        let args = cep18::cep18::approve::Args {
+            spender: Key::Account(spender),
+            amount: U256::from(10u64),
+            expiry: None,
+        };
+        fixture.call(owner, cep18::cep18::approve::NAME, args.into_runtime_args())
+    };
+    let frozen = Err(Cep18Error::AccountFrozen.into());
+
+    assert_eq!(
+        change_frozen(&mut fixture, BOB, Key::Account(ALICE), true),
+        Err(Cep18Error::InsufficientRights.into())
+    );
+    assert_eq!(
+        change_frozen(
+            &mut fixture,
+            *DEFAULT_ACCOUNT_ADDR,
+            Key::Account(ALICE),
+            true
+        ),
+        Ok(())
+    );
+    let is_frozen = |fixture: &Fixture| -> bool {
+        fixture.query_dictionary(DICT_FROZEN, &Key::Account(ALICE).dictionary_key())
+    };
+    assert!(is_frozen(&fixture));
+
+    // A frozen sender, recipient or spender blocks the operation
+    assert_eq!(transfer(&mut fixture, ALICE, BOB), frozen);
+    assert_eq!(transfer(&mut fixture, *DEFAULT_ACCOUNT_ADDR, ALICE), frozen);
+    assert_eq!(transfer_from(&mut fixture), frozen);
+    assert_eq!(approve(&mut fixture, ALICE, BOB), frozen);
+    assert_eq!(approve(&mut fixture, *DEFAULT_ACCOUNT_ADDR, ALICE), frozen);
+    assert_eq!(transfer(&mut fixture, *DEFAULT_ACCOUNT_ADDR, BOB), Ok(()));
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::from(100u64));
+
+    assert_eq!(
+        change_frozen(
+            &mut fixture,
+            *DEFAULT_ACCOUNT_ADDR,
+            Key::Account(ALICE),
+            false
+        ),
+        Ok(())
+    );
+    assert!(!is_frozen(&fixture));
+    assert_eq!(transfer(&mut fixture, ALICE, BOB), Ok(()));
+    assert_eq!(transfer_from(&mut fixture), Ok(()));
+    assert_eq!(fixture.balance_of(Key::Account(BOB)), U256::from(30u64));
+}
+
+#[test]
+fn freezing_is_off_by_default() {
+    let mut fixture = Fixture::new(None);
+
+    assert!(!fixture.has_entry_point(ENTRY_POINT_FREEZE));
+    assert!(!fixture.has_entry_point(ENTRY_POINT_UNFREEZE));
+    assert_eq!(fixture.contract_uref(DICT_FROZEN), None);
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 10);
+    assert_eq!(fixture.balance_of(Key::Account(ALICE)), U256::from(10u64));
+}
+
+#[test]
+fn transfer_hook_is_off_by_default() {
+    let mut fixture = Fixture::new(None);
+
+    // Without the hook, tokens can be sent to a contract that has no `on_token_received`.
+    let contract = Key::Hash([9; 32]);
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, contract, 10);
+    assert_eq!(fixture.balance_of(contract), U256::from(10u64));
+}
+
+#[test]
+fn mint_and_burn_pause_independently() {
+    let installer = Key::Account(*DEFAULT_ACCOUNT_ADDR);
+    let mut fixture = Fixture::new(None);
+    assert!(fixture.has_entry_point(ENTRY_POINT_SET_MINT_PAUSED));
+    assert!(fixture.has_entry_point(ENTRY_POINT_SET_BURN_PAUSED));
+
+    let set_paused = |fixture: &mut Fixture, sender: AccountHash, entry_point, paused| {
+        let args = cep18::cep18::set_mint_paused::Args { paused };
+        fixture.call(sender, entry_point, args.into_runtime_args())
+    };
+    let mint = |fixture: &mut Fixture| {
+        let args = cep18::cep18::mint::Args {
+            owner: installer,
+            amount: U256::from(10u64),
+        };
+        fixture.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::mint::NAME,
+            args.into_runtime_args(),
+        )
+    };
+    let burn = |fixture: &mut Fixture| {
+        let args = cep18::cep18::burn::Args {
+            owner: installer,
+            amount: U256::from(10u64),
+        };
+        fixture.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            cep18::cep18::burn::NAME,
+            args.into_runtime_args(),
+        )
+    };
+    let is_paused = |fixture: &Fixture, name: &str| -> bool {
+        let uref = fixture.contract_uref(name).expect("flag should be stored");
+        fixture
+            .builder
+            .query(None, Key::URef(uref), &[])
+            .expect("should query the flag")
+            .as_cl_value()
+            .expect("should be a CLValue")
+            .clone()
+            .into_t()
+            .expect("should be a bool")
+    };
+    assert!(!is_paused(&fixture, KEY_MINT_PAUSED));
+    assert!(!is_paused(&fixture, KEY_BURN_PAUSED));
+
+    assert_eq!(
+        set_paused(&mut fixture, BOB, ENTRY_POINT_SET_MINT_PAUSED, true),
+        Err(Cep18Error::InsufficientRights.into())
+    );
+    assert_eq!(
+        set_paused(
+            &mut fixture,
+            *DEFAULT_ACCOUNT_ADDR,
+            ENTRY_POINT_SET_MINT_PAUSED,
+            true
+        ),
+        Ok(())
+    );
+    assert!(is_paused(&fixture, KEY_MINT_PAUSED));
+    assert_eq!(mint(&mut fixture), Err(Cep18Error::MintPaused.into()));
+    assert_eq!(burn(&mut fixture), Ok(()));
+    fixture.transfer(*DEFAULT_ACCOUNT_ADDR, Key::Account(ALICE), 10);
+
+    set_paused(
+        &mut fixture,
+        *DEFAULT_ACCOUNT_ADDR,
+        ENTRY_POINT_SET_MINT_PAUSED,
+        false,
+    )
+    .expect("should resume minting");
+    set_paused(
+        &mut fixture,
+        *DEFAULT_ACCOUNT_ADDR,
+        ENTRY_POINT_SET_BURN_PAUSED,
+        true,
+    )
+    .expect("should pause burning");
+    assert!(is_paused(&fixture, KEY_BURN_PAUSED));
+    assert_eq!(burn(&mut fixture), Err(Cep18Error::BurnPaused.into()));
+    assert_eq!(mint(&mut fixture), Ok(()));
+    assert_eq!(fixture.balance_of(installer), U256::from(990u64));
+}
+
+#[test]
+fn pause_entry_points_need_mint_burn() {
+    let mut extra_args = RuntimeArgs::new();
+    extra_args.insert(ARG_ENABLE_MINT_BURN, 0u8).unwrap();
+    let fixture = Fixture::with_args(extra_args);
+
+    assert!(!fixture.has_entry_point(ENTRY_POINT_SET_MINT_PAUSED));
+    assert!(!fixture.has_entry_point(ENTRY_POINT_SET_BURN_PAUSED));
+    assert_eq!(fixture.contract_uref(KEY_MINT_PAUSED), None);
+    assert_eq!(fixture.contract_uref(KEY_BURN_PAUSED), None);
+}
+
+#[test]
+fn permits_let_relayers_submit_signed_approvals() {
+    let mut fixture = Fixture::new(None);
+    let secret_key = SecretKey::ed25519_from_bytes([7; 32]).unwrap();
+    let owner = PublicKey::from(&secret_key);
+    let owner_key = Key::Account(owner.to_account_hash());
+    fixture.mint(owner_key, 100);
+
+    let token = *fixture
+        .builder
+        .get_contract(fixture.token_hash)
+        .expect("token contract should exist")
+        .named_keys()
+        .get(ARG_PACKAGE_HASH)
+        .expect("token should know its package");
+    let deadline = 2_000;
+    let sign = |secret_key: &SecretKey, amount: u64, nonce: u64| {
+        let message = cep18::permit::permit_message(
+            token,
+            &owner,
+            Key::Account(ALICE),
+            U256::from(amount),
+            nonce,
+            deadline,
+        );
+        let signature = crypto::sign(message, secret_key, &PublicKey::from(secret_key));
+        Bytes::from(signature.to_bytes().unwrap())
+    };
+    let permit = |fixture: &mut Fixture, block_time: u64, nonce: Option<u64>, signature| {
+        let args = cep18::cep18::permit::Args {
+            owner: owner.clone(),
+            spender: Key::Account(ALICE),
+            amount: U256::from(60u64),
+            deadline,
+            nonce,
+            signature,
+        };
+        // Submitted and paid for by Bob rather than the owner
+        fixture.call_at(
+            block_time,
+            BOB,
+            cep18::cep18::permit::NAME,
+            args.into_runtime_args(),
+        )
+    };
+
+    assert_eq!(
+        permit(&mut fixture, deadline, None, sign(&secret_key, 60, 0)),
+        Err(Cep18Error::PermitExpired.into())
+    );
+    let other_key = SecretKey::ed25519_from_bytes([8; 32]).unwrap();
+    for signature in [sign(&secret_key, 61, 0), sign(&other_key, 60, 0)] {
+        assert_eq!(
+            permit(&mut fixture, 1_000, None, signature),
+            Err(Cep18Error::InvalidSignature.into())
+        );
+    }
+    assert_eq!(
+        permit(&mut fixture, 1_000, Some(1), sign(&secret_key, 60, 1)),
+        Err(Cep18Error::PermitNonceMismatch.into())
+    );
+
+    assert_eq!(
+        permit(&mut fixture, 1_000, Some(0), sign(&secret_key, 60, 0)),
+        Ok(())
+    );
+    assert_eq!(
+        fixture.query_dictionary::<u64>(DICT_PERMIT_NONCES, &collections_item_key(&owner_key)),
+        1
+    );
+    assert_eq!(
+        permit(&mut fixture, 1_000, None, sign(&secret_key, 60, 0)),
+        Err(Cep18Error::InvalidSignature.into())
+    );
+    assert_eq!(
+        permit(&mut fixture, 1_000, Some(0), sign(&secret_key, 60, 0)),
+        Err(Cep18Error::PermitNonceMismatch.into())
+    );
+
+    let args = cep18::cep18::transfer_from::Args {
+        owner: owner_key,
+        recipient: Key::Account(BOB),
+        amount: U256::from(60u64),
+    };
+    fixture
+        .call(
+            ALICE,
+            cep18::cep18::transfer_from::NAME,
+            args.into_runtime_args(),
+        )
+        .expect("should spend the permitted allowance");
+    assert_eq!(fixture.balance_of(Key::Account(BOB)), U256::from(60u64));
+    assert_eq!(fixture.balance_of(owner_key), U256::from(40u64));
+}
+
+#[test]
+fn upgraded_tokens_accept_permits() {
+    let mut fixture = Fixture::new(None);
+    let previous_hash = fixture.token_hash;
+    let nonces = fixture.contract_uref(DICT_PERMIT_NONCES);
+    assert!(nonces.is_some());
+    fixture.upgrade();
+    assert_ne!(fixture.token_hash, previous_hash);
+    // The nonces of the previous version carry over
+    assert_eq!(fixture.contract_uref(DICT_PERMIT_NONCES), nonces);
+
+    let secret_key = SecretKey::ed25519_from_bytes([7; 32]).unwrap();
+    let owner = PublicKey::from(&secret_key);
+    let owner_key = Key::Account(owner.to_account_hash());
+    fixture.mint(owner_key, 100);
+    let token = *fixture
+        .builder
+        .get_contract(fixture.token_hash)
+        .expect("token contract should exist")
+        .named_keys()
+        .get(ARG_PACKAGE_HASH)
+        .expect("token should know its package");
+    let message = cep18::permit::permit_message(
+        token,
+        &owner,
+        Key::Account(ALICE),
+        U256::from(60u64),
+        0,
+        2_000,
+    );
+    let signature = crypto::sign(message, &secret_key, &owner);
+    let args = cep18::cep18::permit::Args {
+        owner,
+        spender: Key::Account(ALICE),
+        amount: U256::from(60u64),
+        deadline: 2_000,
+        nonce: Some(0),
+        signature: Bytes::from(signature.to_bytes().unwrap()),
+    };
+    assert_eq!(
+        fixture.call_at(
+            1_000,
+            BOB,
+            cep18::cep18::permit::NAME,
+            args.into_runtime_args()
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        fixture.query_dictionary::<u64>(DICT_PERMIT_NONCES, &collections_item_key(&owner_key)),
+        1
+    );
+}
@@ -0,0 +1,15 @@
+use veles_casper_contract_api::{
+    casper_types::account::AccountHash,
+    veles_casper_contract_macros::{CasperMessage, CasperSerialize},
+};
+
+/// Emitted on every `increment`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, CasperMessage, CasperSerialize)]
+#[casper(topic_name = "counter_incremented")]
+pub struct Incremented {
+    pub caller: AccountHash,
+    /// The total count after the increment.
+    pub count: u64,
+    /// The count of `caller` after the increment.
+    pub caller_count: u64,
+}
@@ -0,0 +1,151 @@
+//! A counter that every account can increment and only its owner can reset.
+//!
+//! The total count lives in a [`TypedURef`] and the count of each account in a [`Mapping`], the
+//! owner is managed by the [`ownable`] extras module and each increment emits an
+//! [`event::Incremented`] message. The totals after every increment are kept in a [`Vector`],
+//! which the `history` entry point returns one [`Page`] at a time.
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+pub mod event;
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+
+use veles_casper_contract_api::{
+    casper_types::{NamedKeys, account::AccountHash},
+    collections::{mapping::Mapping, page::Page, vector::Vector},
+    prelude::*,
+};
+use veles_casper_contract_extras::ownable;
+
+pub const HASH_KEY_NAME: &str = "counter_hash";
+pub static HASH_KEY: NamedKey = NamedKey::from_name(HASH_KEY_NAME);
+pub const PACKAGE_HASH_KEY_NAME: &str = "counter_package_hash";
+pub const ACCESS_KEY_NAME: &str = "counter_access";
+
+pub const COUNT_KEY_NAME: &str = "count";
+pub const ACCOUNT_COUNTS_DICT: &str = "account_counts";
+pub const HISTORY_DICT: &str = "count_history";
+
+static COUNT: NamedKey = NamedKey::from_name(COUNT_KEY_NAME);
+/// The total number of increments since the last reset.
+pub static COUNT_TUREF: TypedURef<u64> = TypedURef::from_named_key(&COUNT);
+/// The number of increments of each account since the last reset of its count.
+pub static ACCOUNT_COUNTS: Mapping<AccountHash, u64> =
+    Mapping::from_named_key(NamedKey::from_name(ACCOUNT_COUNTS_DICT));
+/// The total count after every increment, across resets.
+pub static HISTORY: Vector<u64> = Vector::from_named_key(NamedKey::from_name(HISTORY_DICT));
+
+#[casper(contract)]
+pub mod counter {
+    use super::*;
+
+    /// Increments the total count and the count of the calling account, returning the total.
+    #[casper(export)]
+    pub fn increment() -> Result<u64, ApiError> {
+        add(1)
+    }
+
+    /// Increments the counts by `amount`, like `increment`. Exported as `increment-by`, taking
+    /// the amount as the `by` argument.
+    #[casper(export, name = "increment-by")]
+    pub fn increment_by(#[casper(arg = "by")] amount: u64) -> Result<u64, ApiError> {
+        add(amount)
+    }
+
+    #[casper(export)]
+    pub fn count() -> Result<u64, ApiError> {
+        read_count()
+    }
+
+    #[casper(export)]
+    pub fn count_of(account: AccountHash) -> Result<u64, ApiError> {
+        account_count(account)
+    }
+
+    /// Returns the totals after the increments from the `cursor`-th one, at most `limit` of them.
+    #[casper(export)]
+    pub fn history(cursor: Option<u64>, limit: u64) -> Result<Page<u64>, ApiError> {
+        HISTORY.page(cursor, limit)
+    }
+
+    /// Resets the total count to zero, keeping the counts of the accounts.
+    #[casper(export)]
+    pub fn reset() -> Result<(), ApiError> {
+        ownable::ensure_owner()?;
+        COUNT_TUREF.write(0)
+    }
+}
+
+/// Adds `amount` to the total count and the count of the calling account, returning the total.
+fn add(amount: u64) -> Result<u64, ApiError> {
+    let caller = utils::get_immediate_account()?;
+    let count = read_count()?
+        .checked_add(amount)
+        .ok_or(ApiError::Unhandled)?;
+    let caller_count = account_count(caller)?
+        .checked_add(amount)
+        .ok_or(ApiError::Unhandled)?;
+    COUNT_TUREF.write(count)?;
+    ACCOUNT_COUNTS.insert(&caller, caller_count)?;
+    HISTORY.push(count)?;
+
+    utils::emit_message(event::Incremented {
+        caller,
+        count,
+        caller_count,
+    })?;
+    Ok(count)
+}
+
+fn read_count() -> Result<u64, ApiError> {
+    COUNT_TUREF.read()?.ok_or(ApiError::MissingKey)
+}
+
+fn account_count(account: AccountHash) -> Result<u64, ApiError> {
+    Ok(ACCOUNT_COUNTS.get(&account)?.unwrap_or_default())
+}
+
+/// Creates the named keys of the counter, owned by `owner`.
+pub fn install(named_keys: &mut NamedKeys, owner: AccountHash) -> Result<(), ApiError> {
+    named_keys.insert(ownable::OWNER_KEY_NAME.name().into(), Key::Account(owner));
+    COUNT
+        .get_or_init(|| utils::new_uref_key(0u64))?
+        .append_to_named_keys(named_keys)?;
+    ACCOUNT_COUNTS
+        .named_uref()
+        .get_or_init(utils::new_dictionary_key)?
+        .append_to_named_keys(named_keys)?;
+    HISTORY
+        .named_uref()
+        .get_or_init(utils::new_dictionary_key)?
+        .append_to_named_keys(named_keys)?;
+    Ok(())
+}
+
+#[casper(export)]
+pub fn call() -> Result<(), ApiError> {
+    let mut named_keys = NamedKeys::new();
+    install(&mut named_keys, runtime::get_caller())?;
+
+    let mut entry_points = ownable::ownable::entry_points_vec();
+    entry_points.extend(counter::entry_points_vec());
+
+    let mut messages = BTreeMap::new();
+    messages.insert(
+        event::Incremented::TOPIC_NAME.into(),
+        MessageTopicOperation::Add,
+    );
+
+    let (contract_hash, _contract_version) = storage::new_contract(
+        entry_points.into(),
+        Some(named_keys),
+        Some(PACKAGE_HASH_KEY_NAME.into()),
+        Some(ACCESS_KEY_NAME.into()),
+        Some(messages),
+    );
+
+    HASH_KEY.set(Key::Hash(contract_hash.value()))?;
+    Ok(())
+}
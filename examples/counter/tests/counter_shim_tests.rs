@@ -0,0 +1,156 @@
+//! Unit tests of the counter entry points running under the ffi shim, without any wasm.
+//!
+//! The named keys of the counter cache their keys in statics shared by all tests, so every test
+//! creates the keys of the counter in the same order in a fresh environment, which puts them at
+//! the same deterministic addresses.
+use veles_casper_contract_api::{
+    casper_contract::contract_api::{runtime, storage},
+    casper_types::{AccessRights, Key, URef, account::AccountHash},
+    collections::page::{MAX_PAGE_LIMIT, Page},
+    error::UniversalError,
+    macro_support::CasperMessage,
+    veles_casper_ffi_shim::{
+        AccountConfig, Env, EnvBuilder, HostFunction, dispatch_with, run_entry_point,
+    },
+};
+use veles_casper_contract_extras::ownable::{self, OwnableError};
+
+use counter::{
+    ACCOUNT_COUNTS, ACCOUNT_COUNTS_DICT, COUNT_KEY_NAME, COUNT_TUREF, HISTORY_DICT,
+    event::Incremented,
+};
+
+const OWNER: AccountHash = AccountHash::new([1; 32]);
+const BOB: AccountHash = AccountHash::new([2; 32]);
+
+unsafe extern "C-unwind" {
+    #[link_name = "increment"]
+    fn increment_entry_point();
+    #[link_name = "count"]
+    fn count_entry_point();
+    #[link_name = "reset"]
+    fn reset_entry_point();
+    #[link_name = "history"]
+    fn history_entry_point();
+}
+
+/// Returns an environment in which `caller` calls a counter owned by `OWNER`.
+fn installed_env(caller: AccountHash) -> Env {
+    let main_purse = URef::new([0xff; 32], AccessRights::READ_ADD_WRITE);
+    let env = EnvBuilder::new()
+        .with_account(AccountConfig::new(caller, main_purse))
+        .build();
+    dispatch_with(env.clone(), |env| {
+        let count = storage::new_uref(0u64);
+        runtime::put_key(COUNT_KEY_NAME, count.into());
+        storage::new_dictionary(ACCOUNT_COUNTS_DICT).unwrap();
+        storage::new_dictionary(HISTORY_DICT).unwrap();
+        runtime::put_key(ownable::OWNER_KEY_NAME.name(), Key::Account(OWNER));
+        env.trace();
+    });
+    env
+}
+
+fn increment(env: &Env) -> u64 {
+    run_entry_point(env.clone(), |_env| unsafe { increment_entry_point() })
+        .expect("increment should succeed")
+}
+
+#[test]
+fn increment_counts_per_account_and_emits_message() {
+    let env = installed_env(BOB);
+    assert_eq!(increment(&env), 1);
+
+    let trace = env.trace();
+    assert!(trace.contains(&HostFunction::CasperLoadCallerInformation));
+    assert_eq!(
+        trace.iter().rev().take(2).collect::<Vec<_>>(),
+        [&HostFunction::CasperRet, &HostFunction::CasperEmitMessage]
+    );
+
+    assert_eq!(increment(&env), 2);
+    dispatch_with(env.clone(), |_env| {
+        assert_eq!(COUNT_TUREF.read(), Ok(Some(2)));
+        assert_eq!(ACCOUNT_COUNTS.get(&BOB), Ok(Some(2)));
+        assert_eq!(ACCOUNT_COUNTS.get(&OWNER), Ok(None));
+    });
+
+    let messages = env.messages();
+    assert_eq!(messages.len(), 2);
+    assert!(
+        messages
+            .iter()
+            .all(|message| message.topic_name == Incremented::TOPIC_NAME
+                && message.topic_name_hash() == Incremented::TOPIC_NAME_HASH)
+    );
+    let expected = Incremented {
+        caller: BOB,
+        count: 2,
+        caller_count: 2,
+    };
+    assert_eq!(
+        messages[1].payload,
+        expected.into_message_payload().unwrap()
+    );
+}
+
+#[test]
+fn reset_reverts_for_non_owner() {
+    let env = installed_env(BOB);
+    assert_eq!(increment(&env), 1);
+
+    let result: Result<(), _> = run_entry_point(env.clone(), |_env| unsafe { reset_entry_point() });
+    assert_eq!(
+        result.map_err(|revert| revert.api_error),
+        Err(OwnableError::Unauthorized.into())
+    );
+    let count = run_entry_point(env, |_env| unsafe { count_entry_point() });
+    assert_eq!(count, Ok(1u64));
+}
+
+#[test]
+fn owner_resets_total_count_only() {
+    let env = installed_env(OWNER);
+    assert_eq!(increment(&env), 1);
+    assert_eq!(increment(&env), 2);
+
+    let result: Result<(), _> = run_entry_point(env.clone(), |_env| unsafe { reset_entry_point() });
+    assert_eq!(result, Ok(()));
+    dispatch_with(env.clone(), |_env| {
+        assert_eq!(COUNT_TUREF.read(), Ok(Some(0)));
+        assert_eq!(ACCOUNT_COUNTS.get(&OWNER), Ok(Some(2)));
+    });
+    assert_eq!(increment(&env), 1);
+}
+
+#[test]
+fn history_returns_pages_of_totals() {
+    let env = installed_env(BOB);
+    for count in 1..=5 {
+        assert_eq!(increment(&env), count);
+    }
+    let history = |env: &Env| run_entry_point(env.clone(), |_env| unsafe { history_entry_point() });
+
+    env.set_arg("limit", 2u64);
+    assert_eq!(
+        history(&env),
+        Ok(Page {
+            items: vec![1u64, 2],
+            next_cursor: Some(2),
+        })
+    );
+    env.set_arg("cursor", 4u64);
+    assert_eq!(
+        history(&env),
+        Ok(Page {
+            items: vec![5u64],
+            next_cursor: None,
+        })
+    );
+
+    env.set_arg("limit", MAX_PAGE_LIMIT + 1);
+    assert_eq!(
+        history(&env).map_err(|revert| revert.api_error),
+        Err::<Page<u64>, _>(UniversalError::PageLimitExceeded.into())
+    );
+}
@@ -0,0 +1,232 @@
+use veles_casper_contract_api::macro_support::IntoRuntimeArgs;
+use veles_casper_contract_api::{
+    casper_engine_test_support::{
+        DEFAULT_ACCOUNT_ADDR, ExecuteRequestBuilder, LOCAL_GENESIS_REQUEST, LmdbWasmTestBuilder,
+        MINIMUM_ACCOUNT_CREATION_BALANCE, TransferRequestBuilder,
+    },
+    casper_execution_engine::{engine_state, execution},
+    casper_types::{
+        ApiError, Key, RuntimeArgs, URef, account::AccountHash, contracts::ContractHash,
+        runtime_args,
+    },
+    collections::dictionary_key::DictionaryKey,
+    error::UniversalError,
+    keyspace,
+    test_support::{WasmTestBuilderExt, read_wasm},
+};
+use veles_casper_contract_extras::ownable::OwnableError;
+
+const ALICE: AccountHash = AccountHash::new([1; 32]);
+
+struct Fixture {
+    builder: LmdbWasmTestBuilder,
+    contract_hash: ContractHash,
+}
+
+impl Fixture {
+    /// Installs the counter from the default account, which becomes its owner.
+    fn new() -> Self {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+        let transfer_request =
+            TransferRequestBuilder::new(MINIMUM_ACCOUNT_CREATION_BALANCE, ALICE).build();
+        builder
+            .transfer_and_commit(transfer_request)
+            .expect_success();
+
+        let install_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("counter.wasm"),
+            RuntimeArgs::default(),
+        )
+        .build();
+        builder.exec(install_request).expect_success().commit();
+
+        let contract_hash =
+            builder.installed_contract_hash(*DEFAULT_ACCOUNT_ADDR, counter::HASH_KEY_NAME);
+        Self {
+            builder,
+            contract_hash,
+        }
+    }
+
+    fn call(
+        &mut self,
+        sender: AccountHash,
+        entry_point: &str,
+        args: RuntimeArgs,
+    ) -> Result<(), ApiError> {
+        let exec_request = ExecuteRequestBuilder::contract_call_by_hash(
+            sender,
+            self.contract_hash.into(),
+            entry_point,
+            args,
+        )
+        .build();
+        self.builder.exec(exec_request).commit();
+        match self.builder.get_error() {
+            None => Ok(()),
+            Some(engine_state::Error::Exec(execution::ExecError::Revert(api_error))) => {
+                Err(api_error)
+            }
+            Some(error) => panic!("unexpected execution error: {error:?}"),
+        }
+    }
+
+    fn increment(&mut self, sender: AccountHash) {
+        self.call(
+            sender,
+            counter::counter::increment::NAME,
+            counter::counter::increment::Args {}.into_runtime_args(),
+        )
+        .expect("should increment");
+    }
+
+    fn reset(&mut self, sender: AccountHash) -> Result<(), ApiError> {
+        self.call(
+            sender,
+            counter::counter::reset::NAME,
+            counter::counter::reset::Args {}.into_runtime_args(),
+        )
+    }
+
+    fn contract_uref(&self, name: &str) -> URef {
+        let contract = self
+            .builder
+            .get_contract(self.contract_hash)
+            .expect("counter contract should exist");
+        *contract
+            .named_keys()
+            .get(name)
+            .and_then(Key::as_uref)
+            .expect("named key should be a URef")
+    }
+
+    fn count(&self) -> u64 {
+        self.builder
+            .query(
+                None,
+                Key::URef(self.contract_uref(counter::COUNT_KEY_NAME)),
+                &[],
+            )
+            .expect("should query the count")
+            .as_cl_value()
+            .expect("count should be a CLValue")
+            .clone()
+            .into_t()
+            .expect("count should be a u64")
+    }
+
+    fn history(&self) -> Vec<u64> {
+        let uref = self.contract_uref(counter::HISTORY_DICT);
+        (0..)
+            .map_while(|index| {
+                let item = self
+                    .builder
+                    .query_dictionary_item(None, uref, &keyspace::vector_item_key(index))
+                    .ok()?;
+                Some(
+                    item.into_cl_value()
+                        .expect("should be a CLValue")
+                        .into_t()
+                        .expect("should be a u64"),
+                )
+            })
+            .collect()
+    }
+
+    fn count_of(&self, account: AccountHash) -> Option<u64> {
+        let uref = self.contract_uref(counter::ACCOUNT_COUNTS_DICT);
+        let item = self
+            .builder
+            .query_dictionary_item(None, uref, &account.dictionary_key())
+            .ok()?;
+        Some(
+            item.into_cl_value()
+                .expect("should be a CLValue")
+                .into_t()
+                .expect("should be a u64"),
+        )
+    }
+}
+
+#[test]
+fn increments_are_counted_per_account() {
+    let mut fixture = Fixture::new();
+    assert_eq!(fixture.count(), 0);
+
+    fixture.increment(*DEFAULT_ACCOUNT_ADDR);
+    fixture.increment(ALICE);
+    fixture.increment(ALICE);
+
+    assert_eq!(fixture.count(), 3);
+    assert_eq!(fixture.count_of(*DEFAULT_ACCOUNT_ADDR), Some(1));
+    assert_eq!(fixture.count_of(ALICE), Some(2));
+}
+
+#[test]
+fn only_the_owner_resets() {
+    let mut fixture = Fixture::new();
+    fixture.increment(ALICE);
+
+    assert_eq!(fixture.reset(ALICE), Err(OwnableError::Unauthorized.into()));
+    assert_eq!(fixture.count(), 1);
+
+    assert_eq!(fixture.reset(*DEFAULT_ACCOUNT_ADDR), Ok(()));
+    assert_eq!(fixture.count(), 0);
+    assert_eq!(fixture.count_of(ALICE), Some(1));
+}
+
+#[test]
+fn increment_by_is_called_by_its_wire_name() {
+    let mut fixture = Fixture::new();
+    assert_eq!(counter::counter::increment_by::NAME, "increment-by");
+
+    let args = counter::counter::increment_by::Args { amount: 5 }.into_runtime_args();
+    assert!(args.get("by").is_some());
+    assert_eq!(fixture.call(ALICE, "increment-by", args), Ok(()));
+    assert_eq!(fixture.count(), 5);
+    assert_eq!(fixture.count_of(ALICE), Some(5));
+
+    // The parameter is passed under its argument name only.
+    assert_eq!(
+        fixture.call(ALICE, "increment-by", runtime_args! { "amount" => 1u64 },),
+        Err(ApiError::MissingArgument)
+    );
+    assert_eq!(fixture.count(), 5);
+}
+
+#[test]
+fn history_keeps_the_totals_and_enforces_the_page_limit() {
+    let mut fixture = Fixture::new();
+    fixture.increment(ALICE);
+    fixture.increment(ALICE);
+    assert_eq!(fixture.reset(*DEFAULT_ACCOUNT_ADDR), Ok(()));
+    fixture.increment(ALICE);
+    assert_eq!(fixture.history(), [1, 2, 1]);
+
+    let args = counter::counter::history::Args {
+        cursor: Some(1),
+        limit: 2,
+    };
+    assert_eq!(
+        fixture.call(
+            ALICE,
+            counter::counter::history::NAME,
+            args.into_runtime_args()
+        ),
+        Ok(())
+    );
+    let args = counter::counter::history::Args {
+        cursor: None,
+        limit: 101,
+    };
+    assert_eq!(
+        fixture.call(
+            ALICE,
+            counter::counter::history::NAME,
+            args.into_runtime_args()
+        ),
+        Err(UniversalError::PageLimitExceeded.into())
+    );
+}
@@ -1,8 +1,13 @@
 #![cfg_attr(target_arch = "wasm32", no_std)]
 
-use veles_casper_contract_api::prelude::*;
+use veles_casper_contract_api::{
+    casper_types::RuntimeArgs, macro_support::IntoRuntimeArgs, prelude::*,
+};
 
-use do_nothing_stored::HASH_KEY;
+use do_nothing_stored::{HASH_KEY, PACKAGE_HASH_KEY_NAME};
+
+/// Reads the result of the legacy `ping_legacy` entry point as `()` when `true`.
+pub const ARG_STRICT_LEGACY: &str = "strict_legacy";
 
 #[casper(export)]
 pub fn call() -> Result<(), ApiError> {
@@ -21,5 +26,40 @@ pub fn call() -> Result<(), ApiError> {
     // This will revert
     // let _result_2: () = client.delegate(U512::one());
 
+    // Unit calls succeed whether the callee returns nothing, an explicit unit or a legacy value.
+    client.ping();
+    client.ping_session();
+    client.ping_legacy();
+    if utils::get_named_arg_or(ARG_STRICT_LEGACY, false) {
+        // Reading the result as `()` reverts with `ApiError::LeftOverBytes` on the legacy value.
+        runtime::call_contract::<()>(
+            contract_hash.into(),
+            do_nothing_stored::contract::ping_legacy::NAME,
+            RuntimeArgs::new(),
+        );
+    }
+
+    // Dynamic call for when the target isn't known at compile time; the result is still typed.
+    let sum: u64 = utils::call_contract_typed(
+        contract_hash.into(),
+        do_nothing_stored::contract::add::NAME,
+        do_nothing_stored::contract::add::Args { lhs: 1, rhs: 2 }.into_runtime_args(),
+    )?;
+    if sum != 3 {
+        return Err(ApiError::User(50100));
+    }
+
+    // Calls through the package follow upgrades, reaching its latest enabled version.
+    let package_hash = NamedKey::from_name(PACKAGE_HASH_KEY_NAME)
+        .get()?
+        .ok_or(ApiError::MissingKey)?
+        .into_hash_addr()
+        .ok_or(ApiError::MissingKey)?;
+    let package_client = do_nothing_stored::contract::PackageClient::new(package_hash.into(), None);
+    package_client.ping();
+    if package_client.add(2, 3) != 5 {
+        return Err(ApiError::User(50101));
+    }
+
     Ok(())
 }
@@ -0,0 +1,52 @@
+use veles_casper_contract_api::{
+    casper_engine_test_support::{
+        DEFAULT_ACCOUNT_ADDR, ExecuteRequestBuilder, LOCAL_GENESIS_REQUEST, LmdbWasmTestBuilder,
+    },
+    casper_execution_engine::{engine_state, execution},
+    casper_types::{ApiError, RuntimeArgs, runtime_args},
+    test_support::read_wasm,
+};
+
+use do_nothing_caller::ARG_STRICT_LEGACY;
+
+/// Installs the stored contract and runs the caller session with `args`.
+fn run_caller(args: RuntimeArgs) -> LmdbWasmTestBuilder {
+    let mut builder = LmdbWasmTestBuilder::default();
+    builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+
+    let install_request = ExecuteRequestBuilder::module_bytes(
+        *DEFAULT_ACCOUNT_ADDR,
+        read_wasm("do_nothing_stored.wasm"),
+        RuntimeArgs::default(),
+    )
+    .build();
+    builder.exec(install_request).expect_success().commit();
+
+    let call_request = ExecuteRequestBuilder::module_bytes(
+        *DEFAULT_ACCOUNT_ADDR,
+        read_wasm("do_nothing_caller.wasm"),
+        args,
+    )
+    .build();
+    builder.exec(call_request).commit();
+    builder
+}
+
+#[test]
+fn calls_stored_contract() {
+    // The caller pings the new, explicit unit and legacy callees through the `Client`, then calls
+    // the latest version of the package through the `PackageClient`.
+    let mut builder = run_caller(RuntimeArgs::default());
+    builder.expect_success();
+}
+
+#[test]
+fn strict_unit_read_of_legacy_callee_reverts() {
+    let builder = run_caller(runtime_args! { ARG_STRICT_LEGACY => true });
+    match builder.get_error() {
+        Some(engine_state::Error::Exec(execution::ExecError::Revert(api_error))) => {
+            assert_eq!(api_error, ApiError::LeftOverBytes);
+        }
+        other => panic!("expected a revert, got {other:?}"),
+    }
+}
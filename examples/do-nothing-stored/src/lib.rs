@@ -5,20 +5,29 @@ pub(crate) mod event;
 extern crate alloc;
 
 use alloc::collections::BTreeMap;
-use alloc::{format, string::String};
+use alloc::{format, string::String, vec::Vec};
 
-use veles_casper_contract_api::prelude::*;
+use veles_casper_contract_api::{
+    casper_contract::unwrap_or_revert::UnwrapOrRevert, casper_types::CLValue, prelude::*,
+};
 
 pub const HASH_KEY_NAME: &str = "do_nothing_hash";
-pub static HASH_KEY: NamedKey = NamedKey::from_name(HASH_KEY_NAME);
 pub const PACKAGE_HASH_KEY_NAME: &str = "do_nothing_package_hash";
 pub const ACCESS_KEY_NAME: &str = "do_nothing_access";
 pub static CONTRACT_VERSION_KEY: NamedKey = NamedKey::from_name("contract_version");
 
+pub use contract::{CONTRACT_VERSION, HASH_KEY};
+
 #[casper(contract)]
 pub mod contract {
     use super::*;
 
+    // Named keys the installer puts into the account installing the contract
+    #[casper(named_key)]
+    pub static HASH_KEY: NamedKey = NamedKey::from_name(HASH_KEY_NAME);
+    #[casper(named_key)]
+    pub static CONTRACT_VERSION: TypedURef<u32> = TypedURef::from_named_key(&CONTRACT_VERSION_KEY);
+
     #[casper(export)]
     pub fn delegate(amount: U512) -> Result<(), ApiError> {
         if amount == U512::one() {
@@ -50,6 +59,26 @@ pub mod contract {
         Ok(lhs + rhs)
     }
 
+    #[casper(export)]
+    pub fn count_admins(admins: Option<Vec<Key>>) -> u64 {
+        admins.map_or(0, |admins| admins.len() as u64)
+    }
+
+    /// Returns nothing, the caller's host buffer stays empty.
+    #[casper(export)]
+    pub fn ping() {}
+
+    /// Returns an explicit unit, like session code inspected by its caller.
+    #[casper(export, ret_unit)]
+    pub fn ping_session() {}
+
+    /// Declared as returning nothing but rets a value, like a callee built with an older kit
+    /// version whose wrappers always called `runtime::ret`.
+    #[casper(export)]
+    pub fn ping_legacy() {
+        utils::ret(CLValue::from_t(true).unwrap_or_revert());
+    }
+
     #[casper(export)]
     pub fn mapping() -> BTreeMap<String, u64> {
         let mut map = BTreeMap::new();
@@ -0,0 +1,200 @@
+//! Tests of the installer running under the ffi shim, without any wasm.
+use std::collections::BTreeMap;
+
+use veles_casper_contract_api::{
+    casper_contract::contract_api::storage,
+    casper_types::{
+        ApiError, CLValue, Key, StoredValue,
+        contracts::{self, ContractHash, ContractPackageHash, ContractVersionKey, NamedKeys},
+    },
+    utils,
+    veles_casper_ffi_shim::{Env, EnvBuilder, HostFunction, dispatch_with, run_entry_point},
+};
+
+use do_nothing_stored::{
+    ACCESS_KEY_NAME, CONTRACT_VERSION_KEY, HASH_KEY_NAME, PACKAGE_HASH_KEY_NAME, contract,
+};
+
+#[cfg(not(feature = "as_dependency"))]
+unsafe extern "C-unwind" {
+    #[link_name = "call"]
+    fn call_entry_point();
+}
+
+// Workspace builds enable `as_dependency` for the crates linking the contract, which don't export
+// the `call` symbol.
+#[cfg(feature = "as_dependency")]
+unsafe fn call_entry_point() {
+    do_nothing_stored::call::entry_point();
+}
+
+#[test]
+fn installer_runs_under_the_shim() {
+    let env = EnvBuilder::new().build();
+    let result: Result<(), _> = run_entry_point(env.clone(), |_env| unsafe { call_entry_point() });
+    result.expect("call should install the contract");
+
+    let named_keys = env.named_keys();
+    let Some(Key::Hash(package_addr)) = named_keys.get(PACKAGE_HASH_KEY_NAME).copied() else {
+        panic!("the package hash should be a hash key: {named_keys:?}");
+    };
+    let Some(Key::Hash(contract_addr)) = named_keys.get(HASH_KEY_NAME).copied() else {
+        panic!("the contract hash should be a hash key: {named_keys:?}");
+    };
+    assert!(named_keys.contains_key(ACCESS_KEY_NAME));
+    assert!(named_keys.contains_key(CONTRACT_VERSION_KEY.name()));
+
+    let trace = env.trace();
+    assert!(trace.contains(&HostFunction::CasperCreateContractPackageAtHash));
+    assert!(trace.contains(&HostFunction::CasperAddContractVersionWithMessageTopics));
+
+    let package_hash = ContractPackageHash::new(package_addr);
+    let contract_hash = ContractHash::new(contract_addr);
+    let package = env.package(package_hash).expect("the package should exist");
+    assert_eq!(
+        named_keys[ACCESS_KEY_NAME]
+            .as_uref()
+            .map(|uref| uref.addr()),
+        Some(package.access_uref.addr())
+    );
+    assert_eq!(
+        package.current_version(),
+        Some((ContractVersionKey::new(2, 1), contract_hash))
+    );
+    assert_eq!(
+        package.message_topics[&contract_hash]
+            .iter()
+            .collect::<Vec<_>>(),
+        ["DidNothing"]
+    );
+
+    let storage = env.storage();
+    let Some(StoredValue::Contract(stored_contract)) = storage.get(&Key::Hash(contract_addr))
+    else {
+        panic!("the contract should be stored under its hash");
+    };
+    assert_eq!(stored_contract.contract_package_hash(), package_hash);
+    assert_eq!(
+        stored_contract
+            .entry_points()
+            .clone()
+            .take_entry_points()
+            .len(),
+        contract::entry_points().take_entry_points().len()
+    );
+    assert!(matches!(
+        storage.get(&Key::Hash(package_addr)),
+        Some(StoredValue::ContractPackage(stored_package))
+            if *stored_package == package.to_contract_package()
+    ));
+
+    // Upgrading carries the topics forward, and disabling the new version reverts to the old one
+    dispatch_with(env.clone(), |env| {
+        let (upgraded_hash, version) = storage::add_contract_version(
+            package_hash,
+            contract::entry_points(),
+            NamedKeys::new(),
+            BTreeMap::new(),
+        );
+        assert_eq!(version, 2);
+        let package = env.package(package_hash).unwrap();
+        assert_eq!(
+            package.current_version(),
+            Some((ContractVersionKey::new(2, 2), upgraded_hash))
+        );
+        assert_eq!(
+            package.message_topics[&upgraded_hash],
+            package.message_topics[&contract_hash]
+        );
+
+        storage::disable_contract_version(package_hash, upgraded_hash).unwrap();
+        let package = env.package(package_hash).unwrap();
+        assert!(!package.versions[&ContractVersionKey::new(2, 2)].enabled);
+        assert_eq!(
+            package.current_version(),
+            Some((ContractVersionKey::new(2, 1), contract_hash))
+        );
+
+        storage::enable_contract_version(package_hash, upgraded_hash).unwrap();
+        assert_eq!(
+            env.package(package_hash).unwrap().current_version(),
+            Some((ContractVersionKey::new(2, 2), upgraded_hash))
+        );
+        assert_eq!(
+            storage::disable_contract_version(package_hash, ContractHash::new([0xff; 32])),
+            Err(ApiError::ContractHeader(
+                contracts::Error::ContractNotFound as u8
+            ))
+        );
+    });
+}
+
+/// Returns the hash of the package the installer created in `env`.
+fn install(env: &Env) -> ContractPackageHash {
+    let result: Result<(), _> = run_entry_point(env.clone(), |_env| unsafe { call_entry_point() });
+    result.expect("call should install the contract");
+    match env.named_keys().get(PACKAGE_HASH_KEY_NAME) {
+        Some(Key::Hash(package_addr)) => ContractPackageHash::new(*package_addr),
+        other => panic!("the package hash should be a hash key: {other:?}"),
+    }
+}
+
+#[test]
+fn package_client_calls_follow_upgrades() {
+    let env = EnvBuilder::new().build();
+    let package_hash = install(&env);
+    let (_, contract_hash) = env
+        .package(package_hash)
+        .unwrap()
+        .current_version()
+        .unwrap();
+    env.set_contract_entry_point(contract_hash, "hello", || {
+        utils::ret(CLValue::from_t(String::from("v1")).unwrap())
+    });
+
+    dispatch_with(env.clone(), |env| {
+        let client = contract::PackageClient::new(package_hash, None);
+        assert_eq!(client.hello("shim".into()), "v1");
+
+        let (upgraded_hash, version) = storage::add_contract_version(
+            package_hash,
+            contract::entry_points(),
+            NamedKeys::new(),
+            BTreeMap::new(),
+        );
+        assert_eq!(version, 2);
+        env.set_contract_entry_point(upgraded_hash, "hello", || {
+            utils::ret(CLValue::from_t(String::from("v2")).unwrap())
+        });
+
+        // The latest version is resolved on every call, while pinned versions stay put
+        assert_eq!(client.hello("shim".into()), "v2");
+        let pinned = contract::PackageClient::new(package_hash, Some(1));
+        assert_eq!(pinned.hello("shim".into()), "v1");
+
+        storage::disable_contract_version(package_hash, upgraded_hash).unwrap();
+        assert_eq!(client.hello("shim".into()), "v1");
+    });
+    let calls = env
+        .trace()
+        .into_iter()
+        .filter(|host_function| *host_function == HostFunction::CasperCallVersionedContract)
+        .count();
+    assert_eq!(calls, 4);
+
+    // Calling a disabled or missing version traps, like in the engine
+    for version in [2, 3] {
+        let result: Result<String, _> = run_entry_point(env.clone(), |_env| {
+            contract::PackageClient::new(package_hash, Some(version)).hello("shim".into());
+        });
+        assert_eq!(
+            result.unwrap_err().api_error,
+            ApiError::ContractHeader(contracts::Error::ContractNotFound as u8)
+        );
+    }
+    let result: Result<(), _> = run_entry_point(env.clone(), |_env| {
+        contract::PackageClient::new(ContractPackageHash::new([0xff; 32]), None)
+            .hello("shim".into());
+    });
+    assert_eq!(result.unwrap_err().api_error, ApiError::ValueNotFound);
+}
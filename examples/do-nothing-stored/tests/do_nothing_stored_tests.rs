@@ -1,47 +1,13 @@
-use std::{
-    fs,
-    path::{Path, PathBuf},
-};
-
-use once_cell::sync::Lazy;
 use veles_casper_contract_api::macro_support::IntoRuntimeArgs;
 use veles_casper_contract_api::{
     casper_engine_test_support::{
         DEFAULT_ACCOUNT_ADDR, ExecuteRequestBuilder, LOCAL_GENESIS_REQUEST, LmdbWasmTestBuilder,
     },
-    casper_types::{self, Key, contracts::ContractHash},
+    casper_types::{self, CLType, EntityAddr, Key, contracts::ContractHash},
+    state_spec::{MistypedNamedKey, NamedKeyKind, NamedKeySpec, verify_state},
+    test_support::{WasmTestBuilderExt, read_wasm},
 };
 
-pub const PROFILE: &str = "release";
-pub const WASM_TARGET: &str = "wasm32v1-none";
-
-pub static RUST_WORKSPACE_PATH: Lazy<PathBuf> = Lazy::new(|| {
-    Path::new(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .expect("CARGO_MANIFEST_DIR should have parent")
-        .parent()
-        .expect("workspace root should have parent")
-        .to_path_buf()
-        .to_path_buf()
-});
-// The location of compiled Wasm files if compiled from the Rust sources within the casper-node
-// repo, i.e. 'casper-node/target/wasm32v1-none/release/'.
-pub static RUST_WORKSPACE_WASM_PATH: Lazy<PathBuf> = Lazy::new(|| {
-    RUST_WORKSPACE_PATH
-        .join("target")
-        .join(WASM_TARGET)
-        .join(PROFILE)
-});
-
-static DO_NOTHING_STORED_WASM: Lazy<Vec<u8>> = Lazy::new(|| {
-    fs::read(RUST_WORKSPACE_WASM_PATH.join("do_nothing_stored.wasm")).unwrap_or_else(|err| {
-        panic!(
-            "should read {:?} from target dir: {err}",
-            RUST_WORKSPACE_WASM_PATH.clone(),
-        );
-    })
-});
-
 #[test]
 fn install_and_execute() {
     let args = do_nothing_stored::contract::delegate::Args {
@@ -54,8 +20,140 @@ fn install_and_execute() {
     call_delegate(&mut builder, contract_hash, args);
 }
 
+#[test]
+fn optional_argument_can_be_omitted() {
+    let mut builder = LmdbWasmTestBuilder::default();
+    builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+    let contract_hash = install_do_nothing_stored_contract(&mut builder);
+
+    let entry_point = do_nothing_stored::contract::entry_points_vec()
+        .into_iter()
+        .find(|entry_point| entry_point.name() == do_nothing_stored::contract::count_admins::NAME)
+        .expect("count_admins entry point should exist");
+    assert_eq!(
+        entry_point.args()[0].cl_type(),
+        &casper_types::CLType::List(Box::new(casper_types::CLType::Key))
+    );
+
+    // Omitting `admins` makes the entry point count none rather than revert
+    for (admins, expected_count) in [
+        (None, 0u64),
+        (Some(vec![Key::Account(*DEFAULT_ACCOUNT_ADDR)]), 1),
+    ] {
+        let args = do_nothing_stored::contract::count_admins::Args { admins }.into_runtime_args();
+        let exec_request = ExecuteRequestBuilder::contract_call_by_hash(
+            *DEFAULT_ACCOUNT_ADDR,
+            contract_hash.into(),
+            do_nothing_stored::contract::count_admins::NAME,
+            args,
+        );
+        builder.exec(exec_request.build()).expect_success().commit();
+
+        let count: u64 = builder
+            .get_last_exec_result()
+            .and_then(|result| result.ret().cloned())
+            .expect("count_admins should return the count")
+            .into_t()
+            .expect("the count should be a u64");
+        assert_eq!(count, expected_count);
+    }
+}
+
+#[test]
+fn installer_state_matches_the_manifest() {
+    let mut builder = LmdbWasmTestBuilder::default();
+    builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+    install_do_nothing_stored_contract(&mut builder);
+
+    let spec = do_nothing_stored::contract::state_spec()
+        .with_named_key(do_nothing_stored::PACKAGE_HASH_KEY_NAME, NamedKeyKind::Any)
+        .with_named_key(do_nothing_stored::ACCESS_KEY_NAME, NamedKeyKind::Any);
+    verify_state(&builder, installer(), &spec).assert_ok();
+}
+
+#[test]
+fn installed_values_read_in_one_call() {
+    let mut builder = LmdbWasmTestBuilder::default();
+    builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+    let contract_hash = install_do_nothing_stored_contract(&mut builder);
+
+    let contract_version: u32 = builder.read_named_value(installer(), "contract_version");
+    assert_eq!(contract_version, 1);
+    let topics = builder.read_message_topics(EntityAddr::SmartContract(contract_hash.value()));
+    assert_eq!(
+        topics
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        ["DidNothing"]
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "has no named key `do_nothing_balances`, its named keys are [`contract_version`, \
+                `do_nothing_access`, `do_nothing_hash`, `do_nothing_package_hash`]"
+)]
+fn missing_named_keys_list_the_named_keys_present() {
+    let mut builder = LmdbWasmTestBuilder::default();
+    builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+    install_do_nothing_stored_contract(&mut builder);
+
+    builder.expect_named_key(installer(), "do_nothing_balances");
+}
+
+#[test]
+fn broken_spec_reports_every_difference() {
+    let mut builder = LmdbWasmTestBuilder::default();
+    builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+    install_do_nothing_stored_contract(&mut builder);
+    let access_key = builder.expect_named_key(installer(), do_nothing_stored::ACCESS_KEY_NAME);
+
+    // Forgets the access key, mistypes the version and expects a dictionary the installer never
+    // creates
+    let spec = do_nothing_stored::contract::state_spec()
+        .with_named_key(do_nothing_stored::PACKAGE_HASH_KEY_NAME, NamedKeyKind::Any)
+        .with_named_key("contract_version", NamedKeyKind::Value(CLType::String))
+        .with_named_key("do_nothing_balances", NamedKeyKind::Dictionary);
+    let report = verify_state(&builder, installer(), &spec);
+
+    assert_eq!(
+        report.missing,
+        [NamedKeySpec {
+            name: "do_nothing_balances".into(),
+            kind: NamedKeyKind::Dictionary,
+        }]
+    );
+    assert_eq!(
+        report.unexpected,
+        [(do_nothing_stored::ACCESS_KEY_NAME.into(), access_key)]
+    );
+    assert_eq!(
+        report.mistyped,
+        [MistypedNamedKey {
+            name: "contract_version".into(),
+            expected: NamedKeyKind::Value(CLType::String),
+            found: "a URef to U32".into(),
+        }]
+    );
+    assert_eq!(
+        report.to_string(),
+        format!(
+            "State differs from the spec:\n\
+             - missing    `do_nothing_balances`: expected a dictionary\n\
+             + unexpected `do_nothing_access`: {}\n\
+             ~ mistyped   `contract_version`: expected a URef to String, found a URef to U32",
+            access_key.to_formatted_string()
+        )
+    );
+}
+
+fn installer() -> EntityAddr {
+    EntityAddr::Account(DEFAULT_ACCOUNT_ADDR.value())
+}
+
 fn install_do_nothing_stored_contract(builder: &mut LmdbWasmTestBuilder) -> ContractHash {
-    let do_nothing_stored_wasm = DO_NOTHING_STORED_WASM.clone();
+    let do_nothing_stored_wasm = read_wasm("do_nothing_stored.wasm");
 
     let install_request = ExecuteRequestBuilder::module_bytes(
         *DEFAULT_ACCOUNT_ADDR,
@@ -66,18 +164,7 @@ fn install_do_nothing_stored_contract(builder: &mut LmdbWasmTestBuilder) -> Cont
 
     builder.exec(install_request).expect_success().commit();
 
-    let installer = builder
-        .get_account(*DEFAULT_ACCOUNT_ADDR)
-        .expect("installer should exist");
-    let Key::Hash(contract_hash_bytes) = installer
-        .named_keys()
-        .get(do_nothing_stored::HASH_KEY_NAME)
-        .expect("missing oracle contract key")
-    else {
-        panic!("do_nothing_stored contract hash key should exist");
-    };
-
-    ContractHash::from(*contract_hash_bytes)
+    builder.installed_contract_hash(*DEFAULT_ACCOUNT_ADDR, do_nothing_stored::HASH_KEY_NAME)
 }
 
 fn call_delegate(
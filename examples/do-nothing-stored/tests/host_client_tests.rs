@@ -0,0 +1,174 @@
+use std::sync::Mutex;
+
+use do_nothing_stored::{PACKAGE_HASH_KEY_NAME, contract};
+use veles_casper_contract_api::{
+    casper_types::{
+        PricingMode, PublicKey, RuntimeArgs, SecretKey, Timestamp, Transaction, TransactionArgs,
+        TransactionEntryPoint, TransactionV1, U512, contracts::ContractHash,
+        execution::ExecutionResult, runtime_args,
+    },
+    host_client::{SessionOptions, SubmitTransaction},
+};
+use veles_casper_rust_sdk::jsonrpc::{AccountIdentifier, CasperClient};
+
+const CHAIN_NAME: &str = "casper-net-1";
+const CONTRACT_HASH: ContractHash = ContractHash::new([7; 32]);
+
+fn signer() -> SecretKey {
+    SecretKey::ed25519_from_bytes([1; 32]).unwrap()
+}
+
+fn into_v1(transaction: Transaction) -> TransactionV1 {
+    match transaction {
+        Transaction::V1(transaction) => transaction,
+        Transaction::Deploy(_) => panic!("expected a V1 transaction"),
+    }
+}
+
+fn entry_point_and_args(transaction: &TransactionV1) -> (String, RuntimeArgs) {
+    let TransactionEntryPoint::Custom(entry_point) = transaction.deserialize_field(2).unwrap()
+    else {
+        panic!("expected a custom entry point");
+    };
+    let TransactionArgs::Named(args) = transaction.deserialize_field(0).unwrap() else {
+        panic!("expected named args");
+    };
+    (entry_point, args)
+}
+
+/// Records the submitted transactions instead of sending them to a network.
+#[derive(Default)]
+struct RecordingSubmitter(Mutex<Vec<Transaction>>);
+
+impl SubmitTransaction for RecordingSubmitter {
+    type Error = ();
+
+    async fn submit_and_wait(&self, transaction: Transaction) -> Result<ExecutionResult, ()> {
+        self.0.lock().unwrap().push(transaction);
+        Err(())
+    }
+}
+
+#[test]
+fn transaction_calls_the_entry_point_with_its_args() {
+    let args = contract::add::Args { lhs: 2, rhs: 40 };
+    let options = SessionOptions::default().with_timestamp(Timestamp::from(1_000));
+    let transaction = into_v1(contract::add::transaction(
+        CONTRACT_HASH,
+        args,
+        &options,
+        CHAIN_NAME,
+        &signer(),
+    ));
+
+    assert_eq!(
+        entry_point_and_args(&transaction),
+        (
+            String::from("add"),
+            runtime_args! { "lhs" => 2u64, "rhs" => 40u64 }
+        )
+    );
+    assert_eq!(transaction.chain_name(), CHAIN_NAME);
+    assert_eq!(transaction.timestamp(), Timestamp::from(1_000));
+    assert_eq!(
+        transaction.payment_amount(),
+        Some(SessionOptions::DEFAULT_PAYMENT_AMOUNT)
+    );
+}
+
+#[test]
+fn unset_optional_args_are_skipped() {
+    let transaction = into_v1(contract::count_admins::transaction(
+        CONTRACT_HASH,
+        contract::count_admins::Args::builder().build(),
+        &SessionOptions::default(),
+        CHAIN_NAME,
+        &signer(),
+    ));
+
+    assert_eq!(
+        entry_point_and_args(&transaction),
+        (String::from("count_admins"), RuntimeArgs::new())
+    );
+}
+
+#[tokio::test]
+async fn host_client_submits_the_built_transaction() {
+    let submitter = RecordingSubmitter::default();
+    let signer = signer();
+    let host_client = contract::HostClient::new(&submitter, CONTRACT_HASH, CHAIN_NAME, &signer);
+
+    host_client
+        .with_payment_amount(5_000_000_000)
+        .hello("world".into())
+        .await
+        .unwrap_err();
+    host_client.delegate(U512::from(2)).await.unwrap_err();
+
+    let transactions: Vec<_> = submitter
+        .0
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(into_v1)
+        .collect();
+    let calls: Vec<_> = transactions.iter().map(entry_point_and_args).collect();
+    assert_eq!(
+        calls,
+        [
+            (String::from("hello"), runtime_args! { "who" => "world" }),
+            (
+                String::from("delegate"),
+                runtime_args! { "amount" => U512::from(2) }
+            ),
+        ]
+    );
+    // The payment hint only applies to the call it was given for.
+    assert!(matches!(
+        transactions[0].pricing_mode(),
+        PricingMode::PaymentLimited {
+            payment_amount: 5_000_000_000,
+            ..
+        }
+    ));
+    assert_eq!(
+        transactions[1].payment_amount(),
+        Some(SessionOptions::DEFAULT_PAYMENT_AMOUNT)
+    );
+    assert!(
+        transactions
+            .iter()
+            .all(|transaction| transaction.verify().is_ok())
+    );
+}
+
+/// Drives `do-nothing-stored` installed on a local network, e.g. NCTL's `casper-net-1`.
+///
+/// Install the contract with the key of `CASPER_SECRET_KEY` (a PEM file, e.g. NCTL's
+/// `users/user-1/secret_key.pem`), then run with `--ignored`. `CASPER_RPC_ENDPOINT` and
+/// `CASPER_CHAIN_NAME` default to NCTL's first node.
+#[tokio::test]
+#[ignore = "needs a local network with do-nothing-stored installed"]
+async fn host_client_drives_a_local_network() {
+    let endpoint = std::env::var("CASPER_RPC_ENDPOINT")
+        .unwrap_or_else(|_| String::from("http://localhost:11101/rpc"));
+    let chain_name =
+        std::env::var("CASPER_CHAIN_NAME").unwrap_or_else(|_| String::from(CHAIN_NAME));
+    let secret_key_path =
+        std::env::var("CASPER_SECRET_KEY").expect("CASPER_SECRET_KEY should name a PEM file");
+    let signer = SecretKey::from_pem(std::fs::read(secret_key_path).unwrap()).unwrap();
+
+    let client = CasperClient::new(endpoint);
+    let account = AccountIdentifier::PublicKey(PublicKey::from(&signer));
+    let (contract_hash, _) = client
+        .resolve_latest_contract(account, PACKAGE_HASH_KEY_NAME)
+        .await
+        .unwrap();
+    let host_client = contract::HostClient::new(&client, contract_hash, &chain_name, &signer);
+
+    let result = host_client.delegate(U512::from(2)).await.unwrap();
+    assert_eq!(result.error_message(), None);
+
+    let result = host_client.delegate(U512::one()).await.unwrap();
+    assert_eq!(result.error_message().as_deref(), Some("User error: 50000"));
+}
@@ -0,0 +1,37 @@
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use veles_casper_contract_api::{casper_types::NamedKeys, prelude::*};
+use veles_casper_contract_extras::{multisig, ownable};
+
+pub const HASH_KEY_NAME: &str = "multisig_wallet_hash";
+pub static HASH_KEY: NamedKey = NamedKey::from_name(HASH_KEY_NAME);
+pub const PACKAGE_HASH_KEY_NAME: &str = "multisig_wallet_package_hash";
+pub const ACCESS_KEY_NAME: &str = "multisig_wallet_access";
+
+#[casper(export)]
+pub fn call(signers: Vec<Key>, threshold: u32) -> Result<(), ApiError> {
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(
+        ownable::OWNER_KEY_NAME.name().into(),
+        Key::Account(runtime::get_caller()),
+    );
+    multisig::install(&mut named_keys, &signers, threshold)?;
+
+    let mut entry_points = ownable::ownable::entry_points_vec();
+    entry_points.extend(multisig::multisig::entry_points_vec());
+
+    let (contract_hash, _contract_version) = storage::new_contract(
+        entry_points.into(),
+        Some(named_keys),
+        Some(PACKAGE_HASH_KEY_NAME.into()),
+        Some(ACCESS_KEY_NAME.into()),
+        Some(multisig::message_topics()),
+    );
+
+    HASH_KEY.set(Key::Hash(contract_hash.value()))?;
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use veles_casper_contract_api::macro_support::IntoRuntimeArgs;
+use veles_casper_contract_api::{
+    casper_engine_test_support::{
+        DEFAULT_ACCOUNT_ADDR, ExecuteRequestBuilder, LOCAL_GENESIS_REQUEST, LmdbWasmTestBuilder,
+        MINIMUM_ACCOUNT_CREATION_BALANCE, TransferRequestBuilder,
+    },
+    casper_execution_engine::{engine_state, execution},
+    casper_types::{
+        self, ApiError, Key, RuntimeArgs,
+        account::AccountHash,
+        bytesrepr::{Bytes, ToBytes},
+        contracts::ContractHash,
+        runtime_args,
+    },
+    collections::dictionary_key::DictionaryKey,
+    test_support::{WasmTestBuilderExt, read_wasm},
+};
+use veles_casper_contract_extras::multisig::{self, MultisigError, Proposal};
+
+const SIGNER_1: AccountHash = AccountHash::new([1; 32]);
+const SIGNER_2: AccountHash = AccountHash::new([2; 32]);
+const BLOCK_TIME: u64 = 1_000;
+const EXPIRY: u64 = 10_000;
+
+struct Fixture {
+    builder: LmdbWasmTestBuilder,
+    multisig_hash: ContractHash,
+    do_nothing_hash: ContractHash,
+}
+
+impl Fixture {
+    /// Installs a 2-of-3 multisig along with a target contract.
+    fn new() -> Self {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+        for account in [SIGNER_1, SIGNER_2] {
+            let transfer_request =
+                TransferRequestBuilder::new(MINIMUM_ACCOUNT_CREATION_BALANCE, account).build();
+            builder
+                .transfer_and_commit(transfer_request)
+                .expect_success();
+        }
+
+        let install_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("multisig_wallet.wasm"),
+            runtime_args! {
+                "signers" => vec![
+                    Key::Account(*DEFAULT_ACCOUNT_ADDR),
+                    Key::Account(SIGNER_1),
+                    Key::Account(SIGNER_2),
+                ],
+                "threshold" => 2u32,
+            },
+        )
+        .build();
+        builder.exec(install_request).expect_success().commit();
+        let multisig_hash =
+            builder.installed_contract_hash(*DEFAULT_ACCOUNT_ADDR, multisig_wallet::HASH_KEY_NAME);
+
+        let install_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("do_nothing_stored.wasm"),
+            RuntimeArgs::default(),
+        )
+        .build();
+        builder.exec(install_request).expect_success().commit();
+        let do_nothing_hash = builder
+            .installed_contract_hash(*DEFAULT_ACCOUNT_ADDR, do_nothing_stored::HASH_KEY_NAME);
+
+        Self {
+            builder,
+            multisig_hash,
+            do_nothing_hash,
+        }
+    }
+
+    fn call(
+        &mut self,
+        sender: AccountHash,
+        entry_point: &str,
+        args: RuntimeArgs,
+        block_time: u64,
+    ) -> Result<(), ApiError> {
+        let exec_request = ExecuteRequestBuilder::contract_call_by_hash(
+            sender,
+            self.multisig_hash.into(),
+            entry_point,
+            args,
+        )
+        .with_block_time(block_time)
+        .build();
+        self.builder.exec(exec_request).commit();
+        match self.builder.get_error() {
+            None => Ok(()),
+            Some(engine_state::Error::Exec(execution::ExecError::Revert(api_error))) => {
+                Err(api_error)
+            }
+            Some(error) => panic!("unexpected execution error: {error:?}"),
+        }
+    }
+
+    /// Proposes `add(1, 2)` on the target contract as the default account.
+    fn propose_add(&mut self) -> u64 {
+        let proposal_id = self.proposal_count();
+        let args = do_nothing_stored::contract::add::Args { lhs: 1, rhs: 2 }.into_runtime_args();
+        let propose_args = multisig::multisig::propose::Args {
+            target: self.do_nothing_hash,
+            entry_point: do_nothing_stored::contract::add::NAME.into(),
+            args: Bytes::from(args.to_bytes().unwrap()),
+            expiry: EXPIRY,
+        };
+        self.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            multisig::multisig::propose::NAME,
+            propose_args.into_runtime_args(),
+            BLOCK_TIME,
+        )
+        .expect("should propose");
+        proposal_id
+    }
+
+    fn approve(&mut self, sender: AccountHash, proposal_id: u64) -> Result<(), ApiError> {
+        self.call(
+            sender,
+            multisig::multisig::approve_proposal::NAME,
+            multisig::multisig::approve_proposal::Args { proposal_id }.into_runtime_args(),
+            BLOCK_TIME,
+        )
+    }
+
+    fn execute(&mut self, proposal_id: u64, block_time: u64) -> Result<(), ApiError> {
+        self.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            multisig::multisig::execute_proposal::NAME,
+            multisig::multisig::execute_proposal::Args { proposal_id }.into_runtime_args(),
+            block_time,
+        )
+    }
+
+    fn contract_uref(&self, name: &str) -> casper_types::URef {
+        let contract = self
+            .builder
+            .get_contract(self.multisig_hash)
+            .expect("multisig contract should exist");
+        *contract
+            .named_keys()
+            .get(name)
+            .and_then(Key::as_uref)
+            .expect("named key should be a uref")
+    }
+
+    fn proposal_count(&self) -> u64 {
+        let uref = self.contract_uref("multisig_proposal_count");
+        self.builder
+            .query(None, Key::URef(uref), &[])
+            .expect("should query proposal count")
+            .into_cl_value()
+            .expect("should be a CLValue")
+            .into_t()
+            .expect("should be u64")
+    }
+
+    fn proposal(&self, proposal_id: u64) -> Proposal {
+        let uref = self.contract_uref(multisig::PROPOSALS_DICT);
+        self.builder
+            .query_dictionary_item(None, uref, &proposal_id.dictionary_key())
+            .expect("should query proposal")
+            .into_cl_value()
+            .expect("should be a CLValue")
+            .into_t()
+            .expect("should be a proposal")
+    }
+}
+
+#[test]
+fn execute_below_threshold_is_rejected() {
+    let mut fixture = Fixture::new();
+    let proposal_id = fixture.propose_add();
+    fixture.approve(SIGNER_1, proposal_id).unwrap();
+
+    assert_eq!(
+        fixture.execute(proposal_id, BLOCK_TIME),
+        Err(MultisigError::BelowThreshold.into())
+    );
+    assert!(!fixture.proposal(proposal_id).executed);
+}
+
+#[test]
+fn approved_proposal_is_executed() {
+    let mut fixture = Fixture::new();
+    let proposal_id = fixture.propose_add();
+    fixture.approve(SIGNER_1, proposal_id).unwrap();
+    fixture.approve(SIGNER_2, proposal_id).unwrap();
+
+    fixture.execute(proposal_id, BLOCK_TIME).unwrap();
+
+    let proposal = fixture.proposal(proposal_id);
+    assert!(proposal.executed);
+    assert_eq!(proposal.approvals, 2);
+    assert_eq!(proposal.target, fixture.do_nothing_hash);
+}
+
+#[test]
+fn signer_cannot_approve_twice() {
+    let mut fixture = Fixture::new();
+    let proposal_id = fixture.propose_add();
+    fixture.approve(SIGNER_1, proposal_id).unwrap();
+
+    assert_eq!(
+        fixture.approve(SIGNER_1, proposal_id),
+        Err(MultisigError::AlreadyApproved.into())
+    );
+}
+
+#[test]
+fn proposal_cannot_be_executed_twice() {
+    let mut fixture = Fixture::new();
+    let proposal_id = fixture.propose_add();
+    fixture.approve(SIGNER_1, proposal_id).unwrap();
+    fixture.approve(SIGNER_2, proposal_id).unwrap();
+    fixture.execute(proposal_id, BLOCK_TIME).unwrap();
+
+    assert_eq!(
+        fixture.execute(proposal_id, BLOCK_TIME),
+        Err(MultisigError::AlreadyExecuted.into())
+    );
+}
+
+#[test]
+fn expired_proposal_is_rejected() {
+    let mut fixture = Fixture::new();
+    let proposal_id = fixture.propose_add();
+    fixture.approve(SIGNER_1, proposal_id).unwrap();
+    fixture.approve(SIGNER_2, proposal_id).unwrap();
+
+    assert_eq!(
+        fixture.execute(proposal_id, EXPIRY),
+        Err(MultisigError::ProposalExpired.into())
+    );
+    assert!(!fixture.proposal(proposal_id).executed);
+}
+
+#[test]
+fn only_proposer_can_cancel() {
+    let mut fixture = Fixture::new();
+    let proposal_id = fixture.propose_add();
+    let args = multisig::multisig::cancel_proposal::Args { proposal_id }.into_runtime_args();
+
+    assert_eq!(
+        fixture.call(
+            SIGNER_1,
+            multisig::multisig::cancel_proposal::NAME,
+            args.clone(),
+            BLOCK_TIME
+        ),
+        Err(MultisigError::NotProposer.into())
+    );
+    fixture
+        .call(
+            *DEFAULT_ACCOUNT_ADDR,
+            multisig::multisig::cancel_proposal::NAME,
+            args,
+            BLOCK_TIME,
+        )
+        .unwrap();
+    assert_eq!(
+        fixture.approve(SIGNER_1, proposal_id),
+        Err(MultisigError::ProposalCancelled.into())
+    );
+}
+
+#[test]
+fn non_signer_cannot_propose() {
+    let mut fixture = Fixture::new();
+    let outsider = AccountHash::new([3; 32]);
+    let transfer_request =
+        TransferRequestBuilder::new(MINIMUM_ACCOUNT_CREATION_BALANCE, outsider).build();
+    fixture
+        .builder
+        .transfer_and_commit(transfer_request)
+        .expect_success();
+
+    let propose_args = multisig::multisig::propose::Args {
+        target: fixture.do_nothing_hash,
+        entry_point: do_nothing_stored::contract::add::NAME.into(),
+        args: Bytes::from(RuntimeArgs::new().to_bytes().unwrap()),
+        expiry: EXPIRY,
+    };
+    assert_eq!(
+        fixture.call(
+            outsider,
+            multisig::multisig::propose::NAME,
+            propose_args.into_runtime_args(),
+            BLOCK_TIME
+        ),
+        Err(MultisigError::NotSigner.into())
+    );
+}
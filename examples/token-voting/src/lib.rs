@@ -0,0 +1,39 @@
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+extern crate alloc;
+
+use veles_casper_contract_api::{
+    casper_types::{NamedKeys, contracts::ContractHash},
+    prelude::*,
+};
+use veles_casper_contract_extras::{ownable, voting};
+
+pub const HASH_KEY_NAME: &str = "token_voting_hash";
+pub static HASH_KEY: NamedKey = NamedKey::from_name(HASH_KEY_NAME);
+pub const PACKAGE_HASH_KEY_NAME: &str = "token_voting_package_hash";
+pub const ACCESS_KEY_NAME: &str = "token_voting_access";
+
+/// Installs a contract voting with the balances of the CEP-18 `token`, owned by the caller.
+#[casper(export)]
+pub fn call(token: ContractHash) -> Result<(), ApiError> {
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(
+        ownable::OWNER_KEY_NAME.name().into(),
+        Key::Account(runtime::get_caller()),
+    );
+    voting::install(&mut named_keys, token)?;
+
+    let mut entry_points = ownable::ownable::entry_points_vec();
+    entry_points.extend(voting::voting::entry_points_vec());
+
+    let (contract_hash, _contract_version) = storage::new_contract(
+        entry_points.into(),
+        Some(named_keys),
+        Some(PACKAGE_HASH_KEY_NAME.into()),
+        Some(ACCESS_KEY_NAME.into()),
+        Some(voting::message_topics()),
+    );
+
+    HASH_KEY.set(Key::Hash(contract_hash.value()))?;
+    Ok(())
+}
@@ -0,0 +1,302 @@
+use veles_casper_contract_api::macro_support::IntoRuntimeArgs;
+use veles_casper_contract_api::{
+    casper_engine_test_support::{
+        DEFAULT_ACCOUNT_ADDR, ExecuteRequestBuilder, LOCAL_GENESIS_REQUEST, LmdbWasmTestBuilder,
+        MINIMUM_ACCOUNT_CREATION_BALANCE, TransferRequestBuilder,
+    },
+    casper_execution_engine::{engine_state, execution},
+    casper_types::{
+        ApiError, EntityAddr, Key, RuntimeArgs, U256, account::AccountHash,
+        contracts::ContractHash, runtime_args,
+    },
+    collections::dictionary_key::DictionaryKey,
+    test_support::{WasmTestBuilderExt, read_wasm},
+};
+use veles_casper_contract_extras::{
+    cep18::{
+        self,
+        constants::{ARG_DECIMALS, ARG_NAME, ARG_SYMBOL, ARG_TOTAL_SUPPLY},
+    },
+    ownable::OwnableError,
+    voting::{self, Proposal, VotingError},
+};
+
+const TOKEN_NAME: &str = "governance";
+const ALICE: AccountHash = AccountHash::new([1; 32]);
+const BOB: AccountHash = AccountHash::new([2; 32]);
+const CAROL: AccountHash = AccountHash::new([3; 32]);
+const DESCRIPTION_HASH: [u8; 32] = [7; 32];
+const BLOCK_TIME: u64 = 1_000;
+const VOTING_PERIOD: u64 = 10_000;
+const ENDS_AT: u64 = BLOCK_TIME + VOTING_PERIOD;
+
+struct Fixture {
+    builder: LmdbWasmTestBuilder,
+    token_hash: ContractHash,
+    voting_hash: ContractHash,
+}
+
+impl Fixture {
+    /// Installs a token with a supply of 1000, of which Alice holds 300 and Bob 100, and a voting
+    /// contract weighing votes with it.
+    fn new() -> Self {
+        let mut builder = LmdbWasmTestBuilder::default();
+        builder.run_genesis(LOCAL_GENESIS_REQUEST.clone());
+        for account in [ALICE, BOB, CAROL] {
+            let transfer_request =
+                TransferRequestBuilder::new(MINIMUM_ACCOUNT_CREATION_BALANCE, account).build();
+            builder
+                .transfer_and_commit(transfer_request)
+                .expect_success();
+        }
+
+        let install_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("cep18_token.wasm"),
+            runtime_args! {
+                ARG_NAME => TOKEN_NAME,
+                ARG_SYMBOL => "GOV",
+                ARG_DECIMALS => 9u8,
+                ARG_TOTAL_SUPPLY => U256::from(1_000u64),
+            },
+        )
+        .build();
+        builder.exec(install_request).expect_success().commit();
+        let token_hash = builder.installed_contract_hash(
+            *DEFAULT_ACCOUNT_ADDR,
+            &format!("cep18_contract_hash_{TOKEN_NAME}"),
+        );
+
+        let install_request = ExecuteRequestBuilder::module_bytes(
+            *DEFAULT_ACCOUNT_ADDR,
+            read_wasm("token_voting.wasm"),
+            runtime_args! { "token" => token_hash },
+        )
+        .build();
+        builder.exec(install_request).expect_success().commit();
+        let voting_hash =
+            builder.installed_contract_hash(*DEFAULT_ACCOUNT_ADDR, token_voting::HASH_KEY_NAME);
+
+        let mut fixture = Self {
+            builder,
+            token_hash,
+            voting_hash,
+        };
+        fixture.transfer(ALICE, 300);
+        fixture.transfer(BOB, 100);
+        fixture
+    }
+
+    fn call(
+        &mut self,
+        sender: AccountHash,
+        contract_hash: ContractHash,
+        entry_point: &str,
+        args: RuntimeArgs,
+        block_time: u64,
+    ) -> Result<(), ApiError> {
+        let exec_request = ExecuteRequestBuilder::contract_call_by_hash(
+            sender,
+            contract_hash.into(),
+            entry_point,
+            args,
+        )
+        .with_block_time(block_time)
+        .build();
+        self.builder.exec(exec_request).commit();
+        match self.builder.get_error() {
+            None => Ok(()),
+            Some(engine_state::Error::Exec(execution::ExecError::Revert(api_error))) => {
+                Err(api_error)
+            }
+            Some(error) => panic!("unexpected execution error: {error:?}"),
+        }
+    }
+
+    /// Transfers tokens of the default account to `recipient`.
+    fn transfer(&mut self, recipient: AccountHash, amount: u64) {
+        let args = cep18::cep18::transfer::Args {
+            recipient: Key::Account(recipient),
+            amount: U256::from(amount),
+        };
+        self.call(
+            *DEFAULT_ACCOUNT_ADDR,
+            self.token_hash,
+            cep18::cep18::transfer::NAME,
+            args.into_runtime_args(),
+            BLOCK_TIME,
+        )
+        .expect("should transfer");
+    }
+
+    fn create_proposal(&mut self, sender: AccountHash) -> Result<u64, ApiError> {
+        let proposal_id = self.proposal_count();
+        let args = voting::voting::create_proposal::Args {
+            description_hash: DESCRIPTION_HASH,
+            voting_period: VOTING_PERIOD,
+        };
+        self.call(
+            sender,
+            self.voting_hash,
+            voting::voting::create_proposal::NAME,
+            args.into_runtime_args(),
+            BLOCK_TIME,
+        )?;
+        Ok(proposal_id)
+    }
+
+    fn vote(
+        &mut self,
+        voter: AccountHash,
+        proposal_id: u64,
+        support: bool,
+    ) -> Result<(), ApiError> {
+        self.call(
+            voter,
+            self.voting_hash,
+            voting::voting::vote::NAME,
+            voting::voting::vote::Args {
+                proposal_id,
+                support,
+            }
+            .into_runtime_args(),
+            BLOCK_TIME,
+        )
+    }
+
+    fn finalize(&mut self, proposal_id: u64, block_time: u64) -> Result<(), ApiError> {
+        self.call(
+            CAROL,
+            self.voting_hash,
+            voting::voting::finalize::NAME,
+            voting::voting::finalize::Args { proposal_id }.into_runtime_args(),
+            block_time,
+        )
+    }
+
+    fn entity(&self) -> EntityAddr {
+        EntityAddr::SmartContract(self.voting_hash.value())
+    }
+
+    fn proposal_count(&self) -> u64 {
+        self.builder
+            .read_named_value(self.entity(), "voting_proposal_count")
+    }
+
+    fn proposal(&self, proposal_id: u64) -> Proposal {
+        self.builder
+            .read_dictionary_value(
+                self.entity(),
+                voting::PROPOSALS_DICT,
+                &proposal_id.dictionary_key(),
+            )
+            .expect("proposal should exist")
+    }
+
+    fn vote_weight(&self, proposal_id: u64, voter: AccountHash) -> Option<U256> {
+        self.builder.read_dictionary_value(
+            self.entity(),
+            voting::VOTES_DICT,
+            &(proposal_id, Key::Account(voter)).dictionary_key(),
+        )
+    }
+}
+
+#[test]
+fn votes_are_weighed_and_finalized_after_the_period() {
+    let mut fixture = Fixture::new();
+    assert_eq!(
+        fixture.create_proposal(ALICE),
+        Err(OwnableError::Unauthorized.into())
+    );
+    let proposal_id = fixture.create_proposal(*DEFAULT_ACCOUNT_ADDR).unwrap();
+
+    fixture
+        .vote(*DEFAULT_ACCOUNT_ADDR, proposal_id, true)
+        .unwrap();
+    fixture.vote(ALICE, proposal_id, false).unwrap();
+    fixture.vote(BOB, proposal_id, true).unwrap();
+    assert_eq!(
+        fixture.vote(CAROL, proposal_id, true),
+        Err(VotingError::NoVotingPower.into())
+    );
+    assert_eq!(
+        fixture.vote_weight(proposal_id, ALICE),
+        Some(U256::from(300))
+    );
+    assert_eq!(fixture.vote_weight(proposal_id, CAROL), None);
+
+    assert_eq!(
+        fixture.finalize(proposal_id, ENDS_AT - 1),
+        Err(VotingError::VotingPeriodNotElapsed.into())
+    );
+    fixture.finalize(proposal_id, ENDS_AT).unwrap();
+    assert_eq!(
+        fixture.finalize(proposal_id, ENDS_AT),
+        Err(VotingError::AlreadyFinalized.into())
+    );
+
+    let proposal = fixture.proposal(proposal_id);
+    assert_eq!(proposal.description_hash, DESCRIPTION_HASH);
+    assert_eq!(proposal.ends_at, ENDS_AT);
+    assert_eq!(proposal.votes_for, U256::from(700));
+    assert_eq!(proposal.votes_against, U256::from(300));
+    assert!(proposal.passed());
+    assert!(proposal.finalized);
+
+    let mut topics: Vec<String> = fixture
+        .builder
+        .read_message_topics(fixture.entity())
+        .iter()
+        .map(|(topic_name, _)| topic_name.clone())
+        .collect();
+    topics.sort();
+    assert_eq!(
+        topics,
+        [
+            "voting_proposal_created",
+            "voting_proposal_finalized",
+            "voting_voted"
+        ]
+    );
+}
+
+#[test]
+fn voter_cannot_vote_twice() {
+    let mut fixture = Fixture::new();
+    let proposal_id = fixture.create_proposal(*DEFAULT_ACCOUNT_ADDR).unwrap();
+    fixture.vote(ALICE, proposal_id, true).unwrap();
+
+    assert_eq!(
+        fixture.vote(ALICE, proposal_id, false),
+        Err(VotingError::AlreadyVoted.into())
+    );
+    let proposal = fixture.proposal(proposal_id);
+    assert_eq!(proposal.votes_for, U256::from(300));
+    assert_eq!(proposal.votes_against, U256::zero());
+}
+
+#[test]
+fn votes_on_unknown_or_closed_proposals_are_rejected() {
+    let mut fixture = Fixture::new();
+    assert_eq!(
+        fixture.vote(ALICE, 0, true),
+        Err(VotingError::ProposalNotFound.into())
+    );
+
+    let proposal_id = fixture.create_proposal(*DEFAULT_ACCOUNT_ADDR).unwrap();
+    let args = voting::voting::vote::Args {
+        proposal_id,
+        support: true,
+    };
+    assert_eq!(
+        fixture.call(
+            ALICE,
+            fixture.voting_hash,
+            voting::voting::vote::NAME,
+            args.into_runtime_args(),
+            ENDS_AT,
+        ),
+        Err(VotingError::VotingClosed.into())
+    );
+}
@@ -0,0 +1,571 @@
+//! Checks of the entry points exported by the Wasm of a contract, see `cargo xtask check-exports`.
+//!
+//! Every crate linked into a contract exports its `#[casper(export)]` entry points unless it is
+//! linked with its `as_dependency` feature, so a contract depending on another contract without
+//! the feature exports the entry points of both, and fails to link when both export the same one.
+//! [`analyze`] finds the crates of the workspace linked into a package and the entry points each
+//! of them exports from their sources, and [`wasm_exported_functions`] lists the functions a built
+//! Wasm actually exports to compare them with.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use syn::{punctuated::Punctuated, Attribute, Item, Meta, Token};
+use toml_edit::{DocumentMut, Item as TomlItem};
+
+/// The entry points exported by the crates linked into a package.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// The manifest of the package.
+    pub manifest: PathBuf,
+    /// The crates exporting each entry point.
+    pub exports: BTreeMap<String, Vec<String>>,
+    /// The entry points of the package itself, which its Wasm exports unless its own
+    /// `as_dependency` feature is enabled.
+    pub entry_points: BTreeSet<String>,
+}
+
+impl Analysis {
+    /// Returns the entry points exported by more than one crate, with the crates exporting them.
+    pub fn duplicates(&self) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.exports.iter().filter(|(_, crates)| crates.len() > 1)
+    }
+}
+
+/// Finds the entry points exported by `package` and the crates of the workspace at
+/// `workspace_root` linked into it without their `as_dependency` feature.
+pub fn analyze(workspace_root: &Path, package: &str) -> Result<Analysis, String> {
+    let workspace = Workspace::load(workspace_root)?;
+    if !workspace.packages.contains_key(package) {
+        return Err(format!("no package `{package}` in the workspace"));
+    }
+    let features = workspace.resolve_features(package);
+
+    let mut sources = BTreeMap::new();
+    for name in features.keys() {
+        sources.insert(name.as_str(), scan_sources(&workspace.packages[name].dir)?);
+    }
+    let entry_points_of = |name: &str| {
+        let source = &sources[name];
+        let mut entry_points: BTreeSet<String> = source.entry_points.iter().cloned().collect();
+        // `export_<module>_symbols!()` exports the entry points of a contract module of another crate
+        for module in &source.exported_modules {
+            for other in sources.values() {
+                entry_points.extend(
+                    other
+                        .contract_modules
+                        .get(module)
+                        .into_iter()
+                        .flatten()
+                        .cloned(),
+                );
+            }
+        }
+        entry_points
+    };
+    let mut analysis = Analysis {
+        manifest: workspace.packages[package].dir.join("Cargo.toml"),
+        entry_points: entry_points_of(package),
+        ..Analysis::default()
+    };
+    for (name, enabled) in &features {
+        if enabled.contains("as_dependency") {
+            continue;
+        }
+        for entry_point in entry_points_of(name) {
+            analysis
+                .exports
+                .entry(entry_point)
+                .or_default()
+                .push(name.clone());
+        }
+    }
+    Ok(analysis)
+}
+
+/// Returns the names of the functions the Wasm module `bytes` exports.
+pub fn wasm_exported_functions(bytes: &[u8]) -> Result<Vec<String>, String> {
+    const EXPORT_SECTION: u8 = 7;
+    const FUNCTION_EXPORT: u8 = 0;
+
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(8)? != b"\0asm\x01\0\0\0" {
+        return Err("not a Wasm module".into());
+    }
+    let mut functions = Vec::new();
+    while reader.position < bytes.len() {
+        let section_id = reader.byte()?;
+        let size = reader.leb128()? as usize;
+        let section = reader.take(size)?;
+        if section_id != EXPORT_SECTION {
+            continue;
+        }
+        let mut section = Reader {
+            bytes: section,
+            position: 0,
+        };
+        for _ in 0..section.leb128()? {
+            let name_size = section.leb128()? as usize;
+            let name = String::from_utf8(section.take(name_size)?.to_vec())
+                .map_err(|_| "export name is not UTF-8".to_string())?;
+            let kind = section.byte()?;
+            section.leb128()?;
+            if kind == FUNCTION_EXPORT {
+                functions.push(name);
+            }
+        }
+    }
+    Ok(functions)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, size: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(size)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or("truncated Wasm module")?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn leb128(&mut self) -> Result<u32, String> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("invalid LEB128 integer".into())
+    }
+}
+
+struct Package {
+    dir: PathBuf,
+    manifest: DocumentMut,
+}
+
+struct Workspace {
+    packages: BTreeMap<String, Package>,
+    root_manifest: DocumentMut,
+}
+
+/// A dependency of a package on another package of the workspace.
+struct Dependency {
+    /// The name the package refers to the dependency by.
+    key: String,
+    package: String,
+    features: Vec<String>,
+    default_features: bool,
+    optional: bool,
+}
+
+impl Workspace {
+    fn load(root: &Path) -> Result<Self, String> {
+        let root_manifest = read_manifest(&root.join("Cargo.toml"))?;
+        let members = root_manifest
+            .get("workspace")
+            .and_then(|workspace| workspace.get("members"))
+            .and_then(TomlItem::as_array)
+            .ok_or("the root manifest has no workspace members")?;
+
+        let mut dirs = Vec::new();
+        for member in members.iter().filter_map(|member| member.as_str()) {
+            match member.strip_suffix("/*") {
+                Some(parent) => {
+                    let entries = fs::read_dir(root.join(parent))
+                        .map_err(|err| format!("Failed to read {parent}: {err}"))?;
+                    for entry in entries {
+                        let entry =
+                            entry.map_err(|err| format!("Failed to read {parent}: {err}"))?;
+                        dirs.push(entry.path());
+                    }
+                }
+                None => dirs.push(root.join(member)),
+            }
+        }
+
+        let mut packages = BTreeMap::new();
+        for dir in dirs {
+            let manifest_path = dir.join("Cargo.toml");
+            if !manifest_path.is_file() {
+                continue;
+            }
+            let manifest = read_manifest(&manifest_path)?;
+            let Some(name) = manifest
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(TomlItem::as_str)
+            else {
+                continue;
+            };
+            packages.insert(name.to_string(), Package { dir, manifest });
+        }
+        Ok(Self {
+            packages,
+            root_manifest,
+        })
+    }
+
+    /// Returns the dependencies of `package` on other packages of the workspace when built for
+    /// Wasm, including those of `[target.'cfg(target_arch = "wasm32")'.dependencies]`.
+    fn dependencies(&self, package: &str) -> Vec<Dependency> {
+        let manifest = &self.packages[package].manifest;
+        let mut tables: Vec<_> = manifest
+            .get("dependencies")
+            .and_then(TomlItem::as_table_like)
+            .into_iter()
+            .collect();
+        if let Some(targets) = manifest.get("target").and_then(TomlItem::as_table_like) {
+            for (target, item) in targets.iter() {
+                if target.contains("wasm32") && !target.starts_with("cfg(not") {
+                    tables.extend(item.get("dependencies").and_then(TomlItem::as_table_like));
+                }
+            }
+        }
+        let mut dependencies = Vec::new();
+        for (key, item) in tables.into_iter().flat_map(|table| table.iter()) {
+            let inherited = item
+                .get("workspace")
+                .and_then(TomlItem::as_bool)
+                .unwrap_or(false)
+                .then(|| {
+                    self.root_manifest
+                        .get("workspace")
+                        .and_then(|workspace| workspace.get("dependencies"))
+                        .and_then(|dependencies| dependencies.get(key))
+                })
+                .flatten();
+            let lookup = |field: &str| {
+                item.get(field)
+                    .or_else(|| inherited.and_then(|inherited| inherited.get(field)))
+            };
+            let package = lookup("package")
+                .and_then(TomlItem::as_str)
+                .unwrap_or(key)
+                .to_string();
+            if !self.packages.contains_key(&package) {
+                continue;
+            }
+            let mut features = Vec::new();
+            for source in [Some(item), inherited].into_iter().flatten() {
+                let listed = source.get("features").and_then(TomlItem::as_array);
+                features.extend(
+                    listed
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|feature| feature.as_str().map(ToString::to_string)),
+                );
+            }
+            dependencies.push(Dependency {
+                key: key.to_string(),
+                package,
+                features,
+                default_features: lookup("default-features")
+                    .and_then(TomlItem::as_bool)
+                    .unwrap_or(true),
+                optional: item
+                    .get("optional")
+                    .and_then(TomlItem::as_bool)
+                    .unwrap_or(false),
+            });
+        }
+        dependencies
+    }
+
+    /// Returns the entries of the feature `feature` of `package`.
+    fn feature_entries(&self, package: &str, feature: &str) -> Vec<String> {
+        self.packages[package]
+            .manifest
+            .get("features")
+            .and_then(|features| features.get(feature))
+            .and_then(TomlItem::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.as_str().map(ToString::to_string))
+            .collect()
+    }
+
+    /// Returns the packages of the workspace linked into `root` built with its default features,
+    /// with the features enabled on each of them.
+    fn resolve_features(&self, root: &str) -> BTreeMap<String, BTreeSet<String>> {
+        let mut enabled: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        self.enable(&mut enabled, root, "default");
+        loop {
+            let before = enabled.clone();
+            for (package, features) in &before {
+                let dependencies = self.dependencies(package);
+                let entries: Vec<String> = features
+                    .iter()
+                    .flat_map(|feature| self.feature_entries(package, feature))
+                    .collect();
+                for dependency in &dependencies {
+                    let activated = !dependency.optional
+                        || features.contains(&dependency.key)
+                        || entries.iter().any(|entry| {
+                            entry == &format!("dep:{}", dependency.key)
+                                || entry.starts_with(&format!("{}/", dependency.key))
+                        });
+                    if !activated {
+                        continue;
+                    }
+                    enabled.entry(dependency.package.clone()).or_default();
+                    if dependency.default_features {
+                        self.enable(&mut enabled, &dependency.package, "default");
+                    }
+                    for feature in &dependency.features {
+                        self.enable(&mut enabled, &dependency.package, feature);
+                    }
+                    // `dependency/feature` and `dependency?/feature` entries of enabled features
+                    for entry in &entries {
+                        let Some((key, feature)) = entry.split_once('/') else {
+                            continue;
+                        };
+                        if key.trim_end_matches('?') == dependency.key {
+                            self.enable(&mut enabled, &dependency.package, feature);
+                        }
+                    }
+                }
+            }
+            if enabled == before {
+                return enabled;
+            }
+        }
+    }
+
+    /// Enables `feature` of `package` along with the features of the package it enables.
+    fn enable(
+        &self,
+        enabled: &mut BTreeMap<String, BTreeSet<String>>,
+        package: &str,
+        feature: &str,
+    ) {
+        if !enabled
+            .entry(package.to_string())
+            .or_default()
+            .insert(feature.to_string())
+        {
+            return;
+        }
+        for entry in self.feature_entries(package, feature) {
+            if !entry.contains('/') && !entry.starts_with("dep:") {
+                self.enable(enabled, package, &entry);
+            }
+        }
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<DocumentMut, String> {
+    fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?
+        .parse()
+        .map_err(|err| format!("Invalid manifest {}: {err}", path.display()))
+}
+
+/// The entry points declared in the sources of a crate.
+#[derive(Debug, Default)]
+struct Sources {
+    /// Names of the `#[casper(export)]` entry points.
+    entry_points: Vec<String>,
+    /// Names of the entry points of each `#[casper(contract)]` module.
+    contract_modules: BTreeMap<String, Vec<String>>,
+    /// Contract modules whose entry points are exported with `export_<module>_symbols!`.
+    exported_modules: Vec<String>,
+}
+
+fn scan_sources(crate_dir: &Path) -> Result<Sources, String> {
+    let mut sources = Sources::default();
+    let mut dirs = vec![crate_dir.join("src")];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry
+                .map_err(|err| format!("Failed to read {}: {err}", dir.display()))?
+                .path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                let contents = fs::read_to_string(&path)
+                    .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+                let file = syn::parse_file(&contents)
+                    .map_err(|err| format!("Failed to parse {}: {err}", path.display()))?;
+                scan_items(&file.items, &mut sources, None);
+            }
+        }
+    }
+    Ok(sources)
+}
+
+fn scan_items(items: &[Item], sources: &mut Sources, contract_module: Option<&str>) {
+    for item in items {
+        match item {
+            Item::Fn(item_fn) => {
+                if let Some(name) = export_name(&item_fn.attrs, &item_fn.sig.ident) {
+                    if let Some(module) = contract_module {
+                        sources
+                            .contract_modules
+                            .entry(module.to_string())
+                            .or_default()
+                            .push(name.clone());
+                    }
+                    sources.entry_points.push(name);
+                }
+            }
+            Item::Mod(item_mod) => {
+                let Some((_, items)) = &item_mod.content else {
+                    continue;
+                };
+                let name = item_mod.ident.to_string();
+                let module = if casper_options(&item_mod.attrs)
+                    .iter()
+                    .any(|meta| meta.path().is_ident("contract"))
+                {
+                    Some(name.as_str())
+                } else {
+                    contract_module
+                };
+                scan_items(items, sources, module);
+            }
+            Item::Macro(item_macro) => {
+                let Some(segment) = item_macro.mac.path.segments.last() else {
+                    continue;
+                };
+                let macro_name = segment.ident.to_string();
+                if let Some(module) = macro_name
+                    .strip_prefix("export_")
+                    .and_then(|name| name.strip_suffix("_symbols"))
+                {
+                    sources.exported_modules.push(module.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the options of the `#[casper(...)]` attributes in `attrs`.
+fn casper_options(attrs: &[Attribute]) -> Vec<Meta> {
+    attrs
+        .iter()
+        .filter(|attr| {
+            attr.path()
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "casper")
+        })
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .collect()
+}
+
+/// Returns the entry point name of a function marked `#[casper(export)]`.
+fn export_name(attrs: &[Attribute], fn_name: &syn::Ident) -> Option<String> {
+    let options = casper_options(attrs);
+    if !options.iter().any(|meta| meta.path().is_ident("export")) {
+        return None;
+    }
+    let renamed = options.iter().find_map(|meta| match meta {
+        Meta::NameValue(name_value) if name_value.path.is_ident("name") => {
+            match &name_value.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(name),
+                    ..
+                }) => Some(name.value()),
+                _ => None,
+            }
+        }
+        _ => None,
+    });
+    Some(renamed.unwrap_or_else(|| fn_name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/duplicate-init")
+    }
+
+    #[test]
+    fn duplicate_init_is_detected() {
+        let analysis = analyze(&fixture(), "app").unwrap();
+        let duplicates: Vec<_> = analysis.duplicates().collect();
+        assert_eq!(
+            duplicates,
+            [(
+                &"init".to_string(),
+                &vec!["app".to_string(), "base".to_string()]
+            )]
+        );
+        assert_eq!(analysis.exports["call"], ["app"]);
+        assert_eq!(analysis.exports["base_only"], ["base"]);
+        assert_eq!(
+            analysis.entry_points,
+            BTreeSet::from(["call".to_string(), "init".to_string()])
+        );
+    }
+
+    #[test]
+    fn dependencies_linked_as_dependency_export_nothing() {
+        let analysis = analyze(&fixture(), "caller").unwrap();
+        assert_eq!(analysis.duplicates().count(), 0);
+        assert_eq!(
+            analysis.exports.keys().collect::<Vec<_>>(),
+            ["call", "init"]
+        );
+    }
+
+    #[test]
+    fn exported_contract_modules_count_for_the_exporting_crate() {
+        let workspace_root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let analysis = analyze(workspace_root, "cep18-hooked").unwrap();
+        assert_eq!(analysis.duplicates().count(), 0);
+        assert_eq!(analysis.exports["call"], ["cep18-hooked"]);
+        assert_eq!(analysis.exports["pause"], ["cep18-hooked"]);
+    }
+
+    #[test]
+    fn unknown_packages_are_rejected() {
+        assert_eq!(
+            analyze(&fixture(), "missing"),
+            Err("no package `missing` in the workspace".to_string())
+        );
+    }
+
+    #[test]
+    fn lists_exported_functions() {
+        #[rustfmt::skip]
+        let module = [
+            b"\0asm\x01\0\0\0".as_slice(),
+            // A type section, skipped
+            &[1, 4, 1, 0x60, 0, 0],
+            // Exports of the function `call`, the memory `memory` and the function `init`
+            &[7, 24, 3],
+            &[4], b"call", &[0, 0],
+            &[6], b"memory", &[2, 0],
+            &[4], b"init", &[0, 1],
+        ]
+        .concat();
+        assert_eq!(wasm_exported_functions(&module).unwrap(), ["call", "init"]);
+        assert!(wasm_exported_functions(&module[..module.len() - 1]).is_err());
+        assert!(wasm_exported_functions(b"not wasm").is_err());
+    }
+}
@@ -1,10 +1,16 @@
+mod check_exports;
+mod new_contract;
+
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
 use clap::{Parser, Subcommand};
+use toml_edit::DocumentMut;
 use xshell::{cmd, Shell};
 
+use new_contract::Kind;
+
 #[derive(Parser)]
 #[command(name = "xtask")]
 #[command(about = "Workspace task runner", long_about = None)]
@@ -19,6 +25,20 @@ enum Commands {
     BuildExample { package: String },
     /// Build all smart contract examples under ./examples.
     BuildExamples,
+    /// Build all smart contract examples, then run the tests of each of them.
+    TestExamples,
+    /// Build the wasm of a contract by package name and list the entry points it exports,
+    /// flagging those exported by several linked crates and those no crate declares.
+    CheckExports { package: String },
+    /// Generate a new contract crate and add it to the workspace.
+    NewContract {
+        name: String,
+        /// Directory to create the crate in, relative to the workspace root.
+        #[arg(long, default_value = "examples")]
+        dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = Kind::Plain)]
+        kind: Kind,
+    },
 }
 
 fn main() -> xshell::Result<()> {
@@ -35,17 +55,96 @@ fn main() -> xshell::Result<()> {
             .run()?;
         }
         Commands::BuildExamples => {
-            let manifests = example_manifests();
-            if manifests.is_empty() {
-                eprintln!("No examples found under ./examples.");
-                exit(2);
+            build_examples(&sh)?;
+        }
+        Commands::TestExamples => {
+            // Engine tests may install the wasm of other examples, so all of them are built first
+            let manifests = build_examples(&sh)?;
+            let target_dir = target_dir(&sh).unwrap_or_else(|err| {
+                eprintln!("{err}");
+                exit(1);
+            });
+            for manifest in &manifests {
+                let wasm = example_wasm(&target_dir, manifest).unwrap_or_else(|err| {
+                    eprintln!("{err}");
+                    exit(1);
+                });
+                if !wasm.is_file() {
+                    eprintln!(
+                        "Building {} did not produce {}; is the crate a cdylib?",
+                        manifest.display(),
+                        wasm.display()
+                    );
+                    exit(1);
+                }
             }
+            // `test_support::read_wasm` asks cargo for the target directory from the directory of
+            // the example, so a relative `CARGO_TARGET_DIR` is passed on resolved
             for manifest in manifests {
-                cmd!(
-                    sh,
-                    "cargo build --target wasm32v1-none --release --manifest-path {manifest}"
-                )
-                .run()?;
+                cmd!(sh, "cargo test --manifest-path {manifest}")
+                    .env("CARGO_TARGET_DIR", &target_dir)
+                    .run()?;
+            }
+        }
+        Commands::CheckExports { package } => {
+            let fail = |err: String| -> ! {
+                eprintln!("{err}");
+                exit(1);
+            };
+            let analysis =
+                check_exports::analyze(&workspace_root(), &package).unwrap_or_else(|err| fail(err));
+            let mut failed = false;
+            for (entry_point, crates) in analysis.duplicates() {
+                eprintln!(
+                    "`{entry_point}` is exported by {}; link the contract crates other contracts depend on with their `as_dependency` feature",
+                    crates.join(", ")
+                );
+                failed = true;
+            }
+            cmd!(
+                sh,
+                "cargo build --target wasm32v1-none -p {package} --release"
+            )
+            .run()?;
+
+            let target_dir = target_dir(&sh).unwrap_or_else(|err| fail(err));
+            let wasm =
+                example_wasm(&target_dir, &analysis.manifest).unwrap_or_else(|err| fail(err));
+            let bytes = fs::read(&wasm)
+                .unwrap_or_else(|err| fail(format!("Failed to read {}: {err}", wasm.display())));
+            let functions = check_exports::wasm_exported_functions(&bytes)
+                .unwrap_or_else(|err| fail(format!("Invalid wasm {}: {err}", wasm.display())));
+            println!("{} exports:", wasm.display());
+            for function in &functions {
+                match analysis.exports.get(function) {
+                    Some(crates) => println!("  {function} ({})", crates.join(", ")),
+                    None => {
+                        println!("  {function} (not a #[casper(export)] of the linked crates)");
+                        failed = true;
+                    }
+                }
+            }
+            // A contract built with its own `as_dependency` feature, e.g. enabled by the default
+            // features, exports none of its entry points
+            for entry_point in &analysis.entry_points {
+                if !functions.contains(entry_point) {
+                    eprintln!(
+                        "`{entry_point}` of {package} is not exported; disable its `as_dependency` feature when building its Wasm"
+                    );
+                    failed = true;
+                }
+            }
+            if failed {
+                exit(1);
+            }
+        }
+        Commands::NewContract { name, dir, kind } => {
+            match new_contract::generate(&workspace_root(), &dir, &name, kind) {
+                Ok(crate_dir) => println!("Created {}", crate_dir.display()),
+                Err(err) => {
+                    eprintln!("{err}");
+                    exit(1);
+                }
             }
         }
     }
@@ -53,6 +152,64 @@ fn main() -> xshell::Result<()> {
     Ok(())
 }
 
+/// Builds the wasm of every example and returns their manifests.
+fn build_examples(sh: &Shell) -> xshell::Result<Vec<PathBuf>> {
+    let manifests = example_manifests();
+    if manifests.is_empty() {
+        eprintln!("No examples found under ./examples.");
+        exit(2);
+    }
+    for manifest in &manifests {
+        cmd!(
+            sh,
+            "cargo build --target wasm32v1-none --release --manifest-path {manifest}"
+        )
+        .run()?;
+    }
+    Ok(manifests)
+}
+
+/// Returns the target directory of the workspace, as `cargo metadata` reports it.
+///
+/// This honours `CARGO_TARGET_DIR` and `build.target-dir` the way the cargo commands run here do,
+/// and is how `test_support::wasm_dir` finds the wasm the engine tests install.
+fn target_dir(sh: &Shell) -> Result<PathBuf, String> {
+    let metadata = cmd!(sh, "cargo metadata --format-version 1 --no-deps")
+        .quiet()
+        .read()
+        .map_err(|err| format!("Failed to run cargo metadata: {err}"))?;
+    let metadata: serde_json::Value = serde_json::from_str(&metadata)
+        .map_err(|err| format!("Invalid cargo metadata output: {err}"))?;
+    metadata["target_directory"]
+        .as_str()
+        .map(PathBuf::from)
+        .ok_or_else(|| "cargo metadata did not report the target directory".to_string())
+}
+
+/// Returns the path of the wasm built from the example at `manifest` into `target_dir`.
+fn example_wasm(target_dir: &Path, manifest: &Path) -> Result<PathBuf, String> {
+    let contents = fs::read_to_string(manifest)
+        .map_err(|err| format!("Failed to read {}: {err}", manifest.display()))?;
+    let file_name = wasm_file_name(&contents)
+        .map_err(|err| format!("Invalid manifest {}: {err}", manifest.display()))?;
+    Ok(target_dir
+        .join("wasm32v1-none")
+        .join("release")
+        .join(file_name))
+}
+
+/// Returns the file name of the wasm of a crate, named after its library target.
+fn wasm_file_name(manifest: &str) -> Result<String, String> {
+    let doc: DocumentMut = manifest.parse().map_err(|err| format!("{err}"))?;
+    let name = doc
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .or_else(|| doc.get("package").and_then(|package| package.get("name")))
+        .and_then(|name| name.as_str())
+        .ok_or("missing package name")?;
+    Ok(format!("{}.wasm", name.replace('-', "_")))
+}
+
 fn workspace_root() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -88,3 +245,21 @@ fn example_manifests() -> Vec<PathBuf> {
     manifests.sort();
     manifests
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm_is_named_after_the_library_target() {
+        assert_eq!(
+            wasm_file_name("[package]\nname = \"do-nothing-stored\"\n").unwrap(),
+            "do_nothing_stored.wasm"
+        );
+        assert_eq!(
+            wasm_file_name("[package]\nname = \"token\"\n\n[lib]\nname = \"cep18-v2\"\n").unwrap(),
+            "cep18_v2.wasm"
+        );
+        assert!(wasm_file_name("[lib]\ncrate-type = [\"cdylib\"]\n").is_err());
+    }
+}
@@ -0,0 +1,351 @@
+//! Scaffolding of new contract crates from the templates in `xtask/templates`.
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use clap::ValueEnum;
+use toml_edit::{Array, DocumentMut, Item, Value};
+
+/// The kind of contract to generate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Kind {
+    /// A `#[casper(contract)]` module with a sample entry point and event.
+    Plain,
+    /// A CEP-18 token installed through `veles-casper-contract-extras`.
+    Token,
+}
+
+struct Template {
+    path: &'static str,
+    contents: &'static str,
+}
+
+const PLAIN_TEMPLATES: &[Template] = &[
+    Template {
+        path: "Cargo.toml",
+        contents: include_str!("../templates/plain/Cargo.toml.tmpl"),
+    },
+    Template {
+        path: "src/lib.rs",
+        contents: include_str!("../templates/plain/lib.rs.tmpl"),
+    },
+    Template {
+        path: "src/event.rs",
+        contents: include_str!("../templates/plain/event.rs.tmpl"),
+    },
+    Template {
+        path: "tests/{{crate_ident}}_tests.rs",
+        contents: include_str!("../templates/plain/tests.rs.tmpl"),
+    },
+];
+
+const TOKEN_TEMPLATES: &[Template] = &[
+    Template {
+        path: "Cargo.toml",
+        contents: include_str!("../templates/token/Cargo.toml.tmpl"),
+    },
+    Template {
+        path: "src/lib.rs",
+        contents: include_str!("../templates/token/lib.rs.tmpl"),
+    },
+    Template {
+        path: "tests/{{crate_ident}}_tests.rs",
+        contents: include_str!("../templates/token/tests.rs.tmpl"),
+    },
+];
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidName(String),
+    OutsideWorkspace(PathBuf),
+    AlreadyExists(PathBuf),
+    Manifest(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidName(name) => write!(
+                f,
+                "invalid crate name `{name}`: use lowercase ASCII letters, digits, `-` and `_`, starting with a letter"
+            ),
+            Error::OutsideWorkspace(dir) => write!(
+                f,
+                "{} must be a relative path inside the workspace",
+                dir.display()
+            ),
+            Error::AlreadyExists(dir) => write!(f, "{} already exists", dir.display()),
+            Error::Manifest(message) => write!(f, "failed to update workspace manifest: {message}"),
+            Error::Io(err) => write!(f, "io error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::Io(value)
+    }
+}
+
+/// Generates the crate `name` under `dir` of the workspace at `workspace_root`, returning its path.
+///
+/// `dir` is relative to the workspace root. The crate is added to the workspace members unless a
+/// member glob such as `examples/*` already covers it. Existing directories are never overwritten.
+pub fn generate(
+    workspace_root: &Path,
+    dir: &Path,
+    name: &str,
+    kind: Kind,
+) -> Result<PathBuf, Error> {
+    validate_name(name)?;
+    if !dir
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::OutsideWorkspace(dir.to_path_buf()));
+    }
+
+    let member = dir.join(name);
+    let crate_dir = workspace_root.join(&member);
+    if crate_dir.exists() {
+        return Err(Error::AlreadyExists(crate_dir));
+    }
+
+    let depth = member
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .count();
+    let replacements = [
+        ("{{crate_name}}", name.to_string()),
+        ("{{crate_ident}}", name.replace('-', "_")),
+        ("{{workspace_root}}", vec![".."; depth].join("/")),
+    ];
+    let render = |template: &str| {
+        replacements
+            .iter()
+            .fold(template.to_string(), |rendered, (placeholder, value)| {
+                rendered.replace(placeholder, value)
+            })
+    };
+
+    let member = member
+        .to_str()
+        .ok_or_else(|| Error::OutsideWorkspace(member.clone()))?
+        .replace('\\', "/");
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let manifest = add_workspace_member(&fs::read_to_string(&manifest_path)?, &member)?;
+
+    let templates = match kind {
+        Kind::Plain => PLAIN_TEMPLATES,
+        Kind::Token => TOKEN_TEMPLATES,
+    };
+    for template in templates {
+        let path = crate_dir.join(render(template.path));
+        fs::create_dir_all(path.parent().expect("template paths have a parent"))?;
+        fs::write(path, render(template.contents))?;
+    }
+    if let Some(manifest) = manifest {
+        fs::write(manifest_path, manifest)?;
+    }
+
+    Ok(crate_dir)
+}
+
+fn validate_name(name: &str) -> Result<(), Error> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(Error::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Adds `member` to the `workspace.members` of `manifest`.
+///
+/// Returns `None` when the members already cover `member`, either literally or through a
+/// `dir/*` glob.
+fn add_workspace_member(manifest: &str, member: &str) -> Result<Option<String>, Error> {
+    let mut document: DocumentMut = manifest
+        .parse()
+        .map_err(|err: toml_edit::TomlError| Error::Manifest(err.to_string()))?;
+    let workspace = document
+        .get_mut("workspace")
+        .and_then(Item::as_table_like_mut)
+        .ok_or_else(|| Error::Manifest("missing [workspace] table".into()))?;
+    let members = workspace
+        .entry("members")
+        .or_insert(Item::Value(Value::Array(Array::new())))
+        .as_array_mut()
+        .ok_or_else(|| Error::Manifest("workspace.members is not an array".into()))?;
+
+    let parent = member.rsplit_once('/').map_or("", |(parent, _)| parent);
+    let covered = members
+        .iter()
+        .filter_map(Value::as_str)
+        .any(|existing| existing == member || existing.strip_suffix("/*") == Some(parent));
+    if covered {
+        return Ok(None);
+    }
+
+    // Line up with the existing members when they are laid out one per line.
+    let mut value = Value::from(member);
+    if let Some(prefix) = members.iter().last().and_then(|last| last.decor().prefix()) {
+        value.decor_mut().set_prefix(prefix.clone());
+    }
+    members.push_formatted(value);
+    Ok(Some(document.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    const WORKSPACE_MANIFEST: &str = r#"[workspace]
+members = [
+    "examples/*",
+]
+resolver = "3"
+
+[workspace.dependencies]
+veles-casper-contract-api = { path = "crates/contract-api" }
+"#;
+
+    /// Creates a workspace with stand-ins for the crates the templates depend on and an existing
+    /// example, so the `examples/*` member glob always matches.
+    fn workspace() -> tempfile::TempDir {
+        let workspace = tempfile::tempdir().unwrap();
+        fs::write(workspace.path().join("Cargo.toml"), WORKSPACE_MANIFEST).unwrap();
+        for (path, features) in [
+            (
+                "crates/contract-api",
+                "wasm_allocator = []\nwasm_panic_handler = []\nstd = []\nas_dependency = []",
+            ),
+            ("crates/contract-extras", "as_dependency = []"),
+            ("examples/existing", ""),
+        ] {
+            let crate_dir = workspace.path().join(path);
+            let name = path.replace("crates/", "veles-casper-").replace('/', "-");
+            fs::create_dir_all(crate_dir.join("src")).unwrap();
+            fs::write(crate_dir.join("src/lib.rs"), "").unwrap();
+            fs::write(
+                crate_dir.join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[features]\n{features}\n"
+                ),
+            )
+            .unwrap();
+        }
+        workspace
+    }
+
+    fn assert_metadata_parses(workspace_root: &Path, crate_name: &str) {
+        let output = Command::new(env!("CARGO"))
+            .args([
+                "metadata",
+                "--no-deps",
+                "--offline",
+                "--format-version",
+                "1",
+            ])
+            .current_dir(workspace_root)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let metadata = String::from_utf8(output.stdout).unwrap();
+        assert!(metadata.contains(&format!(r#""name":"{crate_name}""#)));
+    }
+
+    #[test]
+    fn generates_plain_contract_covered_by_member_glob() {
+        let workspace = workspace();
+        let crate_dir = generate(
+            workspace.path(),
+            Path::new("examples"),
+            "hello-world",
+            Kind::Plain,
+        )
+        .unwrap();
+
+        for file in [
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/event.rs",
+            "tests/hello_world_tests.rs",
+        ] {
+            assert!(crate_dir.join(file).is_file(), "missing {file}");
+        }
+        let tests = fs::read_to_string(crate_dir.join("tests/hello_world_tests.rs")).unwrap();
+        assert!(tests.contains(r#"join("../..")"#));
+        assert!(tests.contains("hello_world.wasm"));
+        assert_eq!(
+            fs::read_to_string(workspace.path().join("Cargo.toml")).unwrap(),
+            WORKSPACE_MANIFEST
+        );
+        assert_metadata_parses(workspace.path(), "hello-world");
+    }
+
+    #[test]
+    fn generates_token_and_registers_member() {
+        let workspace = workspace();
+        let crate_dir = generate(
+            workspace.path(),
+            Path::new("contracts/tokens"),
+            "my-token",
+            Kind::Token,
+        )
+        .unwrap();
+
+        assert!(crate_dir.join("src/lib.rs").is_file());
+        assert!(!crate_dir.join("src/event.rs").exists());
+        let manifest = fs::read_to_string(crate_dir.join("Cargo.toml")).unwrap();
+        assert!(manifest.contains(r#"path = "../../../crates/contract-extras""#));
+        let workspace_manifest = fs::read_to_string(workspace.path().join("Cargo.toml")).unwrap();
+        assert!(workspace_manifest.contains("\n    \"contracts/tokens/my-token\",\n]"));
+        assert_metadata_parses(workspace.path(), "my-token");
+    }
+
+    #[test]
+    fn refuses_to_overwrite_existing_directory() {
+        let workspace = workspace();
+        fs::create_dir_all(workspace.path().join("examples/taken")).unwrap();
+
+        let result = generate(
+            workspace.path(),
+            Path::new("examples"),
+            "taken",
+            Kind::Plain,
+        );
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+        assert_eq!(
+            fs::read_dir(workspace.path().join("examples/taken"))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names_and_directories() {
+        let workspace = workspace();
+        for name in ["", "Hello", "1st", "with space"] {
+            assert!(matches!(
+                generate(workspace.path(), Path::new("examples"), name, Kind::Plain),
+                Err(Error::InvalidName(_))
+            ));
+        }
+        assert!(matches!(
+            generate(workspace.path(), Path::new("../outside"), "ok", Kind::Plain),
+            Err(Error::OutsideWorkspace(_))
+        ));
+    }
+}
@@ -0,0 +1,13 @@
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+extern crate alloc;
+// Linked like a contract calling the other one through its `Client`
+extern crate base;
+
+use veles_casper_contract_api::prelude::*;
+
+#[casper(export)]
+pub fn call() {}
+
+#[casper(export)]
+pub fn init() {}
@@ -0,0 +1,11 @@
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+extern crate alloc;
+
+use veles_casper_contract_api::prelude::*;
+
+#[casper(export)]
+pub fn init() {}
+
+#[casper(export)]
+pub fn base_only() {}